@@ -9,6 +9,7 @@ struct Parameters {
     index_bounds_check_policy: naga::back::IndexBoundsCheckPolicy,
     spv_adjust_coordinate_space: bool,
     spv_flow_dump_prefix: Option<String>,
+    entry_point: Option<String>,
     spv: naga::back::spv::Options,
     msl: naga::back::msl::Options,
     glsl: naga::back::glsl::Options,
@@ -89,7 +90,7 @@ fn main() {
                     };
                 }
                 "flow-dir" => params.spv_flow_dump_prefix = args.next(),
-                "entry-point" => params.glsl.entry_point = args.next().unwrap(),
+                "entry-point" => params.entry_point = args.next(),
                 "profile" => {
                     use naga::back::glsl::Version;
                     let string = args.next().unwrap();
@@ -124,6 +125,7 @@ fn main() {
             return;
         }
     };
+    let mut input_text = None;
     let module = match Path::new(input_path)
         .extension()
         .expect("Input has no extension?")
@@ -141,21 +143,23 @@ fn main() {
         }
         "wgsl" => {
             let input = fs::read_to_string(input_path).unwrap();
-            let result = naga::front::wgsl::parse_str(&input);
+            let input = input_text.get_or_insert(input);
+            let result = naga::front::wgsl::parse_str(input);
             match result {
                 Ok(v) => v,
                 Err(ref e) => {
-                    e.emit_to_stderr(&input);
+                    e.emit_to_stderr(input);
                     panic!("unable to parse WGSL");
                 }
             }
         }
         "vert" => {
             let input = fs::read_to_string(input_path).unwrap();
+            let input = input_text.get_or_insert(input);
             let mut entry_points = naga::FastHashMap::default();
             entry_points.insert("main".to_string(), naga::ShaderStage::Vertex);
             naga::front::glsl::parse_str(
-                &input,
+                input,
                 &naga::front::glsl::Options {
                     entry_points,
                     defines: Default::default(),
@@ -163,16 +167,17 @@ fn main() {
             )
             .unwrap_or_else(|err| {
                 let filename = input_path.file_name().and_then(std::ffi::OsStr::to_str);
-                emit_glsl_parser_error(err, filename.unwrap_or("glsl"), &input);
+                emit_glsl_parser_error(err, filename.unwrap_or("glsl"), input);
                 std::process::exit(1);
             })
         }
         "frag" => {
             let input = fs::read_to_string(input_path).unwrap();
+            let input = input_text.get_or_insert(input);
             let mut entry_points = naga::FastHashMap::default();
             entry_points.insert("main".to_string(), naga::ShaderStage::Fragment);
             naga::front::glsl::parse_str(
-                &input,
+                input,
                 &naga::front::glsl::Options {
                     entry_points,
                     defines: Default::default(),
@@ -180,16 +185,17 @@ fn main() {
             )
             .unwrap_or_else(|err| {
                 let filename = input_path.file_name().and_then(std::ffi::OsStr::to_str);
-                emit_glsl_parser_error(err, filename.unwrap_or("glsl"), &input);
+                emit_glsl_parser_error(err, filename.unwrap_or("glsl"), input);
                 std::process::exit(1);
             })
         }
         "comp" => {
             let input = fs::read_to_string(input_path).unwrap();
+            let input = input_text.get_or_insert(input);
             let mut entry_points = naga::FastHashMap::default();
             entry_points.insert("main".to_string(), naga::ShaderStage::Compute);
             naga::front::glsl::parse_str(
-                &input,
+                input,
                 &naga::front::glsl::Options {
                     entry_points,
                     defines: Default::default(),
@@ -197,13 +203,30 @@ fn main() {
             )
             .unwrap_or_else(|err| {
                 let filename = input_path.file_name().and_then(std::ffi::OsStr::to_str);
-                emit_glsl_parser_error(err, filename.unwrap_or("glsl"), &input);
+                emit_glsl_parser_error(err, filename.unwrap_or("glsl"), input);
                 std::process::exit(1);
             })
         }
+        "ir" => {
+            let input = fs::read_to_string(input_path).unwrap();
+            naga::front::ir::parse_str(&input).unwrap_pretty()
+        }
         other => panic!("Unknown input extension: {}", other),
     };
 
+    // keep only the requested entry point, for all the outputs
+    let module = match params.entry_point {
+        Some(ref name) => {
+            let stage = entry_point_stage(&module, name, &output_paths);
+            params.glsl.entry_point = name.clone();
+            match naga::proc::extract_entry_point(&module, name, stage) {
+                Some((module, _)) => module,
+                None => panic!("Entry point {:?} is not found", name),
+            }
+        }
+        None => module,
+    };
+
     // validate the IR
     let info = match naga::valid::Validator::new(
         params.validation_flags,
@@ -213,7 +236,13 @@ fn main() {
     {
         Ok(info) => Some(info),
         Err(error) => {
-            print_err(error);
+            match input_text {
+                Some(ref input) => {
+                    let path = input_path.to_string_lossy();
+                    error.emit_to_stderr_with_path(input, &path);
+                }
+                None => print_err(error),
+            }
             None
         }
     };
@@ -276,12 +305,7 @@ fn main() {
             stage @ "vert" | stage @ "frag" | stage @ "comp" => {
                 use naga::back::glsl;
 
-                params.glsl.shader_stage = match stage {
-                    "vert" => naga::ShaderStage::Vertex,
-                    "frag" => naga::ShaderStage::Fragment,
-                    "comp" => naga::ShaderStage::Compute,
-                    _ => unreachable!(),
-                };
+                params.glsl.shader_stage = glsl_stage(stage).unwrap();
 
                 let mut buffer = String::new();
                 let mut writer =
@@ -308,6 +332,10 @@ fn main() {
                 let wgsl = wgsl::write_string(&module, info.as_ref().unwrap()).unwrap_pretty();
                 fs::write(output_path, wgsl).unwrap();
             }
+            "ir" => {
+                let ir = naga::back::ir::write_string(&module).unwrap();
+                fs::write(output_path, ir).unwrap();
+            }
             other => {
                 println!("Unknown output extension: {}", other);
             }
@@ -315,6 +343,41 @@ fn main() {
     }
 }
 
+/// Get the stage of a GLSL output extension.
+fn glsl_stage(extension: &str) -> Option<naga::ShaderStage> {
+    match extension {
+        "vert" => Some(naga::ShaderStage::Vertex),
+        "frag" => Some(naga::ShaderStage::Fragment),
+        "comp" => Some(naga::ShaderStage::Compute),
+        _ => None,
+    }
+}
+
+/// Find the stage of the entry point `name`.
+///
+/// Entry points of different stages can share a name, in which case
+/// the stage is taken from the GLSL outputs.
+fn entry_point_stage(
+    module: &naga::Module,
+    name: &str,
+    output_paths: &[String],
+) -> naga::ShaderStage {
+    let mut stages = module
+        .entry_points
+        .iter()
+        .filter(|ep| ep.name == name)
+        .map(|ep| ep.stage);
+    match (stages.next(), stages.next()) {
+        (Some(stage), None) => stage,
+        (None, _) => panic!("Entry point {:?} is not found", name),
+        (Some(_), Some(_)) => output_paths
+            .iter()
+            .filter_map(|path| Path::new(path).extension()?.to_str().and_then(glsl_stage))
+            .next()
+            .unwrap_or_else(|| panic!("Entry point {:?} exists in several stages", name)),
+    }
+}
+
 use codespan_reporting::{
     diagnostic::{Diagnostic, Label},
     files::SimpleFile,
@@ -1,4 +1,6 @@
-use std::{cmp::Ordering, fmt, hash, marker::PhantomData, num::NonZeroU32, ops};
+use std::{cmp::Ordering, fmt, hash, marker::PhantomData, mem, num::NonZeroU32, ops};
+
+use crate::Span;
 
 /// An unique index in the arena array that a handle points to.
 /// The "non-zero" part ensures that an `Option<Handle<T>>` has
@@ -73,6 +75,11 @@ impl<T> Handle<T> {
         let index = self.index.get() - 1;
         index as usize
     }
+
+    /// Make a handle from a zero-based index.
+    pub(crate) fn from_usize(index: usize) -> Self {
+        Handle::new(Index::new(index as u32 + 1).unwrap())
+    }
 }
 
 /// A strongly typed range of handles.
@@ -101,6 +108,29 @@ impl<T> fmt::Debug for Range<T> {
         write!(formatter, "[{}..{}]", self.inner.start + 1, self.inner.end)
     }
 }
+impl<T> Range<T> {
+    /// Make a range of the handles from `first` to `last`, inclusive.
+    pub(crate) fn new_from_bounds(first: Handle<T>, last: Handle<T>) -> Self {
+        Range {
+            inner: first.index() as u32..last.index() as u32 + 1,
+            marker: PhantomData,
+        }
+    }
+
+    /// Make a range from zero-based indices, with an exclusive end.
+    pub(crate) fn from_index_range(inner: ops::Range<u32>) -> Self {
+        Range {
+            inner,
+            marker: PhantomData,
+        }
+    }
+
+    /// Return the zero-based indices of the range, with an exclusive end.
+    pub(crate) fn index_range(&self) -> ops::Range<u32> {
+        self.inner.clone()
+    }
+}
+
 impl<T> Iterator for Range<T> {
     type Item = Handle<T>;
     fn next(&mut self) -> Option<Self::Item> {
@@ -122,16 +152,23 @@ impl<T> Iterator for Range<T> {
 /// Adding new items to the arena produces a strongly-typed [`Handle`].
 /// The arena can be indexed using the given handle to obtain
 /// a reference to the stored item.
+///
+/// With the `span` feature enabled, the arena also keeps a [`Span`]
+/// for every item, which front-ends fill in for error reporting.
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
 #[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
 #[cfg_attr(
     any(feature = "serialize", feature = "deserialize"),
     serde(transparent)
 )]
+#[derive(Clone)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct Arena<T> {
     /// Values of this arena.
     data: Vec<T>,
+    #[cfg(feature = "span")]
+    #[cfg_attr(any(feature = "serialize", feature = "deserialize"), serde(skip))]
+    span_info: Vec<Span>,
 }
 
 impl<T> Default for Arena<T> {
@@ -148,7 +185,11 @@ impl<T: fmt::Debug> fmt::Debug for Arena<T> {
 impl<T> Arena<T> {
     /// Create a new arena with no initial capacity allocated.
     pub fn new() -> Self {
-        Arena { data: Vec::new() }
+        Arena {
+            data: Vec::new(),
+            #[cfg(feature = "span")]
+            span_info: Vec::new(),
+        }
     }
 
     /// Extracts the inner vector.
@@ -192,9 +233,19 @@ impl<T> Arena<T> {
         let index =
             Index::new(position as u32).expect("Failed to append to Arena. Handle overflows");
         self.data.push(value);
+        // deserialized arenas come without spans, so catch up with the data
+        #[cfg(feature = "span")]
+        self.span_info.resize(self.data.len(), Span::default());
         Handle::new(index)
     }
 
+    /// Adds a new value to the arena, recording where it came from.
+    pub fn append_with_span(&mut self, value: T, span: Span) -> Handle<T> {
+        let handle = self.append(value);
+        self.set_span(handle, span);
+        handle
+    }
+
     /// Fetch a handle to an existing type.
     pub fn fetch_if<F: Fn(&T) -> bool>(&self, fun: F) -> Option<Handle<T>> {
         self.data
@@ -233,6 +284,48 @@ impl<T> Arena<T> {
         self.data.get_mut(handle.index.get() as usize - 1).unwrap()
     }
 
+    /// Get the source span of an item, or [`Span::UNDEFINED`] if it's unknown.
+    pub fn get_span(&self, handle: Handle<T>) -> Span {
+        #[cfg(feature = "span")]
+        {
+            self.span_info
+                .get(handle.index())
+                .cloned()
+                .unwrap_or_default()
+        }
+        #[cfg(not(feature = "span"))]
+        {
+            let _ = handle;
+            Span::UNDEFINED
+        }
+    }
+
+    /// Set the source span of an item.
+    pub fn set_span(&mut self, handle: Handle<T>, span: Span) {
+        #[cfg(feature = "span")]
+        {
+            let index = handle.index();
+            if index >= self.span_info.len() {
+                self.span_info.resize(self.data.len(), Span::default());
+            }
+            self.span_info[index] = span;
+        }
+        #[cfg(not(feature = "span"))]
+        {
+            let _ = (handle, span);
+        }
+    }
+
+    /// Set the source span of an item, unless it already has one.
+    ///
+    /// This is useful for front-ends that reuse handles, where the first
+    /// occurrence is the most meaningful one to report.
+    pub fn init_span(&mut self, handle: Handle<T>, span: Span) {
+        if !self.get_span(handle).is_defined() {
+            self.set_span(handle, span);
+        }
+    }
+
     /// Get the range of handles from a particular number of elements to the end.
     pub fn range_from(&self, old_length: usize) -> Range<T> {
         Range {
@@ -240,6 +333,110 @@ impl<T> Arena<T> {
             marker: PhantomData,
         }
     }
+
+    /// Keep only the items for which `predicate` returns `true`.
+    ///
+    /// The retained items keep their relative order, so handles stored
+    /// inside of them still point backwards. Returns the map from the old
+    /// handles to the new ones, which has to be applied to every handle
+    /// into this arena.
+    pub fn retain<P: FnMut(Handle<T>, &T) -> bool>(&mut self, mut predicate: P) -> HandleMap<T> {
+        let old_data = mem::take(&mut self.data);
+        #[cfg(feature = "span")]
+        let old_span_info = mem::take(&mut self.span_info);
+        let mut new_index = Vec::with_capacity(old_data.len());
+        let mut old_index = Vec::new();
+
+        for (i, value) in old_data.into_iter().enumerate() {
+            let index = unsafe { Index::new_unchecked((i + 1) as u32) };
+            if predicate(Handle::new(index), &value) {
+                self.data.push(value);
+                #[cfg(feature = "span")]
+                self.span_info
+                    .push(old_span_info.get(i).copied().unwrap_or_default());
+                new_index.push(Index::new(self.data.len() as u32));
+                old_index.push(index);
+            } else {
+                new_index.push(None);
+            }
+        }
+
+        HandleMap {
+            new_index,
+            old_index,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// A map from the handles of an [`Arena`] before [`Arena::retain`] to the
+/// handles after it.
+pub struct HandleMap<T> {
+    /// New index of every old item, if it was retained.
+    new_index: Vec<Option<Index>>,
+    /// Old index of every retained item.
+    old_index: Vec<Index>,
+    marker: PhantomData<T>,
+}
+
+impl<T> fmt::Debug for HandleMap<T> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_list()
+            .entries(self.new_index.iter())
+            .finish()
+    }
+}
+
+impl<T> HandleMap<T> {
+    /// Return the map of an arena of `len` items that retains all of them.
+    pub(crate) fn identity(len: usize) -> Self {
+        let old_index = (0..len)
+            .map(|index| Handle::<T>::from_usize(index).index)
+            .collect::<Vec<_>>();
+        HandleMap {
+            new_index: old_index.iter().cloned().map(Some).collect(),
+            old_index,
+            marker: PhantomData,
+        }
+    }
+
+    /// Return the new handle of `old`, or `None` if the item was removed.
+    pub fn try_adjust(&self, old: Handle<T>) -> Option<Handle<T>> {
+        self.new_index[old.index()].map(Handle::new)
+    }
+
+    /// Return the handle that the retained item `new` had before.
+    pub fn original(&self, new: Handle<T>) -> Handle<T> {
+        Handle::new(self.old_index[new.index()])
+    }
+
+    /// Update `handle` to point to the same item as before.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the item was removed.
+    pub fn adjust(&self, handle: &mut Handle<T>) {
+        *handle = self
+            .try_adjust(*handle)
+            .expect("Handle to a removed arena item");
+    }
+
+    /// Update `range` to cover the retained items it covered before.
+    ///
+    /// The retained items of a range are still contiguous,
+    /// so the result is empty only if all of them were removed.
+    pub fn adjust_range(&self, range: &mut Range<T>) {
+        // the number of items retained before an old index
+        let new_position = |old: u32| {
+            self.new_index[..old as usize]
+                .iter()
+                .rev()
+                .find_map(|&index| index)
+                .map_or(0, Index::get)
+        };
+        range.inner = new_position(range.inner.start)..new_position(range.inner.end);
+    }
 }
 
 impl<T> ops::Index<Handle<T>> for Arena<T> {
@@ -287,6 +484,50 @@ mod tests {
         assert!(arena[t1] == arena[t2])
     }
 
+    #[test]
+    fn retain_remap() {
+        let mut arena: Arena<u8> = Arena::new();
+        let handles = (0..5).map(|i| arena.append(i)).collect::<Vec<_>>();
+        let map = arena.retain(|_, &value| value % 2 == 0);
+        assert_eq!(arena.len(), 3);
+        assert_eq!(map.try_adjust(handles[1]), None);
+        let mut handle = handles[4];
+        map.adjust(&mut handle);
+        assert_eq!(arena[handle], 4);
+        assert_eq!(map.original(handle), handles[4]);
+
+        let mut range = Range {
+            inner: 1..4,
+            marker: PhantomData,
+        };
+        map.adjust_range(&mut range);
+        assert_eq!(range.map(|h| arena[h]).collect::<Vec<_>>(), [2]);
+    }
+
+    #[cfg(all(feature = "span", feature = "serialize", feature = "deserialize"))]
+    #[test]
+    fn deserialized_spans() {
+        let mut arena: Arena<u8> = Arena::new();
+        arena.append_with_span(0, Span::new(0, 1));
+        arena.append_with_span(1, Span::new(1, 2));
+        let serialized = ron::ser::to_string(&arena).unwrap();
+        let mut arena: Arena<u8> = ron::de::from_str(&serialized).unwrap();
+        assert_eq!(arena.len(), 2);
+
+        let handle = arena.append_with_span(2, Span::new(2, 3));
+        assert_eq!(arena.get_span(handle), Span::new(2, 3));
+        let first = arena.iter().next().unwrap().0;
+        assert!(!arena.get_span(first).is_defined());
+        arena.set_span(first, Span::new(0, 1));
+        assert_eq!(arena.get_span(first), Span::new(0, 1));
+
+        let mut arena: Arena<u8> = ron::de::from_str(&serialized).unwrap();
+        arena.retain(|_, &value| value != 0);
+        let handle = arena.append_with_span(3, Span::new(3, 4));
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get_span(handle), Span::new(3, 4));
+    }
+
     #[test]
     fn fetch_or_append_unique() {
         let mut arena: Arena<u8> = Arena::new();
@@ -120,6 +120,114 @@ impl StatementGraph {
                     self.calls.push((id, function));
                     "Call"
                 }
+                S::Atomic {
+                    pointer,
+                    ref fun,
+                    value,
+                    result,
+                } => {
+                    self.emits.push((id, result));
+                    self.dependencies.push((id, pointer, "pointer"));
+                    self.dependencies.push((id, value, "value"));
+                    if let crate::AtomicFunction::Exchange { compare: Some(cmp) } = *fun {
+                        self.dependencies.push((id, cmp, "cmp"));
+                    }
+                    "Atomic"
+                }
+                S::SubgroupBallot { result, predicate } => {
+                    if let Some(predicate) = predicate {
+                        self.dependencies.push((id, predicate, "predicate"));
+                    }
+                    self.emits.push((id, result));
+                    "SubgroupBallot"
+                }
+                S::SubgroupCollectiveOperation {
+                    op,
+                    collective_op,
+                    argument,
+                    result,
+                } => {
+                    self.dependencies.push((id, argument, "arg"));
+                    self.emits.push((id, result));
+                    match (collective_op, op) {
+                        (crate::CollectiveOperation::Reduce, crate::SubgroupOperation::All) => {
+                            "SubgroupAll"
+                        }
+                        (crate::CollectiveOperation::Reduce, crate::SubgroupOperation::Any) => {
+                            "SubgroupAny"
+                        }
+                        (crate::CollectiveOperation::Reduce, _) => "SubgroupReduce",
+                        (crate::CollectiveOperation::InclusiveScan, _) => "SubgroupInclusiveScan",
+                        (crate::CollectiveOperation::ExclusiveScan, _) => "SubgroupExclusiveScan",
+                    }
+                }
+                S::SubgroupGather {
+                    ref mode,
+                    argument,
+                    result,
+                } => {
+                    if let Some(index) = mode.index() {
+                        self.dependencies.push((id, index, "index"));
+                    }
+                    self.dependencies.push((id, argument, "arg"));
+                    self.emits.push((id, result));
+                    match *mode {
+                        crate::GatherMode::BroadcastFirst => "SubgroupBroadcastFirst",
+                        crate::GatherMode::Broadcast(_) => "SubgroupBroadcast",
+                        crate::GatherMode::Shuffle(_) => "SubgroupShuffle",
+                        crate::GatherMode::ShuffleDown(_) => "SubgroupShuffleDown",
+                        crate::GatherMode::ShuffleUp(_) => "SubgroupShuffleUp",
+                        crate::GatherMode::ShuffleXor(_) => "SubgroupShuffleXor",
+                    }
+                }
+                S::MeshFunction(ref fun) => match *fun {
+                    crate::MeshFunction::SetMeshOutputs {
+                        vertex_count,
+                        primitive_count,
+                    } => {
+                        self.dependencies.push((id, vertex_count, "vertex_count"));
+                        self.dependencies
+                            .push((id, primitive_count, "primitive_count"));
+                        "SetMeshOutputs"
+                    }
+                    crate::MeshFunction::SetVertex { index, value } => {
+                        self.dependencies.push((id, index, "index"));
+                        self.dependencies.push((id, value, "value"));
+                        "SetVertex"
+                    }
+                    crate::MeshFunction::SetIndices { index, value } => {
+                        self.dependencies.push((id, index, "index"));
+                        self.dependencies.push((id, value, "value"));
+                        "SetIndices"
+                    }
+                    crate::MeshFunction::SetPrimitive { index, value } => {
+                        self.dependencies.push((id, index, "index"));
+                        self.dependencies.push((id, value, "value"));
+                        "SetPrimitive"
+                    }
+                },
+                S::RayQuery { query, ref fun } => {
+                    self.dependencies.push((id, query, "query"));
+                    match *fun {
+                        crate::RayQueryFunction::Initialize {
+                            acceleration_structure,
+                            descriptor,
+                        } => {
+                            self.dependencies.push((
+                                id,
+                                acceleration_structure,
+                                "acceleration_structure",
+                            ));
+                            self.dependencies.push((id, descriptor, "descriptor"));
+                            "RayQueryInitialize"
+                        }
+                        crate::RayQueryFunction::Proceed { result } => {
+                            self.emits.push((id, result));
+                            "RayQueryProceed"
+                        }
+                        crate::RayQueryFunction::Terminate => "RayQueryTerminate",
+                    }
+                }
             };
         }
         root
@@ -213,6 +321,7 @@ fn write_fun(
             E::ImageSample {
                 image,
                 sampler,
+                gather: _,
                 coordinate,
                 array_index,
                 offset: _,
@@ -305,6 +414,7 @@ fn write_fun(
                 arg,
                 arg1,
                 arg2,
+                arg3,
             } => {
                 edges.insert("arg", arg);
                 if let Some(expr) = arg1 {
@@ -313,6 +423,9 @@ fn write_fun(
                 if let Some(expr) = arg2 {
                     edges.insert("arg2", expr);
                 }
+                if let Some(expr) = arg3 {
+                    edges.insert("arg3", expr);
+                }
                 (format!("{:?}", fun).into(), 7)
             }
             E::As {
@@ -328,6 +441,19 @@ fn write_fun(
                 (string.into(), 3)
             }
             E::Call(_function) => ("Call".into(), 4),
+            E::AtomicResult { kind, width, .. } => {
+                (format!("AtomicResult<{:?},{}>", kind, width).into(), 4)
+            }
+            E::SubgroupBallotResult => ("SubgroupBallotResult".into(), 4),
+            E::SubgroupOperationResult { .. } => ("SubgroupOperationResult".into(), 4),
+            E::RayQueryProceedResult => ("RayQueryProceedResult".into(), 4),
+            E::RayQueryGetIntersection {
+                query, committed, ..
+            } => {
+                edges.insert("", query);
+                let ty = if committed { "Committed" } else { "Candidate" };
+                (format!("RayQueryGet{}Intersection", ty).into(), 4)
+            }
             E::ArrayLength(expr) => {
                 edges.insert("", expr);
                 ("ArrayLength".into(), 7)
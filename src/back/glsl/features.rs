@@ -1,7 +1,7 @@
 use super::{BackendResult, Error, Version, Writer};
 use crate::{
-    Binding, Bytes, Handle, ImageClass, ImageDimension, Interpolation, Sampling, ScalarKind,
-    ShaderStage, StorageClass, StorageFormat, Type, TypeInner,
+    valid::FunctionInfo, Arena, ArraySize, Binding, Bytes, Handle, ImageClass, ImageDimension,
+    Interpolation, Sampling, ScalarKind, ShaderStage, StorageClass, StorageFormat, Type, TypeInner,
 };
 use std::fmt::Write;
 
@@ -32,6 +32,23 @@ bitflags::bitflags! {
         const CULL_DISTANCE = 1 << 14;
         // Sample ID
         const SAMPLE_VARIABLES = 1 << 15;
+        /// Subgroup built-ins and operations
+        const SUBGROUP_OPERATIONS = 1 << 16;
+        /// 2 byte floats
+        const HALF_FLOAT = 1 << 17;
+        /// 8 byte integers
+        const INT64 = 1 << 18;
+        const TEXTURE_GATHER = 1 << 19;
+        /// Data packing and unpacking functions
+        const DATA_PACKING = 1 << 20;
+        /// Runtime sized binding arrays and non-uniform indexing into them
+        const NONUNIFORM_QUALIFIER = 1 << 21;
+        /// Second blend source of the fragment output
+        const DUAL_SOURCE_BLENDING = 1 << 22;
+        /// View index for multiview rendering
+        const MULTI_VIEW = 1 << 23;
+        /// Primitive index in fragment shaders
+        const PRIMITIVE_INDEX = 1 << 24;
     }
 }
 
@@ -98,6 +115,16 @@ impl FeaturesManager {
         check_feature!(CLIP_DISTANCE, 130, 300);
         check_feature!(CULL_DISTANCE, 450, 300);
         check_feature!(SAMPLE_VARIABLES, 400, 300);
+        check_feature!(SUBGROUP_OPERATIONS, 430, 310);
+        check_feature!(HALF_FLOAT, 450, 320);
+        check_feature!(INT64, 400);
+        check_feature!(TEXTURE_GATHER, 400, 310);
+        check_feature!(DATA_PACKING, 400, 310);
+        check_feature!(NONUNIFORM_QUALIFIER, 450, 320);
+        // the blend source index needs explicit locations
+        check_feature!(DUAL_SOURCE_BLENDING, 410, 300);
+        check_feature!(MULTI_VIEW, 140, 310);
+        check_feature!(PRIMITIVE_INDEX, 150, 320);
 
         // Return an error if there are missing features
         if missing.is_empty() {
@@ -191,6 +218,62 @@ impl FeaturesManager {
             writeln!(out, "#extension GL_OES_sample_variables : require")?;
         }
 
+        if self.0.contains(Features::SUBGROUP_OPERATIONS) {
+            // https://github.com/KhronosGroup/GLSL/blob/master/extensions/khr/GL_KHR_shader_subgroup.txt
+            writeln!(out, "#extension GL_KHR_shader_subgroup_basic : require")?;
+            writeln!(out, "#extension GL_KHR_shader_subgroup_vote : require")?;
+            writeln!(out, "#extension GL_KHR_shader_subgroup_ballot : require")?;
+            writeln!(
+                out,
+                "#extension GL_KHR_shader_subgroup_arithmetic : require"
+            )?;
+            writeln!(out, "#extension GL_KHR_shader_subgroup_shuffle : require")?;
+            writeln!(
+                out,
+                "#extension GL_KHR_shader_subgroup_shuffle_relative : require"
+            )?;
+        }
+
+        if self.0.contains(Features::INT64) {
+            // https://www.khronos.org/registry/OpenGL/extensions/ARB/ARB_gpu_shader_int64.txt
+            writeln!(out, "#extension GL_ARB_gpu_shader_int64 : require")?;
+        }
+
+        if self.0.contains(Features::TEXTURE_GATHER) && version < Version::Desktop(400) {
+            // https://www.khronos.org/registry/OpenGL/extensions/ARB/ARB_texture_gather.txt
+            writeln!(out, "#extension GL_ARB_texture_gather : require")?;
+        }
+
+        if self.0.contains(Features::DATA_PACKING) && version < Version::Desktop(420) {
+            // https://www.khronos.org/registry/OpenGL/extensions/ARB/ARB_shading_language_packing.txt
+            writeln!(out, "#extension GL_ARB_shading_language_packing : require")?;
+        }
+
+        if self.0.contains(Features::NONUNIFORM_QUALIFIER) {
+            // https://github.com/KhronosGroup/GLSL/blob/master/extensions/ext/GL_EXT_nonuniform_qualifier.txt
+            writeln!(out, "#extension GL_EXT_nonuniform_qualifier : require")?;
+        }
+
+        if self.0.contains(Features::DUAL_SOURCE_BLENDING) && version.is_es() {
+            // https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_blend_func_extended.txt
+            writeln!(out, "#extension GL_EXT_blend_func_extended : require")?;
+        }
+
+        if self.0.contains(Features::MULTI_VIEW) {
+            // https://github.com/KhronosGroup/GLSL/blob/master/extensions/ext/GL_EXT_multiview.txt
+            writeln!(out, "#extension GL_EXT_multiview : require")?;
+        }
+
+        if self.0.contains(Features::HALF_FLOAT) {
+            // https://github.com/KhronosGroup/GLSL/blob/master/extensions/ext/GL_EXT_shader_16bit_storage.txt
+            writeln!(out, "#extension GL_EXT_shader_16bit_storage : require")?;
+            // https://github.com/KhronosGroup/GLSL/blob/master/extensions/ext/GL_EXT_shader_explicit_arithmetic_types.txt
+            writeln!(
+                out,
+                "#extension GL_EXT_shader_explicit_arithmetic_types_float16 : require"
+            )?;
+        }
+
         Ok(())
     }
 }
@@ -233,6 +316,10 @@ impl<'a, W> Writer<'a, W> {
                         self.features.request(Features::ARRAY_OF_ARRAYS)
                     }
                 }
+                TypeInner::BindingArray {
+                    size: ArraySize::Dynamic,
+                    ..
+                } => self.features.request(Features::NONUNIFORM_QUALIFIER),
                 TypeInner::Image {
                     dim,
                     arrayed,
@@ -291,13 +378,132 @@ impl<'a, W> Writer<'a, W> {
             }
         }
 
+        for (handle, function) in self.module.functions.iter() {
+            self.block_required_features(&function.body);
+            self.expressions_required_features(&function.expressions, &self.info[handle]);
+        }
+        self.block_required_features(&self.entry_point.function.body);
+        self.expressions_required_features(
+            &self.entry_point.function.expressions,
+            self.info.get_entry_point(self.entry_point_idx as usize),
+        );
+
         self.features.check_availability(self.options.version)
     }
 
     /// Helper method that checks the [`Features`](Features) needed by a scalar
     fn scalar_required_features(&mut self, kind: ScalarKind, width: Bytes) {
-        if kind == ScalarKind::Float && width == 8 {
-            self.features.request(Features::DOUBLE_TYPE);
+        match (kind, width) {
+            (ScalarKind::Float, 2) => self.features.request(Features::HALF_FLOAT),
+            (ScalarKind::Float, 8) => self.features.request(Features::DOUBLE_TYPE),
+            (ScalarKind::Sint, 8) | (ScalarKind::Uint, 8) => self.features.request(Features::INT64),
+            _ => {}
+        }
+    }
+
+    /// Helper method that checks the [`Features`](Features) needed by statements
+    fn block_required_features(&mut self, block: &[crate::Statement]) {
+        use crate::Statement as S;
+        for statement in block {
+            match *statement {
+                S::Block(ref block) => self.block_required_features(block),
+                S::Loop {
+                    ref body,
+                    ref continuing,
+                } => {
+                    self.block_required_features(body);
+                    self.block_required_features(continuing);
+                }
+                S::If {
+                    ref accept,
+                    ref reject,
+                    ..
+                } => {
+                    self.block_required_features(accept);
+                    self.block_required_features(reject);
+                }
+                S::Switch {
+                    ref cases,
+                    ref default,
+                    ..
+                } => {
+                    for case in cases {
+                        self.block_required_features(&case.body);
+                    }
+                    self.block_required_features(default);
+                }
+                S::SubgroupBallot { .. }
+                | S::SubgroupCollectiveOperation { .. }
+                | S::SubgroupGather { .. } => self.features.request(Features::SUBGROUP_OPERATIONS),
+                _ => {}
+            }
+        }
+    }
+
+    /// Helper method that checks the [`Features`](Features) needed by expressions
+    fn expressions_required_features(
+        &mut self,
+        expressions: &Arena<crate::Expression>,
+        info: &FunctionInfo,
+    ) {
+        use crate::MathFunction as Mf;
+
+        for (_, expression) in expressions.iter() {
+            match *expression {
+                crate::Expression::Access { base, index }
+                    if info[base]
+                        .ty
+                        .inner_with(&self.module.types)
+                        .is_binding_array(&self.module.types)
+                        && info[index].uniformity.non_uniform_result.is_some() =>
+                {
+                    self.features.request(Features::NONUNIFORM_QUALIFIER)
+                }
+                crate::Expression::ImageSample {
+                    gather: Some(_), ..
+                } => self.features.request(Features::TEXTURE_GATHER),
+                crate::Expression::Math {
+                    fun: Mf::Pack4x8snorm,
+                    ..
+                }
+                | crate::Expression::Math {
+                    fun: Mf::Pack4x8unorm,
+                    ..
+                }
+                | crate::Expression::Math {
+                    fun: Mf::Pack2x16snorm,
+                    ..
+                }
+                | crate::Expression::Math {
+                    fun: Mf::Pack2x16unorm,
+                    ..
+                }
+                | crate::Expression::Math {
+                    fun: Mf::Pack2x16float,
+                    ..
+                }
+                | crate::Expression::Math {
+                    fun: Mf::Unpack4x8snorm,
+                    ..
+                }
+                | crate::Expression::Math {
+                    fun: Mf::Unpack4x8unorm,
+                    ..
+                }
+                | crate::Expression::Math {
+                    fun: Mf::Unpack2x16snorm,
+                    ..
+                }
+                | crate::Expression::Math {
+                    fun: Mf::Unpack2x16unorm,
+                    ..
+                }
+                | crate::Expression::Math {
+                    fun: Mf::Unpack2x16float,
+                    ..
+                } => self.features.request(Features::DATA_PACKING),
+                _ => {}
+            }
         }
     }
 
@@ -321,13 +527,26 @@ impl<'a, W> Writer<'a, W> {
                             crate::BuiltIn::SampleIndex => {
                                 self.features.request(Features::SAMPLE_VARIABLES)
                             }
+                            crate::BuiltIn::ViewIndex => {
+                                self.features.request(Features::MULTI_VIEW)
+                            }
+                            crate::BuiltIn::PrimitiveIndex => {
+                                self.features.request(Features::PRIMITIVE_INDEX)
+                            }
+                            crate::BuiltIn::SubgroupSize | crate::BuiltIn::SubgroupInvocationId => {
+                                self.features.request(Features::SUBGROUP_OPERATIONS)
+                            }
                             _ => {}
                         },
                         Binding::Location {
                             location: _,
+                            second_blend_source,
                             interpolation,
                             sampling,
                         } => {
+                            if second_blend_source {
+                                self.features.request(Features::DUAL_SOURCE_BLENDING);
+                            }
                             if interpolation == Some(Interpolation::Linear) {
                                 self.features.request(Features::NOPERSPECTIVE_QUALIFIER);
                             }
@@ -203,9 +203,15 @@ struct VaryingName<'a> {
 impl fmt::Display for VaryingName<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self.binding {
-            Binding::Location { location, .. } => {
+            Binding::Location {
+                location,
+                second_blend_source,
+                ..
+            } => {
                 let prefix = match (self.stage, self.output) {
-                    (ShaderStage::Compute, _) => unreachable!(),
+                    (ShaderStage::Compute, _) | (ShaderStage::Task, _) | (ShaderStage::Mesh, _) => {
+                        unreachable!()
+                    }
                     // pipeline to vertex
                     (ShaderStage::Vertex, false) => "p2vs",
                     // vertex to fragment
@@ -213,7 +219,11 @@ impl fmt::Display for VaryingName<'_> {
                     // fragment to pipeline
                     (ShaderStage::Fragment, true) => "fs2p",
                 };
-                write!(f, "_{}_location{}", prefix, location,)
+                write!(f, "_{}_location{}", prefix, location,)?;
+                if second_blend_source {
+                    write!(f, "_index1")?;
+                }
+                Ok(())
             }
             Binding::BuiltIn(built_in) => {
                 write!(f, "{}", glsl_built_in(built_in, self.output))
@@ -240,6 +250,20 @@ pub enum Error {
     /// supported in the glsl backend
     #[error("Push constants aren't supported")]
     PushConstantNotSupported,
+    /// [`ShaderStage::Task`](crate::ShaderStage::Task) or
+    /// [`ShaderStage::Mesh`](crate::ShaderStage::Mesh) was requested and isn't
+    /// supported in the glsl backend
+    #[error("Task and mesh shaders aren't supported")]
+    MeshShadingNotSupported,
+    /// [`TypeInner::RayQuery`](crate::TypeInner::RayQuery) or
+    /// [`TypeInner::AccelerationStructure`](crate::TypeInner::AccelerationStructure)
+    /// was used and isn't supported in the glsl backend
+    #[error("Ray queries aren't supported")]
+    RayQueryNotSupported,
+    /// [`Module::derived_constants`](crate::Module::derived_constants) isn't empty,
+    /// and the module has to be specialized before it's written
+    #[error("Derived constants aren't supported")]
+    DerivedConstantsNotSupported,
     /// The specified [`Version`](Version) isn't supported
     #[error("The specified version isn't supported")]
     VersionNotSupported,
@@ -315,6 +339,12 @@ impl<'a, W: Write> Writer<'a, W> {
             .iter()
             .position(|ep| options.shader_stage == ep.stage && options.entry_point == ep.name)
             .ok_or(Error::EntryPointNotFound)?;
+        if let ShaderStage::Task | ShaderStage::Mesh = options.shader_stage {
+            return Err(Error::MeshShadingNotSupported);
+        }
+        if !module.derived_constants.is_empty() {
+            return Err(Error::DerivedConstantsNotSupported);
+        }
 
         // Generate a map with names required to write the module
         let mut names = FastHashMap::default();
@@ -422,6 +452,21 @@ impl<'a, W: Write> Writer<'a, W> {
             }
         }
 
+        // Write the specializable constants
+        //
+        // They can't be inlined like the other constants since their value is provided
+        // through the preprocessor
+        let mut wrote_constants = false;
+        for (handle, constant) in self.module.constants.iter() {
+            if let Some(id) = constant.specialization {
+                self.write_specializable_constant(handle, id)?;
+                wrote_constants = true;
+            }
+        }
+        if wrote_constants {
+            writeln!(self.out)?;
+        }
+
         let ep_info = self.info.get_entry_point(self.entry_point_idx as usize);
 
         // Write the globals
@@ -434,7 +479,16 @@ impl<'a, W: Write> Writer<'a, W> {
                 continue;
             }
 
-            match self.module.types[global.ty].inner {
+            // Binding arrays of images and samplers are declared as plain
+            // arrays of the element type
+            let (inner, binding_array_size) = match self.module.types[global.ty].inner {
+                TypeInner::BindingArray { base, size } => {
+                    (&self.module.types[base].inner, Some(size))
+                }
+                ref other => (other, None),
+            };
+
+            match *inner {
                 // We treat images separately because they might require
                 // writing the storage format
                 TypeInner::Image {
@@ -443,11 +497,7 @@ impl<'a, W: Write> Writer<'a, W> {
                     class,
                 } => {
                     // Write the storage format if needed
-                    if let TypeInner::Image {
-                        class: ImageClass::Storage(format),
-                        ..
-                    } = self.module.types[global.ty].inner
-                    {
+                    if let ImageClass::Storage(format) = class {
                         write!(self.out, "layout({}) ", glsl_storage_format(format))?;
                     }
 
@@ -468,7 +518,11 @@ impl<'a, W: Write> Writer<'a, W> {
                     // Finally write the name and end the global with a `;`
                     // The leading space is important
                     let global_name = self.get_global_name(handle, global);
-                    writeln!(self.out, " {};", global_name)?;
+                    write!(self.out, " {}", global_name)?;
+                    if let Some(size) = binding_array_size {
+                        self.write_array_size(size)?;
+                    }
+                    writeln!(self.out, ";")?;
                     writeln!(self.out)?;
 
                     self.reflection_names.insert(global.ty, global_name);
@@ -557,7 +611,9 @@ impl<'a, W: Write> Writer<'a, W> {
     fn write_value_type(&mut self, inner: &TypeInner) -> BackendResult {
         match *inner {
             // Scalars are simple we just get the full name from `glsl_scalar`
+            // Atomics are plain scalars in glsl, which are operated on with `atomic*` functions
             TypeInner::Scalar { kind, width }
+            | TypeInner::Atomic { kind, width }
             | TypeInner::ValuePointer {
                 size: None,
                 kind,
@@ -596,7 +652,9 @@ impl<'a, W: Write> Writer<'a, W> {
             // GLSL arrays are written as `type name[size]`
             // Current code is written arrays only as `[size]`
             // Base `type` and `name` should be written outside
-            TypeInner::Array { size, .. } => self.write_array_size(size)?,
+            TypeInner::Array { size, .. } | TypeInner::BindingArray { size, .. } => {
+                self.write_array_size(size)?
+            }
             // Panic if either Image, Sampler, Pointer, or a Struct is being written
             //
             // Write all variants instead of `_` so that if new variants are added a
@@ -605,6 +663,9 @@ impl<'a, W: Write> Writer<'a, W> {
             | TypeInner::Struct { .. }
             | TypeInner::Image { .. }
             | TypeInner::Sampler { .. } => unreachable!(),
+            TypeInner::AccelerationStructure | TypeInner::RayQuery => {
+                return Err(Error::RayQueryNotSupported)
+            }
         }
 
         Ok(())
@@ -636,7 +697,9 @@ impl<'a, W: Write> Writer<'a, W> {
                 Ok(())
             }
             // glsl array has the size separated from the base type
-            TypeInner::Array { base, .. } => self.write_type(base),
+            TypeInner::Array { base, .. } | TypeInner::BindingArray { base, .. } => {
+                self.write_type(base)
+            }
             ref other => self.write_value_type(other),
         }
     }
@@ -720,8 +783,11 @@ impl<'a, W: Write> Writer<'a, W> {
         // Leading space is important
         let global_name = self.get_global_name(handle, global);
         write!(self.out, " {}", global_name)?;
-        if let TypeInner::Array { size, .. } = self.module.types[global.ty].inner {
-            self.write_array_size(size)?;
+        match self.module.types[global.ty].inner {
+            TypeInner::Array { size, .. } | TypeInner::BindingArray { size, .. } => {
+                self.write_array_size(size)?
+            }
+            _ => {}
         }
         if let Some(default_value) = zero_init_value_str(&self.module.types[global.ty].inner) {
             write!(self.out, " = {}", default_value)?;
@@ -760,12 +826,13 @@ impl<'a, W: Write> Writer<'a, W> {
                 }
             }
             _ => {
-                let (location, interpolation, sampling) = match binding {
+                let (location, second_blend_source, interpolation, sampling) = match binding {
                     Some(&Binding::Location {
                         location,
+                        second_blend_source,
                         interpolation,
                         sampling,
-                    }) => (location, interpolation, sampling),
+                    }) => (location, second_blend_source, interpolation, sampling),
                     _ => return Ok(()),
                 };
 
@@ -788,7 +855,11 @@ impl<'a, W: Write> Writer<'a, W> {
                 if !emit_interpolation_and_auxiliary
                     && self.options.version.supports_explicit_locations()
                 {
-                    write!(self.out, "layout(location = {}) ", location)?;
+                    if second_blend_source {
+                        write!(self.out, "layout(location = {}, index = 1) ", location)?;
+                    } else {
+                        write!(self.out, "layout(location = {}) ", location)?;
+                    }
                 }
 
                 // Write the sampling auxiliary qualifier.
@@ -816,6 +887,7 @@ impl<'a, W: Write> Writer<'a, W> {
                 let vname = VaryingName {
                     binding: &Binding::Location {
                         location,
+                        second_blend_source,
                         interpolation: None,
                         sampling: None,
                     },
@@ -956,7 +1028,7 @@ impl<'a, W: Write> Writer<'a, W> {
 
                 // Write the constant
                 // `write_constant` adds no trailing or leading space/newline
-                self.write_constant(&self.module.constants[init])?;
+                self.write_constant(init)?;
             }
 
             // Finish the local with `;` and add a newline (only for readability)
@@ -1009,25 +1081,16 @@ impl<'a, W: Write> Writer<'a, W> {
     ///
     /// # Notes
     /// Adds no newlines or leading/trailing whitespace
-    fn write_constant(&mut self, constant: &Constant) -> BackendResult {
+    fn write_constant(&mut self, handle: Handle<Constant>) -> BackendResult {
+        let constant = &self.module.constants[handle];
+        // Specializable constants are declared globally, so we refer to them by name
+        if constant.specialization.is_some() {
+            write!(self.out, "{}", self.names[&NameKey::Constant(handle)])?;
+            return Ok(());
+        }
+
         match constant.inner {
-            ConstantInner::Scalar {
-                width: _,
-                ref value,
-            } => match *value {
-                // Signed integers don't need anything special
-                ScalarValue::Sint(int) => write!(self.out, "{}", int)?,
-                // Unsigned integers need a `u` at the end
-                //
-                // While `core` doesn't necessarily need it, it's allowed and since `es` needs it we
-                // always write it as the extra branch wouldn't have any benefit in readability
-                ScalarValue::Uint(int) => write!(self.out, "{}u", int)?,
-                // Floats are written using `Debug` instead of `Display` because it always appends the
-                // decimal part even it's zero which is needed for a valid glsl float constant
-                ScalarValue::Float(float) => write!(self.out, "{:?}", float)?,
-                // Booleans are either `true` or `false` so nothing special needs to be done
-                ScalarValue::Bool(boolean) => write!(self.out, "{}", boolean)?,
-            },
+            ConstantInner::Scalar { width, ref value } => self.write_scalar_value(value, width)?,
             // Composite constant are created using the same syntax as compose
             // `type(components)` where `components` is a comma separated list of constants
             ConstantInner::Composite { ty, ref components } => {
@@ -1035,9 +1098,7 @@ impl<'a, W: Write> Writer<'a, W> {
                 write!(self.out, "(")?;
 
                 // Write the comma separated constants
-                self.write_slice(components, |this, _, arg| {
-                    this.write_constant(&this.module.constants[*arg])
-                })?;
+                self.write_slice(components, |this, _, arg| this.write_constant(*arg))?;
 
                 write!(self.out, ")")?
             }
@@ -1046,6 +1107,65 @@ impl<'a, W: Write> Writer<'a, W> {
         Ok(())
     }
 
+    /// Helper method used to write scalar values
+    ///
+    /// # Notes
+    /// Adds no newlines or leading/trailing whitespace
+    fn write_scalar_value(&mut self, value: &ScalarValue, width: Bytes) -> BackendResult {
+        match *value {
+            // Signed integers don't need anything special, unless they are 64-bit wide
+            ScalarValue::Sint(int) if width == 8 => write!(self.out, "{}l", int)?,
+            ScalarValue::Sint(int) => write!(self.out, "{}", int)?,
+            // Unsigned integers need a `u` at the end
+            //
+            // While `core` doesn't necessarily need it, it's allowed and since `es` needs it we
+            // always write it as the extra branch wouldn't have any benefit in readability
+            ScalarValue::Uint(int) if width == 8 => write!(self.out, "{}ul", int)?,
+            ScalarValue::Uint(int) => write!(self.out, "{}u", int)?,
+            // Floats are written using `Debug` instead of `Display` because it always appends the
+            // decimal part even it's zero which is needed for a valid glsl float constant
+            //
+            // Half floats need the `hf` suffix from `GL_EXT_shader_explicit_arithmetic_types_float16`
+            ScalarValue::Float(float) if width == 2 => write!(self.out, "{:?}hf", float)?,
+            ScalarValue::Float(float) => write!(self.out, "{:?}", float)?,
+            // Booleans are either `true` or `false` so nothing special needs to be done
+            ScalarValue::Bool(boolean) => write!(self.out, "{}", boolean)?,
+        }
+
+        Ok(())
+    }
+
+    /// Helper method used to write the declaration of a specializable constant
+    ///
+    /// The value can be overridden by defining `SPIRV_CROSS_CONSTANT_ID_<id>`
+    /// before the shader source, the same hook `SPIRV-Cross` uses.
+    fn write_specializable_constant(&mut self, handle: Handle<Constant>, id: u32) -> BackendResult {
+        let (kind, width, value) = match self.module.constants[handle].inner {
+            ConstantInner::Scalar { width, ref value } => (value.scalar_kind(), width, value),
+            // Composites can't be specialized, this should fail validation
+            ConstantInner::Composite { .. } => {
+                return Err(Error::Custom(
+                    "Composite constants can't be specialized".to_string(),
+                ))
+            }
+        };
+        let macro_name = format!("SPIRV_CROSS_CONSTANT_ID_{}", id);
+        writeln!(self.out, "#ifndef {}", macro_name)?;
+        write!(self.out, "#define {} ", macro_name)?;
+        self.write_scalar_value(value, width)?;
+        writeln!(self.out)?;
+        writeln!(self.out, "#endif")?;
+        writeln!(
+            self.out,
+            "const {} {} = {};",
+            glsl_scalar(kind, width)?.full,
+            self.names[&NameKey::Constant(handle)],
+            macro_name
+        )?;
+
+        Ok(())
+    }
+
     /// Helper method used to write structs
     ///
     /// # Notes
@@ -1092,7 +1212,9 @@ impl<'a, W: Write> Writer<'a, W> {
                     let ty_name = match self.module.types[base].inner {
                         // Write scalar type by backend so as not to depend on the front-end implementation
                         // Name returned from frontend can be generated (type1, float1, etc.)
-                        TypeInner::Scalar { kind, width } => glsl_scalar(kind, width)?.full,
+                        TypeInner::Scalar { kind, width } | TypeInner::Atomic { kind, width } => {
+                            glsl_scalar(kind, width)?.full
+                        }
                         _ => &self.names[&NameKey::Type(base)],
                     };
 
@@ -1452,6 +1574,141 @@ impl<'a, W: Write> Writer<'a, W> {
                 self.write_slice(arguments, |this, _, arg| this.write_expr(*arg, ctx))?;
                 writeln!(self.out, ");")?
             }
+            // An atomic is written as `atomicOp(pointer, value)`, with the result
+            // holding the original value
+            Statement::Atomic {
+                pointer,
+                ref fun,
+                value,
+                result,
+            } => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                let res_name = format!("_expr{}", result.index());
+                let res_ty = ctx.info[result].ty.inner_with(&self.module.types);
+
+                match *fun {
+                    // `atomicCompSwap` only returns the original value, which
+                    // tells if it was exchanged when compared to the operand
+                    crate::AtomicFunction::Exchange {
+                        compare: Some(compare_expr),
+                    } => {
+                        let value_ty = ctx.info[value].ty.inner_with(&self.module.types);
+                        let compare_name = match self.named_expressions.get(&compare_expr) {
+                            Some(name) => name.clone(),
+                            None => {
+                                let name = format!("{}_compare", res_name);
+                                self.write_value_type(value_ty)?;
+                                write!(self.out, " {} = ", name)?;
+                                self.write_expr(compare_expr, ctx)?;
+                                writeln!(self.out, ";")?;
+                                write!(self.out, "{}", INDENT.repeat(indent))?;
+                                name
+                            }
+                        };
+                        self.write_value_type(value_ty)?;
+                        write!(self.out, " {}_old = atomicCompSwap(", res_name)?;
+                        self.write_expr(pointer, ctx)?;
+                        write!(self.out, ", {}, ", compare_name)?;
+                        self.write_expr(value, ctx)?;
+                        writeln!(self.out, ");")?;
+                        write!(self.out, "{}", INDENT.repeat(indent))?;
+                        self.write_value_type(res_ty)?;
+                        write!(self.out, " {} = ", res_name)?;
+                        self.write_value_type(res_ty)?;
+                        write!(self.out, "({}_old, ", res_name)?;
+                        self.write_value_type(value_ty)?;
+                        write!(self.out, "({}_old == {}))", res_name, compare_name)?;
+                    }
+                    _ => {
+                        self.write_value_type(res_ty)?;
+                        write!(self.out, " {} = ", res_name)?;
+                        let fun_str = fun.to_glsl();
+                        write!(self.out, "atomic{}(", fun_str)?;
+                        self.write_expr(pointer, ctx)?;
+                        write!(self.out, ", ")?;
+                        match *fun {
+                            // There is no `atomicSub`, so we add the negated value instead
+                            crate::AtomicFunction::Subtract => {
+                                write!(self.out, "-(")?;
+                                self.write_expr(value, ctx)?;
+                                write!(self.out, ")")?;
+                            }
+                            _ => self.write_expr(value, ctx)?,
+                        }
+                        write!(self.out, ")")?;
+                    }
+                }
+                self.named_expressions.insert(result, res_name);
+                writeln!(self.out, ";")?;
+            }
+            // Subgroup operations are written like atomics, storing the result
+            // in a named temporary
+            Statement::SubgroupBallot { result, predicate } => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                let res_name = format!("_expr{}", result.index());
+                let res_ty = ctx.info[result].ty.inner_with(&self.module.types);
+                self.write_value_type(res_ty)?;
+                write!(self.out, " {} = subgroupBallot(", res_name)?;
+                self.named_expressions.insert(result, res_name);
+                match predicate {
+                    Some(predicate) => self.write_expr(predicate, ctx)?,
+                    None => write!(self.out, "true")?,
+                }
+                writeln!(self.out, ");")?;
+            }
+            Statement::SubgroupCollectiveOperation {
+                op,
+                collective_op,
+                argument,
+                result,
+            } => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                let res_name = format!("_expr{}", result.index());
+                let res_ty = ctx.info[result].ty.inner_with(&self.module.types);
+                self.write_value_type(res_ty)?;
+                write!(self.out, " {} = ", res_name)?;
+                self.named_expressions.insert(result, res_name);
+
+                let prefix = match collective_op {
+                    crate::CollectiveOperation::Reduce => "",
+                    crate::CollectiveOperation::InclusiveScan => "Inclusive",
+                    crate::CollectiveOperation::ExclusiveScan => "Exclusive",
+                };
+                write!(self.out, "subgroup{}{}(", prefix, op.to_glsl())?;
+                self.write_expr(argument, ctx)?;
+                writeln!(self.out, ");")?;
+            }
+            Statement::SubgroupGather {
+                ref mode,
+                argument,
+                result,
+            } => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                let res_name = format!("_expr{}", result.index());
+                let res_ty = ctx.info[result].ty.inner_with(&self.module.types);
+                self.write_value_type(res_ty)?;
+                write!(self.out, " {} = ", res_name)?;
+                self.named_expressions.insert(result, res_name);
+
+                let fun_str = match *mode {
+                    crate::GatherMode::BroadcastFirst => "subgroupBroadcastFirst",
+                    crate::GatherMode::Broadcast(_) => "subgroupBroadcast",
+                    crate::GatherMode::Shuffle(_) => "subgroupShuffle",
+                    crate::GatherMode::ShuffleDown(_) => "subgroupShuffleDown",
+                    crate::GatherMode::ShuffleUp(_) => "subgroupShuffleUp",
+                    crate::GatherMode::ShuffleXor(_) => "subgroupShuffleXor",
+                };
+                write!(self.out, "{}(", fun_str)?;
+                self.write_expr(argument, ctx)?;
+                if let Some(index) = mode.index() {
+                    write!(self.out, ", ")?;
+                    self.write_expr(index, ctx)?;
+                }
+                writeln!(self.out, ");")?;
+            }
+            // Mesh stages are rejected when creating the writer
+            Statement::MeshFunction(_) => unreachable!(),
+            Statement::RayQuery { .. } => return Err(Error::RayQueryNotSupported),
         }
 
         Ok(())
@@ -1472,7 +1729,20 @@ impl<'a, W: Write> Writer<'a, W> {
             Expression::Access { base, index } => {
                 self.write_expr(base, ctx)?;
                 write!(self.out, "[")?;
+                // Indexing a binding array with a non-uniform value must be
+                // marked explicitly
+                let non_uniform = ctx.info[base]
+                    .ty
+                    .inner_with(&self.module.types)
+                    .is_binding_array(&self.module.types)
+                    && ctx.info[index].uniformity.non_uniform_result.is_some();
+                if non_uniform {
+                    write!(self.out, "nonuniformEXT(")?;
+                }
                 self.write_expr(index, ctx)?;
+                if non_uniform {
+                    write!(self.out, ")")?;
+                }
                 write!(self.out, "]")?
             }
             // `AccessIndex` is the same as `Access` except that the index is a constant and it can
@@ -1498,6 +1768,7 @@ impl<'a, W: Write> Writer<'a, W> {
                     }
                     TypeInner::Matrix { .. }
                     | TypeInner::Array { .. }
+                    | TypeInner::BindingArray { .. }
                     | TypeInner::ValuePointer { .. } => write!(self.out, "[{}]", index)?,
                     TypeInner::Struct { .. } => {
                         // This will never panic in case the type is a `Struct`, this is not true
@@ -1514,9 +1785,7 @@ impl<'a, W: Write> Writer<'a, W> {
                 }
             }
             // Constants are delegated to `write_constant`
-            Expression::Constant(constant) => {
-                self.write_constant(&self.module.constants[constant])?
-            }
+            Expression::Constant(constant) => self.write_constant(constant)?,
             // `Splat` needs to actually write down a vector, it's not always inferred in GLSL.
             Expression::Splat { size: _, value } => {
                 let resolved = ctx.info[expr].ty.inner_with(&self.module.types);
@@ -1571,9 +1840,58 @@ impl<'a, W: Write> Writer<'a, W> {
             // `textureLod(image, coordinate, level)` - Zero or Exact sample level
             //
             // Furthermore if `depth_ref` is some we need to append it to the coordinate vector
+            //
+            // Gathering is different, `textureGather(image, coordinate, component)` takes
+            // the depth reference in place of the component, and has an `Offset` variant.
+            Expression::ImageSample {
+                image,
+                sampler: _, //TODO
+                gather: Some(component),
+                coordinate,
+                array_index,
+                offset,
+                level: _,
+                depth_ref,
+            } => {
+                let fun_name = match offset {
+                    Some(_) => "textureGatherOffset",
+                    None => "textureGather",
+                };
+                write!(self.out, "{}(", fun_name)?;
+                self.write_expr(image, ctx)?;
+                write!(self.out, ", ")?;
+                match array_index {
+                    Some(expr) => {
+                        let size = match *ctx.info[coordinate].ty.inner_with(&self.module.types) {
+                            TypeInner::Vector { size, .. } => size,
+                            _ => unreachable!(),
+                        };
+                        write!(self.out, "vec{}(", size as u8 + 1)?;
+                        self.write_expr(coordinate, ctx)?;
+                        write!(self.out, ", ")?;
+                        self.write_expr(expr, ctx)?;
+                        write!(self.out, ")")?;
+                    }
+                    None => self.write_expr(coordinate, ctx)?,
+                }
+                if let Some(expr) = depth_ref {
+                    write!(self.out, ", ")?;
+                    self.write_expr(expr, ctx)?;
+                }
+                if let Some(constant) = offset {
+                    write!(self.out, ", ")?;
+                    self.write_constant(constant)?;
+                }
+                // the component defaults to 0, and is not allowed for shadow samplers
+                if depth_ref.is_none() && component != crate::SwizzleComponent::X {
+                    write!(self.out, ", {}", component as u8)?;
+                }
+                write!(self.out, ")")?
+            }
             Expression::ImageSample {
                 image,
                 sampler: _, //TODO
+                gather: None,
                 coordinate,
                 array_index,
                 offset: _, //TODO
@@ -1907,6 +2225,7 @@ impl<'a, W: Write> Writer<'a, W> {
                 arg,
                 arg1,
                 arg2,
+                arg3,
             } => {
                 use crate::MathFunction as Mf;
 
@@ -1966,21 +2285,108 @@ impl<'a, W: Write> Writer<'a, W> {
                     Mf::Transpose => "transpose",
                     Mf::Determinant => "determinant",
                     // bits
+                    Mf::CountTrailingZeros | Mf::CountLeadingZeros => {
+                        let (kind, size) = match *ctx.info[arg].ty.inner_with(&self.module.types) {
+                            TypeInner::Scalar { kind, .. } => (kind, None),
+                            TypeInner::Vector { kind, size, .. } => (kind, Some(size)),
+                            _ => unreachable!(),
+                        };
+                        let (result_ty, uint_ty, int_ty) = match size {
+                            Some(size) => (
+                                format!("{}vec{}", glsl_scalar(kind, 4)?.prefix, size as u8),
+                                format!("uvec{}", size as u8),
+                                format!("ivec{}", size as u8),
+                            ),
+                            None => (
+                                glsl_scalar(kind, 4)?.full.to_string(),
+                                "uint".to_string(),
+                                "int".to_string(),
+                            ),
+                        };
+                        // glsl doesn't have these functions, so they are built
+                        // on top of `findLSB` and `findMSB`, which return -1 for zero
+                        if fun == Mf::CountTrailingZeros {
+                            write!(self.out, "{}(min({}(findLSB(", result_ty, uint_ty)?;
+                            self.write_expr(arg, ctx)?;
+                            write!(self.out, ")), {}(32u)))", uint_ty)?;
+                        } else {
+                            // looking at the unsigned value gives 0 for negative numbers
+                            write!(
+                                self.out,
+                                "{}({}(31) - findMSB({}(",
+                                result_ty, int_ty, uint_ty
+                            )?;
+                            self.write_expr(arg, ctx)?;
+                            write!(self.out, ")))")?;
+                        }
+                        return Ok(());
+                    }
                     Mf::CountOneBits => "bitCount",
                     Mf::ReverseBits => "bitfieldReverse",
+                    Mf::ExtractBits => "bitfieldExtract",
+                    Mf::InsertBits => "bitfieldInsert",
+                    Mf::FirstTrailingBit => "findLSB",
+                    Mf::FirstLeadingBit => "findMSB",
+                    // data packing
+                    Mf::Pack4x8snorm => "packSnorm4x8",
+                    Mf::Pack4x8unorm => "packUnorm4x8",
+                    Mf::Pack2x16snorm => "packSnorm2x16",
+                    Mf::Pack2x16unorm => "packUnorm2x16",
+                    Mf::Pack2x16float => "packHalf2x16",
+                    // data unpacking
+                    Mf::Unpack4x8snorm => "unpackSnorm4x8",
+                    Mf::Unpack4x8unorm => "unpackUnorm4x8",
+                    Mf::Unpack2x16snorm => "unpackSnorm2x16",
+                    Mf::Unpack2x16unorm => "unpackUnorm2x16",
+                    Mf::Unpack2x16float => "unpackHalf2x16",
                 };
 
+                // `bitCount`, `findLSB` and `findMSB` always return signed integers
+                let result_conversion = match fun {
+                    Mf::CountOneBits | Mf::FirstTrailingBit | Mf::FirstLeadingBit => {
+                        match *ctx.info[arg].ty.inner_with(&self.module.types) {
+                            TypeInner::Scalar {
+                                kind: ScalarKind::Uint,
+                                ..
+                            } => Some("uint".to_string()),
+                            TypeInner::Vector {
+                                kind: ScalarKind::Uint,
+                                size,
+                                ..
+                            } => Some(format!("uvec{}", size as u8)),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                // bit field offsets and counts are signed in glsl
+                let first_int_arg = match fun {
+                    Mf::ExtractBits => 1,
+                    Mf::InsertBits => 2,
+                    _ => 4,
+                };
+
+                if let Some(ref ty) = result_conversion {
+                    write!(self.out, "{}(", ty)?;
+                }
                 write!(self.out, "{}(", fun_name)?;
                 self.write_expr(arg, ctx)?;
-                if let Some(arg) = arg1 {
-                    write!(self.out, ", ")?;
-                    self.write_expr(arg, ctx)?;
+                for (index, &arg) in [arg1, arg2, arg3].iter().enumerate() {
+                    if let Some(arg) = arg {
+                        write!(self.out, ", ")?;
+                        if index + 1 >= first_int_arg {
+                            write!(self.out, "int(")?;
+                            self.write_expr(arg, ctx)?;
+                            write!(self.out, ")")?;
+                        } else {
+                            self.write_expr(arg, ctx)?;
+                        }
+                    }
                 }
-                if let Some(arg) = arg2 {
-                    write!(self.out, ", ")?;
-                    self.write_expr(arg, ctx)?;
+                write!(self.out, ")")?;
+                if result_conversion.is_some() {
+                    write!(self.out, ")")?
                 }
-                write!(self.out, ")")?
             }
             // `As` is always a call.
             // If `convert` is true the function name is the type
@@ -2030,7 +2436,13 @@ impl<'a, W: Write> Writer<'a, W> {
                 self.write_expr(expr, ctx)?;
                 write!(self.out, ")")?
             }
-            Expression::Call(_function) => unreachable!(),
+            Expression::Call(_)
+            | Expression::AtomicResult { .. }
+            | Expression::SubgroupBallotResult
+            | Expression::SubgroupOperationResult { .. } => unreachable!(),
+            Expression::RayQueryProceedResult | Expression::RayQueryGetIntersection { .. } => {
+                return Err(Error::RayQueryNotSupported)
+            }
             // `ArrayLength` is written as `expr.length()` and we convert it to a uint
             Expression::ArrayLength(expr) => {
                 write!(self.out, "uint(")?;
@@ -2173,6 +2585,37 @@ struct ScalarString<'a> {
     full: &'a str,
 }
 
+impl crate::AtomicFunction {
+    fn to_glsl(self) -> &'static str {
+        match self {
+            Self::Add | Self::Subtract => "Add",
+            Self::And => "And",
+            Self::InclusiveOr => "Or",
+            Self::ExclusiveOr => "Xor",
+            Self::Min => "Min",
+            Self::Max => "Max",
+            Self::Exchange { compare: None } => "Exchange",
+            Self::Exchange { compare: Some(_) } => "CompSwap",
+        }
+    }
+}
+
+impl crate::SubgroupOperation {
+    fn to_glsl(self) -> &'static str {
+        match self {
+            Self::All => "All",
+            Self::Any => "Any",
+            Self::Add => "Add",
+            Self::Mul => "Mul",
+            Self::Min => "Min",
+            Self::Max => "Max",
+            Self::And => "And",
+            Self::Or => "Or",
+            Self::Xor => "Xor",
+        }
+    }
+}
+
 /// Helper function that returns scalar related strings
 ///
 /// Check [`ScalarString`](ScalarString) for the information provided
@@ -2181,15 +2624,31 @@ struct ScalarString<'a> {
 /// If a [`Float`](crate::ScalarKind::Float) with an width that isn't 4 or 8
 fn glsl_scalar(kind: ScalarKind, width: Bytes) -> Result<ScalarString<'static>, Error> {
     Ok(match kind {
-        ScalarKind::Sint => ScalarString {
-            prefix: "i",
-            full: "int",
+        ScalarKind::Sint => match width {
+            8 => ScalarString {
+                prefix: "i64",
+                full: "int64_t",
+            },
+            _ => ScalarString {
+                prefix: "i",
+                full: "int",
+            },
         },
-        ScalarKind::Uint => ScalarString {
-            prefix: "u",
-            full: "uint",
+        ScalarKind::Uint => match width {
+            8 => ScalarString {
+                prefix: "u64",
+                full: "uint64_t",
+            },
+            _ => ScalarString {
+                prefix: "u",
+                full: "uint",
+            },
         },
         ScalarKind::Float => match width {
+            2 => ScalarString {
+                prefix: "f16",
+                full: "float16_t",
+            },
             4 => ScalarString {
                 prefix: "",
                 full: "float",
@@ -2217,6 +2676,7 @@ fn glsl_built_in(built_in: BuiltIn, output: bool) -> &'static str {
                 "gl_FragCoord"
             }
         }
+        BuiltIn::ViewIndex => "uint(gl_ViewIndex)",
         // vertex
         BuiltIn::BaseInstance => "uint(gl_BaseInstance)",
         BuiltIn::BaseVertex => "uint(gl_BaseVertex)",
@@ -2228,6 +2688,7 @@ fn glsl_built_in(built_in: BuiltIn, output: bool) -> &'static str {
         // fragment
         BuiltIn::FragDepth => "gl_FragDepth",
         BuiltIn::FrontFacing => "gl_FrontFacing",
+        BuiltIn::PrimitiveIndex => "uint(gl_PrimitiveID)",
         BuiltIn::SampleIndex => "gl_SampleID",
         BuiltIn::SampleMask => {
             if output {
@@ -2242,6 +2703,11 @@ fn glsl_built_in(built_in: BuiltIn, output: bool) -> &'static str {
         BuiltIn::LocalInvocationIndex => "gl_LocalInvocationIndex",
         BuiltIn::WorkGroupId => "gl_WorkGroupID",
         BuiltIn::WorkGroupSize => "gl_WorkGroupSize",
+        // subgroup
+        BuiltIn::SubgroupSize => "gl_SubgroupSize",
+        BuiltIn::SubgroupInvocationId => "gl_SubgroupInvocationID",
+        // mesh
+        BuiltIn::MeshTaskSize | BuiltIn::CullPrimitive => unreachable!(),
     }
 }
 
@@ -2255,6 +2721,7 @@ fn glsl_storage_class(class: StorageClass) -> Option<&'static str> {
         StorageClass::Handle => Some("uniform"),
         StorageClass::WorkGroup => Some("shared"),
         StorageClass::PushConstant => None,
+        StorageClass::TaskPayload => unreachable!(),
     }
 }
 
@@ -33,6 +33,10 @@ pub struct Options {
     pub fragment_entry_point_name: String,
     /// The comput entry point name in generated shader
     pub compute_entry_point_name: String,
+    /// The task entry point name in generated shader
+    pub task_entry_point_name: String,
+    /// The mesh entry point name in generated shader
+    pub mesh_entry_point_name: String,
 }
 
 impl Default for Options {
@@ -42,6 +46,8 @@ impl Default for Options {
             vertex_entry_point_name: String::from("vert_main"),
             fragment_entry_point_name: String::from("frag_main"),
             compute_entry_point_name: String::from("comp_main"),
+            task_entry_point_name: String::from("task_main"),
+            mesh_entry_point_name: String::from("mesh_main"),
         }
     }
 }
@@ -25,6 +25,13 @@ struct EntryPointBinding {
     members: Vec<EpStructMember>,
 }
 
+/// Names of the output arrays of a mesh entry point.
+struct MeshOutputNames {
+    vertices: String,
+    indices: String,
+    primitives: String,
+}
+
 struct EpStructMember {
     pub name: String,
     pub ty: Handle<Type>,
@@ -38,6 +45,7 @@ pub struct Writer<'a, W> {
     options: &'a Options,
     ep_inputs: Vec<Option<EntryPointBinding>>,
     named_expressions: crate::NamedExpressions,
+    mesh_outputs: Option<MeshOutputNames>,
 }
 
 impl<'a, W: Write> Writer<'a, W> {
@@ -49,6 +57,7 @@ impl<'a, W: Write> Writer<'a, W> {
             options,
             ep_inputs: Vec::with_capacity(3),
             named_expressions: crate::NamedExpressions::default(),
+            mesh_outputs: None,
         }
     }
 
@@ -57,6 +66,7 @@ impl<'a, W: Write> Writer<'a, W> {
         self.namer.reset(module, RESERVED, &[], &mut self.names);
         self.named_expressions.clear();
         self.ep_inputs.clear();
+        self.mesh_outputs = None;
     }
 
     pub fn write(&mut self, module: &Module, info: &ModuleInfo) -> BackendResult {
@@ -66,6 +76,52 @@ impl<'a, W: Write> Writer<'a, W> {
 
         self.reset(module);
 
+        let uses_int64 = module.types.iter().any(|(_, ty)| match ty.inner {
+            TypeInner::Scalar { kind, width } | TypeInner::Vector { kind, width, .. } => {
+                kind != ScalarKind::Float && kind != ScalarKind::Bool && width == 8
+            }
+            _ => false,
+        });
+        if uses_int64 && self.options.shader_model < ShaderModel::new(60) {
+            return Err(Error::Custom(
+                "64-bit integers require shader model 6.0".to_string(),
+            ));
+        }
+        let uses_mesh_shading = module.entry_points.iter().any(|ep| match ep.stage {
+            ShaderStage::Task | ShaderStage::Mesh => true,
+            _ => false,
+        });
+        if uses_mesh_shading && self.options.shader_model < ShaderModel::new(65) {
+            return Err(Error::Custom(
+                "task and mesh shaders require shader model 6.5".to_string(),
+            ));
+        }
+        let uses_view_index = module.entry_points.iter().any(|ep| {
+            ep.function.arguments.iter().any(|arg| {
+                arg.binding == Some(crate::Binding::BuiltIn(BuiltIn::ViewIndex))
+                    || match module.types[arg.ty].inner {
+                        TypeInner::Struct { ref members, .. } => members.iter().any(|member| {
+                            member.binding == Some(crate::Binding::BuiltIn(BuiltIn::ViewIndex))
+                        }),
+                        _ => false,
+                    }
+            })
+        });
+        if uses_view_index && self.options.shader_model < ShaderModel::new(61) {
+            return Err(Error::Custom(
+                "view index requires shader model 6.1".to_string(),
+            ));
+        }
+        let uses_ray_query = module.types.iter().any(|(_, ty)| match ty.inner {
+            TypeInner::AccelerationStructure | TypeInner::RayQuery => true,
+            _ => false,
+        });
+        if uses_ray_query && self.options.shader_model < ShaderModel::new(65) {
+            return Err(Error::Custom(
+                "ray queries require shader model 6.5".to_string(),
+            ));
+        }
+
         // Write all constants
         // For example, input wgsl shader:
         // ```wgsl
@@ -139,7 +195,15 @@ impl<'a, W: Write> Writer<'a, W> {
                 named_expressions: &ep.function.named_expressions,
             };
 
-            if ep.stage == ShaderStage::Compute {
+            if let Some(ref mesh_info) = ep.mesh_info {
+                let topology_str = match mesh_info.topology {
+                    crate::MeshOutputTopology::Points => "point",
+                    crate::MeshOutputTopology::Lines => "line",
+                    crate::MeshOutputTopology::Triangles => "triangle",
+                };
+                writeln!(self.out, "[outputtopology(\"{}\")]", topology_str)?;
+            }
+            if let ShaderStage::Compute | ShaderStage::Task | ShaderStage::Mesh = ep.stage {
                 // HLSL is calling workgroup size, num threads
                 let num_threads = ep.workgroup_size;
                 writeln!(
@@ -153,6 +217,8 @@ impl<'a, W: Write> Writer<'a, W> {
                 ShaderStage::Vertex => &self.options.vertex_entry_point_name,
                 ShaderStage::Fragment => &self.options.fragment_entry_point_name,
                 ShaderStage::Compute => &self.options.compute_entry_point_name,
+                ShaderStage::Task => &self.options.task_entry_point_name,
+                ShaderStage::Mesh => &self.options.mesh_entry_point_name,
             };
 
             self.write_function(module, name, &ep.function, &ctx)?;
@@ -165,11 +231,29 @@ impl<'a, W: Write> Writer<'a, W> {
         Ok(())
     }
 
-    fn write_binding(&mut self, binding: &crate::Binding) -> BackendResult {
+    /// Helper method that checks the shader model supports wave intrinsics
+    fn check_wave_intrinsics(&self) -> BackendResult {
+        if self.options.shader_model < ShaderModel::new(60) {
+            return Err(Error::Custom(
+                "subgroup operations require shader model 6.0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn write_binding(&mut self, binding: &crate::Binding, fragment_output: bool) -> BackendResult {
         match *binding {
             crate::Binding::BuiltIn(builtin) => {
                 write!(self.out, " : {}", builtin_str(builtin))?;
             }
+            // with dual-source blending, the second source is the second render target
+            crate::Binding::Location {
+                second_blend_source: true,
+                ..
+            } => write!(self.out, " : SV_Target1")?,
+            crate::Binding::Location { location, .. } if fragment_output => {
+                write!(self.out, " : SV_Target{}", location)?;
+            }
             crate::Binding::Location { location, .. } => {
                 write!(self.out, " : {}{}", LOCATION_SEMANTIC, location)?;
             }
@@ -190,6 +274,8 @@ impl<'a, W: Write> Writer<'a, W> {
                 ShaderStage::Vertex => "VertexInput",
                 ShaderStage::Fragment => "FragmentInput",
                 ShaderStage::Compute => "ComputeInput",
+                ShaderStage::Task => "TaskInput",
+                ShaderStage::Mesh => "MeshInput",
             });
 
             let mut members = Vec::with_capacity(func.arguments.len());
@@ -209,14 +295,17 @@ impl<'a, W: Write> Writer<'a, W> {
                     binding: arg.binding.clone(),
                 };
 
-                write!(self.out, "{}", INDENT)?;
-                self.write_type(module, member.ty)?;
-                write!(self.out, " {}", &member.name)?;
-                if let Some(ref binding) = member.binding {
-                    self.write_binding(binding)?;
+                // subgroup built-ins have no semantics, and are queried with intrinsics instead
+                if subgroup_builtin_str(member.binding.as_ref()).is_none() {
+                    write!(self.out, "{}", INDENT)?;
+                    self.write_type(module, member.ty)?;
+                    write!(self.out, " {}", &member.name)?;
+                    if let Some(ref binding) = member.binding {
+                        self.write_binding(binding, false)?;
+                    }
+                    write!(self.out, ";")?;
+                    writeln!(self.out)?;
                 }
-                write!(self.out, ";")?;
-                writeln!(self.out)?;
 
                 members.push(member);
             }
@@ -231,6 +320,8 @@ impl<'a, W: Write> Writer<'a, W> {
             };
 
             self.ep_inputs.insert(index, Some(ep_input));
+        } else {
+            self.ep_inputs.insert(index, None);
         }
 
         Ok(())
@@ -241,11 +332,26 @@ impl<'a, W: Write> Writer<'a, W> {
     /// Always adds a newline
     fn write_global(&mut self, module: &Module, handle: Handle<GlobalVariable>) -> BackendResult {
         let global = &module.global_variables[handle];
-        let inner = &module.types[global.ty].inner;
+        // Binding arrays are declared as arrays of their element type
+        let (inner, binding_array_size) = match module.types[global.ty].inner {
+            TypeInner::BindingArray { base, size } => (&module.types[base].inner, Some(size)),
+            ref other => (other, None),
+        };
+
+        if global.class == crate::StorageClass::Storage {
+            return self.write_storage_buffer(module, handle);
+        }
 
         let (storage_class, register_ty) = match *inner {
+            // the payload is shared by the task workgroup, and passed on to the mesh stage
+            _ if global.class == crate::StorageClass::TaskPayload
+                || global.class == crate::StorageClass::WorkGroup =>
+            {
+                ("groupshared ", "")
+            }
             TypeInner::Image { .. } => ("", "t"),
             TypeInner::Sampler { .. } => ("", "s"),
+            TypeInner::AccelerationStructure => ("", "t"),
             TypeInner::Struct { .. } | TypeInner::Vector { .. } => ("static ", ""),
             // TODO: other register ty https://docs.microsoft.com/en-us/windows/win32/direct3dhlsl/dx-graphics-hlsl-variable-register
             _ => return Err(Error::Unimplemented(format!("register_ty {:?}", inner))),
@@ -261,6 +367,9 @@ impl<'a, W: Write> Writer<'a, W> {
             " {}",
             &self.names[&NameKey::GlobalVariable(handle)]
         )?;
+        if let Some(size) = binding_array_size {
+            self.write_array_size(module, size)?;
+        }
 
         if let Some(ref binding) = global.binding {
             writeln!(self.out, " : register({}{});", register_ty, binding.binding)?;
@@ -275,6 +384,65 @@ impl<'a, W: Write> Writer<'a, W> {
         Ok(())
     }
 
+    /// Helper method used to write storage buffers
+    ///
+    /// They are structured buffers of a single element, which is accessed by
+    /// [`write_expr`](Self::write_expr), unless they are runtime-sized arrays,
+    /// whose elements are the ones of the buffer.
+    ///
+    /// # Notes
+    /// Ends in a newline
+    fn write_storage_buffer(
+        &mut self,
+        module: &Module,
+        handle: Handle<GlobalVariable>,
+    ) -> BackendResult {
+        let global = &module.global_variables[handle];
+        let element_ty = match module.types[global.ty].inner {
+            TypeInner::Array {
+                base,
+                size: ArraySize::Dynamic,
+                ..
+            } => base,
+            TypeInner::Struct { ref members, .. }
+                if !members
+                    .iter()
+                    .any(|member| is_runtime_sized(module, member.ty)) =>
+            {
+                global.ty
+            }
+            TypeInner::Scalar { .. } | TypeInner::Vector { .. } | TypeInner::Matrix { .. } => {
+                global.ty
+            }
+            ref other => {
+                return Err(Error::Unimplemented(format!(
+                    "write_storage_buffer {:?}",
+                    other
+                )))
+            }
+        };
+        let (prefix, register_ty) = if global.storage_access.contains(crate::StorageAccess::STORE) {
+            ("RW", "u")
+        } else {
+            ("", "t")
+        };
+
+        write!(self.out, "{}StructuredBuffer<", prefix)?;
+        self.write_type(module, element_ty)?;
+        write!(
+            self.out,
+            "> {}",
+            &self.names[&NameKey::GlobalVariable(handle)]
+        )?;
+        match global.binding {
+            Some(ref binding) => {
+                writeln!(self.out, " : register({}{});", register_ty, binding.binding)?
+            }
+            None => writeln!(self.out, ";")?,
+        }
+        Ok(())
+    }
+
     /// Helper method used to write global constants
     ///
     /// # Notes
@@ -286,24 +454,22 @@ impl<'a, W: Write> Writer<'a, W> {
         handle: Handle<Constant>,
     ) -> BackendResult {
         match *inner {
-            ConstantInner::Scalar {
-                width: _,
-                ref value,
-            } => {
+            ConstantInner::Scalar { width, ref value } => {
                 write!(self.out, "static const ")?;
                 // Write type
-                match *value {
-                    crate::ScalarValue::Sint(_) => write!(self.out, "int")?,
-                    crate::ScalarValue::Uint(_) => write!(self.out, "uint")?,
-                    crate::ScalarValue::Float(_) => write!(self.out, "float")?,
-                    crate::ScalarValue::Bool(_) => write!(self.out, "bool")?,
-                };
+                write!(self.out, "{}", scalar_kind_str(value.scalar_kind(), width)?)?;
                 let name = &self.names[&NameKey::Constant(handle)];
                 write!(self.out, " {} = ", name)?;
 
                 // Second match required to avoid heap allocation by `format!()`
                 match *value {
+                    crate::ScalarValue::Sint(value) if width == 8 => {
+                        write!(self.out, "{}L", value)?
+                    }
                     crate::ScalarValue::Sint(value) => write!(self.out, "{}", value)?,
+                    crate::ScalarValue::Uint(value) if width == 8 => {
+                        write!(self.out, "{}UL", value)?
+                    }
                     crate::ScalarValue::Uint(value) => write!(self.out, "{}", value)?,
                     crate::ScalarValue::Float(value) => {
                         // Floats are written using `Debug` instead of `Display` because it always appends the
@@ -363,6 +529,11 @@ impl<'a, W: Write> Writer<'a, W> {
         write!(self.out, "struct {}", self.names[&NameKey::Type(handle)])?;
         writeln!(self.out, " {{")?;
 
+        let fragment_output = module.entry_points.iter().any(|ep| {
+            ep.stage == ShaderStage::Fragment
+                && ep.function.result.as_ref().map(|result| result.ty) == Some(handle)
+        });
+
         for (index, member) in members.iter().enumerate() {
             // The indentation is only for readability
             write!(self.out, "{}", INDENT)?;
@@ -377,7 +548,9 @@ impl<'a, W: Write> Writer<'a, W> {
                     let ty_name = match module.types[base].inner {
                         // Write scalar type by backend so as not to depend on the front-end implementation
                         // Name returned from frontend can be generated (type1, float1, etc.)
-                        TypeInner::Scalar { kind, width } => scalar_kind_str(kind, width)?,
+                        TypeInner::Scalar { kind, width } | TypeInner::Atomic { kind, width } => {
+                            scalar_kind_str(kind, width)?
+                        }
                         _ => &self.names[&NameKey::Type(base)],
                     };
 
@@ -403,7 +576,7 @@ impl<'a, W: Write> Writer<'a, W> {
             }
 
             if let Some(ref binding) = member.binding {
-                self.write_binding(binding)?;
+                self.write_binding(binding, fragment_output)?;
             };
             write!(self.out, ";")?;
             writeln!(self.out)?;
@@ -422,7 +595,9 @@ impl<'a, W: Write> Writer<'a, W> {
         match *inner {
             TypeInner::Struct { .. } => write!(self.out, "{}", self.names[&NameKey::Type(ty)])?,
             // hlsl array has the size separated from the base type
-            TypeInner::Array { base, .. } => self.write_type(module, base)?,
+            TypeInner::Array { base, .. } | TypeInner::BindingArray { base, .. } => {
+                self.write_type(module, base)?
+            }
             ref other => self.write_value_type(module, other)?,
         }
 
@@ -435,7 +610,7 @@ impl<'a, W: Write> Writer<'a, W> {
     /// Adds no trailing or leading whitespace
     fn write_value_type(&mut self, module: &Module, inner: &TypeInner) -> BackendResult {
         match *inner {
-            TypeInner::Scalar { kind, width } => {
+            TypeInner::Scalar { kind, width } | TypeInner::Atomic { kind, width } => {
                 write!(self.out, "{}", scalar_kind_str(kind, width)?)?;
             }
             TypeInner::Vector { size, kind, width } => {
@@ -463,27 +638,44 @@ impl<'a, W: Write> Writer<'a, W> {
             }
             TypeInner::Image {
                 dim,
-                arrayed: _, //TODO:
+                arrayed,
                 class,
             } => {
                 let dim_str = image_dimension_str(dim);
-                if let crate::ImageClass::Sampled { kind, multi: false } = class {
-                    write!(
+                let arrayed_str = if arrayed { "Array" } else { "" };
+                match class {
+                    crate::ImageClass::Sampled { kind, multi } => write!(
                         self.out,
-                        "Texture{}<{}4>",
+                        "Texture{}{}{}<{}4>",
                         dim_str,
+                        if multi { "MS" } else { "" },
+                        arrayed_str,
                         scalar_kind_str(kind, 4)?
-                    )?
-                } else {
-                    return Err(Error::Unimplemented(format!(
-                        "write_value_type {:?}",
-                        inner
-                    )));
+                    )?,
+                    crate::ImageClass::Depth => {
+                        write!(self.out, "Texture{}{}<float>", dim_str, arrayed_str)?
+                    }
+                    crate::ImageClass::Storage(_) => {
+                        return Err(Error::Unimplemented(format!(
+                            "write_value_type {:?}",
+                            inner
+                        )));
+                    }
                 }
             }
             TypeInner::Sampler { comparison: false } => {
                 write!(self.out, "SamplerState")?;
             }
+            TypeInner::Sampler { comparison: true } => {
+                write!(self.out, "SamplerComparisonState")?;
+            }
+            TypeInner::AccelerationStructure => {
+                write!(self.out, "RaytracingAccelerationStructure")?;
+            }
+            // the flags are given to `TraceRayInline` instead
+            TypeInner::RayQuery => {
+                write!(self.out, "RayQuery<RAY_FLAG_NONE>")?;
+            }
             // HLSL arrays are written as `type name[size]`
             // Current code is written arrays only as `[size]`
             // Base `type` and `name` should be written outside
@@ -511,11 +703,17 @@ impl<'a, W: Write> Writer<'a, W> {
         func: &Function,
         func_ctx: &FunctionCtx<'_>,
     ) -> BackendResult {
+        let ep = match func_ctx.ty {
+            FunctionType::EntryPoint(index) => Some(&module.entry_points[index as usize]),
+            FunctionType::Function(_) => None,
+        };
+        // Task stages dispatch the mesh grid instead of returning it
+        let is_task = ep.map(|ep| ep.stage) == Some(ShaderStage::Task);
+
         // Function Declaration Syntax - https://docs.microsoft.com/en-us/windows/win32/direct3dhlsl/dx-graphics-hlsl-function-syntax
-        if let Some(ref result) = func.result {
-            self.write_type(module, result.ty)?;
-        } else {
-            write!(self.out, "void")?;
+        match func.result {
+            Some(ref result) if !is_task => self.write_type(module, result.ty)?,
+            _ => write!(self.out, "void")?,
         }
 
         // Write function name
@@ -540,6 +738,7 @@ impl<'a, W: Write> Writer<'a, W> {
                 }
             }
             FunctionType::EntryPoint(index) => {
+                let mut separator = "";
                 // EntryPoint arguments wrapped into structure
                 if !self.ep_inputs.is_empty() {
                     if let Some(ref ep_input) = self.ep_inputs[index as usize] {
@@ -550,8 +749,24 @@ impl<'a, W: Write> Writer<'a, W> {
                             self.namer
                                 .call_unique(ep_input.name.to_lowercase().as_str())
                         )?;
+                        separator = ", ";
                     }
                 }
+                let ep = &module.entry_points[index as usize];
+                if let (ShaderStage::Mesh, Some(payload)) = (ep.stage, ep.task_payload) {
+                    write!(self.out, "{}in payload ", separator)?;
+                    self.write_type(module, module.global_variables[payload].ty)?;
+                    // shadows the global declaration
+                    write!(
+                        self.out,
+                        " {}",
+                        self.names[&NameKey::GlobalVariable(payload)]
+                    )?;
+                    separator = ", ";
+                }
+                if let Some(ref mesh_info) = ep.mesh_info {
+                    self.write_mesh_outputs(module, mesh_info, separator)?;
+                }
             }
         }
         // Ends of arguments
@@ -562,7 +777,7 @@ impl<'a, W: Write> Writer<'a, W> {
             FunctionType::EntryPoint(index) => Some(module.entry_points[index as usize].stage),
             _ => None,
         };
-        if let Some(ref result) = func.result {
+        if let Some(result) = func.result.as_ref().filter(|_| !is_task) {
             if let Some(ref binding) = result.binding {
                 match *binding {
                     crate::Binding::BuiltIn(builtin) => {
@@ -585,13 +800,16 @@ impl<'a, W: Write> Writer<'a, W> {
             // Write indentation (only for readability)
             write!(self.out, "{}", INDENT)?;
 
-            // Write the local name
-            // The leading space is important
-            write!(self.out, "var {}: ", self.names[&func_ctx.name_key(handle)])?;
-
             // Write the local type
             self.write_type(module, local.ty)?;
 
+            // Write the local name
+            // The leading space is important
+            write!(self.out, " {}", self.names[&func_ctx.name_key(handle)])?;
+            if let TypeInner::Array { size, .. } = module.types[local.ty].inner {
+                self.write_array_size(module, size)?;
+            }
+
             // Write the local initializer if needed
             if let Some(init) = local.init {
                 // Put the equal signal only if there's a initializer
@@ -624,6 +842,276 @@ impl<'a, W: Write> Writer<'a, W> {
         Ok(())
     }
 
+    /// Helper method used to write the output arrays of a mesh entry point
+    ///
+    /// # Notes
+    /// Adds no trailing or leading whitespace
+    fn write_mesh_outputs(
+        &mut self,
+        module: &Module,
+        mesh_info: &crate::MeshStageInfo,
+        separator: &str,
+    ) -> BackendResult {
+        let names = MeshOutputNames {
+            vertices: self.namer.call_unique("vertices"),
+            indices: self.namer.call_unique("indices"),
+            primitives: self.namer.call_unique("primitives"),
+        };
+
+        write!(self.out, "{}out vertices ", separator)?;
+        self.write_type(module, mesh_info.vertex_output_type)?;
+        write!(self.out, " {}[{}]", names.vertices, mesh_info.max_vertices)?;
+
+        let indices_ty = match mesh_info.topology {
+            crate::MeshOutputTopology::Points => "uint",
+            crate::MeshOutputTopology::Lines => "uint2",
+            crate::MeshOutputTopology::Triangles => "uint3",
+        };
+        write!(
+            self.out,
+            ", out indices {} {}[{}]",
+            indices_ty, names.indices, mesh_info.max_primitives
+        )?;
+
+        if let Some(ty) = mesh_info.primitive_output_type {
+            write!(self.out, ", out primitives ")?;
+            self.write_type(module, ty)?;
+            write!(
+                self.out,
+                " {}[{}]",
+                names.primitives, mesh_info.max_primitives
+            )?;
+        }
+
+        self.mesh_outputs = Some(names);
+        Ok(())
+    }
+
+    /// Helper method used to write an element of a mesh output array
+    ///
+    /// # Notes
+    /// Always adds a newline
+    fn write_mesh_output_store(
+        &mut self,
+        module: &Module,
+        array: &str,
+        index: Handle<Expression>,
+        value: Handle<Expression>,
+        func_ctx: &FunctionCtx<'_>,
+        indent: usize,
+    ) -> BackendResult {
+        // struct values are written as initializer lists, which can't be assigned directly
+        let struct_ty =
+            func_ctx.info[value]
+                .ty
+                .handle()
+                .filter(|&ty| match module.types[ty].inner {
+                    TypeInner::Struct { .. } => true,
+                    _ => false,
+                });
+        let value_name = match struct_ty {
+            Some(ty) if !self.named_expressions.contains_key(&value) => {
+                let struct_name = self.names[&NameKey::Type(ty)].clone();
+                let variable_name = self.namer.call_unique(struct_name.as_str()).to_lowercase();
+                write!(
+                    self.out,
+                    "{}const {} {} = ",
+                    INDENT.repeat(indent),
+                    struct_name,
+                    variable_name
+                )?;
+                self.write_expr(module, value, func_ctx)?;
+                writeln!(self.out, ";")?;
+                Some(variable_name)
+            }
+            _ => None,
+        };
+
+        write!(self.out, "{}{}[", INDENT.repeat(indent), array)?;
+        self.write_expr(module, index, func_ctx)?;
+        write!(self.out, "] = ")?;
+        match value_name {
+            Some(name) => write!(self.out, "{}", name)?,
+            None => self.write_expr(module, value, func_ctx)?,
+        }
+        writeln!(self.out, ";")?;
+        Ok(())
+    }
+
+    /// Helper method used to write the return of a task entry point,
+    /// which launches the mesh workgroups instead
+    ///
+    /// # Notes
+    /// Always adds a newline
+    fn write_mesh_task_dispatch(
+        &mut self,
+        module: &Module,
+        size: Handle<Expression>,
+        payload: Handle<GlobalVariable>,
+        func_ctx: &FunctionCtx<'_>,
+        indent: usize,
+    ) -> BackendResult {
+        let size_name = self.namer.call_unique("mesh_task_size");
+        write!(
+            self.out,
+            "{}const uint3 {} = ",
+            INDENT.repeat(indent),
+            size_name
+        )?;
+        self.write_expr(module, size, func_ctx)?;
+        writeln!(self.out, ";")?;
+        writeln!(
+            self.out,
+            "{}DispatchMesh({1}.x, {1}.y, {1}.z, {2});",
+            INDENT.repeat(indent),
+            size_name,
+            self.names[&NameKey::GlobalVariable(payload)]
+        )?;
+        writeln!(self.out, "{}return;", INDENT.repeat(indent))?;
+        Ok(())
+    }
+
+    /// Helper method used to write a ray query function
+    ///
+    /// # Notes
+    /// Always adds a newline
+    fn write_ray_query(
+        &mut self,
+        module: &Module,
+        query: Handle<Expression>,
+        fun: &crate::RayQueryFunction,
+        func_ctx: &FunctionCtx<'_>,
+        indent: usize,
+    ) -> BackendResult {
+        match *fun {
+            crate::RayQueryFunction::Initialize {
+                acceleration_structure,
+                descriptor,
+            } => {
+                // the descriptor is copied into the builtin `RayDesc`,
+                // with the flags and the mask passed separately
+                let desc_ty = match func_ctx.info[descriptor].ty.handle() {
+                    Some(ty) => ty,
+                    None => {
+                        return Err(Error::Custom("ray descriptor is not a struct".to_string()))
+                    }
+                };
+                let level = INDENT.repeat(indent + 1);
+                writeln!(self.out, "{}{{", INDENT.repeat(indent))?;
+                write!(
+                    self.out,
+                    "{}const {} _desc = ",
+                    level,
+                    self.names[&NameKey::Type(desc_ty)]
+                )?;
+                self.write_expr(module, descriptor, func_ctx)?;
+                writeln!(self.out, ";")?;
+                writeln!(self.out, "{}RayDesc _ray;", level)?;
+                for &(field, member) in
+                    [("Origin", 4), ("TMin", 2), ("Direction", 5), ("TMax", 3)].iter()
+                {
+                    writeln!(
+                        self.out,
+                        "{}_ray.{} = _desc.{};",
+                        level,
+                        field,
+                        self.names[&NameKey::StructMember(desc_ty, member)]
+                    )?;
+                }
+                write!(self.out, "{}", level)?;
+                self.write_expr(module, query, func_ctx)?;
+                write!(self.out, ".TraceRayInline(")?;
+                self.write_expr(module, acceleration_structure, func_ctx)?;
+                writeln!(
+                    self.out,
+                    ", _desc.{}, _desc.{}, _ray);",
+                    self.names[&NameKey::StructMember(desc_ty, 0)],
+                    self.names[&NameKey::StructMember(desc_ty, 1)]
+                )?;
+                writeln!(self.out, "{}}}", INDENT.repeat(indent))?;
+            }
+            crate::RayQueryFunction::Proceed { result } => {
+                let res_name = format!("{}{}", BAKE_PREFIX, result.index());
+                write!(self.out, "{}bool {} = ", INDENT.repeat(indent), res_name)?;
+                self.write_expr(module, query, func_ctx)?;
+                writeln!(self.out, ".Proceed();")?;
+                self.named_expressions.insert(result, res_name);
+            }
+            crate::RayQueryFunction::Terminate => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                self.write_expr(module, query, func_ctx)?;
+                writeln!(self.out, ".Abort();")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Helper method used to write a ray intersection,
+    /// as an initializer list of the `RayIntersection` structure
+    ///
+    /// # Notes
+    /// Adds no trailing or leading whitespace
+    fn write_ray_intersection(
+        &mut self,
+        module: &Module,
+        query: Handle<Expression>,
+        committed: bool,
+        func_ctx: &FunctionCtx<'_>,
+    ) -> BackendResult {
+        let (prefix, methods) = if committed {
+            (
+                "Committed",
+                [
+                    "Status",
+                    "RayT",
+                    "InstanceID",
+                    "InstanceIndex",
+                    "InstanceContributionToHitGroupIndex",
+                    "GeometryIndex",
+                    "PrimitiveIndex",
+                    "TriangleBarycentrics",
+                    "TriangleFrontFace",
+                    "ObjectToWorld4x3",
+                    "WorldToObject4x3",
+                ],
+            )
+        } else {
+            (
+                "Candidate",
+                [
+                    "Type",
+                    "TriangleRayT",
+                    "InstanceID",
+                    "InstanceIndex",
+                    "InstanceContributionToHitGroupIndex",
+                    "GeometryIndex",
+                    "PrimitiveIndex",
+                    "TriangleBarycentrics",
+                    "TriangleFrontFace",
+                    "ObjectToWorld4x3",
+                    "WorldToObject4x3",
+                ],
+            )
+        };
+        write!(self.out, "{{ ")?;
+        for (index, method) in methods.iter().enumerate() {
+            if index != 0 {
+                write!(self.out, ", ")?;
+            }
+            // candidates are triangles (0) or procedural (1), map them to the IR kinds
+            if index == 0 && !committed {
+                write!(self.out, "2u * ")?;
+            }
+            self.write_expr(module, query, func_ctx)?;
+            write!(self.out, ".{}{}()", prefix, method)?;
+            if index == 0 && !committed {
+                write!(self.out, " + 1u")?;
+            }
+        }
+        write!(self.out, " }}")?;
+        Ok(())
+    }
+
     /// Helper method used to write statements
     ///
     /// # Notes
@@ -699,11 +1187,38 @@ impl<'a, W: Write> Writer<'a, W> {
                 writeln!(self.out, "{}}}", INDENT.repeat(indent))?
             }
             // TODO: copy-paste from glsl-out
+            Statement::Loop {
+                ref body,
+                ref continuing,
+            } => {
+                writeln!(self.out, "{}while(true) {{", INDENT.repeat(indent))?;
+                for sta in body.iter().chain(continuing.iter()) {
+                    self.write_stmt(module, sta, func_ctx, indent + 1)?;
+                }
+                writeln!(self.out, "{}}}", INDENT.repeat(indent))?
+            }
+            Statement::Break => writeln!(self.out, "{}break;", INDENT.repeat(indent))?,
+            Statement::Continue => writeln!(self.out, "{}continue;", INDENT.repeat(indent))?,
+            // TODO: copy-paste from glsl-out
             Statement::Kill => writeln!(self.out, "{}discard;", INDENT.repeat(indent))?,
+            Statement::Barrier(barrier) => {
+                let fun_str = if barrier == crate::Barrier::STORAGE {
+                    "DeviceMemoryBarrierWithGroupSync"
+                } else if barrier == crate::Barrier::WORK_GROUP {
+                    "GroupMemoryBarrierWithGroupSync"
+                } else {
+                    "AllMemoryBarrierWithGroupSync"
+                };
+                writeln!(self.out, "{}{}();", INDENT.repeat(indent), fun_str)?;
+            }
             Statement::Return { value: None } => {
                 writeln!(self.out, "{}return;", INDENT.repeat(indent))?;
             }
             Statement::Return { value: Some(expr) } => {
+                if let Some(payload) = task_payload(module, func_ctx) {
+                    return self.write_mesh_task_dispatch(module, expr, payload, func_ctx, indent);
+                }
+
                 let base_ty_res = &func_ctx.info[expr].ty;
                 let mut resolved = base_ty_res.inner_with(&module.types);
                 if let TypeInner::Pointer { base, class: _ } = *resolved {
@@ -723,7 +1238,7 @@ impl<'a, W: Write> Writer<'a, W> {
                         variable_name
                     )?;
                     self.write_expr(module, expr, func_ctx)?;
-                    writeln!(self.out)?;
+                    writeln!(self.out, ";")?;
                     writeln!(
                         self.out,
                         "{}return {};",
@@ -768,6 +1283,219 @@ impl<'a, W: Write> Writer<'a, W> {
                 }
                 writeln!(self.out, ");")?
             }
+            Statement::Atomic {
+                pointer,
+                ref fun,
+                value,
+                result,
+            } => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                let res_name = format!("{}{}", BAKE_PREFIX, result.index());
+                let res_ty = func_ctx.info[result].ty.inner_with(&module.types);
+                let fun_str = fun.to_hlsl_suffix();
+                match *fun {
+                    // `InterlockedCompareExchange` only gives the original value,
+                    // which tells if it was exchanged when compared to the operand
+                    crate::AtomicFunction::Exchange { compare: Some(cmp) } => {
+                        let value_ty = func_ctx.info[value].ty.inner_with(&module.types);
+                        let cmp_name = match self.named_expressions.get(&cmp) {
+                            Some(name) => name.clone(),
+                            None => {
+                                let name = format!("{}_compare", res_name);
+                                self.write_value_type(module, value_ty)?;
+                                write!(self.out, " {} = ", name)?;
+                                self.write_expr(module, cmp, func_ctx)?;
+                                writeln!(self.out, ";")?;
+                                write!(self.out, "{}", INDENT.repeat(indent))?;
+                                name
+                            }
+                        };
+                        self.write_value_type(module, value_ty)?;
+                        writeln!(self.out, " {}_old;", res_name)?;
+                        write!(self.out, "{}Interlocked{}(", INDENT.repeat(indent), fun_str)?;
+                        self.write_expr(module, pointer, func_ctx)?;
+                        write!(self.out, ", {}, ", cmp_name)?;
+                        self.write_expr(module, value, func_ctx)?;
+                        writeln!(self.out, ", {}_old);", res_name)?;
+                        write!(self.out, "{}", INDENT.repeat(indent))?;
+                        self.write_value_type(module, res_ty)?;
+                        write!(self.out, " {} = ", res_name)?;
+                        self.write_value_type(module, res_ty)?;
+                        write!(self.out, "({}_old, ", res_name)?;
+                        self.write_value_type(module, value_ty)?;
+                        writeln!(self.out, "({}_old == {}));", res_name, cmp_name)?;
+                    }
+                    _ => {
+                        self.write_value_type(module, res_ty)?;
+                        writeln!(self.out, " {};", res_name)?;
+                        write!(self.out, "{}Interlocked{}(", INDENT.repeat(indent), fun_str)?;
+                        self.write_expr(module, pointer, func_ctx)?;
+                        write!(self.out, ", ")?;
+                        match *fun {
+                            // There is no `InterlockedSub`, so we add the negated value instead
+                            crate::AtomicFunction::Subtract => {
+                                write!(self.out, "-(")?;
+                                self.write_expr(module, value, func_ctx)?;
+                                write!(self.out, ")")?;
+                            }
+                            _ => self.write_expr(module, value, func_ctx)?,
+                        }
+                        writeln!(self.out, ", {});", res_name)?;
+                    }
+                }
+                self.named_expressions.insert(result, res_name);
+            }
+            Statement::SubgroupBallot { result, predicate } => {
+                self.check_wave_intrinsics()?;
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                let res_name = format!("{}{}", BAKE_PREFIX, result.index());
+                let res_ty = func_ctx.info[result].ty.inner_with(&module.types);
+                self.write_value_type(module, res_ty)?;
+                write!(self.out, " {} = WaveActiveBallot(", res_name)?;
+                match predicate {
+                    Some(predicate) => self.write_expr(module, predicate, func_ctx)?,
+                    None => write!(self.out, "true")?,
+                }
+                writeln!(self.out, ");")?;
+                self.named_expressions.insert(result, res_name);
+            }
+            Statement::SubgroupCollectiveOperation {
+                op,
+                collective_op,
+                argument,
+                result,
+            } => {
+                use crate::{CollectiveOperation as Co, SubgroupOperation as So};
+
+                self.check_wave_intrinsics()?;
+                // inclusive scans are exclusive ones combined with the argument
+                let (fun_str, inclusive_op) = match (collective_op, op) {
+                    (Co::Reduce, So::All) => ("WaveActiveAllTrue", None),
+                    (Co::Reduce, So::Any) => ("WaveActiveAnyTrue", None),
+                    (Co::Reduce, So::Add) => ("WaveActiveSum", None),
+                    (Co::Reduce, So::Mul) => ("WaveActiveProduct", None),
+                    (Co::Reduce, So::Min) => ("WaveActiveMin", None),
+                    (Co::Reduce, So::Max) => ("WaveActiveMax", None),
+                    (Co::Reduce, So::And) => ("WaveActiveBitAnd", None),
+                    (Co::Reduce, So::Or) => ("WaveActiveBitOr", None),
+                    (Co::Reduce, So::Xor) => ("WaveActiveBitXor", None),
+                    (Co::ExclusiveScan, So::Add) => ("WavePrefixSum", None),
+                    (Co::ExclusiveScan, So::Mul) => ("WavePrefixProduct", None),
+                    (Co::InclusiveScan, So::Add) => ("WavePrefixSum", Some("+")),
+                    (Co::InclusiveScan, So::Mul) => ("WavePrefixProduct", Some("*")),
+                    _ => {
+                        return Err(Error::Unimplemented(format!(
+                            "subgroup {:?} {:?}",
+                            collective_op, op
+                        )))
+                    }
+                };
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                let res_name = format!("{}{}", BAKE_PREFIX, result.index());
+                let res_ty = func_ctx.info[result].ty.inner_with(&module.types);
+                self.write_value_type(module, res_ty)?;
+                write!(self.out, " {} = {}(", res_name, fun_str)?;
+                self.write_expr(module, argument, func_ctx)?;
+                write!(self.out, ")")?;
+                if let Some(inclusive_op) = inclusive_op {
+                    write!(self.out, " {} ", inclusive_op)?;
+                    self.write_expr(module, argument, func_ctx)?;
+                }
+                writeln!(self.out, ";")?;
+                self.named_expressions.insert(result, res_name);
+            }
+            Statement::SubgroupGather {
+                ref mode,
+                argument,
+                result,
+            } => {
+                self.check_wave_intrinsics()?;
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                let res_name = format!("{}{}", BAKE_PREFIX, result.index());
+                let res_ty = func_ctx.info[result].ty.inner_with(&module.types);
+                self.write_value_type(module, res_ty)?;
+                write!(self.out, " {} = ", res_name)?;
+                match *mode {
+                    crate::GatherMode::BroadcastFirst => {
+                        write!(self.out, "WaveReadLaneFirst(")?;
+                        self.write_expr(module, argument, func_ctx)?;
+                    }
+                    crate::GatherMode::Broadcast(index) | crate::GatherMode::Shuffle(index) => {
+                        write!(self.out, "WaveReadLaneAt(")?;
+                        self.write_expr(module, argument, func_ctx)?;
+                        write!(self.out, ", ")?;
+                        self.write_expr(module, index, func_ctx)?;
+                    }
+                    // relative shuffles read from a lane computed from the current one
+                    crate::GatherMode::ShuffleDown(index)
+                    | crate::GatherMode::ShuffleUp(index)
+                    | crate::GatherMode::ShuffleXor(index) => {
+                        let op = match *mode {
+                            crate::GatherMode::ShuffleDown(_) => "+",
+                            crate::GatherMode::ShuffleUp(_) => "-",
+                            _ => "^",
+                        };
+                        write!(self.out, "WaveReadLaneAt(")?;
+                        self.write_expr(module, argument, func_ctx)?;
+                        write!(self.out, ", WaveGetLaneIndex() {} ", op)?;
+                        self.write_expr(module, index, func_ctx)?;
+                    }
+                }
+                writeln!(self.out, ");")?;
+                self.named_expressions.insert(result, res_name);
+            }
+            Statement::MeshFunction(ref fun) => {
+                let names = match self.mesh_outputs.take() {
+                    Some(names) => names,
+                    None => return Err(Error::Custom("mesh outputs are missing".to_string())),
+                };
+                match *fun {
+                    crate::MeshFunction::SetMeshOutputs {
+                        vertex_count,
+                        primitive_count,
+                    } => {
+                        write!(self.out, "{}SetMeshOutputCounts(", INDENT.repeat(indent))?;
+                        self.write_expr(module, vertex_count, func_ctx)?;
+                        write!(self.out, ", ")?;
+                        self.write_expr(module, primitive_count, func_ctx)?;
+                        writeln!(self.out, ");")?;
+                    }
+                    crate::MeshFunction::SetVertex { index, value } => {
+                        self.write_mesh_output_store(
+                            module,
+                            &names.vertices,
+                            index,
+                            value,
+                            func_ctx,
+                            indent,
+                        )?;
+                    }
+                    crate::MeshFunction::SetIndices { index, value } => {
+                        self.write_mesh_output_store(
+                            module,
+                            &names.indices,
+                            index,
+                            value,
+                            func_ctx,
+                            indent,
+                        )?;
+                    }
+                    crate::MeshFunction::SetPrimitive { index, value } => {
+                        self.write_mesh_output_store(
+                            module,
+                            &names.primitives,
+                            index,
+                            value,
+                            func_ctx,
+                            indent,
+                        )?;
+                    }
+                }
+                self.mesh_outputs = Some(names);
+            }
+            Statement::RayQuery { query, ref fun } => {
+                self.write_ray_query(module, query, fun, func_ctx, indent)?;
+            }
             _ => return Err(Error::Unimplemented(format!("write_stmt {:?}", stmt))),
         }
 
@@ -814,11 +1542,26 @@ impl<'a, W: Write> Writer<'a, W> {
                     }
                 }
                 if is_struct {
-                    write!(self.out, " }};")?
+                    write!(self.out, " }}")?
                 } else {
                     write!(self.out, ")")?
                 }
             }
+            // casting a scalar to a vector type replicates it
+            Expression::Splat { size, value } => {
+                let (kind, width) = match *func_ctx.info[value].ty.inner_with(&module.types) {
+                    TypeInner::Scalar { kind, width } => (kind, width),
+                    _ => return Err(Error::Unimplemented(format!("write_expr {:?}", expression))),
+                };
+                write!(
+                    self.out,
+                    "(({}{})",
+                    scalar_kind_str(kind, width)?,
+                    vector_size_str(size)
+                )?;
+                self.write_expr(module, value, func_ctx)?;
+                write!(self.out, ")")?;
+            }
             // TODO: copy-paste from wgsl-out
             Expression::Binary { op, left, right } => {
                 write!(self.out, "(")?;
@@ -848,6 +1591,7 @@ impl<'a, W: Write> Writer<'a, W> {
                     }
                     TypeInner::Matrix { .. }
                     | TypeInner::Array { .. }
+                    | TypeInner::BindingArray { .. }
                     | TypeInner::ValuePointer { .. } => write!(self.out, "[{}]", index)?,
                     TypeInner::Struct { .. } => {
                         // This will never panic in case the type is a `Struct`, this is not true
@@ -873,19 +1617,71 @@ impl<'a, W: Write> Writer<'a, W> {
                         // EntryPoint arguments wrapped into structure
                         // We can safery unwrap here, because if we write function arguments it means, that ep_input struct already exists
                         let ep_input = self.ep_inputs[index as usize].as_ref().unwrap();
-                        let member_name = &ep_input.members[pos as usize].name;
-                        write!(
-                            self.out,
-                            "{}.{}",
-                            &ep_input.name.to_lowercase(),
-                            member_name
-                        )?
+                        let member = &ep_input.members[pos as usize];
+                        match subgroup_builtin_str(member.binding.as_ref()) {
+                            Some(intrinsic) => write!(self.out, "{}", intrinsic)?,
+                            None => write!(
+                                self.out,
+                                "{}.{}",
+                                &ep_input.name.to_lowercase(),
+                                member.name
+                            )?,
+                        }
                     }
                 };
             }
             Expression::ImageSample {
                 image,
-                sampler,        // TODO:
+                sampler,
+                gather: Some(component),
+                coordinate,
+                array_index,
+                offset,
+                level: _,
+                depth_ref,
+            } => {
+                // https://docs.microsoft.com/en-us/windows/win32/direct3dhlsl/texture2d-gatherred
+                self.write_expr(module, image, func_ctx)?;
+                let channel = match component {
+                    crate::SwizzleComponent::X => "Red",
+                    crate::SwizzleComponent::Y => "Green",
+                    crate::SwizzleComponent::Z => "Blue",
+                    crate::SwizzleComponent::W => "Alpha",
+                };
+                match depth_ref {
+                    Some(_) => write!(self.out, ".GatherCmp(")?,
+                    None => write!(self.out, ".Gather{}(", channel)?,
+                }
+                self.write_expr(module, sampler, func_ctx)?;
+                write!(self.out, ", ")?;
+                match array_index {
+                    Some(index) => {
+                        let size = match *func_ctx.info[coordinate].ty.inner_with(&module.types) {
+                            TypeInner::Vector { size, .. } => size as u8 + 1,
+                            _ => 2,
+                        };
+                        write!(self.out, "float{}(", size)?;
+                        self.write_expr(module, coordinate, func_ctx)?;
+                        write!(self.out, ", ")?;
+                        self.write_expr(module, index, func_ctx)?;
+                        write!(self.out, ")")?;
+                    }
+                    None => self.write_expr(module, coordinate, func_ctx)?,
+                }
+                if let Some(expr) = depth_ref {
+                    write!(self.out, ", ")?;
+                    self.write_expr(module, expr, func_ctx)?;
+                }
+                if let Some(constant) = offset {
+                    write!(self.out, ", ")?;
+                    self.write_constant(module, constant)?;
+                }
+                write!(self.out, ")")?;
+            }
+            Expression::ImageSample {
+                image,
+                sampler, // TODO:
+                gather: None,
                 coordinate,     // TODO:
                 array_index: _, // TODO:
                 offset: _,      // TODO:
@@ -904,12 +1700,37 @@ impl<'a, W: Write> Writer<'a, W> {
             Expression::GlobalVariable(handle) => {
                 let name = &self.names[&NameKey::GlobalVariable(handle)];
                 write!(self.out, "{}", name)?;
+                // see `write_storage_buffer`
+                let global = &module.global_variables[handle];
+                if global.class == crate::StorageClass::Storage
+                    && !is_runtime_sized(module, global.ty)
+                {
+                    write!(self.out, "[0]")?;
+                }
+            }
+            Expression::LocalVariable(handle) => {
+                write!(self.out, "{}", self.names[&func_ctx.name_key(handle)])?
             }
+            Expression::RayQueryGetIntersection {
+                query, committed, ..
+            } => self.write_ray_intersection(module, query, committed, func_ctx)?,
             Expression::Load { pointer } => self.write_expr(module, pointer, func_ctx)?,
             Expression::Access { base, index } => {
                 self.write_expr(module, base, func_ctx)?;
                 write!(self.out, "[")?;
+                // Resource indices that vary across invocations must be marked
+                let non_uniform = func_ctx.info[base]
+                    .ty
+                    .inner_with(&module.types)
+                    .is_binding_array(&module.types)
+                    && func_ctx.info[index].uniformity.non_uniform_result.is_some();
+                if non_uniform {
+                    write!(self.out, "NonUniformResourceIndex(")?;
+                }
                 self.write_expr(module, index, func_ctx)?;
+                if non_uniform {
+                    write!(self.out, ")")?;
+                }
                 write!(self.out, "]")?
             }
             Expression::Unary { op, expr } => {
@@ -938,12 +1759,258 @@ impl<'a, W: Write> Writer<'a, W> {
 
                 write!(self.out, ")")?
             }
+            Expression::As {
+                expr: value,
+                kind,
+                convert,
+            } => {
+                let (size, width) = match *func_ctx.info[value].ty.inner_with(&module.types) {
+                    TypeInner::Scalar { width, .. } => ("", width),
+                    TypeInner::Vector { size, width, .. } => (vector_size_str(size), width),
+                    _ => return Err(Error::Unimplemented(format!("write_expr {:?}", expression))),
+                };
+                match convert {
+                    Some(width) => write!(self.out, "{}{}(", scalar_kind_str(kind, width)?, size)?,
+                    None => {
+                        let fun = match kind {
+                            ScalarKind::Sint => "asint",
+                            ScalarKind::Uint => "asuint",
+                            ScalarKind::Float if width == 8 => "asdouble",
+                            ScalarKind::Float => "asfloat",
+                            ScalarKind::Bool => {
+                                return Err(Error::Unimplemented(format!(
+                                    "write_expr {:?}",
+                                    expression
+                                )))
+                            }
+                        };
+                        write!(self.out, "{}(", fun)?;
+                    }
+                }
+                self.write_expr(module, value, func_ctx)?;
+                write!(self.out, ")")?;
+            }
+            Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+                arg3,
+            } => {
+                use crate::MathFunction as Mf;
+
+                // The bit intrinsics only take and return unsigned integers,
+                // so signed values are reinterpreted on the way in and out
+                let (signed, zero) = match *func_ctx.info[arg].ty.inner_with(&module.types) {
+                    TypeInner::Scalar { kind, .. } => (
+                        kind == ScalarKind::Sint,
+                        format!("({})0", scalar_kind_str(kind, 4)?),
+                    ),
+                    TypeInner::Vector { kind, size, .. } => (
+                        kind == ScalarKind::Sint,
+                        format!("({}{})0", scalar_kind_str(kind, 4)?, vector_size_str(size)),
+                    ),
+                    _ => (false, String::new()),
+                };
+                let (to_uint, from_uint) = if signed {
+                    ("asuint", "asint")
+                } else {
+                    ("", "")
+                };
+                let missing = || Error::Custom(format!("Missing argument of {:?}", fun));
+
+                // HLSL doesn't have the packing functions, so they are
+                // emulated with conversions and shifts
+                match fun {
+                    Mf::CountTrailingZeros => {
+                        // `firstbitlow` returns all ones for zero
+                        write!(self.out, "{}(min(32u, firstbitlow(", from_uint)?;
+                        self.write_expr(module, arg, func_ctx)?;
+                        write!(self.out, ")))")?;
+                    }
+                    Mf::CountLeadingZeros => {
+                        // `firstbithigh` returns all ones for zero, and
+                        // the subtraction wraps around to 32
+                        write!(self.out, "{}(31u - firstbithigh({}(", from_uint, to_uint)?;
+                        self.write_expr(module, arg, func_ctx)?;
+                        write!(self.out, ")))")?;
+                    }
+                    Mf::CountOneBits | Mf::ReverseBits => {
+                        let fun_name = match fun {
+                            Mf::CountOneBits => "countbits",
+                            _ => "reversebits",
+                        };
+                        write!(self.out, "{}({}({}(", from_uint, fun_name, to_uint)?;
+                        self.write_expr(module, arg, func_ctx)?;
+                        write!(self.out, ")))")?;
+                    }
+                    Mf::FirstTrailingBit | Mf::FirstLeadingBit => {
+                        // `firstbithigh` of a signed value looks for the first
+                        // bit that differs from the sign
+                        let fun_name = match fun {
+                            Mf::FirstTrailingBit => "firstbitlow",
+                            _ => "firstbithigh",
+                        };
+                        write!(self.out, "{}({}(", from_uint, fun_name)?;
+                        self.write_expr(module, arg, func_ctx)?;
+                        write!(self.out, "))")?;
+                    }
+                    Mf::ExtractBits => {
+                        // shift the field to the top, and back down so that
+                        // signed values are sign extended
+                        let offset = arg1.ok_or_else(missing)?;
+                        let count = arg2.ok_or_else(missing)?;
+                        write!(self.out, "(")?;
+                        self.write_expr(module, count, func_ctx)?;
+                        write!(self.out, " == 0u ? {} : (", zero)?;
+                        self.write_expr(module, arg, func_ctx)?;
+                        write!(self.out, " << (32u - ")?;
+                        self.write_expr(module, count, func_ctx)?;
+                        write!(self.out, " - ")?;
+                        self.write_expr(module, offset, func_ctx)?;
+                        write!(self.out, ")) >> (32u - ")?;
+                        self.write_expr(module, count, func_ctx)?;
+                        write!(self.out, "))")?;
+                    }
+                    Mf::InsertBits => {
+                        let newbits = arg1.ok_or_else(missing)?;
+                        let offset = arg2.ok_or_else(missing)?;
+                        let count = arg3.ok_or_else(missing)?;
+                        write!(self.out, "{}(({}(", from_uint, to_uint)?;
+                        self.write_expr(module, arg, func_ctx)?;
+                        write!(self.out, ") & ~")?;
+                        self.write_bit_mask(module, offset, count, func_ctx)?;
+                        write!(self.out, ") | (({}(", to_uint)?;
+                        self.write_expr(module, newbits, func_ctx)?;
+                        write!(self.out, ") << ")?;
+                        self.write_expr(module, offset, func_ctx)?;
+                        write!(self.out, ") & ")?;
+                        self.write_bit_mask(module, offset, count, func_ctx)?;
+                        write!(self.out, "))")?;
+                    }
+                    Mf::Pack4x8snorm | Mf::Pack4x8unorm | Mf::Pack2x16snorm | Mf::Pack2x16unorm => {
+                        let (count, bits) = match fun {
+                            Mf::Pack4x8snorm | Mf::Pack4x8unorm => (4, 8),
+                            _ => (2, 16),
+                        };
+                        let snorm = fun == Mf::Pack4x8snorm || fun == Mf::Pack2x16snorm;
+                        let mask = (1u32 << bits) - 1;
+                        write!(self.out, "(")?;
+                        for (index, component) in COMPONENTS[..count].iter().enumerate() {
+                            if index != 0 {
+                                write!(self.out, " | ")?;
+                            }
+                            if snorm {
+                                write!(self.out, "(uint(int(round(clamp(")?;
+                                self.write_expr(module, arg, func_ctx)?;
+                                write!(
+                                    self.out,
+                                    ".{}, -1.0, 1.0) * {}.0)) & 0x{:X})",
+                                    component,
+                                    mask >> 1,
+                                    mask
+                                )?;
+                            } else {
+                                write!(self.out, "(uint(round(saturate(")?;
+                                self.write_expr(module, arg, func_ctx)?;
+                                write!(self.out, ".{}) * {}.0))", component, mask)?;
+                            }
+                            if index != 0 {
+                                write!(self.out, " << {}", index * bits)?;
+                            }
+                            write!(self.out, ")")?;
+                        }
+                        write!(self.out, ")")?;
+                    }
+                    Mf::Pack2x16float => {
+                        write!(self.out, "(f32tof16(")?;
+                        self.write_expr(module, arg, func_ctx)?;
+                        write!(self.out, ".x) | f32tof16(")?;
+                        self.write_expr(module, arg, func_ctx)?;
+                        write!(self.out, ".y) << 16)")?;
+                    }
+                    Mf::Unpack4x8unorm | Mf::Unpack2x16unorm => {
+                        let (count, bits) = match fun {
+                            Mf::Unpack4x8unorm => (4, 8),
+                            _ => (2, 16),
+                        };
+                        let mask = (1u32 << bits) - 1;
+                        write!(self.out, "(float{}(", count)?;
+                        for index in 0..count {
+                            if index != 0 {
+                                write!(self.out, ", ")?;
+                            }
+                            self.write_expr(module, arg, func_ctx)?;
+                            if index != 0 {
+                                write!(self.out, " >> {}", index * bits)?;
+                            }
+                            if index != count - 1 {
+                                write!(self.out, " & 0x{:X}", mask)?;
+                            }
+                        }
+                        write!(self.out, ") / {}.0)", mask)?;
+                    }
+                    Mf::Unpack4x8snorm | Mf::Unpack2x16snorm => {
+                        // shift each component to the top, so that
+                        // the arithmetic shift down extends the sign
+                        let (count, bits) = match fun {
+                            Mf::Unpack4x8snorm => (4, 8),
+                            _ => (2, 16),
+                        };
+                        write!(self.out, "max(float{}(int{}(", count, count)?;
+                        for index in 0..count {
+                            if index != 0 {
+                                write!(self.out, ", ")?;
+                            }
+                            self.write_expr(module, arg, func_ctx)?;
+                            if index != count - 1 {
+                                write!(self.out, " << {}", 32 - (index + 1) * bits)?;
+                            }
+                        }
+                        write!(
+                            self.out,
+                            ") >> {}) / {}.0, -1.0)",
+                            32 - bits,
+                            (1u32 << (bits - 1)) - 1
+                        )?;
+                    }
+                    Mf::Unpack2x16float => {
+                        write!(self.out, "float2(f16tof32(")?;
+                        self.write_expr(module, arg, func_ctx)?;
+                        write!(self.out, "), f16tof32(")?;
+                        self.write_expr(module, arg, func_ctx)?;
+                        write!(self.out, " >> 16))")?;
+                    }
+                    _ => return Err(Error::Unimplemented(format!("write_expr {:?}", expression))),
+                }
+            }
             _ => return Err(Error::Unimplemented(format!("write_expr {:?}", expression))),
         }
 
         Ok(())
     }
 
+    /// Helper method used to write the mask of `count` bits starting at
+    /// `offset`, as an unsigned integer
+    fn write_bit_mask(
+        &mut self,
+        module: &Module,
+        offset: Handle<Expression>,
+        count: Handle<Expression>,
+        func_ctx: &FunctionCtx<'_>,
+    ) -> BackendResult {
+        // shifts only use the low 5 bits of their amount, so an empty
+        // mask has to be special cased
+        write!(self.out, "(")?;
+        self.write_expr(module, count, func_ctx)?;
+        write!(self.out, " == 0u ? 0u : (0xFFFFFFFFu >> (32u - ")?;
+        self.write_expr(module, count, func_ctx)?;
+        write!(self.out, ")) << ")?;
+        self.write_expr(module, offset, func_ctx)?;
+        write!(self.out, ")")?;
+        Ok(())
+    }
+
     /// Helper method used to write constants
     ///
     /// # Notes
@@ -951,14 +2018,11 @@ impl<'a, W: Write> Writer<'a, W> {
     fn write_constant(&mut self, module: &Module, handle: Handle<Constant>) -> BackendResult {
         let constant = &module.constants[handle];
         match constant.inner {
-            crate::ConstantInner::Scalar {
-                width: _,
-                ref value,
-            } => {
+            crate::ConstantInner::Scalar { width, ref value } => {
                 if constant.name.is_some() {
                     write!(self.out, "{}", &self.names[&NameKey::Constant(handle)])?;
                 } else {
-                    self.write_scalar_value(*value)?;
+                    self.write_scalar_value(*value, width)?;
                 }
             }
             crate::ConstantInner::Composite { ty, ref components } => {
@@ -990,9 +2054,11 @@ impl<'a, W: Write> Writer<'a, W> {
     ///
     /// # Notes
     /// Adds no trailing or leading whitespace
-    fn write_scalar_value(&mut self, value: ScalarValue) -> BackendResult {
+    fn write_scalar_value(&mut self, value: ScalarValue, width: Bytes) -> BackendResult {
         match value {
+            ScalarValue::Sint(value) if width == 8 => write!(self.out, "{}L", value)?,
             ScalarValue::Sint(value) => write!(self.out, "{}", value)?,
+            ScalarValue::Uint(value) if width == 8 => write!(self.out, "{}UL", value)?,
             ScalarValue::Uint(value) => write!(self.out, "{}u", value)?,
             // Floats are written using `Debug` instead of `Display` because it always appends the
             // decimal part even it's zero
@@ -1055,9 +2121,31 @@ fn image_dimension_str(dim: ImageDimension) -> &'static str {
     }
 }
 
+/// Helper function that returns the intrinsic replacing a subgroup built-in,
+/// which don't have HLSL semantics
+fn subgroup_builtin_str(binding: Option<&crate::Binding>) -> Option<&'static str> {
+    match binding {
+        Some(&crate::Binding::BuiltIn(BuiltIn::SubgroupSize)) => Some("WaveGetLaneCount()"),
+        Some(&crate::Binding::BuiltIn(BuiltIn::SubgroupInvocationId)) => Some("WaveGetLaneIndex()"),
+        _ => None,
+    }
+}
+
+/// Returns the payload of the task entry point being written, if any
+fn task_payload(module: &Module, func_ctx: &FunctionCtx<'_>) -> Option<Handle<GlobalVariable>> {
+    match func_ctx.ty {
+        FunctionType::EntryPoint(index) => {
+            let ep = &module.entry_points[index as usize];
+            ep.task_payload.filter(|_| ep.stage == ShaderStage::Task)
+        }
+        FunctionType::Function(_) => None,
+    }
+}
+
 fn builtin_str(built_in: BuiltIn) -> &'static str {
     match built_in {
         BuiltIn::Position => "SV_Position",
+        BuiltIn::ViewIndex => "SV_ViewID",
         // vertex
         BuiltIn::ClipDistance => "SV_ClipDistance",
         BuiltIn::CullDistance => "SV_CullDistance",
@@ -1069,6 +2157,7 @@ fn builtin_str(built_in: BuiltIn) -> &'static str {
         // fragment
         BuiltIn::FragDepth => "SV_Depth",
         BuiltIn::FrontFacing => "SV_IsFrontFace",
+        BuiltIn::PrimitiveIndex => "SV_PrimitiveID",
         BuiltIn::SampleIndex => "SV_SampleIndex",
         BuiltIn::SampleMask => "SV_Coverage",
         // compute
@@ -1076,18 +2165,55 @@ fn builtin_str(built_in: BuiltIn) -> &'static str {
         BuiltIn::LocalInvocationId => "SV_GroupThreadID",
         BuiltIn::LocalInvocationIndex => "SV_GroupIndex",
         BuiltIn::WorkGroupId => "SV_GroupID",
+        // mesh
+        BuiltIn::CullPrimitive => "SV_CullPrimitive",
         _ => todo!("builtin_str {:?}", built_in),
     }
 }
 
+impl crate::AtomicFunction {
+    /// Return the suffix of the `Interlocked*` intrinsic implementing this function.
+    fn to_hlsl_suffix(self) -> &'static str {
+        match self {
+            Self::Add | Self::Subtract => "Add",
+            Self::And => "And",
+            Self::InclusiveOr => "Or",
+            Self::ExclusiveOr => "Xor",
+            Self::Min => "Min",
+            Self::Max => "Max",
+            Self::Exchange { compare: None } => "Exchange",
+            Self::Exchange { compare: Some(_) } => "CompareExchange",
+        }
+    }
+}
+
+/// Returns true if `ty` is a runtime-sized array
+fn is_runtime_sized(module: &Module, ty: Handle<Type>) -> bool {
+    match module.types[ty].inner {
+        TypeInner::Array {
+            size: ArraySize::Dynamic,
+            ..
+        } => true,
+        _ => false,
+    }
+}
+
 /// Helper function that returns scalar related strings
 /// https://docs.microsoft.com/en-us/windows/win32/direct3dhlsl/dx-graphics-hlsl-scalar
 fn scalar_kind_str(kind: ScalarKind, width: Bytes) -> Result<&'static str, Error> {
     match kind {
-        ScalarKind::Sint => Ok("int"),
-        ScalarKind::Uint => Ok("uint"),
+        // 64-bit integers require shader model 6.0
+        ScalarKind::Sint => match width {
+            8 => Ok("int64_t"),
+            _ => Ok("int"),
+        },
+        ScalarKind::Uint => match width {
+            8 => Ok("uint64_t"),
+            _ => Ok("uint"),
+        },
         ScalarKind::Float => match width {
-            2 => Ok("half"),
+            // `half` is an alias of `float` unless 16-bit types are enabled
+            2 => Ok("float16_t"),
             4 => Ok("float"),
             8 => Ok("double"),
             _ => Err(Error::UnsupportedScalar(kind, width)),
@@ -0,0 +1,812 @@
+/*!
+Backend for the textual form of the IR.
+
+Every item of the module is written on its own line, as its handle, its name
+if it has one, and a description mirroring the IR:
+
+```text
+t0 = Scalar(Float, 4)
+t1 = Vector(4, Float, 4)
+c0 = Float(4, 1.0)
+g0 "color" = Global(Private, t1)
+f0 "brighten" = Function(arguments: [Argument(t1, name: "value")], result: FunctionResult(t1)) {
+    e0 = FunctionArgument(0)
+    e1 = Constant(c0)
+    e2 = Splat(4, e1)
+    e3 "result" = Binary(Add, e0, e2)
+    emit e2..e4
+    return e3
+}
+```
+
+Expressions are listed in the order of their arena, followed by the
+statements of the function body. The ranges of `emit` exclude their end.
+Constants derived from specializable ones are written as `derived_constant`
+items, with the expressions evaluating them.
+The [`ir`](crate::front::ir) front end parses it back into the same module.
+!*/
+
+use crate::arena::Handle;
+use std::fmt::{Error as FmtError, Write};
+
+const INDENT: &str = "    ";
+
+/// Write the module in the textual form of the IR.
+pub fn write_string(module: &crate::Module) -> Result<String, FmtError> {
+    let mut writer = Writer {
+        out: String::new(),
+        indent: 0,
+    };
+    writer.write_module(module)?;
+    Ok(writer.out)
+}
+
+struct Writer {
+    out: String,
+    indent: usize,
+}
+
+impl Writer {
+    fn write_module(&mut self, module: &crate::Module) -> Result<(), FmtError> {
+        for (handle, ty) in module.types.iter() {
+            write!(self.out, "t{}", handle.index())?;
+            self.write_name(&ty.name)?;
+            self.out.push_str(" = ");
+            self.write_type_inner(&ty.inner)?;
+            self.out.push('\n');
+        }
+        for (handle, constant) in module.constants.iter() {
+            write!(self.out, "c{}", handle.index())?;
+            self.write_name(&constant.name)?;
+            self.out.push_str(" = ");
+            match constant.inner {
+                crate::ConstantInner::Scalar { width, ref value } => match *value {
+                    crate::ScalarValue::Sint(v) => write!(self.out, "Sint({}, {}", width, v)?,
+                    crate::ScalarValue::Uint(v) => write!(self.out, "Uint({}, {}", width, v)?,
+                    crate::ScalarValue::Float(v) => write!(self.out, "Float({}, {:?}", width, v)?,
+                    crate::ScalarValue::Bool(v) => write!(self.out, "Bool({}, {}", width, v)?,
+                },
+                crate::ConstantInner::Composite { ty, ref components } => {
+                    write!(self.out, "Composite(t{}, ", ty.index())?;
+                    self.write_list(components, 'c')?;
+                }
+            }
+            if let Some(id) = constant.specialization {
+                write!(self.out, ", specialization: {}", id)?;
+            }
+            self.out.push_str(")\n");
+        }
+        for derived in module.derived_constants.iter() {
+            writeln!(
+                self.out,
+                "derived_constant = DerivedConstant(c{}, e{}) {{",
+                derived.constant.index(),
+                derived.root.index()
+            )?;
+            self.indent += 1;
+            for (handle, expression) in derived.expressions.iter() {
+                self.write_indent();
+                write!(self.out, "e{} = ", handle.index())?;
+                self.write_expression(expression)?;
+                self.out.push('\n');
+            }
+            self.indent -= 1;
+            self.out.push_str("}\n");
+        }
+        for (handle, global) in module.global_variables.iter() {
+            write!(self.out, "g{}", handle.index())?;
+            self.write_name(&global.name)?;
+            write!(
+                self.out,
+                " = Global({:?}, t{}",
+                global.class,
+                global.ty.index()
+            )?;
+            if let Some(ref binding) = global.binding {
+                write!(
+                    self.out,
+                    ", binding: ResourceBinding({}, {})",
+                    binding.group, binding.binding
+                )?;
+            }
+            if let Some(init) = global.init {
+                write!(self.out, ", init: c{}", init.index())?;
+            }
+            if !global.storage_access.is_empty() {
+                self.out.push_str(", access: ");
+                let flags = [
+                    (crate::StorageAccess::LOAD, "LOAD"),
+                    (crate::StorageAccess::STORE, "STORE"),
+                ];
+                self.write_flags(&flags, |&flag| global.storage_access.contains(flag))?;
+            }
+            self.out.push_str(")\n");
+        }
+        for (handle, function) in module.functions.iter() {
+            write!(self.out, "f{}", handle.index())?;
+            self.write_function(function)?;
+        }
+        for ep in module.entry_points.iter() {
+            write!(
+                self.out,
+                "entry_point {:?} = EntryPoint({:?}, [{}, {}, {}]",
+                ep.name, ep.stage, ep.workgroup_size[0], ep.workgroup_size[1], ep.workgroup_size[2]
+            )?;
+            if let Some(ref early_depth_test) = ep.early_depth_test {
+                self.out.push_str(", early_depth_test: EarlyDepthTest");
+                if let Some(conservative) = early_depth_test.conservative {
+                    write!(self.out, "(conservative: {:?})", conservative)?;
+                }
+            }
+            if let Some(ref info) = ep.mesh_info {
+                write!(
+                    self.out,
+                    ", mesh_info: MeshStageInfo({:?}, {}, {}, t{}",
+                    info.topology,
+                    info.max_vertices,
+                    info.max_primitives,
+                    info.vertex_output_type.index()
+                )?;
+                if let Some(ty) = info.primitive_output_type {
+                    write!(self.out, ", primitive_output_type: t{}", ty.index())?;
+                }
+                self.out.push(')');
+            }
+            if let Some(global) = ep.task_payload {
+                write!(self.out, ", task_payload: g{}", global.index())?;
+            }
+            self.out.push_str(")\nfunction");
+            self.write_function(&ep.function)?;
+        }
+        Ok(())
+    }
+
+    fn write_name(&mut self, name: &Option<String>) -> Result<(), FmtError> {
+        if let Some(ref name) = *name {
+            write!(self.out, " {:?}", name)?;
+        }
+        Ok(())
+    }
+
+    fn write_list<T>(&mut self, handles: &[Handle<T>], prefix: char) -> Result<(), FmtError> {
+        self.out.push('[');
+        for (index, handle) in handles.iter().enumerate() {
+            if index != 0 {
+                self.out.push_str(", ");
+            }
+            write!(self.out, "{}{}", prefix, handle.index())?;
+        }
+        self.out.push(']');
+        Ok(())
+    }
+
+    fn write_flags<T>(
+        &mut self,
+        flags: &[(T, &str)],
+        contains: impl Fn(&T) -> bool,
+    ) -> Result<(), FmtError> {
+        let names = flags
+            .iter()
+            .filter(|pair| contains(&pair.0))
+            .map(|&(_, name)| name)
+            .collect::<Vec<_>>();
+        write!(self.out, "[{}]", names.join(", "))
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn write_type_inner(&mut self, inner: &crate::TypeInner) -> Result<(), FmtError> {
+        use crate::TypeInner as Ti;
+        match *inner {
+            Ti::Scalar { kind, width } => write!(self.out, "Scalar({:?}, {})", kind, width)?,
+            Ti::Vector { size, kind, width } => {
+                write!(self.out, "Vector({}, {:?}, {})", size as u8, kind, width)?
+            }
+            Ti::Matrix {
+                columns,
+                rows,
+                width,
+            } => write!(
+                self.out,
+                "Matrix({}, {}, {})",
+                columns as u8, rows as u8, width
+            )?,
+            Ti::Atomic { kind, width } => write!(self.out, "Atomic({:?}, {})", kind, width)?,
+            Ti::Pointer { base, class } => {
+                write!(self.out, "Pointer(t{}, {:?})", base.index(), class)?
+            }
+            Ti::ValuePointer {
+                size,
+                kind,
+                width,
+                class,
+            } => {
+                write!(self.out, "ValuePointer({:?}, {}, {:?}", kind, width, class)?;
+                if let Some(size) = size {
+                    write!(self.out, ", size: {}", size as u8)?;
+                }
+                self.out.push(')');
+            }
+            Ti::Array { base, size, stride } => {
+                write!(self.out, "Array(t{}, ", base.index())?;
+                self.write_array_size(size)?;
+                write!(self.out, ", {})", stride)?;
+            }
+            Ti::Struct {
+                top_level,
+                ref members,
+                span,
+            } => {
+                writeln!(self.out, "Struct({}, {}, [", top_level, span)?;
+                for member in members {
+                    write!(
+                        self.out,
+                        "{}Member(t{}, {}",
+                        INDENT,
+                        member.ty.index(),
+                        member.offset
+                    )?;
+                    if let Some(ref name) = member.name {
+                        write!(self.out, ", name: {:?}", name)?;
+                    }
+                    if let Some(ref binding) = member.binding {
+                        self.out.push_str(", binding: ");
+                        self.write_binding(binding)?;
+                    }
+                    self.out.push_str("),\n");
+                }
+                self.out.push_str("])");
+            }
+            Ti::Image {
+                dim,
+                arrayed,
+                class,
+            } => {
+                write!(self.out, "Image({:?}, {}, ", dim, arrayed)?;
+                match class {
+                    crate::ImageClass::Sampled { kind, multi } => {
+                        write!(self.out, "Sampled({:?}, {})", kind, multi)?
+                    }
+                    crate::ImageClass::Depth => self.out.push_str("Depth"),
+                    crate::ImageClass::Storage(format) => {
+                        write!(self.out, "Storage({:?})", format)?
+                    }
+                }
+                self.out.push(')');
+            }
+            Ti::Sampler { comparison } => write!(self.out, "Sampler({})", comparison)?,
+            Ti::AccelerationStructure => self.out.push_str("AccelerationStructure"),
+            Ti::RayQuery => self.out.push_str("RayQuery"),
+            Ti::BindingArray { base, size } => {
+                write!(self.out, "BindingArray(t{}, ", base.index())?;
+                self.write_array_size(size)?;
+                self.out.push(')');
+            }
+        }
+        Ok(())
+    }
+
+    fn write_array_size(&mut self, size: crate::ArraySize) -> Result<(), FmtError> {
+        match size {
+            crate::ArraySize::Constant(constant) => write!(self.out, "c{}", constant.index()),
+            crate::ArraySize::Dynamic => write!(self.out, "Dynamic"),
+        }
+    }
+
+    fn write_binding(&mut self, binding: &crate::Binding) -> Result<(), FmtError> {
+        match *binding {
+            crate::Binding::BuiltIn(built_in) => write!(self.out, "BuiltIn({:?})", built_in)?,
+            crate::Binding::Location {
+                location,
+                second_blend_source,
+                interpolation,
+                sampling,
+            } => {
+                write!(self.out, "Location({}", location)?;
+                if second_blend_source {
+                    self.out.push_str(", second_blend_source: true");
+                }
+                if let Some(interpolation) = interpolation {
+                    write!(self.out, ", interpolation: {:?}", interpolation)?;
+                }
+                if let Some(sampling) = sampling {
+                    write!(self.out, ", sampling: {:?}", sampling)?;
+                }
+                self.out.push(')');
+            }
+        }
+        Ok(())
+    }
+
+    fn write_function(&mut self, function: &crate::Function) -> Result<(), FmtError> {
+        self.write_name(&function.name)?;
+        self.out.push_str(" = Function(");
+        let mut separator = "";
+        if !function.arguments.is_empty() {
+            self.out.push_str("arguments: [");
+            for (index, argument) in function.arguments.iter().enumerate() {
+                if index != 0 {
+                    self.out.push_str(", ");
+                }
+                write!(self.out, "Argument(t{}", argument.ty.index())?;
+                if let Some(ref name) = argument.name {
+                    write!(self.out, ", name: {:?}", name)?;
+                }
+                if let Some(ref binding) = argument.binding {
+                    self.out.push_str(", binding: ");
+                    self.write_binding(binding)?;
+                }
+                self.out.push(')');
+            }
+            self.out.push(']');
+            separator = ", ";
+        }
+        if let Some(ref result) = function.result {
+            write!(
+                self.out,
+                "{}result: FunctionResult(t{}",
+                separator,
+                result.ty.index()
+            )?;
+            if let Some(ref binding) = result.binding {
+                self.out.push_str(", binding: ");
+                self.write_binding(binding)?;
+            }
+            self.out.push(')');
+        }
+        self.out.push_str(") {\n");
+
+        self.indent += 1;
+        for (handle, local) in function.local_variables.iter() {
+            self.write_indent();
+            write!(self.out, "l{}", handle.index())?;
+            self.write_name(&local.name)?;
+            write!(self.out, " = Local(t{}", local.ty.index())?;
+            if let Some(init) = local.init {
+                write!(self.out, ", init: c{}", init.index())?;
+            }
+            self.out.push_str(")\n");
+        }
+        for (handle, expression) in function.expressions.iter() {
+            self.write_indent();
+            write!(self.out, "e{}", handle.index())?;
+            if let Some(name) = function.named_expressions.get(&handle) {
+                write!(self.out, " {:?}", name)?;
+            }
+            self.out.push_str(" = ");
+            self.write_expression(expression)?;
+            self.out.push('\n');
+        }
+        self.write_block_contents(&function.body)?;
+        self.indent -= 1;
+        self.out.push_str("}\n");
+        Ok(())
+    }
+
+    fn write_expression(&mut self, expression: &crate::Expression) -> Result<(), FmtError> {
+        use crate::Expression as E;
+        match *expression {
+            E::Access { base, index } => {
+                write!(self.out, "Access(e{}, e{})", base.index(), index.index())?
+            }
+            E::AccessIndex { base, index } => {
+                write!(self.out, "AccessIndex(e{}, {})", base.index(), index)?
+            }
+            E::Constant(constant) => write!(self.out, "Constant(c{})", constant.index())?,
+            E::Splat { size, value } => {
+                write!(self.out, "Splat({}, e{})", size as u8, value.index())?
+            }
+            E::Swizzle {
+                size,
+                vector,
+                pattern,
+            } => write!(
+                self.out,
+                "Swizzle({}, e{}, [{:?}, {:?}, {:?}, {:?}])",
+                size as u8,
+                vector.index(),
+                pattern[0],
+                pattern[1],
+                pattern[2],
+                pattern[3]
+            )?,
+            E::Compose { ty, ref components } => {
+                write!(self.out, "Compose(t{}, ", ty.index())?;
+                self.write_list(components, 'e')?;
+                self.out.push(')');
+            }
+            E::FunctionArgument(index) => write!(self.out, "FunctionArgument({})", index)?,
+            E::GlobalVariable(global) => write!(self.out, "GlobalVariable(g{})", global.index())?,
+            E::LocalVariable(local) => write!(self.out, "LocalVariable(l{})", local.index())?,
+            E::Load { pointer } => write!(self.out, "Load(e{})", pointer.index())?,
+            E::ImageSample {
+                image,
+                sampler,
+                gather,
+                coordinate,
+                array_index,
+                offset,
+                level,
+                depth_ref,
+            } => {
+                write!(
+                    self.out,
+                    "ImageSample(e{}, e{}, e{}, ",
+                    image.index(),
+                    sampler.index(),
+                    coordinate.index()
+                )?;
+                match level {
+                    crate::SampleLevel::Auto => self.out.push_str("Auto"),
+                    crate::SampleLevel::Zero => self.out.push_str("Zero"),
+                    crate::SampleLevel::Exact(e) => write!(self.out, "Exact(e{})", e.index())?,
+                    crate::SampleLevel::Bias(e) => write!(self.out, "Bias(e{})", e.index())?,
+                    crate::SampleLevel::Gradient { x, y } => {
+                        write!(self.out, "Gradient(e{}, e{})", x.index(), y.index())?
+                    }
+                }
+                if let Some(component) = gather {
+                    write!(self.out, ", gather: {:?}", component)?;
+                }
+                self.write_option_field("array_index", 'e', array_index)?;
+                self.write_option_field("offset", 'c', offset)?;
+                self.write_option_field("depth_ref", 'e', depth_ref)?;
+                self.out.push(')');
+            }
+            E::ImageLoad {
+                image,
+                coordinate,
+                array_index,
+                index,
+            } => {
+                write!(
+                    self.out,
+                    "ImageLoad(e{}, e{}",
+                    image.index(),
+                    coordinate.index()
+                )?;
+                self.write_option_field("array_index", 'e', array_index)?;
+                self.write_option_field("index", 'e', index)?;
+                self.out.push(')');
+            }
+            E::ImageQuery { image, query } => {
+                write!(self.out, "ImageQuery(e{}, ", image.index())?;
+                match query {
+                    crate::ImageQuery::Size { level: None } => self.out.push_str("Size"),
+                    crate::ImageQuery::Size { level: Some(e) } => {
+                        write!(self.out, "Size(e{})", e.index())?
+                    }
+                    crate::ImageQuery::NumLevels => self.out.push_str("NumLevels"),
+                    crate::ImageQuery::NumLayers => self.out.push_str("NumLayers"),
+                    crate::ImageQuery::NumSamples => self.out.push_str("NumSamples"),
+                }
+                self.out.push(')');
+            }
+            E::Unary { op, expr } => write!(self.out, "Unary({:?}, e{})", op, expr.index())?,
+            E::Binary { op, left, right } => write!(
+                self.out,
+                "Binary({:?}, e{}, e{})",
+                op,
+                left.index(),
+                right.index()
+            )?,
+            E::Select {
+                condition,
+                accept,
+                reject,
+            } => write!(
+                self.out,
+                "Select(e{}, e{}, e{})",
+                condition.index(),
+                accept.index(),
+                reject.index()
+            )?,
+            E::Derivative { axis, expr } => {
+                write!(self.out, "Derivative({:?}, e{})", axis, expr.index())?
+            }
+            E::Relational { fun, argument } => {
+                write!(self.out, "Relational({:?}, e{})", fun, argument.index())?
+            }
+            E::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+                arg3,
+            } => {
+                write!(self.out, "Math({:?}, e{}", fun, arg.index())?;
+                self.write_option_field("arg1", 'e', arg1)?;
+                self.write_option_field("arg2", 'e', arg2)?;
+                self.write_option_field("arg3", 'e', arg3)?;
+                self.out.push(')');
+            }
+            E::As {
+                expr,
+                kind,
+                convert,
+            } => {
+                write!(self.out, "As(e{}, {:?}", expr.index(), kind)?;
+                if let Some(width) = convert {
+                    write!(self.out, ", convert: {}", width)?;
+                }
+                self.out.push(')');
+            }
+            E::Call(function) => write!(self.out, "Call(f{})", function.index())?,
+            E::AtomicResult {
+                kind,
+                width,
+                comparison,
+            } => write!(
+                self.out,
+                "AtomicResult({:?}, {}, {})",
+                kind, width, comparison
+            )?,
+            E::SubgroupBallotResult => self.out.push_str("SubgroupBallotResult"),
+            E::SubgroupOperationResult { ty } => {
+                write!(self.out, "SubgroupOperationResult(t{})", ty.index())?
+            }
+            E::RayQueryProceedResult => self.out.push_str("RayQueryProceedResult"),
+            E::RayQueryGetIntersection {
+                query,
+                committed,
+                ty,
+            } => write!(
+                self.out,
+                "RayQueryGetIntersection(e{}, {}, t{})",
+                query.index(),
+                committed,
+                ty.index()
+            )?,
+            E::ArrayLength(expr) => write!(self.out, "ArrayLength(e{})", expr.index())?,
+        }
+        Ok(())
+    }
+
+    fn write_option_field<T>(
+        &mut self,
+        name: &str,
+        prefix: char,
+        handle: Option<Handle<T>>,
+    ) -> Result<(), FmtError> {
+        if let Some(handle) = handle {
+            write!(self.out, ", {}: {}{}", name, prefix, handle.index())?;
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self, block: &[crate::Statement]) -> Result<(), FmtError> {
+        self.out.push_str("{\n");
+        self.indent += 1;
+        self.write_block_contents(block)?;
+        self.indent -= 1;
+        self.write_indent();
+        self.out.push('}');
+        Ok(())
+    }
+
+    fn write_block_contents(&mut self, block: &[crate::Statement]) -> Result<(), FmtError> {
+        for statement in block {
+            self.write_indent();
+            self.write_statement(statement)?;
+            self.out.push('\n');
+        }
+        Ok(())
+    }
+
+    fn write_statement(&mut self, statement: &crate::Statement) -> Result<(), FmtError> {
+        use crate::Statement as S;
+        match *statement {
+            S::Emit(ref range) => {
+                let range = range.index_range();
+                write!(self.out, "emit e{}..e{}", range.start, range.end)?;
+            }
+            S::Block(ref block) => {
+                self.out.push_str("block ");
+                self.write_block(block)?;
+            }
+            S::If {
+                condition,
+                ref accept,
+                ref reject,
+            } => {
+                write!(self.out, "if e{} ", condition.index())?;
+                self.write_block(accept)?;
+                if !reject.is_empty() {
+                    self.out.push_str(" else ");
+                    self.write_block(reject)?;
+                }
+            }
+            S::Switch {
+                selector,
+                ref cases,
+                ref default,
+            } => {
+                writeln!(self.out, "switch e{} {{", selector.index())?;
+                self.indent += 1;
+                for case in cases {
+                    self.write_indent();
+                    write!(self.out, "case {} ", case.value)?;
+                    if case.fall_through {
+                        self.out.push_str("fallthrough ");
+                    }
+                    self.write_block(&case.body)?;
+                    self.out.push('\n');
+                }
+                self.write_indent();
+                self.out.push_str("default ");
+                self.write_block(default)?;
+                self.out.push('\n');
+                self.indent -= 1;
+                self.write_indent();
+                self.out.push('}');
+            }
+            S::Loop {
+                ref body,
+                ref continuing,
+            } => {
+                self.out.push_str("loop ");
+                self.write_block(body)?;
+                if !continuing.is_empty() {
+                    self.out.push_str(" continuing ");
+                    self.write_block(continuing)?;
+                }
+            }
+            S::Break => self.out.push_str("break"),
+            S::Continue => self.out.push_str("continue"),
+            S::Return { value: None } => self.out.push_str("return"),
+            S::Return { value: Some(value) } => write!(self.out, "return e{}", value.index())?,
+            S::Kill => self.out.push_str("kill"),
+            S::Barrier(barrier) => {
+                self.out.push_str("Barrier(");
+                let flags = [
+                    (crate::Barrier::STORAGE, "STORAGE"),
+                    (crate::Barrier::WORK_GROUP, "WORK_GROUP"),
+                ];
+                self.write_flags(&flags, |&flag| barrier.contains(flag))?;
+                self.out.push(')');
+            }
+            S::Store { pointer, value } => {
+                write!(self.out, "Store(e{}, e{})", pointer.index(), value.index())?
+            }
+            S::ImageStore {
+                image,
+                coordinate,
+                array_index,
+                value,
+            } => {
+                write!(
+                    self.out,
+                    "ImageStore(e{}, e{}, e{}",
+                    image.index(),
+                    coordinate.index(),
+                    value.index()
+                )?;
+                self.write_option_field("array_index", 'e', array_index)?;
+                self.out.push(')');
+            }
+            S::Call {
+                function,
+                ref arguments,
+                result,
+            } => {
+                write!(self.out, "Call(f{}, ", function.index())?;
+                self.write_list(arguments, 'e')?;
+                self.write_option_field("result", 'e', result)?;
+                self.out.push(')');
+            }
+            S::Atomic {
+                pointer,
+                ref fun,
+                value,
+                result,
+            } => {
+                write!(self.out, "Atomic(e{}, ", pointer.index())?;
+                match *fun {
+                    crate::AtomicFunction::Exchange { compare: None } => {
+                        self.out.push_str("Exchange")
+                    }
+                    crate::AtomicFunction::Exchange {
+                        compare: Some(compare),
+                    } => write!(self.out, "Exchange(e{})", compare.index())?,
+                    ref other => write!(self.out, "{:?}", other)?,
+                }
+                write!(self.out, ", e{}, e{})", value.index(), result.index())?;
+            }
+            S::SubgroupBallot { result, predicate } => {
+                write!(self.out, "SubgroupBallot(e{}", result.index())?;
+                self.write_option_field("predicate", 'e', predicate)?;
+                self.out.push(')');
+            }
+            S::SubgroupCollectiveOperation {
+                op,
+                collective_op,
+                argument,
+                result,
+            } => write!(
+                self.out,
+                "SubgroupCollectiveOperation({:?}, {:?}, e{}, e{})",
+                op,
+                collective_op,
+                argument.index(),
+                result.index()
+            )?,
+            S::SubgroupGather {
+                mode,
+                argument,
+                result,
+            } => {
+                self.out.push_str("SubgroupGather(");
+                match mode {
+                    crate::GatherMode::BroadcastFirst => self.out.push_str("BroadcastFirst"),
+                    crate::GatherMode::Broadcast(e) => {
+                        write!(self.out, "Broadcast(e{})", e.index())?
+                    }
+                    crate::GatherMode::Shuffle(e) => write!(self.out, "Shuffle(e{})", e.index())?,
+                    crate::GatherMode::ShuffleDown(e) => {
+                        write!(self.out, "ShuffleDown(e{})", e.index())?
+                    }
+                    crate::GatherMode::ShuffleUp(e) => {
+                        write!(self.out, "ShuffleUp(e{})", e.index())?
+                    }
+                    crate::GatherMode::ShuffleXor(e) => {
+                        write!(self.out, "ShuffleXor(e{})", e.index())?
+                    }
+                }
+                write!(self.out, ", e{}, e{})", argument.index(), result.index())?;
+            }
+            S::MeshFunction(ref fun) => match *fun {
+                crate::MeshFunction::SetMeshOutputs {
+                    vertex_count,
+                    primitive_count,
+                } => write!(
+                    self.out,
+                    "SetMeshOutputs(e{}, e{})",
+                    vertex_count.index(),
+                    primitive_count.index()
+                )?,
+                crate::MeshFunction::SetVertex { index, value } => write!(
+                    self.out,
+                    "SetVertex(e{}, e{})",
+                    index.index(),
+                    value.index()
+                )?,
+                crate::MeshFunction::SetIndices { index, value } => write!(
+                    self.out,
+                    "SetIndices(e{}, e{})",
+                    index.index(),
+                    value.index()
+                )?,
+                crate::MeshFunction::SetPrimitive { index, value } => write!(
+                    self.out,
+                    "SetPrimitive(e{}, e{})",
+                    index.index(),
+                    value.index()
+                )?,
+            },
+            S::RayQuery { query, ref fun } => {
+                write!(self.out, "RayQuery(e{}, ", query.index())?;
+                match *fun {
+                    crate::RayQueryFunction::Initialize {
+                        acceleration_structure,
+                        descriptor,
+                    } => write!(
+                        self.out,
+                        "Initialize(e{}, e{})",
+                        acceleration_structure.index(),
+                        descriptor.index()
+                    )?,
+                    crate::RayQueryFunction::Proceed { result } => {
+                        write!(self.out, "Proceed(e{})", result.index())?
+                    }
+                    crate::RayQueryFunction::Terminate => self.out.push_str("Terminate"),
+                }
+                self.out.push(')');
+            }
+        }
+        Ok(())
+    }
+}
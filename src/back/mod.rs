@@ -6,6 +6,8 @@ pub mod dot;
 pub mod glsl;
 #[cfg(feature = "hlsl-out")]
 pub mod hlsl;
+#[cfg(feature = "ir-out")]
+pub mod ir;
 #[cfg(feature = "msl-out")]
 pub mod msl;
 #[cfg(feature = "spv-out")]
@@ -130,6 +132,8 @@ impl crate::Expression {
             // More info - https://github.com/gfx-rs/naga/pull/914
             // And https://github.com/gfx-rs/naga/issues/910
             crate::Expression::Load { .. } => 1,
+            // intersections change as the ray query proceeds
+            crate::Expression::RayQueryGetIntersection { .. } => 1,
             // cache expressions that are referenced multiple times
             _ => 2,
         }
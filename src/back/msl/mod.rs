@@ -92,12 +92,19 @@ pub struct PerStageMap {
     pub fs: PerStageResources,
     #[cfg_attr(feature = "deserialize", serde(default))]
     pub cs: PerStageResources,
+    #[cfg_attr(feature = "deserialize", serde(default))]
+    pub ts: PerStageResources,
+    #[cfg_attr(feature = "deserialize", serde(default))]
+    pub ms: PerStageResources,
 }
 
 enum ResolvedBinding {
     BuiltIn(crate::BuiltIn),
     Attribute(u32),
-    Color(u32),
+    Color {
+        location: u32,
+        second_blend_source: bool,
+    },
     User {
         prefix: &'static str,
         index: u32,
@@ -139,6 +146,8 @@ pub enum Error {
     UnsupportedBuiltIn(crate::BuiltIn),
     #[error("capability {0:?} is not supported")]
     CapabilityNotSupported(crate::valid::Capabilities),
+    #[error("derived constants can't be written, the module has to be specialized first")]
+    DerivedConstants,
 }
 
 #[derive(Clone, Debug, PartialEq, thiserror::Error)]
@@ -220,11 +229,15 @@ impl Options {
             crate::Binding::BuiltIn(built_in) => Ok(ResolvedBinding::BuiltIn(built_in)),
             crate::Binding::Location {
                 location,
+                second_blend_source,
                 interpolation,
                 sampling,
             } => match mode {
                 LocationMode::VertexInput => Ok(ResolvedBinding::Attribute(location)),
-                LocationMode::FragmentOutput => Ok(ResolvedBinding::Color(location)),
+                LocationMode::FragmentOutput => Ok(ResolvedBinding::Color {
+                    location,
+                    second_blend_source,
+                }),
                 LocationMode::Intermediate => Ok(ResolvedBinding::User {
                     prefix: if self.spirv_cross_compatibility {
                         "locn"
@@ -233,12 +246,14 @@ impl Options {
                     },
                     index: location,
                     interpolation: {
-                        // unwrap: The verifier ensures that vertex shader outputs and fragment
+                        // The verifier ensures that vertex shader outputs and fragment
                         // shader inputs always have fully specified interpolation, and that
-                        // sampling is `None` only for Flat interpolation.
-                        let interpolation = interpolation.unwrap();
-                        let sampling = sampling.unwrap_or(crate::Sampling::Center);
-                        Some(ResolvedInterpolation::from_binding(interpolation, sampling))
+                        // sampling is `None` only for Flat interpolation. Per-primitive
+                        // mesh shader outputs are not interpolated at all.
+                        interpolation.map(|interpolation| {
+                            let sampling = sampling.unwrap_or(crate::Sampling::Center);
+                            ResolvedInterpolation::from_binding(interpolation, sampling)
+                        })
                     },
                 }),
                 LocationMode::Uniform => {
@@ -281,6 +296,8 @@ impl Options {
             crate::ShaderStage::Vertex => self.per_stage_map.vs.push_constant_buffer,
             crate::ShaderStage::Fragment => self.per_stage_map.fs.push_constant_buffer,
             crate::ShaderStage::Compute => self.per_stage_map.cs.push_constant_buffer,
+            crate::ShaderStage::Task => self.per_stage_map.ts.push_constant_buffer,
+            crate::ShaderStage::Mesh => self.per_stage_map.ms.push_constant_buffer,
         };
         match slot {
             Some(slot) => Ok(ResolvedBinding::Resource(BindTarget {
@@ -306,6 +323,8 @@ impl Options {
             crate::ShaderStage::Vertex => self.per_stage_map.vs.sizes_buffer,
             crate::ShaderStage::Fragment => self.per_stage_map.fs.sizes_buffer,
             crate::ShaderStage::Compute => self.per_stage_map.cs.sizes_buffer,
+            crate::ShaderStage::Task => self.per_stage_map.ts.sizes_buffer,
+            crate::ShaderStage::Mesh => self.per_stage_map.ms.sizes_buffer,
         };
 
         match slot {
@@ -342,6 +361,7 @@ impl ResolvedBinding {
                 use crate::BuiltIn as Bi;
                 let name = match built_in {
                     Bi::Position => "position",
+                    Bi::ViewIndex => "amplification_id",
                     // vertex
                     Bi::BaseInstance => "base_instance",
                     Bi::BaseVertex => "base_vertex",
@@ -352,6 +372,7 @@ impl ResolvedBinding {
                     // fragment
                     Bi::FragDepth => "depth(any)",
                     Bi::FrontFacing => "front_facing",
+                    Bi::PrimitiveIndex => "primitive_id",
                     Bi::SampleIndex => "sample_id",
                     Bi::SampleMask => "sample_mask",
                     // compute
@@ -360,12 +381,25 @@ impl ResolvedBinding {
                     Bi::LocalInvocationIndex => "thread_index_in_threadgroup",
                     Bi::WorkGroupId => "threadgroup_position_in_grid",
                     Bi::WorkGroupSize => "dispatch_threads_per_threadgroup",
+                    // subgroup
+                    Bi::SubgroupSize => "threads_per_simdgroup",
+                    Bi::SubgroupInvocationId => "thread_index_in_simdgroup",
+                    // mesh
+                    Bi::CullPrimitive => "primitive_culled",
                     _ => return Err(Error::UnsupportedBuiltIn(built_in)),
                 };
                 write!(out, "{}", name)?;
             }
             Self::Attribute(index) => write!(out, "attribute({})", index)?,
-            Self::Color(index) => write!(out, "color({})", index)?,
+            Self::Color {
+                location,
+                second_blend_source,
+            } => {
+                write!(out, "color({})", location)?;
+                if second_blend_source {
+                    write!(out, ", index(1)")?;
+                }
+            }
             Self::User {
                 prefix,
                 index,
@@ -16,6 +16,8 @@ use std::{
 
 const NAMESPACE: &str = "metal";
 const WRAPPED_ARRAY_FIELD: &str = "inner";
+const RAY_QUERY_TYPE: &str = "_RayQuery";
+const RAY_QUERY_INTERSECTOR: &str = "metal::raytracing::intersector<metal::raytracing::instancing, metal::raytracing::triangle_data, metal::raytracing::world_space_data>";
 
 #[derive(Clone)]
 struct Level(usize);
@@ -50,28 +52,40 @@ impl<'a> Display for TypeContext<'a> {
             // work around Metal toolchain bug with `uint` typedef
             crate::TypeInner::Scalar {
                 kind: crate::ScalarKind::Uint,
-                ..
+                width: 4,
             } => {
                 write!(out, "metal::uint")
             }
-            crate::TypeInner::Scalar { kind, .. } => {
-                write!(out, "{}", scalar_kind_string(kind))
+            crate::TypeInner::Scalar { kind, width } => {
+                write!(out, "{}", scalar_kind_string(kind, width))
             }
-            crate::TypeInner::Vector { size, kind, .. } => {
+            crate::TypeInner::Atomic { kind, width } => {
+                write!(
+                    out,
+                    "{}::atomic_{}",
+                    NAMESPACE,
+                    scalar_kind_string(kind, width)
+                )
+            }
+            crate::TypeInner::Vector { size, kind, width } => {
                 write!(
                     out,
                     "{}::{}{}",
                     NAMESPACE,
-                    scalar_kind_string(kind),
+                    scalar_kind_string(kind, width),
                     vector_size_str(size),
                 )
             }
-            crate::TypeInner::Matrix { columns, rows, .. } => {
+            crate::TypeInner::Matrix {
+                columns,
+                rows,
+                width,
+            } => {
                 write!(
                     out,
                     "{}::{}{}x{}",
                     NAMESPACE,
-                    scalar_kind_string(crate::ScalarKind::Float),
+                    scalar_kind_string(crate::ScalarKind::Float, width),
                     vector_size_str(columns),
                     vector_size_str(rows),
                 )
@@ -91,19 +105,19 @@ impl<'a> Display for TypeContext<'a> {
             crate::TypeInner::ValuePointer {
                 size: None,
                 kind,
-                width: _,
+                width,
                 class,
             } => {
                 let class_name = match class.get_name(self.access) {
                     Some(name) => name,
                     None => return Ok(()),
                 };
-                write!(out, "{} {}&", class_name, scalar_kind_string(kind),)
+                write!(out, "{} {}&", class_name, scalar_kind_string(kind, width),)
             }
             crate::TypeInner::ValuePointer {
                 size: Some(size),
                 kind,
-                width: _,
+                width,
                 class,
             } => {
                 let class_name = match class.get_name(self.access) {
@@ -115,7 +129,7 @@ impl<'a> Display for TypeContext<'a> {
                     "{} {}::{}{}&",
                     class_name,
                     NAMESPACE,
-                    scalar_kind_string(kind),
+                    scalar_kind_string(kind, width),
                     vector_size_str(size),
                 )
             }
@@ -167,7 +181,7 @@ impl<'a> Display for TypeContext<'a> {
                         ("texture", "", format.into(), access)
                     }
                 };
-                let base_name = scalar_kind_string(kind);
+                let base_name = scalar_kind_string(kind, 4);
                 let array_str = if arrayed { "_array" } else { "" };
                 write!(
                     out,
@@ -185,6 +199,24 @@ impl<'a> Display for TypeContext<'a> {
             crate::TypeInner::Sampler { comparison: _ } => {
                 write!(out, "{}::sampler", NAMESPACE)
             }
+            crate::TypeInner::AccelerationStructure => {
+                write!(
+                    out,
+                    "{}::raytracing::instance_acceleration_structure",
+                    NAMESPACE
+                )
+            }
+            crate::TypeInner::RayQuery => write!(out, "{}", RAY_QUERY_TYPE),
+            crate::TypeInner::BindingArray { base, .. } => {
+                let sub = Self {
+                    handle: base,
+                    first_time: false,
+                    ..*self
+                };
+                // Binding arrays are only used by global variables,
+                // which write the array around the element type.
+                write!(out, "{}", sub)
+            }
         }
     }
 }
@@ -209,10 +241,35 @@ impl<'a> TypedGlobalVariable<'a> {
             first_time: false,
         };
 
+        if let crate::TypeInner::BindingArray { size, .. } = self.module.types[var.ty].inner {
+            if var.class != crate::StorageClass::Handle {
+                return Err(Error::FeatureNotImplemented(
+                    "binding arrays of buffers".to_string(),
+                ));
+            }
+            let size = match size {
+                crate::ArraySize::Constant(const_handle) => self.module.constants[const_handle]
+                    .to_array_length()
+                    .unwrap(),
+                crate::ArraySize::Dynamic => {
+                    return Err(Error::FeatureNotImplemented(
+                        "dynamically sized binding arrays".to_string(),
+                    ))
+                }
+            };
+            return Ok(write!(
+                out,
+                "{}::array<{}, {}> {}",
+                NAMESPACE, ty_name, size, name
+            )?);
+        }
+
         let (space, access, reference) = match var.class.get_name(var.storage_access) {
             Some(space) if self.reference => {
                 let access = match var.class {
-                    crate::StorageClass::Private | crate::StorageClass::WorkGroup
+                    crate::StorageClass::Private
+                    | crate::StorageClass::WorkGroup
+                    | crate::StorageClass::TaskPayload
                         if !self.usage.contains(GlobalUse::WRITE) =>
                     {
                         "const"
@@ -254,12 +311,14 @@ impl<'a> Display for ConstantContext<'a> {
         }
 
         match con.inner {
-            crate::ConstantInner::Scalar { value, width: _ } => match value {
+            crate::ConstantInner::Scalar { value, width } => match value {
                 crate::ScalarValue::Sint(value) => {
-                    write!(out, "{}", value)
+                    let long = if width == 8 { "L" } else { "" };
+                    write!(out, "{}{}", value, long)
                 }
                 crate::ScalarValue::Uint(value) => {
-                    write!(out, "{}u", value)
+                    let long = if width == 8 { "L" } else { "" };
+                    write!(out, "{}u{}", value, long)
                 }
                 crate::ScalarValue::Float(value) => {
                     if value.is_infinite() {
@@ -269,8 +328,9 @@ impl<'a> Display for ConstantContext<'a> {
                         write!(out, "NAN")
                     } else {
                         let suffix = if value.fract() == 0.0 { ".0" } else { "" };
+                        let half = if width == 2 { "h" } else { "" };
 
-                        write!(out, "{}{}", value, suffix)
+                        write!(out, "{}{}{}", value, suffix, half)
                     }
                 }
                 crate::ScalarValue::Bool(value) => {
@@ -294,12 +354,31 @@ pub struct Writer<W> {
     put_block_stack_pointers: crate::FastHashSet<*const ()>,
 }
 
-fn scalar_kind_string(kind: crate::ScalarKind) -> &'static str {
-    match kind {
-        crate::ScalarKind::Float => "float",
-        crate::ScalarKind::Sint => "int",
-        crate::ScalarKind::Uint => "uint",
-        crate::ScalarKind::Bool => "bool",
+impl crate::AtomicFunction {
+    fn to_msl(self) -> &'static str {
+        match self {
+            Self::Add => "fetch_add",
+            Self::Subtract => "fetch_sub",
+            Self::And => "fetch_and",
+            Self::InclusiveOr => "fetch_or",
+            Self::ExclusiveOr => "fetch_xor",
+            Self::Min => "fetch_min",
+            Self::Max => "fetch_max",
+            Self::Exchange { compare: None } => "exchange",
+            Self::Exchange { compare: Some(_) } => "compare_exchange_weak",
+        }
+    }
+}
+
+fn scalar_kind_string(kind: crate::ScalarKind, width: crate::Bytes) -> &'static str {
+    match (kind, width) {
+        (crate::ScalarKind::Float, 2) => "half",
+        (crate::ScalarKind::Float, _) => "float",
+        (crate::ScalarKind::Sint, 8) => "long",
+        (crate::ScalarKind::Sint, _) => "int",
+        (crate::ScalarKind::Uint, 8) => "ulong",
+        (crate::ScalarKind::Uint, _) => "uint",
+        (crate::ScalarKind::Bool, _) => "bool",
     }
 }
 
@@ -352,6 +431,31 @@ fn needs_array_length(ty: Handle<crate::Type>, arena: &Arena<crate::Type>) -> bo
     false
 }
 
+/// Returns true if the entry point receives the `built_in` input.
+fn uses_built_in(module: &crate::Module, ep: &crate::EntryPoint, built_in: crate::BuiltIn) -> bool {
+    let binding = Some(crate::Binding::BuiltIn(built_in));
+    ep.function
+        .arguments
+        .iter()
+        .any(|arg| match module.types[arg.ty].inner {
+            crate::TypeInner::Struct { ref members, .. } if arg.binding.is_none() => {
+                members.iter().any(|member| member.binding == binding)
+            }
+            _ => arg.binding == binding,
+        })
+}
+
+/// Returns true for mesh shader output members that can't be written in Metal.
+fn is_omitted_output(binding: Option<&crate::Binding>, pipeline_options: &PipelineOptions) -> bool {
+    match binding {
+        Some(&crate::Binding::BuiltIn(crate::BuiltIn::CullDistance)) => true,
+        Some(&crate::Binding::BuiltIn(crate::BuiltIn::PointSize)) => {
+            !pipeline_options.allow_point_size
+        }
+        _ => false,
+    }
+}
+
 impl crate::StorageClass {
     /// Returns true for storage classes, for which the global
     /// variables are passed in function arguments.
@@ -380,6 +484,7 @@ impl crate::StorageClass {
             }),
             Self::Private | Self::Function => Some("thread"),
             Self::WorkGroup => Some("threadgroup"),
+            Self::TaskPayload => Some("object_data"),
         }
     }
 }
@@ -393,12 +498,17 @@ impl crate::Type {
             Ti::Scalar { .. }
             | Ti::Vector { .. }
             | Ti::Matrix { .. }
+            | Ti::Atomic { .. }
             | Ti::Pointer { .. }
             | Ti::ValuePointer { .. } => self.name.is_some(),
             // composite types are better to be aliased, regardless of the name
             Ti::Struct { .. } | Ti::Array { .. } => true,
             // handle types may be different, depending on the global var access, so we always inline them
-            Ti::Image { .. } | Ti::Sampler { .. } => false,
+            Ti::Image { .. }
+            | Ti::Sampler { .. }
+            | Ti::AccelerationStructure
+            | Ti::RayQuery
+            | Ti::BindingArray { .. } => false,
         }
     }
 }
@@ -407,7 +517,9 @@ impl crate::Constant {
     // Returns `true` if we need to emit an alias for this constant.
     fn needs_alias(&self) -> bool {
         match self.inner {
-            crate::ConstantInner::Scalar { .. } => self.name.is_some(),
+            crate::ConstantInner::Scalar { .. } => {
+                self.name.is_some() || self.specialization.is_some()
+            }
             crate::ConstantInner::Composite { .. } => true,
         }
     }
@@ -432,10 +544,34 @@ impl<'a> ExpressionContext<'a> {
     }
 }
 
+/// Names of the outputs of a task or mesh entry point.
+enum MeshStage<'a> {
+    /// An `[[object]]` function, which launches the mesh grid through
+    /// the `mesh_grid_properties` argument instead of returning.
+    Task { grid: String },
+    /// A `[[mesh]]` function, which writes its outputs through the `metal::mesh` argument.
+    Mesh {
+        mesh: String,
+        info: &'a crate::MeshStageInfo,
+        vertex_struct: String,
+        primitive_struct: String,
+    },
+}
+
+impl MeshStage<'_> {
+    fn task_grid(&self) -> Option<&str> {
+        match *self {
+            MeshStage::Task { ref grid } => Some(grid),
+            MeshStage::Mesh { .. } => None,
+        }
+    }
+}
+
 struct StatementContext<'a> {
     expression: ExpressionContext<'a>,
     mod_info: &'a ModuleInfo,
     result_struct: Option<&'a str>,
+    mesh_stage: Option<&'a MeshStage<'a>>,
 }
 
 impl<W: Write> Writer<W> {
@@ -600,27 +736,31 @@ impl<W: Write> Writer<W> {
         context: &ExpressionContext,
     ) -> Result<(), Error> {
         match context.module.types[ty].inner {
-            crate::TypeInner::Scalar { width: 4, kind } if components.len() == 1 => {
-                write!(self.out, "{}", scalar_kind_string(kind))?;
+            crate::TypeInner::Scalar { width, kind } if components.len() == 1 => {
+                write!(self.out, "{}", scalar_kind_string(kind, width))?;
                 self.put_call_parameters(components.iter().cloned(), context)?;
             }
-            crate::TypeInner::Vector { size, kind, .. } => {
+            crate::TypeInner::Vector { size, kind, width } => {
                 write!(
                     self.out,
                     "{}::{}{}",
                     NAMESPACE,
-                    scalar_kind_string(kind),
+                    scalar_kind_string(kind, width),
                     vector_size_str(size)
                 )?;
                 self.put_call_parameters(components.iter().cloned(), context)?;
             }
-            crate::TypeInner::Matrix { columns, rows, .. } => {
+            crate::TypeInner::Matrix {
+                columns,
+                rows,
+                width,
+            } => {
                 let kind = crate::ScalarKind::Float;
                 write!(
                     self.out,
                     "{}::{}{}x{}",
                     NAMESPACE,
-                    scalar_kind_string(kind),
+                    scalar_kind_string(kind, width),
                     vector_size_str(columns),
                     vector_size_str(rows)
                 )?;
@@ -641,6 +781,63 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
+    /// Write the committed intersection of a ray query as a `RayIntersection`.
+    fn put_ray_intersection(
+        &mut self,
+        query: Handle<crate::Expression>,
+        ty: Handle<crate::Type>,
+        context: &ExpressionContext,
+    ) -> Result<(), Error> {
+        const FIELDS: [&str; 11] = [
+            "type",
+            "distance",
+            "user_instance_id",
+            "instance_id",
+            "",
+            "geometry_id",
+            "primitive_id",
+            "triangle_barycentric_coord",
+            "triangle_front_facing",
+            "object_to_world_transform",
+            "world_to_object_transform",
+        ];
+        let members = match context.module.types[ty].inner {
+            crate::TypeInner::Struct { ref members, .. } => members,
+            _ => return Err(Error::Validation),
+        };
+        write!(self.out, "{} {{", &self.names[&NameKey::Type(ty)])?;
+        let mut last_offset = 0;
+        for (index, (member, &field)) in members.iter().zip(FIELDS.iter()).enumerate() {
+            if index != 0 {
+                write!(self.out, ", ")?;
+            }
+            // skip the padding added by `write_type_defs`
+            if member.offset > last_offset {
+                write!(self.out, "{{}}, ")?;
+            }
+            last_offset = member.offset
+                + context.module.types[member.ty]
+                    .inner
+                    .span(&context.module.constants);
+            match field {
+                // Metal has no shader binding tables
+                "" => write!(self.out, "{{}}")?,
+                // the intersection types match the committed kinds
+                "type" => {
+                    write!(self.out, "static_cast<{}::uint>(", NAMESPACE)?;
+                    self.put_expression(query, context, true)?;
+                    write!(self.out, ".intersection.type)")?;
+                }
+                _ => {
+                    self.put_expression(query, context, true)?;
+                    write!(self.out, ".intersection.{}", field)?;
+                }
+            }
+        }
+        write!(self.out, "}}")?;
+        Ok(())
+    }
+
     fn put_array_length(
         &mut self,
         expr: Handle<crate::Expression>,
@@ -763,6 +960,9 @@ impl<W: Write> Writer<W> {
                     crate::TypeInner::Array { .. } => {
                         write!(self.out, ".{}[{}]", WRAPPED_ARRAY_FIELD, index)?;
                     }
+                    crate::TypeInner::BindingArray { .. } => {
+                        write!(self.out, "[{}]", index)?;
+                    }
                     _ => {
                         // unexpected indexing, should fail validation
                     }
@@ -778,11 +978,11 @@ impl<W: Write> Writer<W> {
                 write!(self.out, "{}", coco)?;
             }
             crate::Expression::Splat { size, value } => {
-                let scalar_kind = match *context.resolve_type(value) {
-                    crate::TypeInner::Scalar { kind, .. } => kind,
+                let (scalar_kind, scalar_width) = match *context.resolve_type(value) {
+                    crate::TypeInner::Scalar { kind, width } => (kind, width),
                     _ => return Err(Error::Validation),
                 };
-                let scalar = scalar_kind_string(scalar_kind);
+                let scalar = scalar_kind_string(scalar_kind, scalar_width);
                 let size = vector_size_str(size);
 
                 write!(self.out, "{}::{}{}(", NAMESPACE, scalar, size)?;
@@ -857,12 +1057,20 @@ impl<W: Write> Writer<W> {
                     _ => None,
                 };
 
-                if let Some(scalar_kind) = wrap_packed_vec_scalar_kind {
+                let is_atomic = context
+                    .resolve_type(pointer)
+                    .is_atomic_pointer(&context.module.types);
+
+                if is_atomic {
+                    write!(self.out, "{}::atomic_load_explicit(&", NAMESPACE)?;
+                    self.put_expression(pointer, context, true)?;
+                    write!(self.out, ", {}::memory_order_relaxed)", NAMESPACE)?;
+                } else if let Some(scalar_kind) = wrap_packed_vec_scalar_kind {
                     write!(
                         self.out,
                         "{}::{}3(",
                         NAMESPACE,
-                        scalar_kind_string(scalar_kind)
+                        scalar_kind_string(scalar_kind, 4)
                     )?;
                     self.put_expression(pointer, context, true)?;
                     write!(self.out, ")")?;
@@ -876,15 +1084,18 @@ impl<W: Write> Writer<W> {
             crate::Expression::ImageSample {
                 image,
                 sampler,
+                gather,
                 coordinate,
                 array_index,
                 offset,
                 level,
                 depth_ref,
             } => {
-                let op = match depth_ref {
-                    Some(_) => "sample_compare",
-                    None => "sample",
+                let op = match (gather, depth_ref) {
+                    (None, Some(_)) => "sample_compare",
+                    (None, None) => "sample",
+                    (Some(_), Some(_)) => "gather_compare",
+                    (Some(_), None) => "gather",
                 };
                 self.put_expression(image, context, false)?;
                 write!(self.out, ".{}(", op)?;
@@ -900,7 +1111,9 @@ impl<W: Write> Writer<W> {
                     self.put_expression(dref, context, true)?;
                 }
 
-                self.put_image_sample_level(image, level, context)?;
+                if gather.is_none() {
+                    self.put_image_sample_level(image, level, context)?;
+                }
 
                 if let Some(constant) = offset {
                     let coco = ConstantContext {
@@ -911,6 +1124,25 @@ impl<W: Write> Writer<W> {
                     };
                     write!(self.out, ", {}", coco)?;
                 }
+                match gather {
+                    None | Some(crate::SwizzleComponent::X) => {}
+                    Some(component) => {
+                        let is_cube_map = match *context.resolve_type(image) {
+                            crate::TypeInner::Image {
+                                dim: crate::ImageDimension::Cube,
+                                ..
+                            } => true,
+                            _ => false,
+                        };
+                        // the component goes after the offset, which is only
+                        // available for 2D images
+                        if offset.is_none() && !is_cube_map {
+                            write!(self.out, ", {}::int2(0)", NAMESPACE)?;
+                        }
+                        let letter = COMPONENTS[component as usize];
+                        write!(self.out, ", {}::component::{}", NAMESPACE, letter)?;
+                    }
+                }
                 write!(self.out, ")")?;
             }
             crate::Expression::ImageLoad {
@@ -1047,6 +1279,7 @@ impl<W: Write> Writer<W> {
                 arg,
                 arg1,
                 arg2,
+                arg3,
             } => {
                 use crate::MathFunction as Mf;
 
@@ -1110,8 +1343,25 @@ impl<W: Write> Writer<W> {
                     Mf::Transpose => "transpose",
                     Mf::Determinant => "determinant",
                     // bits
+                    Mf::CountTrailingZeros => "ctz",
+                    Mf::CountLeadingZeros => "clz",
                     Mf::CountOneBits => "popcount",
                     Mf::ReverseBits => "reverse_bits",
+                    Mf::ExtractBits => "extract_bits",
+                    Mf::InsertBits => "insert_bits",
+                    Mf::FirstTrailingBit | Mf::FirstLeadingBit => "",
+                    // data packing
+                    Mf::Pack4x8snorm => "pack_float_to_snorm4x8",
+                    Mf::Pack4x8unorm => "pack_float_to_unorm4x8",
+                    Mf::Pack2x16snorm => "pack_float_to_snorm2x16",
+                    Mf::Pack2x16unorm => "pack_float_to_unorm2x16",
+                    Mf::Pack2x16float => "",
+                    // data unpacking
+                    Mf::Unpack4x8snorm => "unpack_snorm4x8_to_float",
+                    Mf::Unpack4x8unorm => "unpack_unorm4x8_to_float",
+                    Mf::Unpack2x16snorm => "unpack_snorm2x16_to_float",
+                    Mf::Unpack2x16unorm => "unpack_unorm2x16_to_float",
+                    Mf::Unpack2x16float => "",
                 };
 
                 if fun == Mf::Distance && scalar_argument {
@@ -1120,9 +1370,44 @@ impl<W: Write> Writer<W> {
                     write!(self.out, " - ")?;
                     self.put_expression(arg1.unwrap(), context, false)?;
                     write!(self.out, ")")?;
+                } else if fun == Mf::FirstTrailingBit {
+                    // `ctz` returns 32 for zero, which needs to become -1
+                    write!(self.out, "((({}::ctz(", NAMESPACE)?;
+                    self.put_expression(arg, context, true)?;
+                    write!(self.out, ") + 1) % 33) - 1)")?;
+                } else if fun == Mf::FirstLeadingBit {
+                    // `clz` returns 32 for zero, giving -1 here,
+                    // and negative numbers look for the first zero bit instead
+                    let signed =
+                        context.resolve_type(arg).scalar_kind() == Some(crate::ScalarKind::Sint);
+                    write!(self.out, "(31 - {}::clz(", NAMESPACE)?;
+                    if signed {
+                        write!(self.out, "{}::select(", NAMESPACE)?;
+                        self.put_expression(arg, context, true)?;
+                        write!(self.out, ", ~")?;
+                        self.put_expression(arg, context, false)?;
+                        write!(self.out, ", ")?;
+                        self.put_expression(arg, context, false)?;
+                        write!(self.out, " < 0)")?;
+                    } else {
+                        self.put_expression(arg, context, true)?;
+                    }
+                    write!(self.out, "))")?;
+                } else if fun == Mf::Pack2x16float {
+                    // there is no dedicated function, but halves can be reinterpreted
+                    write!(self.out, "as_type<uint>(half2(")?;
+                    self.put_expression(arg, context, false)?;
+                    write!(self.out, "))")?;
+                } else if fun == Mf::Unpack2x16float {
+                    write!(self.out, "float2(as_type<half2>(")?;
+                    self.put_expression(arg, context, false)?;
+                    write!(self.out, "))")?;
                 } else {
                     write!(self.out, "{}::{}", NAMESPACE, fun_name)?;
-                    self.put_call_parameters(iter::once(arg).chain(arg1).chain(arg2), context)?;
+                    self.put_call_parameters(
+                        iter::once(arg).chain(arg1).chain(arg2).chain(arg3),
+                        context,
+                    )?;
                 }
             }
             crate::Expression::As {
@@ -1130,17 +1415,23 @@ impl<W: Write> Writer<W> {
                 kind,
                 convert,
             } => {
-                let scalar = scalar_kind_string(kind);
                 let (size, width) = match *context.resolve_type(expr) {
                     crate::TypeInner::Scalar { width, .. } => ("", width),
                     crate::TypeInner::Vector { size, width, .. } => (vector_size_str(size), width),
                     _ => return Err(Error::Validation),
                 };
+                let scalar = scalar_kind_string(kind, convert.unwrap_or(width));
                 let op = match convert {
                     Some(w) if w == width => "static_cast",
                     Some(8) if kind == crate::ScalarKind::Float => {
                         return Err(Error::CapabilityNotSupported(Capabilities::FLOAT64))
                     }
+                    Some(2) | Some(4) if kind == crate::ScalarKind::Float => "static_cast",
+                    Some(4) | Some(8)
+                        if kind == crate::ScalarKind::Sint || kind == crate::ScalarKind::Uint =>
+                    {
+                        "static_cast"
+                    }
                     Some(_) => return Err(Error::Validation),
                     None => "as_type",
                 };
@@ -1149,11 +1440,186 @@ impl<W: Write> Writer<W> {
                 write!(self.out, ")")?;
             }
             // has to be a named expression
-            crate::Expression::Call(_) => unreachable!(),
+            crate::Expression::Call(_)
+            | crate::Expression::AtomicResult { .. }
+            | crate::Expression::SubgroupBallotResult
+            | crate::Expression::SubgroupOperationResult { .. } => unreachable!(),
             crate::Expression::ArrayLength(expr) => {
                 self.put_array_length(expr, context)?;
             }
+            crate::Expression::RayQueryProceedResult => unreachable!(),
+            crate::Expression::RayQueryGetIntersection {
+                query,
+                committed,
+                ty,
+            } => {
+                if !committed {
+                    return Err(Error::FeatureNotImplemented(
+                        "candidate ray intersections".to_string(),
+                    ));
+                }
+                self.put_ray_intersection(query, ty, context)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the attributed structure that a mesh entry point uses for the
+    /// vertices or primitives of type `ty`.
+    fn put_mesh_output_struct(
+        &mut self,
+        struct_name: &str,
+        ty: Handle<crate::Type>,
+        module: &crate::Module,
+        options: &Options,
+        pipeline_options: &PipelineOptions,
+    ) -> Result<(), Error> {
+        let members = match module.types[ty].inner {
+            crate::TypeInner::Struct { ref members, .. } => members,
+            _ => return Err(Error::Validation),
+        };
+        writeln!(self.out, "struct {} {{", struct_name)?;
+        for (index, member) in members.iter().enumerate() {
+            if is_omitted_output(member.binding.as_ref(), pipeline_options) {
+                continue;
+            }
+            let ty_name = TypeContext {
+                handle: member.ty,
+                arena: &module.types,
+                names: &self.names,
+                access: crate::StorageAccess::empty(),
+                first_time: false,
+            };
+            let name = &self.names[&NameKey::StructMember(ty, index as u32)];
+            let binding = member.binding.as_ref().ok_or(Error::Validation)?;
+            let resolved = options.resolve_local_binding(binding, LocationMode::Intermediate)?;
+            write!(self.out, "{}{} {}", INDENT, ty_name, name)?;
+            resolved.try_fmt_decorated(&mut self.out, "")?;
+            writeln!(self.out, ";")?;
+        }
+        writeln!(self.out, "}};")?;
+        Ok(())
+    }
+
+    /// Write a ray query operation.
+    ///
+    /// Metal intersectors find the committed intersection in one go,
+    /// so the query proceeds only once, without any candidates.
+    fn put_ray_query(
+        &mut self,
+        level: Level,
+        query: Handle<crate::Expression>,
+        fun: &crate::RayQueryFunction,
+        context: &ExpressionContext,
+    ) -> Result<(), Error> {
+        match *fun {
+            crate::RayQueryFunction::Initialize {
+                acceleration_structure,
+                descriptor,
+            } => {
+                let desc_ty = context.info[descriptor]
+                    .ty
+                    .handle()
+                    .ok_or(Error::Validation)?;
+                let field = |index: u32| self.names[&NameKey::StructMember(desc_ty, index)].clone();
+                let (flags, cull_mask, t_min, t_max, origin, dir) =
+                    (field(0), field(1), field(2), field(3), field(4), field(5));
+                let rt = format!("{}::raytracing", NAMESPACE);
+                let lcomp = level.next();
+
+                writeln!(self.out, "{}{{", level)?;
+                write!(self.out, "{}const auto _desc = ", lcomp)?;
+                self.put_expression(descriptor, context, true)?;
+                writeln!(self.out, ";")?;
+                write!(self.out, "{}thread {}& _rq = ", lcomp, RAY_QUERY_TYPE)?;
+                self.put_expression(query, context, true)?;
+                writeln!(self.out, ";")?;
+                writeln!(
+                    self.out,
+                    "{}_rq.intersector.assume_geometry_type({}::geometry_type::triangle);",
+                    lcomp, rt
+                )?;
+                writeln!(
+                    self.out,
+                    "{}_rq.intersector.set_opacity_cull_mode((_desc.{} & 64) != 0 ? {}::opacity_cull_mode::opaque : (_desc.{} & 128) != 0 ? {}::opacity_cull_mode::non_opaque : {}::opacity_cull_mode::none);",
+                    lcomp, flags, rt, flags, rt, rt
+                )?;
+                writeln!(
+                    self.out,
+                    "{}_rq.intersector.force_opacity((_desc.{} & 1) != 0 ? {}::forced_opacity::opaque : (_desc.{} & 2) != 0 ? {}::forced_opacity::non_opaque : {}::forced_opacity::none);",
+                    lcomp, flags, rt, flags, rt, rt
+                )?;
+                writeln!(
+                    self.out,
+                    "{}_rq.intersector.accept_any_intersection((_desc.{} & 4) != 0);",
+                    lcomp, flags
+                )?;
+                write!(
+                    self.out,
+                    "{}_rq.intersection = _rq.intersector.intersect({}::ray(_desc.{}, _desc.{}, _desc.{}, _desc.{}), ",
+                    lcomp, rt, origin, dir, t_min, t_max
+                )?;
+                self.put_expression(acceleration_structure, context, true)?;
+                writeln!(self.out, ", _desc.{});", cull_mask)?;
+                writeln!(self.out, "{}_rq.ready = true;", lcomp)?;
+                writeln!(self.out, "{}}}", level)?;
+            }
+            crate::RayQueryFunction::Proceed { result } => {
+                let name = format!("{}{}", BAKE_PREFIX, result.index());
+                write!(self.out, "{}", level)?;
+                self.start_baking_expression(result, context, &name)?;
+                self.named_expressions.insert(result, name);
+                self.put_expression(query, context, true)?;
+                writeln!(self.out, ".ready;")?;
+                write!(self.out, "{}", level)?;
+                self.put_expression(query, context, true)?;
+                writeln!(self.out, ".ready = false;")?;
+            }
+            crate::RayQueryFunction::Terminate => {
+                write!(self.out, "{}", level)?;
+                self.put_expression(query, context, true)?;
+                writeln!(self.out, ".ready = false;")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a vertex or primitive of a mesh entry point, converting the
+    /// IR structure into the attributed output structure `struct_name`.
+    fn put_mesh_output_store(
+        &mut self,
+        level: Level,
+        method: &str,
+        struct_name: &str,
+        index: Handle<crate::Expression>,
+        value: Handle<crate::Expression>,
+        context: &ExpressionContext,
+    ) -> Result<(), Error> {
+        let ty = context.info[value].ty.handle().ok_or(Error::Validation)?;
+        let members = match context.module.types[ty].inner {
+            crate::TypeInner::Struct { ref members, .. } => members,
+            _ => return Err(Error::Validation),
+        };
+        let lcomp = level.next();
+        writeln!(self.out, "{}{{", level)?;
+        write!(self.out, "{}const auto _tmp = ", lcomp)?;
+        self.put_expression(value, context, true)?;
+        writeln!(self.out, ";")?;
+        write!(self.out, "{}{}(", lcomp, method)?;
+        self.put_expression(index, context, true)?;
+        write!(self.out, ", {} {{", struct_name)?;
+        let mut is_first = true;
+        for (index, member) in members.iter().enumerate() {
+            if is_omitted_output(member.binding.as_ref(), context.pipeline_options) {
+                continue;
+            }
+            let comma = if is_first { "" } else { "," };
+            is_first = false;
+            let name = &self.names[&NameKey::StructMember(ty, index as u32)];
+            write!(self.out, "{} _tmp.{}", comma, name)?;
         }
+        writeln!(self.out, " }});")?;
+        writeln!(self.out, "{}}}", level)?;
         Ok(())
     }
 
@@ -1252,24 +1718,28 @@ impl<W: Write> Writer<W> {
                 };
                 write!(self.out, "{}", ty_name)?;
             }
-            TypeResolution::Value(crate::TypeInner::Scalar { kind, .. }) => {
-                write!(self.out, "{}", scalar_kind_string(kind))?;
+            TypeResolution::Value(crate::TypeInner::Scalar { kind, width }) => {
+                write!(self.out, "{}", scalar_kind_string(kind, width))?;
             }
-            TypeResolution::Value(crate::TypeInner::Vector { size, kind, .. }) => {
+            TypeResolution::Value(crate::TypeInner::Vector { size, kind, width }) => {
                 write!(
                     self.out,
                     "{}::{}{}",
                     NAMESPACE,
-                    scalar_kind_string(kind),
+                    scalar_kind_string(kind, width),
                     vector_size_str(size)
                 )?;
             }
-            TypeResolution::Value(crate::TypeInner::Matrix { columns, rows, .. }) => {
+            TypeResolution::Value(crate::TypeInner::Matrix {
+                columns,
+                rows,
+                width,
+            }) => {
                 write!(
                     self.out,
                     "{}::{}{}x{}",
                     NAMESPACE,
-                    scalar_kind_string(crate::ScalarKind::Float),
+                    scalar_kind_string(crate::ScalarKind::Float, width),
                     vector_size_str(columns),
                     vector_size_str(rows),
                 )?;
@@ -1402,12 +1872,20 @@ impl<W: Write> Writer<W> {
                 crate::Statement::Return {
                     value: Some(expr_handle),
                 } => {
-                    self.put_return_value(
-                        level.clone(),
-                        expr_handle,
-                        context.result_struct,
-                        &context.expression,
-                    )?;
+                    if let Some(grid) = context.mesh_stage.and_then(MeshStage::task_grid) {
+                        // object functions launch the mesh grid instead of returning a value
+                        write!(self.out, "{}{}.set_threadgroups_per_grid(", level, grid)?;
+                        self.put_expression(expr_handle, &context.expression, true)?;
+                        writeln!(self.out, ");")?;
+                        writeln!(self.out, "{}return;", level)?;
+                    } else {
+                        self.put_return_value(
+                            level.clone(),
+                            expr_handle,
+                            context.result_struct,
+                            &context.expression,
+                        )?;
+                    }
                 }
                 crate::Statement::Return { value: None } => {
                     writeln!(self.out, "{}return;", level)?;
@@ -1456,7 +1934,18 @@ impl<W: Write> Writer<W> {
                             }
                             _ => None,
                         };
+                    let is_atomic = pointer_info
+                        .ty
+                        .inner_with(&context.expression.module.types)
+                        .is_atomic_pointer(&context.expression.module.types);
                     match array_size {
+                        _ if is_atomic => {
+                            write!(self.out, "{}{}::atomic_store_explicit(&", level, NAMESPACE)?;
+                            self.put_expression(pointer, &context.expression, true)?;
+                            write!(self.out, ", ")?;
+                            self.put_expression(value, &context.expression, true)?;
+                            writeln!(self.out, ", {}::memory_order_relaxed);", NAMESPACE)?;
+                        }
                         Some(const_handle) => {
                             let size = context.expression.module.constants[const_handle]
                                 .to_array_length()
@@ -1544,6 +2033,220 @@ impl<W: Write> Writer<W> {
                     // done
                     writeln!(self.out, ");")?;
                 }
+                crate::Statement::Atomic {
+                    pointer,
+                    ref fun,
+                    value,
+                    result,
+                } => {
+                    let res_name = format!("{}{}", BAKE_PREFIX, result.index());
+                    match *fun {
+                        crate::AtomicFunction::Exchange { compare: Some(cmp) } => {
+                            // Metal returns the success flag, and writes the original
+                            // value into the comparison operand, so we bake the latter,
+                            // and put them together in the result.
+                            let scalar = match *context.expression.resolve_type(cmp) {
+                                crate::TypeInner::Scalar { kind, width } => {
+                                    scalar_kind_string(kind, width)
+                                }
+                                _ => return Err(Error::Validation),
+                            };
+                            let old_name = format!("{}_old", res_name);
+                            let exchanged_name = format!("{}_exchanged", res_name);
+                            write!(self.out, "{}{} {} = ", level, scalar, old_name)?;
+                            self.put_expression(cmp, &context.expression, true)?;
+                            writeln!(self.out, ";")?;
+                            write!(
+                                self.out,
+                                "{}bool {} = {}::atomic_compare_exchange_weak_explicit(&",
+                                level, exchanged_name, NAMESPACE
+                            )?;
+                            self.put_expression(pointer, &context.expression, true)?;
+                            write!(self.out, ", &{}, ", old_name)?;
+                            self.put_expression(value, &context.expression, true)?;
+                            writeln!(
+                                self.out,
+                                ", {}::memory_order_relaxed, {}::memory_order_relaxed);",
+                                NAMESPACE, NAMESPACE
+                            )?;
+                            write!(self.out, "{}", level)?;
+                            self.start_baking_expression(result, &context.expression, &res_name)?;
+                            writeln!(
+                                self.out,
+                                "{}::{}2({}, static_cast<{}>({}));",
+                                NAMESPACE, scalar, old_name, scalar, exchanged_name
+                            )?;
+                        }
+                        _ => {
+                            write!(self.out, "{}", level)?;
+                            self.start_baking_expression(result, &context.expression, &res_name)?;
+                            let fun_str = fun.to_msl();
+                            write!(self.out, "{}::atomic_{}_explicit(&", NAMESPACE, fun_str)?;
+                            self.put_expression(pointer, &context.expression, true)?;
+                            write!(self.out, ", ")?;
+                            self.put_expression(value, &context.expression, true)?;
+                            writeln!(self.out, ", {}::memory_order_relaxed);", NAMESPACE)?;
+                        }
+                    }
+                    self.named_expressions.insert(result, res_name);
+                }
+                crate::Statement::SubgroupBallot { result, predicate } => {
+                    let res_name = format!("{}{}", BAKE_PREFIX, result.index());
+                    write!(self.out, "{}", level)?;
+                    self.start_baking_expression(result, &context.expression, &res_name)?;
+                    self.named_expressions.insert(result, res_name);
+                    // `simd_vote` converts to a 64-bit mask, which we split into
+                    // the lower two components of the ballot
+                    write!(self.out, "{}::uint4(", NAMESPACE)?;
+                    for shift in &["& 0xFFFFFFFF", ">> 32"] {
+                        write!(self.out, "(uint64_t){}::simd_ballot(", NAMESPACE)?;
+                        match predicate {
+                            Some(predicate) => {
+                                self.put_expression(predicate, &context.expression, true)?
+                            }
+                            None => write!(self.out, "true")?,
+                        }
+                        write!(self.out, ") {}, ", shift)?;
+                    }
+                    writeln!(self.out, "0, 0);")?;
+                }
+                crate::Statement::SubgroupCollectiveOperation {
+                    op,
+                    collective_op,
+                    argument,
+                    result,
+                } => {
+                    use crate::{CollectiveOperation as Co, SubgroupOperation as So};
+                    let fun_str = match (collective_op, op) {
+                        (Co::Reduce, So::All) => "simd_all",
+                        (Co::Reduce, So::Any) => "simd_any",
+                        (Co::Reduce, So::Add) => "simd_sum",
+                        (Co::Reduce, So::Mul) => "simd_product",
+                        (Co::Reduce, So::Min) => "simd_min",
+                        (Co::Reduce, So::Max) => "simd_max",
+                        (Co::Reduce, So::And) => "simd_and",
+                        (Co::Reduce, So::Or) => "simd_or",
+                        (Co::Reduce, So::Xor) => "simd_xor",
+                        (Co::InclusiveScan, So::Add) => "simd_prefix_inclusive_sum",
+                        (Co::InclusiveScan, So::Mul) => "simd_prefix_inclusive_product",
+                        (Co::ExclusiveScan, So::Add) => "simd_prefix_exclusive_sum",
+                        (Co::ExclusiveScan, So::Mul) => "simd_prefix_exclusive_product",
+                        _ => {
+                            return Err(Error::UnsupportedCall(format!(
+                                "{:?} {:?}",
+                                collective_op, op
+                            )))
+                        }
+                    };
+                    let res_name = format!("{}{}", BAKE_PREFIX, result.index());
+                    write!(self.out, "{}", level)?;
+                    self.start_baking_expression(result, &context.expression, &res_name)?;
+                    self.named_expressions.insert(result, res_name);
+                    write!(self.out, "{}::{}(", NAMESPACE, fun_str)?;
+                    self.put_expression(argument, &context.expression, true)?;
+                    writeln!(self.out, ");")?;
+                }
+                crate::Statement::SubgroupGather {
+                    ref mode,
+                    argument,
+                    result,
+                } => {
+                    let res_name = format!("{}{}", BAKE_PREFIX, result.index());
+                    write!(self.out, "{}", level)?;
+                    self.start_baking_expression(result, &context.expression, &res_name)?;
+                    self.named_expressions.insert(result, res_name);
+                    let fun_str = match *mode {
+                        crate::GatherMode::BroadcastFirst => "simd_broadcast_first",
+                        crate::GatherMode::Broadcast(_) => "simd_broadcast",
+                        crate::GatherMode::Shuffle(_) => "simd_shuffle",
+                        crate::GatherMode::ShuffleDown(_) => "simd_shuffle_down",
+                        crate::GatherMode::ShuffleUp(_) => "simd_shuffle_up",
+                        crate::GatherMode::ShuffleXor(_) => "simd_shuffle_xor",
+                    };
+                    write!(self.out, "{}::{}(", NAMESPACE, fun_str)?;
+                    self.put_expression(argument, &context.expression, true)?;
+                    if let Some(index) = mode.index() {
+                        write!(self.out, ", ")?;
+                        self.put_expression(index, &context.expression, true)?;
+                    }
+                    writeln!(self.out, ");")?;
+                }
+                crate::Statement::MeshFunction(ref fun) => {
+                    let (mesh, info, vertex_struct, primitive_struct) = match context.mesh_stage {
+                        Some(&MeshStage::Mesh {
+                            ref mesh,
+                            info,
+                            ref vertex_struct,
+                            ref primitive_struct,
+                        }) => (mesh, info, vertex_struct, primitive_struct),
+                        _ => return Err(Error::Validation),
+                    };
+                    match *fun {
+                        // the vertex count is implied by the written vertices
+                        crate::MeshFunction::SetMeshOutputs {
+                            primitive_count, ..
+                        } => {
+                            write!(self.out, "{}{}.set_primitive_count(", level, mesh)?;
+                            self.put_expression(primitive_count, &context.expression, true)?;
+                            writeln!(self.out, ");")?;
+                        }
+                        crate::MeshFunction::SetVertex { index, value } => {
+                            self.put_mesh_output_store(
+                                level.clone(),
+                                &format!("{}.set_vertex", mesh),
+                                vertex_struct,
+                                index,
+                                value,
+                                &context.expression,
+                            )?;
+                        }
+                        crate::MeshFunction::SetPrimitive { index, value } => {
+                            self.put_mesh_output_store(
+                                level.clone(),
+                                &format!("{}.set_primitive", mesh),
+                                primitive_struct,
+                                index,
+                                value,
+                                &context.expression,
+                            )?;
+                        }
+                        crate::MeshFunction::SetIndices { index, value } => {
+                            let count = match info.topology {
+                                crate::MeshOutputTopology::Points => 1,
+                                crate::MeshOutputTopology::Lines => 2,
+                                crate::MeshOutputTopology::Triangles => 3,
+                            };
+                            if count == 1 {
+                                write!(self.out, "{}{}.set_index(", level, mesh)?;
+                                self.put_expression(index, &context.expression, true)?;
+                                write!(self.out, ", ")?;
+                                self.put_expression(value, &context.expression, true)?;
+                                writeln!(self.out, ");")?;
+                            } else {
+                                // Metal takes a flat index buffer
+                                let lcomp = level.next();
+                                writeln!(self.out, "{}{{", level)?;
+                                write!(self.out, "{}const {}::uint _base = ", lcomp, NAMESPACE)?;
+                                self.put_expression(index, &context.expression, true)?;
+                                writeln!(self.out, " * {}u;", count)?;
+                                write!(self.out, "{}const auto _tmp = ", lcomp)?;
+                                self.put_expression(value, &context.expression, true)?;
+                                writeln!(self.out, ";")?;
+                                for (i, component) in COMPONENTS[..count].iter().enumerate() {
+                                    writeln!(
+                                        self.out,
+                                        "{}{}.set_index(_base + {}u, _tmp.{});",
+                                        lcomp, mesh, i, component
+                                    )?;
+                                }
+                                writeln!(self.out, "{}}}", level)?;
+                            }
+                        }
+                    }
+                }
+                crate::Statement::RayQuery { query, ref fun } => {
+                    self.put_ray_query(level.clone(), query, fun, &context.expression)?;
+                }
             }
         }
 
@@ -1570,10 +2273,37 @@ impl<W: Write> Writer<W> {
         self.namer.reset(module, RESERVED, &[], &mut self.names);
         self.runtime_sized_buffers.clear();
 
+        let uses_ray_query = module.types.iter().any(|(_, ty)| match ty.inner {
+            crate::TypeInner::AccelerationStructure | crate::TypeInner::RayQuery => true,
+            _ => false,
+        });
+        if uses_ray_query && options.lang_version < (2, 4) {
+            return Err(Error::CapabilityNotSupported(Capabilities::RAY_QUERY));
+        }
+        if !module.derived_constants.is_empty() {
+            return Err(Error::DerivedConstants);
+        }
+
         writeln!(self.out, "#include <metal_stdlib>")?;
         writeln!(self.out, "#include <simd/simd.h>")?;
+        if uses_ray_query {
+            writeln!(self.out, "#include <metal_raytracing>")?;
+        }
         writeln!(self.out)?;
 
+        if uses_ray_query {
+            writeln!(self.out, "struct {} {{", RAY_QUERY_TYPE)?;
+            writeln!(self.out, "{}{} intersector;", INDENT, RAY_QUERY_INTERSECTOR)?;
+            writeln!(
+                self.out,
+                "{}{}::result_type intersection;",
+                INDENT, RAY_QUERY_INTERSECTOR
+            )?;
+            writeln!(self.out, "{}bool ready = false;", INDENT)?;
+            writeln!(self.out, "}};")?;
+            writeln!(self.out)?;
+        }
+
         {
             let mut indices = vec![];
             for (handle, var) in module.global_variables.iter() {
@@ -1624,11 +2354,13 @@ impl<W: Write> Writer<W> {
 
                     match size {
                         crate::ArraySize::Constant(const_handle) => {
+                            // function constants can't size arrays,
+                            // so specializable lengths use their default value
                             let coco = ConstantContext {
                                 handle: const_handle,
                                 arena: &module.constants,
                                 names: &self.names,
-                                first_time: false,
+                                first_time: module.constants[const_handle].specialization.is_some(),
                             };
 
                             writeln!(self.out, "struct {} {{", name)?;
@@ -1668,7 +2400,7 @@ impl<W: Write> Writer<W> {
                                     self.out,
                                     "{}packed_{}3 {};",
                                     INDENT,
-                                    scalar_kind_string(kind),
+                                    scalar_kind_string(kind, 4),
                                     member_name
                                 )?;
                             }
@@ -1714,26 +2446,17 @@ impl<W: Write> Writer<W> {
     fn write_scalar_constants(&mut self, module: &crate::Module) -> Result<(), Error> {
         for (handle, constant) in module.constants.iter() {
             match constant.inner {
-                crate::ConstantInner::Scalar {
-                    width: _,
-                    ref value,
-                } if constant.name.is_some() => {
-                    debug_assert!(constant.needs_alias());
-                    write!(self.out, "constexpr constant ")?;
-                    match *value {
-                        crate::ScalarValue::Sint(_) => {
-                            write!(self.out, "int")?;
-                        }
-                        crate::ScalarValue::Uint(_) => {
-                            write!(self.out, "unsigned")?;
-                        }
+                crate::ConstantInner::Scalar { width, ref value } if constant.needs_alias() => {
+                    let ty_name = match *value {
+                        crate::ScalarValue::Sint(_) if width == 8 => "long",
+                        crate::ScalarValue::Sint(_) => "int",
+                        crate::ScalarValue::Uint(_) if width == 8 => "ulong",
+                        crate::ScalarValue::Uint(_) => "unsigned",
                         crate::ScalarValue::Float(_) => {
-                            write!(self.out, "float")?;
+                            scalar_kind_string(crate::ScalarKind::Float, width)
                         }
-                        crate::ScalarValue::Bool(_) => {
-                            write!(self.out, "bool")?;
-                        }
-                    }
+                        crate::ScalarValue::Bool(_) => "bool",
+                    };
                     let name = &self.names[&NameKey::Constant(handle)];
                     let coco = ConstantContext {
                         handle,
@@ -1741,7 +2464,29 @@ impl<W: Write> Writer<W> {
                         names: &self.names,
                         first_time: true,
                     };
-                    writeln!(self.out, " {} = {};", name, coco)?;
+                    match constant.specialization {
+                        // the function constant falls back to the default value
+                        // if the pipeline doesn't provide one
+                        Some(id) => {
+                            writeln!(
+                                self.out,
+                                "constant {} {}_fc [[function_constant({})]];",
+                                ty_name, name, id
+                            )?;
+                            writeln!(
+                                self.out,
+                                "constant {} {} = is_function_constant_defined({}_fc) ? {}_fc : {};",
+                                ty_name, name, name, name, coco
+                            )?;
+                        }
+                        None => {
+                            writeln!(
+                                self.out,
+                                "constexpr constant {} {} = {};",
+                                ty_name, name, coco
+                            )?;
+                        }
+                    }
                 }
                 _ => {}
             }
@@ -1977,6 +2722,7 @@ impl<W: Write> Writer<W> {
                 },
                 mod_info,
                 result_struct: None,
+                mesh_stage: None,
             };
             self.named_expressions.clear();
             self.put_block(Level(1), &fun.body, &context)?;
@@ -1992,6 +2738,26 @@ impl<W: Write> Writer<W> {
             let mut ep_error = None;
             let mut supports_array_length = false;
 
+            if let crate::ShaderStage::Task | crate::ShaderStage::Mesh = ep.stage {
+                if options.lang_version < (3, 0) {
+                    return Err(Error::CapabilityNotSupported(Capabilities::MESH_SHADER));
+                }
+            }
+            for &(built_in, version, capability) in [
+                (
+                    crate::BuiltIn::PrimitiveIndex,
+                    (2, 2),
+                    Capabilities::PRIMITIVE_INDEX,
+                ),
+                (crate::BuiltIn::ViewIndex, (2, 3), Capabilities::MULTIVIEW),
+            ]
+            .iter()
+            {
+                if options.lang_version < version && uses_built_in(module, ep, built_in) {
+                    return Err(Error::CapabilityNotSupported(capability));
+                }
+            }
+
             // skip this entry point if any global bindings are missing
             if !options.fake_missing_bindings {
                 for (var_handle, var) in module.global_variables.iter() {
@@ -2023,7 +2789,7 @@ impl<W: Write> Writer<W> {
                 info.entry_point_names.push(Err(err));
                 continue;
             }
-            let fun_name = &self.names[&NameKey::EntryPoint(ep_index as _)];
+            let fun_name = self.names[&NameKey::EntryPoint(ep_index as _)].clone();
             info.entry_point_names.push(Ok(fun_name.clone()));
 
             writeln!(self.out)?;
@@ -2045,6 +2811,14 @@ impl<W: Write> Writer<W> {
                 crate::ShaderStage::Compute { .. } => {
                     ("kernel", LocationMode::Uniform, LocationMode::Uniform)
                 }
+                crate::ShaderStage::Task => {
+                    ("[[object]]", LocationMode::Uniform, LocationMode::Uniform)
+                }
+                crate::ShaderStage::Mesh => (
+                    "[[mesh]]",
+                    LocationMode::Uniform,
+                    LocationMode::Intermediate,
+                ),
             };
 
             let mut argument_members = Vec::new();
@@ -2094,7 +2868,8 @@ impl<W: Write> Writer<W> {
 
             let result_member_name = self.namer.call("member");
             let result_type_name = match fun.result {
-                Some(ref result) => {
+                // task shaders launch the mesh grid instead of returning
+                Some(ref result) if ep.stage != crate::ShaderStage::Task => {
                     let mut result_members = Vec::new();
                     if let crate::TypeInner::Struct { ref members, .. } =
                         module.types[result.ty].inner
@@ -2155,8 +2930,64 @@ impl<W: Write> Writer<W> {
                     writeln!(self.out, "}};")?;
                     &stage_out_name
                 }
-                None => "void",
+                _ => "void",
+            };
+
+            let mesh_stage = match ep.stage {
+                crate::ShaderStage::Task => Some(MeshStage::Task {
+                    grid: self.namer.call("grid"),
+                }),
+                crate::ShaderStage::Mesh => {
+                    let info = ep.mesh_info.as_ref().ok_or(Error::Validation)?;
+                    let vertex_struct = format!("{}VertexOutput", fun_name);
+                    let primitive_struct = format!("{}PrimitiveOutput", fun_name);
+                    self.put_mesh_output_struct(
+                        &vertex_struct,
+                        info.vertex_output_type,
+                        module,
+                        options,
+                        pipeline_options,
+                    )?;
+                    let primitive_name = match info.primitive_output_type {
+                        Some(ty) => {
+                            self.put_mesh_output_struct(
+                                &primitive_struct,
+                                ty,
+                                module,
+                                options,
+                                pipeline_options,
+                            )?;
+                            primitive_struct.as_str()
+                        }
+                        None => "void",
+                    };
+                    let topology = match info.topology {
+                        crate::MeshOutputTopology::Points => "point",
+                        crate::MeshOutputTopology::Lines => "line",
+                        crate::MeshOutputTopology::Triangles => "triangle",
+                    };
+                    writeln!(
+                        self.out,
+                        "using {}Mesh = {}::mesh<{}, {}, {}, {}, {}::topology::{}>;",
+                        fun_name,
+                        NAMESPACE,
+                        vertex_struct,
+                        primitive_name,
+                        info.max_vertices,
+                        info.max_primitives,
+                        NAMESPACE,
+                        topology
+                    )?;
+                    Some(MeshStage::Mesh {
+                        mesh: self.namer.call("mesh"),
+                        info,
+                        vertex_struct,
+                        primitive_struct,
+                    })
+                }
+                _ => None,
             };
+
             writeln!(self.out, "{} {} {}(", em_str, result_type_name, fun_name)?;
 
             let mut is_first_argument = true;
@@ -2191,6 +3022,24 @@ impl<W: Write> Writer<W> {
                 write!(self.out, "{} {} {}", separator, ty_name, name)?;
                 resolved.try_fmt_decorated(&mut self.out, "\n")?;
             }
+            if let Some(ref mesh_stage) = mesh_stage {
+                let separator = if is_first_argument {
+                    is_first_argument = false;
+                    ' '
+                } else {
+                    ','
+                };
+                match *mesh_stage {
+                    MeshStage::Task { ref grid } => writeln!(
+                        self.out,
+                        "{} {}::mesh_grid_properties {}",
+                        separator, NAMESPACE, grid
+                    )?,
+                    MeshStage::Mesh { ref mesh, .. } => {
+                        writeln!(self.out, "{} {}Mesh {}", separator, fun_name, mesh)?
+                    }
+                }
+            }
             for (handle, var) in module.global_variables.iter() {
                 let usage = fun_info[handle];
                 if usage.is_empty() || var.class == crate::StorageClass::Private {
@@ -2201,7 +3050,7 @@ impl<W: Write> Writer<W> {
                     crate::StorageClass::PushConstant => {
                         options.resolve_push_constants(ep.stage).ok()
                     }
-                    crate::StorageClass::WorkGroup => None,
+                    crate::StorageClass::WorkGroup | crate::StorageClass::TaskPayload => None,
                     _ => options
                         .resolve_resource_binding(ep.stage, var.binding.as_ref().unwrap())
                         .ok(),
@@ -2231,6 +3080,9 @@ impl<W: Write> Writer<W> {
                 if let Some(resolved) = resolved {
                     resolved.try_fmt_decorated(&mut self.out, "")?;
                 }
+                if var.class == crate::StorageClass::TaskPayload {
+                    write!(self.out, " [[payload]]")?;
+                }
                 if let Some(value) = var.init {
                     let coco = ConstantContext {
                         handle: value,
@@ -2380,6 +3232,7 @@ impl<W: Write> Writer<W> {
                 },
                 mod_info,
                 result_struct: Some(&stage_out_name),
+                mesh_stage: mesh_stage.as_ref(),
             };
             self.named_expressions.clear();
             self.put_block(Level(1), &fun.body, &context)?;
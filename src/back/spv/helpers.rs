@@ -20,8 +20,8 @@ pub(super) fn string_to_words(input: &str) -> Vec<Word> {
     words
 }
 
-pub(super) fn map_storage_class(class: crate::StorageClass) -> spirv::StorageClass {
-    match class {
+pub(super) fn map_storage_class(class: crate::StorageClass) -> Word {
+    let class = match class {
         crate::StorageClass::Handle => spirv::StorageClass::UniformConstant,
         crate::StorageClass::Function => spirv::StorageClass::Function,
         crate::StorageClass::Private => spirv::StorageClass::Private,
@@ -29,7 +29,11 @@ pub(super) fn map_storage_class(class: crate::StorageClass) -> spirv::StorageCla
         crate::StorageClass::Uniform => spirv::StorageClass::Uniform,
         crate::StorageClass::WorkGroup => spirv::StorageClass::Workgroup,
         crate::StorageClass::PushConstant => spirv::StorageClass::PushConstant,
-    }
+        crate::StorageClass::TaskPayload => {
+            return super::mesh::STORAGE_CLASS_TASK_PAYLOAD_WORKGROUP_EXT
+        }
+    };
+    class as Word
 }
 
 pub(super) fn contains_builtin(
@@ -48,3 +52,82 @@ pub(super) fn contains_builtin(
         false // unreachable
     }
 }
+
+/// Returns true if the type has 2-byte floats anywhere in it.
+pub(super) fn contains_half_float(ty: Handle<crate::Type>, arena: &Arena<crate::Type>) -> bool {
+    match arena[ty].inner {
+        crate::TypeInner::Scalar { kind, width } | crate::TypeInner::Vector { kind, width, .. } => {
+            kind == crate::ScalarKind::Float && width == 2
+        }
+        crate::TypeInner::Matrix { width, .. } => width == 2,
+        crate::TypeInner::Array { base, .. } => contains_half_float(base, arena),
+        crate::TypeInner::Struct { ref members, .. } => members
+            .iter()
+            .any(|member| contains_half_float(member.ty, arena)),
+        _ => false,
+    }
+}
+
+/// Converts a float to the bits of the nearest IEEE 754 half-precision float,
+/// rounding ties to even.
+pub(super) fn f32_to_f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // infinity stays infinity, NaN stays quiet NaN
+        let nan_bit = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan_bit;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        // overflow
+        return sign | 0x7c00;
+    }
+
+    let (half, shift, mantissa) = if half_exponent <= 0 {
+        if half_exponent < -10 {
+            // underflow
+            return sign;
+        }
+        // subnormal, make the implicit leading bit explicit
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - half_exponent) as u32;
+        (sign | (mantissa >> shift) as u16, shift, mantissa)
+    } else {
+        let half = sign | ((half_exponent as u16) << 10) | (mantissa >> 13) as u16;
+        (half, 13, mantissa)
+    };
+
+    let round_bit = 1 << (shift - 1);
+    // round up if above the halfway point, or exactly on it with an odd result;
+    // a carry out of the mantissa correctly bumps the exponent
+    if mantissa & round_bit != 0 && mantissa & (3 * round_bit - 1) != 0 {
+        half + 1
+    } else {
+        half
+    }
+}
+
+#[test]
+fn half_float_bits() {
+    assert_eq!(f32_to_f16_bits(0.0), 0x0000);
+    assert_eq!(f32_to_f16_bits(-0.0), 0x8000);
+    assert_eq!(f32_to_f16_bits(1.0), 0x3c00);
+    assert_eq!(f32_to_f16_bits(-2.0), 0xc000);
+    assert_eq!(f32_to_f16_bits(0.1), 0x2e66);
+    assert_eq!(f32_to_f16_bits(65504.0), 0x7bff);
+    assert_eq!(f32_to_f16_bits(65536.0), 0x7c00);
+    assert_eq!(f32_to_f16_bits(f32::INFINITY), 0x7c00);
+    assert_eq!(f32_to_f16_bits(f32::NAN) & 0x7e00, 0x7e00);
+    // smallest subnormal, and ties to even around it
+    assert_eq!(f32_to_f16_bits(5.960_464_5e-8), 0x0001);
+    assert_eq!(f32_to_f16_bits(2.980_232_2e-8), 0x0000);
+    assert_eq!(f32_to_f16_bits(8.940_697e-8), 0x0002);
+    // ties to even in the normal range
+    assert_eq!(f32_to_f16_bits(1.000_488_3), 0x3c00);
+    assert_eq!(f32_to_f16_bits(1.001_464_8), 0x3c02);
+}
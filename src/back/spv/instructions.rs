@@ -127,13 +127,13 @@ impl super::Instruction {
     }
 
     pub(super) fn entry_point(
-        execution_model: spirv::ExecutionModel,
+        execution_model: Word,
         entry_point_id: Word,
         name: &str,
         interface_ids: &[Word],
     ) -> Self {
         let mut instruction = Self::new(Op::EntryPoint);
-        instruction.add_operand(execution_model as u32);
+        instruction.add_operand(execution_model);
         instruction.add_operand(entry_point_id);
         instruction.add_operands(helpers::string_to_words(name));
 
@@ -322,14 +322,10 @@ impl super::Instruction {
         instruction
     }
 
-    pub(super) fn type_pointer(
-        id: Word,
-        storage_class: spirv::StorageClass,
-        type_id: Word,
-    ) -> Self {
+    pub(super) fn type_pointer(id: Word, storage_class: Word, type_id: Word) -> Self {
         let mut instruction = Self::new(Op::TypePointer);
         instruction.set_result(id);
-        instruction.add_operand(storage_class as u32);
+        instruction.add_operand(storage_class);
         instruction.add_operand(type_id);
         instruction
     }
@@ -399,6 +395,48 @@ impl super::Instruction {
         instruction
     }
 
+    pub(super) fn spec_constant_true(result_type_id: Word, id: Word) -> Self {
+        let mut instruction = Self::new(Op::SpecConstantTrue);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction
+    }
+
+    pub(super) fn spec_constant_false(result_type_id: Word, id: Word) -> Self {
+        let mut instruction = Self::new(Op::SpecConstantFalse);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction
+    }
+
+    pub(super) fn spec_constant(result_type_id: Word, id: Word, values: &[Word]) -> Self {
+        let mut instruction = Self::new(Op::SpecConstant);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+
+        for value in values {
+            instruction.add_operand(*value);
+        }
+
+        instruction
+    }
+
+    pub(super) fn spec_constant_composite(
+        result_type_id: Word,
+        id: Word,
+        constituent_ids: &[Word],
+    ) -> Self {
+        let mut instruction = Self::new(Op::SpecConstantComposite);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+
+        for constituent_id in constituent_ids {
+            instruction.add_operand(*constituent_id);
+        }
+
+        instruction
+    }
+
     //
     //  Memory Instructions
     //
@@ -406,13 +444,13 @@ impl super::Instruction {
     pub(super) fn variable(
         result_type_id: Word,
         id: Word,
-        storage_class: spirv::StorageClass,
+        storage_class: Word,
         initializer_id: Option<Word>,
     ) -> Self {
         let mut instruction = Self::new(Op::Variable);
         instruction.set_type(result_type_id);
         instruction.set_result(id);
-        instruction.add_operand(storage_class as u32);
+        instruction.add_operand(storage_class);
 
         if let Some(initializer_id) = initializer_id {
             instruction.add_operand(initializer_id);
@@ -579,6 +617,30 @@ impl super::Instruction {
         instruction
     }
 
+    pub(super) fn image_gather(
+        result_type_id: Word,
+        id: Word,
+        sampled_image: Word,
+        coordinates: Word,
+        component_id: Word,
+        depth_ref: Option<Word>,
+    ) -> Self {
+        let op = match depth_ref {
+            None => Op::ImageGather,
+            Some(_) => Op::ImageDrefGather,
+        };
+
+        let mut instruction = Self::new(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(sampled_image);
+        instruction.add_operand(coordinates);
+        // the depth reference takes the place of the component
+        instruction.add_operand(depth_ref.unwrap_or(component_id));
+
+        instruction
+    }
+
     pub(super) fn image_fetch(
         result_type_id: Word,
         id: Word,
@@ -726,6 +788,42 @@ impl super::Instruction {
         instruction
     }
 
+    pub(super) fn ternary(
+        op: Op,
+        result_type_id: Word,
+        id: Word,
+        operand_1: Word,
+        operand_2: Word,
+        operand_3: Word,
+    ) -> Self {
+        let mut instruction = Self::new(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(operand_1);
+        instruction.add_operand(operand_2);
+        instruction.add_operand(operand_3);
+        instruction
+    }
+
+    pub(super) fn quaternary(
+        op: Op,
+        result_type_id: Word,
+        id: Word,
+        operand_1: Word,
+        operand_2: Word,
+        operand_3: Word,
+        operand_4: Word,
+    ) -> Self {
+        let mut instruction = Self::new(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(operand_1);
+        instruction.add_operand(operand_2);
+        instruction.add_operand(operand_3);
+        instruction.add_operand(operand_4);
+        instruction
+    }
+
     pub(super) fn relational(op: Op, result_type_id: Word, id: Word, expr_id: Word) -> Self {
         let mut instruction = Self::new(op);
         instruction.set_type(result_type_id);
@@ -865,6 +963,77 @@ impl super::Instruction {
     //  Atomic Instructions
     //
 
+    pub(super) fn atomic_binary(
+        op: Op,
+        result_type_id: Word,
+        id: Word,
+        pointer: Word,
+        scope_id: Word,
+        semantics_id: Word,
+        value: Word,
+    ) -> Self {
+        let mut instruction = Self::new(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(pointer);
+        instruction.add_operand(scope_id);
+        instruction.add_operand(semantics_id);
+        instruction.add_operand(value);
+        instruction
+    }
+
+    pub(super) fn atomic_compare_exchange(
+        result_type_id: Word,
+        id: Word,
+        pointer: Word,
+        scope_id: Word,
+        semantics_id: Word,
+        value: Word,
+        comparator: Word,
+    ) -> Self {
+        let mut instruction = Self::new(Op::AtomicCompareExchange);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(pointer);
+        instruction.add_operand(scope_id);
+        // semantics for the equal and unequal cases
+        instruction.add_operand(semantics_id);
+        instruction.add_operand(semantics_id);
+        instruction.add_operand(value);
+        instruction.add_operand(comparator);
+        instruction
+    }
+
+    pub(super) fn atomic_load(
+        result_type_id: Word,
+        id: Word,
+        pointer: Word,
+        scope_id: Word,
+        semantics_id: Word,
+    ) -> Self {
+        let mut instruction = Self::new(Op::AtomicLoad);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(pointer);
+        instruction.add_operand(scope_id);
+        instruction.add_operand(semantics_id);
+        instruction
+    }
+
+    pub(super) fn atomic_store(
+        pointer: Word,
+        scope_id: Word,
+        semantics_id: Word,
+        value: Word,
+    ) -> Self {
+        let mut instruction = Self::new(Op::AtomicStore);
+        instruction.add_operand(pointer);
+        instruction.add_operand(scope_id);
+        instruction.add_operand(semantics_id);
+        instruction.add_operand(value);
+        instruction
+    }
+
     //
     //  Primitive Instructions
     //
@@ -882,4 +1051,73 @@ impl super::Instruction {
         instruction.add_operand(semantics_id);
         instruction
     }
+
+    //
+    //  Non-Uniform Instructions
+    //
+
+    pub(super) fn group_non_uniform_ballot(
+        result_type_id: Word,
+        id: Word,
+        exec_scope_id: Word,
+        predicate: Word,
+    ) -> Self {
+        let mut instruction = Self::new(Op::GroupNonUniformBallot);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(exec_scope_id);
+        instruction.add_operand(predicate);
+        instruction
+    }
+
+    pub(super) fn group_non_uniform_vote(
+        op: Op,
+        result_type_id: Word,
+        id: Word,
+        exec_scope_id: Word,
+        predicate: Word,
+    ) -> Self {
+        let mut instruction = Self::new(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(exec_scope_id);
+        instruction.add_operand(predicate);
+        instruction
+    }
+
+    pub(super) fn group_non_uniform_arithmetic(
+        op: Op,
+        result_type_id: Word,
+        id: Word,
+        exec_scope_id: Word,
+        group_op: spirv::GroupOperation,
+        value: Word,
+    ) -> Self {
+        let mut instruction = Self::new(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(exec_scope_id);
+        instruction.add_operand(group_op as u32);
+        instruction.add_operand(value);
+        instruction
+    }
+
+    pub(super) fn group_non_uniform_gather(
+        op: Op,
+        result_type_id: Word,
+        id: Word,
+        exec_scope_id: Word,
+        value: Word,
+        index: Option<Word>,
+    ) -> Self {
+        let mut instruction = Self::new(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(exec_scope_id);
+        instruction.add_operand(value);
+        if let Some(index) = index {
+            instruction.add_operand(index);
+        }
+        instruction
+    }
 }
@@ -43,6 +43,11 @@ impl LogicalLayout {
 
 impl Instruction {
     pub(super) fn new(op: Op) -> Self {
+        Self::new_raw(op as Word)
+    }
+
+    /// Creates an instruction from a raw opcode, for the ones that `spirv::Op` is missing.
+    pub(super) fn new_raw(op: Word) -> Self {
         Instruction {
             op,
             wc: 1, // Always start at 1 for the first word (OP + WC),
@@ -78,7 +83,7 @@ impl Instruction {
     }
 
     pub(super) fn to_words(&self, sink: &mut impl Extend<Word>) {
-        sink.extend(Some(self.wc << 16 | self.op as u32));
+        sink.extend(Some(self.wc << 16 | self.op));
         sink.extend(self.type_id);
         sink.extend(self.result_id);
         sink.extend(self.operands.iter().cloned());
@@ -0,0 +1,382 @@
+//! Task and mesh shaders for SPIR-V output, following `SPV_EXT_mesh_shader`.
+//!
+//! The SPIR-V headers we depend on predate the extension, so the
+//! enumerants it introduces are spelled out here as raw words.
+
+use super::{Block, Error, Instruction, LocalType, LookupType, Writer, WriterFlags};
+use crate::{arena::Handle, valid::FunctionInfo};
+use spirv::Word;
+
+const OP_EMIT_MESH_TASKS_EXT: Word = 5294;
+const OP_SET_MESH_OUTPUTS_EXT: Word = 5295;
+const CAPABILITY_MESH_SHADING_EXT: Word = 5283;
+const EXECUTION_MODEL_TASK_EXT: Word = 5364;
+const EXECUTION_MODEL_MESH_EXT: Word = 5365;
+pub(super) const STORAGE_CLASS_TASK_PAYLOAD_WORKGROUP_EXT: Word = 5402;
+const BUILT_IN_PRIMITIVE_POINT_INDICES_EXT: Word = 5294;
+const BUILT_IN_PRIMITIVE_LINE_INDICES_EXT: Word = 5295;
+const BUILT_IN_PRIMITIVE_TRIANGLE_INDICES_EXT: Word = 5296;
+pub(super) const BUILT_IN_CULL_PRIMITIVE_EXT: Word = 5299;
+
+/// Output variables of a mesh entry point.
+pub(super) struct MeshOutputs {
+    /// Per-vertex arrays, one for each member of the vertex output structure.
+    vertex_members: Vec<Word>,
+    /// Per-primitive arrays, one for each member of the primitive output structure.
+    primitive_members: Vec<Word>,
+    /// The primitive indices built-in array.
+    indices: Word,
+}
+
+pub(super) enum MeshStageContext {
+    /// A task entry point, which launches the mesh grid with the given payload.
+    Task {
+        payload_id: Word,
+    },
+    Mesh(MeshOutputs),
+}
+
+impl Instruction {
+    fn set_mesh_outputs(vertex_count_id: Word, primitive_count_id: Word) -> Self {
+        let mut instruction = Self::new_raw(OP_SET_MESH_OUTPUTS_EXT);
+        instruction.add_operand(vertex_count_id);
+        instruction.add_operand(primitive_count_id);
+        instruction
+    }
+
+    fn emit_mesh_tasks(group_count_ids: [Word; 3], payload_id: Word) -> Self {
+        let mut instruction = Self::new_raw(OP_EMIT_MESH_TASKS_EXT);
+        for &id in group_count_ids.iter() {
+            instruction.add_operand(id);
+        }
+        instruction.add_operand(payload_id);
+        instruction
+    }
+}
+
+impl Writer {
+    pub(super) fn write_mesh_stage_context(
+        &mut self,
+        entry_point: &crate::EntryPoint,
+        ir_module: &crate::Module,
+        interface_ids: &mut Vec<Word>,
+    ) -> Result<Option<MeshStageContext>, Error> {
+        match entry_point.stage {
+            crate::ShaderStage::Task => {
+                let payload = entry_point
+                    .task_payload
+                    .ok_or(Error::Validation("task shader without a payload"))?;
+                Ok(Some(MeshStageContext::Task {
+                    payload_id: self.global_variables[payload.index()].id,
+                }))
+            }
+            crate::ShaderStage::Mesh => {
+                let info = entry_point
+                    .mesh_info
+                    .as_ref()
+                    .ok_or(Error::Validation("mesh shader without outputs"))?;
+                let vertex_members = self.write_mesh_output_members(
+                    ir_module,
+                    info.vertex_output_type,
+                    info.max_vertices,
+                    false,
+                    interface_ids,
+                )?;
+                let primitive_members = match info.primitive_output_type {
+                    Some(ty) => self.write_mesh_output_members(
+                        ir_module,
+                        ty,
+                        info.max_primitives,
+                        true,
+                        interface_ids,
+                    )?,
+                    None => Vec::new(),
+                };
+
+                let (vector_size, built_in) = match info.topology {
+                    crate::MeshOutputTopology::Points => {
+                        (None, BUILT_IN_PRIMITIVE_POINT_INDICES_EXT)
+                    }
+                    crate::MeshOutputTopology::Lines => (
+                        Some(crate::VectorSize::Bi),
+                        BUILT_IN_PRIMITIVE_LINE_INDICES_EXT,
+                    ),
+                    crate::MeshOutputTopology::Triangles => (
+                        Some(crate::VectorSize::Tri),
+                        BUILT_IN_PRIMITIVE_TRIANGLE_INDICES_EXT,
+                    ),
+                };
+                let index_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+                    vector_size,
+                    kind: crate::ScalarKind::Uint,
+                    width: 4,
+                    pointer_class: None,
+                }))?;
+                let indices =
+                    self.write_mesh_output_array(index_type_id, info.max_primitives, None)?;
+                self.decorate(indices, spirv::Decoration::BuiltIn, &[built_in]);
+                interface_ids.push(indices);
+
+                Ok(Some(MeshStageContext::Mesh(MeshOutputs {
+                    vertex_members,
+                    primitive_members,
+                    indices,
+                })))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Declare an output array for each member of the structure `ty`.
+    fn write_mesh_output_members(
+        &mut self,
+        ir_module: &crate::Module,
+        ty: Handle<crate::Type>,
+        length: u32,
+        per_primitive: bool,
+        interface_ids: &mut Vec<Word>,
+    ) -> Result<Vec<Word>, Error> {
+        let members = match ir_module.types[ty].inner {
+            crate::TypeInner::Struct { ref members, .. } => members,
+            _ => return Err(Error::Validation("mesh shader output is not a structure")),
+        };
+        let mut ids = Vec::with_capacity(members.len());
+        for member in members {
+            let element_type_id = self.get_type_id(LookupType::Handle(member.ty))?;
+            let name = member.name.as_ref().map(AsRef::as_ref);
+            let id = self.write_mesh_output_array(element_type_id, length, name)?;
+            let binding = member
+                .binding
+                .as_ref()
+                .ok_or(Error::Validation("mesh shader output without a binding"))?;
+            self.decorate_varying(id, spirv::StorageClass::Output as Word, binding)?;
+            if per_primitive {
+                // `PerPrimitiveEXT` shares the value of its NV counterpart
+                self.decorate(id, spirv::Decoration::PerPrimitiveNV, &[]);
+            }
+            interface_ids.push(id);
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+
+    fn write_mesh_output_array(
+        &mut self,
+        element_type_id: Word,
+        length: u32,
+        debug_name: Option<&str>,
+    ) -> Result<Word, Error> {
+        let class = spirv::StorageClass::Output as Word;
+        let length_id = self.get_index_constant(length)?;
+        let array_type_id = self.id_gen.next();
+        Instruction::type_array(array_type_id, element_type_id, length_id)
+            .to_words(&mut self.logical_layout.declarations);
+        let pointer_type_id = self.id_gen.next();
+        Instruction::type_pointer(pointer_type_id, class, array_type_id)
+            .to_words(&mut self.logical_layout.declarations);
+        let id = self.id_gen.next();
+        Instruction::variable(pointer_type_id, id, class, None)
+            .to_words(&mut self.logical_layout.declarations);
+
+        if self.flags.contains(WriterFlags::DEBUG) {
+            if let Some(name) = debug_name {
+                self.debugs.push(Instruction::name(id, name));
+            }
+        }
+        Ok(id)
+    }
+
+    pub(super) fn write_mesh_entry_point(
+        &mut self,
+        entry_point: &crate::EntryPoint,
+        function_id: Word,
+        interface_ids: &[Word],
+    ) -> Instruction {
+        use spirv::ExecutionMode as Em;
+
+        let mut modes = vec![(Em::LocalSize, entry_point.workgroup_size.to_vec())];
+        let execution_model = match entry_point.mesh_info {
+            Some(ref info) => {
+                // The EXT execution modes share the values of their NV counterparts.
+                let topology = match info.topology {
+                    crate::MeshOutputTopology::Points => Em::OutputPoints,
+                    crate::MeshOutputTopology::Lines => Em::OutputLinesNV,
+                    crate::MeshOutputTopology::Triangles => Em::OutputTrianglesNV,
+                };
+                modes.push((Em::OutputVertices, vec![info.max_vertices]));
+                modes.push((Em::OutputPrimitivesNV, vec![info.max_primitives]));
+                modes.push((topology, Vec::new()));
+                EXECUTION_MODEL_MESH_EXT
+            }
+            None => EXECUTION_MODEL_TASK_EXT,
+        };
+        for (mode, args) in modes {
+            Instruction::execution_mode(function_id, mode, &args)
+                .to_words(&mut self.logical_layout.execution_modes);
+        }
+
+        Instruction::entry_point(
+            execution_model,
+            function_id,
+            &entry_point.name,
+            interface_ids,
+        )
+    }
+
+    /// Write the end of a task shader, which launches the mesh grid
+    /// of the size `value_id` instead of returning.
+    pub(super) fn write_emit_mesh_tasks(
+        &mut self,
+        value_id: Word,
+        payload_id: Word,
+        body: &mut Vec<Instruction>,
+    ) -> Result<Instruction, Error> {
+        let uint_type_id = self.get_uint_type_id()?;
+        let mut group_count_ids = [0; 3];
+        for (index, id) in group_count_ids.iter_mut().enumerate() {
+            *id = self.id_gen.next();
+            body.push(Instruction::composite_extract(
+                uint_type_id,
+                *id,
+                value_id,
+                &[index as Word],
+            ));
+        }
+        Ok(Instruction::emit_mesh_tasks(group_count_ids, payload_id))
+    }
+
+    pub(super) fn write_mesh_function(
+        &mut self,
+        ir_module: &crate::Module,
+        fun: &crate::MeshFunction,
+        outputs: &MeshOutputs,
+        fun_info: &FunctionInfo,
+        block: &mut Block,
+    ) -> Result<(), Error> {
+        match *fun {
+            crate::MeshFunction::SetMeshOutputs {
+                vertex_count,
+                primitive_count,
+            } => {
+                block.body.push(Instruction::set_mesh_outputs(
+                    self.cached[vertex_count],
+                    self.cached[primitive_count],
+                ));
+            }
+            crate::MeshFunction::SetVertex { index, value } => {
+                self.write_mesh_output_store(
+                    ir_module,
+                    &outputs.vertex_members,
+                    index,
+                    value,
+                    fun_info,
+                    block,
+                )?;
+            }
+            crate::MeshFunction::SetPrimitive { index, value } => {
+                self.write_mesh_output_store(
+                    ir_module,
+                    &outputs.primitive_members,
+                    index,
+                    value,
+                    fun_info,
+                    block,
+                )?;
+            }
+            crate::MeshFunction::SetIndices { index, value } => {
+                let vector_size = match *fun_info[value].ty.inner_with(&ir_module.types) {
+                    crate::TypeInner::Vector { size, .. } => Some(size),
+                    _ => None,
+                };
+                let pointer_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+                    vector_size,
+                    kind: crate::ScalarKind::Uint,
+                    width: 4,
+                    pointer_class: Some(spirv::StorageClass::Output as Word),
+                }))?;
+                let pointer_id = self.id_gen.next();
+                block.body.push(Instruction::access_chain(
+                    pointer_type_id,
+                    pointer_id,
+                    outputs.indices,
+                    &[self.cached[index]],
+                ));
+                block
+                    .body
+                    .push(Instruction::store(pointer_id, self.cached[value], None));
+            }
+        }
+        Ok(())
+    }
+
+    /// Store each member of the structure `value` into its output array.
+    fn write_mesh_output_store(
+        &mut self,
+        ir_module: &crate::Module,
+        member_ids: &[Word],
+        index: Handle<crate::Expression>,
+        value: Handle<crate::Expression>,
+        fun_info: &FunctionInfo,
+        block: &mut Block,
+    ) -> Result<(), Error> {
+        let members = match *fun_info[value].ty.inner_with(&ir_module.types) {
+            crate::TypeInner::Struct { ref members, .. } => members,
+            _ => return Err(Error::Validation("mesh shader output is not a structure")),
+        };
+        let index_id = self.cached[index];
+        let value_id = self.cached[value];
+        for (member_index, (member, &array_id)) in members.iter().zip(member_ids).enumerate() {
+            let member_type_id = self.get_type_id(LookupType::Handle(member.ty))?;
+            let member_id = self.id_gen.next();
+            block.body.push(Instruction::composite_extract(
+                member_type_id,
+                member_id,
+                value_id,
+                &[member_index as Word],
+            ));
+            let pointer_type_id = self.get_pointer_id(
+                &ir_module.types,
+                member.ty,
+                spirv::StorageClass::Output as Word,
+            )?;
+            let pointer_id = self.id_gen.next();
+            block.body.push(Instruction::access_chain(
+                pointer_type_id,
+                pointer_id,
+                array_id,
+                &[index_id],
+            ));
+            block
+                .body
+                .push(Instruction::store(pointer_id, member_id, None));
+        }
+        Ok(())
+    }
+
+    pub(super) fn write_mesh_shading_requirements(
+        &mut self,
+        ir_module: &crate::Module,
+    ) -> Result<(), Error> {
+        let uses_mesh_shading = ir_module.entry_points.iter().any(|ep| match ep.stage {
+            crate::ShaderStage::Task | crate::ShaderStage::Mesh => true,
+            _ => false,
+        });
+        if !uses_mesh_shading {
+            return Ok(());
+        }
+        let version = self.physical_layout.version;
+        if version < 0x10400 {
+            return Err(Error::UnsupportedVersion(
+                (version >> 16) as u8,
+                (version >> 8) as u8,
+            ));
+        }
+        // The capability is missing from `spirv::Capability`, so it can't be
+        // checked against the options, and is implied by the stages instead.
+        let mut instruction = Instruction::new(spirv::Op::Capability);
+        instruction.add_operand(CAPABILITY_MESH_SHADING_EXT);
+        instruction.to_words(&mut self.logical_layout.capabilities);
+        Instruction::extension("SPV_EXT_mesh_shader").to_words(&mut self.logical_layout.extensions);
+        Ok(())
+    }
+}
@@ -5,6 +5,9 @@ mod helpers;
 mod index;
 mod instructions;
 mod layout;
+mod mesh;
+mod ray;
+mod subgroup;
 mod writer;
 
 pub use spirv::Capability;
@@ -39,7 +42,7 @@ struct LogicalLayout {
 }
 
 struct Instruction {
-    op: spirv::Op,
+    op: Word,
     wc: u32,
     type_id: Option<Word>,
     result_id: Option<Word>,
@@ -58,6 +61,8 @@ pub enum Error {
     FeatureNotImplemented(&'static str),
     #[error("module is not validated properly: {0}")]
     Validation(&'static str),
+    #[error("derived constants can't be written, the module has to be specialized first")]
+    DerivedConstants,
     #[error(transparent)]
     Proc(#[from] crate::proc::ProcError),
 }
@@ -102,6 +107,7 @@ struct ResultMember {
 struct EntryPointContext {
     argument_ids: Vec<Word>,
     results: Vec<ResultMember>,
+    mesh_stage: Option<mesh::MeshStageContext>,
 }
 
 #[derive(Default)]
@@ -144,7 +150,7 @@ enum LocalType {
         vector_size: Option<crate::VectorSize>,
         kind: crate::ScalarKind,
         width: crate::Bytes,
-        pointer_class: Option<spirv::StorageClass>,
+        pointer_class: Option<Word>,
     },
     /// A matrix of floating-point values.
     Matrix {
@@ -154,7 +160,7 @@ enum LocalType {
     },
     Pointer {
         base: Handle<crate::Type>,
-        class: spirv::StorageClass,
+        class: Word,
     },
     Image {
         dim: crate::ImageDimension,
@@ -249,6 +255,7 @@ pub struct Writer {
     lookup_function_call: crate::FastHashMap<Handle<crate::Expression>, Word>,
     constant_ids: Vec<Word>,
     cached_constants: crate::FastHashMap<(crate::ScalarValue, crate::Bytes), Word>,
+    cached_uint_splats: crate::FastHashMap<(u32, crate::VectorSize), Word>,
     global_variables: Vec<GlobalVariable>,
     cached: CachedExpressions,
     gl450_ext_inst_id: Word,
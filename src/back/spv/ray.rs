@@ -0,0 +1,257 @@
+//! Ray queries for SPIR-V output, following `SPV_KHR_ray_query`.
+//!
+//! The SPIR-V headers we depend on only know the provisional version
+//! of the extension, so its enumerants are spelled out here as raw words.
+
+use super::{Block, Error, Instruction, LookupType, Writer};
+use crate::{arena::Handle, valid::FunctionInfo};
+use spirv::Word;
+
+const OP_TYPE_RAY_QUERY_KHR: Word = 4472;
+const OP_RAY_QUERY_INITIALIZE_KHR: Word = 4473;
+const OP_RAY_QUERY_TERMINATE_KHR: Word = 4474;
+const OP_RAY_QUERY_PROCEED_KHR: Word = 4477;
+const OP_RAY_QUERY_GET_INTERSECTION_TYPE_KHR: Word = 4479;
+const OP_TYPE_ACCELERATION_STRUCTURE_KHR: Word = 5341;
+const CAPABILITY_RAY_QUERY_KHR: Word = 4472;
+
+/// Operations reading the members of a `RayIntersection`, in order.
+const INTERSECTION_OPS: [Word; 11] = [
+    OP_RAY_QUERY_GET_INTERSECTION_TYPE_KHR,
+    6018, // OpRayQueryGetIntersectionTKHR
+    6019, // OpRayQueryGetIntersectionInstanceCustomIndexKHR
+    6020, // OpRayQueryGetIntersectionInstanceIdKHR
+    6021, // OpRayQueryGetIntersectionInstanceShaderBindingTableRecordOffsetKHR
+    6022, // OpRayQueryGetIntersectionGeometryIndexKHR
+    6023, // OpRayQueryGetIntersectionPrimitiveIndexKHR
+    6024, // OpRayQueryGetIntersectionBarycentricsKHR
+    6025, // OpRayQueryGetIntersectionFrontFaceKHR
+    6031, // OpRayQueryGetIntersectionObjectToWorldKHR
+    6032, // OpRayQueryGetIntersectionWorldToObjectKHR
+];
+
+impl Instruction {
+    pub(super) fn type_acceleration_structure(id: Word) -> Self {
+        let mut instruction = Self::new_raw(OP_TYPE_ACCELERATION_STRUCTURE_KHR);
+        instruction.set_result(id);
+        instruction
+    }
+
+    pub(super) fn type_ray_query(id: Word) -> Self {
+        let mut instruction = Self::new_raw(OP_TYPE_RAY_QUERY_KHR);
+        instruction.set_result(id);
+        instruction
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn ray_query_initialize(
+        query_id: Word,
+        acceleration_structure_id: Word,
+        flags_id: Word,
+        cull_mask_id: Word,
+        origin_id: Word,
+        t_min_id: Word,
+        dir_id: Word,
+        t_max_id: Word,
+    ) -> Self {
+        let mut instruction = Self::new_raw(OP_RAY_QUERY_INITIALIZE_KHR);
+        for &id in [
+            query_id,
+            acceleration_structure_id,
+            flags_id,
+            cull_mask_id,
+            origin_id,
+            t_min_id,
+            dir_id,
+            t_max_id,
+        ]
+        .iter()
+        {
+            instruction.add_operand(id);
+        }
+        instruction
+    }
+
+    fn ray_query_get(
+        op: Word,
+        result_type_id: Word,
+        id: Word,
+        query_id: Word,
+        intersection_id: Option<Word>,
+    ) -> Self {
+        let mut instruction = Self::new_raw(op);
+        instruction.set_type(result_type_id);
+        instruction.set_result(id);
+        instruction.add_operand(query_id);
+        if let Some(intersection_id) = intersection_id {
+            instruction.add_operand(intersection_id);
+        }
+        instruction
+    }
+}
+
+impl Writer {
+    pub(super) fn write_ray_query_function(
+        &mut self,
+        ir_module: &crate::Module,
+        ir_function: &crate::Function,
+        query: Handle<crate::Expression>,
+        fun: &crate::RayQueryFunction,
+        fun_info: &FunctionInfo,
+        block: &mut Block,
+    ) -> Result<(), Error> {
+        let query_id = self.cached[query];
+        match *fun {
+            crate::RayQueryFunction::Initialize {
+                acceleration_structure,
+                descriptor,
+            } => {
+                let acceleration_structure_id =
+                    self.get_expression_global(ir_function, acceleration_structure);
+                let desc_id = self.cached[descriptor];
+                let desc_ty = fun_info[descriptor]
+                    .ty
+                    .handle()
+                    .ok_or(Error::Validation("ray descriptor"))?;
+                let desc_members = match ir_module.types[desc_ty].inner {
+                    crate::TypeInner::Struct { ref members, .. } if members.len() == 6 => members,
+                    _ => return Err(Error::Validation("ray descriptor")),
+                };
+                let mut member_ids = [0; 6];
+                for (index, member_id) in member_ids.iter_mut().enumerate() {
+                    let member_type_id =
+                        self.get_type_id(LookupType::Handle(desc_members[index].ty))?;
+                    *member_id = self.id_gen.next();
+                    block.body.push(Instruction::composite_extract(
+                        member_type_id,
+                        *member_id,
+                        desc_id,
+                        &[index as Word],
+                    ));
+                }
+                let [flags_id, cull_mask_id, t_min_id, t_max_id, origin_id, dir_id] = member_ids;
+                block.body.push(Instruction::ray_query_initialize(
+                    query_id,
+                    acceleration_structure_id,
+                    flags_id,
+                    cull_mask_id,
+                    origin_id,
+                    t_min_id,
+                    dir_id,
+                    t_max_id,
+                ));
+            }
+            crate::RayQueryFunction::Proceed { result } => {
+                let id = self.id_gen.next();
+                let result_type_id = self.get_expression_type_id(&fun_info[result].ty)?;
+                block.body.push(Instruction::ray_query_get(
+                    OP_RAY_QUERY_PROCEED_KHR,
+                    result_type_id,
+                    id,
+                    query_id,
+                    None,
+                ));
+                self.cached[result] = id;
+            }
+            crate::RayQueryFunction::Terminate => {
+                let mut instruction = Instruction::new_raw(OP_RAY_QUERY_TERMINATE_KHR);
+                instruction.add_operand(query_id);
+                block.body.push(instruction);
+            }
+        }
+        Ok(())
+    }
+
+    /// Read all the members of the committed or candidate intersection,
+    /// and compose them into a `RayIntersection`.
+    pub(super) fn write_ray_query_get_intersection(
+        &mut self,
+        ir_module: &crate::Module,
+        query: Handle<crate::Expression>,
+        committed: bool,
+        ty: Handle<crate::Type>,
+        block: &mut Block,
+    ) -> Result<Word, Error> {
+        let query_id = self.cached[query];
+        let members = match ir_module.types[ty].inner {
+            crate::TypeInner::Struct { ref members, .. } => members,
+            _ => return Err(Error::Validation("ray intersection")),
+        };
+        // `RayQueryIntersection::RayQuery{Candidate,Committed}IntersectionKHR`
+        let intersection_id = self.get_index_constant(committed as Word)?;
+
+        let mut member_ids = Vec::with_capacity(members.len());
+        for (member, &op) in members.iter().zip(INTERSECTION_OPS.iter()) {
+            let member_type_id = self.get_type_id(LookupType::Handle(member.ty))?;
+            let id = self.id_gen.next();
+            block.body.push(Instruction::ray_query_get(
+                op,
+                member_type_id,
+                id,
+                query_id,
+                Some(intersection_id),
+            ));
+            member_ids.push(id);
+        }
+
+        if !committed {
+            // candidates are triangles (0) or boxes (1), map them to the IR kinds
+            let kind_id = member_ids[0];
+            let uint_type_id = self.get_type_id(LookupType::Handle(members[0].ty))?;
+            let two_id = self.get_index_constant(2)?;
+            let one_id = self.get_index_constant(1)?;
+            let scaled_id = self.id_gen.next();
+            block.body.push(Instruction::binary(
+                spirv::Op::IMul,
+                uint_type_id,
+                scaled_id,
+                kind_id,
+                two_id,
+            ));
+            member_ids[0] = self.id_gen.next();
+            block.body.push(Instruction::binary(
+                spirv::Op::IAdd,
+                uint_type_id,
+                member_ids[0],
+                scaled_id,
+                one_id,
+            ));
+        }
+
+        let id = self.id_gen.next();
+        let result_type_id = self.get_type_id(LookupType::Handle(ty))?;
+        block.body.push(Instruction::composite_construct(
+            result_type_id,
+            id,
+            &member_ids,
+        ));
+        Ok(id)
+    }
+
+    pub(super) fn write_ray_query_requirements(
+        &mut self,
+        ir_module: &crate::Module,
+    ) -> Result<(), Error> {
+        let uses_ray_query = ir_module.types.iter().any(|(_, ty)| match ty.inner {
+            crate::TypeInner::AccelerationStructure | crate::TypeInner::RayQuery => true,
+            _ => false,
+        });
+        if !uses_ray_query {
+            return Ok(());
+        }
+        let version = self.physical_layout.version;
+        if version < 0x10400 {
+            return Err(Error::UnsupportedVersion(
+                (version >> 16) as u8,
+                (version >> 8) as u8,
+            ));
+        }
+        // The capability is missing from `spirv::Capability`, so it can't be
+        // checked against the options, and is implied by the types instead.
+        let mut instruction = Instruction::new(spirv::Op::Capability);
+        instruction.add_operand(CAPABILITY_RAY_QUERY_KHR);
+        instruction.to_words(&mut self.logical_layout.capabilities);
+        Instruction::extension("SPV_KHR_ray_query").to_words(&mut self.logical_layout.extensions);
+        Ok(())
+    }
+}
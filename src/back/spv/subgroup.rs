@@ -0,0 +1,171 @@
+//! Subgroup operations for SPIR-V output.
+
+use super::{Block, Error, Instruction, Writer};
+use crate::{arena::Handle, valid::FunctionInfo};
+
+impl Writer {
+    pub(super) fn write_subgroup_ballot(
+        &mut self,
+        predicate: Option<Handle<crate::Expression>>,
+        result: Handle<crate::Expression>,
+        fun_info: &FunctionInfo,
+        block: &mut Block,
+    ) -> Result<(), Error> {
+        self.check(&[spirv::Capability::GroupNonUniformBallot])?;
+        self.check(&[spirv::Capability::GroupNonUniform])?;
+
+        let id = self.id_gen.next();
+        let result_type_id = self.get_expression_type_id(&fun_info[result].ty)?;
+        let exec_scope_id = self.get_index_constant(spirv::Scope::Subgroup as u32)?;
+        let predicate_id = match predicate {
+            Some(predicate) => self.cached[predicate],
+            None => self.get_constant_scalar(crate::ScalarValue::Bool(true), crate::BOOL_WIDTH)?,
+        };
+        block.body.push(Instruction::group_non_uniform_ballot(
+            result_type_id,
+            id,
+            exec_scope_id,
+            predicate_id,
+        ));
+        self.cached[result] = id;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn write_subgroup_operation(
+        &mut self,
+        ir_module: &crate::Module,
+        op: crate::SubgroupOperation,
+        collective_op: crate::CollectiveOperation,
+        argument: Handle<crate::Expression>,
+        result: Handle<crate::Expression>,
+        fun_info: &FunctionInfo,
+        block: &mut Block,
+    ) -> Result<(), Error> {
+        use crate::{ScalarKind as Sk, SubgroupOperation as So};
+
+        let id = self.id_gen.next();
+        let result_type_id = self.get_expression_type_id(&fun_info[result].ty)?;
+        let exec_scope_id = self.get_index_constant(spirv::Scope::Subgroup as u32)?;
+        let argument_id = self.cached[argument];
+
+        let instruction = match op {
+            So::All | So::Any => {
+                self.check(&[spirv::Capability::GroupNonUniformVote])?;
+                let spirv_op = match op {
+                    So::All => spirv::Op::GroupNonUniformAll,
+                    _ => spirv::Op::GroupNonUniformAny,
+                };
+                Instruction::group_non_uniform_vote(
+                    spirv_op,
+                    result_type_id,
+                    id,
+                    exec_scope_id,
+                    argument_id,
+                )
+            }
+            _ => {
+                self.check(&[spirv::Capability::GroupNonUniformArithmetic])?;
+                let kind = match *fun_info[argument].ty.inner_with(&ir_module.types) {
+                    crate::TypeInner::Scalar { kind, .. }
+                    | crate::TypeInner::Vector { kind, .. } => kind,
+                    _ => return Err(Error::Validation("subgroup operand")),
+                };
+                let spirv_op = match (op, kind) {
+                    (So::Add, Sk::Float) => spirv::Op::GroupNonUniformFAdd,
+                    (So::Add, _) => spirv::Op::GroupNonUniformIAdd,
+                    (So::Mul, Sk::Float) => spirv::Op::GroupNonUniformFMul,
+                    (So::Mul, _) => spirv::Op::GroupNonUniformIMul,
+                    (So::Min, Sk::Float) => spirv::Op::GroupNonUniformFMin,
+                    (So::Min, Sk::Sint) => spirv::Op::GroupNonUniformSMin,
+                    (So::Min, _) => spirv::Op::GroupNonUniformUMin,
+                    (So::Max, Sk::Float) => spirv::Op::GroupNonUniformFMax,
+                    (So::Max, Sk::Sint) => spirv::Op::GroupNonUniformSMax,
+                    (So::Max, _) => spirv::Op::GroupNonUniformUMax,
+                    (So::And, Sk::Bool) => spirv::Op::GroupNonUniformLogicalAnd,
+                    (So::And, _) => spirv::Op::GroupNonUniformBitwiseAnd,
+                    (So::Or, Sk::Bool) => spirv::Op::GroupNonUniformLogicalOr,
+                    (So::Or, _) => spirv::Op::GroupNonUniformBitwiseOr,
+                    (So::Xor, Sk::Bool) => spirv::Op::GroupNonUniformLogicalXor,
+                    (So::Xor, _) => spirv::Op::GroupNonUniformBitwiseXor,
+                    (So::All, _) | (So::Any, _) => unreachable!(),
+                };
+                let group_op = match collective_op {
+                    crate::CollectiveOperation::Reduce => spirv::GroupOperation::Reduce,
+                    crate::CollectiveOperation::InclusiveScan => {
+                        spirv::GroupOperation::InclusiveScan
+                    }
+                    crate::CollectiveOperation::ExclusiveScan => {
+                        spirv::GroupOperation::ExclusiveScan
+                    }
+                };
+                Instruction::group_non_uniform_arithmetic(
+                    spirv_op,
+                    result_type_id,
+                    id,
+                    exec_scope_id,
+                    group_op,
+                    argument_id,
+                )
+            }
+        };
+        self.check(&[spirv::Capability::GroupNonUniform])?;
+
+        block.body.push(instruction);
+        self.cached[result] = id;
+        Ok(())
+    }
+
+    pub(super) fn write_subgroup_gather(
+        &mut self,
+        mode: &crate::GatherMode,
+        argument: Handle<crate::Expression>,
+        result: Handle<crate::Expression>,
+        fun_info: &FunctionInfo,
+        block: &mut Block,
+    ) -> Result<(), Error> {
+        let (spirv_op, capability) = match *mode {
+            crate::GatherMode::BroadcastFirst => (
+                spirv::Op::GroupNonUniformBroadcastFirst,
+                spirv::Capability::GroupNonUniformBallot,
+            ),
+            crate::GatherMode::Broadcast(_) => (
+                spirv::Op::GroupNonUniformBroadcast,
+                spirv::Capability::GroupNonUniformBallot,
+            ),
+            crate::GatherMode::Shuffle(_) => (
+                spirv::Op::GroupNonUniformShuffle,
+                spirv::Capability::GroupNonUniformShuffle,
+            ),
+            crate::GatherMode::ShuffleXor(_) => (
+                spirv::Op::GroupNonUniformShuffleXor,
+                spirv::Capability::GroupNonUniformShuffle,
+            ),
+            crate::GatherMode::ShuffleDown(_) => (
+                spirv::Op::GroupNonUniformShuffleDown,
+                spirv::Capability::GroupNonUniformShuffleRelative,
+            ),
+            crate::GatherMode::ShuffleUp(_) => (
+                spirv::Op::GroupNonUniformShuffleUp,
+                spirv::Capability::GroupNonUniformShuffleRelative,
+            ),
+        };
+        self.check(&[capability])?;
+        self.check(&[spirv::Capability::GroupNonUniform])?;
+
+        let id = self.id_gen.next();
+        let result_type_id = self.get_expression_type_id(&fun_info[result].ty)?;
+        let exec_scope_id = self.get_index_constant(spirv::Scope::Subgroup as u32)?;
+        let index_id = mode.index().map(|index| self.cached[index]);
+        block.body.push(Instruction::group_non_uniform_gather(
+            spirv_op,
+            result_type_id,
+            id,
+            exec_scope_id,
+            self.cached[argument],
+            index_id,
+        ));
+        self.cached[result] = id;
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use super::{
-    helpers::{contains_builtin, map_storage_class},
+    helpers::{contains_builtin, contains_half_float, f32_to_f16_bits, map_storage_class},
     index::{BoundsCheckResult, ExpressionPointer},
+    mesh::MeshStageContext,
     Block, CachedExpressions, Dimension, EntryPointContext, Error, Function, GlobalVariable,
     IdGenerator, Instruction, LocalType, LocalVariable, LogicalLayout, LookupFunctionType,
     LookupType, Options, PhysicalLayout, ResultMember, Writer, WriterFlags, BITS_PER_BYTE,
@@ -10,6 +11,7 @@ use crate::{
     proc::TypeResolution,
     valid::{FunctionInfo, ModuleInfo},
 };
+use num_traits::cast::FromPrimitive;
 use spirv::Word;
 use std::collections::hash_map::Entry;
 
@@ -22,6 +24,13 @@ fn map_dim(dim: crate::ImageDimension) -> spirv::Dim {
     }
 }
 
+fn arg_vector_size(type_inner: &crate::TypeInner) -> Option<crate::VectorSize> {
+    match *type_inner {
+        crate::TypeInner::Vector { size, .. } => Some(size),
+        _ => None,
+    }
+}
+
 fn get_dimension(type_inner: &crate::TypeInner) -> Dimension {
     match *type_inner {
         crate::TypeInner::Scalar { .. } => Dimension::Scalar,
@@ -55,12 +64,14 @@ impl Function {
 impl PhysicalLayout {
     fn make_local(&self, inner: &crate::TypeInner) -> Option<LocalType> {
         Some(match *inner {
-            crate::TypeInner::Scalar { kind, width } => LocalType::Value {
-                vector_size: None,
-                kind,
-                width,
-                pointer_class: None,
-            },
+            crate::TypeInner::Scalar { kind, width } | crate::TypeInner::Atomic { kind, width } => {
+                LocalType::Value {
+                    vector_size: None,
+                    kind,
+                    width,
+                    pointer_class: None,
+                }
+            }
             crate::TypeInner::Vector { size, kind, width } => LocalType::Value {
                 vector_size: Some(size),
                 kind,
@@ -150,6 +161,7 @@ impl Writer {
             lookup_function_call: crate::FastHashMap::default(),
             constant_ids: Vec::new(),
             cached_constants: crate::FastHashMap::default(),
+            cached_uint_splats: crate::FastHashMap::default(),
             global_variables: Vec::new(),
             cached: CachedExpressions::default(),
             gl450_ext_inst_id,
@@ -157,7 +169,7 @@ impl Writer {
         })
     }
 
-    fn check(&mut self, capabilities: &[spirv::Capability]) -> Result<(), Error> {
+    pub(super) fn check(&mut self, capabilities: &[spirv::Capability]) -> Result<(), Error> {
         if capabilities.is_empty()
             || capabilities
                 .iter()
@@ -175,7 +187,7 @@ impl Writer {
         Err(Error::MissingCapabilities(capabilities.to_vec()))
     }
 
-    fn get_type_id(&mut self, lookup_ty: LookupType) -> Result<Word, Error> {
+    pub(super) fn get_type_id(&mut self, lookup_ty: LookupType) -> Result<Word, Error> {
         if let Entry::Occupied(e) = self.lookup_type.entry(lookup_ty) {
             Ok(*e.get())
         } else {
@@ -196,11 +208,11 @@ impl Writer {
         self.get_type_id(lookup_ty)
     }
 
-    fn get_pointer_id(
+    pub(super) fn get_pointer_id(
         &mut self,
         arena: &Arena<crate::Type>,
         handle: Handle<crate::Type>,
-        class: spirv::StorageClass,
+        class: Word,
     ) -> Result<Word, Error> {
         let ty_id = self.get_type_id(LookupType::Handle(handle))?;
         if let crate::TypeInner::Pointer { .. } = arena[handle].inner {
@@ -241,7 +253,7 @@ impl Writer {
         self.get_type_id(local_type.into())
     }
 
-    fn decorate(&mut self, id: Word, decoration: spirv::Decoration, operands: &[Word]) {
+    pub(super) fn decorate(&mut self, id: Word, decoration: spirv::Decoration, operands: &[Word]) {
         self.annotations
             .push(Instruction::decorate(id, decoration, operands));
     }
@@ -252,6 +264,7 @@ impl Writer {
         info: &FunctionInfo,
         ir_module: &crate::Module,
         mut varying_ids: Option<&mut Vec<Word>>,
+        mesh_stage: Option<MeshStageContext>,
     ) -> Result<Word, Error> {
         let mut function = Function::default();
 
@@ -267,12 +280,15 @@ impl Writer {
             let init_word = variable
                 .init
                 .map(|constant| self.constant_ids[constant.index()]);
-            let pointer_type_id =
-                self.get_pointer_id(&ir_module.types, variable.ty, spirv::StorageClass::Function)?;
+            let pointer_type_id = self.get_pointer_id(
+                &ir_module.types,
+                variable.ty,
+                spirv::StorageClass::Function as Word,
+            )?;
             let instruction = Instruction::variable(
                 pointer_type_id,
                 id,
-                spirv::StorageClass::Function,
+                spirv::StorageClass::Function as Word,
                 init_word,
             );
             function
@@ -282,14 +298,17 @@ impl Writer {
 
         let prelude_id = self.id_gen.next();
         let mut prelude = Block::new(prelude_id);
+        // task shaders launch the mesh grid instead of writing their result
+        let is_task = matches!(mesh_stage, Some(MeshStageContext::Task { .. }));
         let mut ep_context = EntryPointContext {
             argument_ids: Vec::new(),
             results: Vec::new(),
+            mesh_stage,
         };
 
         let mut parameter_type_ids = Vec::with_capacity(ir_function.arguments.len());
         for argument in ir_function.arguments.iter() {
-            let class = spirv::StorageClass::Input;
+            let class = spirv::StorageClass::Input as Word;
             let argument_type_id = self.get_type_id(LookupType::Handle(argument.ty))?;
             if let Some(ref mut list) = varying_ids {
                 let id = if let Some(ref binding) = argument.binding {
@@ -339,9 +358,10 @@ impl Writer {
         }
 
         let return_type_id = match ir_function.result {
+            Some(_) if is_task => self.void_type,
             Some(ref result) => {
                 if let Some(ref mut list) = varying_ids {
-                    let class = spirv::StorageClass::Output;
+                    let class = spirv::StorageClass::Output as Word;
                     if let Some(ref binding) = result.binding {
                         let type_id = self.get_type_id(LookupType::Handle(result.ty))?;
                         let varying_id =
@@ -409,9 +429,13 @@ impl Writer {
         }
         for (handle, var) in ir_module.global_variables.iter() {
             // Handle globals are pre-emitted and should be loaded automatically.
+            // Binding arrays are loaded element by element when accessed.
             if info[handle].is_empty() || var.class != crate::StorageClass::Handle {
                 continue;
             }
+            if let crate::TypeInner::BindingArray { .. } = ir_module.types[var.ty].inner {
+                continue;
+            }
             let id = self.id_gen.next();
             let result_type_id = self.get_type_id(LookupType::Handle(var.ty))?;
             let gv = &mut self.global_variables[handle.index()];
@@ -473,13 +497,25 @@ impl Writer {
         ir_module: &crate::Module,
     ) -> Result<Instruction, Error> {
         let mut interface_ids = Vec::new();
+        let mesh_stage =
+            self.write_mesh_stage_context(entry_point, ir_module, &mut interface_ids)?;
         let function_id = self.write_function(
             &entry_point.function,
             info,
             ir_module,
             Some(&mut interface_ids),
+            mesh_stage,
         )?;
 
+        if self.physical_layout.version >= 0x10400 {
+            // since SPV-1.4, the interface lists all the global variables used
+            for (handle, _) in ir_module.global_variables.iter() {
+                if !info[handle].is_empty() {
+                    interface_ids.push(self.global_variables[handle.index()].id);
+                }
+            }
+        }
+
         let exec_model = match entry_point.stage {
             crate::ShaderStage::Vertex => spirv::ExecutionModel::Vertex,
             crate::ShaderStage::Fragment => {
@@ -510,11 +546,14 @@ impl Writer {
                 .to_words(&mut self.logical_layout.execution_modes);
                 spirv::ExecutionModel::GLCompute
             }
+            crate::ShaderStage::Task | crate::ShaderStage::Mesh => {
+                return Ok(self.write_mesh_entry_point(entry_point, function_id, &interface_ids));
+            }
         };
         self.check(exec_model.required_capabilities())?;
 
         Ok(Instruction::entry_point(
-            exec_model,
+            exec_model as Word,
             function_id,
             &entry_point.name,
             interface_ids.as_slice(),
@@ -549,8 +588,14 @@ impl Writer {
                 Instruction::type_int(id, bits, signedness)
             }
             Sk::Float => {
-                if bits == 64 {
-                    self.capabilities.insert(spirv::Capability::Float64);
+                match bits {
+                    16 => {
+                        self.capabilities.insert(spirv::Capability::Float16);
+                    }
+                    64 => {
+                        self.capabilities.insert(spirv::Capability::Float64);
+                    }
+                    _ => {}
                 }
                 Instruction::type_float(id, bits)
             }
@@ -660,7 +705,9 @@ impl Writer {
         use spirv::Decoration;
 
         let instruction = match ty.inner {
-            crate::TypeInner::Scalar { kind, width } => self.make_scalar(id, kind, width),
+            crate::TypeInner::Scalar { kind, width } | crate::TypeInner::Atomic { kind, width } => {
+                self.make_scalar(id, kind, width)
+            }
             crate::TypeInner::Vector { size, kind, width } => {
                 let scalar_id = self.get_type_id(LookupType::Local(LocalType::Value {
                     vector_size: None,
@@ -713,6 +760,8 @@ impl Writer {
                 Instruction::type_image(id, type_id, dim, arrayed, class)
             }
             crate::TypeInner::Sampler { comparison: _ } => Instruction::type_sampler(id),
+            crate::TypeInner::AccelerationStructure => Instruction::type_acceleration_structure(id),
+            crate::TypeInner::RayQuery => Instruction::type_ray_query(id),
             crate::TypeInner::Array { base, size, stride } => {
                 if decorate_layout {
                     self.decorate(id, Decoration::ArrayStride, &[stride]);
@@ -727,6 +776,19 @@ impl Writer {
                     crate::ArraySize::Dynamic => Instruction::type_runtime_array(id, type_id),
                 }
             }
+            crate::TypeInner::BindingArray { base, size } => {
+                let type_id = self.get_type_id(LookupType::Handle(base))?;
+                match size {
+                    crate::ArraySize::Constant(const_handle) => {
+                        let length_id = self.constant_ids[const_handle.index()];
+                        Instruction::type_array(id, type_id, length_id)
+                    }
+                    crate::ArraySize::Dynamic => {
+                        self.check(&[spirv::Capability::RuntimeDescriptorArray])?;
+                        Instruction::type_runtime_array(id, type_id)
+                    }
+                }
+            }
             crate::TypeInner::Struct {
                 top_level,
                 ref members,
@@ -815,11 +877,32 @@ impl Writer {
         Ok(id)
     }
 
+    /// Return the ids of the memory scope and semantics constants
+    /// for atomic operations on the given storage class.
+    fn get_atomic_scope_and_semantics(
+        &mut self,
+        class: crate::StorageClass,
+    ) -> Result<(Word, Word), Error> {
+        let (scope, semantics) = match class {
+            crate::StorageClass::Storage => {
+                (spirv::Scope::Device, spirv::MemorySemantics::UNIFORM_MEMORY)
+            }
+            crate::StorageClass::WorkGroup => (
+                spirv::Scope::Workgroup,
+                spirv::MemorySemantics::WORKGROUP_MEMORY,
+            ),
+            _ => return Err(Error::Validation("atomic storage class")),
+        };
+        let scope_id = self.get_index_constant(scope as u32)?;
+        let semantics_id = self.get_index_constant(semantics.bits())?;
+        Ok((scope_id, semantics_id))
+    }
+
     pub(super) fn get_index_constant(&mut self, index: Word) -> Result<Word, Error> {
         self.get_constant_scalar(crate::ScalarValue::Uint(index as _), 4)
     }
 
-    fn get_constant_scalar(
+    pub(super) fn get_constant_scalar(
         &mut self,
         value: crate::ScalarValue,
         width: crate::Bytes,
@@ -828,76 +911,122 @@ impl Writer {
             return Ok(id);
         }
         let id = self.id_gen.next();
-        self.write_constant_scalar(id, &value, width, None)?;
+        self.write_constant_scalar(id, &value, width, None, None)?;
         self.cached_constants.insert((value, width), id);
         Ok(id)
     }
 
+    fn get_uint_vector_type_id(
+        &mut self,
+        vector_size: Option<crate::VectorSize>,
+    ) -> Result<Word, Error> {
+        self.get_type_id(LookupType::Local(LocalType::Value {
+            vector_size,
+            kind: crate::ScalarKind::Uint,
+            width: 4,
+            pointer_class: None,
+        }))
+    }
+
+    /// Returns a constant with all the components equal to `value`.
+    fn get_uint_splat_id(
+        &mut self,
+        value: u32,
+        vector_size: Option<crate::VectorSize>,
+    ) -> Result<Word, Error> {
+        let scalar_id = self.get_constant_scalar(crate::ScalarValue::Uint(value as u64), 4)?;
+        match vector_size {
+            Some(size) => {
+                if let Some(&id) = self.cached_uint_splats.get(&(value, size)) {
+                    return Ok(id);
+                }
+                let type_id = self.get_uint_vector_type_id(vector_size)?;
+                let id = self.id_gen.next();
+                Instruction::constant_composite(type_id, id, &vec![scalar_id; size as usize])
+                    .to_words(&mut self.logical_layout.declarations);
+                self.cached_uint_splats.insert((value, size), id);
+                Ok(id)
+            }
+            None => Ok(scalar_id),
+        }
+    }
+
     fn write_constant_scalar(
         &mut self,
         id: Word,
         value: &crate::ScalarValue,
         width: crate::Bytes,
         debug_name: Option<&String>,
+        specialization: Option<Word>,
     ) -> Result<(), Error> {
         if self.flags.contains(WriterFlags::DEBUG) {
             if let Some(name) = debug_name {
                 self.debugs.push(Instruction::name(id, name));
             }
         }
+        if let Some(spec_id) = specialization {
+            self.decorate(id, spirv::Decoration::SpecId, &[spec_id]);
+        }
         let type_id = self.get_type_id(LookupType::Local(LocalType::Value {
             vector_size: None,
             kind: value.scalar_kind(),
             width,
             pointer_class: None,
         }))?;
+        // multi-word literals go low-order word first
         let (solo, pair);
-        let instruction = match *value {
-            crate::ScalarValue::Sint(val) => {
-                let words = match width {
-                    4 => {
-                        solo = [val as u32];
-                        &solo[..]
-                    }
-                    8 => {
-                        pair = [(val >> 32) as u32, val as u32];
-                        &pair
-                    }
-                    _ => unreachable!(),
-                };
-                Instruction::constant(type_id, id, words)
-            }
-            crate::ScalarValue::Uint(val) => {
-                let words = match width {
-                    4 => {
-                        solo = [val as u32];
-                        &solo[..]
-                    }
-                    8 => {
-                        pair = [(val >> 32) as u32, val as u32];
-                        &pair
-                    }
-                    _ => unreachable!(),
-                };
-                Instruction::constant(type_id, id, words)
+        let words = match *value {
+            crate::ScalarValue::Sint(val) => match width {
+                4 => {
+                    solo = [val as u32];
+                    &solo[..]
+                }
+                8 => {
+                    pair = [val as u32, (val >> 32) as u32];
+                    &pair
+                }
+                _ => unreachable!(),
+            },
+            crate::ScalarValue::Uint(val) => match width {
+                4 => {
+                    solo = [val as u32];
+                    &solo[..]
+                }
+                8 => {
+                    pair = [val as u32, (val >> 32) as u32];
+                    &pair
+                }
+                _ => unreachable!(),
+            },
+            crate::ScalarValue::Float(val) => match width {
+                2 => {
+                    solo = [f32_to_f16_bits(val as f32) as u32];
+                    &solo[..]
+                }
+                4 => {
+                    solo = [(val as f32).to_bits()];
+                    &solo[..]
+                }
+                8 => {
+                    let bits = f64::to_bits(val);
+                    pair = [bits as u32, (bits >> 32) as u32];
+                    &pair
+                }
+                _ => unreachable!(),
+            },
+            crate::ScalarValue::Bool(_) => &[],
+        };
+        let instruction = match (*value, specialization) {
+            (crate::ScalarValue::Bool(true), None) => Instruction::constant_true(type_id, id),
+            (crate::ScalarValue::Bool(false), None) => Instruction::constant_false(type_id, id),
+            (crate::ScalarValue::Bool(true), Some(_)) => {
+                Instruction::spec_constant_true(type_id, id)
             }
-            crate::ScalarValue::Float(val) => {
-                let words = match width {
-                    4 => {
-                        solo = [(val as f32).to_bits()];
-                        &solo[..]
-                    }
-                    8 => {
-                        let bits = f64::to_bits(val);
-                        pair = [(bits >> 32) as u32, bits as u32];
-                        &pair
-                    }
-                    _ => unreachable!(),
-                };
-                Instruction::constant(type_id, id, words)
+            (crate::ScalarValue::Bool(false), Some(_)) => {
+                Instruction::spec_constant_false(type_id, id)
             }
-            crate::ScalarValue::Bool(true) => Instruction::constant_true(type_id, id),
-            crate::ScalarValue::Bool(false) => Instruction::constant_false(type_id, id),
+            (_, None) => Instruction::constant(type_id, id, words),
+            (_, Some(_)) => Instruction::spec_constant(type_id, id, words),
         };
 
         instruction.to_words(&mut self.logical_layout.declarations);
@@ -909,6 +1038,7 @@ impl Writer {
         id: Word,
         ty: Handle<crate::Type>,
         components: &[Handle<crate::Constant>],
+        specializable: bool,
     ) -> Result<(), Error> {
         let mut constituent_ids = Vec::with_capacity(components.len());
         for constituent in components.iter() {
@@ -917,8 +1047,12 @@ impl Writer {
         }
 
         let type_id = self.get_type_id(LookupType::Handle(ty))?;
-        Instruction::constant_composite(type_id, id, constituent_ids.as_slice())
-            .to_words(&mut self.logical_layout.declarations);
+        let instruction = if specializable {
+            Instruction::spec_constant_composite(type_id, id, constituent_ids.as_slice())
+        } else {
+            Instruction::constant_composite(type_id, id, constituent_ids.as_slice())
+        };
+        instruction.to_words(&mut self.logical_layout.declarations);
         Ok(())
     }
 
@@ -932,7 +1066,7 @@ impl Writer {
     fn write_varying(
         &mut self,
         ir_module: &crate::Module,
-        class: spirv::StorageClass,
+        class: Word,
         debug_name: Option<&str>,
         ty: Handle<crate::Type>,
         binding: &crate::Binding,
@@ -948,15 +1082,29 @@ impl Writer {
             }
         }
 
+        self.decorate_varying(id, class, binding)?;
+        Ok(id)
+    }
+
+    pub(super) fn decorate_varying(
+        &mut self,
+        id: Word,
+        class: Word,
+        binding: &crate::Binding,
+    ) -> Result<(), Error> {
         use spirv::{BuiltIn, Decoration};
 
         match *binding {
             crate::Binding::Location {
                 location,
+                second_blend_source,
                 interpolation,
                 sampling,
             } => {
                 self.decorate(id, Decoration::Location, &[location]);
+                if second_blend_source {
+                    self.decorate(id, Decoration::Index, &[1]);
+                }
 
                 match interpolation {
                     // Perspective-correct interpolation is the default in SPIR-V.
@@ -984,12 +1132,16 @@ impl Writer {
                 use crate::BuiltIn as Bi;
                 let built_in = match built_in {
                     Bi::Position => {
-                        if class == spirv::StorageClass::Output {
+                        if class == spirv::StorageClass::Output as Word {
                             BuiltIn::Position
                         } else {
                             BuiltIn::FragCoord
                         }
                     }
+                    Bi::ViewIndex => {
+                        self.check(&[spirv::Capability::MultiView])?;
+                        BuiltIn::ViewIndex
+                    }
                     // vertex
                     Bi::BaseInstance => BuiltIn::BaseInstance,
                     Bi::BaseVertex => BuiltIn::BaseVertex,
@@ -1001,6 +1153,12 @@ impl Writer {
                     // fragment
                     Bi::FragDepth => BuiltIn::FragDepth,
                     Bi::FrontFacing => BuiltIn::FrontFacing,
+                    Bi::PrimitiveIndex => {
+                        self.check(&[spirv::Capability::Geometry])?;
+                        // integer fragment inputs have to be flat
+                        self.decorate(id, Decoration::Flat, &[]);
+                        BuiltIn::PrimitiveId
+                    }
                     Bi::SampleIndex => BuiltIn::SampleId,
                     Bi::SampleMask => BuiltIn::SampleMask,
                     // compute
@@ -1009,13 +1167,34 @@ impl Writer {
                     Bi::LocalInvocationIndex => BuiltIn::LocalInvocationIndex,
                     Bi::WorkGroupId => BuiltIn::WorkgroupId,
                     Bi::WorkGroupSize => BuiltIn::WorkgroupSize,
+                    // subgroup
+                    Bi::SubgroupSize => {
+                        self.check(&[spirv::Capability::GroupNonUniform])?;
+                        BuiltIn::SubgroupSize
+                    }
+                    Bi::SubgroupInvocationId => {
+                        self.check(&[spirv::Capability::GroupNonUniform])?;
+                        BuiltIn::SubgroupLocalInvocationId
+                    }
+                    // mesh
+                    Bi::MeshTaskSize => {
+                        return Err(Error::Validation("mesh task size is not a varying"))
+                    }
+                    Bi::CullPrimitive => {
+                        self.decorate(
+                            id,
+                            Decoration::BuiltIn,
+                            &[super::mesh::BUILT_IN_CULL_PRIMITIVE_EXT],
+                        );
+                        return Ok(());
+                    }
                 };
 
                 self.decorate(id, Decoration::BuiltIn, &[built_in as u32]);
             }
         }
 
-        Ok(id)
+        Ok(())
     }
 
     fn write_global_variable(
@@ -1026,7 +1205,14 @@ impl Writer {
         let id = self.id_gen.next();
 
         let class = map_storage_class(global_variable.class);
-        self.check(class.required_capabilities())?;
+        if let Some(class) = spirv::StorageClass::from_u32(class) {
+            self.check(class.required_capabilities())?;
+        }
+        if let crate::TypeInner::BindingArray { .. } = ir_module.types[global_variable.ty].inner {
+            if global_variable.class != crate::StorageClass::Handle {
+                return Err(Error::FeatureNotImplemented("binding arrays of buffers"));
+            }
+        }
 
         let init_word = global_variable
             .init
@@ -1195,6 +1381,74 @@ impl Writer {
         }
     }
 
+    /// Load an element of a binding array of images or samplers.
+    ///
+    /// Non-uniform indices need both the pointer and the loaded
+    /// handle to be decorated with `NonUniform`.
+    #[allow(clippy::too_many_arguments)]
+    fn write_binding_array_access(
+        &mut self,
+        ir_module: &crate::Module,
+        ir_function: &crate::Function,
+        fun_info: &FunctionInfo,
+        expr_handle: Handle<crate::Expression>,
+        base: Handle<crate::Expression>,
+        index_id: Word,
+        non_uniform: bool,
+        block: &mut Block,
+    ) -> Result<Word, Error> {
+        let global_id = match ir_function.expressions[base] {
+            crate::Expression::GlobalVariable(handle) => self.global_variables[handle.index()].id,
+            _ => {
+                return Err(Error::Validation(
+                    "binding arrays can only be accessed through their global variable",
+                ))
+            }
+        };
+        let element_ty = match fun_info[expr_handle].ty {
+            TypeResolution::Handle(ty) => ty,
+            TypeResolution::Value(_) => {
+                return Err(Error::Validation(
+                    "binding array element must be a type handle",
+                ))
+            }
+        };
+        let pointer_type_id = self.get_pointer_id(
+            &ir_module.types,
+            element_ty,
+            spirv::StorageClass::UniformConstant as Word,
+        )?;
+        let result_type_id = self.get_type_id(LookupType::Handle(element_ty))?;
+
+        let pointer_id = self.id_gen.next();
+        block.body.push(Instruction::access_chain(
+            pointer_type_id,
+            pointer_id,
+            global_id,
+            &[index_id],
+        ));
+        let id = self.id_gen.next();
+        block
+            .body
+            .push(Instruction::load(result_type_id, id, pointer_id, None));
+
+        if non_uniform {
+            let required_caps = match ir_module.types[element_ty].inner {
+                crate::TypeInner::Image {
+                    class: crate::ImageClass::Storage(_),
+                    ..
+                } => spirv::Capability::StorageImageArrayNonUniformIndexing,
+                _ => spirv::Capability::SampledImageArrayNonUniformIndexing,
+            };
+            self.check(&[spirv::Capability::ShaderNonUniform])?;
+            self.check(&[required_caps])?;
+            self.decorate(pointer_id, spirv::Decoration::NonUniform, &[]);
+            self.decorate(id, spirv::Decoration::NonUniform, &[]);
+        }
+
+        Ok(id)
+    }
+
     /// Cache an expression for a value.
     fn cache_expression_value(
         &mut self,
@@ -1208,6 +1462,43 @@ impl Writer {
         let result_type_id = self.get_expression_type_id(&fun_info[expr_handle].ty)?;
 
         let id = match ir_function.expressions[expr_handle] {
+            crate::Expression::Access { base, index }
+                if fun_info[base]
+                    .ty
+                    .inner_with(&ir_module.types)
+                    .is_binding_array(&ir_module.types) =>
+            {
+                let index_id = self.cached[index];
+                let non_uniform = fun_info[index].uniformity.non_uniform_result.is_some();
+                self.write_binding_array_access(
+                    ir_module,
+                    ir_function,
+                    fun_info,
+                    expr_handle,
+                    base,
+                    index_id,
+                    non_uniform,
+                    block,
+                )?
+            }
+            crate::Expression::AccessIndex { base, index }
+                if fun_info[base]
+                    .ty
+                    .inner_with(&ir_module.types)
+                    .is_binding_array(&ir_module.types) =>
+            {
+                let index_id = self.get_index_constant(index)?;
+                self.write_binding_array_access(
+                    ir_module,
+                    ir_function,
+                    fun_info,
+                    expr_handle,
+                    base,
+                    index_id,
+                    false,
+                    block,
+                )?
+            }
             crate::Expression::Access { base, index: _ }
                 if self.is_intermediate(base, ir_function, &ir_module.types) =>
             {
@@ -1482,6 +1773,7 @@ impl Writer {
                 arg,
                 arg1,
                 arg2,
+                arg3,
             } => {
                 use crate::MathFunction as Mf;
                 enum MathOp {
@@ -1499,6 +1791,10 @@ impl Writer {
                     Some(handle) => self.cached[handle],
                     None => 0,
                 };
+                let arg3_id = match arg3 {
+                    Some(handle) => self.cached[handle],
+                    None => 0,
+                };
 
                 let id = self.id_gen.next();
                 let math_op = match fun {
@@ -1604,10 +1900,149 @@ impl Writer {
                         arg0_id,
                     )),
                     Mf::Determinant => MathOp::Ext(spirv::GLOp::Determinant),
-                    Mf::ReverseBits | Mf::CountOneBits => {
-                        log::error!("unimplemented math function {:?}", fun);
-                        return Err(Error::FeatureNotImplemented("math function"));
+                    // bits
+                    Mf::CountTrailingZeros => {
+                        // `FindILsb` returns -1 for zero, which is the
+                        // biggest unsigned value, so clamp it to 32
+                        let vector_size =
+                            arg_vector_size(fun_info[arg].ty.inner_with(&ir_module.types));
+                        let uint_type_id = self.get_uint_vector_type_id(vector_size)?;
+                        let thirty_two_id = self.get_uint_splat_id(32, vector_size)?;
+                        let lsb_id = self.id_gen.next();
+                        block.body.push(Instruction::ext_inst(
+                            self.gl450_ext_inst_id,
+                            spirv::GLOp::FindILsb,
+                            uint_type_id,
+                            lsb_id,
+                            &[arg0_id],
+                        ));
+                        if arg_scalar_kind == Some(crate::ScalarKind::Sint) {
+                            let min_id = self.id_gen.next();
+                            block.body.push(Instruction::ext_inst(
+                                self.gl450_ext_inst_id,
+                                spirv::GLOp::UMin,
+                                uint_type_id,
+                                min_id,
+                                &[lsb_id, thirty_two_id],
+                            ));
+                            MathOp::Custom(Instruction::unary(
+                                spirv::Op::Bitcast,
+                                result_type_id,
+                                id,
+                                min_id,
+                            ))
+                        } else {
+                            MathOp::Custom(Instruction::ext_inst(
+                                self.gl450_ext_inst_id,
+                                spirv::GLOp::UMin,
+                                result_type_id,
+                                id,
+                                &[lsb_id, thirty_two_id],
+                            ))
+                        }
+                    }
+                    Mf::CountLeadingZeros => {
+                        // `FindUMsb` returns -1 for zero, so that
+                        // `31 - msb` wraps around to 32
+                        let vector_size =
+                            arg_vector_size(fun_info[arg].ty.inner_with(&ir_module.types));
+                        let uint_type_id = self.get_uint_vector_type_id(vector_size)?;
+                        let thirty_one_id = self.get_uint_splat_id(31, vector_size)?;
+                        let signed = arg_scalar_kind == Some(crate::ScalarKind::Sint);
+                        let uint_arg_id = if signed {
+                            let cast_id = self.id_gen.next();
+                            block.body.push(Instruction::unary(
+                                spirv::Op::Bitcast,
+                                uint_type_id,
+                                cast_id,
+                                arg0_id,
+                            ));
+                            cast_id
+                        } else {
+                            arg0_id
+                        };
+                        let msb_id = self.id_gen.next();
+                        block.body.push(Instruction::ext_inst(
+                            self.gl450_ext_inst_id,
+                            spirv::GLOp::FindUMsb,
+                            uint_type_id,
+                            msb_id,
+                            &[uint_arg_id],
+                        ));
+                        if signed {
+                            let sub_id = self.id_gen.next();
+                            block.body.push(Instruction::binary(
+                                spirv::Op::ISub,
+                                uint_type_id,
+                                sub_id,
+                                thirty_one_id,
+                                msb_id,
+                            ));
+                            MathOp::Custom(Instruction::unary(
+                                spirv::Op::Bitcast,
+                                result_type_id,
+                                id,
+                                sub_id,
+                            ))
+                        } else {
+                            MathOp::Custom(Instruction::binary(
+                                spirv::Op::ISub,
+                                result_type_id,
+                                id,
+                                thirty_one_id,
+                                msb_id,
+                            ))
+                        }
                     }
+                    Mf::CountOneBits => MathOp::Custom(Instruction::unary(
+                        spirv::Op::BitCount,
+                        result_type_id,
+                        id,
+                        arg0_id,
+                    )),
+                    Mf::ReverseBits => MathOp::Custom(Instruction::unary(
+                        spirv::Op::BitReverse,
+                        result_type_id,
+                        id,
+                        arg0_id,
+                    )),
+                    Mf::ExtractBits => MathOp::Custom(Instruction::ternary(
+                        match arg_scalar_kind {
+                            Some(crate::ScalarKind::Sint) => spirv::Op::BitFieldSExtract,
+                            _ => spirv::Op::BitFieldUExtract,
+                        },
+                        result_type_id,
+                        id,
+                        arg0_id,
+                        arg1_id,
+                        arg2_id,
+                    )),
+                    Mf::InsertBits => MathOp::Custom(Instruction::quaternary(
+                        spirv::Op::BitFieldInsert,
+                        result_type_id,
+                        id,
+                        arg0_id,
+                        arg1_id,
+                        arg2_id,
+                        arg3_id,
+                    )),
+                    Mf::FirstTrailingBit => MathOp::Ext(spirv::GLOp::FindILsb),
+                    Mf::FirstLeadingBit => MathOp::Ext(match arg_scalar_kind {
+                        Some(crate::ScalarKind::Sint) => spirv::GLOp::FindSMsb,
+                        _ => spirv::GLOp::FindUMsb,
+                    }),
+                    // data packing
+                    Mf::Pack4x8snorm => MathOp::Ext(spirv::GLOp::PackSnorm4x8),
+                    Mf::Pack4x8unorm => MathOp::Ext(spirv::GLOp::PackUnorm4x8),
+                    Mf::Pack2x16snorm => MathOp::Ext(spirv::GLOp::PackSnorm2x16),
+                    Mf::Pack2x16unorm => MathOp::Ext(spirv::GLOp::PackUnorm2x16),
+                    Mf::Pack2x16float => MathOp::Ext(spirv::GLOp::PackHalf2x16),
+                    // data unpacking
+                    Mf::Unpack4x8snorm => MathOp::Ext(spirv::GLOp::UnpackSnorm4x8),
+                    Mf::Unpack4x8unorm => MathOp::Ext(spirv::GLOp::UnpackUnorm4x8),
+                    Mf::Unpack2x16snorm => MathOp::Ext(spirv::GLOp::UnpackSnorm2x16),
+                    Mf::Unpack2x16unorm => MathOp::Ext(spirv::GLOp::UnpackUnorm2x16),
+                    Mf::Unpack2x16float => MathOp::Ext(spirv::GLOp::UnpackHalf2x16),
                 };
 
                 block.body.push(match math_op {
@@ -1616,7 +2051,7 @@ impl Writer {
                         op,
                         result_type_id,
                         id,
-                        &[arg0_id, arg1_id, arg2_id][..fun.argument_count()],
+                        &[arg0_id, arg1_id, arg2_id, arg3_id][..fun.argument_count()],
                     ),
                     MathOp::Custom(inst) => inst,
                 });
@@ -1624,6 +2059,30 @@ impl Writer {
             }
             crate::Expression::LocalVariable(variable) => function.variables[&variable].id,
             crate::Expression::Load { pointer } => {
+                let atomic_class = match *fun_info[pointer].ty.inner_with(&ir_module.types) {
+                    crate::TypeInner::Pointer { base, class } => {
+                        match ir_module.types[base].inner {
+                            crate::TypeInner::Atomic { .. } => Some(class),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                let atomic_operands = match atomic_class {
+                    Some(class) => Some(self.get_atomic_scope_and_semantics(class)?),
+                    None => None,
+                };
+                let make_load = move |id: Word, pointer_id: Word| match atomic_operands {
+                    Some((scope_id, semantics_id)) => Instruction::atomic_load(
+                        result_type_id,
+                        id,
+                        pointer_id,
+                        scope_id,
+                        semantics_id,
+                    ),
+                    None => Instruction::load(result_type_id, id, pointer_id, None),
+                };
+
                 match self.write_expression_pointer(
                     ir_module,
                     ir_function,
@@ -1634,9 +2093,7 @@ impl Writer {
                 )? {
                     ExpressionPointer::Ready { pointer_id } => {
                         let id = self.id_gen.next();
-                        block
-                            .body
-                            .push(Instruction::load(result_type_id, id, pointer_id, None));
+                        block.body.push(make_load(id, pointer_id));
                         id
                     }
                     ExpressionPointer::Conditional { condition, access } => {
@@ -1650,12 +2107,7 @@ impl Writer {
                                 let pointer_id = access.result_id.unwrap();
                                 let value_id = id_gen.next();
                                 block.body.push(access);
-                                block.body.push(Instruction::load(
-                                    result_type_id,
-                                    value_id,
-                                    pointer_id,
-                                    None,
-                                ));
+                                block.body.push(make_load(value_id, pointer_id));
                                 value_id
                             },
                         )
@@ -1664,6 +2116,18 @@ impl Writer {
             }
             crate::Expression::FunctionArgument(index) => function.parameter_id(index),
             crate::Expression::Call(_function) => self.lookup_function_call[&expr_handle],
+            // the id is assigned by the `Statement::Atomic` producing the result
+            crate::Expression::AtomicResult { .. } => self.cached[expr_handle],
+            // the id is assigned by the subgroup statement producing the result
+            crate::Expression::SubgroupBallotResult
+            | crate::Expression::SubgroupOperationResult { .. } => self.cached[expr_handle],
+            // the id is assigned by the ray query statement producing the result
+            crate::Expression::RayQueryProceedResult => self.cached[expr_handle],
+            crate::Expression::RayQueryGetIntersection {
+                query,
+                committed,
+                ty,
+            } => self.write_ray_query_get_intersection(ir_module, query, committed, ty, block)?,
             crate::Expression::As {
                 expr,
                 kind,
@@ -1782,6 +2246,7 @@ impl Writer {
             crate::Expression::ImageSample {
                 image,
                 sampler,
+                gather,
                 coordinate,
                 array_index,
                 offset,
@@ -1798,7 +2263,7 @@ impl Writer {
                     crate::TypeInner::Image {
                         class: crate::ImageClass::Depth,
                         ..
-                    } => depth_ref.is_none(),
+                    } => depth_ref.is_none() && gather.is_none(),
                     _ => false,
                 };
                 let sample_result_type_id = if needs_sub_access {
@@ -1833,14 +2298,40 @@ impl Writer {
                     image_id,
                     sampler_id,
                 ));
+                // combining a non-uniform image or sampler gives a non-uniform result
+                let non_uniform = fun_info[image].uniformity.non_uniform_result.is_some()
+                    || fun_info[sampler].uniformity.non_uniform_result.is_some();
+                if non_uniform
+                    && self
+                        .capabilities
+                        .contains(&spirv::Capability::ShaderNonUniform)
+                {
+                    self.decorate(sampled_image_id, spirv::Decoration::NonUniform, &[]);
+                }
                 let id = self.id_gen.next();
 
                 let depth_id = depth_ref.map(|handle| self.cached[handle]);
                 let mut mask = spirv::ImageOperands::empty();
                 mask.set(spirv::ImageOperands::CONST_OFFSET, offset.is_some());
 
-                let mut main_instruction = match level {
-                    crate::SampleLevel::Zero => {
+                let mut main_instruction = match (gather, level) {
+                    (Some(component), _) => {
+                        let component_id = self
+                            .get_constant_scalar(crate::ScalarValue::Uint(component as u64), 4)?;
+                        let mut inst = Instruction::image_gather(
+                            sample_result_type_id,
+                            id,
+                            sampled_image_id,
+                            coordinate_id,
+                            component_id,
+                            depth_id,
+                        );
+                        if !mask.is_empty() {
+                            inst.add_operand(mask.bits());
+                        }
+                        inst
+                    }
+                    (None, crate::SampleLevel::Zero) => {
                         let mut inst = Instruction::image_sample(
                             sample_result_type_id,
                             id,
@@ -1859,7 +2350,7 @@ impl Writer {
 
                         inst
                     }
-                    crate::SampleLevel::Auto => {
+                    (None, crate::SampleLevel::Auto) => {
                         let mut inst = Instruction::image_sample(
                             sample_result_type_id,
                             id,
@@ -1873,7 +2364,7 @@ impl Writer {
                         }
                         inst
                     }
-                    crate::SampleLevel::Exact(lod_handle) => {
+                    (None, crate::SampleLevel::Exact(lod_handle)) => {
                         let mut inst = Instruction::image_sample(
                             sample_result_type_id,
                             id,
@@ -1890,7 +2381,7 @@ impl Writer {
 
                         inst
                     }
-                    crate::SampleLevel::Bias(bias_handle) => {
+                    (None, crate::SampleLevel::Bias(bias_handle)) => {
                         let mut inst = Instruction::image_sample(
                             sample_result_type_id,
                             id,
@@ -1907,7 +2398,7 @@ impl Writer {
 
                         inst
                     }
-                    crate::SampleLevel::Gradient { x, y } => {
+                    (None, crate::SampleLevel::Gradient { x, y }) => {
                         let mut inst = Instruction::image_sample(
                             sample_result_type_id,
                             id,
@@ -2270,7 +2761,7 @@ impl Writer {
         Ok(pointer)
     }
 
-    fn get_expression_global(
+    pub(super) fn get_expression_global(
         &self,
         ir_function: &crate::Function,
         expr_handle: Handle<crate::Expression>,
@@ -2283,6 +2774,10 @@ impl Writer {
                 }
                 id
             }
+            // Elements of binding arrays are loaded when accessed
+            crate::Expression::Access { .. } | crate::Expression::AccessIndex { .. } => {
+                self.cached[expr_handle]
+            }
             ref other => unreachable!("Unexpected global expression {:?}", other),
         }
     }
@@ -2321,7 +2816,7 @@ impl Writer {
                     vector_size: None,
                     kind: crate::ScalarKind::Float,
                     width: 4,
-                    pointer_class: Some(spirv::StorageClass::Output),
+                    pointer_class: Some(spirv::StorageClass::Output as Word),
                 }))?;
                 let index_y_id = self.get_index_constant(1)?;
                 body.push(Instruction::access_chain(
@@ -2585,6 +3080,10 @@ impl Writer {
                 crate::Statement::Return { value: Some(value) } => {
                     let value_id = self.cached[value];
                     let instruction = match function.entry_point_context {
+                        Some(EntryPointContext {
+                            mesh_stage: Some(MeshStageContext::Task { payload_id }),
+                            ..
+                        }) => self.write_emit_mesh_tasks(value_id, payload_id, &mut block.body)?,
                         // If this is an entry point, and we need to return anything,
                         // let's instead store the output variables and return `void`.
                         Some(ref context) => {
@@ -2632,6 +3131,23 @@ impl Writer {
                 }
                 crate::Statement::Store { pointer, value } => {
                     let value_id = self.cached[value];
+                    let atomic_operands = match *fun_info[pointer].ty.inner_with(&ir_module.types) {
+                        crate::TypeInner::Pointer { base, class } => {
+                            match ir_module.types[base].inner {
+                                crate::TypeInner::Atomic { .. } => {
+                                    Some(self.get_atomic_scope_and_semantics(class)?)
+                                }
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                    let make_store = move |pointer_id: Word| match atomic_operands {
+                        Some((scope_id, semantics_id)) => {
+                            Instruction::atomic_store(pointer_id, scope_id, semantics_id, value_id)
+                        }
+                        None => Instruction::store(pointer_id, value_id, None),
+                    };
                     match self.write_expression_pointer(
                         ir_module,
                         ir_function,
@@ -2641,9 +3157,7 @@ impl Writer {
                         function,
                     )? {
                         ExpressionPointer::Ready { pointer_id } => {
-                            block
-                                .body
-                                .push(Instruction::store(pointer_id, value_id, None));
+                            block.body.push(make_store(pointer_id));
                         }
                         ExpressionPointer::Conditional { condition, access } => {
                             let merge_block = self.id_gen.next();
@@ -2666,9 +3180,7 @@ impl Writer {
                             // The in-bounds path. Perform the access and the store.
                             let pointer_id = access.result_id.unwrap();
                             block.body.push(access);
-                            block
-                                .body
-                                .push(Instruction::store(pointer_id, value_id, None));
+                            block.body.push(make_store(pointer_id));
 
                             // Finish the in-bounds block and start the merge block. This
                             // is the block we'll leave current on return.
@@ -2731,6 +3243,202 @@ impl Writer {
                         &self.temp_list,
                     ));
                 }
+                crate::Statement::Atomic {
+                    pointer,
+                    ref fun,
+                    value,
+                    result,
+                } => {
+                    let (class, kind, width) = match *fun_info[pointer]
+                        .ty
+                        .inner_with(&ir_module.types)
+                    {
+                        crate::TypeInner::Pointer { base, class } => {
+                            match ir_module.types[base].inner {
+                                crate::TypeInner::Atomic { kind, width } => (class, kind, width),
+                                _ => return Err(Error::Validation("atomic pointer base")),
+                            }
+                        }
+                        _ => return Err(Error::Validation("atomic pointer")),
+                    };
+                    // The instruction produces the original value, which
+                    // is the whole result unless comparing.
+                    let id = self.id_gen.next();
+                    let result_type_id = self.get_type_id(LookupType::Local(LocalType::Value {
+                        vector_size: None,
+                        kind,
+                        width,
+                        pointer_class: None,
+                    }))?;
+                    let (scope_id, semantics_id) = self.get_atomic_scope_and_semantics(class)?;
+                    let value_id = self.cached[value];
+                    let compare_id = match *fun {
+                        crate::AtomicFunction::Exchange { compare: Some(cmp) } => {
+                            Some(self.cached[cmp])
+                        }
+                        _ => None,
+                    };
+
+                    use crate::AtomicFunction as Af;
+                    let make_atomic = move |pointer_id: Word| {
+                        let op = match *fun {
+                            Af::Add => spirv::Op::AtomicIAdd,
+                            Af::Subtract => spirv::Op::AtomicISub,
+                            Af::And => spirv::Op::AtomicAnd,
+                            Af::InclusiveOr => spirv::Op::AtomicOr,
+                            Af::ExclusiveOr => spirv::Op::AtomicXor,
+                            Af::Min => match kind {
+                                crate::ScalarKind::Sint => spirv::Op::AtomicSMin,
+                                _ => spirv::Op::AtomicUMin,
+                            },
+                            Af::Max => match kind {
+                                crate::ScalarKind::Sint => spirv::Op::AtomicSMax,
+                                _ => spirv::Op::AtomicUMax,
+                            },
+                            Af::Exchange { .. } => match compare_id {
+                                Some(comparator_id) => {
+                                    return Instruction::atomic_compare_exchange(
+                                        result_type_id,
+                                        id,
+                                        pointer_id,
+                                        scope_id,
+                                        semantics_id,
+                                        value_id,
+                                        comparator_id,
+                                    );
+                                }
+                                None => spirv::Op::AtomicExchange,
+                            },
+                        };
+                        Instruction::atomic_binary(
+                            op,
+                            result_type_id,
+                            id,
+                            pointer_id,
+                            scope_id,
+                            semantics_id,
+                            value_id,
+                        )
+                    };
+
+                    let old_id = match self.write_expression_pointer(
+                        ir_module,
+                        ir_function,
+                        fun_info,
+                        pointer,
+                        &mut block,
+                        function,
+                    )? {
+                        ExpressionPointer::Ready { pointer_id } => {
+                            block.body.push(make_atomic(pointer_id));
+                            id
+                        }
+                        ExpressionPointer::Conditional { condition, access } => {
+                            // Perform the operation on the in-bounds path only,
+                            // and produce a zero result otherwise.
+                            let instruction = make_atomic(access.result_id.unwrap());
+                            self.write_conditional_indexed_load(
+                                result_type_id,
+                                condition,
+                                function,
+                                &mut block,
+                                move |_, block| {
+                                    block.body.push(access);
+                                    block.body.push(instruction);
+                                    id
+                                },
+                            )
+                        }
+                    };
+
+                    // A compare-exchange results in the original value and
+                    // whether it was exchanged, converted to the same type.
+                    self.cached[result] = if let Some(comparator_id) = compare_id {
+                        let (zero, one) = match kind {
+                            crate::ScalarKind::Sint => {
+                                (crate::ScalarValue::Sint(0), crate::ScalarValue::Sint(1))
+                            }
+                            _ => (crate::ScalarValue::Uint(0), crate::ScalarValue::Uint(1)),
+                        };
+                        let zero_id = self.get_constant_scalar(zero, width)?;
+                        let one_id = self.get_constant_scalar(one, width)?;
+                        let bool_type_id = self.get_bool_type_id()?;
+                        let equal_id = self.id_gen.next();
+                        block.body.push(Instruction::binary(
+                            spirv::Op::IEqual,
+                            bool_type_id,
+                            equal_id,
+                            old_id,
+                            comparator_id,
+                        ));
+                        let exchanged_id = self.id_gen.next();
+                        block.body.push(Instruction::select(
+                            result_type_id,
+                            exchanged_id,
+                            equal_id,
+                            one_id,
+                            zero_id,
+                        ));
+                        let vec_type_id = self.get_expression_type_id(&fun_info[result].ty)?;
+                        let id = self.id_gen.next();
+                        block.body.push(Instruction::composite_construct(
+                            vec_type_id,
+                            id,
+                            &[old_id, exchanged_id],
+                        ));
+                        id
+                    } else {
+                        old_id
+                    };
+                }
+                crate::Statement::SubgroupBallot { result, predicate } => {
+                    self.write_subgroup_ballot(predicate, result, fun_info, &mut block)?;
+                }
+                crate::Statement::SubgroupCollectiveOperation {
+                    op,
+                    collective_op,
+                    argument,
+                    result,
+                } => {
+                    self.write_subgroup_operation(
+                        ir_module,
+                        op,
+                        collective_op,
+                        argument,
+                        result,
+                        fun_info,
+                        &mut block,
+                    )?;
+                }
+                crate::Statement::SubgroupGather {
+                    ref mode,
+                    argument,
+                    result,
+                } => {
+                    self.write_subgroup_gather(mode, argument, result, fun_info, &mut block)?;
+                }
+                crate::Statement::MeshFunction(ref fun) => {
+                    let outputs = match function.entry_point_context {
+                        Some(EntryPointContext {
+                            mesh_stage: Some(MeshStageContext::Mesh(ref outputs)),
+                            ..
+                        }) => outputs,
+                        _ => {
+                            return Err(Error::Validation("mesh function outside of a mesh shader"))
+                        }
+                    };
+                    self.write_mesh_function(ir_module, fun, outputs, fun_info, &mut block)?;
+                }
+                crate::Statement::RayQuery { query, ref fun } => {
+                    self.write_ray_query_function(
+                        ir_module,
+                        ir_function,
+                        query,
+                        fun,
+                        fun_info,
+                        &mut block,
+                    )?;
+                }
             }
         }
 
@@ -2773,6 +3481,26 @@ impl Writer {
             Instruction::extension("SPV_KHR_storage_buffer_storage_class")
                 .to_words(&mut self.logical_layout.extensions);
         }
+        let mut uses_16bit_storage = false;
+        for (_, var) in ir_module.global_variables.iter() {
+            let cap = match var.class {
+                crate::StorageClass::Storage => spirv::Capability::StorageBuffer16BitAccess,
+                crate::StorageClass::Uniform => {
+                    spirv::Capability::UniformAndStorageBuffer16BitAccess
+                }
+                crate::StorageClass::PushConstant => spirv::Capability::StoragePushConstant16,
+                _ => continue,
+            };
+            if contains_half_float(var.ty, &ir_module.types) {
+                self.capabilities.insert(cap);
+                uses_16bit_storage = true;
+            }
+        }
+        if self.physical_layout.version < 0x10300 && uses_16bit_storage {
+            // 16-bit storage is core since SPV-1.3
+            Instruction::extension("SPV_KHR_16bit_storage")
+                .to_words(&mut self.logical_layout.extensions);
+        }
         Instruction::type_void(self.void_type).to_words(&mut self.logical_layout.declarations);
         Instruction::ext_inst_import(self.gl450_ext_inst_id, "GLSL.std.450")
             .to_words(&mut self.logical_layout.ext_inst_imports);
@@ -2784,19 +3512,29 @@ impl Writer {
 
         self.constant_ids.clear();
         self.constant_ids.resize(ir_module.constants.len(), 0);
+        // composites built from specializable constants are specializable too
+        let mut specializable = vec![false; ir_module.constants.len()];
         // first, output all the scalar constants
         for (handle, constant) in ir_module.constants.iter() {
             match constant.inner {
                 crate::ConstantInner::Composite { .. } => continue,
                 crate::ConstantInner::Scalar { width, ref value } => {
-                    self.constant_ids[handle.index()] = match constant.name {
-                        Some(ref name) => {
-                            let id = self.id_gen.next();
-                            self.write_constant_scalar(id, value, width, Some(name))?;
-                            id
-                        }
-                        None => self.get_constant_scalar(*value, width)?,
-                    };
+                    self.constant_ids[handle.index()] =
+                        match (constant.specialization, &constant.name) {
+                            (None, &None) => self.get_constant_scalar(*value, width)?,
+                            (specialization, name) => {
+                                let id = self.id_gen.next();
+                                self.write_constant_scalar(
+                                    id,
+                                    value,
+                                    width,
+                                    name.as_ref(),
+                                    specialization,
+                                )?;
+                                specializable[handle.index()] = specialization.is_some();
+                                id
+                            }
+                        };
                 }
             }
         }
@@ -2818,7 +3556,11 @@ impl Writer {
                             self.debugs.push(Instruction::name(id, name));
                         }
                     }
-                    self.write_constant_composite(id, ty, components)?;
+                    let is_specializable = components
+                        .iter()
+                        .any(|component| specializable[component.index()]);
+                    specializable[handle.index()] = is_specializable;
+                    self.write_constant_composite(id, ty, components, is_specializable)?;
                 }
             }
         }
@@ -2836,7 +3578,7 @@ impl Writer {
         // all functions
         for (handle, ir_function) in ir_module.functions.iter() {
             let info = &mod_info[handle];
-            let id = self.write_function(ir_function, info, ir_module, None)?;
+            let id = self.write_function(ir_function, info, ir_module, None, None)?;
             self.lookup_function.insert(handle, id);
         }
 
@@ -2847,9 +3589,28 @@ impl Writer {
             ep_instruction.to_words(&mut self.logical_layout.entry_points);
         }
 
+        let uses_descriptor_indexing = self.capabilities.iter().any(|&cap| match cap {
+            spirv::Capability::ShaderNonUniform | spirv::Capability::RuntimeDescriptorArray => true,
+            _ => false,
+        });
+        if self.physical_layout.version < 0x10500 && uses_descriptor_indexing {
+            // descriptor indexing is core since SPV-1.5
+            Instruction::extension("SPV_EXT_descriptor_indexing")
+                .to_words(&mut self.logical_layout.extensions);
+        }
+        if self.physical_layout.version < 0x10300
+            && self.capabilities.contains(&spirv::Capability::MultiView)
+        {
+            // multiview is core since SPV-1.3
+            Instruction::extension("SPV_KHR_multiview")
+                .to_words(&mut self.logical_layout.extensions);
+        }
+
         for capability in self.capabilities.iter() {
             Instruction::capability(*capability).to_words(&mut self.logical_layout.capabilities);
         }
+        self.write_mesh_shading_requirements(ir_module)?;
+        self.write_ray_query_requirements(ir_module)?;
         if ir_module.entry_points.is_empty() {
             // SPIR-V doesn't like modules without entry points
             Instruction::capability(spirv::Capability::Linkage)
@@ -2887,6 +3648,10 @@ impl Writer {
         self.lookup_function_type.clear();
         self.lookup_function_call.clear();
 
+        if !ir_module.derived_constants.is_empty() {
+            return Err(Error::DerivedConstants);
+        }
+
         self.write_logical_layout(ir_module, info)?;
         self.write_physical_layout();
 
@@ -24,10 +24,17 @@ enum Attribute {
     Block,
     BuiltIn(crate::BuiltIn),
     Group(u32),
+    Index(u32),
     Interpolate(Option<Interpolation>, Option<Sampling>),
     Location(u32),
+    MaxPrimitives(u32),
+    MaxVertices(u32),
+    OutputTopology(crate::MeshOutputTopology),
+    Payload(Handle<GlobalVariable>),
+    PrimitiveOutput(Handle<Type>),
     Stage(ShaderStage),
     Stride(u32),
+    VertexOutput(Handle<Type>),
     WorkGroupSize([u32; 3]),
 }
 
@@ -67,6 +74,27 @@ impl<W: Write> Writer<W> {
             }
         }
 
+        // Write the extensions required by the types
+        let uses_f16 = module.types.iter().any(|(_, ty)| match ty.inner {
+            TypeInner::Scalar { kind, width } | TypeInner::Vector { kind, width, .. } => {
+                kind == ScalarKind::Float && width == 2
+            }
+            TypeInner::Matrix { width, .. } => width == 2,
+            _ => false,
+        });
+        if uses_f16 {
+            writeln!(self.out, "enable f16;")?;
+            writeln!(self.out)?;
+        }
+        let uses_mesh_shading = module.entry_points.iter().any(|ep| match ep.stage {
+            ShaderStage::Task | ShaderStage::Mesh => true,
+            _ => false,
+        });
+        if uses_mesh_shading {
+            writeln!(self.out, "enable mesh_shading;")?;
+            writeln!(self.out)?;
+        }
+
         // Write all structs
         for (handle, ty) in module.types.iter() {
             if let TypeInner::Struct {
@@ -82,7 +110,7 @@ impl<W: Write> Writer<W> {
 
         // Write all constants
         for (handle, constant) in module.constants.iter() {
-            if constant.name.is_some() {
+            if constant.name.is_some() || constant.specialization.is_some() {
                 self.write_global_constant(module, &constant.inner, handle)?;
             }
         }
@@ -116,13 +144,25 @@ impl<W: Write> Writer<W> {
 
         // Write all entry points
         for (index, ep) in module.entry_points.iter().enumerate() {
-            let attributes = match ep.stage {
+            let mut attributes = match ep.stage {
                 ShaderStage::Vertex | ShaderStage::Fragment => vec![Attribute::Stage(ep.stage)],
-                ShaderStage::Compute => vec![
-                    Attribute::Stage(ShaderStage::Compute),
+                ShaderStage::Compute | ShaderStage::Task | ShaderStage::Mesh => vec![
+                    Attribute::Stage(ep.stage),
                     Attribute::WorkGroupSize(ep.workgroup_size),
                 ],
             };
+            if let Some(payload) = ep.task_payload {
+                attributes.push(Attribute::Payload(payload));
+            }
+            if let Some(ref mesh_info) = ep.mesh_info {
+                attributes.push(Attribute::OutputTopology(mesh_info.topology));
+                attributes.push(Attribute::MaxVertices(mesh_info.max_vertices));
+                attributes.push(Attribute::MaxPrimitives(mesh_info.max_primitives));
+                attributes.push(Attribute::VertexOutput(mesh_info.vertex_output_type));
+                if let Some(ty) = mesh_info.primitive_output_type {
+                    attributes.push(Attribute::PrimitiveOutput(ty));
+                }
+            }
 
             self.write_attributes(&attributes, false)?;
             // Add a newline after attribute
@@ -148,12 +188,15 @@ impl<W: Write> Writer<W> {
     ///
     /// # Notes
     /// Adds no trailing or leading whitespace
-    fn write_scalar_value(&mut self, value: ScalarValue) -> BackendResult {
+    fn write_scalar_value(&mut self, value: ScalarValue, width: crate::Bytes) -> BackendResult {
         match value {
+            ScalarValue::Sint(value) if width == 8 => write!(self.out, "{}i64", value)?,
             ScalarValue::Sint(value) => write!(self.out, "{}", value)?,
+            ScalarValue::Uint(value) if width == 8 => write!(self.out, "{}u64", value)?,
             ScalarValue::Uint(value) => write!(self.out, "{}u", value)?,
             // Floats are written using `Debug` instead of `Display` because it always appends the
             // decimal part even it's zero
+            ScalarValue::Float(value) if width == 2 => write!(self.out, "{:?}f16", value)?,
             ScalarValue::Float(value) => write!(self.out, "{:?}", value)?,
             ScalarValue::Bool(value) => write!(self.out, "{}", value)?,
         }
@@ -172,6 +215,8 @@ impl<W: Write> Writer<W> {
                     ShaderStage::Compute => "ComputeOutput",
                     ShaderStage::Fragment => "FragmentOutput",
                     ShaderStage::Vertex => "VertexOutput",
+                    ShaderStage::Task => "TaskOutput",
+                    ShaderStage::Mesh => "MeshOutput",
                 };
 
                 write!(self.out, "{}", name)?;
@@ -307,6 +352,7 @@ impl<W: Write> Writer<W> {
                 }
                 Attribute::Block => String::from("block"),
                 Attribute::Location(id) => format!("location({})", id),
+                Attribute::Index(index) => format!("index({})", index),
                 Attribute::BuiltIn(builtin_attrib) => {
                     let builtin_str = builtin_str(builtin_attrib);
                     if let Some(builtin) = builtin_str {
@@ -320,7 +366,28 @@ impl<W: Write> Writer<W> {
                     ShaderStage::Vertex => String::from("stage(vertex)"),
                     ShaderStage::Fragment => String::from("stage(fragment)"),
                     ShaderStage::Compute => String::from("stage(compute)"),
+                    ShaderStage::Task => String::from("stage(task)"),
+                    ShaderStage::Mesh => String::from("stage(mesh)"),
                 },
+                Attribute::OutputTopology(topology) => {
+                    let topology_str = match topology {
+                        crate::MeshOutputTopology::Points => "points",
+                        crate::MeshOutputTopology::Lines => "lines",
+                        crate::MeshOutputTopology::Triangles => "triangles",
+                    };
+                    format!("output_topology({})", topology_str)
+                }
+                Attribute::MaxVertices(count) => format!("max_vertices({})", count),
+                Attribute::MaxPrimitives(count) => format!("max_primitives({})", count),
+                Attribute::VertexOutput(ty) => {
+                    format!("vertex_output({})", self.names[&NameKey::Type(ty)])
+                }
+                Attribute::PrimitiveOutput(ty) => {
+                    format!("primitive_output({})", self.names[&NameKey::Type(ty)])
+                }
+                Attribute::Payload(handle) => {
+                    format!("payload({})", self.names[&NameKey::GlobalVariable(handle)])
+                }
                 Attribute::Stride(stride) => format!("stride({})", stride),
                 Attribute::WorkGroupSize(size) => {
                     format!("workgroup_size({}, {}, {})", size[0], size[1], size[2])
@@ -450,10 +517,14 @@ impl<W: Write> Writer<W> {
     /// Adds no trailing or leading whitespace
     fn write_value_type(&mut self, module: &Module, inner: &TypeInner) -> BackendResult {
         match *inner {
-            TypeInner::Vector { size, kind, .. } => write!(
+            TypeInner::Vector { size, kind, width } => write!(
                 self.out,
                 "{}",
-                format!("vec{}<{}>", vector_size_str(size), scalar_kind_str(kind),)
+                format!(
+                    "vec{}<{}>",
+                    vector_size_str(size),
+                    scalar_kind_str(kind, width),
+                )
             )?,
             TypeInner::Sampler { comparison: false } => {
                 write!(self.out, "sampler")?;
@@ -461,6 +532,8 @@ impl<W: Write> Writer<W> {
             TypeInner::Sampler { comparison: true } => {
                 write!(self.out, "sampler_comparison")?;
             }
+            TypeInner::AccelerationStructure => write!(self.out, "acceleration_structure")?,
+            TypeInner::RayQuery => write!(self.out, "ray_query")?,
             TypeInner::Image {
                 dim,
                 arrayed,
@@ -473,7 +546,7 @@ impl<W: Write> Writer<W> {
                     ImageClass::Sampled { kind, multi } => (
                         "",
                         if multi { "multisampled_" } else { "" },
-                        format!("<{}>", scalar_kind_str(kind)),
+                        format!("<{}>", scalar_kind_str(kind, 4)),
                     ),
                     ImageClass::Depth => ("depth_", "", String::from("")),
                     ImageClass::Storage(storage_format) => (
@@ -488,8 +561,11 @@ impl<W: Write> Writer<W> {
                 );
                 write!(self.out, "{}", ty_str)?;
             }
-            TypeInner::Scalar { kind, .. } => {
-                write!(self.out, "{}", scalar_kind_str(kind))?;
+            TypeInner::Scalar { kind, width } => {
+                write!(self.out, "{}", scalar_kind_str(kind, width))?;
+            }
+            TypeInner::Atomic { kind, width } => {
+                write!(self.out, "atomic<{}>", scalar_kind_str(kind, width))?;
             }
             TypeInner::Array { base, size, .. } => {
                 // More info https://gpuweb.github.io/gpuweb/wgsl/#array-types
@@ -508,17 +584,28 @@ impl<W: Write> Writer<W> {
                 }
                 write!(self.out, ">")?;
             }
+            TypeInner::BindingArray { base, size } => {
+                // binding_array<A, 3> -- Constant array
+                // binding_array<A> -- Dynamic array
+                write!(self.out, "binding_array<")?;
+                self.write_type(module, base)?;
+                if let ArraySize::Constant(handle) = size {
+                    write!(self.out, ",")?;
+                    self.write_constant(module, handle)?;
+                }
+                write!(self.out, ">")?;
+            }
             TypeInner::Matrix {
                 columns,
                 rows,
-                width: _,
+                width,
             } => {
                 write!(
                     self.out,
-                    //TODO: Can matrix be other than f32?
-                    "mat{}x{}<f32>",
+                    "mat{}x{}<{}>",
                     vector_size_str(columns),
                     vector_size_str(rows),
+                    scalar_kind_str(ScalarKind::Float, width),
                 )?;
             }
             TypeInner::Pointer { base, class } => {
@@ -526,7 +613,7 @@ impl<W: Write> Writer<W> {
                 if let Some(storage_class) = storage_class_str(class) {
                     write!(self.out, "<{}>", storage_class)?;
                 }
-            },
+            }
             _ => {
                 return Err(Error::Unimplemented(format!(
                     "write_value_type {:?}",
@@ -654,10 +741,22 @@ impl<W: Write> Writer<W> {
                     }
                 }
                 write!(self.out, "{}", INDENT.repeat(indent))?;
-                self.write_expr(module, pointer, func_ctx)?;
-                write!(self.out, " = ")?;
-                self.write_expr(module, value, func_ctx)?;
-                writeln!(self.out, ";")?
+                let is_atomic = func_ctx.info[pointer]
+                    .ty
+                    .inner_with(&module.types)
+                    .is_atomic_pointer(&module.types);
+                if is_atomic {
+                    write!(self.out, "atomicStore(&")?;
+                    self.write_expr(module, pointer, func_ctx)?;
+                    write!(self.out, ", ")?;
+                    self.write_expr(module, value, func_ctx)?;
+                    writeln!(self.out, ");")?
+                } else {
+                    self.write_expr(module, pointer, func_ctx)?;
+                    write!(self.out, " = ")?;
+                    self.write_expr(module, value, func_ctx)?;
+                    writeln!(self.out, ";")?
+                }
             }
             Statement::Call {
                 function,
@@ -683,6 +782,134 @@ impl<W: Write> Writer<W> {
                 }
                 writeln!(self.out, ");")?
             }
+            Statement::Atomic {
+                pointer,
+                ref fun,
+                value,
+                result,
+            } => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                let name = format!("{}{}", BAKE_PREFIX, result.index());
+                self.start_named_expr(module, result, func_ctx, &name)?;
+                self.named_expressions.insert(result, name);
+
+                let fun_str = atomic_fun_str(fun);
+                write!(self.out, "atomic{}(&", fun_str)?;
+                self.write_expr(module, pointer, func_ctx)?;
+                if let crate::AtomicFunction::Exchange { compare: Some(cmp) } = *fun {
+                    write!(self.out, ", ")?;
+                    self.write_expr(module, cmp, func_ctx)?;
+                }
+                write!(self.out, ", ")?;
+                self.write_expr(module, value, func_ctx)?;
+                writeln!(self.out, ");")?
+            }
+            Statement::SubgroupBallot { result, predicate } => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                let name = format!("{}{}", BAKE_PREFIX, result.index());
+                self.start_named_expr(module, result, func_ctx, &name)?;
+                self.named_expressions.insert(result, name);
+
+                write!(self.out, "subgroupBallot(")?;
+                if let Some(predicate) = predicate {
+                    self.write_expr(module, predicate, func_ctx)?;
+                }
+                writeln!(self.out, ");")?
+            }
+            Statement::SubgroupCollectiveOperation {
+                op,
+                collective_op,
+                argument,
+                result,
+            } => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                let name = format!("{}{}", BAKE_PREFIX, result.index());
+                self.start_named_expr(module, result, func_ctx, &name)?;
+                self.named_expressions.insert(result, name);
+
+                let prefix = match collective_op {
+                    crate::CollectiveOperation::Reduce => "",
+                    crate::CollectiveOperation::InclusiveScan => "Inclusive",
+                    crate::CollectiveOperation::ExclusiveScan => "Exclusive",
+                };
+                write!(self.out, "subgroup{}{}(", prefix, subgroup_op_str(op))?;
+                self.write_expr(module, argument, func_ctx)?;
+                writeln!(self.out, ");")?
+            }
+            Statement::SubgroupGather {
+                ref mode,
+                argument,
+                result,
+            } => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                let name = format!("{}{}", BAKE_PREFIX, result.index());
+                self.start_named_expr(module, result, func_ctx, &name)?;
+                self.named_expressions.insert(result, name);
+
+                let fun_str = match *mode {
+                    crate::GatherMode::BroadcastFirst => "BroadcastFirst",
+                    crate::GatherMode::Broadcast(_) => "Broadcast",
+                    crate::GatherMode::Shuffle(_) => "Shuffle",
+                    crate::GatherMode::ShuffleDown(_) => "ShuffleDown",
+                    crate::GatherMode::ShuffleUp(_) => "ShuffleUp",
+                    crate::GatherMode::ShuffleXor(_) => "ShuffleXor",
+                };
+                write!(self.out, "subgroup{}(", fun_str)?;
+                self.write_expr(module, argument, func_ctx)?;
+                if let Some(index) = mode.index() {
+                    write!(self.out, ", ")?;
+                    self.write_expr(module, index, func_ctx)?;
+                }
+                writeln!(self.out, ");")?
+            }
+            Statement::MeshFunction(ref fun) => {
+                let (fun_str, first, second) = match *fun {
+                    crate::MeshFunction::SetMeshOutputs {
+                        vertex_count,
+                        primitive_count,
+                    } => ("setMeshOutputs", vertex_count, primitive_count),
+                    crate::MeshFunction::SetVertex { index, value } => ("setVertex", index, value),
+                    crate::MeshFunction::SetIndices { index, value } => {
+                        ("setIndices", index, value)
+                    }
+                    crate::MeshFunction::SetPrimitive { index, value } => {
+                        ("setPrimitive", index, value)
+                    }
+                };
+                write!(self.out, "{}{}(", INDENT.repeat(indent), fun_str)?;
+                self.write_expr(module, first, func_ctx)?;
+                write!(self.out, ", ")?;
+                self.write_expr(module, second, func_ctx)?;
+                writeln!(self.out, ");")?;
+            }
+            Statement::RayQuery { query, ref fun } => {
+                write!(self.out, "{}", INDENT.repeat(indent))?;
+                match *fun {
+                    crate::RayQueryFunction::Initialize {
+                        acceleration_structure,
+                        descriptor,
+                    } => {
+                        write!(self.out, "rayQueryInitialize(&")?;
+                        self.write_expr(module, query, func_ctx)?;
+                        write!(self.out, ", ")?;
+                        self.write_expr(module, acceleration_structure, func_ctx)?;
+                        write!(self.out, ", ")?;
+                        self.write_expr(module, descriptor, func_ctx)?;
+                    }
+                    crate::RayQueryFunction::Proceed { result } => {
+                        let name = format!("{}{}", BAKE_PREFIX, result.index());
+                        self.start_named_expr(module, result, func_ctx, &name)?;
+                        self.named_expressions.insert(result, name);
+                        write!(self.out, "rayQueryProceed(&")?;
+                        self.write_expr(module, query, func_ctx)?;
+                    }
+                    crate::RayQueryFunction::Terminate => {
+                        write!(self.out, "rayQueryTerminate(&")?;
+                        self.write_expr(module, query, func_ctx)?;
+                    }
+                }
+                writeln!(self.out, ");")?;
+            }
             Statement::ImageStore {
                 image,
                 coordinate,
@@ -940,6 +1167,7 @@ impl<W: Write> Writer<W> {
                     }
                     TypeInner::Matrix { .. }
                     | TypeInner::Array { .. }
+                    | TypeInner::BindingArray { .. }
                     | TypeInner::ValuePointer { .. } => write!(self.out, "[{}]", index)?,
                     TypeInner::Struct { .. } => {
                         // This will never panic in case the type is a `Struct`, this is not true
@@ -958,6 +1186,53 @@ impl<W: Write> Writer<W> {
             Expression::ImageSample {
                 image,
                 sampler,
+                gather: Some(component),
+                coordinate,
+                array_index,
+                offset,
+                level: _,
+                depth_ref,
+            } => {
+                let suffix_cmp = match depth_ref {
+                    Some(_) => "Compare",
+                    None => "",
+                };
+
+                write!(self.out, "textureGather{}(", suffix_cmp)?;
+                match *func_ctx.info[image].ty.inner_with(&module.types) {
+                    TypeInner::Image {
+                        class: ImageClass::Depth,
+                        ..
+                    } => {}
+                    _ => write!(self.out, "{}, ", component as u8)?,
+                }
+                self.write_expr(module, image, func_ctx)?;
+                write!(self.out, ", ")?;
+                self.write_expr(module, sampler, func_ctx)?;
+                write!(self.out, ", ")?;
+                self.write_expr(module, coordinate, func_ctx)?;
+
+                if let Some(array_index) = array_index {
+                    write!(self.out, ", ")?;
+                    self.write_expr(module, array_index, func_ctx)?;
+                }
+
+                if let Some(depth_ref) = depth_ref {
+                    write!(self.out, ", ")?;
+                    self.write_expr(module, depth_ref, func_ctx)?;
+                }
+
+                if let Some(offset) = offset {
+                    write!(self.out, ", ")?;
+                    self.write_constant(module, offset)?;
+                }
+
+                write!(self.out, ")")?;
+            }
+            Expression::ImageSample {
+                image,
+                sampler,
+                gather: None,
                 coordinate,
                 array_index,
                 offset,
@@ -1063,26 +1338,39 @@ impl<W: Write> Writer<W> {
                 let name = &self.names[&NameKey::GlobalVariable(handle)];
                 write!(self.out, "{}", name)?;
             }
-            Expression::As { expr, kind, .. } => {
+            Expression::As {
+                expr,
+                kind,
+                convert,
+            } => {
                 let inner = func_ctx.info[expr].ty.inner_with(&module.types);
                 match *inner {
-                    TypeInner::Matrix { columns, rows, .. } => {
+                    TypeInner::Matrix {
+                        columns,
+                        rows,
+                        width,
+                    } => {
                         write!(
                             self.out,
-                            "mat{}x{}<f32>",
+                            "mat{}x{}<{}>",
                             vector_size_str(columns),
-                            vector_size_str(rows)
+                            vector_size_str(rows),
+                            scalar_kind_str(kind, convert.unwrap_or(width))
                         )?;
                     }
-                    TypeInner::Vector { size, .. } => {
+                    TypeInner::Vector { size, width, .. } => {
                         write!(
                             self.out,
                             "vec{}<{}>",
                             vector_size_str(size),
-                            scalar_kind_str(kind)
+                            scalar_kind_str(kind, convert.unwrap_or(width))
                         )?;
                     }
-                    TypeInner::Scalar { .. } => write!(self.out, "{}", scalar_kind_str(kind))?,
+                    TypeInner::Scalar { width, .. } => write!(
+                        self.out,
+                        "{}",
+                        scalar_kind_str(kind, convert.unwrap_or(width))
+                    )?,
                     _ => {
                         return Err(Error::Unimplemented(format!(
                             "write_expr expression::as {:?}",
@@ -1096,8 +1384,8 @@ impl<W: Write> Writer<W> {
             }
             Expression::Splat { size, value } => {
                 let inner = func_ctx.info[value].ty.inner_with(&module.types);
-                let scalar_kind = match *inner {
-                    crate::TypeInner::Scalar { kind, .. } => kind,
+                let (scalar_kind, scalar_width) = match *inner {
+                    crate::TypeInner::Scalar { kind, width } => (kind, width),
                     _ => {
                         return Err(Error::Unimplemented(format!(
                             "write_expr expression::splat {:?}",
@@ -1105,14 +1393,26 @@ impl<W: Write> Writer<W> {
                         )));
                     }
                 };
-                let scalar = scalar_kind_str(scalar_kind);
+                let scalar = scalar_kind_str(scalar_kind, scalar_width);
                 let size = vector_size_str(size);
 
                 write!(self.out, "vec{}<{}>(", size, scalar)?;
                 self.write_expr(module, value, func_ctx)?;
                 write!(self.out, ")")?;
             }
-            Expression::Load { pointer } => self.write_expr(module, pointer, func_ctx)?,
+            Expression::Load { pointer } => {
+                let is_atomic = func_ctx.info[pointer]
+                    .ty
+                    .inner_with(&module.types)
+                    .is_atomic_pointer(&module.types);
+                if is_atomic {
+                    write!(self.out, "atomicLoad(&")?;
+                    self.write_expr(module, pointer, func_ctx)?;
+                    write!(self.out, ")")?;
+                } else {
+                    self.write_expr(module, pointer, func_ctx)?;
+                }
+            }
             Expression::LocalVariable(handle) => {
                 write!(self.out, "{}", self.names[&func_ctx.name_key(handle)])?
             }
@@ -1129,6 +1429,7 @@ impl<W: Write> Writer<W> {
                 arg,
                 arg1,
                 arg2,
+                arg3,
             } => {
                 use crate::MathFunction as Mf;
 
@@ -1183,8 +1484,26 @@ impl<W: Write> Writer<W> {
                     Mf::Transpose => "transpose",
                     Mf::Determinant => "determinant",
                     // bits
+                    Mf::CountTrailingZeros => "countTrailingZeros",
+                    Mf::CountLeadingZeros => "countLeadingZeros",
                     Mf::CountOneBits => "countOneBits",
                     Mf::ReverseBits => "reverseBits",
+                    Mf::ExtractBits => "extractBits",
+                    Mf::InsertBits => "insertBits",
+                    Mf::FirstTrailingBit => "firstTrailingBit",
+                    Mf::FirstLeadingBit => "firstLeadingBit",
+                    // data packing
+                    Mf::Pack4x8snorm => "pack4x8snorm",
+                    Mf::Pack4x8unorm => "pack4x8unorm",
+                    Mf::Pack2x16snorm => "pack2x16snorm",
+                    Mf::Pack2x16unorm => "pack2x16unorm",
+                    Mf::Pack2x16float => "pack2x16float",
+                    // data unpacking
+                    Mf::Unpack4x8snorm => "unpack4x8snorm",
+                    Mf::Unpack4x8unorm => "unpack4x8unorm",
+                    Mf::Unpack2x16snorm => "unpack2x16snorm",
+                    Mf::Unpack2x16unorm => "unpack2x16unorm",
+                    Mf::Unpack2x16float => "unpack2x16float",
                     _ => {
                         return Err(Error::UnsupportedMathFunction(fun));
                     }
@@ -1200,6 +1519,10 @@ impl<W: Write> Writer<W> {
                     write!(self.out, ", ")?;
                     self.write_expr(module, arg, func_ctx)?;
                 }
+                if let Some(arg) = arg3 {
+                    write!(self.out, ", ")?;
+                    self.write_expr(module, arg, func_ctx)?;
+                }
                 write!(self.out, ")")?
             }
             Expression::Swizzle {
@@ -1271,8 +1594,22 @@ impl<W: Write> Writer<W> {
 
                 write!(self.out, ")")?
             }
+            Expression::RayQueryGetIntersection {
+                query,
+                committed,
+                ty: _,
+            } => {
+                let kind = if committed { "Committed" } else { "Candidate" };
+                write!(self.out, "rayQueryGet{}Intersection(&", kind)?;
+                self.write_expr(module, query, func_ctx)?;
+                write!(self.out, ")")?
+            }
             // Nothing to do here, since call expression already cached
-            Expression::Call(_) => {}
+            Expression::Call(_)
+            | Expression::AtomicResult { .. }
+            | Expression::SubgroupBallotResult
+            | Expression::SubgroupOperationResult { .. }
+            | Expression::RayQueryProceedResult => {}
         }
 
         Ok(())
@@ -1332,14 +1669,11 @@ impl<W: Write> Writer<W> {
     fn write_constant(&mut self, module: &Module, handle: Handle<Constant>) -> BackendResult {
         let constant = &module.constants[handle];
         match constant.inner {
-            crate::ConstantInner::Scalar {
-                width: _,
-                ref value,
-            } => {
-                if constant.name.is_some() {
+            crate::ConstantInner::Scalar { width, ref value } => {
+                if constant.name.is_some() || constant.specialization.is_some() {
                     write!(self.out, "{}", self.names[&NameKey::Constant(handle)])?;
                 } else {
-                    self.write_scalar_value(*value)?;
+                    self.write_scalar_value(*value, width)?;
                 }
             }
             crate::ConstantInner::Composite { ty, ref components } => {
@@ -1390,30 +1724,20 @@ impl<W: Write> Writer<W> {
         handle: Handle<Constant>,
     ) -> BackendResult {
         match *inner {
-            crate::ConstantInner::Scalar {
-                width: _,
-                ref value,
-            } => {
+            crate::ConstantInner::Scalar { width, ref value } => {
                 let name = self.names[&NameKey::Constant(handle)].clone();
                 // First write only constant name
-                write!(self.out, "let {}: ", name)?;
+                match module.constants[handle].specialization {
+                    Some(id) => write!(self.out, "[[id({})]] override {}: ", id, name)?,
+                    None => write!(self.out, "let {}: ", name)?,
+                }
                 // Next write constant type and value
-                match *value {
-                    crate::ScalarValue::Sint(value) => {
-                        write!(self.out, "i32 = {}", value)?;
-                    }
-                    crate::ScalarValue::Uint(value) => {
-                        write!(self.out, "u32 = {}u", value)?;
-                    }
-                    crate::ScalarValue::Float(value) => {
-                        // Floats are written using `Debug` instead of `Display` because it always appends the
-                        // decimal part even it's zero
-                        write!(self.out, "f32 = {:?}", value)?;
-                    }
-                    crate::ScalarValue::Bool(value) => {
-                        write!(self.out, "bool = {}", value)?;
-                    }
-                };
+                write!(
+                    self.out,
+                    "{} = ",
+                    scalar_kind_str(value.scalar_kind(), width)
+                )?;
+                self.write_scalar_value(*value, width)?;
                 // End with semicolon
                 writeln!(self.out, ";")?;
             }
@@ -1455,8 +1779,10 @@ fn builtin_str(built_in: crate::BuiltIn) -> Option<&'static str> {
         BuiltIn::VertexIndex => Some("vertex_index"),
         BuiltIn::InstanceIndex => Some("instance_index"),
         BuiltIn::Position => Some("position"),
+        BuiltIn::ViewIndex => Some("view_index"),
         BuiltIn::FrontFacing => Some("front_facing"),
         BuiltIn::FragDepth => Some("frag_depth"),
+        BuiltIn::PrimitiveIndex => Some("primitive_index"),
         BuiltIn::LocalInvocationId => Some("local_invocation_id"),
         BuiltIn::LocalInvocationIndex => Some("local_invocation_index"),
         BuiltIn::GlobalInvocationId => Some("global_invocation_id"),
@@ -1464,6 +1790,10 @@ fn builtin_str(built_in: crate::BuiltIn) -> Option<&'static str> {
         BuiltIn::WorkGroupSize => Some("workgroup_size"),
         BuiltIn::SampleIndex => Some("sample_index"),
         BuiltIn::SampleMask => Some("sample_mask"),
+        BuiltIn::SubgroupSize => Some("subgroup_size"),
+        BuiltIn::SubgroupInvocationId => Some("subgroup_invocation_id"),
+        BuiltIn::MeshTaskSize => Some("mesh_task_size"),
+        BuiltIn::CullPrimitive => Some("cull_primitive"),
         _ => None,
     }
 }
@@ -1477,12 +1807,16 @@ fn image_dimension_str(dim: ImageDimension) -> &'static str {
     }
 }
 
-fn scalar_kind_str(kind: ScalarKind) -> &'static str {
-    match kind {
-        crate::ScalarKind::Float => "f32",
-        crate::ScalarKind::Sint => "i32",
-        crate::ScalarKind::Uint => "u32",
-        crate::ScalarKind::Bool => "bool",
+fn scalar_kind_str(kind: ScalarKind, width: crate::Bytes) -> &'static str {
+    match (kind, width) {
+        (ScalarKind::Float, 2) => "f16",
+        (ScalarKind::Float, 8) => "f64",
+        (ScalarKind::Float, _) => "f32",
+        (ScalarKind::Sint, 8) => "i64",
+        (ScalarKind::Sint, _) => "i32",
+        (ScalarKind::Uint, 8) => "u64",
+        (ScalarKind::Uint, _) => "u32",
+        (ScalarKind::Bool, _) => "bool",
     }
 }
 
@@ -1548,6 +1882,7 @@ fn storage_class_str(storage_class: StorageClass) -> Option<&'static str> {
         StorageClass::Storage => Some("storage"),
         StorageClass::PushConstant => Some("push_constant"),
         StorageClass::WorkGroup => Some("workgroup"),
+        StorageClass::TaskPayload => Some("task_payload"),
         StorageClass::Function | StorageClass::Handle => None,
     }
 }
@@ -1557,12 +1892,45 @@ fn map_binding_to_attribute(binding: &Binding) -> Vec<Attribute> {
         Binding::BuiltIn(built_in) => vec![Attribute::BuiltIn(built_in)],
         Binding::Location {
             location,
+            second_blend_source,
             interpolation,
             sampling,
-        } => vec![
-            Attribute::Location(location),
-            Attribute::Interpolate(interpolation, sampling),
-        ],
+        } => {
+            let mut attributes = vec![Attribute::Location(location)];
+            if second_blend_source {
+                attributes.push(Attribute::Index(1));
+            }
+            attributes.push(Attribute::Interpolate(interpolation, sampling));
+            attributes
+        }
+    }
+}
+
+fn atomic_fun_str(fun: &crate::AtomicFunction) -> &'static str {
+    match *fun {
+        crate::AtomicFunction::Add => "Add",
+        crate::AtomicFunction::Subtract => "Sub",
+        crate::AtomicFunction::And => "And",
+        crate::AtomicFunction::InclusiveOr => "Or",
+        crate::AtomicFunction::ExclusiveOr => "Xor",
+        crate::AtomicFunction::Min => "Min",
+        crate::AtomicFunction::Max => "Max",
+        crate::AtomicFunction::Exchange { compare: None } => "Exchange",
+        crate::AtomicFunction::Exchange { compare: Some(_) } => "CompareExchangeWeak",
+    }
+}
+
+fn subgroup_op_str(op: crate::SubgroupOperation) -> &'static str {
+    match op {
+        crate::SubgroupOperation::All => "All",
+        crate::SubgroupOperation::Any => "Any",
+        crate::SubgroupOperation::Add => "Add",
+        crate::SubgroupOperation::Mul => "Mul",
+        crate::SubgroupOperation::Min => "Min",
+        crate::SubgroupOperation::Max => "Max",
+        crate::SubgroupOperation::And => "And",
+        crate::SubgroupOperation::Or => "Or",
+        crate::SubgroupOperation::Xor => "Xor",
     }
 }
 
@@ -0,0 +1,790 @@
+//! Helpers for constructing modules programmatically.
+//!
+//! [`ModuleBuilder`] deduplicates types and constants, and
+//! [`FunctionBuilder`] takes care of the [`Statement::Emit`] ranges,
+//! resolving the type of every expression as it's added, so that type
+//! errors are reported where they are made.
+//!
+//! [`Statement::Emit`]: crate::Statement::Emit
+
+use crate::{
+    arena::Handle,
+    front::Emitter,
+    proc::{ResolveContext, ResolveError, TypeResolution},
+};
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum BuildError {
+    #[error(transparent)]
+    Resolve(#[from] ResolveError),
+    #[error("Expression {0:?} can only be produced by a statement")]
+    ResultExpression(crate::Expression),
+    #[error("Condition {0:?} is not a boolean scalar")]
+    InvalidCondition(Handle<crate::Expression>),
+    #[error("Selector {0:?} is not an integer scalar")]
+    InvalidSelector(Handle<crate::Expression>),
+    #[error("Expression {0:?} is not a pointer")]
+    InvalidPointer(Handle<crate::Expression>),
+    #[error("Value {0:?} doesn't match the type it's stored to")]
+    InvalidStoreValue(Handle<crate::Expression>),
+    #[error("Called function {function:?} with {given} arguments, but it takes {expected}")]
+    ArgumentCount {
+        function: Handle<crate::Function>,
+        expected: usize,
+        given: usize,
+    },
+    #[error("Argument {index} of the call to {function:?} has the wrong type")]
+    InvalidArgument {
+        function: Handle<crate::Function>,
+        index: usize,
+    },
+    #[error("Returned value {0:?} doesn't match the function result")]
+    InvalidReturnValue(Option<Handle<crate::Expression>>),
+}
+
+/// Builds a [`Module`](crate::Module).
+#[derive(Debug, Default)]
+pub struct ModuleBuilder {
+    module: crate::Module,
+}
+
+impl ModuleBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the module built so far.
+    pub fn module(&self) -> &crate::Module {
+        &self.module
+    }
+
+    /// Get an unnamed type, adding it if it's not there yet.
+    pub fn ty(&mut self, inner: crate::TypeInner) -> Handle<crate::Type> {
+        self.module
+            .types
+            .fetch_or_append(crate::Type { name: None, inner })
+    }
+
+    /// Get a named type, like a structure, adding it if it's not there yet.
+    pub fn named_ty(&mut self, name: &str, inner: crate::TypeInner) -> Handle<crate::Type> {
+        self.module.types.fetch_or_append(crate::Type {
+            name: Some(name.to_string()),
+            inner,
+        })
+    }
+
+    /// Get an unnamed constant, adding it if it's not there yet.
+    pub fn constant(&mut self, inner: crate::ConstantInner) -> Handle<crate::Constant> {
+        self.module.constants.fetch_or_append(crate::Constant {
+            name: None,
+            specialization: None,
+            inner,
+        })
+    }
+
+    /// Add a named or specializable constant.
+    pub fn named_constant(&mut self, constant: crate::Constant) -> Handle<crate::Constant> {
+        self.module.constants.append(constant)
+    }
+
+    pub fn global_variable(
+        &mut self,
+        global: crate::GlobalVariable,
+    ) -> Handle<crate::GlobalVariable> {
+        self.module.global_variables.append(global)
+    }
+
+    /// Start building a function, which can call the ones built before.
+    pub fn function(&mut self, name: &str) -> FunctionBuilder<'_> {
+        FunctionBuilder::new(&mut self.module, name)
+    }
+
+    pub fn finish(self) -> crate::Module {
+        self.module
+    }
+}
+
+/// Builds a [`Function`](crate::Function) or an entry point of a module.
+///
+/// Expressions are added to the innermost block being built, and every
+/// statement ends the range of expressions emitted before it. The
+/// structured statements take closures building their blocks.
+///
+/// After an error is returned, the function being built should be dropped.
+#[derive(Debug)]
+pub struct FunctionBuilder<'m> {
+    module: &'m mut crate::Module,
+    function: crate::Function,
+    resolutions: Vec<TypeResolution>,
+    /// Blocks being built, starting with the function body.
+    blocks: Vec<crate::Block>,
+    emitter: Emitter,
+}
+
+impl<'m> FunctionBuilder<'m> {
+    /// Start building a function of an existing module, which can call the
+    /// functions of the module.
+    pub fn new(module: &'m mut crate::Module, name: &str) -> Self {
+        let function = crate::Function {
+            name: Some(name.to_string()),
+            ..crate::Function::default()
+        };
+        let mut emitter = Emitter::default();
+        emitter.start(&function.expressions);
+        FunctionBuilder {
+            module,
+            function,
+            resolutions: Vec::new(),
+            blocks: vec![crate::Block::new()],
+            emitter,
+        }
+    }
+
+    /// Get an unnamed type, adding it to the module if it's not there yet.
+    pub fn ty(&mut self, inner: crate::TypeInner) -> Handle<crate::Type> {
+        self.module
+            .types
+            .fetch_or_append(crate::Type { name: None, inner })
+    }
+
+    /// Get an unnamed constant, adding it to the module if it's not there yet.
+    pub fn constant(&mut self, inner: crate::ConstantInner) -> Handle<crate::Constant> {
+        self.module.constants.fetch_or_append(crate::Constant {
+            name: None,
+            specialization: None,
+            inner,
+        })
+    }
+
+    /// Add an argument, returning the expression for its value.
+    pub fn argument(
+        &mut self,
+        name: &str,
+        ty: Handle<crate::Type>,
+        binding: Option<crate::Binding>,
+    ) -> Result<Handle<crate::Expression>, BuildError> {
+        let index = self.function.arguments.len() as u32;
+        self.function.arguments.push(crate::FunctionArgument {
+            name: Some(name.to_string()),
+            ty,
+            binding,
+        });
+        self.expression(crate::Expression::FunctionArgument(index))
+    }
+
+    pub fn result(&mut self, ty: Handle<crate::Type>, binding: Option<crate::Binding>) {
+        self.function.result = Some(crate::FunctionResult { ty, binding });
+    }
+
+    /// Add a local variable, returning the expression for its pointer.
+    pub fn local_variable(
+        &mut self,
+        name: &str,
+        ty: Handle<crate::Type>,
+        init: Option<Handle<crate::Constant>>,
+    ) -> Result<Handle<crate::Expression>, BuildError> {
+        let local = self.function.local_variables.append(crate::LocalVariable {
+            name: Some(name.to_string()),
+            ty,
+            init,
+        });
+        self.expression(crate::Expression::LocalVariable(local))
+    }
+
+    /// Add an expression, emitting it if needed.
+    ///
+    /// The expressions produced by statements, like the results of calls
+    /// and atomics, can't be added this way: use [`call`](Self::call),
+    /// [`atomic`](Self::atomic) and the like instead.
+    pub fn expression(
+        &mut self,
+        expression: crate::Expression,
+    ) -> Result<Handle<crate::Expression>, BuildError> {
+        use crate::Expression as E;
+        match expression {
+            E::Call(_)
+            | E::AtomicResult { .. }
+            | E::SubgroupBallotResult
+            | E::SubgroupOperationResult { .. }
+            | E::RayQueryProceedResult => return Err(BuildError::ResultExpression(expression)),
+            _ => {}
+        }
+        if expression.needs_pre_emit() {
+            self.append_unemitted(expression)
+        } else {
+            self.append(expression)
+        }
+    }
+
+    /// Give a name to an expression, for the backends to use.
+    pub fn name(&mut self, expression: Handle<crate::Expression>, name: &str) {
+        self.function
+            .named_expressions
+            .insert(expression, name.to_string());
+    }
+
+    /// Get the type of an expression.
+    pub fn resolve(&self, expression: Handle<crate::Expression>) -> &crate::TypeInner {
+        self.resolutions[expression.index()].inner_with(&self.module.types)
+    }
+
+    /// Add a statement that doesn't have a block.
+    pub fn statement(&mut self, statement: crate::Statement) {
+        self.flush();
+        self.blocks.last_mut().unwrap().push(statement);
+    }
+
+    pub fn store(
+        &mut self,
+        pointer: Handle<crate::Expression>,
+        value: Handle<crate::Expression>,
+    ) -> Result<(), BuildError> {
+        let types = &self.module.types;
+        let value_inner = self.resolve(value);
+        let matches = match *self.resolve(pointer) {
+            crate::TypeInner::Pointer { base, .. } => types[base].inner == *value_inner,
+            crate::TypeInner::ValuePointer {
+                size: None,
+                kind,
+                width,
+                ..
+            } => *value_inner == crate::TypeInner::Scalar { kind, width },
+            crate::TypeInner::ValuePointer {
+                size: Some(size),
+                kind,
+                width,
+                ..
+            } => *value_inner == crate::TypeInner::Vector { size, kind, width },
+            _ => return Err(BuildError::InvalidPointer(pointer)),
+        };
+        if !matches {
+            return Err(BuildError::InvalidStoreValue(value));
+        }
+        self.statement(crate::Statement::Store { pointer, value });
+        Ok(())
+    }
+
+    /// Call a function, returning the expression for its result, if any.
+    pub fn call(
+        &mut self,
+        function: Handle<crate::Function>,
+        arguments: Vec<Handle<crate::Expression>>,
+    ) -> Result<Option<Handle<crate::Expression>>, BuildError> {
+        let callee = &self.module.functions[function];
+        if callee.arguments.len() != arguments.len() {
+            return Err(BuildError::ArgumentCount {
+                function,
+                expected: callee.arguments.len(),
+                given: arguments.len(),
+            });
+        }
+        for (index, (argument, &value)) in callee.arguments.iter().zip(&arguments).enumerate() {
+            if self.module.types[argument.ty].inner != *self.resolve(value) {
+                return Err(BuildError::InvalidArgument { function, index });
+            }
+        }
+        let result = if callee.result.is_some() {
+            Some(self.append_unemitted(crate::Expression::Call(function))?)
+        } else {
+            None
+        };
+        self.statement(crate::Statement::Call {
+            function,
+            arguments,
+            result,
+        });
+        Ok(result)
+    }
+
+    /// Run an atomic function, returning the expression for its result.
+    pub fn atomic(
+        &mut self,
+        pointer: Handle<crate::Expression>,
+        fun: crate::AtomicFunction,
+        value: Handle<crate::Expression>,
+    ) -> Result<Handle<crate::Expression>, BuildError> {
+        let (kind, width) = match *self.resolve(pointer) {
+            crate::TypeInner::Pointer { base, .. } => match self.module.types[base].inner {
+                crate::TypeInner::Atomic { kind, width } => (kind, width),
+                _ => return Err(BuildError::InvalidPointer(pointer)),
+            },
+            _ => return Err(BuildError::InvalidPointer(pointer)),
+        };
+        let comparison = match fun {
+            crate::AtomicFunction::Exchange { compare: Some(_) } => true,
+            _ => false,
+        };
+        let result = self.append_unemitted(crate::Expression::AtomicResult {
+            kind,
+            width,
+            comparison,
+        })?;
+        self.statement(crate::Statement::Atomic {
+            pointer,
+            fun,
+            value,
+            result,
+        });
+        Ok(result)
+    }
+
+    /// Gather the `predicate` of the subgroup invocations into a bitmask,
+    /// returning the expression for it.
+    pub fn subgroup_ballot(
+        &mut self,
+        predicate: Option<Handle<crate::Expression>>,
+    ) -> Result<Handle<crate::Expression>, BuildError> {
+        if let Some(predicate) = predicate {
+            match *self.resolve(predicate) {
+                crate::TypeInner::Scalar {
+                    kind: crate::ScalarKind::Bool,
+                    ..
+                } => {}
+                _ => return Err(BuildError::InvalidCondition(predicate)),
+            }
+        }
+        let result = self.append_unemitted(crate::Expression::SubgroupBallotResult)?;
+        self.statement(crate::Statement::SubgroupBallot { result, predicate });
+        Ok(result)
+    }
+
+    /// Combine the `argument` of the subgroup invocations, returning the
+    /// expression for the result.
+    pub fn subgroup_collective_operation(
+        &mut self,
+        op: crate::SubgroupOperation,
+        collective_op: crate::CollectiveOperation,
+        argument: Handle<crate::Expression>,
+    ) -> Result<Handle<crate::Expression>, BuildError> {
+        let ty = self.resolve_handle(argument);
+        let result = self.append_unemitted(crate::Expression::SubgroupOperationResult { ty })?;
+        self.statement(crate::Statement::SubgroupCollectiveOperation {
+            op,
+            collective_op,
+            argument,
+            result,
+        });
+        Ok(result)
+    }
+
+    /// Read the `argument` of another subgroup invocation, returning the
+    /// expression for it.
+    pub fn subgroup_gather(
+        &mut self,
+        mode: crate::GatherMode,
+        argument: Handle<crate::Expression>,
+    ) -> Result<Handle<crate::Expression>, BuildError> {
+        let ty = self.resolve_handle(argument);
+        let result = self.append_unemitted(crate::Expression::SubgroupOperationResult { ty })?;
+        self.statement(crate::Statement::SubgroupGather {
+            mode,
+            argument,
+            result,
+        });
+        Ok(result)
+    }
+
+    /// Advance a ray query, returning the expression telling if there
+    /// is a candidate intersection.
+    pub fn ray_query_proceed(
+        &mut self,
+        query: Handle<crate::Expression>,
+    ) -> Result<Handle<crate::Expression>, BuildError> {
+        match *self.resolve(query) {
+            crate::TypeInner::Pointer { base, .. }
+                if self.module.types[base].inner == crate::TypeInner::RayQuery => {}
+            _ => return Err(BuildError::InvalidPointer(query)),
+        }
+        let result = self.append_unemitted(crate::Expression::RayQueryProceedResult)?;
+        self.statement(crate::Statement::RayQuery {
+            query,
+            fun: crate::RayQueryFunction::Proceed { result },
+        });
+        Ok(result)
+    }
+
+    pub fn ret(&mut self, value: Option<Handle<crate::Expression>>) -> Result<(), BuildError> {
+        let matches = match (self.function.result.as_ref(), value) {
+            (Some(result), Some(value)) => {
+                self.module.types[result.ty].inner == *self.resolve(value)
+            }
+            (None, None) => true,
+            _ => false,
+        };
+        if !matches {
+            return Err(BuildError::InvalidReturnValue(value));
+        }
+        self.statement(crate::Statement::Return { value });
+        Ok(())
+    }
+
+    pub fn if_else<A, R>(
+        &mut self,
+        condition: Handle<crate::Expression>,
+        accept: A,
+        reject: R,
+    ) -> Result<(), BuildError>
+    where
+        A: FnOnce(&mut Self) -> Result<(), BuildError>,
+        R: FnOnce(&mut Self) -> Result<(), BuildError>,
+    {
+        match *self.resolve(condition) {
+            crate::TypeInner::Scalar {
+                kind: crate::ScalarKind::Bool,
+                ..
+            } => {}
+            _ => return Err(BuildError::InvalidCondition(condition)),
+        }
+        let accept = self.block(accept)?;
+        let reject = self.block(reject)?;
+        self.statement(crate::Statement::If {
+            condition,
+            accept,
+            reject,
+        });
+        Ok(())
+    }
+
+    pub fn loop_block<B, C>(&mut self, body: B, continuing: C) -> Result<(), BuildError>
+    where
+        B: FnOnce(&mut Self) -> Result<(), BuildError>,
+        C: FnOnce(&mut Self) -> Result<(), BuildError>,
+    {
+        let body = self.block(body)?;
+        let continuing = self.block(continuing)?;
+        self.statement(crate::Statement::Loop { body, continuing });
+        Ok(())
+    }
+
+    /// Add a switch with the given `(value, fall_through)` cases.
+    ///
+    /// The body of each case, and then of the default case, is built by
+    /// calling `body` with the value of the case, or `None` for the default.
+    pub fn switch<B>(
+        &mut self,
+        selector: Handle<crate::Expression>,
+        cases: &[(i32, bool)],
+        mut body: B,
+    ) -> Result<(), BuildError>
+    where
+        B: FnMut(&mut Self, Option<i32>) -> Result<(), BuildError>,
+    {
+        match *self.resolve(selector) {
+            crate::TypeInner::Scalar {
+                kind: crate::ScalarKind::Sint,
+                ..
+            }
+            | crate::TypeInner::Scalar {
+                kind: crate::ScalarKind::Uint,
+                ..
+            } => {}
+            _ => return Err(BuildError::InvalidSelector(selector)),
+        }
+        let mut switch_cases = Vec::with_capacity(cases.len());
+        for &(value, fall_through) in cases {
+            switch_cases.push(crate::SwitchCase {
+                value,
+                body: self.block(|builder| body(builder, Some(value)))?,
+                fall_through,
+            });
+        }
+        let default = self.block(|builder| body(builder, None))?;
+        self.statement(crate::Statement::Switch {
+            selector,
+            cases: switch_cases,
+            default,
+        });
+        Ok(())
+    }
+
+    /// Add the function to the module.
+    pub fn finish(self) -> Handle<crate::Function> {
+        let (module, function) = self.finish_function();
+        module.functions.append(function)
+    }
+
+    /// Add the function to the module as an entry point.
+    pub fn finish_entry_point(self, stage: crate::ShaderStage, workgroup_size: [u32; 3]) {
+        let (module, function) = self.finish_function();
+        let name = function.name.clone().unwrap_or_default();
+        module.entry_points.push(crate::EntryPoint {
+            name,
+            stage,
+            early_depth_test: None,
+            workgroup_size,
+            mesh_info: None,
+            task_payload: None,
+            function,
+        });
+    }
+
+    /// Return the function without adding it to the module.
+    pub fn into_function(self) -> crate::Function {
+        self.finish_function().1
+    }
+
+    fn finish_function(mut self) -> (&'m mut crate::Module, crate::Function) {
+        self.flush();
+        self.function.body = self.blocks.pop().unwrap();
+        (self.module, self.function)
+    }
+
+    /// End the range of emitted expressions with an `Emit` statement.
+    fn flush(&mut self) {
+        let emit = self.emitter.finish(&self.function.expressions);
+        self.blocks.last_mut().unwrap().extend(emit);
+        self.emitter.start(&self.function.expressions);
+    }
+
+    /// Get the type of an expression as a handle, adding it if needed.
+    fn resolve_handle(&mut self, expression: Handle<crate::Expression>) -> Handle<crate::Type> {
+        match self.resolutions[expression.index()].clone() {
+            TypeResolution::Handle(ty) => ty,
+            TypeResolution::Value(inner) => self.ty(inner),
+        }
+    }
+
+    fn block<F>(&mut self, fun: F) -> Result<crate::Block, BuildError>
+    where
+        F: FnOnce(&mut Self) -> Result<(), BuildError>,
+    {
+        self.flush();
+        self.blocks.push(crate::Block::new());
+        fun(self)?;
+        self.flush();
+        Ok(self.blocks.pop().unwrap())
+    }
+
+    /// Add an expression that isn't emitted, like the result of a statement.
+    fn append_unemitted(
+        &mut self,
+        expression: crate::Expression,
+    ) -> Result<Handle<crate::Expression>, BuildError> {
+        let emit = self.emitter.finish(&self.function.expressions);
+        self.blocks.last_mut().unwrap().extend(emit);
+        let result = self.append(expression);
+        self.emitter.start(&self.function.expressions);
+        result
+    }
+
+    fn append(
+        &mut self,
+        expression: crate::Expression,
+    ) -> Result<Handle<crate::Expression>, BuildError> {
+        let resolve_ctx = ResolveContext {
+            constants: &self.module.constants,
+            types: &self.module.types,
+            global_vars: &self.module.global_variables,
+            local_vars: &self.function.local_variables,
+            functions: &self.module.functions,
+            arguments: &self.function.arguments,
+        };
+        let resolutions = &self.resolutions;
+        let resolution = resolve_ctx.resolve(&expression, |h| &resolutions[h.index()])?;
+        self.resolutions.push(resolution);
+        Ok(self.function.expressions.append(expression))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BuildError, ModuleBuilder};
+    use crate::{BinaryOperator as Bo, Expression as E};
+
+    fn sint(builder: &mut ModuleBuilder, value: i64) -> crate::Expression {
+        E::Constant(builder.constant(crate::ConstantInner::Scalar {
+            width: 4,
+            value: crate::ScalarValue::Sint(value),
+        }))
+    }
+
+    #[test]
+    fn build_module() {
+        let mut builder = ModuleBuilder::new();
+        let ty_int = builder.ty(crate::TypeInner::Scalar {
+            kind: crate::ScalarKind::Sint,
+            width: 4,
+        });
+        let zero = sint(&mut builder, 0);
+        let one = sint(&mut builder, 1);
+        let three = sint(&mut builder, 3);
+        let total = builder.global_variable(crate::GlobalVariable {
+            name: Some("total".to_string()),
+            class: crate::StorageClass::Private,
+            binding: None,
+            ty: ty_int,
+            init: None,
+            storage_access: crate::StorageAccess::empty(),
+        });
+
+        // sum of 0..n, except for multiples of 3
+        let mut fun = builder.function("sum");
+        let n = fun.argument("n", ty_int, None).unwrap();
+        fun.result(ty_int, None);
+        let sum = fun.local_variable("sum", ty_int, None).unwrap();
+        let i = fun.local_variable("i", ty_int, None).unwrap();
+        let zero_expr = fun.expression(zero.clone()).unwrap();
+        fun.store(sum, zero_expr).unwrap();
+        fun.store(i, zero_expr).unwrap();
+        fun.loop_block(
+            |fun| {
+                let value = fun.expression(E::Load { pointer: i })?;
+                let done = fun.expression(E::Binary {
+                    op: Bo::GreaterEqual,
+                    left: value,
+                    right: n,
+                })?;
+                fun.if_else(
+                    done,
+                    |fun| {
+                        fun.statement(crate::Statement::Break);
+                        Ok(())
+                    },
+                    |_| Ok(()),
+                )?;
+                let three = fun.expression(three)?;
+                let rem = fun.expression(E::Binary {
+                    op: Bo::Modulo,
+                    left: value,
+                    right: three,
+                })?;
+                fun.switch(rem, &[(0, false)], |fun, case| {
+                    if case.is_some() {
+                        fun.statement(crate::Statement::Continue);
+                    } else {
+                        let old = fun.expression(E::Load { pointer: sum })?;
+                        let new = fun.expression(E::Binary {
+                            op: Bo::Add,
+                            left: old,
+                            right: value,
+                        })?;
+                        fun.store(sum, new)?;
+                    }
+                    Ok(())
+                })
+            },
+            |fun| {
+                let value = fun.expression(E::Load { pointer: i })?;
+                let one = fun.expression(one)?;
+                let next = fun.expression(E::Binary {
+                    op: Bo::Add,
+                    left: value,
+                    right: one,
+                })?;
+                fun.store(i, next)
+            },
+        )
+        .unwrap();
+        let result = fun.expression(E::Load { pointer: sum }).unwrap();
+        fun.ret(Some(result)).unwrap();
+        let sum_function = fun.finish();
+
+        // total = sum(10)
+        let mut main = builder.function("main");
+        let ten = main.constant(crate::ConstantInner::Scalar {
+            width: 4,
+            value: crate::ScalarValue::Sint(10),
+        });
+        let ten = main.expression(E::Constant(ten)).unwrap();
+        let result = main.call(sum_function, vec![ten]).unwrap().unwrap();
+        let pointer = main.expression(E::GlobalVariable(total)).unwrap();
+        main.store(pointer, result).unwrap();
+        assert!(matches!(
+            main.call(sum_function, Vec::new()),
+            Err(BuildError::ArgumentCount { given: 0, .. })
+        ));
+        assert!(matches!(
+            main.if_else(ten, |_| Ok(()), |_| Ok(())),
+            Err(BuildError::InvalidCondition(_))
+        ));
+        main.finish_entry_point(crate::ShaderStage::Compute, [1, 1, 1]);
+
+        let module = builder.finish();
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .unwrap();
+    }
+
+    #[test]
+    fn build_result_statements() {
+        let mut builder = ModuleBuilder::new();
+        let ty_atomic = builder.ty(crate::TypeInner::Atomic {
+            kind: crate::ScalarKind::Uint,
+            width: 4,
+        });
+        let ty_query = builder.ty(crate::TypeInner::RayQuery);
+        let counter = builder.global_variable(crate::GlobalVariable {
+            name: Some("counter".to_string()),
+            class: crate::StorageClass::WorkGroup,
+            binding: None,
+            ty: ty_atomic,
+            init: None,
+            storage_access: crate::StorageAccess::empty(),
+        });
+        let one = builder.constant(crate::ConstantInner::Scalar {
+            width: 4,
+            value: crate::ScalarValue::Uint(1),
+        });
+
+        let mut main = builder.function("main");
+        let query = main.local_variable("query", ty_query, None).unwrap();
+        let pointer = main.expression(E::GlobalVariable(counter)).unwrap();
+        let one = main.expression(E::Constant(one)).unwrap();
+        let old = main
+            .atomic(pointer, crate::AtomicFunction::Add, one)
+            .unwrap();
+        let sum = main
+            .subgroup_collective_operation(
+                crate::SubgroupOperation::Add,
+                crate::CollectiveOperation::Reduce,
+                old,
+            )
+            .unwrap();
+        assert_eq!(
+            *main.resolve(sum),
+            crate::TypeInner::Scalar {
+                kind: crate::ScalarKind::Uint,
+                width: 4,
+            }
+        );
+        let first = main
+            .subgroup_gather(crate::GatherMode::BroadcastFirst, sum)
+            .unwrap();
+        let _ = main.subgroup_ballot(None).unwrap();
+        assert!(matches!(
+            main.subgroup_ballot(Some(first)),
+            Err(BuildError::InvalidCondition(_))
+        ));
+        assert!(matches!(
+            main.atomic(query, crate::AtomicFunction::Add, one),
+            Err(BuildError::InvalidPointer(_))
+        ));
+        assert!(matches!(
+            main.ray_query_proceed(pointer),
+            Err(BuildError::InvalidPointer(_))
+        ));
+        assert!(matches!(
+            main.expression(E::RayQueryProceedResult),
+            Err(BuildError::ResultExpression(_))
+        ));
+        let proceed = main.ray_query_proceed(query).unwrap();
+        main.expression(E::Select {
+            condition: proceed,
+            accept: first,
+            reject: old,
+        })
+        .unwrap();
+        main.finish_entry_point(crate::ShaderStage::Compute, [1, 1, 1]);
+
+        let module = builder.finish();
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::SUBGROUP | crate::valid::Capabilities::RAY_QUERY,
+        )
+        .validate(&module)
+        .unwrap();
+    }
+}
@@ -1,12 +1,12 @@
 use super::{
     super::{Emitter, Typifier},
-    constants::ConstantSolver,
     error::ErrorKind,
     SourceMetadata,
 };
 use crate::{
-    proc::ResolveContext, Arena, BinaryOperator, Binding, Block, Constant, Expression, FastHashMap,
-    Function, FunctionArgument, GlobalVariable, Handle, Interpolation, LocalVariable, Module,
+    proc::{ConstantEvaluator, ResolveContext},
+    Arena, BinaryOperator, Binding, Block, Constant, Expression, FastHashMap, Function,
+    FunctionArgument, GlobalVariable, Handle, Interpolation, LocalVariable, Module,
     RelationalFunction, ResourceBinding, Sampling, ScalarKind, ScalarValue, ShaderStage, Statement,
     StorageClass, Type, TypeInner, UnaryOperator, VectorSize,
 };
@@ -59,6 +59,7 @@ impl From<ShaderStage> for PrologueStage {
             ShaderStage::Vertex => PrologueStage::VERTEX,
             ShaderStage::Fragment => PrologueStage::FRAGMENT,
             ShaderStage::Compute => PrologueStage::COMPUTE,
+            ShaderStage::Task | ShaderStage::Mesh => PrologueStage::empty(),
         }
     }
 }
@@ -154,13 +155,13 @@ impl<'a> Program<'a> {
         root: Handle<Expression>,
         meta: SourceMetadata,
     ) -> Result<Handle<Constant>, ErrorKind> {
-        let mut solver = ConstantSolver {
-            types: &self.module.types,
-            expressions: ctx.expressions,
+        let mut evaluator = ConstantEvaluator {
+            types: &mut self.module.types,
             constants: &mut self.module.constants,
+            expressions: ctx.expressions,
         };
 
-        solver.solve(root).map_err(|e| (meta, e).into())
+        evaluator.eval(root).map_err(|e| (meta, e).into())
     }
 }
 
@@ -451,6 +452,20 @@ impl<'function> Context<'function> {
         expr: Handle<HirExpr>,
         lhs: bool,
         body: &mut Block,
+    ) -> Result<(Option<Handle<Expression>>, SourceMetadata), ErrorKind> {
+        let (maybe_expr, meta) = self.lower_inner(program, expr, lhs, body)?;
+        if let Some(handle) = maybe_expr {
+            self.expressions.init_span(handle, meta.into());
+        }
+        Ok((maybe_expr, meta))
+    }
+
+    fn lower_inner(
+        &mut self,
+        program: &mut Program,
+        expr: Handle<HirExpr>,
+        lhs: bool,
+        body: &mut Block,
     ) -> Result<(Option<Handle<Expression>>, SourceMetadata), ErrorKind> {
         let HirExpr { kind, meta } = self.hir_exprs[expr].clone();
 
@@ -821,6 +836,21 @@ impl<'function> Context<'function> {
             .and_then(type_power))
     }
 
+    /// Width of the scalars in the value of `expr`, used to convert it.
+    fn expr_width(
+        &mut self,
+        program: &mut Program,
+        expr: Handle<Expression>,
+        meta: SourceMetadata,
+    ) -> Result<crate::Bytes, ErrorKind> {
+        Ok(match *program.resolve_type(self, expr, meta)? {
+            TypeInner::Scalar { width, .. }
+            | TypeInner::Vector { width, .. }
+            | TypeInner::Matrix { width, .. } => width,
+            _ => 4,
+        })
+    }
+
     pub fn get_expression(&self, expr: Handle<Expression>) -> &Expression {
         &self.expressions[expr]
     }
@@ -836,10 +866,11 @@ impl<'function> Context<'function> {
             (type_power(kind), self.expr_power(program, *expr, meta)?)
         {
             if tgt_power > expr_power {
+                let width = self.expr_width(program, *expr, meta)?;
                 *expr = self.expressions.append(Expression::As {
                     expr: *expr,
                     kind,
-                    convert: None,
+                    convert: Some(width),
                 })
             }
         }
@@ -864,18 +895,20 @@ impl<'function> Context<'function> {
         ) {
             match left_power.cmp(&right_power) {
                 std::cmp::Ordering::Less => {
+                    let width = self.expr_width(program, *left, left_meta)?;
                     *left = self.expressions.append(Expression::As {
                         expr: *left,
                         kind: right_kind,
-                        convert: None,
+                        convert: Some(width),
                     })
                 }
                 std::cmp::Ordering::Equal => {}
                 std::cmp::Ordering::Greater => {
+                    let width = self.expr_width(program, *right, right_meta)?;
                     *right = self.expressions.append(Expression::As {
                         expr: *right,
                         kind: left_kind,
-                        convert: None,
+                        convert: Some(width),
                     })
                 }
             }
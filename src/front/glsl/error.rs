@@ -1,7 +1,5 @@
-use super::{
-    constants::ConstantSolvingError,
-    token::{SourceMetadata, Token, TokenValue},
-};
+use super::token::{SourceMetadata, Token, TokenValue};
+use crate::proc::ConstantEvaluatorError;
 use std::borrow::Cow;
 use thiserror::Error;
 
@@ -107,8 +105,8 @@ impl ErrorKind {
     }
 }
 
-impl From<(SourceMetadata, ConstantSolvingError)> for ErrorKind {
-    fn from((meta, err): (SourceMetadata, ConstantSolvingError)) -> Self {
+impl From<(SourceMetadata, ConstantEvaluatorError)> for ErrorKind {
+    fn from((meta, err): (SourceMetadata, ConstantEvaluatorError)) -> Self {
         ErrorKind::SemanticError(meta, err.to_string().into())
     }
 }
@@ -1,8 +1,8 @@
 use crate::{
-    proc::ensure_block_returns, Arena, BinaryOperator, Block, EntryPoint, Expression, Function,
-    FunctionArgument, FunctionResult, Handle, ImageQuery, LocalVariable, MathFunction,
-    RelationalFunction, SampleLevel, ScalarKind, Statement, StructMember, SwizzleComponent, Type,
-    TypeInner, VectorSize,
+    proc::ensure_block_returns, Arena, BinaryOperator, Block, ConstantInner, EntryPoint,
+    Expression, Function, FunctionArgument, FunctionResult, Handle, ImageClass, ImageQuery,
+    LocalVariable, MathFunction, RelationalFunction, SampleLevel, ScalarKind, ScalarValue,
+    Statement, StructMember, SwizzleComponent, Type, TypeInner, VectorSize,
 };
 
 use super::{ast::*, error::ErrorKind, SourceMetadata};
@@ -160,6 +160,7 @@ impl Program<'_> {
                                 Expression::ImageSample {
                                     image: args[0].0,
                                     sampler,
+                                    gather: None,
                                     coordinate: args[1].0,
                                     array_index: None, //TODO
                                     offset: None,      //TODO
@@ -189,6 +190,7 @@ impl Program<'_> {
                                 Expression::ImageSample {
                                     image: args[0].0,
                                     sampler,
+                                    gather: None,
                                     coordinate: args[1].0,
                                     array_index: None, //TODO
                                     offset: None,      //TODO
@@ -204,6 +206,72 @@ impl Program<'_> {
                             ))
                         }
                     }
+                    "textureGather" => {
+                        if !(2..=3).contains(&args.len()) {
+                            return Err(ErrorKind::wrong_function_args(name, 3, args.len(), meta));
+                        }
+                        let sampler = match ctx.samplers.get(&args[0].0).copied() {
+                            Some(sampler) => sampler,
+                            None => {
+                                return Err(ErrorKind::SemanticError(
+                                    meta,
+                                    "Bad call to textureGather".into(),
+                                ))
+                            }
+                        };
+                        let is_depth = match *self.resolve_type(ctx, args[0].0, args[0].1)? {
+                            TypeInner::Image {
+                                class: ImageClass::Depth,
+                                ..
+                            } => true,
+                            _ => false,
+                        };
+                        // shadow samplers take a depth reference instead of the component
+                        let (component, depth_ref) = match args.get(2) {
+                            Some(&(expr, _)) if is_depth => (SwizzleComponent::X, Some(expr)),
+                            Some(&(expr, meta)) => {
+                                let constant = self.solve_constant(ctx, expr, meta)?;
+                                let index = match self.module.constants[constant].inner {
+                                    ConstantInner::Scalar {
+                                        value: ScalarValue::Sint(value),
+                                        ..
+                                    } => value,
+                                    ConstantInner::Scalar {
+                                        value: ScalarValue::Uint(value),
+                                        ..
+                                    } => value as i64,
+                                    _ => -1,
+                                };
+                                let component = match index {
+                                    0 => SwizzleComponent::X,
+                                    1 => SwizzleComponent::Y,
+                                    2 => SwizzleComponent::Z,
+                                    3 => SwizzleComponent::W,
+                                    _ => {
+                                        return Err(ErrorKind::SemanticError(
+                                            meta,
+                                            "Gather component must be between 0 and 3".into(),
+                                        ))
+                                    }
+                                };
+                                (component, None)
+                            }
+                            None => (SwizzleComponent::X, None),
+                        };
+                        Ok(Some(ctx.add_expression(
+                            Expression::ImageSample {
+                                image: args[0].0,
+                                sampler,
+                                gather: Some(component),
+                                coordinate: args[1].0,
+                                array_index: None, //TODO
+                                offset: None,      //TODO
+                                level: SampleLevel::Zero,
+                                depth_ref,
+                            },
+                            body,
+                        )))
+                    }
                     "textureSize" => {
                         if !(1..=2).contains(&args.len()) {
                             return Err(ErrorKind::wrong_function_args(name, 1, args.len(), meta));
@@ -294,7 +362,10 @@ impl Program<'_> {
                     | "inversesqrt" | "exp" | "exp2" | "sign" | "transpose" | "inverse"
                     | "normalize" | "sinh" | "cos" | "cosh" | "tan" | "tanh" | "acos" | "asin"
                     | "log" | "log2" | "length" | "determinant" | "bitCount"
-                    | "bitfieldReverse" => {
+                    | "bitfieldReverse" | "findLSB" | "findMSB" | "packSnorm4x8"
+                    | "packUnorm4x8" | "packSnorm2x16" | "packUnorm2x16" | "packHalf2x16"
+                    | "unpackSnorm4x8" | "unpackUnorm4x8" | "unpackSnorm2x16"
+                    | "unpackUnorm2x16" | "unpackHalf2x16" => {
                         if args.len() != 1 {
                             return Err(ErrorKind::wrong_function_args(name, 1, args.len(), meta));
                         }
@@ -329,11 +400,24 @@ impl Program<'_> {
                                     "determinant" => MathFunction::Determinant,
                                     "bitCount" => MathFunction::CountOneBits,
                                     "bitfieldReverse" => MathFunction::ReverseBits,
+                                    "findLSB" => MathFunction::FirstTrailingBit,
+                                    "findMSB" => MathFunction::FirstLeadingBit,
+                                    "packSnorm4x8" => MathFunction::Pack4x8snorm,
+                                    "packUnorm4x8" => MathFunction::Pack4x8unorm,
+                                    "packSnorm2x16" => MathFunction::Pack2x16snorm,
+                                    "packUnorm2x16" => MathFunction::Pack2x16unorm,
+                                    "packHalf2x16" => MathFunction::Pack2x16float,
+                                    "unpackSnorm4x8" => MathFunction::Unpack4x8snorm,
+                                    "unpackUnorm4x8" => MathFunction::Unpack4x8unorm,
+                                    "unpackSnorm2x16" => MathFunction::Unpack2x16snorm,
+                                    "unpackUnorm2x16" => MathFunction::Unpack2x16unorm,
+                                    "unpackHalf2x16" => MathFunction::Unpack2x16float,
                                     _ => unreachable!(),
                                 },
                                 arg: args[0].0,
                                 arg1: None,
                                 arg2: None,
+                                arg3: None,
                             },
                             body,
                         )))
@@ -345,12 +429,14 @@ impl Program<'_> {
                                 arg: args[0].0,
                                 arg1: None,
                                 arg2: None,
+                                arg3: None,
                             },
                             2 => Expression::Math {
                                 fun: MathFunction::Atan2,
                                 arg: args[0].0,
                                 arg1: Some(args[1].0),
                                 arg2: None,
+                                arg3: None,
                             },
                             _ => {
                                 return Err(ErrorKind::wrong_function_args(
@@ -409,6 +495,7 @@ impl Program<'_> {
                                 arg: args[0].0,
                                 arg1: Some(args[1].0),
                                 arg2: None,
+                                arg3: None,
                             },
                             body,
                         )))
@@ -436,6 +523,7 @@ impl Program<'_> {
                                         arg: args[0].0,
                                         arg1: Some(args[1].0),
                                         arg2: Some(args[2].0),
+                                        arg3: None,
                                     },
                                     body,
                                 )
@@ -459,10 +547,55 @@ impl Program<'_> {
                                 arg: args[0].0,
                                 arg1: Some(args[1].0),
                                 arg2: Some(args[2].0),
+                                arg3: None,
                             },
                             body,
                         )))
                     }
+                    "bitfieldExtract" | "bitfieldInsert" => {
+                        let (fun, arg_count) = match name.as_str() {
+                            "bitfieldExtract" => (MathFunction::ExtractBits, 3),
+                            _ => (MathFunction::InsertBits, 4),
+                        };
+                        if args.len() != arg_count {
+                            return Err(ErrorKind::wrong_function_args(
+                                name,
+                                arg_count,
+                                args.len(),
+                                meta,
+                            ));
+                        }
+                        // GLSL takes the offset and the count as `int`
+                        let mut offset_count = args[arg_count - 2..].iter().map(|&(expr, _)| {
+                            ctx.add_expression(
+                                Expression::As {
+                                    kind: ScalarKind::Uint,
+                                    expr,
+                                    convert: Some(4),
+                                },
+                                body,
+                            )
+                        });
+                        let offset = offset_count.next();
+                        let count = offset_count.next();
+                        let expr = match fun {
+                            MathFunction::ExtractBits => Expression::Math {
+                                fun,
+                                arg: args[0].0,
+                                arg1: offset,
+                                arg2: count,
+                                arg3: None,
+                            },
+                            _ => Expression::Math {
+                                fun,
+                                arg: args[0].0,
+                                arg1: Some(args[1].0),
+                                arg2: offset,
+                                arg3: count,
+                            },
+                        };
+                        Ok(Some(ctx.add_expression(expr, body)))
+                    }
                     "lessThan" | "greaterThan" | "lessThanEqual" | "greaterThanEqual" | "equal"
                     | "notEqual" => {
                         if args.len() != 2 {
@@ -962,6 +1095,8 @@ impl Program<'_> {
                 } else {
                     [0; 3]
                 },
+                mesh_info: None,
+                task_payload: None,
                 function: Function {
                     arguments,
                     expressions,
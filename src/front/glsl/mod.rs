@@ -1,7 +1,7 @@
 pub use error::ErrorKind;
 pub use token::{SourceMetadata, Token};
 
-use crate::{FastHashMap, Module, ShaderStage};
+use crate::{FastHashMap, Function, Handle, Module, ShaderStage};
 
 mod lex;
 
@@ -10,7 +10,6 @@ use ast::Program;
 
 mod error;
 pub use error::ParseError;
-mod constants;
 mod functions;
 mod parser;
 #[cfg(test)]
@@ -26,11 +25,28 @@ pub struct Options {
 }
 
 pub fn parse_str(source: &str, options: &Options) -> Result<Module, ParseError> {
+    parse_str_with_declarations(source, options).map(|(module, _)| module)
+}
+
+/// Parse `source` like [`parse_str`], and also return the functions that are
+/// declared by a prototype without being defined, so that they can be
+/// [linked](crate::proc::link) to the definitions of another module.
+pub fn parse_str_with_declarations(
+    source: &str,
+    options: &Options,
+) -> Result<(Module, Vec<Handle<Function>>), ParseError> {
     let mut program = Program::new(&options.entry_points);
 
     let lex = lex::Lexer::new(source, &options.defines);
     let mut parser = parser::Parser::new(&mut program, lex);
     parser.parse()?;
 
-    Ok(program.module)
+    let declarations = program
+        .lookup_function
+        .values()
+        .flatten()
+        .filter(|decl| !decl.defined)
+        .map(|decl| decl.handle)
+        .collect();
+    Ok((program.module, declarations))
 }
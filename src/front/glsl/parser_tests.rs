@@ -257,6 +257,37 @@ fn declarations() {
     .unwrap();
 }
 
+#[test]
+fn int64_types() {
+    let mut entry_points = crate::FastHashMap::default();
+    entry_points.insert("".to_string(), ShaderStage::Compute);
+
+    let program = parse_program(
+        r#"
+        #version 450
+        layout(std430, set = 0, binding = 0)
+        buffer Keys {
+            uint64_t key;
+            i64vec2 range;
+        };
+        "#,
+        &entry_points,
+    )
+    .unwrap();
+
+    let has_type =
+        |inner: crate::TypeInner| program.module.types.iter().any(|(_, ty)| ty.inner == inner);
+    assert!(has_type(crate::TypeInner::Scalar {
+        kind: crate::ScalarKind::Uint,
+        width: 8,
+    }));
+    assert!(has_type(crate::TypeInner::Vector {
+        size: crate::VectorSize::Bi,
+        kind: crate::ScalarKind::Sint,
+        width: 8,
+    }));
+}
+
 #[test]
 fn textures() {
     let mut entry_points = crate::FastHashMap::default();
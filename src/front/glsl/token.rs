@@ -29,6 +29,12 @@ impl From<SourceMetadata> for Range<usize> {
     }
 }
 
+impl From<SourceMetadata> for crate::Span {
+    fn from(meta: SourceMetadata) -> Self {
+        crate::Span::new(meta.start as u32, meta.end as u32)
+    }
+}
+
 #[derive(Debug)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct Token {
@@ -37,6 +37,20 @@ pub fn parse_type(type_name: &str) -> Option<Type> {
                 width: 4,
             },
         }),
+        "int64_t" => Some(Type {
+            name: None,
+            inner: TypeInner::Scalar {
+                kind: ScalarKind::Sint,
+                width: 8,
+            },
+        }),
+        "uint64_t" => Some(Type {
+            name: None,
+            inner: TypeInner::Scalar {
+                kind: ScalarKind::Uint,
+                width: 8,
+            },
+        }),
         "texture2D" => Some(Type {
             name: None,
             inner: TypeInner::Image {
@@ -60,6 +74,8 @@ pub fn parse_type(type_name: &str) -> Option<Type> {
                     "i" => (ScalarKind::Sint, 4),
                     "u" => (ScalarKind::Uint, 4),
                     "d" => (ScalarKind::Float, 8),
+                    "i64" => (ScalarKind::Sint, 8),
+                    "u64" => (ScalarKind::Uint, 8),
                     _ => return None,
                 })
             }
@@ -402,20 +402,24 @@ impl Program<'_> {
                 }
             });
 
-            let handle = self.module.global_variables.append(GlobalVariable {
-                name: name.clone(),
-                class: StorageClass::Private,
-                binding: None,
-                ty,
-                init,
-                storage_access: StorageAccess::empty(),
-            });
+            let handle = self.module.global_variables.append_with_span(
+                GlobalVariable {
+                    name: name.clone(),
+                    class: StorageClass::Private,
+                    binding: None,
+                    ty,
+                    init,
+                    storage_access: StorageAccess::empty(),
+                },
+                meta.into(),
+            );
 
             let idx = self.entry_args.len();
             self.entry_args.push(EntryArg {
                 name: name.clone(),
                 binding: Binding::Location {
                     location,
+                    second_blend_source: false,
                     interpolation,
                     sampling,
                 },
@@ -439,6 +443,7 @@ impl Program<'_> {
             let init = init.ok_or_else(|| {
                 ErrorKind::SemanticError(meta, "const values must have an initializer".into())
             })?;
+            self.module.constants.init_span(init, meta.into());
             if let Some(name) = name {
                 self.global_variables.push((
                     name,
@@ -479,14 +484,17 @@ impl Program<'_> {
             }
         };
 
-        let handle = self.module.global_variables.append(GlobalVariable {
-            name: name.clone(),
-            class,
-            binding,
-            ty,
-            init,
-            storage_access,
-        });
+        let handle = self.module.global_variables.append_with_span(
+            GlobalVariable {
+                name: name.clone(),
+                class,
+                binding,
+                ty,
+                init,
+                storage_access,
+            },
+            meta.into(),
+        );
 
         if let Some(name) = name {
             self.global_variables.push((
@@ -506,7 +514,6 @@ impl Program<'_> {
         &mut self,
         ctx: &mut Context,
         body: &mut Block,
-        #[cfg_attr(not(feature = "glsl-validate"), allow(unused_variables))]
         VarDeclaration {
             qualifiers,
             ty,
@@ -545,11 +552,14 @@ impl Program<'_> {
             }
         }
 
-        let handle = ctx.locals.append(LocalVariable {
-            name: name.clone(),
-            ty,
-            init,
-        });
+        let handle = ctx.locals.append_with_span(
+            LocalVariable {
+                name: name.clone(),
+                ty,
+                init,
+            },
+            meta.into(),
+        );
         let expr = ctx.add_expression(Expression::LocalVariable(handle), body);
 
         if let Some(name) = name {
@@ -0,0 +1,1915 @@
+/*!
+Front end for the textual form of the IR.
+
+Parses the output of the [`ir`](crate::back::ir) backend, and hand-written
+modules in the same syntax, which may also contain `//` comments. Every
+handle has to be defined in order, and referenced handles have to exist,
+but nothing else is validated.
+!*/
+
+use crate::arena::{Arena, Handle, Range};
+use std::str::FromStr;
+
+/// Error of the textual IR parser, at a one-based line and column.
+#[derive(Clone, Debug, thiserror::Error, PartialEq)]
+#[error("{message} at line {line}, column {column}")]
+pub struct ParseError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Parse a module in the textual form of the IR.
+pub fn parse_str(source: &str) -> Result<crate::Module, ParseError> {
+    let mut parser = Parser {
+        lexer: Lexer { source, offset: 0 },
+        module_refs: Vec::new(),
+        function_refs: Vec::new(),
+    };
+    parser.parse_module().map_err(|error| {
+        let before = &source[..error.offset];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        ParseError {
+            message: error.message,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    })
+}
+
+/// Error at a byte offset of the source.
+struct Error {
+    message: String,
+    offset: usize,
+}
+
+impl Error {
+    fn new(message: impl Into<String>, offset: usize) -> Self {
+        Error {
+            message: message.into(),
+            offset,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    Number(&'a str),
+    String(String),
+    Punct(char),
+    DotDot,
+    End,
+}
+
+#[derive(Clone)]
+struct Lexer<'a> {
+    source: &'a str,
+    offset: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.offset += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.offset += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Take the characters matching `predicate`, starting after `skip` bytes.
+    fn take_while(&mut self, skip: usize, mut predicate: impl FnMut(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let length = rest[skip..]
+            .find(|c: char| !predicate(c))
+            .map_or(rest.len(), |length| length + skip);
+        self.offset += length;
+        &rest[..length]
+    }
+
+    /// Return the next token and its offset.
+    fn next(&mut self) -> Result<(Token<'a>, usize), Error> {
+        self.skip_whitespace();
+        let start = self.offset;
+        let rest = self.rest();
+        let first = match rest.chars().next() {
+            Some(c) => c,
+            None => return Ok((Token::End, start)),
+        };
+        let token = match first {
+            'a'..='z' | 'A'..='Z' | '_' => {
+                Token::Word(self.take_while(0, |c| c.is_ascii_alphanumeric() || c == '_'))
+            }
+            '0'..='9' | '-' => {
+                let mut previous = first;
+                let number = self.take_while(1, |c| {
+                    let exponent_sign = (c == '-' || c == '+') && (previous == 'e');
+                    let accepted = c.is_ascii_alphanumeric() || c == '.' || exponent_sign;
+                    previous = c;
+                    accepted
+                });
+                // a number never ends with a dot, but may be followed by `..`
+                if number.ends_with('.') {
+                    self.offset -= 1;
+                    Token::Number(&number[..number.len() - 1])
+                } else {
+                    Token::Number(number)
+                }
+            }
+            '"' => Token::String(self.string()?),
+            '(' | ')' | '[' | ']' | '{' | '}' | ',' | ':' | '=' => {
+                self.offset += 1;
+                Token::Punct(first)
+            }
+            '.' if rest.starts_with("..") => {
+                self.offset += 2;
+                Token::DotDot
+            }
+            _ => return Err(Error::new(format!("unexpected `{}`", first), start)),
+        };
+        Ok((token, start))
+    }
+
+    fn peek(&self) -> Result<Token<'a>, Error> {
+        self.clone().next().map(|(token, _)| token)
+    }
+
+    /// Parse a string literal, with the escapes of Rust's `Debug` output.
+    fn string(&mut self) -> Result<String, Error> {
+        let start = self.offset;
+        let mut chars = self.rest().char_indices().skip(1);
+        let mut string = String::new();
+        loop {
+            let (index, c) = match chars.next() {
+                Some(pair) => pair,
+                None => return Err(Error::new("unterminated string", start)),
+            };
+            match c {
+                '"' => {
+                    self.offset += index + 1;
+                    return Ok(string);
+                }
+                '\\' => {
+                    let escaped = match chars.next() {
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, 't')) => '\t',
+                        Some((_, '0')) => '\0',
+                        Some((_, '\\')) => '\\',
+                        Some((_, '\'')) => '\'',
+                        Some((_, '"')) => '"',
+                        Some((_, 'u')) => {
+                            let mut digits = String::new();
+                            let mut closed = false;
+                            for (_, c) in chars.by_ref() {
+                                match c {
+                                    '{' => {}
+                                    '}' => {
+                                        closed = true;
+                                        break;
+                                    }
+                                    _ => digits.push(c),
+                                }
+                            }
+                            let code = u32::from_str_radix(&digits, 16).ok();
+                            match code.and_then(std::char::from_u32) {
+                                Some(c) if closed => c,
+                                _ => {
+                                    return Err(Error::new("invalid unicode escape", start + index))
+                                }
+                            }
+                        }
+                        _ => return Err(Error::new("invalid escape", start + index)),
+                    };
+                    string.push(escaped);
+                }
+                _ => string.push(c),
+            }
+        }
+    }
+}
+
+/// A generic value, before it's converted to the IR.
+struct Value<'a> {
+    kind: ValueKind<'a>,
+    offset: usize,
+}
+
+enum ValueKind<'a> {
+    Word(&'a str),
+    Number(&'a str),
+    String(String),
+    List(Vec<Value<'a>>),
+    Call {
+        name: &'a str,
+        arguments: Vec<Value<'a>>,
+        fields: Vec<(&'a str, Value<'a>)>,
+    },
+}
+
+/// A call, or a word taken as a call without arguments.
+struct Call<'v, 'a> {
+    name: &'a str,
+    arguments: &'v [Value<'a>],
+    fields: &'v [(&'a str, Value<'a>)],
+    offset: usize,
+}
+
+impl<'v, 'a> Call<'v, 'a> {
+    /// Check the number of arguments and the names of the fields.
+    fn check(&self, count: usize, names: &[&str]) -> Result<(), Error> {
+        if self.arguments.len() != count {
+            return Err(Error::new(
+                format!(
+                    "`{}` takes {} arguments, but {} were given",
+                    self.name,
+                    count,
+                    self.arguments.len()
+                ),
+                self.offset,
+            ));
+        }
+        for (index, &(name, ref value)) in self.fields.iter().enumerate() {
+            if !names.contains(&name) {
+                return Err(Error::new(
+                    format!("`{}` has no field `{}`", self.name, name),
+                    value.offset,
+                ));
+            }
+            if self.fields[..index].iter().any(|&(other, _)| other == name) {
+                return Err(Error::new(
+                    format!("duplicate field `{}`", name),
+                    value.offset,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn arg(&self, index: usize) -> &'v Value<'a> {
+        &self.arguments[index]
+    }
+
+    fn field(&self, name: &str) -> Option<&'v Value<'a>> {
+        self.fields
+            .iter()
+            .find(|field| field.0 == name)
+            .map(|field| &field.1)
+    }
+
+    fn unknown(&self, what: &str) -> Error {
+        Error::new(format!("unknown {} `{}`", what, self.name), self.offset)
+    }
+}
+
+impl<'a> Value<'a> {
+    fn error(&self, expected: &str) -> Error {
+        Error::new(format!("expected {}", expected), self.offset)
+    }
+
+    fn word(&self) -> Result<&'a str, Error> {
+        match self.kind {
+            ValueKind::Word(word) => Ok(word),
+            _ => Err(self.error("a word")),
+        }
+    }
+
+    fn call(&self) -> Result<Call<'_, 'a>, Error> {
+        match self.kind {
+            ValueKind::Word(name) => Ok(Call {
+                name,
+                arguments: &[],
+                fields: &[],
+                offset: self.offset,
+            }),
+            ValueKind::Call {
+                name,
+                ref arguments,
+                ref fields,
+            } => Ok(Call {
+                name,
+                arguments,
+                fields,
+                offset: self.offset,
+            }),
+            _ => Err(self.error("a name")),
+        }
+    }
+
+    fn list(&self) -> Result<&[Value<'a>], Error> {
+        match self.kind {
+            ValueKind::List(ref values) => Ok(values),
+            _ => Err(self.error("a list")),
+        }
+    }
+
+    fn string(&self) -> Result<String, Error> {
+        match self.kind {
+            ValueKind::String(ref string) => Ok(string.clone()),
+            _ => Err(self.error("a string")),
+        }
+    }
+
+    fn number<T: FromStr>(&self) -> Result<T, Error> {
+        match self.kind {
+            ValueKind::Number(number) => number.parse().map_err(|_| self.error("a valid number")),
+            _ => Err(self.error("a number")),
+        }
+    }
+
+    fn boolean(&self) -> Result<bool, Error> {
+        match self.word()? {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err(self.error("`true` or `false`")),
+        }
+    }
+
+    fn vector_size(&self) -> Result<crate::VectorSize, Error> {
+        match self.number::<u8>()? {
+            2 => Ok(crate::VectorSize::Bi),
+            3 => Ok(crate::VectorSize::Tri),
+            4 => Ok(crate::VectorSize::Quad),
+            _ => Err(self.error("a vector size")),
+        }
+    }
+}
+
+macro_rules! unit_enum {
+    ($fun:ident, $ty:ident, [$($variant:ident),* $(,)*]) => {
+        fn $fun(value: &Value) -> Result<crate::$ty, Error> {
+            match value.word()? {
+                $(stringify!($variant) => Ok(crate::$ty::$variant),)*
+                _ => Err(value.error(stringify!($ty))),
+            }
+        }
+    };
+}
+
+unit_enum!(scalar_kind, ScalarKind, [Sint, Uint, Float, Bool]);
+unit_enum!(
+    storage_class,
+    StorageClass,
+    [
+        Function,
+        Private,
+        WorkGroup,
+        Uniform,
+        Storage,
+        Handle,
+        PushConstant,
+        TaskPayload
+    ]
+);
+unit_enum!(
+    built_in,
+    BuiltIn,
+    [
+        Position,
+        ViewIndex,
+        BaseInstance,
+        BaseVertex,
+        ClipDistance,
+        CullDistance,
+        InstanceIndex,
+        PointSize,
+        VertexIndex,
+        FragDepth,
+        FrontFacing,
+        PrimitiveIndex,
+        SampleIndex,
+        SampleMask,
+        GlobalInvocationId,
+        LocalInvocationId,
+        LocalInvocationIndex,
+        WorkGroupId,
+        WorkGroupSize,
+        SubgroupSize,
+        SubgroupInvocationId,
+        MeshTaskSize,
+        CullPrimitive,
+    ]
+);
+unit_enum!(interpolation, Interpolation, [Perspective, Linear, Flat]);
+unit_enum!(sampling, Sampling, [Center, Centroid, Sample]);
+unit_enum!(image_dimension, ImageDimension, [D1, D2, D3, Cube]);
+unit_enum!(
+    storage_format,
+    StorageFormat,
+    [
+        R8Unorm,
+        R8Snorm,
+        R8Uint,
+        R8Sint,
+        R16Uint,
+        R16Sint,
+        R16Float,
+        Rg8Unorm,
+        Rg8Snorm,
+        Rg8Uint,
+        Rg8Sint,
+        R32Uint,
+        R32Sint,
+        R32Float,
+        Rg16Uint,
+        Rg16Sint,
+        Rg16Float,
+        Rgba8Unorm,
+        Rgba8Snorm,
+        Rgba8Uint,
+        Rgba8Sint,
+        Rgb10a2Unorm,
+        Rg11b10Float,
+        Rg32Uint,
+        Rg32Sint,
+        Rg32Float,
+        Rgba16Uint,
+        Rgba16Sint,
+        Rgba16Float,
+        Rgba32Uint,
+        Rgba32Sint,
+        Rgba32Float,
+    ]
+);
+unit_enum!(
+    shader_stage,
+    ShaderStage,
+    [Vertex, Fragment, Compute, Task, Mesh]
+);
+unit_enum!(
+    conservative_depth,
+    ConservativeDepth,
+    [GreaterEqual, LessEqual, Unchanged]
+);
+unit_enum!(
+    mesh_output_topology,
+    MeshOutputTopology,
+    [Points, Lines, Triangles]
+);
+unit_enum!(unary_operator, UnaryOperator, [Negate, Not]);
+unit_enum!(
+    binary_operator,
+    BinaryOperator,
+    [
+        Add,
+        Subtract,
+        Multiply,
+        Divide,
+        Modulo,
+        Equal,
+        NotEqual,
+        Less,
+        LessEqual,
+        Greater,
+        GreaterEqual,
+        And,
+        ExclusiveOr,
+        InclusiveOr,
+        LogicalAnd,
+        LogicalOr,
+        ShiftLeft,
+        ShiftRight,
+    ]
+);
+unit_enum!(derivative_axis, DerivativeAxis, [X, Y, Width]);
+unit_enum!(
+    relational_function,
+    RelationalFunction,
+    [All, Any, IsNan, IsInf, IsFinite, IsNormal]
+);
+unit_enum!(
+    math_function,
+    MathFunction,
+    [
+        Abs,
+        Min,
+        Max,
+        Clamp,
+        Cos,
+        Cosh,
+        Sin,
+        Sinh,
+        Tan,
+        Tanh,
+        Acos,
+        Asin,
+        Atan,
+        Atan2,
+        Ceil,
+        Floor,
+        Round,
+        Fract,
+        Trunc,
+        Modf,
+        Frexp,
+        Ldexp,
+        Exp,
+        Exp2,
+        Log,
+        Log2,
+        Pow,
+        Dot,
+        Outer,
+        Cross,
+        Distance,
+        Length,
+        Normalize,
+        FaceForward,
+        Reflect,
+        Refract,
+        Sign,
+        Fma,
+        Mix,
+        Step,
+        SmoothStep,
+        Sqrt,
+        InverseSqrt,
+        Inverse,
+        Transpose,
+        Determinant,
+        CountTrailingZeros,
+        CountLeadingZeros,
+        CountOneBits,
+        ReverseBits,
+        ExtractBits,
+        InsertBits,
+        FirstTrailingBit,
+        FirstLeadingBit,
+        Pack4x8snorm,
+        Pack4x8unorm,
+        Pack2x16snorm,
+        Pack2x16unorm,
+        Pack2x16float,
+        Unpack4x8snorm,
+        Unpack4x8unorm,
+        Unpack2x16snorm,
+        Unpack2x16unorm,
+        Unpack2x16float,
+    ]
+);
+unit_enum!(
+    subgroup_operation,
+    SubgroupOperation,
+    [All, Any, Add, Mul, Min, Max, And, Or, Xor]
+);
+unit_enum!(
+    collective_operation,
+    CollectiveOperation,
+    [Reduce, InclusiveScan, ExclusiveScan]
+);
+unit_enum!(swizzle_component, SwizzleComponent, [X, Y, Z, W]);
+
+/// Kinds of handles, by their prefix.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum HandleKind {
+    Type,
+    Constant,
+    GlobalVariable,
+    Function,
+    LocalVariable,
+    Expression,
+}
+
+impl HandleKind {
+    fn prefix(self) -> char {
+        match self {
+            Self::Type => 't',
+            Self::Constant => 'c',
+            Self::GlobalVariable => 'g',
+            Self::Function => 'f',
+            Self::LocalVariable => 'l',
+            Self::Expression => 'e',
+        }
+    }
+
+    /// Return the index of a handle written with this kind's prefix.
+    fn index(self, word: &str) -> Option<usize> {
+        let digits = word.strip_prefix(self.prefix())?;
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        // keep one index free, so that the handle is representable
+        digits.parse::<u32>().ok().filter(|&index| index != !0)?;
+        digits.parse().ok()
+    }
+}
+
+/// A handle referenced at an offset of the source.
+struct Reference {
+    kind: HandleKind,
+    index: usize,
+    offset: usize,
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    /// Referenced handles of the module arenas.
+    module_refs: Vec<Reference>,
+    /// Referenced handles of the arenas of the current function.
+    function_refs: Vec<Reference>,
+}
+
+impl<'a> Parser<'a> {
+    fn expect(&mut self, expected: Token) -> Result<(), Error> {
+        let (token, offset) = self.lexer.next()?;
+        if token == expected {
+            Ok(())
+        } else {
+            let description = match expected {
+                Token::Punct(c) => format!("`{}`", c),
+                Token::Word(word) => format!("`{}`", word),
+                Token::DotDot => "`..`".to_string(),
+                _ => "a token".to_string(),
+            };
+            Err(Error::new(format!("expected {}", description), offset))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value<'a>, Error> {
+        let (token, offset) = self.lexer.next()?;
+        let kind = match token {
+            Token::Word(name) => {
+                if self.lexer.peek()? != Token::Punct('(') {
+                    ValueKind::Word(name)
+                } else {
+                    self.lexer.next()?;
+                    let mut arguments = Vec::new();
+                    let mut fields = Vec::new();
+                    while self.lexer.peek()? != Token::Punct(')') {
+                        let mut lookahead = self.lexer.clone();
+                        match (lookahead.next()?.0, lookahead.next()?.0) {
+                            (Token::Word(field), Token::Punct(':')) => {
+                                self.lexer = lookahead;
+                                fields.push((field, self.parse_value()?));
+                            }
+                            _ if !fields.is_empty() => {
+                                let (_, offset) = self.lexer.clone().next()?;
+                                return Err(Error::new("expected a field", offset));
+                            }
+                            _ => arguments.push(self.parse_value()?),
+                        }
+                        if self.lexer.peek()? != Token::Punct(')') {
+                            self.expect(Token::Punct(','))?;
+                        }
+                    }
+                    self.lexer.next()?;
+                    ValueKind::Call {
+                        name,
+                        arguments,
+                        fields,
+                    }
+                }
+            }
+            Token::Number(number) => ValueKind::Number(number),
+            Token::String(string) => ValueKind::String(string),
+            Token::Punct('[') => {
+                let mut values = Vec::new();
+                while self.lexer.peek()? != Token::Punct(']') {
+                    values.push(self.parse_value()?);
+                    if self.lexer.peek()? != Token::Punct(']') {
+                        self.expect(Token::Punct(','))?;
+                    }
+                }
+                self.lexer.next()?;
+                ValueKind::List(values)
+            }
+            _ => return Err(Error::new("expected a value", offset)),
+        };
+        Ok(Value { kind, offset })
+    }
+
+    fn handle<T>(&mut self, value: &Value, kind: HandleKind) -> Result<Handle<T>, Error> {
+        let index = match value.kind {
+            ValueKind::Word(word) => kind.index(word),
+            _ => None,
+        };
+        let index = index.ok_or_else(|| value.error(&format!("a `{}` handle", kind.prefix())))?;
+        let reference = Reference {
+            kind,
+            index,
+            offset: value.offset,
+        };
+        match kind {
+            HandleKind::LocalVariable | HandleKind::Expression => {
+                self.function_refs.push(reference)
+            }
+            _ => self.module_refs.push(reference),
+        }
+        Ok(Handle::from_usize(index))
+    }
+
+    fn option_handle<T>(
+        &mut self,
+        value: Option<&Value>,
+        kind: HandleKind,
+    ) -> Result<Option<Handle<T>>, Error> {
+        match value {
+            Some(value) => self.handle(value, kind).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn handles<T>(&mut self, value: &Value, kind: HandleKind) -> Result<Vec<Handle<T>>, Error> {
+        value
+            .list()?
+            .iter()
+            .map(|value| self.handle(value, kind))
+            .collect()
+    }
+
+    fn expression(&mut self, value: &Value) -> Result<Handle<crate::Expression>, Error> {
+        self.handle(value, HandleKind::Expression)
+    }
+
+    fn option_expression(
+        &mut self,
+        value: Option<&Value>,
+    ) -> Result<Option<Handle<crate::Expression>>, Error> {
+        self.option_handle(value, HandleKind::Expression)
+    }
+
+    /// Check that every referenced handle is in its arena.
+    fn check_references(
+        refs: &[Reference],
+        len: impl Fn(HandleKind) -> usize,
+    ) -> Result<(), Error> {
+        match refs.iter().find(|r| r.index >= len(r.kind)) {
+            Some(r) => Err(Error::new(
+                format!("`{}{}` is not defined", r.kind.prefix(), r.index),
+                r.offset,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Parse the header of an item, as a handle of `kind`, which has to be
+    /// the next one in its arena, and an optional name.
+    fn parse_header(
+        &mut self,
+        word: &str,
+        offset: usize,
+        kind: HandleKind,
+        next_index: usize,
+    ) -> Result<Option<String>, Error> {
+        if kind.index(word) != Some(next_index) {
+            return Err(Error::new(
+                format!("expected `{}{}`", kind.prefix(), next_index),
+                offset,
+            ));
+        }
+        let name = match self.lexer.peek()? {
+            Token::String(_) => self.parse_value()?.string().map(Some)?,
+            _ => None,
+        };
+        self.expect(Token::Punct('='))?;
+        Ok(name)
+    }
+
+    fn parse_module(&mut self) -> Result<crate::Module, Error> {
+        let mut module = crate::Module::default();
+        loop {
+            let (token, offset) = self.lexer.next()?;
+            let word = match token {
+                Token::End => break,
+                Token::Word(word) => word,
+                _ => return Err(Error::new("expected an item", offset)),
+            };
+            if word == "entry_point" {
+                let name = self.parse_value()?.string()?;
+                self.expect(Token::Punct('='))?;
+                let value = self.parse_value()?;
+                let mut ep = self.entry_point(name, &value)?;
+                self.expect(Token::Word("function"))?;
+                let name = match self.lexer.peek()? {
+                    Token::String(_) => self.parse_value()?.string().map(Some)?,
+                    _ => None,
+                };
+                self.expect(Token::Punct('='))?;
+                ep.function = self.parse_function(name)?;
+                module.entry_points.push(ep);
+                continue;
+            }
+            if word == "derived_constant" {
+                self.expect(Token::Punct('='))?;
+                let derived = self.parse_derived_constant()?;
+                module.derived_constants.push(derived);
+                continue;
+            }
+            match word.chars().next() {
+                Some('t') => {
+                    let name =
+                        self.parse_header(word, offset, HandleKind::Type, module.types.len())?;
+                    let value = self.parse_value()?;
+                    let inner = self.type_inner(&value)?;
+                    module.types.append(crate::Type { name, inner });
+                }
+                Some('c') => {
+                    let next = module.constants.len();
+                    let name = self.parse_header(word, offset, HandleKind::Constant, next)?;
+                    let value = self.parse_value()?;
+                    let constant = self.constant(name, &value)?;
+                    module.constants.append(constant);
+                }
+                Some('g') => {
+                    let next = module.global_variables.len();
+                    let name = self.parse_header(word, offset, HandleKind::GlobalVariable, next)?;
+                    let value = self.parse_value()?;
+                    let global = self.global_variable(name, &value)?;
+                    module.global_variables.append(global);
+                }
+                Some('f') => {
+                    let next = module.functions.len();
+                    let name = self.parse_header(word, offset, HandleKind::Function, next)?;
+                    let function = self.parse_function(name)?;
+                    module.functions.append(function);
+                }
+                _ => return Err(Error::new(format!("unexpected `{}`", word), offset)),
+            }
+        }
+        Self::check_references(&self.module_refs, |kind| match kind {
+            HandleKind::Type => module.types.len(),
+            HandleKind::Constant => module.constants.len(),
+            HandleKind::GlobalVariable => module.global_variables.len(),
+            _ => module.functions.len(),
+        })?;
+        Ok(module)
+    }
+
+    fn type_inner(&mut self, value: &Value) -> Result<crate::TypeInner, Error> {
+        use crate::TypeInner as Ti;
+        let call = value.call()?;
+        Ok(match call.name {
+            "Scalar" => {
+                call.check(2, &[])?;
+                Ti::Scalar {
+                    kind: scalar_kind(call.arg(0))?,
+                    width: call.arg(1).number()?,
+                }
+            }
+            "Vector" => {
+                call.check(3, &[])?;
+                Ti::Vector {
+                    size: call.arg(0).vector_size()?,
+                    kind: scalar_kind(call.arg(1))?,
+                    width: call.arg(2).number()?,
+                }
+            }
+            "Matrix" => {
+                call.check(3, &[])?;
+                Ti::Matrix {
+                    columns: call.arg(0).vector_size()?,
+                    rows: call.arg(1).vector_size()?,
+                    width: call.arg(2).number()?,
+                }
+            }
+            "Atomic" => {
+                call.check(2, &[])?;
+                Ti::Atomic {
+                    kind: scalar_kind(call.arg(0))?,
+                    width: call.arg(1).number()?,
+                }
+            }
+            "Pointer" => {
+                call.check(2, &[])?;
+                Ti::Pointer {
+                    base: self.handle(call.arg(0), HandleKind::Type)?,
+                    class: storage_class(call.arg(1))?,
+                }
+            }
+            "ValuePointer" => {
+                call.check(3, &["size"])?;
+                Ti::ValuePointer {
+                    size: match call.field("size") {
+                        Some(size) => Some(size.vector_size()?),
+                        None => None,
+                    },
+                    kind: scalar_kind(call.arg(0))?,
+                    width: call.arg(1).number()?,
+                    class: storage_class(call.arg(2))?,
+                }
+            }
+            "Array" => {
+                call.check(3, &[])?;
+                Ti::Array {
+                    base: self.handle(call.arg(0), HandleKind::Type)?,
+                    size: self.array_size(call.arg(1))?,
+                    stride: call.arg(2).number()?,
+                }
+            }
+            "Struct" => {
+                call.check(3, &[])?;
+                let mut members = Vec::new();
+                for value in call.arg(2).list()? {
+                    let member = value.call()?;
+                    if member.name != "Member" {
+                        return Err(value.error("`Member`"));
+                    }
+                    member.check(2, &["name", "binding"])?;
+                    members.push(crate::StructMember {
+                        name: member.field("name").map(Value::string).transpose()?,
+                        ty: self.handle(member.arg(0), HandleKind::Type)?,
+                        binding: self.option_binding(member.field("binding"))?,
+                        offset: member.arg(1).number()?,
+                    });
+                }
+                Ti::Struct {
+                    top_level: call.arg(0).boolean()?,
+                    members,
+                    span: call.arg(1).number()?,
+                }
+            }
+            "Image" => {
+                call.check(3, &[])?;
+                let class_call = call.arg(2).call()?;
+                let class = match class_call.name {
+                    "Sampled" => {
+                        class_call.check(2, &[])?;
+                        crate::ImageClass::Sampled {
+                            kind: scalar_kind(class_call.arg(0))?,
+                            multi: class_call.arg(1).boolean()?,
+                        }
+                    }
+                    "Depth" => {
+                        class_call.check(0, &[])?;
+                        crate::ImageClass::Depth
+                    }
+                    "Storage" => {
+                        class_call.check(1, &[])?;
+                        crate::ImageClass::Storage(storage_format(class_call.arg(0))?)
+                    }
+                    _ => return Err(class_call.unknown("image class")),
+                };
+                Ti::Image {
+                    dim: image_dimension(call.arg(0))?,
+                    arrayed: call.arg(1).boolean()?,
+                    class,
+                }
+            }
+            "Sampler" => {
+                call.check(1, &[])?;
+                Ti::Sampler {
+                    comparison: call.arg(0).boolean()?,
+                }
+            }
+            "AccelerationStructure" => {
+                call.check(0, &[])?;
+                Ti::AccelerationStructure
+            }
+            "RayQuery" => {
+                call.check(0, &[])?;
+                Ti::RayQuery
+            }
+            "BindingArray" => {
+                call.check(2, &[])?;
+                Ti::BindingArray {
+                    base: self.handle(call.arg(0), HandleKind::Type)?,
+                    size: self.array_size(call.arg(1))?,
+                }
+            }
+            _ => return Err(call.unknown("type")),
+        })
+    }
+
+    fn array_size(&mut self, value: &Value) -> Result<crate::ArraySize, Error> {
+        match value.kind {
+            ValueKind::Word("Dynamic") => Ok(crate::ArraySize::Dynamic),
+            _ => Ok(crate::ArraySize::Constant(
+                self.handle(value, HandleKind::Constant)?,
+            )),
+        }
+    }
+
+    fn constant(&mut self, name: Option<String>, value: &Value) -> Result<crate::Constant, Error> {
+        let call = value.call()?;
+        call.check(2, &["specialization"])?;
+        let inner = if call.name == "Composite" {
+            crate::ConstantInner::Composite {
+                ty: self.handle(call.arg(0), HandleKind::Type)?,
+                components: self.handles(call.arg(1), HandleKind::Constant)?,
+            }
+        } else {
+            let argument = call.arg(1);
+            let value = match call.name {
+                "Sint" => crate::ScalarValue::Sint(argument.number()?),
+                "Uint" => crate::ScalarValue::Uint(argument.number()?),
+                "Float" => match argument.kind {
+                    // `inf` and `NaN` are words
+                    ValueKind::Word(word) => crate::ScalarValue::Float(
+                        word.parse().map_err(|_| argument.error("a float"))?,
+                    ),
+                    _ => crate::ScalarValue::Float(argument.number()?),
+                },
+                "Bool" => crate::ScalarValue::Bool(argument.boolean()?),
+                _ => return Err(call.unknown("constant")),
+            };
+            crate::ConstantInner::Scalar {
+                width: call.arg(0).number()?,
+                value,
+            }
+        };
+        Ok(crate::Constant {
+            name,
+            specialization: call
+                .field("specialization")
+                .map(Value::number)
+                .transpose()?,
+            inner,
+        })
+    }
+
+    fn global_variable(
+        &mut self,
+        name: Option<String>,
+        value: &Value,
+    ) -> Result<crate::GlobalVariable, Error> {
+        let call = value.call()?;
+        if call.name != "Global" {
+            return Err(value.error("`Global`"));
+        }
+        call.check(2, &["binding", "init", "access"])?;
+        let binding = match call.field("binding") {
+            Some(value) => {
+                let binding = value.call()?;
+                if binding.name != "ResourceBinding" {
+                    return Err(value.error("`ResourceBinding`"));
+                }
+                binding.check(2, &[])?;
+                Some(crate::ResourceBinding {
+                    group: binding.arg(0).number()?,
+                    binding: binding.arg(1).number()?,
+                })
+            }
+            None => None,
+        };
+        let mut storage_access = crate::StorageAccess::empty();
+        if let Some(value) = call.field("access") {
+            for flag in value.list()? {
+                storage_access |= match flag.word()? {
+                    "LOAD" => crate::StorageAccess::LOAD,
+                    "STORE" => crate::StorageAccess::STORE,
+                    _ => return Err(flag.error("a storage access flag")),
+                };
+            }
+        }
+        Ok(crate::GlobalVariable {
+            name,
+            class: storage_class(call.arg(0))?,
+            binding,
+            ty: self.handle(call.arg(1), HandleKind::Type)?,
+            init: self.option_handle(call.field("init"), HandleKind::Constant)?,
+            storage_access,
+        })
+    }
+
+    fn option_binding(&mut self, value: Option<&Value>) -> Result<Option<crate::Binding>, Error> {
+        let value = match value {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let call = value.call()?;
+        Ok(Some(match call.name {
+            "BuiltIn" => {
+                call.check(1, &[])?;
+                crate::Binding::BuiltIn(built_in(call.arg(0))?)
+            }
+            "Location" => {
+                call.check(1, &["second_blend_source", "interpolation", "sampling"])?;
+                crate::Binding::Location {
+                    location: call.arg(0).number()?,
+                    second_blend_source: match call.field("second_blend_source") {
+                        Some(value) => value.boolean()?,
+                        None => false,
+                    },
+                    interpolation: call.field("interpolation").map(interpolation).transpose()?,
+                    sampling: call.field("sampling").map(sampling).transpose()?,
+                }
+            }
+            _ => return Err(call.unknown("binding")),
+        }))
+    }
+
+    fn entry_point(&mut self, name: String, value: &Value) -> Result<crate::EntryPoint, Error> {
+        let call = value.call()?;
+        if call.name != "EntryPoint" {
+            return Err(value.error("`EntryPoint`"));
+        }
+        call.check(2, &["early_depth_test", "mesh_info", "task_payload"])?;
+        let sizes = call.arg(1).list()?;
+        if sizes.len() != 3 {
+            return Err(call.arg(1).error("three workgroup sizes"));
+        }
+        let early_depth_test = match call.field("early_depth_test") {
+            Some(value) => {
+                let test = value.call()?;
+                if test.name != "EarlyDepthTest" {
+                    return Err(value.error("`EarlyDepthTest`"));
+                }
+                test.check(0, &["conservative"])?;
+                Some(crate::EarlyDepthTest {
+                    conservative: test
+                        .field("conservative")
+                        .map(conservative_depth)
+                        .transpose()?,
+                })
+            }
+            None => None,
+        };
+        let mesh_info = match call.field("mesh_info") {
+            Some(value) => {
+                let info = value.call()?;
+                if info.name != "MeshStageInfo" {
+                    return Err(value.error("`MeshStageInfo`"));
+                }
+                info.check(4, &["primitive_output_type"])?;
+                Some(crate::MeshStageInfo {
+                    topology: mesh_output_topology(info.arg(0))?,
+                    max_vertices: info.arg(1).number()?,
+                    max_primitives: info.arg(2).number()?,
+                    vertex_output_type: self.handle(info.arg(3), HandleKind::Type)?,
+                    primitive_output_type: self
+                        .option_handle(info.field("primitive_output_type"), HandleKind::Type)?,
+                })
+            }
+            None => None,
+        };
+        Ok(crate::EntryPoint {
+            name,
+            stage: shader_stage(call.arg(0))?,
+            early_depth_test,
+            workgroup_size: [sizes[0].number()?, sizes[1].number()?, sizes[2].number()?],
+            mesh_info,
+            task_payload: self
+                .option_handle(call.field("task_payload"), HandleKind::GlobalVariable)?,
+            function: crate::Function::default(),
+        })
+    }
+
+    fn parse_derived_constant(&mut self) -> Result<crate::DerivedConstant, Error> {
+        let value = self.parse_value()?;
+        let call = value.call()?;
+        if call.name != "DerivedConstant" {
+            return Err(value.error("`DerivedConstant`"));
+        }
+        call.check(2, &[])?;
+        let constant = self.handle(call.arg(0), HandleKind::Constant)?;
+        let root = self.expression(call.arg(1))?;
+
+        let mut expressions = Arena::new();
+        self.expect(Token::Punct('{'))?;
+        loop {
+            let (word, offset) = match self.lexer.next()? {
+                (Token::Punct('}'), _) => break,
+                (Token::Word(word), offset) => (word, offset),
+                (_, offset) => return Err(Error::new("expected an expression", offset)),
+            };
+            let next = expressions.len();
+            if self
+                .parse_header(word, offset, HandleKind::Expression, next)?
+                .is_some()
+            {
+                return Err(Error::new(
+                    "derived constant expressions are unnamed",
+                    offset,
+                ));
+            }
+            let value = self.parse_value()?;
+            let expression = self.expression_value(&value)?;
+            expressions.append(expression);
+        }
+
+        // derived constants have no local variables
+        Self::check_references(&self.function_refs, |kind| match kind {
+            HandleKind::Expression => expressions.len(),
+            _ => 0,
+        })?;
+        self.function_refs.clear();
+        Ok(crate::DerivedConstant {
+            constant,
+            expressions,
+            root,
+        })
+    }
+
+    fn parse_function(&mut self, name: Option<String>) -> Result<crate::Function, Error> {
+        let value = self.parse_value()?;
+        let call = value.call()?;
+        if call.name != "Function" {
+            return Err(value.error("`Function`"));
+        }
+        call.check(0, &["arguments", "result"])?;
+        let mut function = crate::Function {
+            name,
+            ..crate::Function::default()
+        };
+        if let Some(arguments) = call.field("arguments") {
+            for value in arguments.list()? {
+                let argument = value.call()?;
+                if argument.name != "Argument" {
+                    return Err(value.error("`Argument`"));
+                }
+                argument.check(1, &["name", "binding"])?;
+                function.arguments.push(crate::FunctionArgument {
+                    name: argument.field("name").map(Value::string).transpose()?,
+                    ty: self.handle(argument.arg(0), HandleKind::Type)?,
+                    binding: self.option_binding(argument.field("binding"))?,
+                });
+            }
+        }
+        if let Some(value) = call.field("result") {
+            let result = value.call()?;
+            if result.name != "FunctionResult" {
+                return Err(value.error("`FunctionResult`"));
+            }
+            result.check(1, &["binding"])?;
+            function.result = Some(crate::FunctionResult {
+                ty: self.handle(result.arg(0), HandleKind::Type)?,
+                binding: self.option_binding(result.field("binding"))?,
+            });
+        }
+
+        self.expect(Token::Punct('{'))?;
+        loop {
+            let mut lookahead = self.lexer.clone();
+            let (word, offset) = match lookahead.next()? {
+                (Token::Word(word), offset) => (word, offset),
+                _ => break,
+            };
+            if HandleKind::LocalVariable.index(word).is_some() {
+                self.lexer = lookahead;
+                let next = function.local_variables.len();
+                let name = self.parse_header(word, offset, HandleKind::LocalVariable, next)?;
+                let value = self.parse_value()?;
+                let call = value.call()?;
+                if call.name != "Local" {
+                    return Err(value.error("`Local`"));
+                }
+                call.check(1, &["init"])?;
+                let local = crate::LocalVariable {
+                    name,
+                    ty: self.handle(call.arg(0), HandleKind::Type)?,
+                    init: self.option_handle(call.field("init"), HandleKind::Constant)?,
+                };
+                function.local_variables.append(local);
+            } else if HandleKind::Expression.index(word).is_some() {
+                self.lexer = lookahead;
+                let next = function.expressions.len();
+                let name = self.parse_header(word, offset, HandleKind::Expression, next)?;
+                let value = self.parse_value()?;
+                let expression = self.expression_value(&value)?;
+                let handle = function.expressions.append(expression);
+                if let Some(name) = name {
+                    function.named_expressions.insert(handle, name);
+                }
+            } else {
+                break;
+            }
+        }
+        function.body = self.parse_block_contents()?;
+        self.expect(Token::Punct('}'))?;
+
+        Self::check_references(&self.function_refs, |kind| match kind {
+            HandleKind::LocalVariable => function.local_variables.len(),
+            _ => function.expressions.len(),
+        })?;
+        self.function_refs.clear();
+        Ok(function)
+    }
+
+    fn expression_value(&mut self, value: &Value) -> Result<crate::Expression, Error> {
+        use crate::Expression as E;
+        let call = value.call()?;
+        Ok(match call.name {
+            "Access" => {
+                call.check(2, &[])?;
+                E::Access {
+                    base: self.expression(call.arg(0))?,
+                    index: self.expression(call.arg(1))?,
+                }
+            }
+            "AccessIndex" => {
+                call.check(2, &[])?;
+                E::AccessIndex {
+                    base: self.expression(call.arg(0))?,
+                    index: call.arg(1).number()?,
+                }
+            }
+            "Constant" => {
+                call.check(1, &[])?;
+                E::Constant(self.handle(call.arg(0), HandleKind::Constant)?)
+            }
+            "Splat" => {
+                call.check(2, &[])?;
+                E::Splat {
+                    size: call.arg(0).vector_size()?,
+                    value: self.expression(call.arg(1))?,
+                }
+            }
+            "Swizzle" => {
+                call.check(3, &[])?;
+                let components = call.arg(2).list()?;
+                if components.len() != 4 {
+                    return Err(call.arg(2).error("four components"));
+                }
+                E::Swizzle {
+                    size: call.arg(0).vector_size()?,
+                    vector: self.expression(call.arg(1))?,
+                    pattern: [
+                        swizzle_component(&components[0])?,
+                        swizzle_component(&components[1])?,
+                        swizzle_component(&components[2])?,
+                        swizzle_component(&components[3])?,
+                    ],
+                }
+            }
+            "Compose" => {
+                call.check(2, &[])?;
+                E::Compose {
+                    ty: self.handle(call.arg(0), HandleKind::Type)?,
+                    components: self.handles(call.arg(1), HandleKind::Expression)?,
+                }
+            }
+            "FunctionArgument" => {
+                call.check(1, &[])?;
+                E::FunctionArgument(call.arg(0).number()?)
+            }
+            "GlobalVariable" => {
+                call.check(1, &[])?;
+                E::GlobalVariable(self.handle(call.arg(0), HandleKind::GlobalVariable)?)
+            }
+            "LocalVariable" => {
+                call.check(1, &[])?;
+                E::LocalVariable(self.handle(call.arg(0), HandleKind::LocalVariable)?)
+            }
+            "Load" => {
+                call.check(1, &[])?;
+                E::Load {
+                    pointer: self.expression(call.arg(0))?,
+                }
+            }
+            "ImageSample" => {
+                call.check(4, &["gather", "array_index", "offset", "depth_ref"])?;
+                let level_call = call.arg(3).call()?;
+                let level = match level_call.name {
+                    "Auto" | "Zero" => {
+                        level_call.check(0, &[])?;
+                        if level_call.name == "Auto" {
+                            crate::SampleLevel::Auto
+                        } else {
+                            crate::SampleLevel::Zero
+                        }
+                    }
+                    "Exact" => {
+                        level_call.check(1, &[])?;
+                        crate::SampleLevel::Exact(self.expression(level_call.arg(0))?)
+                    }
+                    "Bias" => {
+                        level_call.check(1, &[])?;
+                        crate::SampleLevel::Bias(self.expression(level_call.arg(0))?)
+                    }
+                    "Gradient" => {
+                        level_call.check(2, &[])?;
+                        crate::SampleLevel::Gradient {
+                            x: self.expression(level_call.arg(0))?,
+                            y: self.expression(level_call.arg(1))?,
+                        }
+                    }
+                    _ => return Err(level_call.unknown("sample level")),
+                };
+                E::ImageSample {
+                    image: self.expression(call.arg(0))?,
+                    sampler: self.expression(call.arg(1))?,
+                    gather: call.field("gather").map(swizzle_component).transpose()?,
+                    coordinate: self.expression(call.arg(2))?,
+                    array_index: self.option_expression(call.field("array_index"))?,
+                    offset: self.option_handle(call.field("offset"), HandleKind::Constant)?,
+                    level,
+                    depth_ref: self.option_expression(call.field("depth_ref"))?,
+                }
+            }
+            "ImageLoad" => {
+                call.check(2, &["array_index", "index"])?;
+                E::ImageLoad {
+                    image: self.expression(call.arg(0))?,
+                    coordinate: self.expression(call.arg(1))?,
+                    array_index: self.option_expression(call.field("array_index"))?,
+                    index: self.option_expression(call.field("index"))?,
+                }
+            }
+            "ImageQuery" => {
+                call.check(2, &[])?;
+                let query_call = call.arg(1).call()?;
+                let query = match query_call.name {
+                    "Size" => {
+                        let level = match query_call.arguments.len() {
+                            0 => None,
+                            _ => {
+                                query_call.check(1, &[])?;
+                                Some(self.expression(query_call.arg(0))?)
+                            }
+                        };
+                        crate::ImageQuery::Size { level }
+                    }
+                    "NumLevels" => crate::ImageQuery::NumLevels,
+                    "NumLayers" => crate::ImageQuery::NumLayers,
+                    "NumSamples" => crate::ImageQuery::NumSamples,
+                    _ => return Err(query_call.unknown("image query")),
+                };
+                if query_call.name != "Size" {
+                    query_call.check(0, &[])?;
+                }
+                E::ImageQuery {
+                    image: self.expression(call.arg(0))?,
+                    query,
+                }
+            }
+            "Unary" => {
+                call.check(2, &[])?;
+                E::Unary {
+                    op: unary_operator(call.arg(0))?,
+                    expr: self.expression(call.arg(1))?,
+                }
+            }
+            "Binary" => {
+                call.check(3, &[])?;
+                E::Binary {
+                    op: binary_operator(call.arg(0))?,
+                    left: self.expression(call.arg(1))?,
+                    right: self.expression(call.arg(2))?,
+                }
+            }
+            "Select" => {
+                call.check(3, &[])?;
+                E::Select {
+                    condition: self.expression(call.arg(0))?,
+                    accept: self.expression(call.arg(1))?,
+                    reject: self.expression(call.arg(2))?,
+                }
+            }
+            "Derivative" => {
+                call.check(2, &[])?;
+                E::Derivative {
+                    axis: derivative_axis(call.arg(0))?,
+                    expr: self.expression(call.arg(1))?,
+                }
+            }
+            "Relational" => {
+                call.check(2, &[])?;
+                E::Relational {
+                    fun: relational_function(call.arg(0))?,
+                    argument: self.expression(call.arg(1))?,
+                }
+            }
+            "Math" => {
+                call.check(2, &["arg1", "arg2", "arg3"])?;
+                E::Math {
+                    fun: math_function(call.arg(0))?,
+                    arg: self.expression(call.arg(1))?,
+                    arg1: self.option_expression(call.field("arg1"))?,
+                    arg2: self.option_expression(call.field("arg2"))?,
+                    arg3: self.option_expression(call.field("arg3"))?,
+                }
+            }
+            "As" => {
+                call.check(2, &["convert"])?;
+                E::As {
+                    expr: self.expression(call.arg(0))?,
+                    kind: scalar_kind(call.arg(1))?,
+                    convert: call.field("convert").map(Value::number).transpose()?,
+                }
+            }
+            "Call" => {
+                call.check(1, &[])?;
+                E::Call(self.handle(call.arg(0), HandleKind::Function)?)
+            }
+            "AtomicResult" => {
+                call.check(3, &[])?;
+                E::AtomicResult {
+                    kind: scalar_kind(call.arg(0))?,
+                    width: call.arg(1).number()?,
+                    comparison: call.arg(2).boolean()?,
+                }
+            }
+            "SubgroupBallotResult" => {
+                call.check(0, &[])?;
+                E::SubgroupBallotResult
+            }
+            "SubgroupOperationResult" => {
+                call.check(1, &[])?;
+                E::SubgroupOperationResult {
+                    ty: self.handle(call.arg(0), HandleKind::Type)?,
+                }
+            }
+            "RayQueryProceedResult" => {
+                call.check(0, &[])?;
+                E::RayQueryProceedResult
+            }
+            "RayQueryGetIntersection" => {
+                call.check(3, &[])?;
+                E::RayQueryGetIntersection {
+                    query: self.expression(call.arg(0))?,
+                    committed: call.arg(1).boolean()?,
+                    ty: self.handle(call.arg(2), HandleKind::Type)?,
+                }
+            }
+            "ArrayLength" => {
+                call.check(1, &[])?;
+                E::ArrayLength(self.expression(call.arg(0))?)
+            }
+            _ => return Err(call.unknown("expression")),
+        })
+    }
+
+    fn parse_block(&mut self) -> Result<crate::Block, Error> {
+        self.expect(Token::Punct('{'))?;
+        let block = self.parse_block_contents()?;
+        self.expect(Token::Punct('}'))?;
+        Ok(block)
+    }
+
+    /// Parse statements up to the closing brace of the block.
+    fn parse_block_contents(&mut self) -> Result<crate::Block, Error> {
+        use crate::Statement as S;
+        let mut block = crate::Block::new();
+        loop {
+            let word = match self.lexer.peek()? {
+                Token::Punct('}') => break,
+                Token::Word(word) => word,
+                _ => {
+                    let (_, offset) = self.lexer.next()?;
+                    return Err(Error::new("expected a statement", offset));
+                }
+            };
+            let statement = match word {
+                "emit" => {
+                    self.lexer.next()?;
+                    let start = self.parse_value()?;
+                    self.expect(Token::DotDot)?;
+                    let end = self.parse_value()?;
+                    let start_index = self.expression(&start)?.index();
+                    let end_index = match end.kind {
+                        ValueKind::Word(word) => HandleKind::Expression.index(word),
+                        _ => None,
+                    };
+                    let end_index = end_index.ok_or_else(|| end.error("an `e` handle"))?;
+                    if end_index < start_index {
+                        return Err(end.error("the end of the range after its start"));
+                    }
+                    if end_index > start_index {
+                        self.function_refs.push(Reference {
+                            kind: HandleKind::Expression,
+                            index: end_index - 1,
+                            offset: end.offset,
+                        });
+                    } else {
+                        // the start of an empty range doesn't have to exist
+                        self.function_refs.pop();
+                    }
+                    S::Emit(Range::from_index_range(
+                        start_index as u32..end_index as u32,
+                    ))
+                }
+                "block" => {
+                    self.lexer.next()?;
+                    S::Block(self.parse_block()?)
+                }
+                "if" => {
+                    self.lexer.next()?;
+                    let value = self.parse_value()?;
+                    let condition = self.expression(&value)?;
+                    let accept = self.parse_block()?;
+                    let reject = if self.lexer.peek()? == Token::Word("else") {
+                        self.lexer.next()?;
+                        self.parse_block()?
+                    } else {
+                        crate::Block::new()
+                    };
+                    S::If {
+                        condition,
+                        accept,
+                        reject,
+                    }
+                }
+                "switch" => {
+                    self.lexer.next()?;
+                    let value = self.parse_value()?;
+                    let selector = self.expression(&value)?;
+                    self.expect(Token::Punct('{'))?;
+                    let mut cases = Vec::new();
+                    while self.lexer.peek()? == Token::Word("case") {
+                        self.lexer.next()?;
+                        let value = self.parse_value()?.number()?;
+                        let fall_through = self.lexer.peek()? == Token::Word("fallthrough");
+                        if fall_through {
+                            self.lexer.next()?;
+                        }
+                        cases.push(crate::SwitchCase {
+                            value,
+                            body: self.parse_block()?,
+                            fall_through,
+                        });
+                    }
+                    self.expect(Token::Word("default"))?;
+                    let default = self.parse_block()?;
+                    self.expect(Token::Punct('}'))?;
+                    S::Switch {
+                        selector,
+                        cases,
+                        default,
+                    }
+                }
+                "loop" => {
+                    self.lexer.next()?;
+                    let body = self.parse_block()?;
+                    let continuing = if self.lexer.peek()? == Token::Word("continuing") {
+                        self.lexer.next()?;
+                        self.parse_block()?
+                    } else {
+                        crate::Block::new()
+                    };
+                    S::Loop { body, continuing }
+                }
+                "break" => {
+                    self.lexer.next()?;
+                    S::Break
+                }
+                "continue" => {
+                    self.lexer.next()?;
+                    S::Continue
+                }
+                "kill" => {
+                    self.lexer.next()?;
+                    S::Kill
+                }
+                "return" => {
+                    self.lexer.next()?;
+                    let value = match self.lexer.peek()? {
+                        Token::Word(word) if HandleKind::Expression.index(word).is_some() => {
+                            let value = self.parse_value()?;
+                            Some(self.expression(&value)?)
+                        }
+                        _ => None,
+                    };
+                    S::Return { value }
+                }
+                _ => {
+                    let value = self.parse_value()?;
+                    self.statement_value(&value)?
+                }
+            };
+            block.push(statement);
+        }
+        Ok(block)
+    }
+
+    fn statement_value(&mut self, value: &Value) -> Result<crate::Statement, Error> {
+        use crate::Statement as S;
+        let call = value.call()?;
+        Ok(match call.name {
+            "Barrier" => {
+                call.check(1, &[])?;
+                let mut barrier = crate::Barrier::empty();
+                for flag in call.arg(0).list()? {
+                    barrier |= match flag.word()? {
+                        "STORAGE" => crate::Barrier::STORAGE,
+                        "WORK_GROUP" => crate::Barrier::WORK_GROUP,
+                        _ => return Err(flag.error("a barrier flag")),
+                    };
+                }
+                S::Barrier(barrier)
+            }
+            "Store" => {
+                call.check(2, &[])?;
+                S::Store {
+                    pointer: self.expression(call.arg(0))?,
+                    value: self.expression(call.arg(1))?,
+                }
+            }
+            "ImageStore" => {
+                call.check(3, &["array_index"])?;
+                S::ImageStore {
+                    image: self.expression(call.arg(0))?,
+                    coordinate: self.expression(call.arg(1))?,
+                    array_index: self.option_expression(call.field("array_index"))?,
+                    value: self.expression(call.arg(2))?,
+                }
+            }
+            "Call" => {
+                call.check(2, &["result"])?;
+                S::Call {
+                    function: self.handle(call.arg(0), HandleKind::Function)?,
+                    arguments: self.handles(call.arg(1), HandleKind::Expression)?,
+                    result: self.option_expression(call.field("result"))?,
+                }
+            }
+            "Atomic" => {
+                call.check(4, &[])?;
+                let fun_call = call.arg(1).call()?;
+                if fun_call.name != "Exchange" {
+                    fun_call.check(0, &[])?;
+                }
+                let fun = match fun_call.name {
+                    "Add" => crate::AtomicFunction::Add,
+                    "Subtract" => crate::AtomicFunction::Subtract,
+                    "And" => crate::AtomicFunction::And,
+                    "ExclusiveOr" => crate::AtomicFunction::ExclusiveOr,
+                    "InclusiveOr" => crate::AtomicFunction::InclusiveOr,
+                    "Min" => crate::AtomicFunction::Min,
+                    "Max" => crate::AtomicFunction::Max,
+                    "Exchange" => {
+                        let compare = match fun_call.arguments.len() {
+                            0 => None,
+                            _ => {
+                                fun_call.check(1, &[])?;
+                                Some(self.expression(fun_call.arg(0))?)
+                            }
+                        };
+                        crate::AtomicFunction::Exchange { compare }
+                    }
+                    _ => return Err(fun_call.unknown("atomic function")),
+                };
+                S::Atomic {
+                    pointer: self.expression(call.arg(0))?,
+                    fun,
+                    value: self.expression(call.arg(2))?,
+                    result: self.expression(call.arg(3))?,
+                }
+            }
+            "SubgroupBallot" => {
+                call.check(1, &["predicate"])?;
+                S::SubgroupBallot {
+                    result: self.expression(call.arg(0))?,
+                    predicate: self.option_expression(call.field("predicate"))?,
+                }
+            }
+            "SubgroupCollectiveOperation" => {
+                call.check(4, &[])?;
+                S::SubgroupCollectiveOperation {
+                    op: subgroup_operation(call.arg(0))?,
+                    collective_op: collective_operation(call.arg(1))?,
+                    argument: self.expression(call.arg(2))?,
+                    result: self.expression(call.arg(3))?,
+                }
+            }
+            "SubgroupGather" => {
+                call.check(3, &[])?;
+                let mode_call = call.arg(0).call()?;
+                let mode = if mode_call.name == "BroadcastFirst" {
+                    mode_call.check(0, &[])?;
+                    crate::GatherMode::BroadcastFirst
+                } else {
+                    mode_call.check(1, &[])?;
+                    let index = self.expression(mode_call.arg(0))?;
+                    match mode_call.name {
+                        "Broadcast" => crate::GatherMode::Broadcast(index),
+                        "Shuffle" => crate::GatherMode::Shuffle(index),
+                        "ShuffleDown" => crate::GatherMode::ShuffleDown(index),
+                        "ShuffleUp" => crate::GatherMode::ShuffleUp(index),
+                        "ShuffleXor" => crate::GatherMode::ShuffleXor(index),
+                        _ => return Err(mode_call.unknown("gather mode")),
+                    }
+                };
+                S::SubgroupGather {
+                    mode,
+                    argument: self.expression(call.arg(1))?,
+                    result: self.expression(call.arg(2))?,
+                }
+            }
+            "SetMeshOutputs" => {
+                call.check(2, &[])?;
+                S::MeshFunction(crate::MeshFunction::SetMeshOutputs {
+                    vertex_count: self.expression(call.arg(0))?,
+                    primitive_count: self.expression(call.arg(1))?,
+                })
+            }
+            "SetVertex" | "SetIndices" | "SetPrimitive" => {
+                call.check(2, &[])?;
+                let index = self.expression(call.arg(0))?;
+                let value = self.expression(call.arg(1))?;
+                S::MeshFunction(match call.name {
+                    "SetVertex" => crate::MeshFunction::SetVertex { index, value },
+                    "SetIndices" => crate::MeshFunction::SetIndices { index, value },
+                    _ => crate::MeshFunction::SetPrimitive { index, value },
+                })
+            }
+            "RayQuery" => {
+                call.check(2, &[])?;
+                let fun_call = call.arg(1).call()?;
+                let fun = match fun_call.name {
+                    "Initialize" => {
+                        fun_call.check(2, &[])?;
+                        crate::RayQueryFunction::Initialize {
+                            acceleration_structure: self.expression(fun_call.arg(0))?,
+                            descriptor: self.expression(fun_call.arg(1))?,
+                        }
+                    }
+                    "Proceed" => {
+                        fun_call.check(1, &[])?;
+                        crate::RayQueryFunction::Proceed {
+                            result: self.expression(fun_call.arg(0))?,
+                        }
+                    }
+                    "Terminate" => {
+                        fun_call.check(0, &[])?;
+                        crate::RayQueryFunction::Terminate
+                    }
+                    _ => return Err(fun_call.unknown("ray query function")),
+                };
+                S::RayQuery {
+                    query: self.expression(call.arg(0))?,
+                    fun,
+                }
+            }
+            _ => return Err(call.unknown("statement")),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "ir-out"))]
+mod tests {
+    use super::{parse_str, ParseError};
+
+    #[test]
+    fn parse_hand_written() {
+        let source = r#"
+            // double the argument
+            t0 = Scalar(Float, 4)
+            c0 = Float(4, 2.0)
+            f0 "double" = Function(arguments: [Argument(t0, name: "x")], result: FunctionResult(t0)) {
+                e0 = FunctionArgument(0)
+                e1 = Constant(c0)
+                e2 = Binary(Multiply, e0, e1)
+                emit e2..e3
+                return e2
+            }
+        "#;
+        let module = parse_str(source).unwrap();
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .unwrap();
+        let string = crate::back::ir::write_string(&module).unwrap();
+        let reparsed = parse_str(&string).unwrap();
+        assert_eq!(crate::back::ir::write_string(&reparsed).unwrap(), string);
+    }
+
+    #[cfg(feature = "wgsl-in")]
+    #[test]
+    fn round_trip_derived_constants() {
+        let module = crate::front::wgsl::parse_str(
+            "
+            override width: u32 = 4u;
+            let area: u32 = width * 2u;
+            let extent: vec2<u32> = vec2<u32>(width, area);
+            ",
+        )
+        .unwrap();
+        assert_eq!(module.derived_constants.len(), 2);
+        let string = crate::back::ir::write_string(&module).unwrap();
+        let reparsed = parse_str(&string).unwrap();
+        assert_eq!(
+            format!("{:?}", reparsed.derived_constants),
+            format!("{:?}", module.derived_constants)
+        );
+        assert_eq!(crate::back::ir::write_string(&reparsed).unwrap(), string);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |source: &str| parse_str(source).unwrap_err();
+        assert_eq!(
+            error("t0 = Scalar(Float, 4)\nt2 = Vector(2, Float, 4)"),
+            ParseError {
+                message: "expected `t1`".to_string(),
+                line: 2,
+                column: 1,
+            }
+        );
+        assert_eq!(
+            error("t0 = Array(t1, Dynamic, 4)"),
+            ParseError {
+                message: "`t1` is not defined".to_string(),
+                line: 1,
+                column: 12,
+            }
+        );
+        assert_eq!(
+            error("t0 = Scalar(Float)").message,
+            "`Scalar` takes 2 arguments, but 1 were given"
+        );
+        assert_eq!(
+            error("f0 = Function() {\n    e0 = Load(e0)\n    emit e0..e2\n}").line,
+            3
+        );
+    }
+}
@@ -2,6 +2,8 @@
 
 #[cfg(feature = "glsl-in")]
 pub mod glsl;
+#[cfg(feature = "ir-in")]
+pub mod ir;
 #[cfg(feature = "spv-in")]
 pub mod spv;
 #[cfg(feature = "wgsl-in")]
@@ -15,20 +17,20 @@ use crate::{
 /// Helper class to emit expressions
 #[allow(dead_code)]
 #[derive(Default, Debug)]
-struct Emitter {
+pub(crate) struct Emitter {
     start_len: Option<usize>,
 }
 
 #[allow(dead_code)]
 impl Emitter {
-    fn start(&mut self, arena: &Arena<crate::Expression>) {
+    pub(crate) fn start(&mut self, arena: &Arena<crate::Expression>) {
         if self.start_len.is_some() {
             unreachable!("Emitting has already started!");
         }
         self.start_len = Some(arena.len());
     }
     #[must_use]
-    fn finish(&mut self, arena: &Arena<crate::Expression>) -> Option<crate::Statement> {
+    pub(crate) fn finish(&mut self, arena: &Arena<crate::Expression>) -> Option<crate::Statement> {
         let start_len = self.start_len.take().unwrap();
         if start_len != arena.len() {
             Some(crate::Statement::Emit(arena.range_from(start_len)))
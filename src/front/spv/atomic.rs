@@ -0,0 +1,100 @@
+use crate::arena::{Arena, Handle};
+
+use super::Error;
+
+/// A global variable accessed by atomic instructions, together with the
+/// chain of indices leading to the atomically accessed scalar.
+///
+/// SPIR-V performs atomic operations on pointers to plain integers,
+/// while the IR requires those integers to have [`Atomic`] type.
+/// The path is recorded while parsing, and the types are upgraded
+/// once all the functions are known.
+///
+/// [`Atomic`]: crate::TypeInner::Atomic
+#[derive(Debug)]
+pub(super) struct AtomicAccess {
+    var: Handle<crate::GlobalVariable>,
+    /// Struct member indices, or zeroes for array elements.
+    path: Vec<u32>,
+}
+
+impl AtomicAccess {
+    pub(super) fn trace(
+        expressions: &Arena<crate::Expression>,
+        pointer: Handle<crate::Expression>,
+    ) -> Result<Self, Error> {
+        let mut path = Vec::new();
+        let mut handle = pointer;
+        loop {
+            match expressions[handle] {
+                crate::Expression::AccessIndex { base, index } => {
+                    path.push(index);
+                    handle = base;
+                }
+                crate::Expression::Access { base, index: _ } => {
+                    path.push(0);
+                    handle = base;
+                }
+                crate::Expression::GlobalVariable(var) => {
+                    path.reverse();
+                    return Ok(AtomicAccess { var, path });
+                }
+                ref other => return Err(Error::InvalidAtomicPointer(other.clone())),
+            }
+        }
+    }
+
+    /// Replace the type of the global variable with one that has
+    /// an [`Atomic`](crate::TypeInner::Atomic) at the end of the path.
+    pub(super) fn upgrade(
+        &self,
+        globals: &mut Arena<crate::GlobalVariable>,
+        types: &mut Arena<crate::Type>,
+    ) -> Result<(), Error> {
+        let var = globals.get_mut(self.var);
+        var.ty = upgrade_type(var.ty, &self.path, types)?;
+        Ok(())
+    }
+}
+
+fn upgrade_type(
+    ty: Handle<crate::Type>,
+    path: &[u32],
+    types: &mut Arena<crate::Type>,
+) -> Result<Handle<crate::Type>, Error> {
+    let name = types[ty].name.clone();
+    let inner = match (&types[ty].inner, path.split_first()) {
+        (&crate::TypeInner::Atomic { .. }, None) => return Ok(ty),
+        (&crate::TypeInner::Scalar { kind, width }, None) => {
+            crate::TypeInner::Atomic { kind, width }
+        }
+        (
+            &crate::TypeInner::Struct {
+                top_level,
+                ref members,
+                span,
+            },
+            Some((&index, rest)),
+        ) => {
+            let mut members = members.clone();
+            let member = members
+                .get_mut(index as usize)
+                .ok_or(Error::InvalidAtomicType(ty))?;
+            member.ty = upgrade_type(member.ty, rest, types)?;
+            crate::TypeInner::Struct {
+                top_level,
+                members,
+                span,
+            }
+        }
+        (&crate::TypeInner::Array { base, size, stride }, Some((_, rest))) => {
+            crate::TypeInner::Array {
+                base: upgrade_type(base, rest, types)?,
+                size,
+                stride,
+            }
+        }
+        _ => return Err(Error::InvalidAtomicType(ty)),
+    };
+    Ok(types.fetch_or_append(crate::Type { name, inner }))
+}
@@ -12,7 +12,13 @@ pub(super) fn map_binary_operator(word: spirv::Op) -> Result<crate::BinaryOperat
         Op::ISub | Op::FSub => Ok(BinaryOperator::Subtract),
         Op::IMul | Op::FMul => Ok(BinaryOperator::Multiply),
         Op::UDiv | Op::SDiv | Op::FDiv => Ok(BinaryOperator::Divide),
-        Op::UMod | Op::SMod | Op::FMod => Ok(BinaryOperator::Modulo),
+        Op::UMod | Op::SMod | Op::FMod | Op::SRem | Op::FRem => Ok(BinaryOperator::Modulo),
+        // Bit Instructions
+        Op::BitwiseOr => Ok(BinaryOperator::InclusiveOr),
+        Op::BitwiseXor => Ok(BinaryOperator::ExclusiveOr),
+        Op::BitwiseAnd => Ok(BinaryOperator::And),
+        Op::ShiftLeftLogical => Ok(BinaryOperator::ShiftLeft),
+        Op::ShiftRightLogical | Op::ShiftRightArithmetic => Ok(BinaryOperator::ShiftRight),
         // Relational and Logical Instructions
         Op::IEqual | Op::FOrdEqual | Op::FUnordEqual | Op::LogicalEqual => {
             Ok(BinaryOperator::Equal)
@@ -34,6 +40,8 @@ pub(super) fn map_binary_operator(word: spirv::Op) -> Result<crate::BinaryOperat
         | Op::SGreaterThanEqual
         | Op::FOrdGreaterThanEqual
         | Op::FUnordGreaterThanEqual => Ok(BinaryOperator::GreaterEqual),
+        Op::LogicalOr => Ok(BinaryOperator::LogicalOr),
+        Op::LogicalAnd => Ok(BinaryOperator::LogicalAnd),
         _ => Err(Error::UnknownBinaryOperator(word)),
     }
 }
@@ -117,10 +125,24 @@ pub(super) fn map_width(word: spirv::Word) -> Result<crate::Bytes, Error> {
         .map_err(|_| Error::InvalidTypeWidth(word))
 }
 
+/// Converts the bits of an IEEE 754 half-precision float to a float.
+pub(super) fn half_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f32::from(bits & 0x3ff);
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
 pub(super) fn map_builtin(word: spirv::Word) -> Result<crate::BuiltIn, Error> {
     use spirv::BuiltIn as Bi;
     Ok(match spirv::BuiltIn::from_u32(word) {
         Some(Bi::Position) | Some(Bi::FragCoord) => crate::BuiltIn::Position,
+        Some(Bi::ViewIndex) => crate::BuiltIn::ViewIndex,
         // vertex
         Some(Bi::BaseInstance) => crate::BuiltIn::BaseInstance,
         Some(Bi::BaseVertex) => crate::BuiltIn::BaseVertex,
@@ -132,6 +154,7 @@ pub(super) fn map_builtin(word: spirv::Word) -> Result<crate::BuiltIn, Error> {
         // fragment
         Some(Bi::FragDepth) => crate::BuiltIn::FragDepth,
         Some(Bi::FrontFacing) => crate::BuiltIn::FrontFacing,
+        Some(Bi::PrimitiveId) => crate::BuiltIn::PrimitiveIndex,
         Some(Bi::SampleId) => crate::BuiltIn::SampleIndex,
         Some(Bi::SampleMask) => crate::BuiltIn::SampleMask,
         // compute
@@ -140,10 +163,23 @@ pub(super) fn map_builtin(word: spirv::Word) -> Result<crate::BuiltIn, Error> {
         Some(Bi::LocalInvocationIndex) => crate::BuiltIn::LocalInvocationIndex,
         Some(Bi::WorkgroupId) => crate::BuiltIn::WorkGroupId,
         Some(Bi::WorkgroupSize) => crate::BuiltIn::WorkGroupSize,
+        // subgroup
+        Some(Bi::SubgroupSize) => crate::BuiltIn::SubgroupSize,
+        Some(Bi::SubgroupLocalInvocationId) => crate::BuiltIn::SubgroupInvocationId,
         _ => return Err(Error::UnsupportedBuiltIn(word)),
     })
 }
 
+pub(super) fn map_group_operation(word: spirv::Word) -> Result<crate::CollectiveOperation, Error> {
+    use spirv::GroupOperation as Go;
+    Ok(match Go::from_u32(word) {
+        Some(Go::Reduce) => crate::CollectiveOperation::Reduce,
+        Some(Go::InclusiveScan) => crate::CollectiveOperation::InclusiveScan,
+        Some(Go::ExclusiveScan) => crate::CollectiveOperation::ExclusiveScan,
+        _ => return Err(Error::UnsupportedGroupOperation(word)),
+    })
+}
+
 pub(super) fn map_storage_class(word: spirv::Word) -> Result<super::ExtendedClass, Error> {
     use super::ExtendedClass as Ec;
     use spirv::StorageClass as Sc;
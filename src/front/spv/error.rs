@@ -37,6 +37,8 @@ pub enum Error {
     UnsupportedImageFormat(spirv::Word),
     #[error("unsupported builtin %{0}")]
     UnsupportedBuiltIn(spirv::Word),
+    #[error("unsupported group operation %{0}")]
+    UnsupportedGroupOperation(spirv::Word),
     #[error("unsupported control flow %{0}")]
     UnsupportedControlFlow(spirv::Word),
     #[error("unsupported binary operator %{0}")]
@@ -107,5 +109,15 @@ pub enum Error {
     InvalidBarrierScope(spirv::Word),
     #[error("invalid barrier memory semantics %{0}")]
     InvalidBarrierMemorySemantics(spirv::Word),
+    #[error("invalid gather component %{0}")]
+    InvalidGatherComponent(spirv::Word),
+    #[error("invalid atomic pointer {0:?}")]
+    InvalidAtomicPointer(crate::Expression),
+    #[error("invalid atomic type {0:?}")]
+    InvalidAtomicType(Handle<crate::Type>),
+    #[error("unsupported specialization constant operation {0:?}")]
+    UnsupportedSpecConstantOp(spirv::Op),
+    #[error("invalid constant expression: {0}")]
+    InvalidConstantExpression(#[from] crate::proc::ConstantEvaluatorError),
     // incomplete implementation errors
 }
@@ -378,6 +378,8 @@ impl<I: Iterator<Item = u32>> super::Parser<I> {
                 stage: ep.stage,
                 early_depth_test: ep.early_depth_test,
                 workgroup_size: ep.workgroup_size,
+                mesh_info: None,
+                task_payload: None,
                 function,
             });
         }
@@ -38,6 +38,8 @@ pub struct SamplingOptions {
     pub project: bool,
     /// Depth comparison sampling with a reference value.
     pub compare: bool,
+    /// Gathering a component of the four texels, instead of filtering them.
+    pub gather: bool,
 }
 
 enum ExtraCoordinate {
@@ -375,6 +377,7 @@ impl<I: Iterator<Item = u32>> super::Parser<I> {
         options: SamplingOptions,
         type_arena: &Arena<crate::Type>,
         global_arena: &Arena<crate::GlobalVariable>,
+        const_arena: &Arena<crate::Constant>,
         expressions: &mut Arena<crate::Expression>,
     ) -> Result<(), Error> {
         let result_type_id = self.next()?;
@@ -386,6 +389,52 @@ impl<I: Iterator<Item = u32>> super::Parser<I> {
         } else {
             None
         };
+        let gather = if options.gather {
+            // `OpImageDrefGather` always gathers the depth
+            if options.compare {
+                Some(crate::SwizzleComponent::X)
+            } else {
+                let component_id = self.next()?;
+                let component_const = self.lookup_constant.lookup(component_id)?;
+                Some(match const_arena[component_const.handle].inner {
+                    crate::ConstantInner::Scalar {
+                        value: crate::ScalarValue::Uint(0),
+                        width: _,
+                    }
+                    | crate::ConstantInner::Scalar {
+                        value: crate::ScalarValue::Sint(0),
+                        width: _,
+                    } => crate::SwizzleComponent::X,
+                    crate::ConstantInner::Scalar {
+                        value: crate::ScalarValue::Uint(1),
+                        width: _,
+                    }
+                    | crate::ConstantInner::Scalar {
+                        value: crate::ScalarValue::Sint(1),
+                        width: _,
+                    } => crate::SwizzleComponent::Y,
+                    crate::ConstantInner::Scalar {
+                        value: crate::ScalarValue::Uint(2),
+                        width: _,
+                    }
+                    | crate::ConstantInner::Scalar {
+                        value: crate::ScalarValue::Sint(2),
+                        width: _,
+                    } => crate::SwizzleComponent::Z,
+                    crate::ConstantInner::Scalar {
+                        value: crate::ScalarValue::Uint(3),
+                        width: _,
+                    }
+                    | crate::ConstantInner::Scalar {
+                        value: crate::ScalarValue::Sint(3),
+                        width: _,
+                    } => crate::SwizzleComponent::W,
+                    _ => return Err(Error::InvalidGatherComponent(component_id)),
+                })
+            }
+        } else {
+            None
+        };
 
         let mut image_ops = if words_left != 0 {
             words_left -= 1;
@@ -394,7 +443,11 @@ impl<I: Iterator<Item = u32>> super::Parser<I> {
             0
         };
 
-        let mut level = crate::SampleLevel::Auto;
+        let mut level = if gather.is_some() {
+            crate::SampleLevel::Zero
+        } else {
+            crate::SampleLevel::Auto
+        };
         let mut offset = None;
         while image_ops != 0 {
             let bit = 1 << image_ops.trailing_zeros();
@@ -476,6 +529,7 @@ impl<I: Iterator<Item = u32>> super::Parser<I> {
         let expr = crate::Expression::ImageSample {
             image: si_lexp.image,
             sampler: si_lexp.sampler,
+            gather,
             coordinate,
             array_index,
             offset,
@@ -26,6 +26,7 @@ This value then gets used instead of `OpLoad` result later on.
 !*/
 #![allow(dead_code)]
 
+mod atomic;
 mod convert;
 mod error;
 mod flow;
@@ -69,6 +70,16 @@ pub const SUPPORTED_CAPABILITIES: &[spirv::Capability] = &[
     spirv::Capability::Int8,
     spirv::Capability::Int16,
     spirv::Capability::Int64,
+    spirv::Capability::Float16,
+    spirv::Capability::StorageBuffer16BitAccess,
+    spirv::Capability::UniformAndStorageBuffer16BitAccess,
+    spirv::Capability::StoragePushConstant16,
+    spirv::Capability::GroupNonUniform,
+    spirv::Capability::GroupNonUniformVote,
+    spirv::Capability::GroupNonUniformArithmetic,
+    spirv::Capability::GroupNonUniformBallot,
+    spirv::Capability::GroupNonUniformShuffle,
+    spirv::Capability::GroupNonUniformShuffleRelative,
     // tricky ones
     spirv::Capability::UniformBufferArrayDynamicIndexing,
     spirv::Capability::StorageBufferArrayDynamicIndexing,
@@ -76,6 +87,7 @@ pub const SUPPORTED_CAPABILITIES: &[spirv::Capability] = &[
 pub const SUPPORTED_EXTENSIONS: &[&str] = &[
     "SPV_KHR_storage_buffer_storage_class",
     "SPV_KHR_vulkan_memory_model",
+    "SPV_KHR_16bit_storage",
 ];
 pub const SUPPORTED_EXT_SETS: &[&str] = &["GLSL.std.450"];
 
@@ -219,6 +231,8 @@ struct Decoration {
     name: Option<String>,
     built_in: Option<spirv::Word>,
     location: Option<spirv::Word>,
+    /// Blend source index of a fragment output.
+    index: Option<spirv::Word>,
     desc_set: Option<spirv::Word>,
     desc_index: Option<spirv::Word>,
     specialization: Option<spirv::Word>,
@@ -261,11 +275,13 @@ impl Decoration {
             Decoration {
                 built_in: None,
                 location: Some(location),
+                index,
                 interpolation,
                 sampling,
                 ..
             } => Ok(crate::Binding::Location {
                 location,
+                second_blend_source: index == Some(1),
                 interpolation,
                 sampling,
             }),
@@ -381,6 +397,7 @@ pub struct Parser<I> {
     future_member_decor: FastHashMap<(spirv::Word, MemberIndex), Decoration>,
     lookup_member: FastHashMap<(Handle<crate::Type>, MemberIndex), LookupMember>,
     handle_sampling: FastHashMap<Handle<crate::GlobalVariable>, image::SamplingFlags>,
+    atomic_accesses: Vec<atomic::AtomicAccess>,
     lookup_type: FastHashMap<spirv::Word, LookupType>,
     lookup_void_type: Option<spirv::Word>,
     lookup_storage_buffer_types: FastHashMap<Handle<crate::Type>, crate::StorageAccess>,
@@ -394,6 +411,8 @@ pub struct Parser<I> {
     lookup_function_type: FastHashMap<spirv::Word, LookupFunctionType>,
     lookup_function: FastHashMap<spirv::Word, Handle<crate::Function>>,
     lookup_entry_point: FastHashMap<spirv::Word, EntryPoint>,
+    // Byte ranges of the lines of embedded sources, used to resolve `OpLine`.
+    lookup_source_lines: FastHashMap<spirv::Word, Vec<std::ops::Range<usize>>>,
     //Note: each `OpFunctionCall` gets a single entry here, indexed by the
     // dummy `Handle<crate::Function>` of the call site.
     deferred_function_calls: Vec<spirv::Word>,
@@ -418,6 +437,7 @@ impl<I: Iterator<Item = u32>> Parser<I> {
             future_decor: FastHashMap::default(),
             future_member_decor: FastHashMap::default(),
             handle_sampling: FastHashMap::default(),
+            atomic_accesses: Vec::new(),
             lookup_member: FastHashMap::default(),
             lookup_type: FastHashMap::default(),
             lookup_void_type: None,
@@ -430,6 +450,7 @@ impl<I: Iterator<Item = u32>> Parser<I> {
             lookup_function_type: FastHashMap::default(),
             lookup_function: FastHashMap::default(),
             lookup_entry_point: FastHashMap::default(),
+            lookup_source_lines: FastHashMap::default(),
             deferred_function_calls: Vec::default(),
             dummy_functions: Arena::new(),
             function_call_graph: GraphMap::new(),
@@ -491,6 +512,10 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                 inst.expect(base_words + 2)?;
                 dec.location = Some(self.next()?);
             }
+            spirv::Decoration::Index => {
+                inst.expect(base_words + 2)?;
+                dec.index = Some(self.next()?);
+            }
             spirv::Decoration::DescriptorSet => {
                 inst.expect(base_words + 2)?;
                 dec.desc_set = Some(self.next()?);
@@ -692,6 +717,29 @@ impl<I: Iterator<Item = u32>> Parser<I> {
         Ok(())
     }
 
+    /// Bit field offsets and counts can be signed in SPIR-V,
+    /// while the IR expects them to be `u32`.
+    fn get_bit_field_operand(
+        &self,
+        id: spirv::Word,
+        types: &Arena<crate::Type>,
+        expressions: &mut Arena<crate::Expression>,
+    ) -> Result<Handle<crate::Expression>, Error> {
+        let lexp = self.lookup_expression.lookup(id)?;
+        let lookup_ty = self.lookup_type.lookup(lexp.type_id)?;
+        Ok(match types[lookup_ty.handle].inner {
+            crate::TypeInner::Scalar {
+                kind: crate::ScalarKind::Uint,
+                width: 4,
+            } => lexp.handle,
+            _ => expressions.append(crate::Expression::As {
+                expr: lexp.handle,
+                kind: crate::ScalarKind::Uint,
+                convert: Some(4),
+            }),
+        })
+    }
+
     fn parse_expr_derivative(
         &mut self,
         expressions: &mut Arena<crate::Expression>,
@@ -789,17 +837,31 @@ impl<I: Iterator<Item = u32>> Parser<I> {
         let mut emitter = super::Emitter::default();
         emitter.start(expressions);
         let mut merge = None;
+        let mut span = crate::Span::UNDEFINED;
+        let mut span_start = expressions.len();
         let terminator = loop {
             use spirv::Op;
+            if span.is_defined() {
+                for handle in expressions.range_from(span_start) {
+                    expressions.init_span(handle, span);
+                }
+            }
+            span_start = expressions.len();
+
             let inst = self.next_inst()?;
             log::debug!("\t\t{:?} [{}]", inst.op, inst.wc);
 
             match inst.op {
                 Op::Line => {
                     inst.expect(4)?;
-                    let _file_id = self.next()?;
-                    let _row_id = self.next()?;
-                    let _col_id = self.next()?;
+                    let file_id = self.next()?;
+                    let row = self.next()?;
+                    let col = self.next()?;
+                    span = self.line_span(file_id, row, col);
+                }
+                Op::NoLine => {
+                    inst.expect(1)?;
+                    span = crate::Span::UNDEFINED;
                 }
                 Op::Undef => {
                     inst.expect(3)?;
@@ -964,6 +1026,7 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                                                         arg: loaded,
                                                         arg1: None,
                                                         arg2: None,
+                                                        arg3: None,
                                                     });
                                                 LookupLoadOverride::Loaded(transposed)
                                             }
@@ -1031,6 +1094,7 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                                                     arg: loaded,
                                                     arg1: None,
                                                     arg2: None,
+                                                    arg3: None,
                                                 })
                                             }
                                             // We are indexing inside a row-major matrix.
@@ -1381,6 +1445,7 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                         arg: matrix_lexp.handle,
                         arg1: None,
                         arg2: None,
+                        arg3: None,
                     };
                     self.lookup_expression.insert(
                         result_id,
@@ -1404,6 +1469,7 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                         arg: left_lexp.handle,
                         arg1: Some(right_lexp.handle),
                         arg2: None,
+                        arg3: None,
                     };
                     self.lookup_expression.insert(
                         result_id,
@@ -1427,6 +1493,7 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                         arg: left_lexp.handle,
                         arg1: Some(right_lexp.handle),
                         arg2: None,
+                        arg3: None,
                     };
                     self.lookup_expression.insert(
                         result_id,
@@ -1467,6 +1534,86 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                     inst.expect(5)?;
                     self.parse_expr_shift_op(expressions, crate::BinaryOperator::ShiftLeft)?;
                 }
+                Op::BitCount | Op::BitReverse => {
+                    inst.expect(4)?;
+
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let base_id = self.next()?;
+                    let base_lexp = self.lookup_expression.lookup(base_id)?;
+                    let expr = crate::Expression::Math {
+                        fun: match inst.op {
+                            Op::BitCount => crate::MathFunction::CountOneBits,
+                            _ => crate::MathFunction::ReverseBits,
+                        },
+                        arg: base_lexp.handle,
+                        arg1: None,
+                        arg2: None,
+                        arg3: None,
+                    };
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle: expressions.append(expr),
+                            type_id: result_type_id,
+                        },
+                    );
+                }
+                Op::BitFieldSExtract | Op::BitFieldUExtract => {
+                    inst.expect(6)?;
+
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let base_id = self.next()?;
+                    let offset_id = self.next()?;
+                    let count_id = self.next()?;
+                    let base_lexp = self.lookup_expression.lookup(base_id)?;
+                    let base_handle = base_lexp.handle;
+                    let offset = self.get_bit_field_operand(offset_id, type_arena, expressions)?;
+                    let count = self.get_bit_field_operand(count_id, type_arena, expressions)?;
+                    let expr = crate::Expression::Math {
+                        fun: crate::MathFunction::ExtractBits,
+                        arg: base_handle,
+                        arg1: Some(offset),
+                        arg2: Some(count),
+                        arg3: None,
+                    };
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle: expressions.append(expr),
+                            type_id: result_type_id,
+                        },
+                    );
+                }
+                Op::BitFieldInsert => {
+                    inst.expect(7)?;
+
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let base_id = self.next()?;
+                    let insert_id = self.next()?;
+                    let offset_id = self.next()?;
+                    let count_id = self.next()?;
+                    let base_handle = self.lookup_expression.lookup(base_id)?.handle;
+                    let insert_handle = self.lookup_expression.lookup(insert_id)?.handle;
+                    let offset = self.get_bit_field_operand(offset_id, type_arena, expressions)?;
+                    let count = self.get_bit_field_operand(count_id, type_arena, expressions)?;
+                    let expr = crate::Expression::Math {
+                        fun: crate::MathFunction::InsertBits,
+                        arg: base_handle,
+                        arg1: Some(insert_handle),
+                        arg2: Some(offset),
+                        arg3: Some(count),
+                    };
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle: expressions.append(expr),
+                            type_id: result_type_id,
+                        },
+                    );
+                }
                 // Sampling
                 Op::Image => {
                     inst.expect(4)?;
@@ -1493,32 +1640,80 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                     let options = image::SamplingOptions {
                         compare: false,
                         project: false,
+                        gather: false,
                     };
-                    self.parse_image_sample(extra, options, type_arena, global_arena, expressions)?;
+                    self.parse_image_sample(
+                        extra,
+                        options,
+                        type_arena,
+                        global_arena,
+                        const_arena,
+                        expressions,
+                    )?;
                 }
                 Op::ImageSampleProjImplicitLod | Op::ImageSampleProjExplicitLod => {
                     let extra = inst.expect_at_least(5)?;
                     let options = image::SamplingOptions {
                         compare: false,
                         project: true,
+                        gather: false,
                     };
-                    self.parse_image_sample(extra, options, type_arena, global_arena, expressions)?;
+                    self.parse_image_sample(
+                        extra,
+                        options,
+                        type_arena,
+                        global_arena,
+                        const_arena,
+                        expressions,
+                    )?;
                 }
                 Op::ImageSampleDrefImplicitLod | Op::ImageSampleDrefExplicitLod => {
                     let extra = inst.expect_at_least(6)?;
                     let options = image::SamplingOptions {
                         compare: true,
                         project: false,
+                        gather: false,
                     };
-                    self.parse_image_sample(extra, options, type_arena, global_arena, expressions)?;
+                    self.parse_image_sample(
+                        extra,
+                        options,
+                        type_arena,
+                        global_arena,
+                        const_arena,
+                        expressions,
+                    )?;
                 }
                 Op::ImageSampleProjDrefImplicitLod | Op::ImageSampleProjDrefExplicitLod => {
                     let extra = inst.expect_at_least(6)?;
                     let options = image::SamplingOptions {
                         compare: true,
                         project: true,
+                        gather: false,
+                    };
+                    self.parse_image_sample(
+                        extra,
+                        options,
+                        type_arena,
+                        global_arena,
+                        const_arena,
+                        expressions,
+                    )?;
+                }
+                Op::ImageGather | Op::ImageDrefGather => {
+                    let extra = inst.expect_at_least(6)?;
+                    let options = image::SamplingOptions {
+                        compare: inst.op == Op::ImageDrefGather,
+                        project: false,
+                        gather: true,
                     };
-                    self.parse_image_sample(extra, options, type_arena, global_arena, expressions)?;
+                    self.parse_image_sample(
+                        extra,
+                        options,
+                        type_arena,
+                        global_arena,
+                        const_arena,
+                        expressions,
+                    )?;
                 }
                 Op::ImageQuerySize => {
                     inst.expect(4)?;
@@ -1822,6 +2017,18 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                             Glo::FaceForward => Mf::FaceForward,
                             Glo::Reflect => Mf::Reflect,
                             Glo::Refract => Mf::Refract,
+                            Glo::FindILsb => Mf::FirstTrailingBit,
+                            Glo::FindSMsb | Glo::FindUMsb => Mf::FirstLeadingBit,
+                            Glo::PackSnorm4x8 => Mf::Pack4x8snorm,
+                            Glo::PackUnorm4x8 => Mf::Pack4x8unorm,
+                            Glo::PackSnorm2x16 => Mf::Pack2x16snorm,
+                            Glo::PackUnorm2x16 => Mf::Pack2x16unorm,
+                            Glo::PackHalf2x16 => Mf::Pack2x16float,
+                            Glo::UnpackSnorm4x8 => Mf::Unpack4x8snorm,
+                            Glo::UnpackUnorm4x8 => Mf::Unpack4x8unorm,
+                            Glo::UnpackSnorm2x16 => Mf::Unpack2x16snorm,
+                            Glo::UnpackUnorm2x16 => Mf::Unpack2x16unorm,
+                            Glo::UnpackHalf2x16 => Mf::Unpack2x16float,
                             _ => return Err(Error::UnsupportedExtInst(inst_id)),
                         };
 
@@ -1849,6 +2056,7 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                             arg,
                             arg1,
                             arg2,
+                            arg3: None,
                         };
                         self.lookup_expression.insert(
                             result_id,
@@ -2090,6 +2298,273 @@ impl<I: Iterator<Item = u32>> Parser<I> {
 
                     emitter.start(expressions);
                 }
+                Op::AtomicLoad => {
+                    inst.expect(6)?;
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let pointer_id = self.next()?;
+                    let _scope_id = self.next()?;
+                    let _semantics_id = self.next()?;
+
+                    let pointer = self.lookup_expression.lookup(pointer_id)?.handle;
+                    self.atomic_accesses
+                        .push(atomic::AtomicAccess::trace(expressions, pointer)?);
+                    let handle = expressions.append(crate::Expression::Load { pointer });
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle,
+                            type_id: result_type_id,
+                        },
+                    );
+                }
+                Op::AtomicStore => {
+                    inst.expect(5)?;
+                    block.extend(emitter.finish(expressions));
+
+                    let pointer_id = self.next()?;
+                    let _scope_id = self.next()?;
+                    let _semantics_id = self.next()?;
+                    let value_id = self.next()?;
+
+                    let pointer = self.lookup_expression.lookup(pointer_id)?.handle;
+                    self.atomic_accesses
+                        .push(atomic::AtomicAccess::trace(expressions, pointer)?);
+                    let value = self.lookup_expression.lookup(value_id)?.handle;
+                    block.push(crate::Statement::Store { pointer, value });
+                    emitter.start(expressions);
+                }
+                Op::AtomicExchange
+                | Op::AtomicCompareExchange
+                | Op::AtomicIAdd
+                | Op::AtomicISub
+                | Op::AtomicSMin
+                | Op::AtomicUMin
+                | Op::AtomicSMax
+                | Op::AtomicUMax
+                | Op::AtomicAnd
+                | Op::AtomicOr
+                | Op::AtomicXor => {
+                    let is_compare = inst.op == Op::AtomicCompareExchange;
+                    inst.expect(if is_compare { 9 } else { 7 })?;
+                    block.extend(emitter.finish(expressions));
+
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let pointer_id = self.next()?;
+                    let _scope_id = self.next()?;
+                    let _semantics_id = self.next()?;
+                    if is_compare {
+                        let _unequal_semantics_id = self.next()?;
+                    }
+                    let value_id = self.next()?;
+
+                    let pointer = self.lookup_expression.lookup(pointer_id)?.handle;
+                    self.atomic_accesses
+                        .push(atomic::AtomicAccess::trace(expressions, pointer)?);
+                    let value = self.lookup_expression.lookup(value_id)?.handle;
+                    let fun = match inst.op {
+                        Op::AtomicExchange => crate::AtomicFunction::Exchange { compare: None },
+                        Op::AtomicCompareExchange => {
+                            let comparator_id = self.next()?;
+                            crate::AtomicFunction::Exchange {
+                                compare: Some(self.lookup_expression.lookup(comparator_id)?.handle),
+                            }
+                        }
+                        Op::AtomicIAdd => crate::AtomicFunction::Add,
+                        Op::AtomicISub => crate::AtomicFunction::Subtract,
+                        Op::AtomicSMin | Op::AtomicUMin => crate::AtomicFunction::Min,
+                        Op::AtomicSMax | Op::AtomicUMax => crate::AtomicFunction::Max,
+                        Op::AtomicAnd => crate::AtomicFunction::And,
+                        Op::AtomicOr => crate::AtomicFunction::InclusiveOr,
+                        _ => crate::AtomicFunction::ExclusiveOr,
+                    };
+
+                    let result_type = self.lookup_type.lookup(result_type_id)?;
+                    let (kind, width) = match type_arena[result_type.handle].inner {
+                        crate::TypeInner::Scalar { kind, width } => (kind, width),
+                        _ => return Err(Error::InvalidAtomicType(result_type.handle)),
+                    };
+                    let result = expressions.append(crate::Expression::AtomicResult {
+                        kind,
+                        width,
+                        comparison: is_compare,
+                    });
+                    block.push(crate::Statement::Atomic {
+                        pointer,
+                        fun,
+                        value,
+                        result,
+                    });
+                    emitter.start(expressions);
+                    // SPIR-V only produces the original value of a compare-exchange
+                    let handle = if is_compare {
+                        expressions.append(crate::Expression::AccessIndex {
+                            base: result,
+                            index: 0,
+                        })
+                    } else {
+                        result
+                    };
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle,
+                            type_id: result_type_id,
+                        },
+                    );
+                }
+                Op::GroupNonUniformBallot => {
+                    inst.expect(5)?;
+                    block.extend(emitter.finish(expressions));
+
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let _exec_scope_id = self.next()?;
+                    let predicate_id = self.next()?;
+
+                    let predicate = self.lookup_expression.lookup(predicate_id)?.handle;
+                    let result = expressions.append(crate::Expression::SubgroupBallotResult);
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle: result,
+                            type_id: result_type_id,
+                        },
+                    );
+                    block.push(crate::Statement::SubgroupBallot {
+                        result,
+                        predicate: Some(predicate),
+                    });
+                    emitter.start(expressions);
+                }
+                Op::GroupNonUniformAll
+                | Op::GroupNonUniformAny
+                | Op::GroupNonUniformIAdd
+                | Op::GroupNonUniformFAdd
+                | Op::GroupNonUniformIMul
+                | Op::GroupNonUniformFMul
+                | Op::GroupNonUniformSMin
+                | Op::GroupNonUniformUMin
+                | Op::GroupNonUniformFMin
+                | Op::GroupNonUniformSMax
+                | Op::GroupNonUniformUMax
+                | Op::GroupNonUniformFMax
+                | Op::GroupNonUniformBitwiseAnd
+                | Op::GroupNonUniformBitwiseOr
+                | Op::GroupNonUniformBitwiseXor
+                | Op::GroupNonUniformLogicalAnd
+                | Op::GroupNonUniformLogicalOr
+                | Op::GroupNonUniformLogicalXor => {
+                    let is_vote = match inst.op {
+                        Op::GroupNonUniformAll | Op::GroupNonUniformAny => true,
+                        _ => false,
+                    };
+                    inst.expect(if is_vote { 5 } else { 6 })?;
+                    block.extend(emitter.finish(expressions));
+
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let _exec_scope_id = self.next()?;
+                    let collective_op = if is_vote {
+                        crate::CollectiveOperation::Reduce
+                    } else {
+                        map_group_operation(self.next()?)?
+                    };
+                    let argument_id = self.next()?;
+
+                    let argument = self.lookup_expression.lookup(argument_id)?.handle;
+                    let op = match inst.op {
+                        Op::GroupNonUniformAll => crate::SubgroupOperation::All,
+                        Op::GroupNonUniformAny => crate::SubgroupOperation::Any,
+                        Op::GroupNonUniformIAdd | Op::GroupNonUniformFAdd => {
+                            crate::SubgroupOperation::Add
+                        }
+                        Op::GroupNonUniformIMul | Op::GroupNonUniformFMul => {
+                            crate::SubgroupOperation::Mul
+                        }
+                        Op::GroupNonUniformSMin
+                        | Op::GroupNonUniformUMin
+                        | Op::GroupNonUniformFMin => crate::SubgroupOperation::Min,
+                        Op::GroupNonUniformSMax
+                        | Op::GroupNonUniformUMax
+                        | Op::GroupNonUniformFMax => crate::SubgroupOperation::Max,
+                        Op::GroupNonUniformBitwiseAnd | Op::GroupNonUniformLogicalAnd => {
+                            crate::SubgroupOperation::And
+                        }
+                        Op::GroupNonUniformBitwiseOr | Op::GroupNonUniformLogicalOr => {
+                            crate::SubgroupOperation::Or
+                        }
+                        _ => crate::SubgroupOperation::Xor,
+                    };
+
+                    let result_type = self.lookup_type.lookup(result_type_id)?;
+                    let result = expressions.append(crate::Expression::SubgroupOperationResult {
+                        ty: result_type.handle,
+                    });
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle: result,
+                            type_id: result_type_id,
+                        },
+                    );
+                    block.push(crate::Statement::SubgroupCollectiveOperation {
+                        op,
+                        collective_op,
+                        argument,
+                        result,
+                    });
+                    emitter.start(expressions);
+                }
+                Op::GroupNonUniformBroadcastFirst
+                | Op::GroupNonUniformBroadcast
+                | Op::GroupNonUniformShuffle
+                | Op::GroupNonUniformShuffleDown
+                | Op::GroupNonUniformShuffleUp
+                | Op::GroupNonUniformShuffleXor => {
+                    let has_index = inst.op != Op::GroupNonUniformBroadcastFirst;
+                    inst.expect(if has_index { 6 } else { 5 })?;
+                    block.extend(emitter.finish(expressions));
+
+                    let result_type_id = self.next()?;
+                    let result_id = self.next()?;
+                    let _exec_scope_id = self.next()?;
+                    let argument_id = self.next()?;
+
+                    let argument = self.lookup_expression.lookup(argument_id)?.handle;
+                    let mode = if has_index {
+                        let index_id = self.next()?;
+                        let index = self.lookup_expression.lookup(index_id)?.handle;
+                        match inst.op {
+                            Op::GroupNonUniformBroadcast => crate::GatherMode::Broadcast(index),
+                            Op::GroupNonUniformShuffle => crate::GatherMode::Shuffle(index),
+                            Op::GroupNonUniformShuffleDown => crate::GatherMode::ShuffleDown(index),
+                            Op::GroupNonUniformShuffleUp => crate::GatherMode::ShuffleUp(index),
+                            _ => crate::GatherMode::ShuffleXor(index),
+                        }
+                    } else {
+                        crate::GatherMode::BroadcastFirst
+                    };
+
+                    let result_type = self.lookup_type.lookup(result_type_id)?;
+                    let result = expressions.append(crate::Expression::SubgroupOperationResult {
+                        ty: result_type.handle,
+                    });
+                    self.lookup_expression.insert(
+                        result_id,
+                        LookupExpression {
+                            handle: result,
+                            type_id: result_type_id,
+                        },
+                    );
+                    block.push(crate::Statement::SubgroupGather {
+                        mode,
+                        argument,
+                        result,
+                    });
+                    emitter.start(expressions);
+                }
                 Op::ControlBarrier => {
                     inst.expect(4)?;
                     let exec_scope_id = self.next()?;
@@ -2251,7 +2726,13 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                 | S::Kill
                 | S::Barrier(_)
                 | S::Store { .. }
-                | S::ImageStore { .. } => {}
+                | S::ImageStore { .. }
+                | S::Atomic { .. }
+                | S::SubgroupBallot { .. }
+                | S::SubgroupCollectiveOperation { .. }
+                | S::SubgroupGather { .. }
+                | S::MeshFunction(_)
+                | S::RayQuery { .. } => {}
                 S::Call {
                     ref mut function, ..
                 } => {
@@ -2347,10 +2828,17 @@ impl<I: Iterator<Item = u32>> Parser<I> {
                 Op::TypeSampledImage => self.parse_type_sampled_image(inst),
                 Op::TypeSampler => self.parse_type_sampler(inst, &mut module),
                 Op::Constant | Op::SpecConstant => self.parse_constant(inst, &mut module),
-                Op::ConstantComposite => self.parse_composite_constant(inst, &mut module),
+                Op::ConstantComposite | Op::SpecConstantComposite => {
+                    self.parse_composite_constant(inst, &mut module)
+                }
+                Op::SpecConstantOp => self.parse_spec_constant_op(inst, &mut module),
                 Op::ConstantNull | Op::Undef => self.parse_null_constant(inst, &mut module),
-                Op::ConstantTrue => self.parse_bool_constant(inst, true, &mut module),
-                Op::ConstantFalse => self.parse_bool_constant(inst, false, &mut module),
+                Op::ConstantTrue | Op::SpecConstantTrue => {
+                    self.parse_bool_constant(inst, true, &mut module)
+                }
+                Op::ConstantFalse | Op::SpecConstantFalse => {
+                    self.parse_bool_constant(inst, false, &mut module)
+                }
                 Op::Variable => self.parse_global_variable(inst, &mut module),
                 Op::Function => {
                     self.switch(ModuleState::Function, inst.op)?;
@@ -2398,6 +2886,11 @@ impl<I: Iterator<Item = u32>> Parser<I> {
             }
         }
 
+        // Give atomic types to everything accessed atomically.
+        for access in self.atomic_accesses.drain(..) {
+            access.upgrade(&mut module.global_variables, &mut module.types)?;
+        }
+
         if !self.future_decor.is_empty() {
             log::warn!("Unused item decorations: {:?}", self.future_decor);
             self.future_decor.clear();
@@ -2552,12 +3045,44 @@ impl<I: Iterator<Item = u32>> Parser<I> {
 
     fn parse_source(&mut self, inst: Instruction) -> Result<(), Error> {
         self.switch(ModuleState::Source, inst.op)?;
-        for _ in 1..inst.wc {
-            let _ = self.next()?;
+        inst.expect_at_least(3)?;
+        let _language = self.next()?;
+        let _version = self.next()?;
+        if inst.wc > 3 {
+            let file_id = self.next()?;
+            if inst.wc > 4 {
+                let (source, left) = self.next_string(inst.wc - 4)?;
+                for _ in 0..left {
+                    let _ = self.next()?;
+                }
+                let mut lines = Vec::new();
+                let mut start = 0;
+                for line in source.split_terminator('\n') {
+                    lines.push(start..start + line.len());
+                    start += line.len() + 1;
+                }
+                self.lookup_source_lines.insert(file_id, lines);
+            }
         }
         Ok(())
     }
 
+    /// Returns the span of an `OpLine` location, from the given column to
+    /// the end of the line, if the source of the file is embedded.
+    fn line_span(&self, file_id: spirv::Word, row: u32, col: u32) -> crate::Span {
+        let line = self
+            .lookup_source_lines
+            .get(&file_id)
+            .and_then(|lines| lines.get((row as usize).checked_sub(1)?));
+        match line {
+            Some(line) => {
+                let start = (line.start + (col as usize).saturating_sub(1)).min(line.end);
+                crate::Span::from(start..line.end)
+            }
+            None => crate::Span::UNDEFINED,
+        }
+    }
+
     fn parse_source_extension(&mut self, inst: Instruction) -> Result<(), Error> {
         self.switch(ModuleState::Source, inst.op)?;
         inst.expect_at_least(2)?;
@@ -3190,6 +3715,7 @@ impl<I: Iterator<Item = u32>> Parser<I> {
             } => {
                 let low = self.next()?;
                 let extended = match width {
+                    2 => f64::from(half_to_f32(low as u16)),
                     4 => f64::from(f32::from_bits(low)),
                     8 => {
                         inst.expect(5)?;
@@ -3255,6 +3781,110 @@ impl<I: Iterator<Item = u32>> Parser<I> {
         Ok(())
     }
 
+    /// Fold a specialization constant operation, using the current values
+    /// of the specialization constants it depends on.
+    fn parse_spec_constant_op(
+        &mut self,
+        inst: Instruction,
+        module: &mut crate::Module,
+    ) -> Result<(), Error> {
+        use spirv::Op;
+
+        self.switch(ModuleState::Type, inst.op)?;
+        inst.expect_at_least(5)?;
+        let type_id = self.next()?;
+        let id = self.next()?;
+        let opcode = self.next()?;
+        let op = spirv::Op::from_u32(opcode).ok_or(Error::UnknownInstruction(opcode as u16))?;
+        let mut operands = Vec::with_capacity(inst.wc as usize - 4);
+        for _ in 0..operands.capacity() {
+            operands.push(self.next()?);
+        }
+        let ty = self.lookup_type.lookup(type_id)?.handle;
+
+        let mut expressions = Arena::new();
+        let mut constant = |index: usize| -> Result<_, Error> {
+            let id = *operands.get(index).ok_or(Error::InvalidOperand)?;
+            let handle = self.lookup_constant.lookup(id)?.handle;
+            Ok(expressions.append(crate::Expression::Constant(handle)))
+        };
+        let expr = match op {
+            Op::SNegate => crate::Expression::Unary {
+                op: crate::UnaryOperator::Negate,
+                expr: constant(0)?,
+            },
+            Op::Not | Op::LogicalNot => crate::Expression::Unary {
+                op: crate::UnaryOperator::Not,
+                expr: constant(0)?,
+            },
+            Op::Select => crate::Expression::Select {
+                condition: constant(0)?,
+                accept: constant(1)?,
+                reject: constant(2)?,
+            },
+            Op::SConvert | Op::UConvert | Op::FConvert => {
+                let (kind, width) = match module.types[ty].inner {
+                    crate::TypeInner::Scalar { kind, width }
+                    | crate::TypeInner::Vector { kind, width, .. } => (kind, width),
+                    _ => return Err(Error::InvalidAsType(ty)),
+                };
+                crate::Expression::As {
+                    expr: constant(0)?,
+                    kind,
+                    convert: Some(width),
+                }
+            }
+            Op::CompositeExtract => {
+                let mut base = constant(0)?;
+                let (&last, indices) = operands[1..].split_last().ok_or(Error::InvalidOperand)?;
+                for &index in indices {
+                    base = expressions.append(crate::Expression::AccessIndex { base, index });
+                }
+                crate::Expression::AccessIndex { base, index: last }
+            }
+            _ => {
+                let op =
+                    map_binary_operator(op).map_err(|_| Error::UnsupportedSpecConstantOp(op))?;
+                crate::Expression::Binary {
+                    op,
+                    left: constant(0)?,
+                    right: constant(1)?,
+                }
+            }
+        };
+        let root = expressions.append(expr);
+
+        let mut handle = crate::proc::ConstantEvaluator {
+            types: &mut module.types,
+            constants: &mut module.constants,
+            expressions: &expressions,
+        }
+        .eval_derived(root, &mut module.derived_constants)?;
+        if let Some(name) = self.future_decor.remove(&id).and_then(|dec| dec.name) {
+            // a value derived from specialization constants already has
+            // a constant of its own, while a folded one may be shared
+            if module
+                .derived_constants
+                .last()
+                .map(|derived| derived.constant)
+                == Some(handle)
+            {
+                module.constants.get_mut(handle).name = Some(name);
+            } else {
+                let inner = module.constants[handle].inner.clone();
+                handle = module.constants.append(crate::Constant {
+                    name: Some(name),
+                    specialization: None,
+                    inner,
+                });
+            }
+        }
+
+        self.lookup_constant
+            .insert(id, LookupConstant { handle, type_id });
+        Ok(())
+    }
+
     fn parse_null_constant(
         &mut self,
         inst: Instruction,
@@ -3294,12 +3924,13 @@ impl<I: Iterator<Item = u32>> Parser<I> {
         let type_id = self.next()?;
         let id = self.next()?;
 
+        let decor = self.future_decor.remove(&id).unwrap_or_default();
         self.lookup_constant.insert(
             id,
             LookupConstant {
                 handle: module.constants.append(crate::Constant {
-                    name: self.future_decor.remove(&id).and_then(|dec| dec.name),
-                    specialization: None, //TODO
+                    name: decor.name,
+                    specialization: decor.specialization,
                     inner: crate::ConstantInner::boolean(value),
                 }),
                 type_id,
@@ -3550,4 +4181,52 @@ mod test {
         ];
         let _ = super::parse_u8_slice(&bin, &Default::default()).unwrap();
     }
+
+    #[test]
+    fn spec_constant_op() {
+        let words: &[u32] = &[
+            // header, with a bound of 5
+            0x0723_0203,
+            0x0001_0000,
+            0,
+            5,
+            0,
+            // OpMemoryModel Logical GLSL450
+            0x0003_000e,
+            0,
+            1,
+            // %1 = OpTypeInt 32 0
+            0x0004_0015,
+            1,
+            32,
+            0,
+            // %2 = OpSpecConstant %1 4
+            0x0004_0032,
+            1,
+            2,
+            4,
+            // %3 = OpConstant %1 3
+            0x0004_002b,
+            1,
+            3,
+            3,
+            // %4 = OpSpecConstantOp %1 IMul %2 %3
+            0x0006_0034,
+            1,
+            4,
+            spirv::Op::IMul as u32,
+            2,
+            3,
+        ];
+        let bin = words
+            .iter()
+            .flat_map(|word| word.to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+        let module = super::parse_u8_slice(&bin, &Default::default()).unwrap();
+        assert!(module.constants.iter().any(|(_, c)| c.inner
+            == crate::ConstantInner::Scalar {
+                width: 4,
+                value: crate::ScalarValue::Uint(12),
+            }));
+    }
 }
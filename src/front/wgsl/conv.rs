@@ -7,6 +7,7 @@ pub fn map_storage_class(word: &str, span: Span) -> Result<crate::StorageClass,
         "uniform" => Ok(crate::StorageClass::Uniform),
         "storage" => Ok(crate::StorageClass::Storage),
         "push_constant" => Ok(crate::StorageClass::PushConstant),
+        "task_payload" => Ok(crate::StorageClass::TaskPayload),
         _ => Err(Error::UnknownStorageClass(span)),
     }
 }
@@ -14,12 +15,14 @@ pub fn map_storage_class(word: &str, span: Span) -> Result<crate::StorageClass,
 pub fn map_built_in(word: &str, span: Span) -> Result<crate::BuiltIn, Error<'_>> {
     Ok(match word {
         "position" => crate::BuiltIn::Position,
+        "view_index" => crate::BuiltIn::ViewIndex,
         // vertex
         "vertex_index" => crate::BuiltIn::VertexIndex,
         "instance_index" => crate::BuiltIn::InstanceIndex,
         // fragment
         "front_facing" => crate::BuiltIn::FrontFacing,
         "frag_depth" => crate::BuiltIn::FragDepth,
+        "primitive_index" => crate::BuiltIn::PrimitiveIndex,
         "sample_index" => crate::BuiltIn::SampleIndex,
         "sample_mask" => crate::BuiltIn::SampleMask,
         // compute
@@ -28,19 +31,71 @@ pub fn map_built_in(word: &str, span: Span) -> Result<crate::BuiltIn, Error<'_>>
         "local_invocation_index" => crate::BuiltIn::LocalInvocationIndex,
         "workgroup_id" => crate::BuiltIn::WorkGroupId,
         "workgroup_size" => crate::BuiltIn::WorkGroupSize,
+        // subgroup
+        "subgroup_size" => crate::BuiltIn::SubgroupSize,
+        "subgroup_invocation_id" => crate::BuiltIn::SubgroupInvocationId,
+        // mesh
+        "mesh_task_size" => crate::BuiltIn::MeshTaskSize,
+        "cull_primitive" => crate::BuiltIn::CullPrimitive,
         _ => return Err(Error::UnknownBuiltin(span)),
     })
 }
 
+pub fn map_subgroup_operation(
+    word: &str,
+) -> Option<(crate::SubgroupOperation, crate::CollectiveOperation)> {
+    use crate::{CollectiveOperation as Co, SubgroupOperation as So};
+    const INCLUSIVE: &str = "subgroupInclusive";
+    const EXCLUSIVE: &str = "subgroupExclusive";
+    const REDUCE: &str = "subgroup";
+
+    let (collective_op, op_name) = if word.starts_with(INCLUSIVE) {
+        (Co::InclusiveScan, &word[INCLUSIVE.len()..])
+    } else if word.starts_with(EXCLUSIVE) {
+        (Co::ExclusiveScan, &word[EXCLUSIVE.len()..])
+    } else if word.starts_with(REDUCE) {
+        (Co::Reduce, &word[REDUCE.len()..])
+    } else {
+        return None;
+    };
+    let op = match op_name {
+        "All" if collective_op == Co::Reduce => So::All,
+        "Any" if collective_op == Co::Reduce => So::Any,
+        "Add" => So::Add,
+        "Mul" => So::Mul,
+        "Min" => So::Min,
+        "Max" => So::Max,
+        "And" => So::And,
+        "Or" => So::Or,
+        "Xor" => So::Xor,
+        _ => return None,
+    };
+    Some((op, collective_op))
+}
+
 pub fn map_shader_stage(word: &str, span: Span) -> Result<crate::ShaderStage, Error<'_>> {
     match word {
         "vertex" => Ok(crate::ShaderStage::Vertex),
         "fragment" => Ok(crate::ShaderStage::Fragment),
         "compute" => Ok(crate::ShaderStage::Compute),
+        "task" => Ok(crate::ShaderStage::Task),
+        "mesh" => Ok(crate::ShaderStage::Mesh),
         _ => Err(Error::UnknownShaderStage(span)),
     }
 }
 
+pub fn map_mesh_output_topology(
+    word: &str,
+    span: Span,
+) -> Result<crate::MeshOutputTopology, Error<'_>> {
+    match word {
+        "points" => Ok(crate::MeshOutputTopology::Points),
+        "lines" => Ok(crate::MeshOutputTopology::Lines),
+        "triangles" => Ok(crate::MeshOutputTopology::Triangles),
+        _ => Err(Error::UnknownAttribute(span)),
+    }
+}
+
 pub fn map_interpolation(word: &str, span: Span) -> Result<crate::Interpolation, Error<'_>> {
     match word {
         "linear" => Ok(crate::Interpolation::Linear),
@@ -190,8 +245,26 @@ pub fn map_standard_fun(word: &str) -> Option<crate::MathFunction> {
         "transpose" => Mf::Transpose,
         "determinant" => Mf::Determinant,
         // bits
+        "countTrailingZeros" => Mf::CountTrailingZeros,
+        "countLeadingZeros" => Mf::CountLeadingZeros,
         "countOneBits" => Mf::CountOneBits,
         "reverseBits" => Mf::ReverseBits,
+        "extractBits" => Mf::ExtractBits,
+        "insertBits" => Mf::InsertBits,
+        "firstTrailingBit" => Mf::FirstTrailingBit,
+        "firstLeadingBit" => Mf::FirstLeadingBit,
+        // data packing
+        "pack4x8snorm" => Mf::Pack4x8snorm,
+        "pack4x8unorm" => Mf::Pack4x8unorm,
+        "pack2x16snorm" => Mf::Pack2x16snorm,
+        "pack2x16unorm" => Mf::Pack2x16unorm,
+        "pack2x16float" => Mf::Pack2x16float,
+        // data unpacking
+        "unpack4x8snorm" => Mf::Unpack4x8snorm,
+        "unpack4x8unorm" => Mf::Unpack4x8unorm,
+        "unpack2x16snorm" => Mf::Unpack2x16snorm,
+        "unpack2x16unorm" => Mf::Unpack2x16unorm,
+        "unpack2x16float" => Mf::Unpack2x16float,
         _ => return None,
     })
 }
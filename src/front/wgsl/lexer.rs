@@ -190,6 +190,23 @@ impl<'a> Lexer<'a> {
         Ok((res, start..end))
     }
 
+    /// Skips any trivia and returns the byte offset where the next token starts.
+    pub(super) fn start_byte_offset(&mut self) -> usize {
+        loop {
+            let (token, rest) = consume_token(self.input, false);
+            if let Token::Trivia = token {
+                self.input = rest;
+            } else {
+                return self.current_byte_offset();
+            }
+        }
+    }
+
+    /// Returns the span from the given byte offset up to the last parsed token.
+    pub(super) fn span_from(&self, offset: usize) -> Span {
+        offset..self.current_byte_offset()
+    }
+
     fn peek_token_and_rest(&mut self) -> (TokenSpan<'a>, &'a str) {
         let mut cloned = self.clone();
         let token = cloned.next();
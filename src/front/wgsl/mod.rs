@@ -28,13 +28,17 @@ use std::{
     borrow::Cow,
     convert::TryFrom,
     io::{self, Write},
-    iter,
+    iter, mem,
     num::{NonZeroU32, ParseFloatError, ParseIntError},
     ops,
 };
 use thiserror::Error;
 
 type Span = ops::Range<usize>;
+
+/// Placeholder specialization ID of the overridable constants declared
+/// without an `id` attribute, until the module is parsed.
+const IMPLICIT_OVERRIDE_ID: u32 = !0;
 type TokenSpan<'a> = (Token<'a>, Span);
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -104,6 +108,12 @@ pub enum Error<'a> {
     BadScalarWidth(Span, &'a str),
     #[error("")]
     BadAccessor(Span),
+    #[error("atomic pointer is invalid")]
+    InvalidAtomicPointer(Span),
+    #[error("invalid ray query pointer")]
+    InvalidRayQueryPointer(Span),
+    #[error("blend source index must be 0 or 1")]
+    BadBlendSourceIndex(Span),
     #[error("bad texture`")]
     BadTexture(Span),
     #[error("bad texture coordinate")]
@@ -171,6 +181,24 @@ pub enum Error<'a> {
     UnimplementedBuiltin(crate::BuiltIn),
     #[error("expression {0} doesn't match its given type {1:?}")]
     LetTypeMismatch(&'a str, Handle<crate::Type>),
+    #[error("pipeline-overridable constant must be a scalar")]
+    InvalidOverride(Span),
+    #[error("pipeline-overridable constant must have an initializer")]
+    MissingOverrideInitializer(Span),
+    #[error("expression can't be evaluated at compile time: {1}")]
+    NonConstantExpression(Span, crate::proc::ConstantEvaluatorError),
+    #[error("subgroup operand is invalid")]
+    InvalidSubgroupOperand(Span),
+    #[error("gather component must be a constant between 0 and 3")]
+    InvalidGatherComponent(Span),
+    #[error("unknown extension")]
+    UnknownExtension(Span),
+    #[error("extension `f16` is not enabled")]
+    Float16NotEnabled(Span),
+    #[error("extension `mesh_shading` is not enabled")]
+    MeshShadingNotEnabled(Span),
+    #[error("mesh outputs are incomplete")]
+    IncompleteMeshOutputs(Span),
     #[error("other error")]
     Other,
 }
@@ -290,6 +318,24 @@ impl<'a> Error<'a> {
                 labels: vec![(span.clone(), "must be one of f32, i32 or u32".into())],
                 notes: vec![],
             },
+            Error::InvalidAtomicPointer(ref span) => ParseError {
+                message: "atomic operation is done on a pointer to a non-atomic".to_string(),
+                labels: vec![(span.clone(), "atomic pointer is invalid".into())],
+                notes: vec![],
+            },
+            Error::InvalidRayQueryPointer(ref span) => ParseError {
+                message: "ray query operation is done on a pointer to a non-ray-query".to_string(),
+                labels: vec![(span.clone(), "ray query pointer is invalid".into())],
+                notes: vec![],
+            },
+            Error::BadBlendSourceIndex(ref bad_span) => ParseError {
+                message: format!(
+                    "blend source index must be 0 or 1, found `{}`",
+                    &source[bad_span.clone()],
+                ),
+                labels: vec![(bad_span.clone(), "invalid blend source index".into())],
+                notes: vec![],
+            },
             Error::BadTexture(ref bad_span) => ParseError {
                 message: format!("expected an image, but found '{}' which is not an image", &source[bad_span.clone()]),
                 labels: vec![(bad_span.clone(), "not an image".into())],
@@ -353,6 +399,56 @@ impl<'a> Error<'a> {
                 labels: vec![(bad_span.clone(), "struct member size or alignment must not be 0".into())],
                 notes: vec![],
             },
+            Error::InvalidOverride(ref bad_span) => ParseError {
+                message: format!("override '{}' must be a scalar", &source[bad_span.clone()]),
+                labels: vec![(bad_span.clone(), "not a scalar".into())],
+                notes: vec![],
+            },
+            Error::MissingOverrideInitializer(ref bad_span) => ParseError {
+                message: format!("override '{}' has no initializer", &source[bad_span.clone()]),
+                labels: vec![(bad_span.clone(), "needs an initializer".into())],
+                notes: vec!["the pipeline isn't required to provide the value".to_string()],
+            },
+            Error::NonConstantExpression(ref bad_span, ref error) => ParseError {
+                message: format!("expression '{}' can't be evaluated at compile time", &source[bad_span.clone()]),
+                labels: vec![(bad_span.clone(), error.to_string().into())],
+                notes: vec![],
+            },
+            Error::InvalidSubgroupOperand(ref bad_span) => ParseError {
+                message: format!("subgroup operand '{}' must be a scalar or a vector", &source[bad_span.clone()]),
+                labels: vec![(bad_span.clone(), "not a scalar or a vector".into())],
+                notes: vec![],
+            },
+            Error::InvalidGatherComponent(ref bad_span) => ParseError {
+                message: format!("gather component '{}' is invalid", &source[bad_span.clone()]),
+                labels: vec![(bad_span.clone(), "must be a constant between 0 and 3".into())],
+                notes: vec![],
+            },
+            Error::UnknownExtension(ref bad_span) => ParseError {
+                message: format!("unknown extension: '{}'", &source[bad_span.clone()]),
+                labels: vec![(bad_span.clone(), "unknown extension".into())],
+                notes: vec!["The supported extensions are f16 and mesh_shading".into()],
+            },
+            Error::Float16NotEnabled(ref bad_span) => ParseError {
+                message: format!("type '{}' requires the f16 extension", &source[bad_span.clone()]),
+                labels: vec![(bad_span.clone(), "uses f16".into())],
+                notes: vec!["Add `enable f16;` at the start of the module".into()],
+            },
+            Error::MeshShadingNotEnabled(ref bad_span) => ParseError {
+                message: format!(
+                    "'{}' requires the mesh_shading extension",
+                    &source[bad_span.clone()]
+                ),
+                labels: vec![(bad_span.clone(), "uses mesh shading".into())],
+                notes: vec!["Add `enable mesh_shading;` at the start of the module".into()],
+            },
+            Error::IncompleteMeshOutputs(ref bad_span) => ParseError {
+                message: "mesh outputs are incomplete".to_string(),
+                labels: vec![(bad_span.clone(), "mesh entry point".into())],
+                notes: vec![
+                    "`output_topology`, `max_vertices`, `max_primitives` and `vertex_output` are all required".into(),
+                ],
+            },
 
             ref error => ParseError {
                 message: error.to_string(),
@@ -416,6 +512,9 @@ impl crate::TypeInner {
     ) -> String {
         match *self {
             crate::TypeInner::Scalar { kind, width } => kind.to_wgsl(width),
+            crate::TypeInner::Atomic { kind, width } => {
+                format!("atomic<{}>", kind.to_wgsl(width))
+            }
             crate::TypeInner::Vector { size, kind, width } => {
                 format!("vec{}<{}>", size as u32, kind.to_wgsl(width))
             }
@@ -493,6 +592,22 @@ impl crate::TypeInner {
                 )
             }
             crate::TypeInner::Sampler { .. } => "sampler".to_string(),
+            crate::TypeInner::AccelerationStructure => "acceleration_structure".to_string(),
+            crate::TypeInner::RayQuery => "ray_query".to_string(),
+            crate::TypeInner::BindingArray { base, size } => {
+                let member_type = &types[base];
+                let base = match member_type.name {
+                    Some(ref name) => name.clone(),
+                    None => member_type.inner.to_wgsl(types, constants),
+                };
+                match size {
+                    crate::ArraySize::Constant(size) => match constants[size].to_array_length() {
+                        Some(length) => format!("binding_array<{}, {}>", base, length),
+                        None => format!("binding_array<{}, unknown>", base),
+                    },
+                    crate::ArraySize::Dynamic => format!("binding_array<{}>", base),
+                }
+            }
         }
     }
 }
@@ -567,6 +682,24 @@ mod type_inner_tests {
             class: crate::ImageClass::Depth,
         };
         assert_eq!(img2.to_wgsl(&types, &constants), "texture_depth_cube_array");
+
+        let binding_array = crate::TypeInner::BindingArray {
+            base: mytype1,
+            size: crate::ArraySize::Constant(c),
+        };
+        assert_eq!(
+            binding_array.to_wgsl(&types, &constants),
+            "binding_array<MyType1, 32>"
+        );
+
+        let dynamic_binding_array = crate::TypeInner::BindingArray {
+            base: mytype2,
+            size: crate::ArraySize::Dynamic,
+        };
+        assert_eq!(
+            dynamic_binding_array.to_wgsl(&types, &constants),
+            "binding_array<MyType2>"
+        );
     }
 }
 
@@ -585,17 +718,6 @@ impl crate::ScalarKind {
     }
 }
 
-trait StringValueLookup<'a> {
-    type Value;
-    fn lookup(&self, key: &'a str, span: Span) -> Result<Self::Value, Error<'a>>;
-}
-impl<'a> StringValueLookup<'a> for FastHashMap<&'a str, Handle<crate::Expression>> {
-    type Value = Handle<crate::Expression>;
-    fn lookup(&self, key: &'a str, span: Span) -> Result<Self::Value, Error<'a>> {
-        self.get(key).cloned().ok_or(Error::UnknownIdent(span, key))
-    }
-}
-
 struct StatementContext<'input, 'temp, 'out> {
     lookup_ident: &'temp mut FastHashMap<&'input str, Handle<crate::Expression>>,
     typifier: &'temp mut super::Typifier,
@@ -705,10 +827,9 @@ impl<'a> ExpressionContext<'a, '_, '_> {
 
     fn prepare_sampling(
         &mut self,
-        image_name: &'a str,
+        image: Handle<crate::Expression>,
         span: Span,
     ) -> Result<SamplingContext, Error<'a>> {
-        let image = self.lookup_ident.lookup(image_name, span.clone())?;
         Ok(SamplingContext {
             image,
             arrayed: match *self.resolve_type(image)? {
@@ -727,13 +848,15 @@ impl<'a> ExpressionContext<'a, '_, '_> {
             ExpressionContext<'a, '_, '_>,
         ) -> Result<Handle<crate::Expression>, Error<'a>>,
     ) -> Result<Handle<crate::Expression>, Error<'a>> {
+        let start = lexer.start_byte_offset();
         let mut left = parser(lexer, self.reborrow())?;
         while let Some(op) = classifier(lexer.peek().0) {
             let _ = lexer.next();
             let right = parser(lexer, self.reborrow())?;
-            left = self
-                .expressions
-                .append(crate::Expression::Binary { op, left, right });
+            left = self.expressions.append_with_span(
+                crate::Expression::Binary { op, left, right },
+                lexer.span_from(start).into(),
+            );
         }
         Ok(left)
     }
@@ -747,6 +870,7 @@ impl<'a> ExpressionContext<'a, '_, '_> {
             ExpressionContext<'a, '_, '_>,
         ) -> Result<Handle<crate::Expression>, Error<'a>>,
     ) -> Result<Handle<crate::Expression>, Error<'a>> {
+        let start = lexer.start_byte_offset();
         let mut left = parser(lexer, self.reborrow())?;
         while let Some(op) = classifier(lexer.peek().0) {
             let _ = lexer.next();
@@ -771,9 +895,10 @@ impl<'a> ExpressionContext<'a, '_, '_> {
                     _ => {}
                 }
             }
-            left = self
-                .expressions
-                .append(crate::Expression::Binary { op, left, right });
+            left = self.expressions.append_with_span(
+                crate::Expression::Binary { op, left, right },
+                lexer.span_from(start).into(),
+            );
         }
         Ok(left)
     }
@@ -863,7 +988,11 @@ type LocalFunctionCall = (Handle<crate::Function>, Vec<Handle<crate::Expression>
 
 #[derive(Default)]
 struct BindingParser {
+    /// Set by the `enable mesh_shading;` directive.
+    mesh_shading_enabled: bool,
     location: Option<u32>,
+    /// Set by `index(1)`, for the second source of dual-source blending.
+    second_blend_source: bool,
     built_in: Option<crate::BuiltIn>,
     interpolation: Option<crate::Interpolation>,
     sampling: Option<crate::Sampling>,
@@ -882,10 +1011,29 @@ impl BindingParser {
                 self.location = Some(lexer.next_uint_literal()?);
                 lexer.expect(Token::Paren(')'))?;
             }
+            "index" => {
+                lexer.expect(Token::Paren('('))?;
+                let (index, span) = lexer.capture_span(Lexer::next_uint_literal)?;
+                self.second_blend_source = match index {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(Error::BadBlendSourceIndex(span)),
+                };
+                lexer.expect(Token::Paren(')'))?;
+            }
             "builtin" => {
                 lexer.expect(Token::Paren('('))?;
                 let (raw, span) = lexer.next_ident_with_span()?;
-                self.built_in = Some(conv::map_built_in(raw, span)?);
+                let built_in = conv::map_built_in(raw, span.clone())?;
+                match built_in {
+                    crate::BuiltIn::MeshTaskSize | crate::BuiltIn::CullPrimitive
+                        if !self.mesh_shading_enabled =>
+                    {
+                        return Err(Error::MeshShadingNotEnabled(span))
+                    }
+                    _ => {}
+                }
+                self.built_in = Some(built_in);
                 lexer.expect(Token::Paren(')'))?;
             }
             "interpolate" => {
@@ -910,7 +1058,7 @@ impl BindingParser {
             self.interpolation,
             self.sampling,
         ) {
-            (None, None, None, None) => Ok(None),
+            (None, None, None, None) if !self.second_blend_source => Ok(None),
             (Some(location), None, interpolation, sampling) => {
                 // Before handing over the completed `Module`, we call
                 // `apply_common_default_interpolation` to ensure that the interpolation and
@@ -918,11 +1066,14 @@ impl BindingParser {
                 // shader input user bindings, so leaving them potentially `None` here is fine.
                 Ok(Some(crate::Binding::Location {
                     location,
+                    second_blend_source: self.second_blend_source,
                     interpolation,
                     sampling,
                 }))
             }
-            (None, Some(bi), None, None) => Ok(Some(crate::Binding::BuiltIn(bi))),
+            (None, Some(bi), None, None) if !self.second_blend_source => {
+                Ok(Some(crate::Binding::BuiltIn(bi)))
+            }
             (location, built_in, interpolation, sampling) => Err(Error::InconsistentBinding(
                 location,
                 built_in,
@@ -1019,6 +1170,14 @@ pub struct Parser {
     scopes: Vec<Scope>,
     lookup_type: FastHashMap<String, Handle<crate::Type>>,
     layouter: Layouter,
+    /// Overridable constants declared without an `id` attribute.
+    implicit_overrides: Vec<Handle<crate::Constant>>,
+    /// Constants depending on overridable ones.
+    derived_constants: Vec<crate::DerivedConstant>,
+    /// Set by the `enable f16;` directive.
+    f16_enabled: bool,
+    /// Set by the `enable mesh_shading;` directive.
+    mesh_shading_enabled: bool,
 }
 
 impl Parser {
@@ -1027,6 +1186,10 @@ impl Parser {
             scopes: Vec::new(),
             lookup_type: FastHashMap::default(),
             layouter: Default::default(),
+            implicit_overrides: Vec::new(),
+            derived_constants: Vec::new(),
+            f16_enabled: false,
+            mesh_shading_enabled: false,
         }
     }
 
@@ -1093,6 +1256,222 @@ impl Parser {
         Ok(Some((fun_handle, arguments)))
     }
 
+    fn parse_atomic_pointer<'a>(
+        &mut self,
+        lexer: &mut Lexer<'a>,
+        mut ctx: ExpressionContext<'a, '_, '_>,
+    ) -> Result<Handle<crate::Expression>, Error<'a>> {
+        let (pointer, pointer_span) =
+            lexer.capture_span(|lexer| self.parse_singular_expression(lexer, ctx.reborrow()))?;
+        ctx.resolve_type(pointer)?;
+        if ctx
+            .typifier
+            .get(pointer, ctx.types)
+            .is_atomic_pointer(ctx.types)
+        {
+            Ok(pointer)
+        } else {
+            Err(Error::InvalidAtomicPointer(pointer_span))
+        }
+    }
+
+    fn parse_ray_query_pointer<'a>(
+        &mut self,
+        lexer: &mut Lexer<'a>,
+        mut ctx: ExpressionContext<'a, '_, '_>,
+    ) -> Result<Handle<crate::Expression>, Error<'a>> {
+        let (pointer, pointer_span) =
+            lexer.capture_span(|lexer| self.parse_singular_expression(lexer, ctx.reborrow()))?;
+        let is_ray_query = match *ctx.resolve_type(pointer)? {
+            crate::TypeInner::Pointer { base, .. } => match ctx.types[base].inner {
+                crate::TypeInner::RayQuery => true,
+                _ => false,
+            },
+            _ => false,
+        };
+        if is_ray_query {
+            Ok(pointer)
+        } else {
+            Err(Error::InvalidRayQueryPointer(pointer_span))
+        }
+    }
+
+    /// Find a named type, generating the ray query structures on first use.
+    fn lookup_named_type(
+        &mut self,
+        name: &str,
+        type_arena: &mut Arena<crate::Type>,
+        const_arena: &Arena<crate::Constant>,
+    ) -> Option<Handle<crate::Type>> {
+        if let Some(&handle) = self.lookup_type.get(name) {
+            return Some(handle);
+        }
+        let ray_query_struct = match name {
+            "RayDesc" => crate::proc::RayQueryStruct::Desc,
+            "RayIntersection" => crate::proc::RayQueryStruct::Intersection,
+            _ => return None,
+        };
+        let handle = ray_query_struct.generate(type_arena, const_arena);
+        self.lookup_type.insert(name.to_string(), handle);
+        Some(handle)
+    }
+
+    /// Parse a call to one of the atomic read-modify-write functions.
+    ///
+    /// Like local function calls, these are statements producing a result,
+    /// so the emitter is interrupted around them.
+    fn parse_atomic_call<'a>(
+        &mut self,
+        lexer: &mut Lexer<'a>,
+        name: &'a str,
+        mut ctx: ExpressionContext<'a, '_, '_>,
+    ) -> Result<Option<Handle<crate::Expression>>, Error<'a>> {
+        use crate::AtomicFunction as Af;
+        let fun = match name {
+            "atomicAdd" => Af::Add,
+            "atomicSub" => Af::Subtract,
+            "atomicAnd" => Af::And,
+            "atomicOr" => Af::InclusiveOr,
+            "atomicXor" => Af::ExclusiveOr,
+            "atomicMin" => Af::Min,
+            "atomicMax" => Af::Max,
+            "atomicExchange" | "atomicCompareExchangeWeak" => Af::Exchange { compare: None },
+            _ => return Ok(None),
+        };
+
+        lexer.open_arguments()?;
+        let pointer = self.parse_atomic_pointer(lexer, ctx.reborrow())?;
+        lexer.expect(Token::Separator(','))?;
+        let fun = if name == "atomicCompareExchangeWeak" {
+            let compare = self.parse_general_expression(lexer, ctx.reborrow())?;
+            lexer.expect(Token::Separator(','))?;
+            Af::Exchange {
+                compare: Some(compare),
+            }
+        } else {
+            fun
+        };
+        let value = self.parse_general_expression(lexer, ctx.reborrow())?;
+        lexer.close_arguments()?;
+
+        let (kind, width) = match *ctx.typifier.get(pointer, ctx.types) {
+            crate::TypeInner::Pointer { base, .. } => match ctx.types[base].inner {
+                crate::TypeInner::Atomic { kind, width } => (kind, width),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        ctx.block.extend(ctx.emitter.finish(ctx.expressions));
+        let result = ctx.expressions.append(crate::Expression::AtomicResult {
+            kind,
+            width,
+            comparison: name == "atomicCompareExchangeWeak",
+        });
+        ctx.block.push(crate::Statement::Atomic {
+            pointer,
+            fun,
+            value,
+            result,
+        });
+        // restart the emitter
+        ctx.emitter.start(ctx.expressions);
+        Ok(Some(result))
+    }
+
+    /// Parse a call to one of the subgroup functions.
+    ///
+    /// Just like atomics, these are statements producing a result.
+    fn parse_subgroup_call<'a>(
+        &mut self,
+        lexer: &mut Lexer<'a>,
+        name: &'a str,
+        mut ctx: ExpressionContext<'a, '_, '_>,
+    ) -> Result<Option<Handle<crate::Expression>>, Error<'a>> {
+        if name == "subgroupBallot" {
+            lexer.open_arguments()?;
+            let predicate = if lexer.skip(Token::Paren(')')) {
+                None
+            } else {
+                let predicate = self.parse_general_expression(lexer, ctx.reborrow())?;
+                lexer.close_arguments()?;
+                Some(predicate)
+            };
+            ctx.block.extend(ctx.emitter.finish(ctx.expressions));
+            let result = ctx
+                .expressions
+                .append(crate::Expression::SubgroupBallotResult);
+            ctx.block
+                .push(crate::Statement::SubgroupBallot { result, predicate });
+            ctx.emitter.start(ctx.expressions);
+            return Ok(Some(result));
+        }
+
+        let operation = conv::map_subgroup_operation(name);
+        let gather_has_index = match name {
+            "subgroupBroadcastFirst" => Some(false),
+            "subgroupBroadcast"
+            | "subgroupShuffle"
+            | "subgroupShuffleDown"
+            | "subgroupShuffleUp"
+            | "subgroupShuffleXor" => Some(true),
+            _ => None,
+        };
+        if operation.is_none() && gather_has_index.is_none() {
+            return Ok(None);
+        }
+
+        lexer.open_arguments()?;
+        let (argument, argument_span) =
+            lexer.capture_span(|lexer| self.parse_general_expression(lexer, ctx.reborrow()))?;
+        let index = if gather_has_index == Some(true) {
+            lexer.expect(Token::Separator(','))?;
+            Some(self.parse_general_expression(lexer, ctx.reborrow())?)
+        } else {
+            None
+        };
+        lexer.close_arguments()?;
+
+        let inner = match *ctx.resolve_type(argument)? {
+            crate::TypeInner::Scalar { kind, width } => crate::TypeInner::Scalar { kind, width },
+            crate::TypeInner::Vector { size, kind, width } => {
+                crate::TypeInner::Vector { size, kind, width }
+            }
+            _ => return Err(Error::InvalidSubgroupOperand(argument_span)),
+        };
+        let ty = ctx.types.fetch_or_append(crate::Type { name: None, inner });
+
+        ctx.block.extend(ctx.emitter.finish(ctx.expressions));
+        let result = ctx
+            .expressions
+            .append(crate::Expression::SubgroupOperationResult { ty });
+        ctx.block.push(match operation {
+            Some((op, collective_op)) => crate::Statement::SubgroupCollectiveOperation {
+                op,
+                collective_op,
+                argument,
+                result,
+            },
+            None => {
+                let mode = match (name, index) {
+                    ("subgroupBroadcast", Some(index)) => crate::GatherMode::Broadcast(index),
+                    ("subgroupShuffle", Some(index)) => crate::GatherMode::Shuffle(index),
+                    ("subgroupShuffleDown", Some(index)) => crate::GatherMode::ShuffleDown(index),
+                    ("subgroupShuffleUp", Some(index)) => crate::GatherMode::ShuffleUp(index),
+                    ("subgroupShuffleXor", Some(index)) => crate::GatherMode::ShuffleXor(index),
+                    _ => crate::GatherMode::BroadcastFirst,
+                };
+                crate::Statement::SubgroupGather {
+                    mode,
+                    argument,
+                    result,
+                }
+            }
+        });
+        // restart the emitter
+        ctx.emitter.start(ctx.expressions);
+        Ok(Some(result))
+    }
+
     fn parse_function_call_inner<'a>(
         &mut self,
         lexer: &mut Lexer<'a>,
@@ -1125,12 +1504,19 @@ impl Parser {
             } else {
                 None
             };
+            let arg3 = if arg_count > 3 {
+                lexer.expect(Token::Separator(','))?;
+                Some(self.parse_general_expression(lexer, ctx.reborrow())?)
+            } else {
+                None
+            };
             lexer.close_arguments()?;
             crate::Expression::Math {
                 fun,
                 arg,
                 arg1,
                 arg2,
+                arg3,
             }
         } else if name == "select" {
             lexer.open_arguments()?;
@@ -1155,12 +1541,13 @@ impl Parser {
             match name {
                 "textureSample" => {
                     lexer.open_arguments()?;
-                    let (image_name, image_span) = lexer.next_ident_with_span()?;
+                    let (image, image_span) =
+                        self.parse_general_expression_with_span(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
-                    let (sampler_name, sampler_span) = lexer.next_ident_with_span()?;
+                    let sampler = self.parse_general_expression(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
                     let coordinate = self.parse_general_expression(lexer, ctx.reborrow())?;
-                    let sc = ctx.prepare_sampling(image_name, image_span)?;
+                    let sc = ctx.prepare_sampling(image, image_span)?;
                     let array_index = if sc.arrayed {
                         lexer.expect(Token::Separator(','))?;
                         Some(self.parse_general_expression(lexer, ctx.reborrow())?)
@@ -1175,7 +1562,8 @@ impl Parser {
                     lexer.close_arguments()?;
                     crate::Expression::ImageSample {
                         image: sc.image,
-                        sampler: ctx.lookup_ident.lookup(sampler_name, sampler_span)?,
+                        sampler,
+                        gather: None,
                         coordinate,
                         array_index,
                         offset,
@@ -1185,12 +1573,13 @@ impl Parser {
                 }
                 "textureSampleLevel" => {
                     lexer.open_arguments()?;
-                    let (image_name, image_span) = lexer.next_ident_with_span()?;
+                    let (image, image_span) =
+                        self.parse_general_expression_with_span(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
-                    let (sampler_name, sampler_span) = lexer.next_ident_with_span()?;
+                    let sampler = self.parse_general_expression(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
                     let coordinate = self.parse_general_expression(lexer, ctx.reborrow())?;
-                    let sc = ctx.prepare_sampling(image_name, image_span)?;
+                    let sc = ctx.prepare_sampling(image, image_span)?;
                     let array_index = if sc.arrayed {
                         lexer.expect(Token::Separator(','))?;
                         Some(self.parse_general_expression(lexer, ctx.reborrow())?)
@@ -1207,7 +1596,8 @@ impl Parser {
                     lexer.close_arguments()?;
                     crate::Expression::ImageSample {
                         image: sc.image,
-                        sampler: ctx.lookup_ident.lookup(sampler_name, sampler_span)?,
+                        sampler,
+                        gather: None,
                         coordinate,
                         array_index,
                         offset,
@@ -1217,12 +1607,13 @@ impl Parser {
                 }
                 "textureSampleBias" => {
                     lexer.open_arguments()?;
-                    let (image_name, image_span) = lexer.next_ident_with_span()?;
+                    let (image, image_span) =
+                        self.parse_general_expression_with_span(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
-                    let (sampler_name, sampler_span) = lexer.next_ident_with_span()?;
+                    let sampler = self.parse_general_expression(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
                     let coordinate = self.parse_general_expression(lexer, ctx.reborrow())?;
-                    let sc = ctx.prepare_sampling(image_name, image_span)?;
+                    let sc = ctx.prepare_sampling(image, image_span)?;
                     let array_index = if sc.arrayed {
                         lexer.expect(Token::Separator(','))?;
                         Some(self.parse_general_expression(lexer, ctx.reborrow())?)
@@ -1239,7 +1630,8 @@ impl Parser {
                     lexer.close_arguments()?;
                     crate::Expression::ImageSample {
                         image: sc.image,
-                        sampler: ctx.lookup_ident.lookup(sampler_name, sampler_span)?,
+                        sampler,
+                        gather: None,
                         coordinate,
                         array_index,
                         offset,
@@ -1249,12 +1641,13 @@ impl Parser {
                 }
                 "textureSampleGrad" => {
                     lexer.open_arguments()?;
-                    let (image_name, image_span) = lexer.next_ident_with_span()?;
+                    let (image, image_span) =
+                        self.parse_general_expression_with_span(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
-                    let (sampler_name, sampler_span) = lexer.next_ident_with_span()?;
+                    let sampler = self.parse_general_expression(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
                     let coordinate = self.parse_general_expression(lexer, ctx.reborrow())?;
-                    let sc = ctx.prepare_sampling(image_name, image_span)?;
+                    let sc = ctx.prepare_sampling(image, image_span)?;
                     let array_index = if sc.arrayed {
                         lexer.expect(Token::Separator(','))?;
                         Some(self.parse_general_expression(lexer, ctx.reborrow())?)
@@ -1273,7 +1666,8 @@ impl Parser {
                     lexer.close_arguments()?;
                     crate::Expression::ImageSample {
                         image: sc.image,
-                        sampler: ctx.lookup_ident.lookup(sampler_name, sampler_span)?,
+                        sampler,
+                        gather: None,
                         coordinate,
                         array_index,
                         offset,
@@ -1283,12 +1677,13 @@ impl Parser {
                 }
                 "textureSampleCompare" => {
                     lexer.open_arguments()?;
-                    let (image_name, image_span) = lexer.next_ident_with_span()?;
+                    let (image, image_span) =
+                        self.parse_general_expression_with_span(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
-                    let (sampler_name, sampler_span) = lexer.next_ident_with_span()?;
+                    let sampler = self.parse_general_expression(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
                     let coordinate = self.parse_general_expression(lexer, ctx.reborrow())?;
-                    let sc = ctx.prepare_sampling(image_name, image_span)?;
+                    let sc = ctx.prepare_sampling(image, image_span)?;
                     let array_index = if sc.arrayed {
                         lexer.expect(Token::Separator(','))?;
                         Some(self.parse_general_expression(lexer, ctx.reborrow())?)
@@ -1305,7 +1700,8 @@ impl Parser {
                     lexer.close_arguments()?;
                     crate::Expression::ImageSample {
                         image: sc.image,
-                        sampler: ctx.lookup_ident.lookup(sampler_name, sampler_span)?,
+                        sampler,
+                        gather: None,
                         coordinate,
                         array_index,
                         offset,
@@ -1315,12 +1711,117 @@ impl Parser {
                 }
                 "textureSampleCompareLevel" => {
                     lexer.open_arguments()?;
-                    let (image_name, image_span) = lexer.next_ident_with_span()?;
+                    let (image, image_span) =
+                        self.parse_general_expression_with_span(lexer, ctx.reborrow())?;
+                    lexer.expect(Token::Separator(','))?;
+                    let sampler = self.parse_general_expression(lexer, ctx.reborrow())?;
+                    lexer.expect(Token::Separator(','))?;
+                    let coordinate = self.parse_general_expression(lexer, ctx.reborrow())?;
+                    let sc = ctx.prepare_sampling(image, image_span)?;
+                    let array_index = if sc.arrayed {
+                        lexer.expect(Token::Separator(','))?;
+                        Some(self.parse_general_expression(lexer, ctx.reborrow())?)
+                    } else {
+                        None
+                    };
+                    lexer.expect(Token::Separator(','))?;
+                    let reference = self.parse_general_expression(lexer, ctx.reborrow())?;
+                    let offset = if lexer.skip(Token::Separator(',')) {
+                        Some(self.parse_const_expression(lexer, ctx.types, ctx.constants)?)
+                    } else {
+                        None
+                    };
+                    lexer.close_arguments()?;
+                    crate::Expression::ImageSample {
+                        image: sc.image,
+                        sampler,
+                        gather: None,
+                        coordinate,
+                        array_index,
+                        offset,
+                        level: crate::SampleLevel::Zero,
+                        depth_ref: Some(reference),
+                    }
+                }
+                "textureGather" => {
+                    lexer.open_arguments()?;
+                    // depth images don't take a component
+                    let first_image = match lexer.peek() {
+                        (Token::Word(word), _) => match ctx.lookup_ident.get(word) {
+                            Some(&handle) => match *ctx.resolve_type(handle)? {
+                                crate::TypeInner::Image { .. } => true,
+                                _ => false,
+                            },
+                            None => false,
+                        },
+                        _ => false,
+                    };
+                    let component = if first_image {
+                        crate::SwizzleComponent::X
+                    } else {
+                        let start = lexer.start_byte_offset();
+                        let constant =
+                            self.parse_const_expression(lexer, ctx.types, ctx.constants)?;
+                        let span = lexer.span_from(start);
+                        lexer.expect(Token::Separator(','))?;
+                        let index = match ctx.constants[constant].inner {
+                            ConstantInner::Scalar {
+                                value: ScalarValue::Uint(int),
+                                ..
+                            } => int as i64,
+                            ConstantInner::Scalar {
+                                value: ScalarValue::Sint(int),
+                                ..
+                            } => int,
+                            _ => -1,
+                        };
+                        match index {
+                            0 => crate::SwizzleComponent::X,
+                            1 => crate::SwizzleComponent::Y,
+                            2 => crate::SwizzleComponent::Z,
+                            3 => crate::SwizzleComponent::W,
+                            _ => return Err(Error::InvalidGatherComponent(span)),
+                        }
+                    };
+                    let (image, image_span) =
+                        self.parse_general_expression_with_span(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
-                    let (sampler_name, sampler_span) = lexer.next_ident_with_span()?;
+                    let sampler = self.parse_general_expression(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
                     let coordinate = self.parse_general_expression(lexer, ctx.reborrow())?;
-                    let sc = ctx.prepare_sampling(image_name, image_span)?;
+                    let sc = ctx.prepare_sampling(image, image_span)?;
+                    let array_index = if sc.arrayed {
+                        lexer.expect(Token::Separator(','))?;
+                        Some(self.parse_general_expression(lexer, ctx.reborrow())?)
+                    } else {
+                        None
+                    };
+                    let offset = if lexer.skip(Token::Separator(',')) {
+                        Some(self.parse_const_expression(lexer, ctx.types, ctx.constants)?)
+                    } else {
+                        None
+                    };
+                    lexer.close_arguments()?;
+                    crate::Expression::ImageSample {
+                        image: sc.image,
+                        sampler,
+                        gather: Some(component),
+                        coordinate,
+                        array_index,
+                        offset,
+                        level: crate::SampleLevel::Zero,
+                        depth_ref: None,
+                    }
+                }
+                "textureGatherCompare" => {
+                    lexer.open_arguments()?;
+                    let (image, image_span) =
+                        self.parse_general_expression_with_span(lexer, ctx.reborrow())?;
+                    lexer.expect(Token::Separator(','))?;
+                    let sampler = self.parse_general_expression(lexer, ctx.reborrow())?;
+                    lexer.expect(Token::Separator(','))?;
+                    let coordinate = self.parse_general_expression(lexer, ctx.reborrow())?;
+                    let sc = ctx.prepare_sampling(image, image_span)?;
                     let array_index = if sc.arrayed {
                         lexer.expect(Token::Separator(','))?;
                         Some(self.parse_general_expression(lexer, ctx.reborrow())?)
@@ -1337,7 +1838,8 @@ impl Parser {
                     lexer.close_arguments()?;
                     crate::Expression::ImageSample {
                         image: sc.image,
-                        sampler: ctx.lookup_ident.lookup(sampler_name, sampler_span)?,
+                        sampler,
+                        gather: Some(crate::SwizzleComponent::X),
                         coordinate,
                         array_index,
                         offset,
@@ -1347,8 +1849,8 @@ impl Parser {
                 }
                 "textureLoad" => {
                     lexer.open_arguments()?;
-                    let (image_name, image_span) = lexer.next_ident_with_span()?;
-                    let image = ctx.lookup_ident.lookup(image_name, image_span.clone())?;
+                    let (image, image_span) =
+                        self.parse_general_expression_with_span(lexer, ctx.reborrow())?;
                     lexer.expect(Token::Separator(','))?;
                     let coordinate = self.parse_general_expression(lexer, ctx.reborrow())?;
                     let (class, arrayed) = match *ctx.resolve_type(image)? {
@@ -1379,8 +1881,7 @@ impl Parser {
                 }
                 "textureDimensions" => {
                     lexer.open_arguments()?;
-                    let (image_name, image_span) = lexer.next_ident_with_span()?;
-                    let image = ctx.lookup_ident.lookup(image_name, image_span)?;
+                    let image = self.parse_general_expression(lexer, ctx.reborrow())?;
                     let level = if lexer.skip(Token::Separator(',')) {
                         let expr = self.parse_general_expression(lexer, ctx.reborrow())?;
                         Some(expr)
@@ -1395,8 +1896,7 @@ impl Parser {
                 }
                 "textureNumLevels" => {
                     lexer.open_arguments()?;
-                    let (image_name, image_span) = lexer.next_ident_with_span()?;
-                    let image = ctx.lookup_ident.lookup(image_name, image_span)?;
+                    let image = self.parse_general_expression(lexer, ctx.reborrow())?;
                     lexer.close_arguments()?;
                     crate::Expression::ImageQuery {
                         image,
@@ -1405,8 +1905,7 @@ impl Parser {
                 }
                 "textureNumLayers" => {
                     lexer.open_arguments()?;
-                    let (image_name, image_span) = lexer.next_ident_with_span()?;
-                    let image = ctx.lookup_ident.lookup(image_name, image_span)?;
+                    let image = self.parse_general_expression(lexer, ctx.reborrow())?;
                     lexer.close_arguments()?;
                     crate::Expression::ImageQuery {
                         image,
@@ -1415,16 +1914,56 @@ impl Parser {
                 }
                 "textureNumSamples" => {
                     lexer.open_arguments()?;
-                    let (image_name, image_span) = lexer.next_ident_with_span()?;
-                    let image = ctx.lookup_ident.lookup(image_name, image_span)?;
+                    let image = self.parse_general_expression(lexer, ctx.reborrow())?;
                     lexer.close_arguments()?;
                     crate::Expression::ImageQuery {
                         image,
                         query: crate::ImageQuery::NumSamples,
                     }
                 }
+                "atomicLoad" => {
+                    lexer.open_arguments()?;
+                    let pointer = self.parse_atomic_pointer(lexer, ctx.reborrow())?;
+                    lexer.close_arguments()?;
+                    crate::Expression::Load { pointer }
+                }
+                "rayQueryProceed" => {
+                    lexer.open_arguments()?;
+                    let query = self.parse_ray_query_pointer(lexer, ctx.reborrow())?;
+                    lexer.close_arguments()?;
+                    ctx.block.extend(ctx.emitter.finish(ctx.expressions));
+                    let result = ctx
+                        .expressions
+                        .append(crate::Expression::RayQueryProceedResult);
+                    ctx.block.push(crate::Statement::RayQuery {
+                        query,
+                        fun: crate::RayQueryFunction::Proceed { result },
+                    });
+                    // restart the emitter
+                    ctx.emitter.start(ctx.expressions);
+                    return Ok(Some(result));
+                }
+                "rayQueryGetCommittedIntersection" | "rayQueryGetCandidateIntersection" => {
+                    lexer.open_arguments()?;
+                    let query = self.parse_ray_query_pointer(lexer, ctx.reborrow())?;
+                    lexer.close_arguments()?;
+                    let ty = self
+                        .lookup_named_type("RayIntersection", ctx.types, ctx.constants)
+                        .unwrap();
+                    crate::Expression::RayQueryGetIntersection {
+                        query,
+                        committed: name == "rayQueryGetCommittedIntersection",
+                        ty,
+                    }
+                }
                 // other
                 _ => {
+                    if let Some(handle) = self.parse_atomic_call(lexer, name, ctx.reborrow())? {
+                        return Ok(Some(handle));
+                    }
+                    if let Some(handle) = self.parse_subgroup_call(lexer, name, ctx.reborrow())? {
+                        return Ok(Some(handle));
+                    }
                     let handle =
                         match self.parse_local_function_call(lexer, name, ctx.reborrow())? {
                             Some((function, arguments)) => {
@@ -1453,10 +1992,11 @@ impl Parser {
         &mut self,
         lexer: &mut Lexer<'a>,
         type_name: &'a str,
+        type_span: Span,
         mut ctx: ExpressionContext<'a, '_, '_>,
     ) -> Result<Option<Handle<crate::Expression>>, Error<'a>> {
-        let ty_resolution = match self.lookup_type.get(type_name) {
-            Some(&handle) => TypeResolution::Handle(handle),
+        let ty_resolution = match self.lookup_named_type(type_name, ctx.types, ctx.constants) {
+            Some(handle) => TypeResolution::Handle(handle),
             None => match self.parse_type_decl_impl(
                 lexer,
                 TypeAttributes::default(),
@@ -1464,7 +2004,10 @@ impl Parser {
                 ctx.types,
                 ctx.constants,
             )? {
-                Some(inner) => TypeResolution::Value(inner),
+                Some(inner) => {
+                    self.check_f16_enabled(&inner, type_span)?;
+                    TypeResolution::Value(inner)
+                }
                 None => return Ok(None),
             },
         };
@@ -1542,70 +2085,94 @@ impl Parser {
         Ok(Some(ctx.expressions.append(expr)))
     }
 
-    fn parse_const_expression_impl<'a>(
+    fn parse_literal<'a>(
         &mut self,
         first_token_span: TokenSpan<'a>,
         lexer: &mut Lexer<'a>,
-        register_name: Option<&'a str>,
-        type_arena: &mut Arena<crate::Type>,
         const_arena: &mut Arena<crate::Constant>,
     ) -> Result<Handle<crate::Constant>, Error<'a>> {
-        self.scopes.push(Scope::ConstantExpr);
+        let start = first_token_span.1.start;
         let inner = match first_token_span {
             (Token::Word("true"), _) => crate::ConstantInner::boolean(true),
             (Token::Word("false"), _) => crate::ConstantInner::boolean(false),
             (Token::Number { value, ty, width }, _) => {
                 Self::get_constant_inner(value, ty, width, first_token_span)?
             }
-            (Token::Word(name), name_span) => {
-                // look for an existing constant first
-                for (handle, var) in const_arena.iter() {
-                    match var.name {
-                        Some(ref string) if string == name => {
-                            self.scopes.pop();
-                            return Ok(handle);
-                        }
-                        _ => {}
-                    }
-                }
-                let composite_ty = self.parse_type_decl_name(
-                    lexer,
-                    name,
-                    name_span,
-                    None,
-                    TypeAttributes::default(),
-                    type_arena,
-                    const_arena,
-                )?;
-
-                lexer.open_arguments()?;
-                //Note: this expects at least one argument
-                let mut components = Vec::new();
-                while components.is_empty() || lexer.next_argument()? {
-                    let component = self.parse_const_expression(lexer, type_arena, const_arena)?;
-                    components.push(component);
-                }
-                crate::ConstantInner::Composite {
-                    ty: composite_ty,
-                    components,
-                }
-            }
             other => return Err(Error::Unexpected(other, ExpectedToken::Constant)),
         };
 
-        let handle = if let Some(name) = register_name {
-            const_arena.append(crate::Constant {
-                name: Some(name.to_string()),
-                specialization: None,
-                inner,
-            })
+        let handle = const_arena.fetch_or_append(crate::Constant {
+            name: None,
+            specialization: None,
+            inner,
+        });
+        const_arena.init_span(handle, lexer.span_from(start).into());
+        Ok(handle)
+    }
+
+    /// Parse an expression, and evaluate it into a constant.
+    ///
+    /// If `register_name` is provided, the result is registered under this name.
+    /// Array sizes are parsed as additive expressions only, so that the closing
+    /// bracket isn't taken for a comparison or a shift.
+    fn parse_const_expression_impl<'a>(
+        &mut self,
+        lexer: &mut Lexer<'a>,
+        register_name: Option<&'a str>,
+        array_size: bool,
+        type_arena: &mut Arena<crate::Type>,
+        const_arena: &mut Arena<crate::Constant>,
+    ) -> Result<Handle<crate::Constant>, Error<'a>> {
+        self.scopes.push(Scope::ConstantExpr);
+        let start = lexer.start_byte_offset();
+        let old_length = const_arena.len();
+        let mut expressions = Arena::new();
+        let mut typifier = super::Typifier::new();
+        let mut block = crate::Block::new();
+        let mut emitter = super::Emitter::default();
+        emitter.start(&expressions);
+        let ctx = ExpressionContext {
+            lookup_ident: &FastHashMap::default(),
+            typifier: &mut typifier,
+            expressions: &mut expressions,
+            types: type_arena,
+            constants: const_arena,
+            global_vars: &Arena::new(),
+            local_vars: &Arena::new(),
+            arguments: &[],
+            functions: &Arena::new(),
+            block: &mut block,
+            emitter: &mut emitter,
+        };
+        let root = if array_size {
+            self.parse_additive_expression(lexer, ctx)?
         } else {
-            const_arena.fetch_or_append(crate::Constant {
-                name: None,
-                specialization: None,
-                inner,
-            })
+            self.parse_general_expression(lexer, ctx)?
         };
+        let span = lexer.span_from(start);
+
+        let mut handle = crate::proc::ConstantEvaluator {
+            types: type_arena,
+            constants: const_arena,
+            expressions: &expressions,
+        }
+        .eval_derived(root, &mut self.derived_constants)
+        .map_err(|error| Error::NonConstantExpression(span.clone(), error))?;
+
+        if let Some(name) = register_name {
+            // a freshly evaluated value is registered under our name,
+            // while an existing constant is copied
+            if handle.index() < old_length || const_arena[handle].name.is_some() {
+                let inner = const_arena[handle].inner.clone();
+                handle = const_arena.append(crate::Constant {
+                    name: None,
+                    specialization: None,
+                    inner,
+                });
+            }
+            const_arena.get_mut(handle).name = Some(name.to_string());
+        }
+        const_arena.init_span(handle, span.into());
 
         self.scopes.pop();
         Ok(handle)
@@ -1617,7 +2184,7 @@ impl Parser {
         type_arena: &mut Arena<crate::Type>,
         const_arena: &mut Arena<crate::Constant>,
     ) -> Result<Handle<crate::Constant>, Error<'a>> {
-        self.parse_const_expression_impl(lexer.next(), lexer, None, type_arena, const_arena)
+        self.parse_const_expression_impl(lexer, None, false, type_arena, const_arena)
     }
 
     fn parse_primary_expression<'a>(
@@ -1635,8 +2202,7 @@ impl Parser {
             token @ (Token::Word("true"), _)
             | token @ (Token::Word("false"), _)
             | token @ (Token::Number { .. }, _) => {
-                let const_handle =
-                    self.parse_const_expression_impl(token, lexer, None, ctx.types, ctx.constants)?;
+                let const_handle = self.parse_literal(token, lexer, ctx.constants)?;
                 // pause the emitter while generating this expression, since it's pre-emitted
                 ctx.block.extend(ctx.emitter.finish(ctx.expressions));
                 let expr = ctx
@@ -1648,12 +2214,24 @@ impl Parser {
             (Token::Word(word), span) => {
                 if let Some(&expr) = ctx.lookup_ident.get(word) {
                     expr
+                } else if let Some(constant) =
+                    ctx.constants.fetch_if(|c| c.name.as_deref() == Some(word))
+                {
+                    // named constants are visible in constant expressions
+                    ctx.block.extend(ctx.emitter.finish(ctx.expressions));
+                    let expr = ctx
+                        .expressions
+                        .append(crate::Expression::Constant(constant));
+                    ctx.emitter.start(ctx.expressions);
+                    expr
                 } else if let Some(expr) =
                     self.parse_function_call_inner(lexer, word, ctx.reborrow())?
                 {
                     //TODO: resolve the duplicate call in `parse_singular_expression`
                     expr
-                } else if let Some(expr) = self.parse_construction(lexer, word, ctx.reborrow())? {
+                } else if let Some(expr) =
+                    self.parse_construction(lexer, word, span.clone(), ctx.reborrow())?
+                {
                     expr
                 } else {
                     return Err(Error::UnknownIdent(span, word));
@@ -1673,8 +2251,10 @@ impl Parser {
         allow_deref: bool,
     ) -> Result<Handle<crate::Expression>, Error<'a>> {
         let mut needs_deref = match ctx.expressions[handle] {
-            crate::Expression::LocalVariable(_) | crate::Expression::GlobalVariable(_) => {
-                allow_deref
+            crate::Expression::LocalVariable(_) => allow_deref,
+            // handles, including binding arrays of them, are not behind a pointer
+            crate::Expression::GlobalVariable(var) => {
+                allow_deref && ctx.global_vars[var].class != crate::StorageClass::Handle
             }
             _ => false,
         };
@@ -1800,6 +2380,7 @@ impl Parser {
         mut ctx: ExpressionContext<'a, '_, '_>,
     ) -> Result<Handle<crate::Expression>, Error<'a>> {
         self.scopes.push(Scope::SingularExpr);
+        let start = lexer.start_byte_offset();
         //TODO: refactor this to avoid backing up
         let backup = lexer.clone();
         let (allow_deref, handle) = match lexer.next().0 {
@@ -1821,7 +2402,8 @@ impl Parser {
                 let handle = self.parse_primary_expression(lexer, ctx.reborrow())?;
                 (false, handle)
             }
-            Token::Word(word) => {
+            // identifiers that aren't called may name constants, like `length`
+            Token::Word(word) if lexer.peek().0 == Token::Paren('(') => {
                 let handle = match self.parse_function_call_inner(lexer, word, ctx.reborrow())? {
                     Some(handle) => handle,
                     None => {
@@ -1838,11 +2420,42 @@ impl Parser {
             }
         };
 
-        let post_handle = self.parse_postfix(lexer, ctx, handle, allow_deref)?;
+        let post_handle = self.parse_postfix(lexer, ctx.reborrow(), handle, allow_deref)?;
+        ctx.expressions
+            .init_span(post_handle, lexer.span_from(start).into());
         self.scopes.pop();
         Ok(post_handle)
     }
 
+    fn parse_additive_expression<'a>(
+        &mut self,
+        lexer: &mut Lexer<'a>,
+        mut context: ExpressionContext<'a, '_, '_>,
+    ) -> Result<Handle<crate::Expression>, Error<'a>> {
+        // additive_expression
+        context.parse_binary_splat_op(
+            lexer,
+            |token| match token {
+                Token::Operation('+') => Some(crate::BinaryOperator::Add),
+                Token::Operation('-') => Some(crate::BinaryOperator::Subtract),
+                _ => None,
+            },
+            // multiplicative_expression
+            |lexer, mut context| {
+                context.parse_binary_splat_op(
+                    lexer,
+                    |token| match token {
+                        Token::Operation('*') => Some(crate::BinaryOperator::Multiply),
+                        Token::Operation('/') => Some(crate::BinaryOperator::Divide),
+                        Token::Operation('%') => Some(crate::BinaryOperator::Modulo),
+                        _ => None,
+                    },
+                    |lexer, context| self.parse_singular_expression(lexer, context),
+                )
+            },
+        )
+    }
+
     fn parse_equality_expression<'a>(
         &mut self,
         lexer: &mut Lexer<'a>,
@@ -1880,40 +2493,7 @@ impl Parser {
                                 }
                                 _ => None,
                             },
-                            // additive_expression
-                            |lexer, mut context| {
-                                context.parse_binary_splat_op(
-                                    lexer,
-                                    |token| match token {
-                                        Token::Operation('+') => Some(crate::BinaryOperator::Add),
-                                        Token::Operation('-') => {
-                                            Some(crate::BinaryOperator::Subtract)
-                                        }
-                                        _ => None,
-                                    },
-                                    // multiplicative_expression
-                                    |lexer, mut context| {
-                                        context.parse_binary_splat_op(
-                                            lexer,
-                                            |token| match token {
-                                                Token::Operation('*') => {
-                                                    Some(crate::BinaryOperator::Multiply)
-                                                }
-                                                Token::Operation('/') => {
-                                                    Some(crate::BinaryOperator::Divide)
-                                                }
-                                                Token::Operation('%') => {
-                                                    Some(crate::BinaryOperator::Modulo)
-                                                }
-                                                _ => None,
-                                            },
-                                            |lexer, context| {
-                                                self.parse_singular_expression(lexer, context)
-                                            },
-                                        )
-                                    },
-                                )
-                            },
+                            |lexer, context| self.parse_additive_expression(lexer, context),
                         )
                     },
                 )
@@ -1921,6 +2501,16 @@ impl Parser {
         )
     }
 
+    fn parse_general_expression_with_span<'a>(
+        &mut self,
+        lexer: &mut Lexer<'a>,
+        context: ExpressionContext<'a, '_, '_>,
+    ) -> Result<(Handle<crate::Expression>, Span), Error<'a>> {
+        let start = lexer.start_byte_offset();
+        let handle = self.parse_general_expression(lexer, context)?;
+        Ok((handle, lexer.span_from(start)))
+    }
+
     fn parse_general_expression<'a>(
         &mut self,
         lexer: &mut Lexer<'a>,
@@ -2008,7 +2598,11 @@ impl Parser {
         let mut class = None;
         if lexer.skip(Token::Paren('<')) {
             let (class_str, span) = lexer.next_ident_with_span()?;
-            class = Some(conv::map_storage_class(class_str, span)?);
+            let parsed_class = conv::map_storage_class(class_str, span.clone())?;
+            if parsed_class == crate::StorageClass::TaskPayload && !self.mesh_shading_enabled {
+                return Err(Error::MeshShadingNotEnabled(span));
+            }
+            class = Some(parsed_class);
             lexer.expect(Token::Paren('>'))?;
         }
         let name = lexer.next_ident()?;
@@ -2045,7 +2639,10 @@ impl Parser {
         lexer.expect(Token::Paren('{'))?;
         loop {
             let (mut size, mut align) = (None, None);
-            let mut bind_parser = BindingParser::default();
+            let mut bind_parser = BindingParser {
+                mesh_shading_enabled: self.mesh_shading_enabled,
+                ..BindingParser::default()
+            };
             if lexer.skip(Token::DoubleParen('[')) {
                 self.scopes.push(Scope::Attribute);
                 let mut ready = true;
@@ -2238,12 +2835,21 @@ impl Parser {
                 lexer.expect_generic_paren('>')?;
                 crate::TypeInner::Pointer { base, class }
             }
+            "atomic" => {
+                let (kind, width) = lexer.next_scalar_generic()?;
+                crate::TypeInner::Atomic { kind, width }
+            }
             "array" => {
                 lexer.expect_generic_paren('<')?;
                 let (base, _access) = self.parse_type_decl(lexer, None, type_arena, const_arena)?;
                 let size = if lexer.skip(Token::Separator(',')) {
-                    let const_handle =
-                        self.parse_const_expression(lexer, type_arena, const_arena)?;
+                    let const_handle = self.parse_const_expression_impl(
+                        lexer,
+                        None,
+                        true,
+                        type_arena,
+                        const_arena,
+                    )?;
                     crate::ArraySize::Constant(const_handle)
                 } else {
                     crate::ArraySize::Dynamic
@@ -2256,8 +2862,29 @@ impl Parser {
 
                 crate::TypeInner::Array { base, size, stride }
             }
+            "binding_array" => {
+                lexer.expect_generic_paren('<')?;
+                let (base, _access) = self.parse_type_decl(lexer, None, type_arena, const_arena)?;
+                let size = if lexer.skip(Token::Separator(',')) {
+                    let const_handle = self.parse_const_expression_impl(
+                        lexer,
+                        None,
+                        true,
+                        type_arena,
+                        const_arena,
+                    )?;
+                    crate::ArraySize::Constant(const_handle)
+                } else {
+                    crate::ArraySize::Dynamic
+                };
+                lexer.expect_generic_paren('>')?;
+
+                crate::TypeInner::BindingArray { base, size }
+            }
             "sampler" => crate::TypeInner::Sampler { comparison: false },
             "sampler_comparison" => crate::TypeInner::Sampler { comparison: true },
+            "acceleration_structure" => crate::TypeInner::AccelerationStructure,
+            "ray_query" => crate::TypeInner::RayQuery,
             "texture_1d" => {
                 let (kind, width, span) = lexer.next_scalar_generic_with_span()?;
                 Self::check_texture_sample_type(kind, width, span)?;
@@ -2403,6 +3030,26 @@ impl Parser {
         }))
     }
 
+    fn check_f16_enabled(
+        &self,
+        inner: &crate::TypeInner,
+        span: Span,
+    ) -> Result<(), Error<'static>> {
+        let uses_f16 = match *inner {
+            crate::TypeInner::Scalar { kind, width }
+            | crate::TypeInner::Vector { kind, width, .. } => {
+                kind == crate::ScalarKind::Float && width == 2
+            }
+            crate::TypeInner::Matrix { width, .. } => width == 2,
+            _ => false,
+        };
+        if uses_f16 && !self.f16_enabled {
+            Err(Error::Float16NotEnabled(span))
+        } else {
+            Ok(())
+        }
+    }
+
     fn check_texture_sample_type(
         kind: crate::ScalarKind,
         width: u8,
@@ -2428,18 +3075,31 @@ impl Parser {
         type_arena: &mut Arena<crate::Type>,
         const_arena: &mut Arena<crate::Constant>,
     ) -> Result<Handle<crate::Type>, Error<'a>> {
-        Ok(match self.lookup_type.get(name) {
-            Some(&handle) => handle,
-            None => {
-                match self.parse_type_decl_impl(lexer, attribute, name, type_arena, const_arena)? {
-                    Some(inner) => type_arena.fetch_or_append(crate::Type {
-                        name: debug_name.map(|s| s.to_string()),
-                        inner,
-                    }),
-                    None => return Err(Error::UnknownType(name_span)),
+        Ok(
+            match self.lookup_named_type(name, type_arena, const_arena) {
+                Some(handle) => handle,
+                None => {
+                    match self.parse_type_decl_impl(
+                        lexer,
+                        attribute,
+                        name,
+                        type_arena,
+                        const_arena,
+                    )? {
+                        Some(inner) => {
+                            self.check_f16_enabled(&inner, name_span.clone())?;
+                            let handle = type_arena.fetch_or_append(crate::Type {
+                                name: debug_name.map(|s| s.to_string()),
+                                inner,
+                            });
+                            type_arena.init_span(handle, lexer.span_from(name_span.start).into());
+                            handle
+                        }
+                        None => return Err(Error::UnknownType(name_span)),
+                    }
                 }
-            }
-        })
+            },
+        )
     }
 
     fn parse_type_decl<'a>(
@@ -2514,6 +3174,19 @@ impl Parser {
                 }
             }
             None => {
+                if self
+                    .parse_atomic_call(lexer, ident, context.reborrow())?
+                    .is_some()
+                    || self
+                        .parse_subgroup_call(lexer, ident, context.reborrow())?
+                        .is_some()
+                {
+                    // the atomic or subgroup statement is already in the block
+                    context
+                        .block
+                        .extend(context.emitter.finish(context.expressions));
+                    return Ok(());
+                }
                 let (function, arguments) = self
                     .parse_local_function_call(lexer, ident, context.reborrow())?
                     .ok_or(Error::UnknownLocalFunction(ident))?;
@@ -2538,7 +3211,7 @@ impl Parser {
         block: &'out mut crate::Block,
         is_uniform_control_flow: bool,
     ) -> Result<(), Error<'a>> {
-        let word = match lexer.next() {
+        let (word, word_span) = match lexer.next() {
             (Token::Separator(';'), _) => return Ok(()),
             (Token::Paren('{'), _) => {
                 self.scopes.push(Scope::Block);
@@ -2555,7 +3228,7 @@ impl Parser {
                 block.push(crate::Statement::Block(statements));
                 return Ok(());
             }
-            (Token::Word(word), _) => word,
+            (Token::Word(word), word_span) => (word, word_span),
             other => return Err(Error::Unexpected(other, ExpectedToken::Statement)),
         };
 
@@ -2626,18 +3299,22 @@ impl Parser {
                 };
 
                 lexer.expect(Token::Separator(';'))?;
-                let var_id = context.variables.append(crate::LocalVariable {
-                    name: Some(name.to_owned()),
-                    ty,
-                    init: match init {
-                        Init::Constant(value) => Some(value),
-                        _ => None,
+                let span = crate::Span::from(lexer.span_from(word_span.start));
+                let var_id = context.variables.append_with_span(
+                    crate::LocalVariable {
+                        name: Some(name.to_owned()),
+                        ty,
+                        init: match init {
+                            Init::Constant(value) => Some(value),
+                            _ => None,
+                        },
                     },
-                });
+                    span,
+                );
 
                 let expr_id = context
                     .expressions
-                    .append(crate::Expression::LocalVariable(var_id));
+                    .append_with_span(crate::Expression::LocalVariable(var_id), span);
                 context.lookup_ident.insert(name, expr_id);
 
                 if let Init::Variable(value) = init {
@@ -2870,10 +3547,10 @@ impl Parser {
             "textureStore" => {
                 emitter.start(context.expressions);
                 lexer.open_arguments()?;
-                let (image_name, image_span) = lexer.next_ident_with_span()?;
-                let image = context
-                    .lookup_ident
-                    .lookup(image_name, image_span.clone())?;
+                let (image, image_span) = self.parse_general_expression_with_span(
+                    lexer,
+                    context.as_expression(block, &mut emitter),
+                )?;
                 lexer.expect(Token::Separator(','))?;
                 let mut expr_context = context.as_expression(block, &mut emitter);
                 let arrayed = match *expr_context.resolve_type(image)? {
@@ -2902,6 +3579,84 @@ impl Parser {
                     value,
                 });
             }
+            "setMeshOutputs" | "setVertex" | "setIndices" | "setPrimitive" => {
+                if !self.mesh_shading_enabled {
+                    return Err(Error::MeshShadingNotEnabled(word_span));
+                }
+                emitter.start(context.expressions);
+                lexer.open_arguments()?;
+                let first = self
+                    .parse_general_expression(lexer, context.as_expression(block, &mut emitter))?;
+                lexer.expect(Token::Separator(','))?;
+                let second = self
+                    .parse_general_expression(lexer, context.as_expression(block, &mut emitter))?;
+                lexer.close_arguments()?;
+                block.extend(emitter.finish(context.expressions));
+                let fun = match word {
+                    "setMeshOutputs" => crate::MeshFunction::SetMeshOutputs {
+                        vertex_count: first,
+                        primitive_count: second,
+                    },
+                    "setVertex" => crate::MeshFunction::SetVertex {
+                        index: first,
+                        value: second,
+                    },
+                    "setIndices" => crate::MeshFunction::SetIndices {
+                        index: first,
+                        value: second,
+                    },
+                    _ => crate::MeshFunction::SetPrimitive {
+                        index: first,
+                        value: second,
+                    },
+                };
+                block.push(crate::Statement::MeshFunction(fun));
+            }
+            "rayQueryInitialize" => {
+                emitter.start(context.expressions);
+                lexer.open_arguments()?;
+                let query = self
+                    .parse_ray_query_pointer(lexer, context.as_expression(block, &mut emitter))?;
+                lexer.expect(Token::Separator(','))?;
+                let acceleration_structure = self
+                    .parse_general_expression(lexer, context.as_expression(block, &mut emitter))?;
+                lexer.expect(Token::Separator(','))?;
+                let descriptor = self
+                    .parse_general_expression(lexer, context.as_expression(block, &mut emitter))?;
+                lexer.close_arguments()?;
+                block.extend(emitter.finish(context.expressions));
+                block.push(crate::Statement::RayQuery {
+                    query,
+                    fun: crate::RayQueryFunction::Initialize {
+                        acceleration_structure,
+                        descriptor,
+                    },
+                });
+            }
+            "rayQueryTerminate" => {
+                emitter.start(context.expressions);
+                lexer.open_arguments()?;
+                let query = self
+                    .parse_ray_query_pointer(lexer, context.as_expression(block, &mut emitter))?;
+                lexer.close_arguments()?;
+                block.extend(emitter.finish(context.expressions));
+                block.push(crate::Statement::RayQuery {
+                    query,
+                    fun: crate::RayQueryFunction::Terminate,
+                });
+            }
+            "atomicStore" => {
+                emitter.start(context.expressions);
+                lexer.open_arguments()?;
+                let pointer =
+                    self.parse_atomic_pointer(lexer, context.as_expression(block, &mut emitter))?;
+                lexer.expect(Token::Separator(','))?;
+                let value = self
+                    .parse_general_expression(lexer, context.as_expression(block, &mut emitter))?;
+                lexer.close_arguments()?;
+                block.extend(emitter.finish(context.expressions));
+                block.push(crate::Statement::Store { pointer, value });
+            }
             // assignment or a function call
             ident => {
                 self.parse_statement_restricted(
@@ -2945,7 +3700,10 @@ impl Parser {
             return Ok(None);
         }
 
-        let mut bind_parser = BindingParser::default();
+        let mut bind_parser = BindingParser {
+            mesh_shading_enabled: self.mesh_shading_enabled,
+            ..BindingParser::default()
+        };
         self.scopes.push(Scope::Attribute);
         loop {
             let (word, span) = lexer.next_ident_with_span()?;
@@ -2990,11 +3748,14 @@ impl Parser {
                 ));
             }
             let binding = self.parse_varying_binding(lexer)?;
+            let start = lexer.start_byte_offset();
             let (param_name, param_type, _access) =
                 self.parse_variable_ident_decl(lexer, &mut module.types, &mut module.constants)?;
             let param_index = arguments.len() as u32;
-            let expression_token =
-                expressions.append(crate::Expression::FunctionArgument(param_index));
+            let expression_token = expressions.append_with_span(
+                crate::Expression::FunctionArgument(param_index),
+                lexer.span_from(start).into(),
+            );
             lookup_ident.insert(param_name, expression_token);
             arguments.push(crate::FunctionArgument {
                 name: Some(param_name.to_string()),
@@ -3066,6 +3827,13 @@ impl Parser {
         let mut is_block = false;
         let mut workgroup_size = [0u32; 3];
         let mut early_depth_test = None;
+        let mut override_id = None;
+        let mut mesh_topology = None;
+        let mut max_vertices = None;
+        let mut max_primitives = None;
+        let mut vertex_output_type = None;
+        let mut primitive_output_type = None;
+        let mut task_payload = None;
 
         if lexer.skip(Token::DoubleParen('[')) {
             let (mut bind_index, mut bind_group) = (None, None);
@@ -3085,10 +3853,69 @@ impl Parser {
                         bind_group = Some(lexer.next_uint_literal()?);
                         lexer.expect(Token::Paren(')'))?;
                     }
+                    ("id", _) => {
+                        lexer.expect(Token::Paren('('))?;
+                        override_id = Some(lexer.next_uint_literal()?);
+                        lexer.expect(Token::Paren(')'))?;
+                    }
                     ("stage", _) => {
                         lexer.expect(Token::Paren('('))?;
                         let (ident, ident_span) = lexer.next_ident_with_span()?;
-                        stage = Some(conv::map_shader_stage(ident, ident_span)?);
+                        let parsed_stage = conv::map_shader_stage(ident, ident_span.clone())?;
+                        match parsed_stage {
+                            crate::ShaderStage::Task | crate::ShaderStage::Mesh
+                                if !self.mesh_shading_enabled =>
+                            {
+                                return Err(Error::MeshShadingNotEnabled(ident_span))
+                            }
+                            _ => {}
+                        }
+                        stage = Some(parsed_stage);
+                        lexer.expect(Token::Paren(')'))?;
+                    }
+                    ("output_topology", _) => {
+                        lexer.expect(Token::Paren('('))?;
+                        let (ident, ident_span) = lexer.next_ident_with_span()?;
+                        mesh_topology = Some(conv::map_mesh_output_topology(ident, ident_span)?);
+                        lexer.expect(Token::Paren(')'))?;
+                    }
+                    ("max_vertices", _) => {
+                        lexer.expect(Token::Paren('('))?;
+                        max_vertices = Some(lexer.next_uint_literal()?);
+                        lexer.expect(Token::Paren(')'))?;
+                    }
+                    ("max_primitives", _) => {
+                        lexer.expect(Token::Paren('('))?;
+                        max_primitives = Some(lexer.next_uint_literal()?);
+                        lexer.expect(Token::Paren(')'))?;
+                    }
+                    ("vertex_output", _) => {
+                        lexer.expect(Token::Paren('('))?;
+                        let (name, name_span) = lexer.next_ident_with_span()?;
+                        match self.lookup_type.get(name) {
+                            Some(&ty) => vertex_output_type = Some(ty),
+                            None => return Err(Error::UnknownType(name_span)),
+                        }
+                        lexer.expect(Token::Paren(')'))?;
+                    }
+                    ("primitive_output", _) => {
+                        lexer.expect(Token::Paren('('))?;
+                        let (name, name_span) = lexer.next_ident_with_span()?;
+                        match self.lookup_type.get(name) {
+                            Some(&ty) => primitive_output_type = Some(ty),
+                            None => return Err(Error::UnknownType(name_span)),
+                        }
+                        lexer.expect(Token::Paren(')'))?;
+                    }
+                    ("payload", _) => {
+                        lexer.expect(Token::Paren('('))?;
+                        let (name, name_span) = lexer.next_ident_with_span()?;
+                        match lookup_global_expression.get(name) {
+                            Some(&crate::Expression::GlobalVariable(handle)) => {
+                                task_payload = Some(handle)
+                            }
+                            _ => return Err(Error::UnknownIdent(name_span, name)),
+                        }
                         lexer.expect(Token::Paren(')'))?;
                     }
                     ("workgroup_size", _) => {
@@ -3145,8 +3972,17 @@ impl Parser {
         }
 
         // read items
+        let start = lexer.start_byte_offset();
         match lexer.next() {
             (Token::Separator(';'), _) => {}
+            (Token::Word("enable"), _) => {
+                match lexer.next_ident_with_span()? {
+                    ("f16", _) => self.f16_enabled = true,
+                    ("mesh_shading", _) => self.mesh_shading_enabled = true,
+                    (_, span) => return Err(Error::UnknownExtension(span)),
+                }
+                lexer.expect(Token::Separator(';'))?;
+            }
             (Token::Word("struct"), _) => {
                 let name = lexer.next_ident()?;
                 let (members, span) =
@@ -3159,6 +3995,7 @@ impl Parser {
                         span,
                     },
                 });
+                module.types.init_span(ty, lexer.span_from(start).into());
                 self.lookup_type.insert(name.to_owned(), ty);
                 lexer.expect(Token::Separator(';'))?;
             }
@@ -3181,11 +4018,10 @@ impl Parser {
                     &mut module.constants,
                 )?;
                 lexer.expect(Token::Operation('='))?;
-                let first_token_span = lexer.next();
                 let const_handle = self.parse_const_expression_impl(
-                    first_token_span,
                     lexer,
                     Some(name),
+                    false,
                     &mut module.types,
                     &mut module.constants,
                 )?;
@@ -3205,6 +4041,67 @@ impl Parser {
                 }
                 //TODO: check `ty` against `const_handle`.
                 lexer.expect(Token::Separator(';'))?;
+                module
+                    .constants
+                    .set_span(const_handle, lexer.span_from(start).into());
+                lookup_global_expression.insert(name, crate::Expression::Constant(const_handle));
+            }
+            (Token::Word("override"), _) => {
+                let (name, name_span) = lexer.next_ident_with_span()?;
+                let explicit_ty = if lexer.skip(Token::Separator(':')) {
+                    let (ty, _access) = self.parse_type_decl(
+                        lexer,
+                        None,
+                        &mut module.types,
+                        &mut module.constants,
+                    )?;
+                    Some(ty)
+                } else {
+                    None
+                };
+                if !lexer.skip(Token::Operation('=')) {
+                    // the pipeline may not provide a value, and there is
+                    // no default one to use instead
+                    return Err(match explicit_ty.map(|ty| &module.types[ty].inner) {
+                        Some(&crate::TypeInner::Scalar { .. }) => {
+                            Error::MissingOverrideInitializer(name_span)
+                        }
+                        _ => Error::InvalidOverride(name_span),
+                    });
+                }
+                // the initializer is registered under our name
+                let const_handle = self.parse_const_expression_impl(
+                    lexer,
+                    Some(name),
+                    false,
+                    &mut module.types,
+                    &mut module.constants,
+                )?;
+                let (width, value) = match module.constants[const_handle].inner {
+                    ConstantInner::Scalar { width, value } => (width, value),
+                    ConstantInner::Composite { .. } => {
+                        return Err(Error::InvalidOverride(name_span))
+                    }
+                };
+                if let Some(ty) = explicit_ty {
+                    let scalar = crate::TypeInner::Scalar {
+                        kind: value.scalar_kind(),
+                        width,
+                    };
+                    if module.types[ty].inner != scalar {
+                        return Err(Error::LetTypeMismatch(name, ty));
+                    }
+                }
+                lexer.expect(Token::Separator(';'))?;
+                let span = lexer.span_from(start).into();
+                // without an `id` attribute, the ID is only known once the
+                // whole module is parsed, but the constant is specializable
+                let specialization = Some(override_id.unwrap_or(IMPLICIT_OVERRIDE_ID));
+                module.constants.get_mut(const_handle).specialization = specialization;
+                module.constants.set_span(const_handle, span);
+                if override_id.is_none() {
+                    self.implicit_overrides.push(const_handle);
+                }
                 lookup_global_expression.insert(name, crate::Expression::Constant(const_handle));
             }
             (Token::Word("var"), _) => {
@@ -3223,36 +4120,69 @@ impl Parser {
                         crate::TypeInner::Array { .. } if binding.is_some() => {
                             crate::StorageClass::Storage
                         }
-                        crate::TypeInner::Image { .. } | crate::TypeInner::Sampler { .. } => {
-                            crate::StorageClass::Handle
+                        crate::TypeInner::Image { .. }
+                        | crate::TypeInner::Sampler { .. }
+                        | crate::TypeInner::AccelerationStructure => crate::StorageClass::Handle,
+                        crate::TypeInner::BindingArray { base, .. } => {
+                            match module.types[base].inner {
+                                crate::TypeInner::Image { .. }
+                                | crate::TypeInner::Sampler { .. } => crate::StorageClass::Handle,
+                                _ => crate::StorageClass::Uniform,
+                            }
                         }
                         _ => crate::StorageClass::Private,
                     },
                 };
-                let var_handle = module.global_variables.append(crate::GlobalVariable {
-                    name: Some(pvar.name.to_owned()),
-                    class,
-                    binding: binding.take(),
-                    ty: pvar.ty,
-                    init: pvar.init,
-                    storage_access: pvar.access,
-                });
+                let var_handle = module.global_variables.append_with_span(
+                    crate::GlobalVariable {
+                        name: Some(pvar.name.to_owned()),
+                        class,
+                        binding: binding.take(),
+                        ty: pvar.ty,
+                        init: pvar.init,
+                        storage_access: pvar.access,
+                    },
+                    lexer.span_from(start).into(),
+                );
                 lookup_global_expression
                     .insert(pvar.name, crate::Expression::GlobalVariable(var_handle));
             }
             (Token::Word("fn"), _) => {
+                // only the signature is reported, not the whole body
+                let (_, name_span) = lexer.peek();
+                let span = crate::Span::from(start..name_span.end);
                 let (function, name) =
                     self.parse_function_decl(lexer, module, lookup_global_expression)?;
+                let mesh_info = match (
+                    mesh_topology,
+                    max_vertices,
+                    max_primitives,
+                    vertex_output_type,
+                ) {
+                    (Some(topology), Some(max_vertices), Some(max_primitives), Some(ty)) => {
+                        Some(crate::MeshStageInfo {
+                            topology,
+                            max_vertices,
+                            max_primitives,
+                            vertex_output_type: ty,
+                            primitive_output_type,
+                        })
+                    }
+                    (None, None, None, None) if primitive_output_type.is_none() => None,
+                    _ => return Err(Error::IncompleteMeshOutputs(start..name_span.end)),
+                };
                 match stage {
                     Some(stage) => module.entry_points.push(crate::EntryPoint {
                         name: name.to_string(),
                         stage,
                         early_depth_test,
                         workgroup_size,
+                        mesh_info,
+                        task_payload,
                         function,
                     }),
                     None => {
-                        module.functions.append(function);
+                        module.functions.append_with_span(function, span);
                     }
                 }
             }
@@ -3267,10 +4197,30 @@ impl Parser {
         }
     }
 
+    /// Give the overridable constants declared without an `id` attribute
+    /// the lowest IDs that aren't taken, in declaration order.
+    fn assign_override_ids(&mut self, module: &mut crate::Module) {
+        let mut next_id = 0;
+        for handle in self.implicit_overrides.drain(..) {
+            while module
+                .constants
+                .iter()
+                .any(|(_, constant)| constant.specialization == Some(next_id))
+            {
+                next_id += 1;
+            }
+            module.constants.get_mut(handle).specialization = Some(next_id);
+        }
+    }
+
     pub fn parse(&mut self, source: &str) -> Result<crate::Module, ParseError> {
         self.scopes.clear();
         self.lookup_type.clear();
         self.layouter.clear();
+        self.implicit_overrides.clear();
+        self.derived_constants.clear();
+        self.f16_enabled = false;
+        self.mesh_shading_enabled = false;
 
         let mut module = crate::Module::default();
         let mut lexer = Lexer::new(source);
@@ -3285,6 +4235,8 @@ impl Parser {
                         return Err(Error::Other.as_parse_error(lexer.source));
                     };
                     module.apply_common_default_interpolation();
+                    self.assign_override_ids(&mut module);
+                    module.derived_constants = mem::take(&mut self.derived_constants);
                     return Ok(module);
                 }
             }
@@ -73,6 +73,14 @@ An `Expression` may only be used, whether by a `Statement` or another
 - The expression is the `result` of a [`Statement::Call`], representing the
   call's return value. The call must be 'in scope' for the use (see below).
 
+- The expression is the `result` of a [`Statement::Atomic`], representing the
+  value the atomic held before the operation. The same scoping rules as for
+  `Call` results apply.
+
+- The expression is the `result` of one of the subgroup statements, like
+  [`Statement::SubgroupBallot`]. The same scoping rules as for `Call` results
+  apply.
+
 - The expression is included in the range of some [`Statement::Emit`] that is
   'in scope' for the use (see below). The [`Expression::needs_pre_emit`] method
   returns `true` if the given expression does *not* need to be covered by an
@@ -127,11 +135,14 @@ opaque values like samplers and images.
 
 mod arena;
 pub mod back;
+pub mod builder;
 pub mod front;
 pub mod proc;
+mod span;
 pub mod valid;
 
-pub use crate::arena::{Arena, Handle, Range};
+pub use crate::arena::{Arena, Handle, HandleMap, Range};
+pub use crate::span::{Span, SpanContext, WithSpan};
 
 use std::{
     collections::{HashMap, HashSet},
@@ -205,6 +216,8 @@ pub enum ShaderStage {
     Vertex,
     Fragment,
     Compute,
+    Task,
+    Mesh,
 }
 
 /// Class of storage for variables.
@@ -226,6 +239,8 @@ pub enum StorageClass {
     Handle,
     /// Push constants.
     PushConstant,
+    /// Data written by a task shader and read by the mesh shaders it launches.
+    TaskPayload,
 }
 
 /// Built-in inputs and outputs.
@@ -234,6 +249,7 @@ pub enum StorageClass {
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub enum BuiltIn {
     Position,
+    ViewIndex,
     // vertex
     BaseInstance,
     BaseVertex,
@@ -245,6 +261,7 @@ pub enum BuiltIn {
     // fragment
     FragDepth,
     FrontFacing,
+    PrimitiveIndex,
     SampleIndex,
     SampleMask,
     // compute
@@ -253,6 +270,12 @@ pub enum BuiltIn {
     LocalInvocationIndex,
     WorkGroupId,
     WorkGroupSize,
+    // subgroup
+    SubgroupSize,
+    SubgroupInvocationId,
+    // mesh
+    MeshTaskSize,
+    CullPrimitive,
 }
 
 /// Number of bytes per scalar.
@@ -446,7 +469,7 @@ pub enum ImageClass {
 }
 
 /// A data type declared in the module.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct Type {
@@ -457,7 +480,7 @@ pub struct Type {
 }
 
 /// Enum with additional information, depending on the kind of type.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub enum TypeInner {
@@ -475,6 +498,12 @@ pub enum TypeInner {
         rows: VectorSize,
         width: Bytes,
     },
+    /// Atomic scalar.
+    ///
+    /// Atomics may only live in the `Storage` or `WorkGroup` storage classes,
+    /// and may only be accessed through [`Statement::Atomic`], or loaded and
+    /// stored as a whole.
+    Atomic { kind: ScalarKind, width: Bytes },
     /// Pointer to another type.
     ///
     /// ## Pointers to non-`SIZED` types
@@ -571,10 +600,40 @@ pub enum TypeInner {
     },
     /// Can be used to sample values from images.
     Sampler { comparison: bool },
+
+    /// Opaque handle to a top-level acceleration structure, traced by ray queries.
+    AccelerationStructure,
+    /// Opaque state of an inline ray query.
+    ///
+    /// Only local variables can have this type, and they are operated on
+    /// with [`Statement::RayQuery`].
+    RayQuery,
+
+    /// Array of bindings.
+    ///
+    /// A `BindingArray` represents an array where each element draws its value
+    /// from a separate bound resource. The array's element type `base` may be
+    /// [`Image`], [`Sampler`], or a top-level [`Struct`] describing a buffer.
+    /// Global variables of this type occupy a single binding slot, and are
+    /// indexed with [`Access`] or [`AccessIndex`] expressions to get at the
+    /// individual resources.
+    ///
+    /// A `BindingArray` with a [`Dynamic`] size has as many elements as the
+    /// pipeline layout provides. Indexing it with a non-uniform value requires
+    /// the corresponding [`Capabilities`] of the validator.
+    ///
+    /// [`Image`]: TypeInner::Image
+    /// [`Sampler`]: TypeInner::Sampler
+    /// [`Struct`]: TypeInner::Struct
+    /// [`Access`]: Expression::Access
+    /// [`AccessIndex`]: Expression::AccessIndex
+    /// [`Dynamic`]: ArraySize::Dynamic
+    /// [`Capabilities`]: crate::valid::Capabilities
+    BindingArray { base: Handle<Type>, size: ArraySize },
 }
 
 /// Constant value.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct Constant {
@@ -583,6 +642,26 @@ pub struct Constant {
     pub inner: ConstantInner,
 }
 
+/// Constant whose value depends on specializable constants.
+///
+/// The value held by [`constant`] is computed with the current values of the
+/// specializable constants, and [`specialize`] evaluates `expressions` again
+/// once they are known.
+///
+/// [`constant`]: DerivedConstant::constant
+/// [`specialize`]: proc::specialize
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub struct DerivedConstant {
+    /// The constant holding the value, used by no other expression.
+    pub constant: Handle<Constant>,
+    /// Expressions computing the value from other constants.
+    pub expressions: Arena<Expression>,
+    /// The expression whose value is held by the constant.
+    pub root: Handle<Expression>,
+}
+
 /// A literal scalar value, used in constants.
 #[derive(Debug, Clone, Copy, PartialOrd)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
@@ -619,6 +698,9 @@ pub enum Binding {
     /// Indexed location.
     Location {
         location: u32,
+        /// Indicates the second blend source of a fragment output,
+        /// used for dual-source blending.
+        second_blend_source: bool,
         interpolation: Option<Interpolation>,
         sampling: Option<Sampling>,
     },
@@ -783,8 +865,164 @@ pub enum MathFunction {
     Transpose,
     Determinant,
     // bits
+    CountTrailingZeros,
+    CountLeadingZeros,
     CountOneBits,
     ReverseBits,
+    ExtractBits,
+    InsertBits,
+    FirstTrailingBit,
+    FirstLeadingBit,
+    // data packing
+    Pack4x8snorm,
+    Pack4x8unorm,
+    Pack2x16snorm,
+    Pack2x16unorm,
+    Pack2x16float,
+    // data unpacking
+    Unpack4x8snorm,
+    Unpack4x8unorm,
+    Unpack2x16snorm,
+    Unpack2x16unorm,
+    Unpack2x16float,
+}
+
+/// Function on an atomic value.
+///
+/// Note: these do not include load/store, which use the existing
+/// [`Expression::Load`] and [`Statement::Store`].
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum AtomicFunction {
+    Add,
+    Subtract,
+    And,
+    ExclusiveOr,
+    InclusiveOr,
+    Min,
+    Max,
+    /// Unconditional exchange, or a compare-exchange if `compare` is provided.
+    Exchange {
+        compare: Option<Handle<Expression>>,
+    },
+}
+
+/// Operation combining the values of the invocations in a subgroup.
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum SubgroupOperation {
+    /// True if the boolean argument is true for all the invocations.
+    All,
+    /// True if the boolean argument is true for any of the invocations.
+    Any,
+    Add,
+    Mul,
+    Min,
+    Max,
+    And,
+    Or,
+    Xor,
+}
+
+/// The set of invocations a [`SubgroupOperation`] is applied to.
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum CollectiveOperation {
+    /// All the active invocations of the subgroup.
+    Reduce,
+    /// The active invocations up to and including the current one.
+    InclusiveScan,
+    /// The active invocations before the current one.
+    ExclusiveScan,
+}
+
+/// The invocation a [`Statement::SubgroupGather`] reads the value from.
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum GatherMode {
+    /// The active invocation with the lowest id.
+    BroadcastFirst,
+    /// The invocation with the given id, which has to be a constant.
+    Broadcast(Handle<Expression>),
+    /// The invocation with the given id.
+    Shuffle(Handle<Expression>),
+    /// The invocation with the current id plus the given delta.
+    ShuffleDown(Handle<Expression>),
+    /// The invocation with the current id minus the given delta.
+    ShuffleUp(Handle<Expression>),
+    /// The invocation with the current id xor-ed with the given mask.
+    ShuffleXor(Handle<Expression>),
+}
+
+/// Primitive topology produced by a mesh shader.
+#[derive(Clone, Copy, Debug, Hash, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+#[allow(missing_docs)] // The names are self evident
+pub enum MeshOutputTopology {
+    Points,
+    Lines,
+    Triangles,
+}
+
+/// Operation writing the outputs of a mesh shader.
+///
+/// The indices are not bounds checked against the counts.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum MeshFunction {
+    /// Set the number of vertices and primitives actually written.
+    SetMeshOutputs {
+        vertex_count: Handle<Expression>,
+        primitive_count: Handle<Expression>,
+    },
+    /// Write the vertex at `index`, of the [`MeshStageInfo::vertex_output_type`].
+    SetVertex {
+        index: Handle<Expression>,
+        value: Handle<Expression>,
+    },
+    /// Write the vertex indices of the primitive at `index`.
+    ///
+    /// The `value` is an `u32` for points, a `vec2<u32>` for lines
+    /// and a `vec3<u32>` for triangles.
+    SetIndices {
+        index: Handle<Expression>,
+        value: Handle<Expression>,
+    },
+    /// Write the per-primitive outputs of the primitive at `index`,
+    /// of the [`MeshStageInfo::primitive_output_type`].
+    SetPrimitive {
+        index: Handle<Expression>,
+        value: Handle<Expression>,
+    },
+}
+
+/// Operation on a ray query.
+///
+/// The ray description and intersection structures are laid out as
+/// described by [`proc::RayQueryStruct`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub enum RayQueryFunction {
+    /// Start tracing a ray through the `acceleration_structure`,
+    /// with the `RayDesc` structure in `descriptor`.
+    Initialize {
+        acceleration_structure: Handle<Expression>,
+        descriptor: Handle<Expression>,
+    },
+    /// Advance the query to the next candidate intersection.
+    ///
+    /// The `result` must be an [`Expression::RayQueryProceedResult`],
+    /// which is `true` while there are candidates left.
+    Proceed { result: Handle<Expression> },
+    /// Stop the traversal, leaving the committed intersection as it is.
+    Terminate,
 }
 
 /// Sampling modifier to control the level of detail.
@@ -946,6 +1184,12 @@ pub enum Expression {
     ImageSample {
         image: Handle<Expression>,
         sampler: Handle<Expression>,
+        /// If provided, gather the given component from the four texels
+        /// used for bilinear filtering, instead of filtering them.
+        ///
+        /// Gathering works on 2D and cube images only, with
+        /// [`SampleLevel::Zero`]. Depth images gather the `X` component.
+        gather: Option<SwizzleComponent>,
         coordinate: Handle<Expression>,
         array_index: Option<Handle<Expression>>,
         offset: Option<Handle<Constant>>,
@@ -1005,6 +1249,7 @@ pub enum Expression {
         arg: Handle<Expression>,
         arg1: Option<Handle<Expression>>,
         arg2: Option<Handle<Expression>>,
+        arg3: Option<Handle<Expression>>,
     },
     /// Cast a simple type to another kind.
     As {
@@ -1018,6 +1263,38 @@ pub enum Expression {
     },
     /// Result of calling another function.
     Call(Handle<Function>),
+    /// Result of an atomic operation.
+    ///
+    /// This is the value the atomic held before the operation was applied.
+    /// When `comparison` is true, the operation was a compare-exchange,
+    /// and the result is a `vec2` of that value and of 1 if it was
+    /// exchanged, or 0 otherwise.
+    AtomicResult {
+        kind: ScalarKind,
+        width: Bytes,
+        comparison: bool,
+    },
+    /// Result of a [`Statement::SubgroupBallot`].
+    ///
+    /// This is a `vec4<u32>`, with one bit for every invocation of the subgroup.
+    SubgroupBallotResult,
+    /// Result of a [`Statement::SubgroupCollectiveOperation`] or
+    /// a [`Statement::SubgroupGather`].
+    SubgroupOperationResult { ty: Handle<Type> },
+    /// Result of a [`RayQueryFunction::Proceed`].
+    ///
+    /// This is `true` while the query has a candidate intersection to consider.
+    RayQueryProceedResult,
+    /// Read the committed or the candidate intersection of a ray query.
+    ///
+    /// The `query` has to be a pointer to a [`RayQuery`](TypeInner::RayQuery),
+    /// and `ty` is the `RayIntersection` structure described by
+    /// [`proc::RayQueryStruct`].
+    RayQueryGetIntersection {
+        query: Handle<Expression>,
+        committed: bool,
+        ty: Handle<Type>,
+    },
     /// Get the length of an array.
     /// The expression must resolve to a pointer to an array with a dynamic size.
     ///
@@ -1116,6 +1393,70 @@ pub enum Statement {
         arguments: Vec<Handle<Expression>>,
         result: Option<Handle<Expression>>,
     },
+    /// Atomic function.
+    ///
+    /// The `pointer` has to point to an [`Atomic`](TypeInner::Atomic) value
+    /// in the `Storage` or `WorkGroup` storage class. The `result` has to be an
+    /// `Expression::AtomicResult`, holding the original value, and this
+    /// statement serves as a barrier for any operations on that expression.
+    Atomic {
+        /// Pointer to an atomic value.
+        pointer: Handle<Expression>,
+        /// Function to run on the atomic.
+        fun: AtomicFunction,
+        /// Value to use in the function.
+        value: Handle<Expression>,
+        /// Emitted expression as a result.
+        result: Handle<Expression>,
+    },
+    /// Gather the `predicate` of all the active invocations of the subgroup
+    /// into a bitmask.
+    ///
+    /// The `result` has to be an [`Expression::SubgroupBallotResult`].
+    /// Without a `predicate`, the bits of all the active invocations are set.
+    SubgroupBallot {
+        /// Emitted expression as a result.
+        result: Handle<Expression>,
+        /// Boolean to gather, true when `None`.
+        predicate: Option<Handle<Expression>>,
+    },
+    /// Combine the `argument` of the invocations of the subgroup.
+    ///
+    /// The `result` has to be an [`Expression::SubgroupOperationResult`]
+    /// of the same type as the `argument`.
+    SubgroupCollectiveOperation {
+        /// Operation to combine the values with.
+        op: SubgroupOperation,
+        /// The invocations to combine.
+        collective_op: CollectiveOperation,
+        /// Value of the current invocation.
+        argument: Handle<Expression>,
+        /// Emitted expression as a result.
+        result: Handle<Expression>,
+    },
+    /// Read the `argument` of another invocation of the subgroup.
+    ///
+    /// The `result` has to be an [`Expression::SubgroupOperationResult`]
+    /// of the same type as the `argument`.
+    SubgroupGather {
+        /// The invocation to read from.
+        mode: GatherMode,
+        /// Value of the current invocation.
+        argument: Handle<Expression>,
+        /// Emitted expression as a result.
+        result: Handle<Expression>,
+    },
+    /// Write the outputs of a mesh shader.
+    ///
+    /// Only allowed in the entry point of the [`ShaderStage::Mesh`] stage.
+    MeshFunction(MeshFunction),
+    /// Operate on a ray query.
+    RayQuery {
+        /// Pointer to a local variable of [`RayQuery`](TypeInner::RayQuery) type.
+        query: Handle<Expression>,
+        /// Operation to run on the query.
+        fun: RayQueryFunction,
+    },
 }
 
 /// A function argument.
@@ -1144,7 +1485,7 @@ pub struct FunctionResult {
 }
 
 /// A function defined in the module.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct Function {
@@ -1164,8 +1505,25 @@ pub struct Function {
     pub body: Block,
 }
 
+/// Outputs of a mesh shader entry point.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+#[cfg_attr(feature = "deserialize", derive(Deserialize))]
+pub struct MeshStageInfo {
+    /// Topology of the output primitives.
+    pub topology: MeshOutputTopology,
+    /// Maximum number of vertices written by a workgroup.
+    pub max_vertices: u32,
+    /// Maximum number of primitives written by a workgroup.
+    pub max_primitives: u32,
+    /// Structure written for each vertex.
+    pub vertex_output_type: Handle<Type>,
+    /// Structure written for each primitive, if there are per-primitive outputs.
+    pub primitive_output_type: Option<Handle<Type>>,
+}
+
 /// Exported function, to be run at a certain stage in the pipeline.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct EntryPoint {
@@ -1175,8 +1533,13 @@ pub struct EntryPoint {
     pub stage: ShaderStage,
     /// Early depth test for fragment stages.
     pub early_depth_test: Option<EarlyDepthTest>,
-    /// Workgroup size for compute stages
+    /// Workgroup size for compute, task and mesh stages
     pub workgroup_size: [u32; 3],
+    /// Outputs of mesh stages.
+    pub mesh_info: Option<MeshStageInfo>,
+    /// Global variable in the [`StorageClass::TaskPayload`] class,
+    /// written by task stages and read by mesh stages.
+    pub task_payload: Option<Handle<GlobalVariable>>,
     /// The entrance function.
     pub function: Function,
 }
@@ -1192,7 +1555,7 @@ pub struct EntryPoint {
 /// Alternatively, you can load an existing shader using one of the [available front ends][front].
 ///
 /// When finished, you can export modules using one of the [available backends][back].
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 #[cfg_attr(feature = "deserialize", derive(Deserialize))]
 pub struct Module {
@@ -1206,4 +1569,9 @@ pub struct Module {
     pub functions: Arena<Function>,
     /// Entry points.
     pub entry_points: Vec<EntryPoint>,
+    /// Constants depending on specializable constants, in the order
+    /// they are evaluated.
+    #[cfg_attr(feature = "serialize", serde(skip_serializing_if = "Vec::is_empty"))]
+    #[cfg_attr(feature = "deserialize", serde(default))]
+    pub derived_constants: Vec<DerivedConstant>,
 }
@@ -0,0 +1,1071 @@
+use crate::arena::{Handle, HandleMap};
+
+/// Remove the parts of `module` that can't affect any entry point.
+///
+/// Functions are kept if they are reachable from an entry point, or all of
+/// them if the module has no entry points at all, since it's a library then.
+/// Global variables, constants and types are kept if something that is kept
+/// refers to them, and so are the expressions and local variables within
+/// every function. Constants with a [`specialization`] ID are always kept,
+/// since they are a part of the pipeline interface, and so are the ones
+/// used by kept [`derived_constants`].
+///
+/// All the handles in the module are rewritten to match the compacted
+/// arenas, so any [`ModuleInfo`] produced before is stale, and the module
+/// has to be validated again. The returned map relates the old handles of
+/// the module items to the new ones.
+///
+/// [`specialization`]: crate::Constant::specialization
+/// [`derived_constants`]: crate::Module::derived_constants
+/// [`ModuleInfo`]: crate::valid::ModuleInfo
+pub fn compact(module: &mut crate::Module) -> ModuleMap {
+    let mut tracer = ModuleTracer {
+        module: &*module,
+        types: vec![false; module.types.len()],
+        constants: vec![false; module.constants.len()],
+        global_variables: vec![false; module.global_variables.len()],
+        functions: vec![false; module.functions.len()],
+    };
+
+    let mut function_tracers = Vec::new();
+    let mut function_stack = Vec::new();
+    for ep in module.entry_points.iter() {
+        if let Some(ref info) = ep.mesh_info {
+            tracer.trace_type(info.vertex_output_type);
+            if let Some(ty) = info.primitive_output_type {
+                tracer.trace_type(ty);
+            }
+        }
+        if let Some(global) = ep.task_payload {
+            tracer.trace_global_variable(global);
+        }
+    }
+    for (handle, constant) in module.constants.iter() {
+        if constant.specialization.is_some() {
+            tracer.trace_constant(handle);
+        }
+    }
+    let entry_point_tracers = module
+        .entry_points
+        .iter()
+        .map(|ep| tracer.trace_function(&ep.function, &mut function_stack))
+        .collect::<Vec<_>>();
+    if module.entry_points.is_empty() {
+        function_stack.extend(module.functions.iter().map(|(handle, _)| handle));
+    }
+    function_tracers.resize_with(module.functions.len(), || None);
+    while let Some(handle) = function_stack.pop() {
+        if tracer.functions[handle.index()] {
+            continue;
+        }
+        tracer.functions[handle.index()] = true;
+        let function_tracer = tracer.trace_function(&module.functions[handle], &mut function_stack);
+        function_tracers[handle.index()] = Some(function_tracer);
+    }
+    // later derived constants may be computed from earlier ones
+    for derived in module.derived_constants.iter().rev() {
+        if tracer.constants[derived.constant.index()] {
+            for (_, expression) in derived.expressions.iter() {
+                match *expression {
+                    crate::Expression::Constant(constant) => tracer.trace_constant(constant),
+                    crate::Expression::Compose { ty, .. } => tracer.trace_type(ty),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let ModuleTracer {
+        types,
+        constants,
+        global_variables,
+        functions,
+        ..
+    } = tracer;
+    let map = ModuleMap {
+        types: module.types.retain(|handle, _| types[handle.index()]),
+        constants: module
+            .constants
+            .retain(|handle, _| constants[handle.index()]),
+        global_variables: module
+            .global_variables
+            .retain(|handle, _| global_variables[handle.index()]),
+        functions: module
+            .functions
+            .retain(|handle, _| functions[handle.index()]),
+    };
+
+    for (_, ty) in module.types.iter_mut() {
+        map.adjust_type_inner(&mut ty.inner);
+    }
+    for (_, constant) in module.constants.iter_mut() {
+        if let crate::ConstantInner::Composite {
+            ref mut ty,
+            ref mut components,
+        } = constant.inner
+        {
+            map.types.adjust(ty);
+            for component in components.iter_mut() {
+                map.constants.adjust(component);
+            }
+        }
+    }
+    module
+        .derived_constants
+        .retain(|derived| constants[derived.constant.index()]);
+    for derived in module.derived_constants.iter_mut() {
+        map.constants.adjust(&mut derived.constant);
+        for (_, expression) in derived.expressions.iter_mut() {
+            match *expression {
+                crate::Expression::Constant(ref mut constant) => map.constants.adjust(constant),
+                crate::Expression::Compose { ref mut ty, .. } => map.types.adjust(ty),
+                _ => {}
+            }
+        }
+    }
+    for (_, global) in module.global_variables.iter_mut() {
+        map.types.adjust(&mut global.ty);
+        if let Some(ref mut init) = global.init {
+            map.constants.adjust(init);
+        }
+    }
+    let mut function_tracers = function_tracers.into_iter().flatten();
+    for (_, function) in module.functions.iter_mut() {
+        map.compact_function(function, function_tracers.next().unwrap());
+    }
+    for (ep, function_tracer) in module.entry_points.iter_mut().zip(entry_point_tracers) {
+        if let Some(ref mut info) = ep.mesh_info {
+            map.types.adjust(&mut info.vertex_output_type);
+            if let Some(ref mut ty) = info.primitive_output_type {
+                map.types.adjust(ty);
+            }
+        }
+        if let Some(ref mut global) = ep.task_payload {
+            map.global_variables.adjust(global);
+        }
+        map.compact_function(&mut ep.function, function_tracer);
+    }
+    map
+}
+
+/// Remove the expressions and local variables of `function` that are
+/// unused, like [`compact`] does, but leave the rest of `module` as it is.
+pub(super) fn compact_function(module: &crate::Module, function: &mut crate::Function) {
+    let mut tracer = ModuleTracer {
+        module,
+        types: vec![false; module.types.len()],
+        constants: vec![false; module.constants.len()],
+        global_variables: vec![false; module.global_variables.len()],
+        functions: vec![false; module.functions.len()],
+    };
+    let function_tracer = tracer.trace_function(function, &mut Vec::new());
+    let map = ModuleMap {
+        types: HandleMap::identity(module.types.len()),
+        constants: HandleMap::identity(module.constants.len()),
+        global_variables: HandleMap::identity(module.global_variables.len()),
+        functions: HandleMap::identity(module.functions.len()),
+    };
+    map.compact_function(function, function_tracer);
+}
+
+/// Make a module with only the entry point `name` of the given `stage`.
+///
+/// The result is self-contained: it has everything the entry point
+/// uses, and nothing else, as if the other entry points were removed
+/// before calling [`compact`]. The returned map leads from the handles
+/// of `module` to the ones of the new module, and back.
+///
+/// Returns `None` if there is no such entry point.
+pub fn extract_entry_point(
+    module: &crate::Module,
+    name: &str,
+    stage: crate::ShaderStage,
+) -> Option<(crate::Module, ModuleMap)> {
+    let ep = module
+        .entry_points
+        .iter()
+        .find(|ep| ep.name == name && ep.stage == stage)?;
+    let mut extracted = crate::Module {
+        types: module.types.clone(),
+        constants: module.constants.clone(),
+        global_variables: module.global_variables.clone(),
+        functions: module.functions.clone(),
+        entry_points: vec![ep.clone()],
+        derived_constants: module.derived_constants.clone(),
+    };
+    let map = compact(&mut extracted);
+    Some((extracted, map))
+}
+
+/// Marks the module items that are in use.
+struct ModuleTracer<'a> {
+    module: &'a crate::Module,
+    types: Vec<bool>,
+    constants: Vec<bool>,
+    global_variables: Vec<bool>,
+    functions: Vec<bool>,
+}
+
+/// Expressions and local variables of a function that are in use.
+struct FunctionTracer {
+    expressions: Vec<bool>,
+    local_variables: Vec<bool>,
+}
+
+impl<'a> ModuleTracer<'a> {
+    fn trace_type(&mut self, handle: Handle<crate::Type>) {
+        if std::mem::replace(&mut self.types[handle.index()], true) {
+            return;
+        }
+        use crate::TypeInner as Ti;
+        match self.module.types[handle].inner {
+            Ti::Pointer { base, .. } => self.trace_type(base),
+            Ti::Array { base, size, .. } | Ti::BindingArray { base, size } => {
+                self.trace_type(base);
+                if let crate::ArraySize::Constant(constant) = size {
+                    self.trace_constant(constant);
+                }
+            }
+            Ti::Struct { ref members, .. } => {
+                for member in members.iter() {
+                    self.trace_type(member.ty);
+                }
+            }
+            Ti::Scalar { .. }
+            | Ti::Vector { .. }
+            | Ti::Matrix { .. }
+            | Ti::Atomic { .. }
+            | Ti::ValuePointer { .. }
+            | Ti::Image { .. }
+            | Ti::Sampler { .. }
+            | Ti::AccelerationStructure
+            | Ti::RayQuery => {}
+        }
+    }
+
+    fn trace_constant(&mut self, handle: Handle<crate::Constant>) {
+        if std::mem::replace(&mut self.constants[handle.index()], true) {
+            return;
+        }
+        if let crate::ConstantInner::Composite { ty, ref components } =
+            self.module.constants[handle].inner
+        {
+            self.trace_type(ty);
+            for &component in components.iter() {
+                self.trace_constant(component);
+            }
+        }
+    }
+
+    fn trace_global_variable(&mut self, handle: Handle<crate::GlobalVariable>) {
+        if std::mem::replace(&mut self.global_variables[handle.index()], true) {
+            return;
+        }
+        let global = &self.module.global_variables[handle];
+        self.trace_type(global.ty);
+        if let Some(init) = global.init {
+            self.trace_constant(init);
+        }
+    }
+
+    /// Trace everything `function` uses, pushing the functions it calls
+    /// onto `function_stack`.
+    fn trace_function(
+        &mut self,
+        function: &crate::Function,
+        function_stack: &mut Vec<Handle<crate::Function>>,
+    ) -> FunctionTracer {
+        let mut expression_stack = Vec::new();
+        trace_block(&function.body, &mut expression_stack, function_stack);
+        expression_stack.extend(function.named_expressions.keys().cloned());
+
+        let mut tracer = FunctionTracer {
+            expressions: vec![false; function.expressions.len()],
+            local_variables: vec![false; function.local_variables.len()],
+        };
+        while let Some(handle) = expression_stack.pop() {
+            if std::mem::replace(&mut tracer.expressions[handle.index()], true) {
+                continue;
+            }
+            self.trace_expression(
+                &function.expressions[handle],
+                &mut tracer,
+                &mut expression_stack,
+                function_stack,
+            );
+        }
+
+        for argument in function.arguments.iter() {
+            self.trace_type(argument.ty);
+        }
+        if let Some(ref result) = function.result {
+            self.trace_type(result.ty);
+        }
+        for (handle, local) in function.local_variables.iter() {
+            if tracer.local_variables[handle.index()] {
+                self.trace_type(local.ty);
+                if let Some(init) = local.init {
+                    self.trace_constant(init);
+                }
+            }
+        }
+        tracer
+    }
+
+    fn trace_expression(
+        &mut self,
+        expression: &crate::Expression,
+        tracer: &mut FunctionTracer,
+        expression_stack: &mut Vec<Handle<crate::Expression>>,
+        function_stack: &mut Vec<Handle<crate::Function>>,
+    ) {
+        use crate::Expression as E;
+        match *expression {
+            E::Access { base, index } => expression_stack.extend(&[base, index]),
+            E::AccessIndex { base, .. } => expression_stack.push(base),
+            E::Constant(constant) => self.trace_constant(constant),
+            E::Splat { value, .. } => expression_stack.push(value),
+            E::Swizzle { vector, .. } => expression_stack.push(vector),
+            E::Compose { ty, ref components } => {
+                self.trace_type(ty);
+                expression_stack.extend_from_slice(components);
+            }
+            E::FunctionArgument(_)
+            | E::AtomicResult { .. }
+            | E::SubgroupBallotResult
+            | E::RayQueryProceedResult => {}
+            E::GlobalVariable(global) => self.trace_global_variable(global),
+            E::LocalVariable(local) => tracer.local_variables[local.index()] = true,
+            E::Load { pointer } => expression_stack.push(pointer),
+            E::ImageSample {
+                image,
+                sampler,
+                gather: _,
+                coordinate,
+                array_index,
+                offset,
+                ref level,
+                depth_ref,
+            } => {
+                expression_stack.extend(&[image, sampler, coordinate]);
+                expression_stack.extend(array_index);
+                expression_stack.extend(depth_ref);
+                if let Some(offset) = offset {
+                    self.trace_constant(offset);
+                }
+                match *level {
+                    crate::SampleLevel::Auto | crate::SampleLevel::Zero => {}
+                    crate::SampleLevel::Exact(expr) | crate::SampleLevel::Bias(expr) => {
+                        expression_stack.push(expr)
+                    }
+                    crate::SampleLevel::Gradient { x, y } => expression_stack.extend(&[x, y]),
+                }
+            }
+            E::ImageLoad {
+                image,
+                coordinate,
+                array_index,
+                index,
+            } => {
+                expression_stack.extend(&[image, coordinate]);
+                expression_stack.extend(array_index);
+                expression_stack.extend(index);
+            }
+            E::ImageQuery { image, ref query } => {
+                expression_stack.push(image);
+                if let crate::ImageQuery::Size { level: Some(level) } = *query {
+                    expression_stack.push(level);
+                }
+            }
+            E::Unary { expr, .. } => expression_stack.push(expr),
+            E::Binary { left, right, .. } => expression_stack.extend(&[left, right]),
+            E::Select {
+                condition,
+                accept,
+                reject,
+            } => expression_stack.extend(&[condition, accept, reject]),
+            E::Derivative { expr, .. } => expression_stack.push(expr),
+            E::Relational { argument, .. } => expression_stack.push(argument),
+            E::Math {
+                arg,
+                arg1,
+                arg2,
+                arg3,
+                ..
+            } => {
+                expression_stack.push(arg);
+                expression_stack.extend(arg1);
+                expression_stack.extend(arg2);
+                expression_stack.extend(arg3);
+            }
+            E::As { expr, .. } => expression_stack.push(expr),
+            E::Call(function) => function_stack.push(function),
+            E::SubgroupOperationResult { ty } => self.trace_type(ty),
+            E::RayQueryGetIntersection { query, ty, .. } => {
+                expression_stack.push(query);
+                self.trace_type(ty);
+            }
+            E::ArrayLength(expr) => expression_stack.push(expr),
+        }
+    }
+}
+
+/// Direct uses of the expressions of a function.
+pub(super) struct ExpressionUses {
+    /// Expressions used by statements, once for every use.
+    pub statements: Vec<Handle<crate::Expression>>,
+    /// Operands of every expression.
+    pub operands: Vec<Vec<Handle<crate::Expression>>>,
+}
+
+/// Collect the direct uses of the expressions of `function`.
+pub(super) fn expression_uses(
+    module: &crate::Module,
+    function: &crate::Function,
+) -> ExpressionUses {
+    let mut tracer = ModuleTracer {
+        module,
+        types: vec![false; module.types.len()],
+        constants: vec![false; module.constants.len()],
+        global_variables: vec![false; module.global_variables.len()],
+        functions: vec![false; module.functions.len()],
+    };
+    let mut function_tracer = FunctionTracer {
+        expressions: Vec::new(),
+        local_variables: vec![false; function.local_variables.len()],
+    };
+    let mut function_stack = Vec::new();
+    let mut statements = Vec::new();
+    trace_block(&function.body, &mut statements, &mut function_stack);
+    let operands = function
+        .expressions
+        .iter()
+        .map(|(_, expression)| {
+            let mut operands = Vec::new();
+            tracer.trace_expression(
+                expression,
+                &mut function_tracer,
+                &mut operands,
+                &mut function_stack,
+            );
+            operands
+        })
+        .collect();
+    ExpressionUses {
+        statements,
+        operands,
+    }
+}
+
+/// Collect the expressions used by the statements of `block`, and the
+/// functions it calls.
+fn trace_block(
+    block: &[crate::Statement],
+    expression_stack: &mut Vec<Handle<crate::Expression>>,
+    function_stack: &mut Vec<Handle<crate::Function>>,
+) {
+    use crate::Statement as S;
+    for statement in block {
+        match *statement {
+            S::Emit(_) | S::Break | S::Continue | S::Kill | S::Barrier(_) => {}
+            S::Block(ref block) => trace_block(block, expression_stack, function_stack),
+            S::If {
+                condition,
+                ref accept,
+                ref reject,
+            } => {
+                expression_stack.push(condition);
+                trace_block(accept, expression_stack, function_stack);
+                trace_block(reject, expression_stack, function_stack);
+            }
+            S::Switch {
+                selector,
+                ref cases,
+                ref default,
+            } => {
+                expression_stack.push(selector);
+                for case in cases.iter() {
+                    trace_block(&case.body, expression_stack, function_stack);
+                }
+                trace_block(default, expression_stack, function_stack);
+            }
+            S::Loop {
+                ref body,
+                ref continuing,
+            } => {
+                trace_block(body, expression_stack, function_stack);
+                trace_block(continuing, expression_stack, function_stack);
+            }
+            S::Return { value } => expression_stack.extend(value),
+            S::Store { pointer, value } => expression_stack.extend(&[pointer, value]),
+            S::ImageStore {
+                image,
+                coordinate,
+                array_index,
+                value,
+            } => {
+                expression_stack.extend(&[image, coordinate, value]);
+                expression_stack.extend(array_index);
+            }
+            S::Call {
+                function,
+                ref arguments,
+                result,
+            } => {
+                function_stack.push(function);
+                expression_stack.extend_from_slice(arguments);
+                expression_stack.extend(result);
+            }
+            S::Atomic {
+                pointer,
+                ref fun,
+                value,
+                result,
+            } => {
+                expression_stack.extend(&[pointer, value, result]);
+                if let crate::AtomicFunction::Exchange {
+                    compare: Some(compare),
+                } = *fun
+                {
+                    expression_stack.push(compare);
+                }
+            }
+            S::SubgroupBallot { result, predicate } => {
+                expression_stack.push(result);
+                expression_stack.extend(predicate);
+            }
+            S::SubgroupCollectiveOperation {
+                argument, result, ..
+            } => expression_stack.extend(&[argument, result]),
+            S::SubgroupGather {
+                ref mode,
+                argument,
+                result,
+            } => {
+                expression_stack.extend(&[argument, result]);
+                match *mode {
+                    crate::GatherMode::BroadcastFirst => {}
+                    crate::GatherMode::Broadcast(index)
+                    | crate::GatherMode::Shuffle(index)
+                    | crate::GatherMode::ShuffleDown(index)
+                    | crate::GatherMode::ShuffleUp(index)
+                    | crate::GatherMode::ShuffleXor(index) => expression_stack.push(index),
+                }
+            }
+            S::MeshFunction(ref fun) => match *fun {
+                crate::MeshFunction::SetMeshOutputs {
+                    vertex_count,
+                    primitive_count,
+                } => expression_stack.extend(&[vertex_count, primitive_count]),
+                crate::MeshFunction::SetVertex { index, value }
+                | crate::MeshFunction::SetIndices { index, value }
+                | crate::MeshFunction::SetPrimitive { index, value } => {
+                    expression_stack.extend(&[index, value])
+                }
+            },
+            S::RayQuery { query, ref fun } => {
+                expression_stack.push(query);
+                match *fun {
+                    crate::RayQueryFunction::Initialize {
+                        acceleration_structure,
+                        descriptor,
+                    } => expression_stack.extend(&[acceleration_structure, descriptor]),
+                    crate::RayQueryFunction::Proceed { result } => expression_stack.push(result),
+                    crate::RayQueryFunction::Terminate => {}
+                }
+            }
+        }
+    }
+}
+
+/// Maps between the handles of a module before and after [`compact`].
+#[derive(Debug)]
+pub struct ModuleMap {
+    pub types: HandleMap<crate::Type>,
+    pub constants: HandleMap<crate::Constant>,
+    pub global_variables: HandleMap<crate::GlobalVariable>,
+    pub functions: HandleMap<crate::Function>,
+}
+
+/// Handle maps of the compacted function arenas.
+struct FunctionMap {
+    expressions: HandleMap<crate::Expression>,
+    local_variables: HandleMap<crate::LocalVariable>,
+}
+
+impl ModuleMap {
+    fn adjust_type_inner(&self, inner: &mut crate::TypeInner) {
+        use crate::TypeInner as Ti;
+        match *inner {
+            Ti::Pointer { ref mut base, .. } => self.types.adjust(base),
+            Ti::Array {
+                ref mut base,
+                ref mut size,
+                ..
+            }
+            | Ti::BindingArray {
+                ref mut base,
+                ref mut size,
+            } => {
+                self.types.adjust(base);
+                if let crate::ArraySize::Constant(ref mut constant) = *size {
+                    self.constants.adjust(constant);
+                }
+            }
+            Ti::Struct {
+                ref mut members, ..
+            } => {
+                for member in members.iter_mut() {
+                    self.types.adjust(&mut member.ty);
+                }
+            }
+            Ti::Scalar { .. }
+            | Ti::Vector { .. }
+            | Ti::Matrix { .. }
+            | Ti::Atomic { .. }
+            | Ti::ValuePointer { .. }
+            | Ti::Image { .. }
+            | Ti::Sampler { .. }
+            | Ti::AccelerationStructure
+            | Ti::RayQuery => {}
+        }
+    }
+
+    fn compact_function(&self, function: &mut crate::Function, tracer: FunctionTracer) {
+        let map = FunctionMap {
+            expressions: function
+                .expressions
+                .retain(|handle, _| tracer.expressions[handle.index()]),
+            local_variables: function
+                .local_variables
+                .retain(|handle, _| tracer.local_variables[handle.index()]),
+        };
+
+        for argument in function.arguments.iter_mut() {
+            self.types.adjust(&mut argument.ty);
+        }
+        if let Some(ref mut result) = function.result {
+            self.types.adjust(&mut result.ty);
+        }
+        for (_, local) in function.local_variables.iter_mut() {
+            self.types.adjust(&mut local.ty);
+            if let Some(ref mut init) = local.init {
+                self.constants.adjust(init);
+            }
+        }
+        for (_, expression) in function.expressions.iter_mut() {
+            self.adjust_expression(expression, &map);
+        }
+        function.named_expressions = function
+            .named_expressions
+            .drain()
+            .map(|(mut handle, name)| {
+                map.expressions.adjust(&mut handle);
+                (handle, name)
+            })
+            .collect();
+        self.adjust_block(&mut function.body, &map);
+    }
+
+    fn adjust_expression(&self, expression: &mut crate::Expression, map: &FunctionMap) {
+        let adjust = |handle: &mut Handle<crate::Expression>| map.expressions.adjust(handle);
+        let adjust_option = |handle: &mut Option<Handle<crate::Expression>>| {
+            if let Some(ref mut handle) = *handle {
+                map.expressions.adjust(handle);
+            }
+        };
+        use crate::Expression as E;
+        match *expression {
+            E::Access {
+                ref mut base,
+                ref mut index,
+            } => {
+                adjust(base);
+                adjust(index);
+            }
+            E::AccessIndex { ref mut base, .. } => adjust(base),
+            E::Constant(ref mut constant) => self.constants.adjust(constant),
+            E::Splat { ref mut value, .. } => adjust(value),
+            E::Swizzle { ref mut vector, .. } => adjust(vector),
+            E::Compose {
+                ref mut ty,
+                ref mut components,
+            } => {
+                self.types.adjust(ty);
+                components.iter_mut().for_each(adjust);
+            }
+            E::FunctionArgument(_)
+            | E::AtomicResult { .. }
+            | E::SubgroupBallotResult
+            | E::RayQueryProceedResult => {}
+            E::GlobalVariable(ref mut global) => self.global_variables.adjust(global),
+            E::LocalVariable(ref mut local) => map.local_variables.adjust(local),
+            E::Load { ref mut pointer } => adjust(pointer),
+            E::ImageSample {
+                ref mut image,
+                ref mut sampler,
+                gather: _,
+                ref mut coordinate,
+                ref mut array_index,
+                ref mut offset,
+                ref mut level,
+                ref mut depth_ref,
+            } => {
+                adjust(image);
+                adjust(sampler);
+                adjust(coordinate);
+                adjust_option(array_index);
+                adjust_option(depth_ref);
+                if let Some(ref mut offset) = *offset {
+                    self.constants.adjust(offset);
+                }
+                match *level {
+                    crate::SampleLevel::Auto | crate::SampleLevel::Zero => {}
+                    crate::SampleLevel::Exact(ref mut expr)
+                    | crate::SampleLevel::Bias(ref mut expr) => adjust(expr),
+                    crate::SampleLevel::Gradient {
+                        ref mut x,
+                        ref mut y,
+                    } => {
+                        adjust(x);
+                        adjust(y);
+                    }
+                }
+            }
+            E::ImageLoad {
+                ref mut image,
+                ref mut coordinate,
+                ref mut array_index,
+                ref mut index,
+            } => {
+                adjust(image);
+                adjust(coordinate);
+                adjust_option(array_index);
+                adjust_option(index);
+            }
+            E::ImageQuery {
+                ref mut image,
+                ref mut query,
+            } => {
+                adjust(image);
+                if let crate::ImageQuery::Size { ref mut level } = *query {
+                    adjust_option(level);
+                }
+            }
+            E::Unary { ref mut expr, .. } => adjust(expr),
+            E::Binary {
+                ref mut left,
+                ref mut right,
+                ..
+            } => {
+                adjust(left);
+                adjust(right);
+            }
+            E::Select {
+                ref mut condition,
+                ref mut accept,
+                ref mut reject,
+            } => {
+                adjust(condition);
+                adjust(accept);
+                adjust(reject);
+            }
+            E::Derivative { ref mut expr, .. } => adjust(expr),
+            E::Relational {
+                ref mut argument, ..
+            } => adjust(argument),
+            E::Math {
+                ref mut arg,
+                ref mut arg1,
+                ref mut arg2,
+                ref mut arg3,
+                ..
+            } => {
+                adjust(arg);
+                adjust_option(arg1);
+                adjust_option(arg2);
+                adjust_option(arg3);
+            }
+            E::As { ref mut expr, .. } => adjust(expr),
+            E::Call(ref mut function) => self.functions.adjust(function),
+            E::SubgroupOperationResult { ref mut ty } => self.types.adjust(ty),
+            E::RayQueryGetIntersection {
+                ref mut query,
+                ref mut ty,
+                ..
+            } => {
+                adjust(query);
+                self.types.adjust(ty);
+            }
+            E::ArrayLength(ref mut expr) => adjust(expr),
+        }
+    }
+
+    fn adjust_block(&self, block: &mut crate::Block, map: &FunctionMap) {
+        let adjust = |handle: &mut Handle<crate::Expression>| map.expressions.adjust(handle);
+        let adjust_option = |handle: &mut Option<Handle<crate::Expression>>| {
+            if let Some(ref mut handle) = *handle {
+                map.expressions.adjust(handle);
+            }
+        };
+        use crate::Statement as S;
+        for statement in block.iter_mut() {
+            match *statement {
+                S::Emit(ref mut range) => map.expressions.adjust_range(range),
+                S::Break | S::Continue | S::Kill | S::Barrier(_) => {}
+                S::Block(ref mut block) => self.adjust_block(block, map),
+                S::If {
+                    ref mut condition,
+                    ref mut accept,
+                    ref mut reject,
+                } => {
+                    adjust(condition);
+                    self.adjust_block(accept, map);
+                    self.adjust_block(reject, map);
+                }
+                S::Switch {
+                    ref mut selector,
+                    ref mut cases,
+                    ref mut default,
+                } => {
+                    adjust(selector);
+                    for case in cases.iter_mut() {
+                        self.adjust_block(&mut case.body, map);
+                    }
+                    self.adjust_block(default, map);
+                }
+                S::Loop {
+                    ref mut body,
+                    ref mut continuing,
+                } => {
+                    self.adjust_block(body, map);
+                    self.adjust_block(continuing, map);
+                }
+                S::Return { ref mut value } => adjust_option(value),
+                S::Store {
+                    ref mut pointer,
+                    ref mut value,
+                } => {
+                    adjust(pointer);
+                    adjust(value);
+                }
+                S::ImageStore {
+                    ref mut image,
+                    ref mut coordinate,
+                    ref mut array_index,
+                    ref mut value,
+                } => {
+                    adjust(image);
+                    adjust(coordinate);
+                    adjust_option(array_index);
+                    adjust(value);
+                }
+                S::Call {
+                    ref mut function,
+                    ref mut arguments,
+                    ref mut result,
+                } => {
+                    self.functions.adjust(function);
+                    arguments.iter_mut().for_each(adjust);
+                    adjust_option(result);
+                }
+                S::Atomic {
+                    ref mut pointer,
+                    ref mut fun,
+                    ref mut value,
+                    ref mut result,
+                } => {
+                    adjust(pointer);
+                    adjust(value);
+                    adjust(result);
+                    if let crate::AtomicFunction::Exchange { ref mut compare } = *fun {
+                        adjust_option(compare);
+                    }
+                }
+                S::SubgroupBallot {
+                    ref mut result,
+                    ref mut predicate,
+                } => {
+                    adjust(result);
+                    adjust_option(predicate);
+                }
+                S::SubgroupCollectiveOperation {
+                    ref mut argument,
+                    ref mut result,
+                    ..
+                } => {
+                    adjust(argument);
+                    adjust(result);
+                }
+                S::SubgroupGather {
+                    ref mut mode,
+                    ref mut argument,
+                    ref mut result,
+                } => {
+                    adjust(argument);
+                    adjust(result);
+                    match *mode {
+                        crate::GatherMode::BroadcastFirst => {}
+                        crate::GatherMode::Broadcast(ref mut index)
+                        | crate::GatherMode::Shuffle(ref mut index)
+                        | crate::GatherMode::ShuffleDown(ref mut index)
+                        | crate::GatherMode::ShuffleUp(ref mut index)
+                        | crate::GatherMode::ShuffleXor(ref mut index) => adjust(index),
+                    }
+                }
+                S::MeshFunction(ref mut fun) => match *fun {
+                    crate::MeshFunction::SetMeshOutputs {
+                        ref mut vertex_count,
+                        ref mut primitive_count,
+                    } => {
+                        adjust(vertex_count);
+                        adjust(primitive_count);
+                    }
+                    crate::MeshFunction::SetVertex {
+                        ref mut index,
+                        ref mut value,
+                    }
+                    | crate::MeshFunction::SetIndices {
+                        ref mut index,
+                        ref mut value,
+                    }
+                    | crate::MeshFunction::SetPrimitive {
+                        ref mut index,
+                        ref mut value,
+                    } => {
+                        adjust(index);
+                        adjust(value);
+                    }
+                },
+                S::RayQuery {
+                    ref mut query,
+                    ref mut fun,
+                } => {
+                    adjust(query);
+                    match *fun {
+                        crate::RayQueryFunction::Initialize {
+                            ref mut acceleration_structure,
+                            ref mut descriptor,
+                        } => {
+                            adjust(acceleration_structure);
+                            adjust(descriptor);
+                        }
+                        crate::RayQueryFunction::Proceed { ref mut result } => adjust(result),
+                        crate::RayQueryFunction::Terminate => {}
+                    }
+                }
+            }
+        }
+        // drop the emits of removed expressions
+        block.retain(|statement| match *statement {
+            S::Emit(ref range) => range.clone().next().is_some(),
+            _ => true,
+        });
+    }
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    #[test]
+    fn compact_unused() {
+        let source = "
+            struct Unused { a: vec3<f32>; };
+            let UNUSED: u32 = 7u;
+            let SCALE: f32 = 2.0;
+            var<private> unused: Unused;
+            var<private> counter: u32;
+
+            fn dead() -> f32 {
+                return f32(UNUSED);
+            }
+
+            fn scale(x: f32) -> f32 {
+                return x * SCALE;
+            }
+
+            [[stage(compute), workgroup_size(1)]]
+            fn main() {
+                var unused_local: vec2<f32>;
+                let value = scale(1.0) + 1.0;
+                counter = counter + 1u;
+            }
+        ";
+        let mut module = crate::front::wgsl::parse_str(source).unwrap();
+        super::compact(&mut module);
+
+        let names = |arena: &crate::Arena<crate::GlobalVariable>| {
+            arena
+                .iter()
+                .map(|(_, v)| v.name.clone().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&module.global_variables), ["counter"]);
+        assert_eq!(module.functions.len(), 1);
+        assert!(module
+            .constants
+            .iter()
+            .all(|(_, c)| c.name.as_deref() != Some("UNUSED")));
+        assert!(module
+            .types
+            .iter()
+            .all(|(_, t)| t.name.as_deref() != Some("Unused")));
+        let function = &module.entry_points[0].function;
+        assert!(function.local_variables.is_empty());
+        // named expressions are kept even if unused
+        assert!(function
+            .named_expressions
+            .values()
+            .any(|name| name == "value"));
+
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .unwrap();
+    }
+
+    #[test]
+    fn extract_entry_point() {
+        let source = "
+            [[block]]
+            struct Globals { offset: vec4<f32>; };
+            [[group(0), binding(0)]] var<uniform> globals: Globals;
+            [[group(0), binding(1)]] var color_texture: texture_2d<f32>;
+
+            [[stage(vertex)]]
+            fn vs_main() -> [[builtin(position)]] vec4<f32> {
+                return globals.offset;
+            }
+
+            [[stage(fragment)]]
+            fn fs_main() -> [[location(0)]] vec4<f32> {
+                return textureLoad(color_texture, vec2<i32>(0, 0), 0);
+            }
+        ";
+        let module = crate::front::wgsl::parse_str(source).unwrap();
+        assert!(
+            super::extract_entry_point(&module, "vs_main", crate::ShaderStage::Fragment).is_none()
+        );
+        let (extracted, map) =
+            super::extract_entry_point(&module, "fs_main", crate::ShaderStage::Fragment).unwrap();
+
+        assert_eq!(extracted.entry_points.len(), 1);
+        assert_eq!(extracted.global_variables.len(), 1);
+        let (handle, global) = extracted.global_variables.iter().next().unwrap();
+        assert_eq!(global.name.as_deref(), Some("color_texture"));
+        let original = map.global_variables.original(handle);
+        assert_eq!(module.global_variables[original].name, global.name);
+        assert!(extracted
+            .types
+            .iter()
+            .all(|(_, ty)| ty.name.as_deref() != Some("Globals")));
+
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::empty(),
+        )
+        .validate(&extracted)
+        .unwrap();
+    }
+}
@@ -0,0 +1,1304 @@
+use crate::{
+    arena::{Arena, Handle},
+    BinaryOperator, Bytes, Constant, ConstantInner, DerivedConstant, Expression, MathFunction,
+    ScalarKind, ScalarValue, Type, TypeInner, UnaryOperator, VectorSize,
+};
+
+/// Evaluator of constant expressions.
+///
+/// Every intermediate and final value is registered in `constants`, and the
+/// vector types needed by the results, e.g. of a splat or a swizzle, are
+/// registered in `types`.
+#[derive(Debug)]
+pub struct ConstantEvaluator<'a> {
+    pub types: &'a mut Arena<Type>,
+    pub constants: &'a mut Arena<Constant>,
+    pub expressions: &'a Arena<Expression>,
+}
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum ConstantEvaluatorError {
+    #[error("Constants cannot access function arguments")]
+    FunctionArg,
+    #[error("Constants cannot access global variables")]
+    GlobalVariable,
+    #[error("Constants cannot access local variables")]
+    LocalVariable,
+    #[error("Cannot get the array length of a non array type")]
+    InvalidArrayLengthArg,
+    #[error("Constants cannot get the array length of a dynamically sized array")]
+    ArrayLengthDynamic,
+    #[error("Constants cannot call functions")]
+    Call,
+    #[error("Constants don't support atomic functions")]
+    Atomic,
+    #[error("Constants don't support subgroup operations")]
+    Subgroup,
+    #[error("Constants don't support ray queries")]
+    RayQuery,
+    #[error("Constants don't support relational functions")]
+    Relational,
+    #[error("Constants don't support derivative functions")]
+    Derivative,
+    #[error("Constants don't support load expressions")]
+    Load,
+    #[error("Constants don't support image expressions")]
+    ImageExpression,
+    #[error("Cannot access the type")]
+    InvalidAccessBase,
+    #[error("Cannot access at the index")]
+    InvalidAccessIndex,
+    #[error("Cannot access with index of type")]
+    InvalidAccessIndexTy,
+    #[error("Cannot cast type")]
+    InvalidCastArg,
+    #[error("Cannot apply the unary op to the argument")]
+    InvalidUnaryOpArg,
+    #[error("Cannot apply the binary op to the arguments")]
+    InvalidBinaryOpArgs,
+    #[error("Cannot apply the math function to the arguments")]
+    InvalidMathArg,
+    #[error("Cannot select between the arguments")]
+    InvalidSelectArgs,
+    #[error("Composite operands don't have the same number of components")]
+    ComponentCountMismatch,
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("Not implemented: {0}")]
+    NotImplemented(String),
+}
+
+type Error = ConstantEvaluatorError;
+
+/// Operation on the scalar components of the operands.
+///
+/// It also receives the width of the first operand.
+type ScalarFn<'f> = &'f dyn Fn(&[ScalarValue], Bytes) -> Result<ScalarValue, Error>;
+
+impl<'a> ConstantEvaluator<'a> {
+    /// Evaluate the expression `expr`, and return the constant holding its value.
+    pub fn eval(&mut self, expr: Handle<Expression>) -> Result<Handle<Constant>, Error> {
+        match self.expressions[expr] {
+            Expression::Constant(constant) => Ok(constant),
+            Expression::AccessIndex { base, index } => {
+                let base = self.eval(base)?;
+                self.access(base, index as usize)
+            }
+            Expression::Access { base, index } => {
+                let base = self.eval(base)?;
+                let index = self.eval(index)?;
+                let index = self.constant_index(index)?;
+                self.access(base, index)
+            }
+            Expression::Splat { size, value } => {
+                let value = self.eval(value)?;
+                let (kind, width) = match self.constants[value].inner {
+                    ConstantInner::Scalar { width, ref value } => (value.scalar_kind(), width),
+                    ConstantInner::Composite { .. } => return Err(Error::InvalidCastArg),
+                };
+                let ty = self.vector_type(size, kind, width);
+                Ok(self.register(ConstantInner::Composite {
+                    ty,
+                    components: vec![value; size as usize],
+                }))
+            }
+            Expression::Swizzle {
+                size,
+                vector,
+                pattern,
+            } => {
+                let vector = self.eval(vector)?;
+                let (ty, components) = match self.constants[vector].inner {
+                    ConstantInner::Composite { ty, ref components } => match self.types[ty].inner {
+                        TypeInner::Vector { kind, width, .. } => {
+                            let components = pattern[..size as usize]
+                                .iter()
+                                .map(|&sc| components.get(sc as usize).copied())
+                                .collect::<Option<Vec<_>>>()
+                                .ok_or(Error::InvalidAccessIndex)?;
+                            (self.vector_type(size, kind, width), components)
+                        }
+                        _ => return Err(Error::InvalidAccessBase),
+                    },
+                    ConstantInner::Scalar { .. } => return Err(Error::InvalidAccessBase),
+                };
+                Ok(self.register(ConstantInner::Composite { ty, components }))
+            }
+            Expression::Compose {
+                ty,
+                components: ref component_exprs,
+            } => {
+                let is_vector = match self.types[ty].inner {
+                    TypeInner::Vector { .. } => true,
+                    _ => false,
+                };
+                let mut components = Vec::with_capacity(component_exprs.len());
+                for &expr in component_exprs {
+                    let component = self.eval(expr)?;
+                    match self.constants[component].inner {
+                        // vectors can be composed of smaller vectors
+                        ConstantInner::Composite {
+                            components: ref inner,
+                            ..
+                        } if is_vector => components.extend_from_slice(inner),
+                        _ => components.push(component),
+                    }
+                }
+                Ok(self.register(ConstantInner::Composite { ty, components }))
+            }
+            Expression::Unary { op, expr } => {
+                let value = self.eval(expr)?;
+                self.map_scalars(&[value], None, &|args, _| unary_op(op, args[0]))
+            }
+            Expression::Binary { op, left, right } => {
+                let left = self.eval(left)?;
+                let right = self.eval(right)?;
+                if op == BinaryOperator::Multiply
+                    && (self.is_matrix(left) || self.is_matrix(right))
+                    && !(self.is_scalar(left) || self.is_scalar(right))
+                {
+                    return Err(Error::NotImplemented("matrix multiplication".to_string()));
+                }
+                self.map_scalars(&[left, right], None, &|args, _| {
+                    binary_op(op, args[0], args[1])
+                })
+            }
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                let condition = self.eval(condition)?;
+                let accept = self.eval(accept)?;
+                let reject = self.eval(reject)?;
+                self.map_scalars(
+                    &[condition, accept, reject],
+                    None,
+                    &|args, _| match args[0] {
+                        ScalarValue::Bool(true) => Ok(args[1]),
+                        ScalarValue::Bool(false) => Ok(args[2]),
+                        _ => Err(Error::InvalidSelectArgs),
+                    },
+                )
+            }
+            Expression::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+                arg3,
+            } => {
+                let mut args = vec![self.eval(arg)?];
+                for &extra in [arg1, arg2, arg3].iter() {
+                    if let Some(expr) = extra {
+                        args.push(self.eval(expr)?);
+                    }
+                }
+                self.math(fun, &args)
+            }
+            Expression::As {
+                expr,
+                kind,
+                convert,
+            } => {
+                let value = self.eval(expr)?;
+                match convert {
+                    Some(width) => self.map_scalars(&[value], Some(width), &|args, _| {
+                        Ok(cast(args[0], kind, width))
+                    }),
+                    None => self
+                        .map_scalars(&[value], None, &|args, width| bitcast(args[0], kind, width)),
+                }
+            }
+            Expression::ArrayLength(expr) => {
+                let array = self.eval(expr)?;
+
+                match self.constants[array].inner {
+                    ConstantInner::Scalar { .. } => Err(Error::InvalidArrayLengthArg),
+                    ConstantInner::Composite { ty, .. } => match self.types[ty].inner {
+                        TypeInner::Array { size, .. } => match size {
+                            crate::ArraySize::Constant(constant) => Ok(constant),
+                            crate::ArraySize::Dynamic => Err(Error::ArrayLengthDynamic),
+                        },
+                        _ => Err(Error::InvalidArrayLengthArg),
+                    },
+                }
+            }
+
+            Expression::Load { .. } => Err(Error::Load),
+            Expression::LocalVariable(_) => Err(Error::LocalVariable),
+            Expression::Derivative { .. } => Err(Error::Derivative),
+            Expression::Relational { .. } => Err(Error::Relational),
+            Expression::Call { .. } => Err(Error::Call),
+            Expression::AtomicResult { .. } => Err(Error::Atomic),
+            Expression::SubgroupBallotResult | Expression::SubgroupOperationResult { .. } => {
+                Err(Error::Subgroup)
+            }
+            Expression::RayQueryProceedResult | Expression::RayQueryGetIntersection { .. } => {
+                Err(Error::RayQuery)
+            }
+            Expression::FunctionArgument(_) => Err(Error::FunctionArg),
+            Expression::GlobalVariable(_) => Err(Error::GlobalVariable),
+            Expression::ImageSample { .. }
+            | Expression::ImageLoad { .. }
+            | Expression::ImageQuery { .. } => Err(Error::ImageExpression),
+        }
+    }
+
+    /// Evaluate the expression `expr` like [`eval`](Self::eval), and keep track
+    /// of the values depending on specializable constants.
+    ///
+    /// Such a value is computed with the current values of the specializable
+    /// constants, and is given a constant of its own, whose expression is
+    /// recorded in `derived`, so that [`specialize`] can evaluate it again.
+    ///
+    /// [`specialize`]: super::specialize
+    pub fn eval_derived(
+        &mut self,
+        expr: Handle<Expression>,
+        derived: &mut Vec<DerivedConstant>,
+    ) -> Result<Handle<Constant>, Error> {
+        let handle = self.eval(expr)?;
+        if !self.is_specializable(expr, derived) {
+            return Ok(handle);
+        }
+        let inner = self.constants[handle].inner.clone();
+        let constant = self.constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner,
+        });
+        derived.push(DerivedConstant {
+            constant,
+            expressions: self.expressions.clone(),
+            root: expr,
+        });
+        Ok(constant)
+    }
+
+    /// Check if the value of `expr` depends on specializable constants,
+    /// directly or through the `derived` ones.
+    fn is_specializable(&self, expr: Handle<Expression>, derived: &[DerivedConstant]) -> bool {
+        match self.expressions[expr] {
+            Expression::Constant(constant) => self.is_specializable_constant(constant, derived),
+            Expression::AccessIndex { base: expr, .. }
+            | Expression::Splat { value: expr, .. }
+            | Expression::Swizzle { vector: expr, .. }
+            | Expression::Unary { expr, .. }
+            | Expression::As { expr, .. }
+            | Expression::ArrayLength(expr) => self.is_specializable(expr, derived),
+            Expression::Access { base, index } => {
+                self.is_specializable(base, derived) || self.is_specializable(index, derived)
+            }
+            Expression::Binary { left, right, .. } => {
+                self.is_specializable(left, derived) || self.is_specializable(right, derived)
+            }
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => [condition, accept, reject]
+                .iter()
+                .any(|&expr| self.is_specializable(expr, derived)),
+            Expression::Math {
+                arg,
+                arg1,
+                arg2,
+                arg3,
+                ..
+            } => [Some(arg), arg1, arg2, arg3]
+                .iter()
+                .flatten()
+                .any(|&expr| self.is_specializable(expr, derived)),
+            Expression::Compose { ref components, .. } => components
+                .iter()
+                .any(|&expr| self.is_specializable(expr, derived)),
+            _ => false,
+        }
+    }
+
+    fn is_specializable_constant(
+        &self,
+        handle: Handle<Constant>,
+        derived: &[DerivedConstant],
+    ) -> bool {
+        let constant = &self.constants[handle];
+        if constant.specialization.is_some() || derived.iter().any(|d| d.constant == handle) {
+            return true;
+        }
+        match constant.inner {
+            ConstantInner::Scalar { .. } => false,
+            ConstantInner::Composite { ref components, .. } => components
+                .iter()
+                .any(|&component| self.is_specializable_constant(component, derived)),
+        }
+    }
+
+    fn register(&mut self, inner: ConstantInner) -> Handle<Constant> {
+        self.constants.fetch_or_append(Constant {
+            name: None,
+            specialization: None,
+            inner,
+        })
+    }
+
+    fn vector_type(&mut self, size: VectorSize, kind: ScalarKind, width: Bytes) -> Handle<Type> {
+        self.types.fetch_if_or_append(
+            Type {
+                name: None,
+                inner: TypeInner::Vector { size, kind, width },
+            },
+            |a, b| a.inner == b.inner,
+        )
+    }
+
+    fn is_scalar(&self, constant: Handle<Constant>) -> bool {
+        match self.constants[constant].inner {
+            ConstantInner::Scalar { .. } => true,
+            ConstantInner::Composite { .. } => false,
+        }
+    }
+
+    fn is_matrix(&self, constant: Handle<Constant>) -> bool {
+        match self.constants[constant].inner {
+            ConstantInner::Composite { ty, .. } => match self.types[ty].inner {
+                TypeInner::Matrix { .. } => true,
+                _ => false,
+            },
+            ConstantInner::Scalar { .. } => false,
+        }
+    }
+
+    fn access(&mut self, base: Handle<Constant>, index: usize) -> Result<Handle<Constant>, Error> {
+        match self.constants[base].inner {
+            ConstantInner::Scalar { .. } => Err(Error::InvalidAccessBase),
+            ConstantInner::Composite { ty, ref components } => {
+                match self.types[ty].inner {
+                    TypeInner::Vector { .. }
+                    | TypeInner::Matrix { .. }
+                    | TypeInner::Array { .. }
+                    | TypeInner::Struct { .. } => (),
+                    _ => return Err(Error::InvalidAccessBase),
+                }
+
+                components
+                    .get(index)
+                    .copied()
+                    .ok_or(Error::InvalidAccessIndex)
+            }
+        }
+    }
+
+    fn constant_index(&self, constant: Handle<Constant>) -> Result<usize, Error> {
+        match self.constants[constant].inner {
+            ConstantInner::Scalar {
+                value: ScalarValue::Uint(index),
+                ..
+            } => Ok(index as usize),
+            ConstantInner::Scalar {
+                value: ScalarValue::Sint(index),
+                ..
+            } if index >= 0 => Ok(index as usize),
+            _ => Err(Error::InvalidAccessIndexTy),
+        }
+    }
+
+    /// Apply `fun` to the scalar components of `args`, component-wise.
+    ///
+    /// Scalar arguments are broadcast to the size of the composite ones. The
+    /// result has the width of the first argument of the same kind, unless
+    /// `width` is provided.
+    fn map_scalars(
+        &mut self,
+        args: &[Handle<Constant>],
+        width: Option<Bytes>,
+        fun: ScalarFn,
+    ) -> Result<Handle<Constant>, Error> {
+        let composite = args
+            .iter()
+            .find_map(|&arg| match self.constants[arg].inner {
+                ConstantInner::Composite { ty, ref components } => Some((ty, components.len())),
+                ConstantInner::Scalar { .. } => None,
+            });
+
+        let (ty, count) = match composite {
+            Some(composite) => composite,
+            None => {
+                let mut values = Vec::with_capacity(args.len());
+                let mut widths = Vec::with_capacity(args.len());
+                for &arg in args {
+                    if let ConstantInner::Scalar { width, value } = self.constants[arg].inner {
+                        values.push(value);
+                        widths.push(width);
+                    }
+                }
+                let value = fun(&values, widths[0])?;
+                let kind = value.scalar_kind();
+                let width = match width {
+                    _ if kind == ScalarKind::Bool => crate::BOOL_WIDTH,
+                    Some(width) => width,
+                    None => values
+                        .iter()
+                        .zip(widths.iter())
+                        .find(|&(value, _)| value.scalar_kind() == kind)
+                        .map_or(widths[0], |(_, &width)| width),
+                };
+                return Ok(self.register(scalar(value, width)));
+            }
+        };
+
+        let mut components = Vec::with_capacity(count);
+        for index in 0..count {
+            let mut component_args = Vec::with_capacity(args.len());
+            for &arg in args {
+                component_args.push(match self.constants[arg].inner {
+                    ConstantInner::Composite { ref components, .. } => {
+                        if components.len() != count {
+                            return Err(Error::ComponentCountMismatch);
+                        }
+                        components[index]
+                    }
+                    ConstantInner::Scalar { .. } => arg,
+                });
+            }
+            components.push(self.map_scalars(&component_args, width, fun)?);
+        }
+
+        // the kind of the components may have changed
+        let ty = match (&self.types[ty].inner, &self.constants[components[0]].inner) {
+            (
+                &TypeInner::Vector { size, .. },
+                &ConstantInner::Scalar {
+                    width,
+                    value: ref sample,
+                },
+            ) => self.vector_type(size, sample.scalar_kind(), width),
+            (&TypeInner::Matrix { columns, rows, .. }, &ConstantInner::Composite { ty, .. }) => {
+                match self.types[ty].inner {
+                    TypeInner::Vector {
+                        kind: ScalarKind::Float,
+                        width,
+                        ..
+                    } => self.types.fetch_if_or_append(
+                        Type {
+                            name: None,
+                            inner: TypeInner::Matrix {
+                                columns,
+                                rows,
+                                width,
+                            },
+                        },
+                        |a, b| a.inner == b.inner,
+                    ),
+                    _ => return Err(Error::InvalidBinaryOpArgs),
+                }
+            }
+            _ => return Err(Error::InvalidCastArg),
+        };
+
+        Ok(self.register(ConstantInner::Composite { ty, components }))
+    }
+
+    /// Return the floating-point components of a scalar or vector constant,
+    /// along with its width and vector type.
+    fn floats(&self, constant: Handle<Constant>) -> Result<FloatVector, Error> {
+        let (ty, handles) = match self.constants[constant].inner {
+            ConstantInner::Scalar { .. } => (None, vec![constant]),
+            ConstantInner::Composite { ty, ref components } => (Some(ty), components.clone()),
+        };
+        let mut values = Vec::with_capacity(handles.len());
+        let mut width = 4;
+        for handle in handles {
+            match self.constants[handle].inner {
+                ConstantInner::Scalar {
+                    width: w,
+                    value: ScalarValue::Float(v),
+                } => {
+                    width = w;
+                    values.push(v);
+                }
+                _ => return Err(Error::InvalidMathArg),
+            }
+        }
+        Ok(FloatVector { ty, width, values })
+    }
+
+    fn register_floats(&mut self, vector: FloatVector) -> Handle<Constant> {
+        let width = vector.width;
+        let mut components = vector
+            .values
+            .into_iter()
+            .map(|v| self.register(scalar(ScalarValue::Float(v), width)))
+            .collect::<Vec<_>>();
+        match vector.ty {
+            Some(ty) => self.register(ConstantInner::Composite { ty, components }),
+            None => components.pop().unwrap(),
+        }
+    }
+
+    fn math(
+        &mut self,
+        fun: MathFunction,
+        args: &[Handle<Constant>],
+    ) -> Result<Handle<Constant>, Error> {
+        use MathFunction as Mf;
+
+        let arg = |index: usize| args.get(index).copied().ok_or(Error::InvalidMathArg);
+
+        match fun {
+            Mf::Dot => {
+                let (a, b) = (self.floats(arg(0)?)?, self.floats(arg(1)?)?);
+                let value = dot(&a.values, &b.values)?;
+                Ok(self.register(scalar(ScalarValue::Float(value), a.width)))
+            }
+            Mf::Length => {
+                let a = self.floats(arg(0)?)?;
+                let value = dot(&a.values, &a.values)?.sqrt();
+                Ok(self.register(scalar(ScalarValue::Float(value), a.width)))
+            }
+            Mf::Distance => {
+                let (a, b) = (self.floats(arg(0)?)?, self.floats(arg(1)?)?);
+                let diff = a.zip_with(&b, |x, y| x - y)?;
+                let value = dot(&diff, &diff)?.sqrt();
+                Ok(self.register(scalar(ScalarValue::Float(value), a.width)))
+            }
+            Mf::Normalize => {
+                let mut a = self.floats(arg(0)?)?;
+                let length = dot(&a.values, &a.values)?.sqrt();
+                for v in a.values.iter_mut() {
+                    *v /= length;
+                }
+                Ok(self.register_floats(a))
+            }
+            Mf::Cross => {
+                let (mut a, b) = (self.floats(arg(0)?)?, self.floats(arg(1)?)?);
+                let (x, y) = match (&a.values[..], &b.values[..]) {
+                    (&[x0, x1, x2], &[y0, y1, y2]) => ([x0, x1, x2], [y0, y1, y2]),
+                    _ => return Err(Error::InvalidMathArg),
+                };
+                a.values = vec![
+                    x[1] * y[2] - x[2] * y[1],
+                    x[2] * y[0] - x[0] * y[2],
+                    x[0] * y[1] - x[1] * y[0],
+                ];
+                Ok(self.register_floats(a))
+            }
+            Mf::Reflect => {
+                let (i, n) = (self.floats(arg(0)?)?, self.floats(arg(1)?)?);
+                let d = dot(&n.values, &i.values)?;
+                let values = i.zip_with(&n, |x, y| x - 2.0 * d * y)?;
+                Ok(self.register_floats(FloatVector { values, ..i }))
+            }
+            Mf::FaceForward => {
+                let (mut n, i, nref) = (
+                    self.floats(arg(0)?)?,
+                    self.floats(arg(1)?)?,
+                    self.floats(arg(2)?)?,
+                );
+                if dot(&nref.values, &i.values)? >= 0.0 {
+                    for v in n.values.iter_mut() {
+                        *v = -*v;
+                    }
+                }
+                Ok(self.register_floats(n))
+            }
+            Mf::Refract => {
+                let (i, n, eta) = (
+                    self.floats(arg(0)?)?,
+                    self.floats(arg(1)?)?,
+                    self.floats(arg(2)?)?,
+                );
+                let eta = match eta.values[..] {
+                    [eta] => eta,
+                    _ => return Err(Error::InvalidMathArg),
+                };
+                let d = dot(&n.values, &i.values)?;
+                let k = 1.0 - eta * eta * (1.0 - d * d);
+                let values = if k < 0.0 {
+                    vec![0.0; i.values.len()]
+                } else {
+                    i.zip_with(&n, |x, y| eta * x - (eta * d + k.sqrt()) * y)?
+                };
+                Ok(self.register_floats(FloatVector { values, ..i }))
+            }
+            Mf::Modf
+            | Mf::Frexp
+            | Mf::Outer
+            | Mf::Inverse
+            | Mf::Transpose
+            | Mf::Determinant
+            | Mf::Pack4x8snorm
+            | Mf::Pack4x8unorm
+            | Mf::Pack2x16snorm
+            | Mf::Pack2x16unorm
+            | Mf::Pack2x16float
+            | Mf::Unpack4x8snorm
+            | Mf::Unpack4x8unorm
+            | Mf::Unpack2x16snorm
+            | Mf::Unpack2x16unorm
+            | Mf::Unpack2x16float => Err(Error::NotImplemented(format!("{:?}", fun))),
+            _ => self.map_scalars(args, None, &|values, width| math_scalar(fun, values, width)),
+        }
+    }
+}
+
+/// Floating-point scalar or vector, unpacked from a constant.
+struct FloatVector {
+    ty: Option<Handle<Type>>,
+    width: Bytes,
+    values: Vec<f64>,
+}
+
+impl FloatVector {
+    fn zip_with(&self, other: &Self, fun: impl Fn(f64, f64) -> f64) -> Result<Vec<f64>, Error> {
+        if self.values.len() != other.values.len() {
+            return Err(Error::ComponentCountMismatch);
+        }
+        Ok(self
+            .values
+            .iter()
+            .zip(other.values.iter())
+            .map(|(&x, &y)| fun(x, y))
+            .collect())
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> Result<f64, Error> {
+    if a.len() != b.len() {
+        return Err(Error::ComponentCountMismatch);
+    }
+    Ok(a.iter().zip(b.iter()).map(|(&x, &y)| x * y).sum())
+}
+
+/// Make a scalar constant, wrapping or rounding `value` to `width`.
+fn scalar(value: ScalarValue, width: Bytes) -> ConstantInner {
+    let value = match value {
+        ScalarValue::Sint(v) if width < 8 => ScalarValue::Sint(i64::from(v as i32)),
+        ScalarValue::Uint(v) if width < 8 => ScalarValue::Uint(v & u64::from(!0u32)),
+        ScalarValue::Float(v) if width < 8 => ScalarValue::Float(f64::from(v as f32)),
+        other => other,
+    };
+    ConstantInner::Scalar { width, value }
+}
+
+fn float_to_int(value: f64, min: f64, max: f64) -> f64 {
+    if value.is_nan() {
+        0.0
+    } else {
+        value.max(min).min(max)
+    }
+}
+
+/// Convert `value` to `kind`, clamping floats to the range of integers.
+fn cast(value: ScalarValue, kind: ScalarKind, width: Bytes) -> ScalarValue {
+    match kind {
+        ScalarKind::Sint => ScalarValue::Sint(match value {
+            ScalarValue::Sint(v) => v,
+            ScalarValue::Uint(v) => v as i64,
+            ScalarValue::Float(v) if width < 8 => {
+                float_to_int(v, f64::from(i32::MIN), f64::from(i32::MAX)) as i64
+            }
+            ScalarValue::Float(v) => float_to_int(v, i64::MIN as f64, i64::MAX as f64) as i64,
+            ScalarValue::Bool(v) => v as i64,
+        }),
+        ScalarKind::Uint => ScalarValue::Uint(match value {
+            ScalarValue::Sint(v) => v as u64,
+            ScalarValue::Uint(v) => v,
+            ScalarValue::Float(v) if width < 8 => float_to_int(v, 0.0, f64::from(u32::MAX)) as u64,
+            ScalarValue::Float(v) => float_to_int(v, 0.0, u64::MAX as f64) as u64,
+            ScalarValue::Bool(v) => v as u64,
+        }),
+        ScalarKind::Float => ScalarValue::Float(match value {
+            ScalarValue::Sint(v) => v as f64,
+            ScalarValue::Uint(v) => v as f64,
+            ScalarValue::Float(v) => v,
+            ScalarValue::Bool(v) => {
+                if v {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }),
+        ScalarKind::Bool => ScalarValue::Bool(match value {
+            ScalarValue::Sint(v) => v != 0,
+            ScalarValue::Uint(v) => v != 0,
+            ScalarValue::Float(v) => v != 0.0,
+            ScalarValue::Bool(v) => v,
+        }),
+    }
+}
+
+/// Reinterpret the bits of `value`, keeping its `width`.
+fn bitcast(value: ScalarValue, kind: ScalarKind, width: Bytes) -> Result<ScalarValue, Error> {
+    let bits = match value {
+        ScalarValue::Sint(v) => v as u64,
+        ScalarValue::Uint(v) => v,
+        ScalarValue::Float(v) if width == 4 => u64::from((v as f32).to_bits()),
+        ScalarValue::Float(v) if width == 8 => v.to_bits(),
+        _ => return Err(Error::InvalidCastArg),
+    };
+    Ok(match kind {
+        ScalarKind::Sint => ScalarValue::Sint(bits as i64),
+        ScalarKind::Uint => ScalarValue::Uint(bits),
+        ScalarKind::Float if width == 4 => {
+            ScalarValue::Float(f64::from(f32::from_bits(bits as u32)))
+        }
+        ScalarKind::Float if width == 8 => ScalarValue::Float(f64::from_bits(bits)),
+        _ => return Err(Error::InvalidCastArg),
+    })
+}
+
+fn unary_op(op: UnaryOperator, value: ScalarValue) -> Result<ScalarValue, Error> {
+    Ok(match op {
+        UnaryOperator::Negate => match value {
+            ScalarValue::Sint(v) => ScalarValue::Sint(v.wrapping_neg()),
+            ScalarValue::Float(v) => ScalarValue::Float(-v),
+            _ => return Err(Error::InvalidUnaryOpArg),
+        },
+        UnaryOperator::Not => match value {
+            ScalarValue::Sint(v) => ScalarValue::Sint(!v),
+            ScalarValue::Uint(v) => ScalarValue::Uint(!v),
+            ScalarValue::Bool(v) => ScalarValue::Bool(!v),
+            _ => return Err(Error::InvalidUnaryOpArg),
+        },
+    })
+}
+
+fn binary_op(
+    op: BinaryOperator,
+    left: ScalarValue,
+    right: ScalarValue,
+) -> Result<ScalarValue, Error> {
+    if left.scalar_kind() == right.scalar_kind() {
+        match op {
+            BinaryOperator::Equal => return Ok(ScalarValue::Bool(left == right)),
+            BinaryOperator::NotEqual => return Ok(ScalarValue::Bool(left != right)),
+            BinaryOperator::Less => return Ok(ScalarValue::Bool(left < right)),
+            BinaryOperator::LessEqual => return Ok(ScalarValue::Bool(left <= right)),
+            BinaryOperator::Greater => return Ok(ScalarValue::Bool(left > right)),
+            BinaryOperator::GreaterEqual => return Ok(ScalarValue::Bool(left >= right)),
+            _ => {}
+        }
+    }
+
+    Ok(match (left, right) {
+        (ScalarValue::Sint(a), ScalarValue::Sint(b)) => ScalarValue::Sint(match op {
+            BinaryOperator::Add => a.wrapping_add(b),
+            BinaryOperator::Subtract => a.wrapping_sub(b),
+            BinaryOperator::Multiply => a.wrapping_mul(b),
+            BinaryOperator::Divide => a.checked_div(b).ok_or(Error::DivisionByZero)?,
+            BinaryOperator::Modulo => a.checked_rem(b).ok_or(Error::DivisionByZero)?,
+            BinaryOperator::And => a & b,
+            BinaryOperator::ExclusiveOr => a ^ b,
+            BinaryOperator::InclusiveOr => a | b,
+            _ => return Err(Error::InvalidBinaryOpArgs),
+        }),
+        (ScalarValue::Sint(a), ScalarValue::Uint(b)) => ScalarValue::Sint(match op {
+            BinaryOperator::ShiftLeft => a.wrapping_shl(b as u32),
+            BinaryOperator::ShiftRight => a.wrapping_shr(b as u32),
+            _ => return Err(Error::InvalidBinaryOpArgs),
+        }),
+        (ScalarValue::Uint(a), ScalarValue::Uint(b)) => ScalarValue::Uint(match op {
+            BinaryOperator::Add => a.wrapping_add(b),
+            BinaryOperator::Subtract => a.wrapping_sub(b),
+            BinaryOperator::Multiply => a.wrapping_mul(b),
+            BinaryOperator::Divide => a.checked_div(b).ok_or(Error::DivisionByZero)?,
+            BinaryOperator::Modulo => a.checked_rem(b).ok_or(Error::DivisionByZero)?,
+            BinaryOperator::And => a & b,
+            BinaryOperator::ExclusiveOr => a ^ b,
+            BinaryOperator::InclusiveOr => a | b,
+            BinaryOperator::ShiftLeft => a.wrapping_shl(b as u32),
+            BinaryOperator::ShiftRight => a.wrapping_shr(b as u32),
+            _ => return Err(Error::InvalidBinaryOpArgs),
+        }),
+        (ScalarValue::Float(a), ScalarValue::Float(b)) => ScalarValue::Float(match op {
+            BinaryOperator::Add => a + b,
+            BinaryOperator::Subtract => a - b,
+            BinaryOperator::Multiply => a * b,
+            BinaryOperator::Divide => a / b,
+            BinaryOperator::Modulo => a % b,
+            _ => return Err(Error::InvalidBinaryOpArgs),
+        }),
+        (ScalarValue::Bool(a), ScalarValue::Bool(b)) => ScalarValue::Bool(match op {
+            BinaryOperator::LogicalAnd => a && b,
+            BinaryOperator::LogicalOr => a || b,
+            BinaryOperator::And => a & b,
+            BinaryOperator::InclusiveOr => a | b,
+            _ => return Err(Error::InvalidBinaryOpArgs),
+        }),
+        _ => return Err(Error::InvalidBinaryOpArgs),
+    })
+}
+
+/// Round half-way cases to the nearest even integer.
+fn round_ties_even(v: f64) -> f64 {
+    let rounded = v.round();
+    if (v - v.trunc()).abs() == 0.5 {
+        2.0 * (v / 2.0).round()
+    } else {
+        rounded
+    }
+}
+
+fn math_scalar(
+    fun: MathFunction,
+    args: &[ScalarValue],
+    width: Bytes,
+) -> Result<ScalarValue, Error> {
+    use MathFunction as Mf;
+    use ScalarValue as Sv;
+
+    let bits = u32::from(width) * 8;
+    let mask = if bits < 64 { (1u64 << bits) - 1 } else { !0 };
+    let arg = |index: usize| args.get(index).copied().ok_or(Error::InvalidMathArg);
+    let float = |index: usize| match arg(index)? {
+        Sv::Float(v) => Ok(v),
+        _ => Err(Error::InvalidMathArg),
+    };
+    let uint = |index: usize| match arg(index)? {
+        Sv::Uint(v) => Ok(v),
+        _ => Err(Error::InvalidMathArg),
+    };
+    // raw bits of an integer argument, within its width
+    let int_bits = |index: usize| match arg(index)? {
+        Sv::Sint(v) => Ok(v as u64 & mask),
+        Sv::Uint(v) => Ok(v & mask),
+        _ => Err(Error::InvalidMathArg),
+    };
+    // integer result with the kind of the first argument
+    let int_result = |value: u64| match args[0] {
+        Sv::Sint(_) => Sv::Sint(value as i64),
+        _ => Sv::Uint(value),
+    };
+
+    Ok(match fun {
+        Mf::Abs => match arg(0)? {
+            Sv::Float(v) => Sv::Float(v.abs()),
+            Sv::Sint(v) => Sv::Sint(v.wrapping_abs()),
+            Sv::Uint(v) => Sv::Uint(v),
+            Sv::Bool(_) => return Err(Error::InvalidMathArg),
+        },
+        Mf::Min | Mf::Max => {
+            let min = fun == Mf::Min;
+            match (arg(0)?, arg(1)?) {
+                (Sv::Float(a), Sv::Float(b)) => Sv::Float(if min { a.min(b) } else { a.max(b) }),
+                (Sv::Sint(a), Sv::Sint(b)) => Sv::Sint(if min { a.min(b) } else { a.max(b) }),
+                (Sv::Uint(a), Sv::Uint(b)) => Sv::Uint(if min { a.min(b) } else { a.max(b) }),
+                _ => return Err(Error::InvalidMathArg),
+            }
+        }
+        Mf::Clamp => match (arg(0)?, arg(1)?, arg(2)?) {
+            (Sv::Float(e), Sv::Float(lo), Sv::Float(hi)) => Sv::Float(e.max(lo).min(hi)),
+            (Sv::Sint(e), Sv::Sint(lo), Sv::Sint(hi)) => Sv::Sint(e.max(lo).min(hi)),
+            (Sv::Uint(e), Sv::Uint(lo), Sv::Uint(hi)) => Sv::Uint(e.max(lo).min(hi)),
+            _ => return Err(Error::InvalidMathArg),
+        },
+        Mf::Cos => Sv::Float(float(0)?.cos()),
+        Mf::Cosh => Sv::Float(float(0)?.cosh()),
+        Mf::Sin => Sv::Float(float(0)?.sin()),
+        Mf::Sinh => Sv::Float(float(0)?.sinh()),
+        Mf::Tan => Sv::Float(float(0)?.tan()),
+        Mf::Tanh => Sv::Float(float(0)?.tanh()),
+        Mf::Acos => Sv::Float(float(0)?.acos()),
+        Mf::Asin => Sv::Float(float(0)?.asin()),
+        Mf::Atan => Sv::Float(float(0)?.atan()),
+        Mf::Atan2 => Sv::Float(float(0)?.atan2(float(1)?)),
+        Mf::Ceil => Sv::Float(float(0)?.ceil()),
+        Mf::Floor => Sv::Float(float(0)?.floor()),
+        Mf::Round => Sv::Float(round_ties_even(float(0)?)),
+        Mf::Fract => {
+            let v = float(0)?;
+            Sv::Float(v - v.floor())
+        }
+        Mf::Trunc => Sv::Float(float(0)?.trunc()),
+        Mf::Ldexp => {
+            let exponent = match arg(1)? {
+                Sv::Sint(v) => v as i32,
+                _ => return Err(Error::InvalidMathArg),
+            };
+            Sv::Float(float(0)? * 2f64.powi(exponent))
+        }
+        Mf::Exp => Sv::Float(float(0)?.exp()),
+        Mf::Exp2 => Sv::Float(float(0)?.exp2()),
+        Mf::Log => Sv::Float(float(0)?.ln()),
+        Mf::Log2 => Sv::Float(float(0)?.log2()),
+        Mf::Pow => Sv::Float(float(0)?.powf(float(1)?)),
+        Mf::Sign => match arg(0)? {
+            Sv::Float(v) if v > 0.0 => Sv::Float(1.0),
+            Sv::Float(v) if v < 0.0 => Sv::Float(-1.0),
+            Sv::Float(_) => Sv::Float(0.0),
+            Sv::Sint(v) => Sv::Sint(v.signum()),
+            _ => return Err(Error::InvalidMathArg),
+        },
+        Mf::Fma => Sv::Float(float(0)?.mul_add(float(1)?, float(2)?)),
+        Mf::Mix => {
+            let (x, y, a) = (float(0)?, float(1)?, float(2)?);
+            Sv::Float(x * (1.0 - a) + y * a)
+        }
+        Mf::Step => Sv::Float(if float(1)? < float(0)? { 0.0 } else { 1.0 }),
+        Mf::SmoothStep => {
+            let (low, high, x) = (float(0)?, float(1)?, float(2)?);
+            let t = (x - low) / (high - low);
+            let t = if t > 1.0 { 1.0 } else { t.max(0.0) };
+            Sv::Float(t * t * (3.0 - 2.0 * t))
+        }
+        Mf::Sqrt => Sv::Float(float(0)?.sqrt()),
+        Mf::InverseSqrt => Sv::Float(1.0 / float(0)?.sqrt()),
+        Mf::CountTrailingZeros => int_result(u64::from(int_bits(0)?.trailing_zeros().min(bits))),
+        Mf::CountLeadingZeros => int_result(u64::from(int_bits(0)?.leading_zeros() - (64 - bits))),
+        Mf::CountOneBits => int_result(u64::from(int_bits(0)?.count_ones())),
+        Mf::ReverseBits => int_result(int_bits(0)?.reverse_bits() >> (64 - bits)),
+        Mf::FirstTrailingBit => {
+            let v = int_bits(0)?;
+            int_result(if v == 0 {
+                mask
+            } else {
+                u64::from(v.trailing_zeros())
+            })
+        }
+        Mf::FirstLeadingBit => {
+            let mut v = int_bits(0)?;
+            // for signed integers, look for the first bit that differs from the sign
+            if let Sv::Sint(s) = args[0] {
+                if s < 0 {
+                    v = !v & mask;
+                }
+            }
+            int_result(if v == 0 {
+                mask
+            } else {
+                u64::from(63 - v.leading_zeros())
+            })
+        }
+        Mf::ExtractBits => {
+            let offset = uint(1)?.min(u64::from(bits));
+            let count = uint(2)?.min(u64::from(bits) - offset);
+            if count == 0 {
+                int_result(0)
+            } else {
+                let value = (int_bits(0)? >> offset) & ((!0u64) >> (64 - count));
+                match args[0] {
+                    // sign-extend from the last extracted bit
+                    Sv::Sint(_) => {
+                        let shift = 64 - count as u32;
+                        Sv::Sint(((value << shift) as i64) >> shift)
+                    }
+                    _ => Sv::Uint(value),
+                }
+            }
+        }
+        Mf::InsertBits => {
+            let offset = uint(2)?.min(u64::from(bits));
+            let count = uint(3)?.min(u64::from(bits) - offset);
+            let field = if count == 0 {
+                0
+            } else {
+                ((!0u64) >> (64 - count)) << offset
+            };
+            let value = (int_bits(0)? & !field) | ((int_bits(1)? << offset) & field);
+            int_result(value)
+        }
+        _ => return Err(Error::NotImplemented(format!("{:?}", fun))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec;
+
+    use crate::{
+        Arena, Constant, ConstantInner, Expression, ScalarKind, ScalarValue, Type, TypeInner,
+        UnaryOperator, VectorSize,
+    };
+
+    use super::ConstantEvaluator;
+
+    #[test]
+    fn unary_op() {
+        let mut types = Arena::new();
+        let mut expressions = Arena::new();
+        let mut constants = Arena::new();
+
+        let vec_ty = types.append(Type {
+            name: None,
+            inner: TypeInner::Vector {
+                size: VectorSize::Bi,
+                kind: ScalarKind::Sint,
+                width: 4,
+            },
+        });
+
+        let h = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Sint(4),
+            },
+        });
+
+        let h1 = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Sint(8),
+            },
+        });
+
+        let vec_h = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Composite {
+                ty: vec_ty,
+                components: vec![h, h1],
+            },
+        });
+
+        let expr = expressions.append(Expression::Constant(h));
+        let expr1 = expressions.append(Expression::Constant(vec_h));
+
+        let root1 = expressions.append(Expression::Unary {
+            op: UnaryOperator::Negate,
+            expr,
+        });
+
+        let root2 = expressions.append(Expression::Unary {
+            op: UnaryOperator::Not,
+            expr,
+        });
+
+        let root3 = expressions.append(Expression::Unary {
+            op: UnaryOperator::Not,
+            expr: expr1,
+        });
+
+        let mut evaluator = ConstantEvaluator {
+            types: &mut types,
+            constants: &mut constants,
+            expressions: &expressions,
+        };
+
+        let res1 = evaluator.eval(root1).unwrap();
+        let res2 = evaluator.eval(root2).unwrap();
+        let res3 = evaluator.eval(root3).unwrap();
+
+        assert_eq!(
+            constants[res1].inner,
+            ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Sint(-4),
+            },
+        );
+
+        assert_eq!(
+            constants[res2].inner,
+            ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Sint(!4),
+            },
+        );
+
+        let res3_inner = &constants[res3].inner;
+
+        match res3_inner {
+            ConstantInner::Composite { ty, components } => {
+                assert_eq!(*ty, vec_ty);
+                let mut components_iter = components.iter().copied();
+                assert_eq!(
+                    constants[components_iter.next().unwrap()].inner,
+                    ConstantInner::Scalar {
+                        width: 4,
+                        value: ScalarValue::Sint(!4),
+                    },
+                );
+                assert_eq!(
+                    constants[components_iter.next().unwrap()].inner,
+                    ConstantInner::Scalar {
+                        width: 4,
+                        value: ScalarValue::Sint(!8),
+                    },
+                );
+                assert!(components_iter.next().is_none());
+            }
+            _ => panic!("Expected vector"),
+        }
+    }
+
+    #[test]
+    fn cast() {
+        let mut expressions = Arena::new();
+        let mut constants = Arena::new();
+
+        let h = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Sint(4),
+            },
+        });
+
+        let expr = expressions.append(Expression::Constant(h));
+
+        let root = expressions.append(Expression::As {
+            expr,
+            kind: ScalarKind::Bool,
+            convert: Some(crate::BOOL_WIDTH),
+        });
+
+        let mut evaluator = ConstantEvaluator {
+            types: &mut Arena::new(),
+            constants: &mut constants,
+            expressions: &expressions,
+        };
+
+        let res = evaluator.eval(root).unwrap();
+
+        assert_eq!(
+            constants[res].inner,
+            ConstantInner::Scalar {
+                width: crate::BOOL_WIDTH,
+                value: ScalarValue::Bool(true),
+            },
+        );
+    }
+
+    #[test]
+    fn access() {
+        let mut types = Arena::new();
+        let mut expressions = Arena::new();
+        let mut constants = Arena::new();
+
+        let matrix_ty = types.append(Type {
+            name: None,
+            inner: TypeInner::Matrix {
+                columns: VectorSize::Bi,
+                rows: VectorSize::Tri,
+                width: 4,
+            },
+        });
+
+        let vec_ty = types.append(Type {
+            name: None,
+            inner: TypeInner::Vector {
+                size: VectorSize::Tri,
+                kind: ScalarKind::Float,
+                width: 4,
+            },
+        });
+
+        let mut vec1_components = Vec::with_capacity(3);
+        let mut vec2_components = Vec::with_capacity(3);
+
+        for i in 0..3 {
+            let h = constants.append(Constant {
+                name: None,
+                specialization: None,
+                inner: ConstantInner::Scalar {
+                    width: 4,
+                    value: ScalarValue::Float(i as f64),
+                },
+            });
+
+            vec1_components.push(h)
+        }
+
+        for i in 3..6 {
+            let h = constants.append(Constant {
+                name: None,
+                specialization: None,
+                inner: ConstantInner::Scalar {
+                    width: 4,
+                    value: ScalarValue::Float(i as f64),
+                },
+            });
+
+            vec2_components.push(h)
+        }
+
+        let vec1 = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Composite {
+                ty: vec_ty,
+                components: vec1_components,
+            },
+        });
+
+        let vec2 = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Composite {
+                ty: vec_ty,
+                components: vec2_components,
+            },
+        });
+
+        let h = constants.append(Constant {
+            name: None,
+            specialization: None,
+            inner: ConstantInner::Composite {
+                ty: matrix_ty,
+                components: vec![vec1, vec2],
+            },
+        });
+
+        let base = expressions.append(Expression::Constant(h));
+        let root1 = expressions.append(Expression::AccessIndex { base, index: 1 });
+        let root2 = expressions.append(Expression::AccessIndex {
+            base: root1,
+            index: 2,
+        });
+
+        let mut evaluator = ConstantEvaluator {
+            types: &mut types,
+            constants: &mut constants,
+            expressions: &expressions,
+        };
+
+        let res1 = evaluator.eval(root1).unwrap();
+        let res2 = evaluator.eval(root2).unwrap();
+
+        let res1_inner = &constants[res1].inner;
+
+        match res1_inner {
+            ConstantInner::Composite { ty, components } => {
+                assert_eq!(*ty, vec_ty);
+                let mut components_iter = components.iter().copied();
+                assert_eq!(
+                    constants[components_iter.next().unwrap()].inner,
+                    ConstantInner::Scalar {
+                        width: 4,
+                        value: ScalarValue::Float(3.),
+                    },
+                );
+                assert_eq!(
+                    constants[components_iter.next().unwrap()].inner,
+                    ConstantInner::Scalar {
+                        width: 4,
+                        value: ScalarValue::Float(4.),
+                    },
+                );
+                assert_eq!(
+                    constants[components_iter.next().unwrap()].inner,
+                    ConstantInner::Scalar {
+                        width: 4,
+                        value: ScalarValue::Float(5.),
+                    },
+                );
+                assert!(components_iter.next().is_none());
+            }
+            _ => panic!("Expected vector"),
+        }
+
+        assert_eq!(
+            constants[res2].inner,
+            ConstantInner::Scalar {
+                width: 4,
+                value: ScalarValue::Float(5.),
+            },
+        );
+    }
+}
@@ -17,7 +17,7 @@ impl crate::TypeInner {
         let known_length = match *self {
             Ti::Vector { size, .. } => size as _,
             Ti::Matrix { columns, .. } => columns as _,
-            Ti::Array { size, .. } => {
+            Ti::Array { size, .. } | Ti::BindingArray { size, .. } => {
                 return size.to_indexable_length(module);
             }
             Ti::ValuePointer {
@@ -31,7 +31,9 @@ impl crate::TypeInner {
                 match *base_inner {
                     Ti::Vector { size, .. } => size as _,
                     Ti::Matrix { columns, .. } => columns as _,
-                    Ti::Array { size, .. } => return size.to_indexable_length(module),
+                    Ti::Array { size, .. } | Ti::BindingArray { size, .. } => {
+                        return size.to_indexable_length(module)
+                    }
                     _ => return Err(ProcError::TypeNotIndexable),
                 }
             }
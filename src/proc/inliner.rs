@@ -0,0 +1,1097 @@
+use crate::{
+    arena::{Arena, Handle, Range},
+    FastHashMap,
+};
+use std::mem;
+
+/// Decides which calls [`inline_functions`] replaces with the callee body.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InlinePolicy {
+    /// Inline all the calls.
+    Always,
+    /// Keep all the calls.
+    Never,
+    /// Inline the calls to functions with at most this many expressions.
+    MaxExpressions(usize),
+}
+
+impl InlinePolicy {
+    fn allows(self, callee: &crate::Function) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::MaxExpressions(max) => callee.expressions.len() <= max,
+        }
+    }
+}
+
+/// Replace the calls chosen by `policy` with the bodies of the callees.
+///
+/// The locals of an inlined callee become locals of the caller, prefixed
+/// with the callee name, and its arguments become the argument expressions
+/// of the call. A `Return` turns into a store to a local holding the result,
+/// followed by a `Break` out of a loop wrapping the callee body, unless it
+/// is the last statement of the callee.
+///
+/// Functions that are no longer called stay in the module,
+/// so this is best followed by [`compact`](super::compact).
+pub fn inline_functions(module: &mut crate::Module, policy: InlinePolicy) {
+    if policy == InlinePolicy::Never {
+        return;
+    }
+    let mut inliner = Inliner {
+        types: &mut module.types,
+        constants: &mut module.constants,
+        policy,
+    };
+
+    // functions only call the ones declared before them,
+    // so the callees have their calls inlined already
+    let handles = module
+        .functions
+        .iter()
+        .map(|(handle, _)| handle)
+        .collect::<Vec<_>>();
+    for handle in handles {
+        let mut function = mem::take(module.functions.get_mut(handle));
+        inliner.inline_calls(&mut function, &module.functions);
+        *module.functions.get_mut(handle) = function;
+    }
+    for ep in module.entry_points.iter_mut() {
+        inliner.inline_calls(&mut ep.function, &module.functions);
+    }
+}
+
+struct Inliner<'a> {
+    types: &'a mut Arena<crate::Type>,
+    constants: &'a mut Arena<crate::Constant>,
+    policy: InlinePolicy,
+}
+
+/// A call to be inlined.
+struct CallSite {
+    function: Handle<crate::Function>,
+    arguments: Vec<Handle<crate::Expression>>,
+    result: Option<Handle<crate::Expression>>,
+}
+
+/// The statements replacing a call.
+struct InlinedCall {
+    body: crate::Block,
+    /// Load of the result, to emit after the body.
+    result: Option<Handle<crate::Expression>>,
+}
+
+/// Where the `Return` statements of an inlined callee go.
+struct ReturnTarget {
+    /// Pointer to the local holding the result.
+    result: Option<Handle<crate::Expression>>,
+    /// Pointer to the local telling that the callee returned,
+    /// if it has to leave nested loops or switches.
+    returned: Option<Handle<crate::Expression>>,
+    /// Constant `true`, to store to `returned`.
+    true_value: Handle<crate::Expression>,
+    /// If the body is wrapped into a loop to break out of.
+    wrapped: bool,
+}
+
+impl<'a> Inliner<'a> {
+    fn inline_calls(&mut self, caller: &mut crate::Function, functions: &Arena<crate::Function>) {
+        let mut sites = Vec::new();
+        collect_calls(&caller.body, functions, self.policy, &mut sites);
+        if sites.iter().all(Option::is_none) {
+            return;
+        }
+        let result_sites = sites
+            .iter()
+            .enumerate()
+            .filter_map(|(index, site)| Some((site.as_ref()?.result?, index)))
+            .collect::<FastHashMap<_, _>>();
+
+        // Rebuild the expressions, putting the ones of a callee in place of
+        // the call result, after the call arguments.
+        let old_expressions = mem::take(&mut caller.expressions);
+        let mut inlined = sites.iter().map(|_| None).collect::<Vec<_>>();
+        let map = map_expressions(
+            &old_expressions,
+            |handle, expression| match result_sites.get(&handle) {
+                Some(&index) => sites[index].as_ref().unwrap().arguments.clone(),
+                None => operands(expression),
+            },
+            |handle, expression, map| match result_sites.get(&handle) {
+                Some(&index) => {
+                    let site = sites[index].as_ref().unwrap();
+                    let arguments = site
+                        .arguments
+                        .iter()
+                        .map(|&argument| mapped(map, argument))
+                        .collect::<Vec<_>>();
+                    let call = self.inline_call(site, &arguments, functions, caller);
+                    let result = call.result.unwrap();
+                    inlined[index] = Some(call);
+                    result
+                }
+                None => {
+                    let mut expression = expression.clone();
+                    map_expression(&mut expression, |old| mapped(map, old));
+                    let span = old_expressions.get_span(handle);
+                    caller.expressions.append_with_span(expression, span)
+                }
+            },
+        );
+        for (site, inlined) in sites.iter().zip(inlined.iter_mut()) {
+            if let Some(ref site) = *site {
+                if site.result.is_none() {
+                    let arguments = site
+                        .arguments
+                        .iter()
+                        .map(|argument| map[argument.index()])
+                        .collect::<Vec<_>>();
+                    *inlined = Some(self.inline_call(site, &arguments, functions, caller));
+                }
+            }
+        }
+
+        caller.named_expressions = caller
+            .named_expressions
+            .drain()
+            .map(|(handle, name)| (map[handle.index()], name))
+            .collect();
+        let body = mem::take(&mut caller.body);
+        let mut rewriter = BlockRewriter {
+            map: &map,
+            first_new: 0,
+            expressions: &mut caller.expressions,
+            target: None,
+            nested: false,
+            returns: 0,
+            inlined: inlined.into_iter(),
+        };
+        caller.body = rewriter.rewrite_block(&body);
+    }
+
+    /// Copy the callee of `site` into `caller`, returning the statements
+    /// to replace the call with.
+    ///
+    /// The `arguments` of the call are the ones of `site` in `caller`.
+    fn inline_call(
+        &mut self,
+        site: &CallSite,
+        arguments: &[Handle<crate::Expression>],
+        functions: &Arena<crate::Function>,
+        caller: &mut crate::Function,
+    ) -> InlinedCall {
+        let callee = &functions[site.function];
+        let prefix = callee.name.as_deref().unwrap_or("inlined");
+        let crate::Function {
+            ref mut local_variables,
+            ref mut expressions,
+            ref mut named_expressions,
+            ..
+        } = *caller;
+        let first_new = expressions.len();
+        let mut prologue = Vec::new();
+
+        let local_map = callee
+            .local_variables
+            .iter()
+            .map(|(_, local)| {
+                local_variables.append(crate::LocalVariable {
+                    name: local
+                        .name
+                        .as_ref()
+                        .map(|name| format!("{}_{}", prefix, name)),
+                    ty: local.ty,
+                    init: local.init,
+                })
+            })
+            .collect::<Vec<_>>();
+        // the locals have to be initialized on every call
+        for ((_, local), &new_local) in callee.local_variables.iter().zip(local_map.iter()) {
+            if let Some(init) = local.init {
+                let pointer = expressions.append(crate::Expression::LocalVariable(new_local));
+                let value = expressions.append(crate::Expression::Constant(init));
+                prologue.push(crate::Statement::Store { pointer, value });
+            }
+        }
+
+        let callee_map = map_expressions(
+            &callee.expressions,
+            |_, expression| operands(expression),
+            |handle, expression, map| match *expression {
+                crate::Expression::FunctionArgument(index) => arguments[index as usize],
+                crate::Expression::LocalVariable(local) => expressions.append_with_span(
+                    crate::Expression::LocalVariable(local_map[local.index()]),
+                    callee.expressions.get_span(handle),
+                ),
+                _ => {
+                    let mut expression = expression.clone();
+                    map_expression(&mut expression, |old| mapped(map, old));
+                    expressions.append_with_span(expression, callee.expressions.get_span(handle))
+                }
+            },
+        );
+        for (&handle, name) in callee.named_expressions.iter() {
+            let new_handle = callee_map[handle.index()];
+            if new_handle.index() >= first_new {
+                named_expressions
+                    .entry(new_handle)
+                    .or_insert_with(|| name.clone());
+            }
+        }
+
+        let result = callee.result.as_ref().map(|result| {
+            let local = local_variables.append(crate::LocalVariable {
+                name: Some(format!("{}_result", prefix)),
+                ty: result.ty,
+                init: None,
+            });
+            expressions.append(crate::Expression::LocalVariable(local))
+        });
+        let mut returns = ReturnScan::default();
+        returns.scan(&callee.body, false, true);
+        let true_value = expressions.append(crate::Expression::Constant(self.bool_constant(true)));
+        let returned = if returns.nested {
+            let ty = self.types.fetch_or_append(crate::Type {
+                name: None,
+                inner: crate::TypeInner::Scalar {
+                    kind: crate::ScalarKind::Bool,
+                    width: crate::BOOL_WIDTH,
+                },
+            });
+            let local = local_variables.append(crate::LocalVariable {
+                name: Some(format!("{}_returned", prefix)),
+                ty,
+                init: None,
+            });
+            let pointer = expressions.append(crate::Expression::LocalVariable(local));
+            let value = expressions.append(crate::Expression::Constant(self.bool_constant(false)));
+            prologue.push(crate::Statement::Store { pointer, value });
+            Some(pointer)
+        } else {
+            None
+        };
+
+        let mut rewriter = BlockRewriter {
+            map: &callee_map,
+            first_new,
+            expressions,
+            target: Some(ReturnTarget {
+                result,
+                returned,
+                true_value,
+                wrapped: returns.early,
+            }),
+            nested: false,
+            returns: 0,
+            inlined: Vec::new().into_iter(),
+        };
+        let mut body = rewriter.rewrite_block(&callee.body);
+        if returns.early {
+            if !matches!(body.last(), Some(statement) if is_terminator(statement)) {
+                body.push(crate::Statement::Break);
+            }
+            body = vec![crate::Statement::Loop {
+                body,
+                continuing: Vec::new(),
+            }];
+        }
+        prologue.extend(body);
+
+        InlinedCall {
+            body: prologue,
+            result: site.result.map(|_| {
+                expressions.append(crate::Expression::Load {
+                    pointer: result.unwrap(),
+                })
+            }),
+        }
+    }
+
+    fn bool_constant(&mut self, value: bool) -> Handle<crate::Constant> {
+        self.constants.fetch_or_append(crate::Constant {
+            name: None,
+            specialization: None,
+            inner: crate::ConstantInner::Scalar {
+                width: crate::BOOL_WIDTH,
+                value: crate::ScalarValue::Bool(value),
+            },
+        })
+    }
+}
+
+/// Collect the calls in `block`, in the order of [`BlockRewriter`],
+/// with `None` for the ones to keep.
+fn collect_calls(
+    block: &[crate::Statement],
+    functions: &Arena<crate::Function>,
+    policy: InlinePolicy,
+    sites: &mut Vec<Option<CallSite>>,
+) {
+    use crate::Statement as S;
+    for statement in block {
+        match *statement {
+            S::Block(ref block) => collect_calls(block, functions, policy, sites),
+            S::If {
+                ref accept,
+                ref reject,
+                ..
+            } => {
+                collect_calls(accept, functions, policy, sites);
+                collect_calls(reject, functions, policy, sites);
+            }
+            S::Switch {
+                ref cases,
+                ref default,
+                ..
+            } => {
+                for case in cases.iter() {
+                    collect_calls(&case.body, functions, policy, sites);
+                }
+                collect_calls(default, functions, policy, sites);
+            }
+            S::Loop {
+                ref body,
+                ref continuing,
+            } => {
+                collect_calls(body, functions, policy, sites);
+                collect_calls(continuing, functions, policy, sites);
+            }
+            S::Call {
+                function,
+                ref arguments,
+                result,
+            } => sites.push(if policy.allows(&functions[function]) {
+                Some(CallSite {
+                    function,
+                    arguments: arguments.clone(),
+                    result,
+                })
+            } else {
+                None
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// Where a function returns.
+#[derive(Default)]
+struct ReturnScan {
+    /// There is a `Return` before the end of the body.
+    early: bool,
+    /// There is a `Return` inside of a loop or a switch.
+    nested: bool,
+}
+
+impl ReturnScan {
+    /// Scan a `block`, which is the whole function body if `top_level`.
+    fn scan(&mut self, block: &[crate::Statement], nested: bool, top_level: bool) {
+        use crate::Statement as S;
+        for (index, statement) in block.iter().enumerate() {
+            match *statement {
+                S::Block(ref block) => self.scan(block, nested, false),
+                S::If {
+                    ref accept,
+                    ref reject,
+                    ..
+                } => {
+                    self.scan(accept, nested, false);
+                    self.scan(reject, nested, false);
+                }
+                S::Switch {
+                    ref cases,
+                    ref default,
+                    ..
+                } => {
+                    for case in cases.iter() {
+                        self.scan(&case.body, true, false);
+                    }
+                    self.scan(default, true, false);
+                }
+                S::Loop { ref body, .. } => self.scan(body, true, false),
+                S::Return { .. } => {
+                    self.nested |= nested;
+                    self.early |= !top_level || index + 1 != block.len();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn is_terminator(statement: &crate::Statement) -> bool {
+    matches!(
+        *statement,
+        crate::Statement::Break
+            | crate::Statement::Continue
+            | crate::Statement::Return { .. }
+            | crate::Statement::Kill
+    )
+}
+
+/// Copies statements, mapping their expressions.
+struct BlockRewriter<'a> {
+    map: &'a [Handle<crate::Expression>],
+    /// Mapped expressions before this one are emitted already.
+    first_new: usize,
+    expressions: &'a mut Arena<crate::Expression>,
+    /// Set when rewriting an inlined callee.
+    target: Option<ReturnTarget>,
+    /// If the current block is inside of a loop or a switch of the callee.
+    nested: bool,
+    /// Number of the `Return` statements rewritten.
+    returns: usize,
+    /// Calls to replace, in the order of the `Call` statements.
+    inlined: std::vec::IntoIter<Option<InlinedCall>>,
+}
+
+impl<'a> BlockRewriter<'a> {
+    fn rewrite_block(&mut self, block: &[crate::Statement]) -> crate::Block {
+        let mut new_block = Vec::with_capacity(block.len());
+        for statement in block {
+            self.rewrite_statement(statement, &mut new_block);
+        }
+        new_block
+    }
+
+    fn rewrite_nested_block(&mut self, block: &[crate::Statement]) -> crate::Block {
+        let nested = mem::replace(&mut self.nested, true);
+        let new_block = self.rewrite_block(block);
+        self.nested = nested;
+        new_block
+    }
+
+    /// Add an exit out of the current loop or switch if the callee returned.
+    fn check_returned(&mut self, returns_before: usize, block: &mut crate::Block) {
+        let returned = match self.target {
+            Some(ReturnTarget {
+                returned: Some(pointer),
+                ..
+            }) if self.returns != returns_before => pointer,
+            _ => return,
+        };
+        let condition = self
+            .expressions
+            .append(crate::Expression::Load { pointer: returned });
+        block.push(crate::Statement::Emit(Range::new_from_bounds(
+            condition, condition,
+        )));
+        block.push(crate::Statement::If {
+            condition,
+            accept: vec![crate::Statement::Break],
+            reject: Vec::new(),
+        });
+    }
+
+    fn rewrite_statement(&mut self, statement: &crate::Statement, block: &mut crate::Block) {
+        use crate::Statement as S;
+        let map = self.map;
+        let adjust = |handle: Handle<crate::Expression>| map[handle.index()];
+        match *statement {
+            S::Emit(ref range) => {
+                // split the range where the mapped expressions aren't contiguous
+                let mut bounds: Option<(Handle<_>, Handle<_>)> = None;
+                for handle in range.clone().map(adjust) {
+                    if handle.index() < self.first_new {
+                        continue;
+                    }
+                    bounds = match bounds {
+                        Some((first, last)) if last.index() + 1 == handle.index() => {
+                            Some((first, handle))
+                        }
+                        Some((first, last)) => {
+                            block.push(S::Emit(Range::new_from_bounds(first, last)));
+                            Some((handle, handle))
+                        }
+                        None => Some((handle, handle)),
+                    };
+                }
+                if let Some((first, last)) = bounds {
+                    block.push(S::Emit(Range::new_from_bounds(first, last)));
+                }
+            }
+            S::Block(ref inner) => {
+                let inner = self.rewrite_block(inner);
+                block.push(S::Block(inner));
+            }
+            S::If {
+                condition,
+                ref accept,
+                ref reject,
+            } => {
+                let accept = self.rewrite_block(accept);
+                let reject = self.rewrite_block(reject);
+                block.push(S::If {
+                    condition: adjust(condition),
+                    accept,
+                    reject,
+                });
+            }
+            S::Switch {
+                selector,
+                ref cases,
+                ref default,
+            } => {
+                let returns_before = self.returns;
+                let cases = cases
+                    .iter()
+                    .map(|case| crate::SwitchCase {
+                        value: case.value,
+                        body: self.rewrite_nested_block(&case.body),
+                        fall_through: case.fall_through,
+                    })
+                    .collect();
+                let default = self.rewrite_nested_block(default);
+                block.push(S::Switch {
+                    selector: adjust(selector),
+                    cases,
+                    default,
+                });
+                self.check_returned(returns_before, block);
+            }
+            S::Loop {
+                ref body,
+                ref continuing,
+            } => {
+                let returns_before = self.returns;
+                let body = self.rewrite_nested_block(body);
+                let continuing = self.rewrite_nested_block(continuing);
+                block.push(S::Loop { body, continuing });
+                self.check_returned(returns_before, block);
+            }
+            S::Return { value } => {
+                let target = match self.target {
+                    Some(ref target) => target,
+                    None => {
+                        block.push(S::Return {
+                            value: value.map(adjust),
+                        });
+                        return;
+                    }
+                };
+                self.returns += 1;
+                if let (Some(pointer), Some(value)) = (target.result, value) {
+                    block.push(S::Store {
+                        pointer,
+                        value: adjust(value),
+                    });
+                }
+                if self.nested {
+                    block.push(S::Store {
+                        pointer: target.returned.unwrap(),
+                        value: target.true_value,
+                    });
+                }
+                if target.wrapped {
+                    block.push(S::Break);
+                }
+            }
+            S::Call {
+                function,
+                ref arguments,
+                result,
+            } => match self.inlined.next() {
+                Some(Some(inlined)) => {
+                    block.push(S::Block(inlined.body));
+                    if let Some(result) = inlined.result {
+                        block.push(S::Emit(Range::new_from_bounds(result, result)));
+                    }
+                }
+                _ => block.push(S::Call {
+                    function,
+                    arguments: arguments.iter().cloned().map(adjust).collect(),
+                    result: result.map(adjust),
+                }),
+            },
+            _ => {
+                let mut statement = statement.clone();
+                map_statement(&mut statement, adjust);
+                block.push(statement);
+            }
+        }
+    }
+}
+
+/// Map every expression of `expressions` to the one `append` adds for it.
+///
+/// The expressions are appended after the ones returned by `dependencies`,
+/// even if those come later in the arena. `append` is given the ones
+/// mapped so far, to look them up with [`mapped`].
+fn map_expressions<D, A>(
+    expressions: &Arena<crate::Expression>,
+    dependencies: D,
+    mut append: A,
+) -> Vec<Handle<crate::Expression>>
+where
+    D: Fn(Handle<crate::Expression>, &crate::Expression) -> Vec<Handle<crate::Expression>>,
+    A: FnMut(
+        Handle<crate::Expression>,
+        &crate::Expression,
+        &[Option<Handle<crate::Expression>>],
+    ) -> Handle<crate::Expression>,
+{
+    let mut map = vec![None; expressions.len()];
+    let mut stack = Vec::new();
+    for (handle, _) in expressions.iter() {
+        stack.push(handle);
+        while let Some(&top) = stack.last() {
+            if map[top.index()].is_some() {
+                stack.pop();
+                continue;
+            }
+            let expression = &expressions[top];
+            // the dependencies on the stack already form a cycle,
+            // which a valid module doesn't have
+            let pending = dependencies(top, expression)
+                .into_iter()
+                .filter(|dep| map[dep.index()].is_none() && !stack.contains(dep))
+                .collect::<Vec<_>>();
+            if pending.is_empty() {
+                map[top.index()] = Some(append(top, expression, &map));
+                stack.pop();
+            } else {
+                stack.extend(pending);
+            }
+        }
+    }
+    map.into_iter().map(Option::unwrap).collect()
+}
+
+/// Look up an expression mapped by [`map_expressions`].
+fn mapped(
+    map: &[Option<Handle<crate::Expression>>],
+    handle: Handle<crate::Expression>,
+) -> Handle<crate::Expression> {
+    map[handle.index()].expect("expressions form a cycle")
+}
+
+/// Return the expression handles used by `expression`.
+fn operands(expression: &crate::Expression) -> Vec<Handle<crate::Expression>> {
+    let operands = std::cell::RefCell::new(Vec::new());
+    map_expression(&mut expression.clone(), |handle| {
+        operands.borrow_mut().push(handle);
+        handle
+    });
+    operands.into_inner()
+}
+
+/// Map the expression handles used by `expression`.
+fn map_expression<F>(expression: &mut crate::Expression, map: F)
+where
+    F: Fn(Handle<crate::Expression>) -> Handle<crate::Expression>,
+{
+    let adjust = |handle: &mut Handle<crate::Expression>| *handle = map(*handle);
+    let adjust_option = |handle: &mut Option<Handle<crate::Expression>>| {
+        if let Some(ref mut handle) = *handle {
+            *handle = map(*handle);
+        }
+    };
+    use crate::Expression as E;
+    match *expression {
+        E::Access {
+            ref mut base,
+            ref mut index,
+        } => {
+            adjust(base);
+            adjust(index);
+        }
+        E::AccessIndex { ref mut base, .. } => adjust(base),
+        E::Splat { ref mut value, .. } => adjust(value),
+        E::Swizzle { ref mut vector, .. } => adjust(vector),
+        E::Compose {
+            ref mut components, ..
+        } => components.iter_mut().for_each(adjust),
+        E::Constant(_)
+        | E::FunctionArgument(_)
+        | E::GlobalVariable(_)
+        | E::LocalVariable(_)
+        | E::Call(_)
+        | E::AtomicResult { .. }
+        | E::SubgroupBallotResult
+        | E::SubgroupOperationResult { .. }
+        | E::RayQueryProceedResult => {}
+        E::Load { ref mut pointer } => adjust(pointer),
+        E::ImageSample {
+            ref mut image,
+            ref mut sampler,
+            ref mut coordinate,
+            ref mut array_index,
+            ref mut level,
+            ref mut depth_ref,
+            ..
+        } => {
+            adjust(image);
+            adjust(sampler);
+            adjust(coordinate);
+            adjust_option(array_index);
+            adjust_option(depth_ref);
+            match *level {
+                crate::SampleLevel::Auto | crate::SampleLevel::Zero => {}
+                crate::SampleLevel::Exact(ref mut expr)
+                | crate::SampleLevel::Bias(ref mut expr) => adjust(expr),
+                crate::SampleLevel::Gradient {
+                    ref mut x,
+                    ref mut y,
+                } => {
+                    adjust(x);
+                    adjust(y);
+                }
+            }
+        }
+        E::ImageLoad {
+            ref mut image,
+            ref mut coordinate,
+            ref mut array_index,
+            ref mut index,
+        } => {
+            adjust(image);
+            adjust(coordinate);
+            adjust_option(array_index);
+            adjust_option(index);
+        }
+        E::ImageQuery {
+            ref mut image,
+            ref mut query,
+        } => {
+            adjust(image);
+            if let crate::ImageQuery::Size { ref mut level } = *query {
+                adjust_option(level);
+            }
+        }
+        E::Unary { ref mut expr, .. } => adjust(expr),
+        E::Binary {
+            ref mut left,
+            ref mut right,
+            ..
+        } => {
+            adjust(left);
+            adjust(right);
+        }
+        E::Select {
+            ref mut condition,
+            ref mut accept,
+            ref mut reject,
+        } => {
+            adjust(condition);
+            adjust(accept);
+            adjust(reject);
+        }
+        E::Derivative { ref mut expr, .. } => adjust(expr),
+        E::Relational {
+            ref mut argument, ..
+        } => adjust(argument),
+        E::Math {
+            ref mut arg,
+            ref mut arg1,
+            ref mut arg2,
+            ref mut arg3,
+            ..
+        } => {
+            adjust(arg);
+            adjust_option(arg1);
+            adjust_option(arg2);
+            adjust_option(arg3);
+        }
+        E::As { ref mut expr, .. } => adjust(expr),
+        E::RayQueryGetIntersection { ref mut query, .. } => adjust(query),
+        E::ArrayLength(ref mut expr) => adjust(expr),
+    }
+}
+
+/// Map the expression handles used by `statement` itself,
+/// leaving its nested blocks alone.
+fn map_statement<F>(statement: &mut crate::Statement, map: F)
+where
+    F: Fn(Handle<crate::Expression>) -> Handle<crate::Expression>,
+{
+    let adjust = |handle: &mut Handle<crate::Expression>| *handle = map(*handle);
+    let adjust_option = |handle: &mut Option<Handle<crate::Expression>>| {
+        if let Some(ref mut handle) = *handle {
+            *handle = map(*handle);
+        }
+    };
+    use crate::Statement as S;
+    match *statement {
+        S::Emit(_)
+        | S::Block(_)
+        | S::Loop { .. }
+        | S::Break
+        | S::Continue
+        | S::Kill
+        | S::Barrier(_) => {}
+        S::If {
+            ref mut condition, ..
+        } => adjust(condition),
+        S::Switch {
+            ref mut selector, ..
+        } => adjust(selector),
+        S::Return { ref mut value } => adjust_option(value),
+        S::Store {
+            ref mut pointer,
+            ref mut value,
+        } => {
+            adjust(pointer);
+            adjust(value);
+        }
+        S::ImageStore {
+            ref mut image,
+            ref mut coordinate,
+            ref mut array_index,
+            ref mut value,
+        } => {
+            adjust(image);
+            adjust(coordinate);
+            adjust_option(array_index);
+            adjust(value);
+        }
+        S::Call {
+            ref mut arguments,
+            ref mut result,
+            ..
+        } => {
+            arguments.iter_mut().for_each(adjust);
+            adjust_option(result);
+        }
+        S::Atomic {
+            ref mut pointer,
+            ref mut fun,
+            ref mut value,
+            ref mut result,
+        } => {
+            adjust(pointer);
+            adjust(value);
+            adjust(result);
+            if let crate::AtomicFunction::Exchange { ref mut compare } = *fun {
+                adjust_option(compare);
+            }
+        }
+        S::SubgroupBallot {
+            ref mut result,
+            ref mut predicate,
+        } => {
+            adjust(result);
+            adjust_option(predicate);
+        }
+        S::SubgroupCollectiveOperation {
+            ref mut argument,
+            ref mut result,
+            ..
+        } => {
+            adjust(argument);
+            adjust(result);
+        }
+        S::SubgroupGather {
+            ref mut mode,
+            ref mut argument,
+            ref mut result,
+        } => {
+            adjust(argument);
+            adjust(result);
+            match *mode {
+                crate::GatherMode::BroadcastFirst => {}
+                crate::GatherMode::Broadcast(ref mut index)
+                | crate::GatherMode::Shuffle(ref mut index)
+                | crate::GatherMode::ShuffleDown(ref mut index)
+                | crate::GatherMode::ShuffleUp(ref mut index)
+                | crate::GatherMode::ShuffleXor(ref mut index) => adjust(index),
+            }
+        }
+        S::MeshFunction(ref mut fun) => match *fun {
+            crate::MeshFunction::SetMeshOutputs {
+                ref mut vertex_count,
+                ref mut primitive_count,
+            } => {
+                adjust(vertex_count);
+                adjust(primitive_count);
+            }
+            crate::MeshFunction::SetVertex {
+                ref mut index,
+                ref mut value,
+            }
+            | crate::MeshFunction::SetIndices {
+                ref mut index,
+                ref mut value,
+            }
+            | crate::MeshFunction::SetPrimitive {
+                ref mut index,
+                ref mut value,
+            } => {
+                adjust(index);
+                adjust(value);
+            }
+        },
+        S::RayQuery {
+            ref mut query,
+            ref mut fun,
+        } => {
+            adjust(query);
+            match *fun {
+                crate::RayQueryFunction::Initialize {
+                    ref mut acceleration_structure,
+                    ref mut descriptor,
+                } => {
+                    adjust(acceleration_structure);
+                    adjust(descriptor);
+                }
+                crate::RayQueryFunction::Proceed { ref mut result } => adjust(result),
+                crate::RayQueryFunction::Terminate => {}
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    use super::InlinePolicy;
+
+    const SOURCE: &str = "
+        var<private> total: f32;
+
+        fn add_to_total(v: f32) {
+            total = total + v;
+        }
+
+        fn first_above(limit: i32) -> i32 {
+            var i: i32 = 0;
+            loop {
+                if (i >= limit) {
+                    break;
+                }
+                if (i > 2) {
+                    return i;
+                }
+                i = i + 1;
+            }
+            return -1;
+        }
+
+        fn pick(x: f32) -> f32 {
+            if (x > 1.0) {
+                return x;
+            }
+            return 2.0 * x;
+        }
+
+        [[stage(compute), workgroup_size(1)]]
+        fn main() {
+            var acc: f32 = 1.0;
+            add_to_total(pick(3.0));
+            let found = first_above(5);
+            total = total + acc * f32(found);
+        }
+    ";
+
+    /// Count the statements in `block` matching `fun`, at any depth.
+    fn count(block: &[crate::Statement], fun: fn(&crate::Statement) -> bool) -> usize {
+        use crate::Statement as S;
+        block
+            .iter()
+            .map(|statement| {
+                let inner = match *statement {
+                    S::Block(ref block) => count(block, fun),
+                    S::If {
+                        ref accept,
+                        ref reject,
+                        ..
+                    } => count(accept, fun) + count(reject, fun),
+                    S::Loop {
+                        ref body,
+                        ref continuing,
+                    } => count(body, fun) + count(continuing, fun),
+                    _ => 0,
+                };
+                inner + fun(statement) as usize
+            })
+            .sum()
+    }
+
+    fn is_call(statement: &crate::Statement) -> bool {
+        matches!(*statement, crate::Statement::Call { .. })
+    }
+
+    fn validate(module: &crate::Module) {
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::empty(),
+        )
+        .validate(module)
+        .unwrap();
+    }
+
+    #[test]
+    fn inline_all() {
+        let mut module = crate::front::wgsl::parse_str(SOURCE).unwrap();
+        super::inline_functions(&mut module, InlinePolicy::Always);
+        validate(&module);
+        let function = &module.entry_points[0].function;
+        assert_eq!(count(&function.body, is_call), 0);
+        // `pick` and `first_above` return early, and the latter has its own loop
+        let loops = count(&function.body, |s| {
+            matches!(*s, crate::Statement::Loop { .. })
+        });
+        assert_eq!(loops, 3);
+        let names = function
+            .local_variables
+            .iter()
+            .filter_map(|(_, local)| local.name.as_deref())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"first_above_i"));
+        assert!(names.contains(&"first_above_returned"));
+
+        crate::proc::compact(&mut module);
+        assert!(module.functions.is_empty());
+        validate(&module);
+    }
+
+    #[cfg(feature = "ir-in")]
+    #[test]
+    fn inline_unordered_expressions() {
+        // the argument of the call comes after its result
+        let mut module = crate::front::ir::parse_str(
+            r#"
+            t0 = Scalar(Float, 4)
+            c0 = Float(4, 2.0)
+            g0 = Global(Private, t0)
+            f0 "double" = Function(arguments: [Argument(t0, name: "x")], result: FunctionResult(t0)) {
+                e0 = FunctionArgument(0)
+                e1 = Constant(c0)
+                e2 = Binary(Multiply, e0, e1)
+                emit e2..e3
+                return e2
+            }
+            entry_point "main" = EntryPoint(Compute, [1, 1, 1])
+            function = Function() {
+                e0 = Call(f0)
+                e1 = GlobalVariable(g0)
+                e2 = Load(e1)
+                emit e2..e3
+                Call(f0, [e2], result: e0)
+                Store(e1, e0)
+                return
+            }
+            "#,
+        )
+        .unwrap();
+        super::inline_functions(&mut module, InlinePolicy::Always);
+        validate(&module);
+        let function = &module.entry_points[0].function;
+        assert_eq!(count(&function.body, is_call), 0);
+    }
+
+    #[test]
+    fn inline_by_size() {
+        let mut module = crate::front::wgsl::parse_str(SOURCE).unwrap();
+        let max = module
+            .functions
+            .iter()
+            .find(|&(_, function)| function.name.as_deref() == Some("add_to_total"))
+            .unwrap()
+            .1
+            .expressions
+            .len();
+        super::inline_functions(&mut module, InlinePolicy::MaxExpressions(max));
+        validate(&module);
+        assert_eq!(count(&module.entry_points[0].function.body, is_call), 2);
+    }
+}
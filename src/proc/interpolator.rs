@@ -3,8 +3,8 @@ pub use crate::{Arena, Handle};
 impl crate::Module {
     /// Apply the usual default interpolation for vertex shader outputs and fragment shader inputs.
     ///
-    /// For every [`Binding`] that is a vertex shader output or a fragment shader
-    /// input, and that has an `interpolation` or `sampling` of `None`, assign a
+    /// For every [`Binding`] that is a vertex shader output, a per-vertex mesh
+    /// shader output or a fragment shader input, and that has an `interpolation` or `sampling` of `None`, assign a
     /// default interpolation and sampling as follows:
     ///
     /// - If the `Binding`'s type contains only 32-bit floating-point values or
@@ -142,8 +142,50 @@ impl crate::Module {
                         default_binding_or_struct(&mut result.binding, result.ty, &mut self.types);
                     }
                 }
+                crate::ShaderStage::Mesh => {
+                    if let Some(ref info) = ep.mesh_info {
+                        default_binding_or_struct(
+                            &mut None,
+                            info.vertex_output_type,
+                            &mut self.types,
+                        );
+                    }
+                }
                 _ => (),
             }
         }
     }
 }
+
+/// Resolve the interpolation and sampling of a varying of type `inner`,
+/// taking the defaults of [`apply_common_default_interpolation`] for the
+/// missing ones, so that equivalent bindings from different front ends match.
+///
+/// Flat varyings aren't interpolated, so they resolve to no sampling.
+///
+/// [`apply_common_default_interpolation`]: crate::Module::apply_common_default_interpolation
+pub(crate) fn resolve_interpolation(
+    inner: &crate::TypeInner,
+    interpolation: Option<crate::Interpolation>,
+    sampling: Option<crate::Sampling>,
+) -> (crate::Interpolation, Option<crate::Sampling>) {
+    let default_interpolation = match *inner {
+        crate::TypeInner::Scalar {
+            kind: crate::ScalarKind::Float,
+            width: 4,
+        }
+        | crate::TypeInner::Vector {
+            kind: crate::ScalarKind::Float,
+            width: 4,
+            ..
+        } => crate::Interpolation::Perspective,
+        _ => crate::Interpolation::Flat,
+    };
+    match interpolation.unwrap_or(default_interpolation) {
+        crate::Interpolation::Flat => (crate::Interpolation::Flat, None),
+        interpolation => (
+            interpolation,
+            Some(sampling.unwrap_or(crate::Sampling::Center)),
+        ),
+    }
+}
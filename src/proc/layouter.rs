@@ -72,7 +72,7 @@ impl Layouter {
         for (ty_handle, ty) in types.iter().skip(self.layouts.len()) {
             let size = ty.inner.span(constants);
             let layout = match ty.inner {
-                Ti::Scalar { width, .. } => TypeLayout {
+                Ti::Scalar { width, .. } | Ti::Atomic { width, .. } => TypeLayout {
                     size,
                     alignment: Alignment::new(width as u32).unwrap(),
                 },
@@ -136,7 +136,11 @@ impl Layouter {
                         alignment,
                     }
                 }
-                Ti::Image { .. } | Ti::Sampler { .. } => TypeLayout {
+                Ti::Image { .. }
+                | Ti::Sampler { .. }
+                | Ti::AccelerationStructure
+                | Ti::RayQuery
+                | Ti::BindingArray { .. } => TypeLayout {
                     size,
                     alignment: Alignment::new(1).unwrap(),
                 },
@@ -148,3 +152,46 @@ impl Layouter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_float_layout() {
+        let mut types = Arena::new();
+        let scalar = types.append(crate::Type {
+            name: None,
+            inner: crate::TypeInner::Scalar {
+                kind: crate::ScalarKind::Float,
+                width: 2,
+            },
+        });
+        let vec2 = types.append(crate::Type {
+            name: None,
+            inner: crate::TypeInner::Vector {
+                size: crate::VectorSize::Bi,
+                kind: crate::ScalarKind::Float,
+                width: 2,
+            },
+        });
+        let vec3 = types.append(crate::Type {
+            name: None,
+            inner: crate::TypeInner::Vector {
+                size: crate::VectorSize::Tri,
+                kind: crate::ScalarKind::Float,
+                width: 2,
+            },
+        });
+
+        let mut layouter = Layouter::default();
+        layouter.update(&types, &Arena::new()).unwrap();
+        let layout = |size, align| TypeLayout {
+            size,
+            alignment: Alignment::new(align).unwrap(),
+        };
+        assert_eq!(layouter[scalar], layout(2, 2));
+        assert_eq!(layouter[vec2], layout(4, 4));
+        assert_eq!(layouter[vec3], layout(6, 8));
+    }
+}
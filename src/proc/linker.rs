@@ -0,0 +1,695 @@
+use crate::{
+    arena::{Arena, Handle},
+    FastHashSet,
+};
+use std::mem;
+
+#[derive(Clone, Debug, thiserror::Error, PartialEq)]
+pub enum LinkError {
+    #[error("function {0:?} is defined more than once")]
+    FunctionRedefined(String),
+    #[error("function {0:?} is called, but never defined")]
+    FunctionUndefined(String),
+    #[error("type {0:?} has conflicting definitions")]
+    TypeConflict(String),
+    #[error("constant {0:?} has conflicting values")]
+    ConstantConflict(String),
+    #[error("specialization ID {0} is used by different constants")]
+    SpecializationConflict(u32),
+    #[error("global variable {0:?} has conflicting declarations")]
+    GlobalVariableConflict(String),
+    #[error("resource binding {0:?} is used by different global variables")]
+    BindingConflict(crate::ResourceBinding),
+    #[error("entry point {name:?} of the {stage:?} stage is defined more than once")]
+    EntryPointConflict {
+        name: String,
+        stage: crate::ShaderStage,
+    },
+}
+
+/// A module given to [`link`].
+#[derive(Clone, Copy, Debug)]
+pub struct LinkInput<'a> {
+    pub module: &'a crate::Module,
+    /// Functions of the module that are only declared, like unresolved
+    /// GLSL prototypes, and have to be defined by another module.
+    pub declarations: &'a [Handle<crate::Function>],
+}
+
+impl<'a> From<&'a crate::Module> for LinkInput<'a> {
+    fn from(module: &'a crate::Module) -> Self {
+        LinkInput {
+            module,
+            declarations: &[],
+        }
+    }
+}
+
+/// Combine several modules into one.
+///
+/// Equal types and constants are merged, and so are global variables of
+/// the same name, as long as they are declared the same way. A function
+/// listed in the [`declarations`] of its module is merged with the function
+/// of the same name and signature defined in another module, so that the
+/// calls reach the definition.
+///
+/// It's an error for two modules to have different constants or global
+/// variables of the same name, different global variables with the same
+/// binding, the same function or entry point defined twice, or a declared
+/// function to be called without being defined anywhere.
+///
+/// [`declarations`]: LinkInput::declarations
+pub fn link(inputs: &[LinkInput]) -> Result<crate::Module, LinkError> {
+    let mut linked = crate::Module::default();
+    let mut declarations = FastHashSet::default();
+    for input in inputs {
+        let module = input.module;
+        ModuleLinker {
+            source: module,
+            source_declarations: input.declarations,
+            target: &mut linked,
+            target_declarations: &mut declarations,
+            types: vec![None; module.types.len()],
+            constants: vec![None; module.constants.len()],
+            global_variables: Vec::with_capacity(module.global_variables.len()),
+            functions: Vec::with_capacity(module.functions.len()),
+        }
+        .link()?;
+    }
+    sort_functions(&mut linked, &declarations)?;
+    Ok(linked)
+}
+
+/// Adds a module to the linked one.
+struct ModuleLinker<'a> {
+    source: &'a crate::Module,
+    source_declarations: &'a [Handle<crate::Function>],
+    target: &'a mut crate::Module,
+    /// Functions of the linked module that are still only declared.
+    target_declarations: &'a mut FastHashSet<Handle<crate::Function>>,
+    types: Vec<Option<Handle<crate::Type>>>,
+    constants: Vec<Option<Handle<crate::Constant>>>,
+    global_variables: Vec<Handle<crate::GlobalVariable>>,
+    functions: Vec<Handle<crate::Function>>,
+}
+
+impl<'a> ModuleLinker<'a> {
+    fn link(mut self) -> Result<(), LinkError> {
+        let source = self.source;
+        for (handle, _) in source.types.iter() {
+            self.ty(handle)?;
+        }
+        for (handle, _) in source.constants.iter() {
+            self.constant(handle)?;
+        }
+        for derived in source.derived_constants.iter() {
+            let mut expressions = derived.expressions.clone();
+            for (_, expression) in expressions.iter_mut() {
+                match *expression {
+                    crate::Expression::Constant(ref mut constant) => {
+                        *constant = self.constant(*constant)?
+                    }
+                    crate::Expression::Compose { ref mut ty, .. } => *ty = self.ty(*ty)?,
+                    _ => {}
+                }
+            }
+            let new_derived = crate::DerivedConstant {
+                constant: self.constant(derived.constant)?,
+                expressions,
+                root: derived.root,
+            };
+            self.target.derived_constants.push(new_derived);
+        }
+
+        for (handle, global) in source.global_variables.iter() {
+            let new_global = crate::GlobalVariable {
+                ty: self.ty(global.ty)?,
+                init: match global.init {
+                    Some(init) => Some(self.constant(init)?),
+                    None => None,
+                },
+                ..global.clone()
+            };
+            let globals = &mut self.target.global_variables;
+            let existing = match new_global.name {
+                Some(ref name) => globals.fetch_if(|g| g.name.as_ref() == Some(name)),
+                None => None,
+            };
+            let new_handle = match existing {
+                Some(existing) if globals[existing] == new_global => existing,
+                Some(_) => return Err(LinkError::GlobalVariableConflict(new_global.name.unwrap())),
+                None => {
+                    if let Some(ref binding) = new_global.binding {
+                        if globals
+                            .iter()
+                            .any(|(_, g)| g.binding.as_ref() == Some(binding))
+                        {
+                            return Err(LinkError::BindingConflict(binding.clone()));
+                        }
+                    }
+                    let span = source.global_variables.get_span(handle);
+                    globals.append_with_span(new_global, span)
+                }
+            };
+            self.global_variables.push(new_handle);
+        }
+
+        for (handle, function) in source.functions.iter() {
+            let mut new_function = function.clone();
+            self.adjust_function(&mut new_function)?;
+            let functions = &mut self.target.functions;
+            let types = &self.target.types;
+            let existing = match new_function.name {
+                Some(ref name) => functions.fetch_if(|f| {
+                    f.name.as_ref() == Some(name) && same_signature(types, f, &new_function)
+                }),
+                None => None,
+            };
+            let is_declaration = self.source_declarations.contains(&handle);
+            let new_handle = match existing {
+                Some(existing) => {
+                    if is_declaration {
+                        existing
+                    } else if self.target_declarations.remove(&existing) {
+                        *functions.get_mut(existing) = new_function;
+                        functions.set_span(existing, source.functions.get_span(handle));
+                        existing
+                    } else {
+                        return Err(LinkError::FunctionRedefined(new_function.name.unwrap()));
+                    }
+                }
+                None => {
+                    let span = source.functions.get_span(handle);
+                    let new_handle = functions.append_with_span(new_function, span);
+                    if is_declaration {
+                        self.target_declarations.insert(new_handle);
+                    }
+                    new_handle
+                }
+            };
+            self.functions.push(new_handle);
+        }
+
+        for ep in source.entry_points.iter() {
+            if self
+                .target
+                .entry_points
+                .iter()
+                .any(|other| other.name == ep.name && other.stage == ep.stage)
+            {
+                return Err(LinkError::EntryPointConflict {
+                    name: ep.name.clone(),
+                    stage: ep.stage,
+                });
+            }
+            let mut new_ep = ep.clone();
+            if let Some(ref mut info) = new_ep.mesh_info {
+                info.vertex_output_type = self.ty(info.vertex_output_type)?;
+                if let Some(ref mut ty) = info.primitive_output_type {
+                    *ty = self.ty(*ty)?;
+                }
+            }
+            if let Some(ref mut global) = new_ep.task_payload {
+                *global = self.global_variables[global.index()];
+            }
+            self.adjust_function(&mut new_ep.function)?;
+            self.target.entry_points.push(new_ep);
+        }
+        Ok(())
+    }
+
+    fn ty(&mut self, handle: Handle<crate::Type>) -> Result<Handle<crate::Type>, LinkError> {
+        if let Some(new_handle) = self.types[handle.index()] {
+            return Ok(new_handle);
+        }
+        let source = self.source;
+        let ty = &source.types[handle];
+        let inner = match ty.inner {
+            crate::TypeInner::Pointer { base, class } => crate::TypeInner::Pointer {
+                base: self.ty(base)?,
+                class,
+            },
+            crate::TypeInner::Array { base, size, stride } => crate::TypeInner::Array {
+                base: self.ty(base)?,
+                size: self.array_size(size)?,
+                stride,
+            },
+            crate::TypeInner::BindingArray { base, size } => crate::TypeInner::BindingArray {
+                base: self.ty(base)?,
+                size: self.array_size(size)?,
+            },
+            crate::TypeInner::Struct {
+                top_level,
+                ref members,
+                span,
+            } => crate::TypeInner::Struct {
+                top_level,
+                members: members
+                    .iter()
+                    .map(|member| {
+                        Ok(crate::StructMember {
+                            ty: self.ty(member.ty)?,
+                            ..member.clone()
+                        })
+                    })
+                    .collect::<Result<_, _>>()?,
+                span,
+            },
+            ref other => other.clone(),
+        };
+        let new_type = crate::Type {
+            name: ty.name.clone(),
+            inner,
+        };
+
+        let types = &mut self.target.types;
+        if let crate::TypeInner::Struct { .. } = new_type.inner {
+            if let Some(ref name) = new_type.name {
+                if let Some(existing) = types.fetch_if(|t| t.name.as_ref() == Some(name)) {
+                    if types[existing] != new_type {
+                        return Err(LinkError::TypeConflict(name.clone()));
+                    }
+                }
+            }
+        }
+        let new_handle = types.fetch_or_append(new_type);
+        types.init_span(new_handle, source.types.get_span(handle));
+        self.types[handle.index()] = Some(new_handle);
+        Ok(new_handle)
+    }
+
+    fn array_size(&mut self, size: crate::ArraySize) -> Result<crate::ArraySize, LinkError> {
+        Ok(match size {
+            crate::ArraySize::Constant(constant) => {
+                crate::ArraySize::Constant(self.constant(constant)?)
+            }
+            crate::ArraySize::Dynamic => crate::ArraySize::Dynamic,
+        })
+    }
+
+    fn constant(
+        &mut self,
+        handle: Handle<crate::Constant>,
+    ) -> Result<Handle<crate::Constant>, LinkError> {
+        if let Some(new_handle) = self.constants[handle.index()] {
+            return Ok(new_handle);
+        }
+        let source = self.source;
+        let constant = &source.constants[handle];
+        let inner = match constant.inner {
+            crate::ConstantInner::Scalar { width, value } => {
+                crate::ConstantInner::Scalar { width, value }
+            }
+            crate::ConstantInner::Composite { ty, ref components } => {
+                crate::ConstantInner::Composite {
+                    ty: self.ty(ty)?,
+                    components: components
+                        .iter()
+                        .map(|&component| self.constant(component))
+                        .collect::<Result<_, _>>()?,
+                }
+            }
+        };
+        let new_constant = crate::Constant {
+            name: constant.name.clone(),
+            specialization: constant.specialization,
+            inner,
+        };
+
+        let constants = &mut self.target.constants;
+        if let Some(id) = new_constant.specialization {
+            if let Some(existing) = constants.fetch_if(|c| c.specialization == Some(id)) {
+                if constants[existing] != new_constant {
+                    return Err(LinkError::SpecializationConflict(id));
+                }
+            }
+        }
+        if let Some(ref name) = new_constant.name {
+            if let Some(existing) = constants.fetch_if(|c| c.name.as_ref() == Some(name)) {
+                if constants[existing] != new_constant {
+                    return Err(LinkError::ConstantConflict(name.clone()));
+                }
+            }
+        }
+        // a derived constant changes on specialization, so it's never shared
+        let new_handle = if source
+            .derived_constants
+            .iter()
+            .any(|d| d.constant == handle)
+        {
+            constants.append(new_constant)
+        } else {
+            constants.fetch_or_append(new_constant)
+        };
+        constants.init_span(new_handle, source.constants.get_span(handle));
+        self.constants[handle.index()] = Some(new_handle);
+        Ok(new_handle)
+    }
+
+    /// Make the module handles of a copied function point into the target.
+    fn adjust_function(&mut self, function: &mut crate::Function) -> Result<(), LinkError> {
+        for argument in function.arguments.iter_mut() {
+            argument.ty = self.ty(argument.ty)?;
+        }
+        if let Some(ref mut result) = function.result {
+            result.ty = self.ty(result.ty)?;
+        }
+        for (_, local) in function.local_variables.iter_mut() {
+            local.ty = self.ty(local.ty)?;
+            if let Some(ref mut init) = local.init {
+                *init = self.constant(*init)?;
+            }
+        }
+        for (_, expression) in function.expressions.iter_mut() {
+            use crate::Expression as E;
+            match *expression {
+                E::Constant(ref mut constant)
+                | E::ImageSample {
+                    offset: Some(ref mut constant),
+                    ..
+                } => *constant = self.constant(*constant)?,
+                E::Compose { ref mut ty, .. }
+                | E::SubgroupOperationResult { ref mut ty }
+                | E::RayQueryGetIntersection { ref mut ty, .. } => *ty = self.ty(*ty)?,
+                E::GlobalVariable(ref mut global) => {
+                    *global = self.global_variables[global.index()]
+                }
+                E::Call(ref mut function) => *function = self.functions[function.index()],
+                _ => {}
+            }
+        }
+        let functions = &self.functions;
+        adjust_calls(&mut function.body, &mut |function| {
+            *function = functions[function.index()]
+        });
+        Ok(())
+    }
+}
+
+/// Check if a function is only declared.
+fn same_signature(types: &Arena<crate::Type>, a: &crate::Function, b: &crate::Function) -> bool {
+    let same_type =
+        |a: Handle<crate::Type>, b: Handle<crate::Type>| types[a].inner == types[b].inner;
+    a.arguments.len() == b.arguments.len()
+        && a.arguments
+            .iter()
+            .zip(b.arguments.iter())
+            .all(|(a, b)| same_type(a.ty, b.ty))
+        && match (a.result.as_ref(), b.result.as_ref()) {
+            (Some(a), Some(b)) => same_type(a.ty, b.ty),
+            (None, None) => true,
+            _ => false,
+        }
+}
+
+/// Apply `fun` to the callee of every `Call` statement in `block`.
+fn adjust_calls<F: FnMut(&mut Handle<crate::Function>)>(block: &mut crate::Block, fun: &mut F) {
+    use crate::Statement as S;
+    for statement in block.iter_mut() {
+        match *statement {
+            S::Call {
+                ref mut function, ..
+            } => fun(function),
+            S::Block(ref mut block) => adjust_calls(block, fun),
+            S::If {
+                ref mut accept,
+                ref mut reject,
+                ..
+            } => {
+                adjust_calls(accept, fun);
+                adjust_calls(reject, fun);
+            }
+            S::Switch {
+                ref mut cases,
+                ref mut default,
+                ..
+            } => {
+                for case in cases.iter_mut() {
+                    adjust_calls(&mut case.body, fun);
+                }
+                adjust_calls(default, fun);
+            }
+            S::Loop {
+                ref mut body,
+                ref mut continuing,
+            } => {
+                adjust_calls(body, fun);
+                adjust_calls(continuing, fun);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Order the functions so that every callee comes before its callers,
+/// as a declaration may be defined by a function of a later module.
+fn sort_functions(
+    module: &mut crate::Module,
+    declarations: &FastHashSet<Handle<crate::Function>>,
+) -> Result<(), LinkError> {
+    let mut callees = Vec::with_capacity(module.functions.len());
+    for (_, function) in module.functions.iter_mut() {
+        let mut list = Vec::new();
+        adjust_calls(&mut function.body, &mut |&mut callee| list.push(callee));
+        callees.push(list);
+    }
+    let mut called = vec![false; module.functions.len()];
+    for ep in module.entry_points.iter_mut() {
+        adjust_calls(&mut ep.function.body, &mut |&mut callee| {
+            called[callee.index()] = true
+        });
+    }
+    for list in callees.iter() {
+        for callee in list.iter() {
+            called[callee.index()] = true;
+        }
+    }
+    for (handle, function) in module.functions.iter() {
+        if called[handle.index()] && declarations.contains(&handle) {
+            let name = function.name.clone().unwrap_or_default();
+            return Err(LinkError::FunctionUndefined(name));
+        }
+    }
+
+    // depth-first, with the callees first
+    let mut order = Vec::with_capacity(callees.len());
+    let mut visited = vec![false; callees.len()];
+    let mut stack = Vec::new();
+    for (handle, _) in module.functions.iter() {
+        stack.push((handle, 0));
+        while let Some((handle, next_callee)) = stack.pop() {
+            if next_callee == 0 {
+                if visited[handle.index()] {
+                    continue;
+                }
+                visited[handle.index()] = true;
+            }
+            match callees[handle.index()].get(next_callee) {
+                Some(&callee) => {
+                    stack.push((handle, next_callee + 1));
+                    if !visited[callee.index()] {
+                        stack.push((callee, 0));
+                    }
+                }
+                None => order.push(handle),
+            }
+        }
+    }
+    if order
+        .iter()
+        .enumerate()
+        .all(|(index, h)| h.index() == index)
+    {
+        return Ok(());
+    }
+
+    let mut old_functions = mem::take(&mut module.functions);
+    let mut map = vec![None; order.len()];
+    for handle in order {
+        let span = old_functions.get_span(handle);
+        let function = mem::take(old_functions.get_mut(handle));
+        map[handle.index()] = Some(module.functions.append_with_span(function, span));
+    }
+    let mut adjust = |function: &mut Handle<crate::Function>| {
+        *function = map[function.index()].unwrap();
+    };
+    let functions = module
+        .functions
+        .iter_mut()
+        .map(|(_, function)| function)
+        .chain(module.entry_points.iter_mut().map(|ep| &mut ep.function));
+    for function in functions {
+        for (_, expression) in function.expressions.iter_mut() {
+            if let crate::Expression::Call(ref mut callee) = *expression {
+                adjust(callee);
+            }
+        }
+        adjust_calls(&mut function.body, &mut adjust);
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    use super::{link, LinkError, LinkInput};
+
+    const LIBRARY: &str = "
+        fn hash(x: f32) -> f32 {
+            return fract(sin(x) * 43758.5453);
+        }
+
+        fn noise(p: vec3<f32>) -> f32 {
+            return hash(p.x + p.y * 57.0 + p.z * 113.0);
+        }
+    ";
+
+    fn validate(module: &crate::Module) {
+        crate::valid::Validator::new(
+            crate::valid::ValidationFlags::all(),
+            crate::valid::Capabilities::all(),
+        )
+        .validate(module)
+        .unwrap();
+    }
+
+    #[cfg(feature = "glsl-in")]
+    #[test]
+    fn link_prototype() {
+        let library = crate::front::wgsl::parse_str(LIBRARY).unwrap();
+        let mut entry_points = crate::FastHashMap::default();
+        entry_points.insert("main".to_string(), crate::ShaderStage::Fragment);
+        let options = crate::front::glsl::Options {
+            entry_points,
+            defines: Default::default(),
+        };
+        let (shader, declarations) = crate::front::glsl::parse_str_with_declarations(
+            "
+            #version 450
+            float noise(vec3 p);
+            layout(location = 0) in vec3 position;
+            layout(location = 0) out vec4 color;
+            void main() {
+                color = vec4(noise(position));
+            }
+            ",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(declarations.len(), 1);
+        let shader = LinkInput {
+            module: &shader,
+            declarations: &declarations,
+        };
+
+        let module = link(&[shader, (&library).into()]).unwrap();
+        validate(&module);
+        let noise = module
+            .functions
+            .iter()
+            .filter(|&(_, f)| f.name.as_deref() == Some("noise"))
+            .collect::<Vec<_>>();
+        assert_eq!(noise.len(), 1);
+        assert!(!noise[0].1.body.is_empty());
+
+        // the declaration alone can't be linked
+        assert_eq!(
+            link(&[shader]).unwrap_err(),
+            LinkError::FunctionUndefined("noise".to_string())
+        );
+
+        // even when it returns nothing
+        let (shader, declarations) = crate::front::glsl::parse_str_with_declarations(
+            "
+            #version 450
+            void shade();
+            void main() {
+                shade();
+            }
+            ",
+            &options,
+        )
+        .unwrap();
+        let shader = LinkInput {
+            module: &shader,
+            declarations: &declarations,
+        };
+        assert_eq!(
+            link(&[shader]).unwrap_err(),
+            LinkError::FunctionUndefined("shade".to_string())
+        );
+    }
+
+    #[test]
+    fn link_conflicts() {
+        let library = crate::front::wgsl::parse_str(LIBRARY).unwrap();
+        let shader = crate::front::wgsl::parse_str(
+            "
+            var<private> scale: f32;
+            let SEED: f32 = 1.0;
+
+            [[stage(compute), workgroup_size(1)]]
+            fn main() {}
+            ",
+        )
+        .unwrap();
+        let module = link(&[
+            (&library).into(),
+            (&shader).into(),
+            (&shader.clone()).into(),
+        ]);
+        assert_eq!(
+            module.unwrap_err(),
+            LinkError::EntryPointConflict {
+                name: "main".to_string(),
+                stage: crate::ShaderStage::Compute,
+            }
+        );
+        assert_eq!(
+            link(&[(&library).into(), (&library).into()]).unwrap_err(),
+            LinkError::FunctionRedefined("hash".to_string())
+        );
+
+        // a function with an empty body is still a definition
+        let noop = crate::front::wgsl::parse_str("fn noop() {}").unwrap();
+        assert_eq!(
+            link(&[(&noop).into(), (&noop).into()]).unwrap_err(),
+            LinkError::FunctionRedefined("noop".to_string())
+        );
+
+        let other = crate::front::wgsl::parse_str(
+            "
+            var<private> scale: f32;
+            let SEED: f32 = 2.0;
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            link(&[(&shader).into(), (&other).into()]).unwrap_err(),
+            LinkError::ConstantConflict("SEED".to_string())
+        );
+
+        let layouts = [
+            "
+            struct Light { color: vec3<f32>; intensity: f32; };
+            var<private> sun: Light;
+            ",
+            "
+            struct Light { intensity: f32; color: vec3<f32>; };
+            var<private> lamp: Light;
+            ",
+        ];
+        let layouts = layouts
+            .iter()
+            .map(|source| crate::front::wgsl::parse_str(source).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            link(&[(&layouts[0]).into(), (&layouts[1]).into()]).unwrap_err(),
+            LinkError::TypeConflict("Light".to_string())
+        );
+
+        let module = link(&[(&library).into(), (&shader).into()]).unwrap();
+        validate(&module);
+        assert_eq!(module.functions.len(), 2);
+        assert_eq!(module.entry_points.len(), 1);
+    }
+}
@@ -1,17 +1,44 @@
 //! Module processing functionality.
 
+mod compact;
+mod constant_evaluator;
 mod index;
+mod inliner;
 mod interpolator;
 mod layouter;
+mod linker;
 mod namer;
+mod ray_query;
+mod reflect;
+mod specializer;
 mod terminator;
 mod typifier;
+mod varyings;
 
+pub use compact::{compact, extract_entry_point, ModuleMap};
+pub use constant_evaluator::{ConstantEvaluator, ConstantEvaluatorError};
 pub use index::IndexableLength;
+pub use inliner::{inline_functions, InlinePolicy};
+pub(crate) use interpolator::resolve_interpolation;
 pub use layouter::{Alignment, InvalidBaseType, Layouter, TypeLayout};
+pub use linker::{link, LinkError, LinkInput};
 pub use namer::{EntryPointIndex, NameKey, Namer};
+pub use ray_query::{
+    RayQueryStruct, RAY_QUERY_INTERSECTION_AABB, RAY_QUERY_INTERSECTION_GENERATED,
+    RAY_QUERY_INTERSECTION_NONE, RAY_QUERY_INTERSECTION_TRIANGLE,
+};
+pub use reflect::{
+    merge_bind_groups, reflect, BindGroupError, BindGroupLayout, BindGroupLayoutEntry,
+    EntryPointReflection, InterfaceFormat, InterfaceVariable, ModuleReflection, PushConstantRange,
+    ResourceKind, ResourceReflection,
+};
+pub use specializer::{specialize, SpecializationError};
 pub use terminator::ensure_block_returns;
 pub use typifier::{ResolveContext, ResolveError, TypeResolution};
+pub use varyings::{
+    pack_varyings, remove_unused_varyings, used_fragment_inputs, varying_packing, PackedVarying,
+    PackingError, VaryingPacking,
+};
 
 #[derive(Clone, Debug, thiserror::Error, PartialEq)]
 pub enum ProcError {
@@ -85,9 +112,33 @@ impl super::TypeInner {
         }
     }
 
+    /// Returns true if this is a pointer to an [`Atomic`](super::TypeInner::Atomic).
+    pub fn is_atomic_pointer(&self, types: &super::Arena<super::Type>) -> bool {
+        match *self {
+            Self::Pointer { base, .. } => match types[base].inner {
+                Self::Atomic { .. } => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns true if this is a [`BindingArray`](super::TypeInner::BindingArray),
+    /// or a pointer to one.
+    pub fn is_binding_array(&self, types: &super::Arena<super::Type>) -> bool {
+        match *self {
+            Self::BindingArray { .. } => true,
+            Self::Pointer { base, .. } => match types[base].inner {
+                Self::BindingArray { .. } => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
     pub fn span(&self, constants: &super::Arena<super::Constant>) -> u32 {
         match *self {
-            Self::Scalar { kind: _, width } => width as u32,
+            Self::Scalar { kind: _, width } | Self::Atomic { kind: _, width } => width as u32,
             Self::Vector {
                 size,
                 kind: _,
@@ -115,7 +166,11 @@ impl super::TypeInner {
                 count * stride
             }
             Self::Struct { span, .. } => span,
-            Self::Image { .. } | Self::Sampler { .. } => 0,
+            Self::Image { .. }
+            | Self::Sampler { .. }
+            | Self::AccelerationStructure
+            | Self::RayQuery
+            | Self::BindingArray { .. } => 0,
         }
     }
 }
@@ -176,8 +231,26 @@ impl super::MathFunction {
             Self::Transpose => 1,
             Self::Determinant => 1,
             // bits
+            Self::CountTrailingZeros => 1,
+            Self::CountLeadingZeros => 1,
             Self::CountOneBits => 1,
             Self::ReverseBits => 1,
+            Self::ExtractBits => 3,
+            Self::InsertBits => 4,
+            Self::FirstTrailingBit => 1,
+            Self::FirstLeadingBit => 1,
+            // data packing
+            Self::Pack4x8snorm => 1,
+            Self::Pack4x8unorm => 1,
+            Self::Pack2x16snorm => 1,
+            Self::Pack2x16unorm => 1,
+            Self::Pack2x16float => 1,
+            // data unpacking
+            Self::Unpack4x8snorm => 1,
+            Self::Unpack4x8unorm => 1,
+            Self::Unpack2x16snorm => 1,
+            Self::Unpack2x16unorm => 1,
+            Self::Unpack2x16float => 1,
         }
     }
 }
@@ -217,6 +290,20 @@ impl crate::Expression {
     }
 }
 
+impl crate::GatherMode {
+    /// Returns the expression selecting the invocation to read from, if any.
+    pub fn index(&self) -> Option<crate::Handle<crate::Expression>> {
+        match *self {
+            Self::BroadcastFirst => None,
+            Self::Broadcast(index)
+            | Self::Shuffle(index)
+            | Self::ShuffleDown(index)
+            | Self::ShuffleUp(index)
+            | Self::ShuffleXor(index) => Some(index),
+        }
+    }
+}
+
 impl crate::SampleLevel {
     pub fn implicit_derivatives(&self) -> bool {
         match *self {
@@ -0,0 +1,175 @@
+use super::Layouter;
+use crate::arena::{Arena, Handle};
+
+/// Structure exchanged with a ray query.
+///
+/// Backends rely on the members being in this exact order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RayQueryStruct {
+    /// Ray to trace, consumed by [`RayQueryFunction::Initialize`].
+    ///
+    /// Members: `flags: u32`, `cull_mask: u32`, `t_min: f32`, `t_max: f32`,
+    /// `origin: vec3<f32>` and `dir: vec3<f32>`.
+    ///
+    /// [`RayQueryFunction::Initialize`]: crate::RayQueryFunction::Initialize
+    Desc,
+    /// Intersection, produced by [`Expression::RayQueryGetIntersection`].
+    ///
+    /// Members: `kind: u32`, `t: f32`, `instance_custom_index: u32`,
+    /// `instance_id: u32`, `sbt_record_offset: u32`, `geometry_index: u32`,
+    /// `primitive_index: u32`, `barycentrics: vec2<f32>`, `front_face: bool`,
+    /// `object_to_world: mat4x3<f32>` and `world_to_object: mat4x3<f32>`.
+    ///
+    /// The `kind` is one of the `RAY_QUERY_INTERSECTION_*` constants.
+    ///
+    /// [`Expression::RayQueryGetIntersection`]: crate::Expression::RayQueryGetIntersection
+    Intersection,
+}
+
+/// No intersection found.
+pub const RAY_QUERY_INTERSECTION_NONE: u32 = 0;
+/// Intersection with a triangle.
+pub const RAY_QUERY_INTERSECTION_TRIANGLE: u32 = 1;
+/// Committed intersection generated by the shader.
+pub const RAY_QUERY_INTERSECTION_GENERATED: u32 = 2;
+/// Candidate intersection with an axis-aligned bounding box.
+pub const RAY_QUERY_INTERSECTION_AABB: u32 = 3;
+
+impl RayQueryStruct {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Desc => "RayDesc",
+            Self::Intersection => "RayIntersection",
+        }
+    }
+
+    /// Return the names and types of the members.
+    pub fn members(self) -> Vec<(&'static str, crate::TypeInner)> {
+        use crate::{ScalarKind as Sk, TypeInner as Ti, VectorSize as Vs};
+
+        let uint = || Ti::Scalar {
+            kind: Sk::Uint,
+            width: 4,
+        };
+        let float = || Ti::Scalar {
+            kind: Sk::Float,
+            width: 4,
+        };
+        let vector = |size| Ti::Vector {
+            size,
+            kind: Sk::Float,
+            width: 4,
+        };
+        let transform = || Ti::Matrix {
+            columns: Vs::Quad,
+            rows: Vs::Tri,
+            width: 4,
+        };
+
+        match self {
+            Self::Desc => vec![
+                ("flags", uint()),
+                ("cull_mask", uint()),
+                ("t_min", float()),
+                ("t_max", float()),
+                ("origin", vector(Vs::Tri)),
+                ("dir", vector(Vs::Tri)),
+            ],
+            Self::Intersection => vec![
+                ("kind", uint()),
+                ("t", float()),
+                ("instance_custom_index", uint()),
+                ("instance_id", uint()),
+                ("sbt_record_offset", uint()),
+                ("geometry_index", uint()),
+                ("primitive_index", uint()),
+                ("barycentrics", vector(Vs::Bi)),
+                (
+                    "front_face",
+                    Ti::Scalar {
+                        kind: Sk::Bool,
+                        width: crate::BOOL_WIDTH,
+                    },
+                ),
+                ("object_to_world", transform()),
+                ("world_to_object", transform()),
+            ],
+        }
+    }
+
+    /// Find or add the structure type in `types`.
+    pub fn generate(
+        self,
+        types: &mut Arena<crate::Type>,
+        constants: &Arena<crate::Constant>,
+    ) -> Handle<crate::Type> {
+        let mut layouter = Layouter::default();
+        let mut members = Vec::new();
+        let mut offset = 0;
+        let mut alignment = super::Alignment::new(1).unwrap();
+
+        for (name, inner) in self.members() {
+            let ty = types.fetch_or_append(crate::Type { name: None, inner });
+            // all the member types are scalars, vectors or matrices
+            layouter.update(types, constants).unwrap();
+            let (range, align) = layouter.member_placement(offset, ty, None, None);
+            alignment = alignment.max(align);
+            offset = range.end;
+            members.push(crate::StructMember {
+                name: Some(name.to_string()),
+                ty,
+                binding: None,
+                offset: range.start,
+            });
+        }
+
+        types.fetch_or_append(crate::Type {
+            name: Some(self.name().to_string()),
+            inner: crate::TypeInner::Struct {
+                top_level: false,
+                members,
+                span: Layouter::round_up(alignment, offset),
+            },
+        })
+    }
+
+    /// Check if `ty` is a structure with the members of this one.
+    pub fn matches(self, types: &Arena<crate::Type>, ty: Handle<crate::Type>) -> bool {
+        match types[ty].inner {
+            crate::TypeInner::Struct { ref members, .. } => {
+                let expected = self.members();
+                members.len() == expected.len()
+                    && members
+                        .iter()
+                        .zip(expected.iter())
+                        .all(|(member, expected)| types[member.ty].inner == expected.1)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_desc_layout() {
+        let mut types = Arena::new();
+        let ty = RayQueryStruct::Desc.generate(&mut types, &Arena::new());
+        assert!(RayQueryStruct::Desc.matches(&types, ty));
+        assert!(!RayQueryStruct::Intersection.matches(&types, ty));
+        match types[ty].inner {
+            crate::TypeInner::Struct {
+                ref members, span, ..
+            } => {
+                let offsets = members.iter().map(|m| m.offset).collect::<Vec<_>>();
+                assert_eq!(offsets, [0, 4, 8, 12, 16, 32]);
+                assert_eq!(span, 48);
+            }
+            _ => unreachable!(),
+        }
+        // generating again finds the same type
+        assert_eq!(RayQueryStruct::Desc.generate(&mut types, &Arena::new()), ty);
+    }
+}
@@ -0,0 +1,669 @@
+//! Reflection of the pipeline interface of entry points.
+
+use super::{InvalidBaseType, Layouter};
+use crate::{
+    arena::Handle,
+    valid::{GlobalUse, ModuleInfo, ShaderStages},
+};
+use std::collections::BTreeMap;
+
+/// Reflection of all the entry points of a module.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct ModuleReflection {
+    /// Reflection of each entry point, in the order of [`Module::entry_points`].
+    ///
+    /// [`Module::entry_points`]: crate::Module::entry_points
+    pub entry_points: Vec<EntryPointReflection>,
+}
+
+/// Pipeline interface of a single entry point.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct EntryPointReflection {
+    pub name: String,
+    pub stage: crate::ShaderStage,
+    /// Workgroup size, for the compute-like stages.
+    pub workgroup_size: [u32; 3],
+    /// Resources used by the entry point, sorted by group and binding.
+    pub resources: Vec<ResourceReflection>,
+    /// Inputs with a location, for the vertex stage.
+    pub vertex_inputs: Vec<InterfaceVariable>,
+    /// Outputs with a location, for the fragment stage.
+    pub fragment_outputs: Vec<InterfaceVariable>,
+    /// Push constant blocks used by the entry point.
+    pub push_constants: Vec<PushConstantRange>,
+}
+
+/// Resource bound to a group and a binding.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct ResourceReflection {
+    pub name: Option<String>,
+    pub group: u32,
+    pub binding: u32,
+    /// Type of the global variable.
+    pub ty: Handle<crate::Type>,
+    pub kind: ResourceKind,
+    /// Declared access of storage buffers and storage images.
+    pub access: crate::StorageAccess,
+    /// How the entry point uses the resource.
+    pub usage: GlobalUse,
+    /// Whether the binding may use a dynamic offset, which is true for buffers.
+    pub dynamic_offset: bool,
+    /// Minimum size of the bound buffer. For a runtime-sized array, this
+    /// includes a single element.
+    pub min_binding_size: Option<u32>,
+    /// Number of elements of a binding array with a fixed size.
+    pub count: Option<u32>,
+}
+
+/// Kind of a bound resource, as needed for a bind group layout entry.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub enum ResourceKind {
+    UniformBuffer,
+    StorageBuffer,
+    Sampler {
+        comparison: bool,
+    },
+    Image {
+        dim: crate::ImageDimension,
+        arrayed: bool,
+        class: crate::ImageClass,
+    },
+    AccelerationStructure,
+}
+
+/// Input or output of an entry point at a location.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct InterfaceVariable {
+    pub name: Option<String>,
+    pub location: u32,
+    /// Whether this is the second source of dual-source blending.
+    pub second_blend_source: bool,
+    pub format: InterfaceFormat,
+}
+
+/// Format of an interface variable, as seen by the shader.
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct InterfaceFormat {
+    pub kind: crate::ScalarKind,
+    pub width: crate::Bytes,
+    /// Number of components, which is 1 for scalars.
+    pub components: u8,
+}
+
+/// Push constant block of an entry point.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct PushConstantRange {
+    pub name: Option<String>,
+    pub size: u32,
+}
+
+/// Bind group layout shared by a set of entry points.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct BindGroupLayout {
+    pub group: u32,
+    /// Entries, sorted by binding.
+    pub entries: Vec<BindGroupLayoutEntry>,
+}
+
+/// Binding of a bind group layout, merged across entry points.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct BindGroupLayoutEntry {
+    pub binding: u32,
+    /// Stages of the entry points using the binding.
+    pub visibility: ShaderStages,
+    /// Type of the bound global variables.
+    pub ty: Handle<crate::Type>,
+    pub kind: ResourceKind,
+    pub access: crate::StorageAccess,
+    /// Union of the uses by all the entry points.
+    pub usage: GlobalUse,
+    pub dynamic_offset: bool,
+    pub min_binding_size: Option<u32>,
+    pub count: Option<u32>,
+}
+
+#[derive(Clone, Debug, thiserror::Error, PartialEq)]
+pub enum BindGroupError {
+    #[error(transparent)]
+    Layout(#[from] InvalidBaseType),
+    #[error("Entry point index {0} is out of bounds")]
+    InvalidEntryPoint(usize),
+    #[error("Binding {binding:?} is used as {first:?} and as {second:?}")]
+    KindMismatch {
+        binding: crate::ResourceBinding,
+        first: ResourceKind,
+        second: ResourceKind,
+    },
+    #[error("Binding {binding:?} is declared with type {first:?} and with type {second:?}")]
+    TypeMismatch {
+        binding: crate::ResourceBinding,
+        first: Handle<crate::Type>,
+        second: Handle<crate::Type>,
+    },
+    #[error("Binding {binding:?} is declared with access {first:?} and with access {second:?}")]
+    AccessMismatch {
+        binding: crate::ResourceBinding,
+        first: crate::StorageAccess,
+        second: crate::StorageAccess,
+    },
+    #[error("Binding {binding:?} is an array of {first:?} and of {second:?} elements")]
+    CountMismatch {
+        binding: crate::ResourceBinding,
+        first: Option<u32>,
+        second: Option<u32>,
+    },
+}
+
+/// Reflect the pipeline interface of all the entry points of a validated module.
+pub fn reflect(
+    module: &crate::Module,
+    info: &ModuleInfo,
+) -> Result<ModuleReflection, InvalidBaseType> {
+    let mut layouter = Layouter::default();
+    layouter.update(&module.types, &module.constants)?;
+
+    let entry_points = module
+        .entry_points
+        .iter()
+        .enumerate()
+        .map(|(index, ep)| {
+            let ep_info = info.get_entry_point(index);
+            let mut reflection = EntryPointReflection {
+                name: ep.name.clone(),
+                stage: ep.stage,
+                workgroup_size: ep.workgroup_size,
+                resources: Vec::new(),
+                vertex_inputs: Vec::new(),
+                fragment_outputs: Vec::new(),
+                push_constants: Vec::new(),
+            };
+
+            for (handle, var) in module.global_variables.iter() {
+                let usage = ep_info[handle];
+                if usage.is_empty() {
+                    continue;
+                }
+                if var.class == crate::StorageClass::PushConstant {
+                    reflection.push_constants.push(PushConstantRange {
+                        name: var.name.clone(),
+                        size: layouter[var.ty].size,
+                    });
+                }
+                if let Some(resource) = reflect_resource(module, &layouter, var, usage) {
+                    reflection.resources.push(resource);
+                }
+            }
+            reflection
+                .resources
+                .sort_by_key(|resource| (resource.group, resource.binding));
+
+            match ep.stage {
+                crate::ShaderStage::Vertex => {
+                    for argument in ep.function.arguments.iter() {
+                        collect_locations(
+                            module,
+                            argument.name.as_ref(),
+                            argument.binding.as_ref(),
+                            argument.ty,
+                            &mut reflection.vertex_inputs,
+                        );
+                    }
+                }
+                crate::ShaderStage::Fragment => {
+                    if let Some(ref result) = ep.function.result {
+                        collect_locations(
+                            module,
+                            None,
+                            result.binding.as_ref(),
+                            result.ty,
+                            &mut reflection.fragment_outputs,
+                        );
+                    }
+                }
+                _ => {}
+            }
+            reflection
+        })
+        .collect();
+
+    Ok(ModuleReflection { entry_points })
+}
+
+/// Merge the resources of the entry points with the given indices into bind
+/// group layouts, sorted by group.
+///
+/// A binding used by several entry points has to have the same type, kind,
+/// access and count in all of them. Its visibility covers all their stages.
+pub fn merge_bind_groups(
+    module: &crate::Module,
+    info: &ModuleInfo,
+    entry_points: &[usize],
+) -> Result<Vec<BindGroupLayout>, BindGroupError> {
+    let reflection = reflect(module, info)?;
+    let mut groups = BTreeMap::<u32, Vec<BindGroupLayoutEntry>>::new();
+    for &index in entry_points {
+        let ep = reflection
+            .entry_points
+            .get(index)
+            .ok_or(BindGroupError::InvalidEntryPoint(index))?;
+        let stage = ShaderStages::from(ep.stage);
+        for resource in ep.resources.iter() {
+            let entries = groups.entry(resource.group).or_default();
+            let entry = match entries
+                .iter_mut()
+                .find(|entry| entry.binding == resource.binding)
+            {
+                Some(entry) => entry,
+                None => {
+                    entries.push(BindGroupLayoutEntry {
+                        binding: resource.binding,
+                        visibility: stage,
+                        ty: resource.ty,
+                        kind: resource.kind.clone(),
+                        access: resource.access,
+                        usage: resource.usage,
+                        dynamic_offset: resource.dynamic_offset,
+                        min_binding_size: resource.min_binding_size,
+                        count: resource.count,
+                    });
+                    continue;
+                }
+            };
+            let binding = crate::ResourceBinding {
+                group: resource.group,
+                binding: resource.binding,
+            };
+            if entry.kind != resource.kind {
+                return Err(BindGroupError::KindMismatch {
+                    binding,
+                    first: entry.kind.clone(),
+                    second: resource.kind.clone(),
+                });
+            }
+            if entry.ty != resource.ty {
+                return Err(BindGroupError::TypeMismatch {
+                    binding,
+                    first: entry.ty,
+                    second: resource.ty,
+                });
+            }
+            if entry.access != resource.access {
+                return Err(BindGroupError::AccessMismatch {
+                    binding,
+                    first: entry.access,
+                    second: resource.access,
+                });
+            }
+            if entry.count != resource.count {
+                return Err(BindGroupError::CountMismatch {
+                    binding,
+                    first: entry.count,
+                    second: resource.count,
+                });
+            }
+            entry.visibility |= stage;
+            entry.usage |= resource.usage;
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|(group, mut entries)| {
+            entries.sort_by_key(|entry| entry.binding);
+            BindGroupLayout { group, entries }
+        })
+        .collect())
+}
+
+fn reflect_resource(
+    module: &crate::Module,
+    layouter: &Layouter,
+    var: &crate::GlobalVariable,
+    usage: GlobalUse,
+) -> Option<ResourceReflection> {
+    let binding = var.binding.as_ref()?;
+    // look through binding arrays for the kind of the elements
+    let (base, count) = match module.types[var.ty].inner {
+        crate::TypeInner::BindingArray { base, size } => {
+            let count = match size {
+                crate::ArraySize::Constant(handle) => module.constants[handle].to_array_length(),
+                crate::ArraySize::Dynamic => None,
+            };
+            (base, count)
+        }
+        _ => (var.ty, None),
+    };
+    let kind = match var.class {
+        crate::StorageClass::Uniform => ResourceKind::UniformBuffer,
+        crate::StorageClass::Storage => ResourceKind::StorageBuffer,
+        crate::StorageClass::Handle => match module.types[base].inner {
+            crate::TypeInner::Sampler { comparison } => ResourceKind::Sampler { comparison },
+            crate::TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            } => ResourceKind::Image {
+                dim,
+                arrayed,
+                class,
+            },
+            crate::TypeInner::AccelerationStructure => ResourceKind::AccelerationStructure,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let is_buffer = match kind {
+        ResourceKind::UniformBuffer | ResourceKind::StorageBuffer => true,
+        _ => false,
+    };
+    let access = match kind {
+        ResourceKind::StorageBuffer
+        | ResourceKind::Image {
+            class: crate::ImageClass::Storage(_),
+            ..
+        } => var.storage_access,
+        _ => crate::StorageAccess::empty(),
+    };
+
+    Some(ResourceReflection {
+        name: var.name.clone(),
+        group: binding.group,
+        binding: binding.binding,
+        ty: var.ty,
+        kind,
+        access,
+        usage,
+        dynamic_offset: is_buffer && count.is_none(),
+        min_binding_size: if is_buffer {
+            Some(min_buffer_size(module, layouter, base))
+        } else {
+            None
+        },
+        count,
+    })
+}
+
+/// Return the minimum size of a buffer holding `ty`.
+fn min_buffer_size(module: &crate::Module, layouter: &Layouter, ty: Handle<crate::Type>) -> u32 {
+    let size = layouter[ty].size;
+    match module.types[ty].inner {
+        // the last member may be a runtime-sized array, which needs an element
+        crate::TypeInner::Struct { ref members, .. } => match members.last() {
+            Some(last) => match module.types[last.ty].inner {
+                crate::TypeInner::Array {
+                    size: crate::ArraySize::Dynamic,
+                    stride,
+                    ..
+                } => size.max(last.offset + stride),
+                _ => size,
+            },
+            None => size,
+        },
+        _ => size,
+    }
+}
+
+/// Collect the variables with a location among `ty` and its struct members.
+fn collect_locations(
+    module: &crate::Module,
+    name: Option<&String>,
+    binding: Option<&crate::Binding>,
+    ty: Handle<crate::Type>,
+    variables: &mut Vec<InterfaceVariable>,
+) {
+    let inner = &module.types[ty].inner;
+    if let crate::TypeInner::Struct { ref members, .. } = *inner {
+        for member in members {
+            collect_locations(
+                module,
+                member.name.as_ref(),
+                member.binding.as_ref(),
+                member.ty,
+                variables,
+            );
+        }
+        return;
+    }
+    let (location, second_blend_source) = match binding {
+        Some(&crate::Binding::Location {
+            location,
+            second_blend_source,
+            ..
+        }) => (location, second_blend_source),
+        _ => return,
+    };
+    let format = match *inner {
+        crate::TypeInner::Scalar { kind, width } => InterfaceFormat {
+            kind,
+            width,
+            components: 1,
+        },
+        crate::TypeInner::Vector { size, kind, width } => InterfaceFormat {
+            kind,
+            width,
+            components: size as u8,
+        },
+        _ => return,
+    };
+    variables.push(InterfaceVariable {
+        name: name.cloned(),
+        location,
+        second_blend_source,
+        format,
+    });
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    use super::{InterfaceFormat, ResourceKind};
+    use crate::valid::{Capabilities, GlobalUse, ValidationFlags, Validator};
+
+    #[test]
+    fn reflect_entry_points() {
+        let source = "
+            [[block]]
+            struct Uniforms { scale: f32; };
+            [[block]]
+            struct Particles { count: u32; data: [[stride(16)]] array<vec4<f32>>; };
+            struct VertexOutput {
+                [[builtin(position)]] position: vec4<f32>;
+                [[location(0)]] uv: vec2<f32>;
+            };
+
+            [[group(1), binding(2)]] var<uniform> uniforms: Uniforms;
+            [[group(0), binding(1)]] var<storage> particles: [[access(read)]] Particles;
+            [[group(0), binding(3)]] var tex: texture_2d<f32>;
+            [[group(0), binding(4)]] var samp: sampler;
+
+            [[stage(vertex)]]
+            fn vs_main([[location(0)]] pos: vec2<f32>, [[location(1)]] index: u32) -> VertexOutput {
+                let p = particles.data[index];
+                return VertexOutput(vec4<f32>(pos * uniforms.scale, 0.0, 1.0) + p, pos);
+            }
+
+            [[stage(fragment)]]
+            fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+                return textureSample(tex, samp, in.uv);
+            }
+
+            [[stage(compute), workgroup_size(8, 4)]]
+            fn cs_main() {}
+        ";
+        let module = crate::front::wgsl::parse_str(source).unwrap();
+        let info = Validator::new(ValidationFlags::all(), Capabilities::empty())
+            .validate(&module)
+            .unwrap();
+        let reflection = super::reflect(&module, &info).unwrap();
+        let (vs, fs, cs) = match reflection.entry_points[..] {
+            [ref vs, ref fs, ref cs] => (vs, fs, cs),
+            _ => unreachable!(),
+        };
+
+        let bindings = |resources: &[super::ResourceReflection]| {
+            resources
+                .iter()
+                .map(|r| (r.group, r.binding))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(bindings(&vs.resources), [(0, 1), (1, 2)]);
+        let particles = &vs.resources[0];
+        assert_eq!(particles.kind, ResourceKind::StorageBuffer);
+        assert_eq!(particles.access, crate::StorageAccess::LOAD);
+        assert_eq!(particles.usage, GlobalUse::READ);
+        assert!(particles.dynamic_offset);
+        assert_eq!(particles.min_binding_size, Some(32));
+        assert_eq!(vs.resources[1].min_binding_size, Some(4));
+        assert_eq!(
+            vs.vertex_inputs
+                .iter()
+                .map(|input| (input.location, input.format))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    0,
+                    InterfaceFormat {
+                        kind: crate::ScalarKind::Float,
+                        width: 4,
+                        components: 2,
+                    }
+                ),
+                (
+                    1,
+                    InterfaceFormat {
+                        kind: crate::ScalarKind::Uint,
+                        width: 4,
+                        components: 1,
+                    }
+                ),
+            ]
+        );
+
+        assert_eq!(bindings(&fs.resources), [(0, 3), (0, 4)]);
+        assert!(!fs.resources[0].dynamic_offset);
+        assert_eq!(
+            fs.resources[1].kind,
+            ResourceKind::Sampler { comparison: false }
+        );
+        assert_eq!(fs.fragment_outputs.len(), 1);
+        assert_eq!(fs.fragment_outputs[0].format.components, 4);
+
+        assert!(cs.resources.is_empty());
+        assert_eq!(cs.workgroup_size, [8, 4, 1]);
+    }
+
+    #[test]
+    fn merge_bind_groups() {
+        use super::BindGroupError;
+        use crate::valid::ShaderStages;
+
+        let source = "
+            [[block]]
+            struct Data { values: [[stride(4)]] array<f32, 4>; };
+            [[block]]
+            struct Small { value: f32; };
+
+            [[group(0), binding(0)]] var<uniform> data: Data;
+            [[group(0), binding(0)]] var<uniform> small: Small;
+            [[group(0), binding(1)]] var<storage> out: [[access(read_write)]] Data;
+            [[group(0), binding(1)]] var<uniform> out_uniform: Small;
+
+            [[stage(vertex)]]
+            fn vs_main() -> [[builtin(position)]] vec4<f32> {
+                return vec4<f32>(data.values[0]);
+            }
+
+            [[stage(fragment)]]
+            fn fs_main() -> [[location(0)]] vec4<f32> {
+                return vec4<f32>(data.values[1]);
+            }
+
+            [[stage(compute), workgroup_size(1)]]
+            fn cs_main() {
+                out.values[0] = data.values[0];
+            }
+
+            [[stage(compute), workgroup_size(1)]]
+            fn cs_uniform() {
+                let value = out_uniform.value;
+            }
+
+            [[stage(fragment)]]
+            fn fs_small() -> [[location(0)]] vec4<f32> {
+                return vec4<f32>(small.value);
+            }
+        ";
+        let module = crate::front::wgsl::parse_str(source).unwrap();
+        let info = Validator::new(ValidationFlags::all(), Capabilities::empty())
+            .validate(&module)
+            .unwrap();
+
+        let groups = super::merge_bind_groups(&module, &info, &[0, 1]).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].group, 0);
+        let entry = &groups[0].entries[0];
+        assert_eq!(entry.binding, 0);
+        assert_eq!(
+            entry.visibility,
+            ShaderStages::VERTEX | ShaderStages::FRAGMENT
+        );
+        assert_eq!(entry.kind, ResourceKind::UniformBuffer);
+        assert_eq!(entry.min_binding_size, Some(16));
+
+        let groups = super::merge_bind_groups(&module, &info, &[2]).unwrap();
+        assert_eq!(groups[0].entries.len(), 2);
+        assert_eq!(groups[0].entries[1].usage, GlobalUse::WRITE);
+
+        assert_eq!(
+            super::merge_bind_groups(&module, &info, &[2, 3]),
+            Err(BindGroupError::KindMismatch {
+                binding: crate::ResourceBinding {
+                    group: 0,
+                    binding: 1,
+                },
+                first: ResourceKind::StorageBuffer,
+                second: ResourceKind::UniformBuffer,
+            })
+        );
+        let ty = |name: &str| {
+            let (_, global) = module
+                .global_variables
+                .iter()
+                .find(|&(_, global)| global.name.as_deref() == Some(name))
+                .unwrap();
+            global.ty
+        };
+        assert_eq!(
+            super::merge_bind_groups(&module, &info, &[0, 4]),
+            Err(BindGroupError::TypeMismatch {
+                binding: crate::ResourceBinding {
+                    group: 0,
+                    binding: 0,
+                },
+                first: ty("data"),
+                second: ty("small"),
+            })
+        );
+        assert_eq!(
+            super::merge_bind_groups(&module, &info, &[5]),
+            Err(BindGroupError::InvalidEntryPoint(5))
+        );
+    }
+}
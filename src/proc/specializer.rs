@@ -0,0 +1,245 @@
+use crate::{arena::Handle, FastHashMap};
+
+#[derive(Clone, Debug, thiserror::Error, PartialEq)]
+pub enum SpecializationError {
+    #[error("specializable constant {0:?} is not a scalar")]
+    NotScalar(Handle<crate::Constant>),
+    #[error("value for specialization ID {id} is {given:?}, but the constant is {expected:?}")]
+    KindMismatch {
+        id: u32,
+        expected: crate::ScalarKind,
+        given: crate::ScalarKind,
+    },
+    #[error("derived constant {0:?} can't be evaluated")]
+    Evaluation(
+        Handle<crate::Constant>,
+        #[source] super::ConstantEvaluatorError,
+    ),
+}
+
+/// Fold the values of pipeline-overridable constants into the module.
+///
+/// Every constant with a [`specialization`] ID present in `values` gets the
+/// given value, while the others keep their default. In both cases the
+/// constants lose their specialization ID, so the resulting module is
+/// concrete, and back ends treat them as regular constants. The
+/// [`derived_constants`] are then evaluated again, so that they follow
+/// the new values.
+///
+/// [`specialization`]: crate::Constant::specialization
+/// [`derived_constants`]: crate::Module::derived_constants
+pub fn specialize(
+    module: &mut crate::Module,
+    values: &FastHashMap<u32, crate::ScalarValue>,
+) -> Result<(), SpecializationError> {
+    // check everything first, so that the module is untouched on error
+    for (handle, constant) in module.constants.iter() {
+        let id = match constant.specialization {
+            Some(id) => id,
+            None => continue,
+        };
+        let expected = match constant.inner {
+            crate::ConstantInner::Scalar { width: _, value } => value.scalar_kind(),
+            crate::ConstantInner::Composite { .. } => {
+                return Err(SpecializationError::NotScalar(handle))
+            }
+        };
+        if let Some(given) = values.get(&id).map(crate::ScalarValue::scalar_kind) {
+            if given != expected {
+                return Err(SpecializationError::KindMismatch {
+                    id,
+                    expected,
+                    given,
+                });
+            }
+        }
+    }
+
+    // work on copies, since evaluating the derived constants may fail
+    let mut types = module.types.clone();
+    let mut constants = module.constants.clone();
+    let mut given = vec![false; constants.len()];
+    for (handle, constant) in constants.iter_mut() {
+        if let Some(id) = constant.specialization.take() {
+            if let crate::ConstantInner::Scalar {
+                width: _,
+                ref mut value,
+            } = constant.inner
+            {
+                if let Some(&new_value) = values.get(&id) {
+                    *value = new_value;
+                    given[handle.index()] = true;
+                }
+            }
+        }
+    }
+
+    for derived in module.derived_constants.iter() {
+        // a WGSL override initialized from other ones may be given a value
+        if given[derived.constant.index()] {
+            continue;
+        }
+        let value = super::ConstantEvaluator {
+            types: &mut types,
+            constants: &mut constants,
+            expressions: &derived.expressions,
+        }
+        .eval(derived.root)
+        .map_err(|error| SpecializationError::Evaluation(derived.constant, error))?;
+        let inner = constants[value].inner.clone();
+        constants.get_mut(derived.constant).inner = inner;
+    }
+
+    module.types = types;
+    module.constants = constants;
+    module.derived_constants.clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specialize_constants() {
+        let mut module = crate::Module::default();
+        let gain = module.constants.append(crate::Constant {
+            name: Some("gain".to_string()),
+            specialization: Some(0),
+            inner: crate::ConstantInner::Scalar {
+                width: 4,
+                value: crate::ScalarValue::Float(1.0),
+            },
+        });
+        let count = module.constants.append(crate::Constant {
+            name: Some("count".to_string()),
+            specialization: Some(1),
+            inner: crate::ConstantInner::Scalar {
+                width: 4,
+                value: crate::ScalarValue::Uint(4),
+            },
+        });
+
+        let mut values = FastHashMap::default();
+        values.insert(1, crate::ScalarValue::Float(2.0));
+        assert_eq!(
+            specialize(&mut module, &values),
+            Err(SpecializationError::KindMismatch {
+                id: 1,
+                expected: crate::ScalarKind::Uint,
+                given: crate::ScalarKind::Float,
+            })
+        );
+
+        assert_eq!(module.constants[gain].specialization, Some(0));
+
+        values.insert(1, crate::ScalarValue::Uint(8));
+        specialize(&mut module, &values).unwrap();
+        assert_eq!(module.constants[gain].specialization, None);
+        assert_eq!(
+            module.constants[count],
+            crate::Constant {
+                name: Some("count".to_string()),
+                specialization: None,
+                inner: crate::ConstantInner::Scalar {
+                    width: 4,
+                    value: crate::ScalarValue::Uint(8),
+                },
+            }
+        );
+    }
+
+    #[cfg(feature = "wgsl-in")]
+    #[test]
+    fn specialize_derived_constants() {
+        let mut module = crate::front::wgsl::parse_str(
+            "
+            [[id(0)]] override width: u32 = 4u;
+            override scale: f32 = 1.0;
+            override height = width * 2u;
+            let area: u32 = width * height;
+            let extent: vec2<f32> = vec2<f32>(scale, 0.5) * 2.0;
+            ",
+        )
+        .unwrap();
+        let find = |module: &crate::Module, name: &str| {
+            module
+                .constants
+                .fetch_if(|c| c.name.as_deref() == Some(name))
+                .unwrap()
+        };
+        let scalar =
+            |module: &crate::Module, name: &str| match module.constants[find(module, name)].inner {
+                crate::ConstantInner::Scalar { value, .. } => value,
+                crate::ConstantInner::Composite { .. } => panic!("{} is not a scalar", name),
+            };
+        assert_eq!(module.derived_constants.len(), 3);
+
+        let mut values = FastHashMap::default();
+        values.insert(0, crate::ScalarValue::Uint(3));
+        values.insert(1, crate::ScalarValue::Float(4.0));
+        let mut given_height = module.clone();
+        specialize(&mut module, &values).unwrap();
+        assert!(module.derived_constants.is_empty());
+        assert_eq!(scalar(&module, "height"), crate::ScalarValue::Uint(6));
+        assert_eq!(scalar(&module, "area"), crate::ScalarValue::Uint(18));
+        match module.constants[find(&module, "extent")].inner {
+            crate::ConstantInner::Composite { ref components, .. } => {
+                let x = &module.constants[components[0]].inner;
+                assert_eq!(
+                    *x,
+                    crate::ConstantInner::Scalar {
+                        width: 4,
+                        value: crate::ScalarValue::Float(8.0),
+                    }
+                );
+            }
+            crate::ConstantInner::Scalar { .. } => panic!("extent is not a composite"),
+        }
+
+        // the value given to an override wins over its initializer
+        values.insert(2, crate::ScalarValue::Uint(10));
+        specialize(&mut given_height, &values).unwrap();
+        assert_eq!(
+            scalar(&given_height, "height"),
+            crate::ScalarValue::Uint(10)
+        );
+        assert_eq!(scalar(&given_height, "area"), crate::ScalarValue::Uint(30));
+    }
+
+    #[cfg(all(feature = "wgsl-in", feature = "spv-out"))]
+    #[test]
+    fn write_specialized_module() {
+        let mut module = crate::front::wgsl::parse_str(
+            "
+            override width: u32 = 4u;
+            let area: u32 = width * width;
+            [[stage(compute), workgroup_size(1)]]
+            fn main() {
+                var x: u32 = area;
+            }
+            ",
+        )
+        .unwrap();
+        let validate = |module: &crate::Module| {
+            crate::valid::Validator::new(
+                crate::valid::ValidationFlags::all(),
+                crate::valid::Capabilities::empty(),
+            )
+            .validate(module)
+            .unwrap()
+        };
+        let options = crate::back::spv::Options::default();
+
+        // the backends can't write the derived constants
+        let info = validate(&module);
+        assert!(matches!(
+            crate::back::spv::write_vec(&module, &info, &options),
+            Err(crate::back::spv::Error::DerivedConstants)
+        ));
+
+        specialize(&mut module, &FastHashMap::default()).unwrap();
+        let info = validate(&module);
+        crate::back::spv::write_vec(&module, &info, &options).unwrap();
+    }
+}
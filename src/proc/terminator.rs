@@ -39,6 +39,12 @@ pub fn ensure_block_returns(block: &mut crate::Block) {
         | Some(&mut S::Store { .. })
         | Some(&mut S::ImageStore { .. })
         | Some(&mut S::Call { .. })
+        | Some(&mut S::Atomic { .. })
+        | Some(&mut S::SubgroupBallot { .. })
+        | Some(&mut S::SubgroupCollectiveOperation { .. })
+        | Some(&mut S::SubgroupGather { .. })
+        | Some(&mut S::MeshFunction(_))
+        | Some(&mut S::RayQuery { .. })
         | Some(&mut S::Barrier(_))
         | None => block.push(S::Return { value: None }),
     }
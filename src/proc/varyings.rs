@@ -0,0 +1,1473 @@
+use super::{
+    compact::{compact_function, expression_uses},
+    Alignment, InvalidBaseType, Layouter,
+};
+use crate::{
+    arena::{Arena, Handle},
+    builder::{BuildError, FunctionBuilder},
+};
+
+/// Return the sorted locations of the inputs that the fragment entry point
+/// `ep` of `module` actually reads.
+///
+/// An input counts as read if an expression that statements depend on uses
+/// it. For struct arguments, this is decided per member, as long as the
+/// struct is only accessed member by member.
+pub fn used_fragment_inputs(module: &crate::Module, ep: &crate::EntryPoint) -> Vec<u32> {
+    let function = &ep.function;
+    let live = live_expressions(module, function);
+    let uses = expression_uses(module, function);
+    let argument_of = |handle: Handle<crate::Expression>| match function.expressions[handle] {
+        crate::Expression::FunctionArgument(index) => Some(index as usize),
+        _ => None,
+    };
+
+    // arguments read as a whole, and the members read of the others
+    let mut whole = vec![false; function.arguments.len()];
+    let mut members = vec![Vec::new(); function.arguments.len()];
+    for &handle in uses.statements.iter() {
+        if let Some(argument) = argument_of(handle) {
+            whole[argument] = true;
+        }
+    }
+    for (handle, expression) in function.expressions.iter() {
+        if !live[handle.index()] {
+            continue;
+        }
+        if let crate::Expression::AccessIndex { base, index } = *expression {
+            if let Some(argument) = argument_of(base) {
+                members[argument].push(index);
+                continue;
+            }
+        }
+        for &operand in uses.operands[handle.index()].iter() {
+            if let Some(argument) = argument_of(operand) {
+                whole[argument] = true;
+            }
+        }
+    }
+
+    let mut locations = Vec::new();
+    for (index, argument) in function.arguments.iter().enumerate() {
+        match argument.binding {
+            Some(crate::Binding::Location { location, .. }) => {
+                if whole[index] || !members[index].is_empty() {
+                    locations.push(location);
+                }
+            }
+            Some(crate::Binding::BuiltIn(_)) => {}
+            None => {
+                if let crate::TypeInner::Struct {
+                    members: ref struct_members,
+                    ..
+                } = module.types[argument.ty].inner
+                {
+                    for (member_index, member) in struct_members.iter().enumerate() {
+                        if let Some(crate::Binding::Location { location, .. }) = member.binding {
+                            if whole[index] || members[index].contains(&(member_index as u32)) {
+                                locations.push(location);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    locations.sort_unstable();
+    locations.dedup();
+    locations
+}
+
+/// Remove the varyings of the entry point at index `entry_point` of
+/// `module` whose locations are not in `used_locations`.
+///
+/// For a vertex entry point, these are the members of its result struct,
+/// and for a fragment entry point, its inputs. Other stages are left as
+/// they are. Given the result of [`used_fragment_inputs`], calling this for
+/// both entry points of a pipeline, in the same module or not, removes the
+/// varyings that don't affect the output.
+///
+/// Removed struct members are dropped from new copies of the struct types,
+/// and the expressions and local variables of the entry point that only fed
+/// removed outputs are pruned. This includes the outputs written to a local
+/// variable that is returned afterwards, as long as it's only written and
+/// returned. The rest of the module is left as it is, so the struct types
+/// that were replaced stay in it until [`compact`](super::compact) is called.
+pub fn remove_unused_varyings(
+    module: &mut crate::Module,
+    entry_point: usize,
+    used_locations: &[u32],
+) {
+    let is_used = |binding: Option<&crate::Binding>| match binding {
+        Some(&crate::Binding::Location { location, .. }) => used_locations.contains(&location),
+        _ => true,
+    };
+
+    let output_locals = match module.entry_points[entry_point].stage {
+        crate::ShaderStage::Vertex => {
+            output_locals(module, &module.entry_points[entry_point].function, is_used)
+        }
+        _ => Vec::new(),
+    };
+    {
+        let crate::Module {
+            ref mut types,
+            ref mut entry_points,
+            ..
+        } = *module;
+        let ep = &mut entry_points[entry_point];
+        match ep.stage {
+            crate::ShaderStage::Vertex => {
+                remove_outputs(types, &mut ep.function, &output_locals, is_used)
+            }
+            crate::ShaderStage::Fragment => remove_inputs(types, &mut ep.function, is_used),
+            _ => {}
+        }
+    }
+
+    // names keep expressions alive, so drop the ones of dead expressions
+    let mut function = std::mem::take(&mut module.entry_points[entry_point].function);
+    let live = live_expressions(module, &function);
+    function
+        .named_expressions
+        .retain(|handle, _| live[handle.index()]);
+    compact_function(module, &mut function);
+    module.entry_points[entry_point].function = function;
+}
+
+/// Return which expressions of `function` the statements depend on.
+fn live_expressions(module: &crate::Module, function: &crate::Function) -> Vec<bool> {
+    let uses = expression_uses(module, function);
+    let mut live = vec![false; function.expressions.len()];
+    let mut stack = uses.statements;
+    while let Some(handle) = stack.pop() {
+        if !std::mem::replace(&mut live[handle.index()], true) {
+            stack.extend_from_slice(&uses.operands[handle.index()]);
+        }
+    }
+    live
+}
+
+/// Return the local variables of the struct type returned by the vertex entry
+/// point `function` that are only written, as a whole or member by member,
+/// and loaded to be returned, so that the stores to the removed outputs can
+/// be dropped.
+fn output_locals(
+    module: &crate::Module,
+    function: &crate::Function,
+    is_used: impl Fn(Option<&crate::Binding>) -> bool,
+) -> Vec<Handle<crate::LocalVariable>> {
+    let ty = match function.result {
+        Some(ref result) if result.binding.is_none() => result.ty,
+        _ => return Vec::new(),
+    };
+    let removed = match module.types[ty].inner {
+        crate::TypeInner::Struct { ref members, .. } => members
+            .iter()
+            .map(|member| !is_used(member.binding.as_ref()))
+            .collect::<Vec<_>>(),
+        _ => return Vec::new(),
+    };
+
+    let uses = expression_uses(module, function);
+    let mut users = vec![Vec::new(); function.expressions.len()];
+    for ((handle, _), operands) in function.expressions.iter().zip(uses.operands.iter()) {
+        for &operand in operands.iter() {
+            users[operand.index()].push(handle);
+        }
+    }
+    let (mut stores, mut returns) = (Vec::new(), Vec::new());
+    collect_stores_and_returns(&function.body, &mut stores, &mut returns);
+    let count = |list: &[Handle<crate::Expression>], handle| {
+        list.iter().filter(|&&other| other == handle).count()
+    };
+    // statements only use `handle` in the way `list` collects
+    let only_used_by = |list: &[Handle<crate::Expression>], handle: Handle<crate::Expression>| {
+        users[handle.index()].is_empty() && count(&uses.statements, handle) == count(list, handle)
+    };
+
+    let mut locals = function
+        .local_variables
+        .iter()
+        .map(|(_, local)| local.ty == ty)
+        .collect::<Vec<_>>();
+    for (handle, expression) in function.expressions.iter() {
+        let local = match *expression {
+            crate::Expression::LocalVariable(local) => local,
+            _ => continue,
+        };
+        let is_output = count(&uses.statements, handle) == count(&stores, handle)
+            && users[handle.index()]
+                .iter()
+                .all(|&user| match function.expressions[user] {
+                    crate::Expression::AccessIndex { index, .. } => {
+                        !removed[index as usize] || only_used_by(&stores, user)
+                    }
+                    crate::Expression::Load { .. } => only_used_by(&returns, user),
+                    _ => false,
+                });
+        if !is_output {
+            locals[local.index()] = false;
+        }
+    }
+    function
+        .local_variables
+        .iter()
+        .filter(|&(handle, _)| locals[handle.index()])
+        .map(|(handle, _)| handle)
+        .collect()
+}
+
+/// Collect the pointers stored to and the values returned in `block`.
+fn collect_stores_and_returns(
+    block: &[crate::Statement],
+    stores: &mut Vec<Handle<crate::Expression>>,
+    returns: &mut Vec<Handle<crate::Expression>>,
+) {
+    use crate::Statement as S;
+    for statement in block {
+        match *statement {
+            S::Block(ref block) => collect_stores_and_returns(block, stores, returns),
+            S::If {
+                ref accept,
+                ref reject,
+                ..
+            } => {
+                collect_stores_and_returns(accept, stores, returns);
+                collect_stores_and_returns(reject, stores, returns);
+            }
+            S::Switch {
+                ref cases,
+                ref default,
+                ..
+            } => {
+                for case in cases.iter() {
+                    collect_stores_and_returns(&case.body, stores, returns);
+                }
+                collect_stores_and_returns(default, stores, returns);
+            }
+            S::Loop {
+                ref body,
+                ref continuing,
+            } => {
+                collect_stores_and_returns(body, stores, returns);
+                collect_stores_and_returns(continuing, stores, returns);
+            }
+            S::Store { pointer, .. } => stores.push(pointer),
+            S::Return { value: Some(value) } => returns.push(value),
+            _ => {}
+        }
+    }
+}
+
+/// Make a copy of the struct type `ty` with only the members for which
+/// `keep` is true, and return it along with the new indices of the old
+/// members. Return `None` if all the members are kept.
+fn retain_members(
+    types: &mut Arena<crate::Type>,
+    ty: Handle<crate::Type>,
+    mut keep: impl FnMut(&crate::StructMember) -> bool,
+) -> Option<(Handle<crate::Type>, Vec<Option<u32>>)> {
+    let (members, span) = match types[ty].inner {
+        crate::TypeInner::Struct {
+            top_level: false,
+            ref members,
+            span,
+        } => (members, span),
+        _ => return None,
+    };
+    let mut kept = Vec::new();
+    let indices = members
+        .iter()
+        .map(|member| {
+            if keep(member) {
+                kept.push(member.clone());
+                Some(kept.len() as u32 - 1)
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    if kept.len() == members.len() {
+        return None;
+    }
+    // the offsets of the remaining members stay valid
+    let new_ty = types.fetch_or_append(crate::Type {
+        name: types[ty].name.clone(),
+        inner: crate::TypeInner::Struct {
+            top_level: false,
+            members: kept,
+            span,
+        },
+    });
+    Some((new_ty, indices))
+}
+
+fn remove_outputs(
+    types: &mut Arena<crate::Type>,
+    function: &mut crate::Function,
+    output_locals: &[Handle<crate::LocalVariable>],
+    is_used: impl Fn(Option<&crate::Binding>) -> bool,
+) {
+    let old_ty = match function.result {
+        Some(ref result) if result.binding.is_none() => result.ty,
+        _ => return,
+    };
+    let (new_ty, indices) =
+        match retain_members(types, old_ty, |member| is_used(member.binding.as_ref())) {
+            Some(pair) => pair,
+            None => return,
+        };
+    let kept = indices
+        .iter()
+        .enumerate()
+        .filter(|&(_, new_index)| new_index.is_some())
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    if let Some(ref mut result) = function.result {
+        result.ty = new_ty;
+    }
+    let rewriter = ReturnRewriter {
+        old_ty,
+        new_ty,
+        kept: &kept,
+        output_locals,
+    };
+    rewriter.rewrite_block(&mut function.body, &mut function.expressions);
+}
+
+/// Rewrites the returned values to the struct type without the removed outputs.
+struct ReturnRewriter<'a> {
+    old_ty: Handle<crate::Type>,
+    new_ty: Handle<crate::Type>,
+    /// Indices of the kept members of the old struct.
+    kept: &'a [usize],
+    /// Local variables of the old struct type that are only written and
+    /// returned, as found by [`output_locals`].
+    output_locals: &'a [Handle<crate::LocalVariable>],
+}
+
+/// Change made by [`ReturnRewriter`] to a statement.
+enum Rewrite {
+    Keep,
+    /// Emit the range before the statement.
+    Emit(crate::Range<crate::Expression>),
+    Remove,
+    Replace(Vec<crate::Statement>),
+}
+
+impl ReturnRewriter<'_> {
+    fn rewrite_block(&self, block: &mut crate::Block, expressions: &mut Arena<crate::Expression>) {
+        use crate::Statement as S;
+        let mut index = 0;
+        while index < block.len() {
+            let rewrite = match block[index] {
+                S::Block(ref mut block) => {
+                    self.rewrite_block(block, expressions);
+                    Rewrite::Keep
+                }
+                S::If {
+                    ref mut accept,
+                    ref mut reject,
+                    ..
+                } => {
+                    self.rewrite_block(accept, expressions);
+                    self.rewrite_block(reject, expressions);
+                    Rewrite::Keep
+                }
+                S::Switch {
+                    ref mut cases,
+                    ref mut default,
+                    ..
+                } => {
+                    for case in cases.iter_mut() {
+                        self.rewrite_block(&mut case.body, expressions);
+                    }
+                    self.rewrite_block(default, expressions);
+                    Rewrite::Keep
+                }
+                S::Loop {
+                    ref mut body,
+                    ref mut continuing,
+                } => {
+                    self.rewrite_block(body, expressions);
+                    self.rewrite_block(continuing, expressions);
+                    Rewrite::Keep
+                }
+                S::Return {
+                    value: Some(ref mut value),
+                } => Rewrite::Emit(self.rewrite_value(value, expressions)),
+                S::Store { pointer, value } => self.rewrite_store(pointer, value, expressions),
+                _ => Rewrite::Keep,
+            };
+            match rewrite {
+                Rewrite::Keep => index += 1,
+                Rewrite::Emit(range) => {
+                    block.insert(index, S::Emit(range));
+                    index += 2;
+                }
+                Rewrite::Remove => {
+                    block.remove(index);
+                }
+                Rewrite::Replace(statements) => {
+                    let count = statements.len();
+                    block.splice(index..index + 1, statements);
+                    index += count;
+                }
+            }
+        }
+    }
+
+    fn is_output_local(
+        &self,
+        pointer: Handle<crate::Expression>,
+        expressions: &Arena<crate::Expression>,
+    ) -> bool {
+        match expressions[pointer] {
+            crate::Expression::LocalVariable(local) => self.output_locals.contains(&local),
+            _ => false,
+        }
+    }
+
+    /// Drop the stores to the removed members of an output local, and split
+    /// the stores of whole values into stores of the kept members.
+    fn rewrite_store(
+        &self,
+        pointer: Handle<crate::Expression>,
+        value: Handle<crate::Expression>,
+        expressions: &mut Arena<crate::Expression>,
+    ) -> Rewrite {
+        match expressions[pointer] {
+            crate::Expression::AccessIndex { base, index }
+                if self.is_output_local(base, expressions) =>
+            {
+                if self.kept.contains(&(index as usize)) {
+                    Rewrite::Keep
+                } else {
+                    Rewrite::Remove
+                }
+            }
+            crate::Expression::LocalVariable(_) if self.is_output_local(pointer, expressions) => {
+                let start = expressions.len();
+                let components = self.kept_components(value, expressions);
+                let mut statements = Vec::with_capacity(components.len() + 1);
+                for (&index, component) in self.kept.iter().zip(components) {
+                    let member = expressions.append(crate::Expression::AccessIndex {
+                        base: pointer,
+                        index: index as u32,
+                    });
+                    statements.push(crate::Statement::Store {
+                        pointer: member,
+                        value: component,
+                    });
+                }
+                statements.insert(0, crate::Statement::Emit(expressions.range_from(start)));
+                Rewrite::Replace(statements)
+            }
+            _ => Rewrite::Keep,
+        }
+    }
+
+    /// Return the kept members of the struct `value`, taking the components
+    /// of a composed struct, so that the removed ones can be pruned, or else
+    /// extracting them.
+    fn kept_components(
+        &self,
+        value: Handle<crate::Expression>,
+        expressions: &mut Arena<crate::Expression>,
+    ) -> Vec<Handle<crate::Expression>> {
+        let composed = match expressions[value] {
+            crate::Expression::Compose { ty, ref components } if ty == self.old_ty => Some(
+                self.kept
+                    .iter()
+                    .map(|&index| components[index])
+                    .collect::<Vec<_>>(),
+            ),
+            _ => None,
+        };
+        match composed {
+            Some(components) => components,
+            None => self
+                .kept
+                .iter()
+                .map(|&index| {
+                    expressions.append(crate::Expression::AccessIndex {
+                        base: value,
+                        index: index as u32,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// Replace the returned `value` with a new one, and return the range of
+    /// new expressions to emit before the return.
+    fn rewrite_value(
+        &self,
+        value: &mut Handle<crate::Expression>,
+        expressions: &mut Arena<crate::Expression>,
+    ) -> crate::Range<crate::Expression> {
+        let start = expressions.len();
+        let components = self.kept_components(*value, expressions);
+        *value = expressions.append(crate::Expression::Compose {
+            ty: self.new_ty,
+            components,
+        });
+        expressions.range_from(start)
+    }
+}
+
+fn remove_inputs(
+    types: &mut Arena<crate::Type>,
+    function: &mut crate::Function,
+    is_used: impl Fn(Option<&crate::Binding>) -> bool,
+) {
+    // new indices of the arguments, and of the members of struct arguments
+    let mut argument_indices = Vec::with_capacity(function.arguments.len());
+    let mut member_indices = Vec::with_capacity(function.arguments.len());
+    let mut next_index = 0;
+    for argument in function.arguments.iter_mut() {
+        let mut members = None;
+        if argument.binding.is_none() {
+            let retained = retain_members(types, argument.ty, |member| {
+                is_used(member.binding.as_ref())
+            });
+            if let Some((new_ty, indices)) = retained {
+                argument.ty = new_ty;
+                members = Some(indices);
+            }
+        }
+        if is_used(argument.binding.as_ref()) {
+            argument_indices.push(Some(next_index));
+            next_index += 1;
+        } else {
+            argument_indices.push(None);
+        }
+        member_indices.push(members);
+    }
+    let mut argument_index = 0;
+    function.arguments.retain(|_| {
+        argument_index += 1;
+        argument_indices[argument_index - 1].is_some()
+    });
+
+    // Dead expressions may still refer to the removed inputs, but they are
+    // pruned afterwards.
+    let arguments = function
+        .expressions
+        .iter()
+        .map(|(_, expression)| match *expression {
+            crate::Expression::FunctionArgument(index) => Some(index as usize),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    for (_, expression) in function.expressions.iter_mut() {
+        match *expression {
+            crate::Expression::FunctionArgument(ref mut index) => {
+                if let Some(new_index) = argument_indices[*index as usize] {
+                    *index = new_index;
+                }
+            }
+            crate::Expression::AccessIndex {
+                base,
+                ref mut index,
+            } => {
+                let new_index = arguments[base.index()]
+                    .and_then(|argument| member_indices[argument].as_ref())
+                    .and_then(|indices| indices[*index as usize]);
+                if let Some(new_index) = new_index {
+                    *index = new_index;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Where a varying of a vertex entry point goes once packed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct PackedVarying {
+    /// Location of the varying before packing.
+    pub location: u32,
+    /// Location the varying is moved to.
+    pub packed_location: u32,
+    /// Index of its first component in the packed location.
+    pub component: u32,
+    /// Number of components of the varying.
+    pub components: u32,
+}
+
+/// Packing of the varyings between a vertex and a fragment stage, computed
+/// by [`varying_packing`] and applied by [`pack_varyings`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct VaryingPacking {
+    /// All the varyings, sorted by their location before packing.
+    pub varyings: Vec<PackedVarying>,
+}
+
+impl VaryingPacking {
+    /// Get the packing of the varying at `location`.
+    pub fn find(&self, location: u32) -> Option<&PackedVarying> {
+        self.varyings
+            .iter()
+            .find(|varying| varying.location == location)
+    }
+
+    /// Return the number of locations used after packing.
+    pub fn location_count(&self) -> u32 {
+        self.varyings
+            .iter()
+            .map(|varying| varying.packed_location + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum PackingError {
+    #[error(transparent)]
+    Layout(#[from] InvalidBaseType),
+    #[error(transparent)]
+    Build(#[from] BuildError),
+    #[error("Varying at location {0} is not in the packing")]
+    UnknownLocation(u32),
+    #[error("Varying at location {0} doesn't match its packing")]
+    InvalidVarying(u32),
+}
+
+/// Return the number of components of a varying type that can share a
+/// location: 32-bit floats, and vectors of less than 4 of them.
+fn packable_components(inner: &crate::TypeInner) -> Option<u32> {
+    match *inner {
+        crate::TypeInner::Scalar {
+            kind: crate::ScalarKind::Float,
+            width: 4,
+        } => Some(1),
+        crate::TypeInner::Vector {
+            size,
+            kind: crate::ScalarKind::Float,
+            width: 4,
+        } if size != crate::VectorSize::Quad => Some(size as u32),
+        _ => None,
+    }
+}
+
+fn component_count(inner: &crate::TypeInner) -> u32 {
+    match *inner {
+        crate::TypeInner::Vector { size, .. } => size as u32,
+        _ => 1,
+    }
+}
+
+fn vector_size(components: u32) -> crate::VectorSize {
+    match components {
+        2 => crate::VectorSize::Bi,
+        3 => crate::VectorSize::Tri,
+        _ => crate::VectorSize::Quad,
+    }
+}
+
+/// Compute a packing of the outputs of the vertex entry point `ep` of
+/// `module`, to apply with [`pack_varyings`].
+///
+/// Varyings that are 32-bit floats or vectors of them share locations when
+/// they agree on their interpolation and sampling, while the others keep a
+/// location for themselves. The locations are then renumbered from zero,
+/// in the order of the first varying they hold.
+///
+/// The packing is empty for other stages.
+pub fn varying_packing(module: &crate::Module, ep: &crate::EntryPoint) -> VaryingPacking {
+    struct Bin {
+        /// Interpolation and sampling of the varyings, if they can be packed.
+        key: Option<(crate::Interpolation, Option<crate::Sampling>)>,
+        size: u32,
+        first_location: u32,
+        varyings: Vec<PackedVarying>,
+    }
+
+    let members = match ep.function.result {
+        Some(ref result) if ep.stage == crate::ShaderStage::Vertex && result.binding.is_none() => {
+            match module.types[result.ty].inner {
+                crate::TypeInner::Struct { ref members, .. } => members.as_slice(),
+                _ => &[],
+            }
+        }
+        _ => &[],
+    };
+    let mut varyings = members
+        .iter()
+        .filter_map(|member| match member.binding {
+            Some(crate::Binding::Location {
+                location,
+                interpolation,
+                sampling,
+                ..
+            }) => {
+                let inner = &module.types[member.ty].inner;
+                // the defaults are applied, so that equivalent bindings match
+                let key = packable_components(inner)
+                    .map(|_| super::resolve_interpolation(inner, interpolation, sampling));
+                Some((location, component_count(inner), key))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // place the largest varyings first, so that the smaller ones fill the gaps
+    varyings.sort_by_key(|varying| (std::cmp::Reverse(varying.1), varying.0));
+
+    let mut bins = Vec::<Bin>::new();
+    for (location, components, key) in varyings {
+        let varying = PackedVarying {
+            location,
+            packed_location: 0,
+            component: 0,
+            components,
+        };
+        let bin = bins
+            .iter_mut()
+            .find(|bin| key.is_some() && bin.key == key && bin.size + components <= 4);
+        match bin {
+            Some(bin) => {
+                bin.varyings.push(PackedVarying {
+                    component: bin.size,
+                    ..varying
+                });
+                bin.size += components;
+                bin.first_location = bin.first_location.min(location);
+            }
+            None => bins.push(Bin {
+                key,
+                size: components,
+                first_location: location,
+                varyings: vec![varying],
+            }),
+        }
+    }
+
+    bins.sort_by_key(|bin| bin.first_location);
+    let mut packing = VaryingPacking::default();
+    for (packed_location, bin) in bins.into_iter().enumerate() {
+        for varying in bin.varyings {
+            packing.varyings.push(PackedVarying {
+                packed_location: packed_location as u32,
+                ..varying
+            });
+        }
+    }
+    packing.varyings.sort_by_key(|varying| varying.location);
+    packing
+}
+
+/// Apply `packing` to the entry point at index `entry_point` of `module`.
+///
+/// For a vertex entry point, this packs the members of its result struct,
+/// and for a fragment entry point, its inputs. Applying the packing computed
+/// for the vertex stage of a pipeline to both of its stages, in the same
+/// module or not, keeps them compatible. Other stages are left as they are.
+///
+/// The function of the entry point is moved to the functions of the module,
+/// without its bindings, and the entry point calls it, packing its outputs
+/// or unpacking its inputs. Locations holding several varyings are named
+/// `packed_<location>`.
+pub fn pack_varyings(
+    module: &mut crate::Module,
+    entry_point: usize,
+    packing: &VaryingPacking,
+) -> Result<(), PackingError> {
+    match module.entry_points[entry_point].stage {
+        crate::ShaderStage::Vertex => pack_outputs(module, entry_point, packing),
+        crate::ShaderStage::Fragment => pack_inputs(module, entry_point, packing),
+        _ => Ok(()),
+    }
+}
+
+/// A location of the interface after packing.
+struct Slot {
+    location: u32,
+    member: crate::StructMember,
+    /// Whether it holds several varyings.
+    packed: bool,
+}
+
+/// Find the slots holding the varyings among `members`, and return them
+/// along with the slot and packing of each member, if it's a varying.
+#[allow(clippy::type_complexity)]
+fn find_slots(
+    types: &mut Arena<crate::Type>,
+    packing: &VaryingPacking,
+    members: &[crate::StructMember],
+) -> Result<(Vec<Slot>, Vec<Option<(usize, PackedVarying)>>), PackingError> {
+    let mut placements = Vec::with_capacity(members.len());
+    for member in members.iter() {
+        let location = match member.binding {
+            Some(crate::Binding::Location { location, .. }) => location,
+            _ => {
+                placements.push(None);
+                continue;
+            }
+        };
+        let varying = *packing
+            .find(location)
+            .ok_or(PackingError::UnknownLocation(location))?;
+        if component_count(&types[member.ty].inner) != varying.components {
+            return Err(PackingError::InvalidVarying(location));
+        }
+        placements.push(Some(varying));
+    }
+    let mut locations = placements
+        .iter()
+        .filter_map(|placement| placement.map(|varying| varying.packed_location))
+        .collect::<Vec<_>>();
+    locations.sort_unstable();
+    locations.dedup();
+
+    let mut slots = Vec::with_capacity(locations.len());
+    for &location in locations.iter() {
+        let (member, varying) = members
+            .iter()
+            .zip(placements.iter())
+            .filter_map(|(member, placement)| placement.map(|varying| (member, varying)))
+            .find(|pair| pair.1.packed_location == location)
+            .unwrap();
+        let binding = match member.binding {
+            Some(crate::Binding::Location {
+                interpolation,
+                sampling,
+                ..
+            }) => Some(crate::Binding::Location {
+                location,
+                second_blend_source: false,
+                interpolation,
+                sampling,
+            }),
+            _ => None,
+        };
+        let shared = packing
+            .varyings
+            .iter()
+            .filter(|other| other.packed_location == location);
+        let size = shared
+            .clone()
+            .map(|other| other.component + other.components)
+            .max()
+            .unwrap_or(0);
+        if shared.count() == 1 {
+            slots.push(Slot {
+                location,
+                member: crate::StructMember {
+                    binding,
+                    ..member.clone()
+                },
+                packed: false,
+            });
+            continue;
+        }
+        if packable_components(&types[member.ty].inner).is_none() {
+            return Err(PackingError::InvalidVarying(varying.location));
+        }
+        let ty = types.fetch_or_append(crate::Type {
+            name: None,
+            inner: crate::TypeInner::Vector {
+                size: vector_size(size),
+                kind: crate::ScalarKind::Float,
+                width: 4,
+            },
+        });
+        slots.push(Slot {
+            location,
+            member: crate::StructMember {
+                name: Some(format!("packed_{}", location)),
+                ty,
+                binding,
+                offset: 0,
+            },
+            packed: true,
+        });
+    }
+
+    let placements = placements
+        .into_iter()
+        .map(|placement| {
+            placement.map(|varying| {
+                let slot = slots
+                    .iter()
+                    .position(|slot| slot.location == varying.packed_location)
+                    .unwrap();
+                (slot, varying)
+            })
+        })
+        .collect();
+    Ok((slots, placements))
+}
+
+/// Find or add an interface struct with the given members, laying them out.
+fn interface_struct(
+    module: &mut crate::Module,
+    name: Option<String>,
+    mut members: Vec<crate::StructMember>,
+) -> Result<Handle<crate::Type>, InvalidBaseType> {
+    let mut layouter = Layouter::default();
+    layouter.update(&module.types, &module.constants)?;
+    let mut offset = 0;
+    let mut alignment = Alignment::new(1).unwrap();
+    for member in members.iter_mut() {
+        let (range, align) = layouter.member_placement(offset, member.ty, None, None);
+        alignment = alignment.max(align);
+        offset = range.end;
+        member.offset = range.start;
+    }
+    Ok(module.types.fetch_or_append(crate::Type {
+        name,
+        inner: crate::TypeInner::Struct {
+            top_level: false,
+            members,
+            span: Layouter::round_up(alignment, offset),
+        },
+    }))
+}
+
+/// Move the function of the entry point to the functions of the module,
+/// without its bindings, and return it along with its original arguments
+/// and result.
+fn detach_function(
+    module: &mut crate::Module,
+    entry_point: usize,
+) -> (
+    Handle<crate::Function>,
+    Vec<crate::FunctionArgument>,
+    Option<crate::FunctionResult>,
+) {
+    let mut function = std::mem::take(&mut module.entry_points[entry_point].function);
+    let arguments = function.arguments.clone();
+    let result = function.result.clone();
+    for argument in function.arguments.iter_mut() {
+        argument.binding = None;
+    }
+    if let Some(ref mut result) = function.result {
+        result.binding = None;
+    }
+    (module.functions.append(function), arguments, result)
+}
+
+fn pack_outputs(
+    module: &mut crate::Module,
+    entry_point: usize,
+    packing: &VaryingPacking,
+) -> Result<(), PackingError> {
+    let function = &module.entry_points[entry_point].function;
+    let function_name = function.name.clone().unwrap_or_default();
+    let (output_ty, members) = match function.result {
+        Some(ref result) if result.binding.is_none() => match module.types[result.ty].inner {
+            crate::TypeInner::Struct { ref members, .. } => (result.ty, members.clone()),
+            _ => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    let (slots, placements) = find_slots(&mut module.types, packing, &members)?;
+    if slots.is_empty() {
+        return Ok(());
+    }
+
+    // the built-ins come first, then the locations
+    let mut new_members = members
+        .iter()
+        .zip(placements.iter())
+        .filter(|pair| pair.1.is_none())
+        .map(|pair| pair.0.clone())
+        .collect::<Vec<_>>();
+    new_members.extend(slots.iter().map(|slot| slot.member.clone()));
+    let name = module.types[output_ty].name.clone();
+    let new_ty = interface_struct(module, name, new_members)?;
+
+    let (function, arguments, _) = detach_function(module, entry_point);
+    let mut builder = FunctionBuilder::new(module, &function_name);
+    let mut argument_values = Vec::with_capacity(arguments.len());
+    for argument in arguments {
+        let name = argument.name.unwrap_or_default();
+        argument_values.push(builder.argument(&name, argument.ty, argument.binding)?);
+    }
+    let output = builder.call(function, argument_values)?.unwrap();
+
+    let mut components = Vec::new();
+    let mut slot_components = vec![Vec::new(); slots.len()];
+    for (index, placement) in placements.into_iter().enumerate() {
+        let value = builder.expression(crate::Expression::AccessIndex {
+            base: output,
+            index: index as u32,
+        })?;
+        match placement {
+            Some((slot, varying)) => slot_components[slot].push((varying.component, value)),
+            None => components.push(value),
+        }
+    }
+    for (slot, mut values) in slots.iter().zip(slot_components) {
+        values.sort_by_key(|pair| pair.0);
+        let value = if slot.packed {
+            builder.expression(crate::Expression::Compose {
+                ty: slot.member.ty,
+                components: values.into_iter().map(|pair| pair.1).collect(),
+            })?
+        } else {
+            values[0].1
+        };
+        components.push(value);
+    }
+    let value = builder.expression(crate::Expression::Compose {
+        ty: new_ty,
+        components,
+    })?;
+    builder.result(new_ty, None);
+    builder.ret(Some(value))?;
+    module.entry_points[entry_point].function = builder.into_function();
+    Ok(())
+}
+
+fn pack_inputs(
+    module: &mut crate::Module,
+    entry_point: usize,
+    packing: &VaryingPacking,
+) -> Result<(), PackingError> {
+    let function = &module.entry_points[entry_point].function;
+    let function_name = function.name.clone().unwrap_or_default();
+    // the inputs, along with the arguments they come from
+    let mut inputs = Vec::new();
+    let mut sources = Vec::new();
+    let mut name = None;
+    for (index, argument) in function.arguments.iter().enumerate() {
+        if argument.binding.is_some() {
+            inputs.push(crate::StructMember {
+                name: argument.name.clone(),
+                ty: argument.ty,
+                binding: argument.binding.clone(),
+                offset: 0,
+            });
+            sources.push(index);
+        } else if let crate::TypeInner::Struct { ref members, .. } = module.types[argument.ty].inner
+        {
+            inputs.extend(members.iter().cloned());
+            sources.extend(members.iter().map(|_| index));
+            if name.is_none() {
+                name = module.types[argument.ty].name.clone();
+            }
+        }
+    }
+    let (slots, placements) = find_slots(&mut module.types, packing, &inputs)?;
+    if slots.is_empty() {
+        return Ok(());
+    }
+
+    // the built-ins come first, then the locations
+    let mut new_members = inputs
+        .iter()
+        .zip(placements.iter())
+        .filter(|pair| pair.1.is_none())
+        .map(|pair| pair.0.clone())
+        .collect::<Vec<_>>();
+    let builtin_count = new_members.len();
+    new_members.extend(slots.iter().map(|slot| slot.member.clone()));
+    let new_ty = interface_struct(module, name, new_members)?;
+
+    let (function, arguments, result) = detach_function(module, entry_point);
+    let mut builder = FunctionBuilder::new(module, &function_name);
+    let input = builder.argument("input", new_ty, None)?;
+    let mut slot_values = Vec::with_capacity(slots.len());
+    for index in 0..slots.len() {
+        slot_values.push(builder.expression(crate::Expression::AccessIndex {
+            base: input,
+            index: (builtin_count + index) as u32,
+        })?);
+    }
+
+    let mut input_values = Vec::with_capacity(placements.len());
+    let mut builtin_index = 0;
+    for placement in placements {
+        let expression = match placement {
+            Some((slot, _)) if !slots[slot].packed => {
+                input_values.push(slot_values[slot]);
+                continue;
+            }
+            Some((slot, varying)) if varying.components == 1 => crate::Expression::AccessIndex {
+                base: slot_values[slot],
+                index: varying.component,
+            },
+            Some((slot, varying)) => {
+                let mut pattern = [crate::SwizzleComponent::X; 4];
+                for (index, component) in pattern
+                    .iter_mut()
+                    .take(varying.components as usize)
+                    .enumerate()
+                {
+                    *component = crate::SwizzleComponent::XYZW[varying.component as usize + index];
+                }
+                crate::Expression::Swizzle {
+                    size: vector_size(varying.components),
+                    vector: slot_values[slot],
+                    pattern,
+                }
+            }
+            None => {
+                builtin_index += 1;
+                crate::Expression::AccessIndex {
+                    base: input,
+                    index: builtin_index - 1,
+                }
+            }
+        };
+        input_values.push(builder.expression(expression)?);
+    }
+
+    let mut argument_values = Vec::with_capacity(arguments.len());
+    for (index, argument) in arguments.iter().enumerate() {
+        let mut components = sources
+            .iter()
+            .zip(input_values.iter())
+            .filter(|pair| *pair.0 == index)
+            .map(|pair| *pair.1)
+            .collect::<Vec<_>>();
+        let value = if argument.binding.is_some() {
+            components.remove(0)
+        } else {
+            builder.expression(crate::Expression::Compose {
+                ty: argument.ty,
+                components,
+            })?
+        };
+        argument_values.push(value);
+    }
+    let value = builder.call(function, argument_values)?;
+    if let Some(result) = result {
+        builder.result(result.ty, result.binding);
+    }
+    builder.ret(value)?;
+    module.entry_points[entry_point].function = builder.into_function();
+    Ok(())
+}
+
+#[cfg(all(test, feature = "wgsl-in"))]
+mod tests {
+    use crate::valid::{Capabilities, ValidationFlags, Validator};
+
+    const SOURCE: &str = "
+        struct VertexOutput {
+            [[builtin(position)]] position: vec4<f32>;
+            [[location(0)]] uv: vec2<f32>;
+            [[location(1)]] fog: f32;
+            [[location(2), interpolate(flat)]] index: u32;
+        };
+
+        [[stage(vertex)]]
+        fn vs_main([[location(0)]] pos: vec2<f32>) -> VertexOutput {
+            let fog = sin(pos.x) * cos(pos.y);
+            return VertexOutput(vec4<f32>(pos, 0.0, 1.0), pos * 0.5, fog, 7u);
+        }
+
+        [[stage(fragment)]]
+        fn fs_main(in: VertexOutput, [[location(3)]] unused: f32) -> [[location(0)]] vec4<f32> {
+            let fog = in.fog;
+            return vec4<f32>(in.uv, 0.0, 1.0);
+        }
+    ";
+
+    fn validate(module: &crate::Module) -> crate::valid::ModuleInfo {
+        Validator::new(ValidationFlags::all(), Capabilities::empty())
+            .validate(module)
+            .unwrap()
+    }
+
+    fn has_math(function: &crate::Function, fun: crate::MathFunction) -> bool {
+        function
+            .expressions
+            .iter()
+            .any(|(_, expression)| match *expression {
+                crate::Expression::Math { fun: other, .. } => other == fun,
+                _ => false,
+            })
+    }
+
+    #[test]
+    fn remove_unused_varyings() {
+        let mut module = crate::front::wgsl::parse_str(SOURCE).unwrap();
+        validate(&module);
+        assert!(has_math(
+            &module.entry_points[0].function,
+            crate::MathFunction::Sin
+        ));
+
+        let used = super::used_fragment_inputs(&module, &module.entry_points[1]);
+        assert_eq!(used, [0]);
+        let types = module.types.len();
+        super::remove_unused_varyings(&mut module, 0, &used);
+        super::remove_unused_varyings(&mut module, 1, &used);
+        // the old output type is only removed by compaction
+        assert_eq!(module.types.len(), types + 1);
+        let info = validate(&module);
+        #[cfg(feature = "wgsl-out")]
+        crate::back::wgsl::write_string(&module, &info).unwrap();
+        #[cfg(feature = "msl-out")]
+        crate::back::msl::write_string(&module, &info, &Default::default(), &Default::default())
+            .unwrap();
+        #[cfg(feature = "spv-out")]
+        crate::back::spv::write_vec(&module, &info, &Default::default()).unwrap();
+        let _ = info;
+
+        let (vs, fs) = (&module.entry_points[0], &module.entry_points[1]);
+        assert!(!has_math(&vs.function, crate::MathFunction::Sin));
+        let output_ty = vs.function.result.as_ref().unwrap().ty;
+        match module.types[output_ty].inner {
+            crate::TypeInner::Struct { ref members, .. } => assert_eq!(members.len(), 2),
+            ref other => panic!("unexpected output type {:?}", other),
+        }
+        assert_eq!(fs.function.arguments.len(), 1);
+        assert_eq!(fs.function.arguments[0].ty, output_ty);
+
+        Validator::new(ValidationFlags::all(), Capabilities::empty())
+            .validate_pipeline((&module, vs), (&module, fs))
+            .unwrap();
+    }
+
+    #[test]
+    fn remove_varyings_written_to_a_local() {
+        let vertex_output = "return VertexOutput(vec4<f32>(pos, 0.0, 1.0), pos * 0.5, fog, 7u);";
+        let check = |body: &str, pruned: bool| {
+            let source = SOURCE.replace(vertex_output, body);
+            let mut module = crate::front::wgsl::parse_str(&source).unwrap();
+            let used = super::used_fragment_inputs(&module, &module.entry_points[1]);
+            super::remove_unused_varyings(&mut module, 0, &used);
+            super::remove_unused_varyings(&mut module, 1, &used);
+            validate(&module);
+            let (vs, fs) = (&module.entry_points[0], &module.entry_points[1]);
+            assert_eq!(
+                has_math(&vs.function, crate::MathFunction::Sin),
+                !pruned,
+                "{}",
+                body
+            );
+            Validator::new(ValidationFlags::all(), Capabilities::empty())
+                .validate_pipeline((&module, vs), (&module, fs))
+                .unwrap();
+        };
+
+        check(
+            "var out: VertexOutput;
+            out.position = vec4<f32>(pos, 0.0, 1.0);
+            out.uv = pos * 0.5;
+            out.fog = fog;
+            out.index = 7u;
+            return out;",
+            true,
+        );
+        check(
+            "var out: VertexOutput;
+            out = VertexOutput(vec4<f32>(pos, 0.0, 1.0), pos * 0.5, fog, 7u);
+            if (pos.x > 0.0) {
+                out.uv = pos;
+            }
+            return out;",
+            true,
+        );
+        // the removed output is read back
+        check(
+            "var out: VertexOutput;
+            out = VertexOutput(vec4<f32>(pos, 0.0, 1.0), pos * 0.5, fog, 7u);
+            out.uv = vec2<f32>(out.fog);
+            return out;",
+            false,
+        );
+    }
+
+    #[test]
+    fn keep_varyings_read_as_a_whole() {
+        let source = SOURCE
+            .replace(
+                "[[stage(fragment)]]",
+                "fn shade(v: VertexOutput) -> vec4<f32> { return vec4<f32>(v.uv, 0.0, 1.0); }
+                [[stage(fragment)]]",
+            )
+            .replace("return vec4<f32>(in.uv, 0.0, 1.0);", "return shade(in);");
+        let mut module = crate::front::wgsl::parse_str(&source).unwrap();
+        let used = super::used_fragment_inputs(&module, &module.entry_points[1]);
+        assert_eq!(used, [0, 1, 2]);
+
+        let source = SOURCE.replace("in.uv", "in.uv * in.fog");
+        module = crate::front::wgsl::parse_str(&source).unwrap();
+        let used = super::used_fragment_inputs(&module, &module.entry_points[1]);
+        assert_eq!(used, [0, 1]);
+        super::remove_unused_varyings(&mut module, 0, &used);
+        super::remove_unused_varyings(&mut module, 1, &used);
+        validate(&module);
+        assert!(has_math(
+            &module.entry_points[0].function,
+            crate::MathFunction::Sin
+        ));
+    }
+
+    const PACKING_SOURCE: &str = "
+        struct VertexOutput {
+            [[builtin(position)]] position: vec4<f32>;
+            [[location(0)]] uv: vec2<f32>;
+            [[location(1)]] fog: f32;
+            [[location(2), interpolate(flat)]] index: u32;
+            [[location(3)]] normal: vec3<f32>;
+            [[location(4)]] light: vec2<f32>;
+            [[location(5), interpolate(linear)]] depth: f32;
+        };
+
+        [[stage(vertex)]]
+        fn vs_main([[location(0)]] pos: vec3<f32>) -> VertexOutput {
+            let uv = pos.xy * 0.5;
+            return VertexOutput(vec4<f32>(pos, 1.0), uv, pos.z, 3u, normalize(pos), uv.yx, pos.z);
+        }
+
+        [[stage(fragment)]]
+        fn fs_main(in: VertexOutput, [[builtin(front_facing)]] front: bool) -> [[location(0)]] vec4<f32> {
+            let shade = dot(in.normal, vec3<f32>(in.light, in.depth)) * in.fog;
+            return vec4<f32>(in.uv, shade, f32(in.index));
+        }
+
+        [[stage(fragment)]]
+        fn fs_split([[location(1)]] fog: f32, [[location(4)]] light: vec2<f32>) -> [[location(0)]] vec4<f32> {
+            return vec4<f32>(light, fog, 1.0);
+        }
+    ";
+
+    #[test]
+    fn pack_varyings() {
+        use super::PackedVarying;
+
+        let mut module = crate::front::wgsl::parse_str(PACKING_SOURCE).unwrap();
+        let packing = super::varying_packing(&module, &module.entry_points[0]);
+        let varying = |location, packed_location, component, components| PackedVarying {
+            location,
+            packed_location,
+            component,
+            components,
+        };
+        assert_eq!(
+            packing.varyings,
+            [
+                varying(0, 0, 0, 2),
+                varying(1, 1, 3, 1),
+                varying(2, 2, 0, 1),
+                varying(3, 1, 0, 3),
+                varying(4, 0, 2, 2),
+                varying(5, 3, 0, 1),
+            ]
+        );
+        assert_eq!(packing.location_count(), 4);
+        assert_eq!(
+            super::varying_packing(&module, &module.entry_points[1]),
+            super::VaryingPacking::default()
+        );
+
+        // missing interpolations and samplings are the defaults,
+        // as with front ends that don't apply them
+        let mut bare = module.clone();
+        let output_ty = bare.entry_points[0].function.result.as_ref().unwrap().ty;
+        if let crate::TypeInner::Struct {
+            ref mut members, ..
+        } = bare.types.get_mut(output_ty).inner
+        {
+            // `fog`, packed with `normal`
+            if let Some(crate::Binding::Location {
+                ref mut interpolation,
+                ref mut sampling,
+                ..
+            }) = members[2].binding
+            {
+                *interpolation = None;
+                *sampling = None;
+            }
+        }
+        assert_eq!(
+            super::varying_packing(&bare, &bare.entry_points[0]),
+            packing
+        );
+
+        for entry_point in 0..module.entry_points.len() {
+            super::pack_varyings(&mut module, entry_point, &packing).unwrap();
+        }
+        let info = validate(&module);
+        #[cfg(feature = "wgsl-out")]
+        crate::back::wgsl::write_string(&module, &info).unwrap();
+        #[cfg(feature = "msl-out")]
+        crate::back::msl::write_string(&module, &info, &Default::default(), &Default::default())
+            .unwrap();
+        #[cfg(feature = "spv-out")]
+        crate::back::spv::write_vec(&module, &info, &Default::default()).unwrap();
+        let _ = info;
+
+        let vs = &module.entry_points[0];
+        let output_ty = vs.function.result.as_ref().unwrap().ty;
+        let locations = match module.types[output_ty].inner {
+            crate::TypeInner::Struct { ref members, .. } => members
+                .iter()
+                .filter_map(|member| match member.binding {
+                    Some(crate::Binding::Location { location, .. }) => {
+                        Some((location, module.types[member.ty].inner.clone()))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            ref other => panic!("unexpected output type {:?}", other),
+        };
+        let vec4 = crate::TypeInner::Vector {
+            size: crate::VectorSize::Quad,
+            kind: crate::ScalarKind::Float,
+            width: 4,
+        };
+        assert_eq!(locations.len(), 4);
+        assert_eq!(locations[0], (0, vec4.clone()));
+        assert_eq!(locations[1], (1, vec4));
+
+        let validator = Validator::new(ValidationFlags::all(), Capabilities::empty());
+        for fs in module.entry_points[1..].iter() {
+            validator
+                .validate_pipeline((&module, vs), (&module, fs))
+                .unwrap();
+        }
+    }
+
+    #[cfg(feature = "glsl-out")]
+    #[test]
+    fn pack_varyings_glsl_es() {
+        // GLSL ES has no linear interpolation
+        let source = PACKING_SOURCE.replace("interpolate(linear)", "interpolate(perspective)");
+        let mut module = crate::front::wgsl::parse_str(&source).unwrap();
+        let packing = super::varying_packing(&module, &module.entry_points[0]);
+        assert_eq!(packing.location_count(), 4);
+        for entry_point in 0..module.entry_points.len() {
+            super::pack_varyings(&mut module, entry_point, &packing).unwrap();
+        }
+        let info = validate(&module);
+        for ep in module.entry_points.iter() {
+            let options = crate::back::glsl::Options {
+                version: crate::back::glsl::Version::Embedded(300),
+                shader_stage: ep.stage,
+                entry_point: ep.name.clone(),
+            };
+            let mut output = String::new();
+            crate::back::glsl::Writer::new(&mut output, &module, &info, &options)
+                .unwrap()
+                .write()
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn pack_unknown_varying() {
+        let source = PACKING_SOURCE.replace(
+            "[[location(4)]] light: vec2<f32>)",
+            "[[location(7)]] light: vec2<f32>)",
+        );
+        let mut module = crate::front::wgsl::parse_str(&source).unwrap();
+        let packing = super::varying_packing(&module, &module.entry_points[0]);
+        match super::pack_varyings(&mut module, 2, &packing) {
+            Err(super::PackingError::UnknownLocation(7)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
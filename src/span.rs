@@ -0,0 +1,229 @@
+use crate::arena::{Arena, Handle};
+use std::{error::Error, fmt, ops::Range};
+
+/// A source code span, used for error reporting.
+///
+/// Spans are byte offsets into the source the module was produced from.
+/// The default span is undefined, and is what items get when the front-end
+/// doesn't know where they came from.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
+pub struct Span {
+    start: u32,
+    end: u32,
+}
+
+impl Span {
+    /// The span that doesn't point anywhere.
+    pub const UNDEFINED: Self = Span { start: 0, end: 0 };
+
+    /// Creates a new span from the byte range `start..end`.
+    pub fn new(start: u32, end: u32) -> Self {
+        Span { start, end }
+    }
+
+    /// Returns a new span covering both `self` and `other`.
+    pub fn union(&self, other: &Self) -> Self {
+        if !self.is_defined() {
+            return *other;
+        }
+        if !other.is_defined() {
+            return *self;
+        }
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// Returns true if the span points to some source.
+    pub fn is_defined(&self) -> bool {
+        *self != Self::UNDEFINED
+    }
+
+    /// Returns the byte range of the span, if it's defined.
+    pub fn to_range(self) -> Option<Range<usize>> {
+        if self.is_defined() {
+            Some(self.start as usize..self.end as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Span {
+            start: range.start as u32,
+            end: range.end as u32,
+        }
+    }
+}
+
+/// A span together with a description of what it points to.
+pub type SpanContext = (Span, String);
+
+/// An error, along with the source spans it relates to.
+///
+/// Dereferences to the inner error, and displays exactly like it.
+#[derive(Clone, Debug)]
+pub struct WithSpan<E> {
+    inner: E,
+    spans: Vec<SpanContext>,
+}
+
+impl<E> WithSpan<E> {
+    /// Wraps an error without any spans.
+    pub fn new(inner: E) -> Self {
+        WithSpan {
+            inner,
+            spans: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the wrapped error.
+    pub fn as_inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Returns the wrapped error, dropping the spans.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+
+    /// Iterates over the spans attached to the error, outermost last.
+    pub fn spans(&self) -> impl ExactSizeIterator<Item = &SpanContext> {
+        self.spans.iter()
+    }
+
+    /// Attaches a span to the error. Undefined spans are ignored.
+    pub fn with_span<S: ToString>(mut self, span: Span, description: S) -> Self {
+        if span.is_defined() {
+            self.spans.push((span, description.to_string()));
+        }
+        self
+    }
+
+    /// Attaches the span of an arena item to the error.
+    pub fn with_handle<T, S: ToString>(
+        self,
+        handle: Handle<T>,
+        arena: &Arena<T>,
+        description: S,
+    ) -> Self {
+        self.with_span(arena.get_span(handle), description)
+    }
+
+    /// Attaches all the spans of another error.
+    pub fn with_context(mut self, spans: impl IntoIterator<Item = SpanContext>) -> Self {
+        self.spans.extend(spans);
+        self
+    }
+
+    /// Converts the wrapped error, keeping the spans.
+    pub fn into_other<E2>(self, fun: impl FnOnce(E) -> E2) -> WithSpan<E2> {
+        WithSpan {
+            inner: fun(self.inner),
+            spans: self.spans,
+        }
+    }
+
+    /// Returns the 1-based line number and column of the first span,
+    /// if there is one.
+    #[cfg(feature = "span")]
+    pub fn location(&self, source: &str) -> Option<(usize, usize)> {
+        use codespan_reporting::files::{Files as _, SimpleFile};
+
+        let files = SimpleFile::new("", source);
+        let &(span, _) = self.spans.first()?;
+        let location = files.location((), span.start as usize).ok()?;
+        Some((location.line_number, location.column_number))
+    }
+}
+
+#[cfg(feature = "span")]
+impl<E: Error> WithSpan<E> {
+    fn diagnostic(&self) -> codespan_reporting::diagnostic::Diagnostic<()> {
+        use codespan_reporting::diagnostic::{Diagnostic, Label};
+
+        let labels = self
+            .spans
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &(span, ref description))| {
+                let range = span.to_range()?;
+                let label = if index == 0 {
+                    Label::primary((), range)
+                } else {
+                    Label::secondary((), range)
+                };
+                Some(label.with_message(description.clone()))
+            })
+            .collect();
+        let mut notes = Vec::new();
+        let mut source = self.inner.source();
+        while let Some(error) = source {
+            notes.push(error.to_string());
+            source = error.source();
+        }
+        Diagnostic::error()
+            .with_message(self.inner.to_string())
+            .with_labels(labels)
+            .with_notes(notes)
+    }
+
+    /// Emits a summary of the error to standard error stream.
+    pub fn emit_to_stderr(&self, source: &str) {
+        self.emit_to_stderr_with_path(source, "shader")
+    }
+
+    /// Emits a summary of the error to standard error stream,
+    /// naming the source after the given path.
+    pub fn emit_to_stderr_with_path(&self, source: &str, path: &str) {
+        use codespan_reporting::{files::SimpleFile, term};
+        use term::termcolor::{ColorChoice, StandardStream};
+
+        let files = SimpleFile::new(path, source);
+        let config = term::Config::default();
+        let writer = StandardStream::stderr(ColorChoice::Auto);
+        term::emit(&mut writer.lock(), &config, &files, &self.diagnostic())
+            .expect("cannot write error");
+    }
+
+    /// Emits a summary of the error to a string.
+    pub fn emit_to_string(&self, source: &str) -> String {
+        use codespan_reporting::{files::SimpleFile, term};
+
+        let files = SimpleFile::new("shader", source);
+        let config = term::Config::default();
+        let mut writer = term::termcolor::NoColor::new(Vec::new());
+        term::emit(&mut writer, &config, &files, &self.diagnostic()).expect("cannot write error");
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+}
+
+impl<E> std::ops::Deref for WithSpan<E> {
+    type Target = E;
+    fn deref(&self) -> &E {
+        &self.inner
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for WithSpan<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<E: Error> Error for WithSpan<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.inner.source()
+    }
+}
+
+impl<E> From<E> for WithSpan<E> {
+    fn from(inner: E) -> Self {
+        Self::new(inner)
+    }
+}
@@ -366,8 +366,9 @@ impl FunctionInfo {
                 let uniform = match var.class {
                     // local data is non-uniform
                     Sc::Function | Sc::Private => false,
-                    // workgroup memory is exclusively accessed by the group
-                    Sc::WorkGroup => true,
+                    // workgroup memory is exclusively accessed by the group,
+                    // and so is the task payload
+                    Sc::WorkGroup | Sc::TaskPayload => true,
                     // uniform data
                     Sc::Uniform | Sc::PushConstant => true,
                     // storage data is only uniform when read-only
@@ -391,21 +392,26 @@ impl FunctionInfo {
             E::ImageSample {
                 image,
                 sampler,
+                gather: _,
                 coordinate,
                 array_index,
                 offset: _,
                 level,
                 depth_ref,
             } => {
+                let resolve_global = |expr| {
+                    super::resolve_resource(
+                        expr,
+                        expression_arena,
+                        resolve_context.global_vars,
+                        resolve_context.types,
+                    )
+                    .map(|(var, _)| var)
+                    .ok_or(ExpressionError::ExpectedGlobalVariable)
+                };
                 self.sampling_set.insert(SamplingKey {
-                    image: match expression_arena[image] {
-                        crate::Expression::GlobalVariable(var) => var,
-                        _ => return Err(ExpressionError::ExpectedGlobalVariable),
-                    },
-                    sampler: match expression_arena[sampler] {
-                        crate::Expression::GlobalVariable(var) => var,
-                        _ => return Err(ExpressionError::ExpectedGlobalVariable),
-                    },
+                    image: resolve_global(image)?,
+                    sampler: resolve_global(sampler)?,
                 });
                 // "nur" == "Non-Uniform Result"
                 let array_nur = array_index.and_then(|h| self.add_ref(h));
@@ -487,12 +493,17 @@ impl FunctionInfo {
                 requirements: UniformityRequirements::empty(),
             },
             E::Math {
-                arg, arg1, arg2, ..
+                arg,
+                arg1,
+                arg2,
+                arg3,
+                ..
             } => {
                 let arg1_nur = arg1.and_then(|h| self.add_ref(h));
                 let arg2_nur = arg2.and_then(|h| self.add_ref(h));
+                let arg3_nur = arg3.and_then(|h| self.add_ref(h));
                 Uniformity {
-                    non_uniform_result: self.add_ref(arg).or(arg1_nur).or(arg2_nur),
+                    non_uniform_result: self.add_ref(arg).or(arg1_nur).or(arg2_nur).or(arg3_nur),
                     requirements: UniformityRequirements::empty(),
                 }
             }
@@ -506,6 +517,28 @@ impl FunctionInfo {
                     .ok_or(ExpressionError::CallToUndeclaredFunction(function))?;
                 self.process_call(fun).result
             }
+            // the result of an atomic operation depends on other invocations
+            E::AtomicResult { .. } => Uniformity {
+                non_uniform_result: Some(handle),
+                requirements: UniformityRequirements::empty(),
+            },
+            // subgroup results may differ between the subgroups of a workgroup
+            E::SubgroupBallotResult | E::SubgroupOperationResult { .. } => Uniformity {
+                non_uniform_result: Some(handle),
+                requirements: UniformityRequirements::empty(),
+            },
+            // each invocation traces its own ray
+            E::RayQueryProceedResult => Uniformity {
+                non_uniform_result: Some(handle),
+                requirements: UniformityRequirements::empty(),
+            },
+            E::RayQueryGetIntersection { query, .. } => {
+                let _ = self.add_ref(query);
+                Uniformity {
+                    non_uniform_result: Some(handle),
+                    requirements: UniformityRequirements::empty(),
+                }
+            }
             E::ArrayLength(expr) => Uniformity {
                 non_uniform_result: self.add_ref_impl(expr, GlobalUse::QUERY),
                 requirements: UniformityRequirements::empty(),
@@ -682,6 +715,78 @@ impl FunctionInfo {
                     //Note: the result is validated by the Validator, not here
                     self.process_call(info)
                 }
+                S::Atomic {
+                    pointer,
+                    ref fun,
+                    value,
+                    result: _,
+                } => {
+                    let _ = self.add_ref_impl(pointer, GlobalUse::READ | GlobalUse::WRITE);
+                    let _ = self.add_ref(value);
+                    if let crate::AtomicFunction::Exchange { compare: Some(cmp) } = *fun {
+                        let _ = self.add_ref(cmp);
+                    }
+                    FunctionUniformity::new()
+                }
+                S::SubgroupBallot {
+                    result: _,
+                    predicate,
+                } => {
+                    if let Some(predicate) = predicate {
+                        let _ = self.add_ref(predicate);
+                    }
+                    FunctionUniformity::new()
+                }
+                S::SubgroupCollectiveOperation {
+                    op: _,
+                    collective_op: _,
+                    argument,
+                    result: _,
+                } => {
+                    let _ = self.add_ref(argument);
+                    FunctionUniformity::new()
+                }
+                S::SubgroupGather {
+                    ref mode,
+                    argument,
+                    result: _,
+                } => {
+                    let _ = self.add_ref(argument);
+                    if let Some(index) = mode.index() {
+                        let _ = self.add_ref(index);
+                    }
+                    FunctionUniformity::new()
+                }
+                S::MeshFunction(ref fun) => {
+                    match *fun {
+                        crate::MeshFunction::SetMeshOutputs {
+                            vertex_count,
+                            primitive_count,
+                        } => {
+                            let _ = self.add_ref(vertex_count);
+                            let _ = self.add_ref(primitive_count);
+                        }
+                        crate::MeshFunction::SetVertex { index, value }
+                        | crate::MeshFunction::SetIndices { index, value }
+                        | crate::MeshFunction::SetPrimitive { index, value } => {
+                            let _ = self.add_ref(index);
+                            let _ = self.add_ref(value);
+                        }
+                    }
+                    FunctionUniformity::new()
+                }
+                S::RayQuery { query, ref fun } => {
+                    let _ = self.add_ref_impl(query, GlobalUse::READ | GlobalUse::WRITE);
+                    if let crate::RayQueryFunction::Initialize {
+                        acceleration_structure,
+                        descriptor,
+                    } = *fun
+                    {
+                        let _ = self.add_ref(acceleration_structure);
+                        let _ = self.add_ref(descriptor);
+                    }
+                    FunctionUniformity::new()
+                }
             };
 
             disruptor = disruptor.or(uniformity.exit_disruptor());
@@ -1,4 +1,7 @@
-use super::{compose::validate_compose, ComposeError, FunctionInfo, ShaderStages, TypeFlags};
+use super::{
+    compose::validate_compose, resolve_resource, Capabilities, ComposeError, FunctionInfo,
+    ShaderStages, TypeFlags,
+};
 use crate::{
     arena::{Arena, Handle},
     proc::{ProcError, ResolveError},
@@ -89,6 +92,12 @@ pub enum ExpressionError {
     },
     #[error("Sample offset constant {1:?} doesn't match the image dimension {0:?}")]
     InvalidSampleOffset(crate::ImageDimension, Handle<crate::Constant>),
+    #[error("Gather is not supported for image dimension {0:?}")]
+    InvalidGatherDimension(crate::ImageDimension),
+    #[error("Gather from a depth image can only select the X component, not {0:?}")]
+    InvalidGatherComponent(crate::SwizzleComponent),
+    #[error("Gather can only be done with a zero sample level")]
+    InvalidGatherLevel,
     #[error("Depth reference {0:?} is not a scalar float")]
     InvalidDepthReference(Handle<crate::Expression>),
     #[error("Sample level is not compatible with the image dimension {0:?}")]
@@ -105,6 +114,14 @@ pub enum ExpressionError {
     WrongArgumentCount(crate::MathFunction),
     #[error("Argument [{1}] to {0:?} as expression {2:?} has an invalid type.")]
     InvalidArgumentType(crate::MathFunction, u32, Handle<crate::Expression>),
+    #[error("Atomic result type can't be {0:?} of {1} bytes")]
+    InvalidAtomicResultType(crate::ScalarKind, crate::Bytes),
+    #[error("Capability {0:?} is required")]
+    MissingCapabilities(Capabilities),
+    #[error("Expression {0:?} is not a pointer to a ray query")]
+    InvalidRayQueryType(Handle<crate::Expression>),
+    #[error("Type {0:?} is not a RayIntersection structure")]
+    InvalidRayIntersectionType(Handle<crate::Type>),
 }
 
 struct ExpressionTypeResolver<'a> {
@@ -149,7 +166,7 @@ impl super::Validator {
                 let base_type = resolver.resolve(base)?;
                 // See the documentation for `Expression::Access`.
                 let dynamic_indexing_restricted = match *base_type {
-                    Ti::Vector { .. } => false,
+                    Ti::Vector { .. } | Ti::BindingArray { .. } => false,
                     Ti::Matrix { .. } | Ti::Array { .. } => true,
                     Ti::Pointer { .. } | Ti::ValuePointer { size: Some(_), .. } => false,
                     ref other => {
@@ -177,6 +194,24 @@ impl super::Validator {
                 {
                     return Err(ExpressionError::IndexMustBeConstant(base));
                 }
+                let binding_array = match *base_type {
+                    Ti::BindingArray { .. } => true,
+                    Ti::Pointer { base, .. } => match module.types[base].inner {
+                        Ti::BindingArray { .. } => true,
+                        _ => false,
+                    },
+                    _ => false,
+                };
+                if binding_array
+                    && info[index].uniformity.non_uniform_result.is_some()
+                    && !self
+                        .capabilities
+                        .contains(Capabilities::BINDING_ARRAY_NON_UNIFORM_INDEXING)
+                {
+                    return Err(ExpressionError::MissingCapabilities(
+                        Capabilities::BINDING_ARRAY_NON_UNIFORM_INDEXING,
+                    ));
+                }
 
                 // If we know both the length and the index, we can do the
                 // bounds check now.
@@ -227,6 +262,11 @@ impl super::Validator {
                             ..
                         } => module.constants[handle].to_array_length().unwrap(),
                         Ti::Array { .. } => !0, // can't statically know, but need run-time checks
+                        Ti::BindingArray {
+                            size: crate::ArraySize::Constant(handle),
+                            ..
+                        } => module.constants[handle].to_array_length().unwrap(),
+                        Ti::BindingArray { .. } => !0,
                         Ti::Pointer { base, .. } if top_level => {
                             resolve_index_limit(module, top, &module.types[base].inner, false)?
                         }
@@ -332,6 +372,7 @@ impl super::Validator {
             E::ImageSample {
                 image,
                 sampler,
+                gather,
                 coordinate,
                 array_index,
                 offset,
@@ -339,24 +380,26 @@ impl super::Validator {
                 depth_ref,
             } => {
                 // check the validity of expressions
-                let image_var = match function.expressions[image] {
-                    crate::Expression::GlobalVariable(var_handle) => {
-                        &module.global_variables[var_handle]
-                    }
-                    _ => return Err(ExpressionError::ExpectedGlobalVariable),
-                };
-                let sampler_var = match function.expressions[sampler] {
-                    crate::Expression::GlobalVariable(var_handle) => {
-                        &module.global_variables[var_handle]
-                    }
-                    _ => return Err(ExpressionError::ExpectedGlobalVariable),
-                };
-                let comparison = match module.types[sampler_var.ty].inner {
+                let (_, image_ty) = resolve_resource(
+                    image,
+                    &function.expressions,
+                    &module.global_variables,
+                    &module.types,
+                )
+                .ok_or(ExpressionError::ExpectedGlobalVariable)?;
+                let (_, sampler_ty) = resolve_resource(
+                    sampler,
+                    &function.expressions,
+                    &module.global_variables,
+                    &module.types,
+                )
+                .ok_or(ExpressionError::ExpectedGlobalVariable)?;
+                let comparison = match module.types[sampler_ty].inner {
                     Ti::Sampler { comparison } => comparison,
-                    _ => return Err(ExpressionError::ExpectedSamplerType(sampler_var.ty)),
+                    _ => return Err(ExpressionError::ExpectedSamplerType(sampler_ty)),
                 };
 
-                let (class, dim) = match module.types[image_var.ty].inner {
+                let (class, dim) = match module.types[image_ty].inner {
                     Ti::Image {
                         class,
                         arrayed,
@@ -377,7 +420,7 @@ impl super::Validator {
                         }
                         (class, dim)
                     }
-                    _ => return Err(ExpressionError::ExpectedImageType(image_var.ty)),
+                    _ => return Err(ExpressionError::ExpectedImageType(image_ty)),
                 };
 
                 // check sampling and comparison properties
@@ -386,6 +429,8 @@ impl super::Validator {
                         kind: crate::ScalarKind::Float,
                         multi: false,
                     } => false,
+                    // integer images can be gathered, but not filtered
+                    crate::ImageClass::Sampled { multi: false, .. } if gather.is_some() => false,
                     crate::ImageClass::Depth => true,
                     _ => return Err(ExpressionError::InvalidImageClass(class)),
                 };
@@ -397,6 +442,21 @@ impl super::Validator {
                     });
                 }
 
+                // check gather properties
+                if let Some(component) = gather {
+                    match dim {
+                        crate::ImageDimension::D2 | crate::ImageDimension::Cube => {}
+                        _ => return Err(ExpressionError::InvalidGatherDimension(dim)),
+                    }
+                    if image_depth && component != crate::SwizzleComponent::X {
+                        return Err(ExpressionError::InvalidGatherComponent(component));
+                    }
+                    match level {
+                        crate::SampleLevel::Zero => {}
+                        _ => return Err(ExpressionError::InvalidGatherLevel),
+                    }
+                }
+
                 // check texture coordinates type
                 let num_components = match dim {
                     crate::ImageDimension::D1 => 1,
@@ -515,13 +575,14 @@ impl super::Validator {
                 array_index,
                 index,
             } => {
-                let var = match function.expressions[image] {
-                    crate::Expression::GlobalVariable(var_handle) => {
-                        &module.global_variables[var_handle]
-                    }
-                    _ => return Err(ExpressionError::ExpectedGlobalVariable),
-                };
-                match module.types[var.ty].inner {
+                let (_, image_ty) = resolve_resource(
+                    image,
+                    &function.expressions,
+                    &module.global_variables,
+                    &module.types,
+                )
+                .ok_or(ExpressionError::ExpectedGlobalVariable)?;
+                match module.types[image_ty].inner {
                     Ti::Image {
                         class,
                         arrayed,
@@ -564,18 +625,19 @@ impl super::Validator {
                             }
                         }
                     }
-                    _ => return Err(ExpressionError::ExpectedImageType(var.ty)),
+                    _ => return Err(ExpressionError::ExpectedImageType(image_ty)),
                 }
                 ShaderStages::all()
             }
             E::ImageQuery { image, query } => {
-                let var = match function.expressions[image] {
-                    crate::Expression::GlobalVariable(var_handle) => {
-                        &module.global_variables[var_handle]
-                    }
-                    _ => return Err(ExpressionError::ExpectedGlobalVariable),
-                };
-                match module.types[var.ty].inner {
+                let (_, image_ty) = resolve_resource(
+                    image,
+                    &function.expressions,
+                    &module.global_variables,
+                    &module.types,
+                )
+                .ok_or(ExpressionError::ExpectedGlobalVariable)?;
+                match module.types[image_ty].inner {
                     Ti::Image { class, arrayed, .. } => {
                         let can_level = match class {
                             crate::ImageClass::Sampled { multi, .. } => !multi,
@@ -593,7 +655,7 @@ impl super::Validator {
                             return Err(ExpressionError::InvalidImageClass(class));
                         }
                     }
-                    _ => return Err(ExpressionError::ExpectedImageType(var.ty)),
+                    _ => return Err(ExpressionError::ExpectedImageType(image_ty)),
                 }
                 ShaderStages::all()
             }
@@ -850,12 +912,17 @@ impl super::Validator {
                 arg,
                 arg1,
                 arg2,
+                arg3,
             } => {
                 use crate::MathFunction as Mf;
 
                 let arg_ty = resolver.resolve(arg)?;
                 let arg1_ty = arg1.map(|expr| resolver.resolve(expr)).transpose()?;
                 let arg2_ty = arg2.map(|expr| resolver.resolve(expr)).transpose()?;
+                // only `InsertBits` takes a fourth argument
+                if arg3.is_some() && fun != Mf::InsertBits {
+                    return Err(ExpressionError::WrongArgumentCount(fun));
+                }
                 match fun {
                     Mf::Abs => {
                         if arg1_ty.is_some() | arg2_ty.is_some() {
@@ -1129,7 +1196,12 @@ impl super::Validator {
                             _ => return Err(ExpressionError::InvalidArgumentType(fun, 0, arg)),
                         }
                     }
-                    Mf::CountOneBits | Mf::ReverseBits => {
+                    Mf::CountTrailingZeros
+                    | Mf::CountLeadingZeros
+                    | Mf::CountOneBits
+                    | Mf::ReverseBits
+                    | Mf::FirstTrailingBit
+                    | Mf::FirstLeadingBit => {
                         if arg1_ty.is_some() | arg2_ty.is_some() {
                             return Err(ExpressionError::WrongArgumentCount(fun));
                         }
@@ -1141,6 +1213,122 @@ impl super::Validator {
                             _ => return Err(ExpressionError::InvalidArgumentType(fun, 0, arg)),
                         }
                     }
+                    Mf::ExtractBits => {
+                        let (arg1_ty, arg2_ty) = match (arg1_ty, arg2_ty) {
+                            (Some(ty1), Some(ty2)) => (ty1, ty2),
+                            _ => return Err(ExpressionError::WrongArgumentCount(fun)),
+                        };
+                        match *arg_ty {
+                            Ti::Scalar { kind: Sk::Sint, .. }
+                            | Ti::Scalar { kind: Sk::Uint, .. }
+                            | Ti::Vector { kind: Sk::Sint, .. }
+                            | Ti::Vector { kind: Sk::Uint, .. } => {}
+                            _ => return Err(ExpressionError::InvalidArgumentType(fun, 0, arg)),
+                        }
+                        match *arg1_ty {
+                            Ti::Scalar { kind: Sk::Uint, .. } => {}
+                            _ => {
+                                return Err(ExpressionError::InvalidArgumentType(
+                                    fun,
+                                    1,
+                                    arg1.unwrap(),
+                                ))
+                            }
+                        }
+                        match *arg2_ty {
+                            Ti::Scalar { kind: Sk::Uint, .. } => {}
+                            _ => {
+                                return Err(ExpressionError::InvalidArgumentType(
+                                    fun,
+                                    2,
+                                    arg2.unwrap(),
+                                ))
+                            }
+                        }
+                    }
+                    Mf::InsertBits => {
+                        let arg3_ty = arg3.map(|expr| resolver.resolve(expr)).transpose()?;
+                        let (arg1_ty, arg2_ty, arg3_ty) = match (arg1_ty, arg2_ty, arg3_ty) {
+                            (Some(ty1), Some(ty2), Some(ty3)) => (ty1, ty2, ty3),
+                            _ => return Err(ExpressionError::WrongArgumentCount(fun)),
+                        };
+                        match *arg_ty {
+                            Ti::Scalar { kind: Sk::Sint, .. }
+                            | Ti::Scalar { kind: Sk::Uint, .. }
+                            | Ti::Vector { kind: Sk::Sint, .. }
+                            | Ti::Vector { kind: Sk::Uint, .. } => {}
+                            _ => return Err(ExpressionError::InvalidArgumentType(fun, 0, arg)),
+                        }
+                        if arg1_ty != arg_ty {
+                            return Err(ExpressionError::InvalidArgumentType(
+                                fun,
+                                1,
+                                arg1.unwrap(),
+                            ));
+                        }
+                        match *arg2_ty {
+                            Ti::Scalar { kind: Sk::Uint, .. } => {}
+                            _ => {
+                                return Err(ExpressionError::InvalidArgumentType(
+                                    fun,
+                                    2,
+                                    arg2.unwrap(),
+                                ))
+                            }
+                        }
+                        match *arg3_ty {
+                            Ti::Scalar { kind: Sk::Uint, .. } => {}
+                            _ => {
+                                return Err(ExpressionError::InvalidArgumentType(
+                                    fun,
+                                    3,
+                                    arg3.unwrap(),
+                                ))
+                            }
+                        }
+                    }
+                    Mf::Pack4x8snorm | Mf::Pack4x8unorm => {
+                        if arg1_ty.is_some() | arg2_ty.is_some() {
+                            return Err(ExpressionError::WrongArgumentCount(fun));
+                        }
+                        match *arg_ty {
+                            Ti::Vector {
+                                size: crate::VectorSize::Quad,
+                                kind: Sk::Float,
+                                width: 4,
+                            } => {}
+                            _ => return Err(ExpressionError::InvalidArgumentType(fun, 0, arg)),
+                        }
+                    }
+                    Mf::Pack2x16snorm | Mf::Pack2x16unorm | Mf::Pack2x16float => {
+                        if arg1_ty.is_some() | arg2_ty.is_some() {
+                            return Err(ExpressionError::WrongArgumentCount(fun));
+                        }
+                        match *arg_ty {
+                            Ti::Vector {
+                                size: crate::VectorSize::Bi,
+                                kind: Sk::Float,
+                                width: 4,
+                            } => {}
+                            _ => return Err(ExpressionError::InvalidArgumentType(fun, 0, arg)),
+                        }
+                    }
+                    Mf::Unpack4x8snorm
+                    | Mf::Unpack4x8unorm
+                    | Mf::Unpack2x16snorm
+                    | Mf::Unpack2x16unorm
+                    | Mf::Unpack2x16float => {
+                        if arg1_ty.is_some() | arg2_ty.is_some() {
+                            return Err(ExpressionError::WrongArgumentCount(fun));
+                        }
+                        match *arg_ty {
+                            Ti::Scalar {
+                                kind: Sk::Uint,
+                                width: 4,
+                            } => {}
+                            _ => return Err(ExpressionError::InvalidArgumentType(fun, 0, arg)),
+                        }
+                    }
                 }
                 ShaderStages::all()
             }
@@ -1165,6 +1353,45 @@ impl super::Validator {
                 ShaderStages::all()
             }
             E::Call(function) => other_infos[function.index()].available_stages,
+            E::AtomicResult {
+                kind,
+                width,
+                comparison: _,
+            } => {
+                let good = match kind {
+                    crate::ScalarKind::Uint | crate::ScalarKind::Sint => {
+                        self.check_width(kind, width)
+                    }
+                    _ => false,
+                };
+                if !good {
+                    return Err(ExpressionError::InvalidAtomicResultType(kind, width));
+                }
+                ShaderStages::all()
+            }
+            // the types are validated by the subgroup statements
+            E::SubgroupBallotResult | E::SubgroupOperationResult { .. } => ShaderStages::all(),
+            // validated by the ray query statement
+            E::RayQueryProceedResult => ShaderStages::FRAGMENT | ShaderStages::COMPUTE,
+            E::RayQueryGetIntersection {
+                query,
+                committed: _,
+                ty,
+            } => {
+                match *resolver.resolve(query)? {
+                    Ti::Pointer { base, .. } => match resolver.types[base].inner {
+                        Ti::RayQuery => {}
+                        _ => return Err(ExpressionError::InvalidRayQueryType(query)),
+                    },
+                    _ => return Err(ExpressionError::InvalidRayQueryType(query)),
+                }
+                if resolver.types.try_get(ty).is_none()
+                    || !crate::proc::RayQueryStruct::Intersection.matches(resolver.types, ty)
+                {
+                    return Err(ExpressionError::InvalidRayIntersectionType(ty));
+                }
+                ShaderStages::FRAGMENT | ShaderStages::COMPUTE
+            }
             E::ArrayLength(expr) => match *resolver.resolve(expr)? {
                 Ti::Pointer { base, .. } => {
                     if let Some(&Ti::Array {
@@ -1,6 +1,7 @@
 use super::{
     analyzer::{UniformityDisruptor, UniformityRequirements},
-    ExpressionError, FunctionInfo, ModuleInfo, ShaderStages, TypeFlags, ValidationFlags,
+    Capabilities, ExpressionError, FunctionInfo, ModuleInfo, ShaderStages, TypeFlags,
+    ValidationFlags,
 };
 use crate::arena::{Arena, Handle};
 use bit_set::BitSet;
@@ -34,6 +35,68 @@ pub enum CallError {
     ExpressionMismatch(Option<Handle<crate::Expression>>),
 }
 
+#[derive(Clone, Debug, thiserror::Error)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum AtomicError {
+    #[error("Pointer {0:?} to atomic is invalid.")]
+    InvalidPointer(Handle<crate::Expression>),
+    #[error("Pointer {0:?} to atomic is not in the storage or workgroup class")]
+    InvalidStorageClass(Handle<crate::Expression>),
+    #[error("Operand {0:?} has invalid type.")]
+    InvalidOperand(Handle<crate::Expression>),
+    #[error("Result expression {0:?} has already been introduced earlier")]
+    ResultAlreadyInScope(Handle<crate::Expression>),
+    #[error("Result type for {0:?} doesn't match the statement")]
+    ResultTypeMismatch(Handle<crate::Expression>),
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum SubgroupError {
+    #[error("Subgroup operations require the SUBGROUP capability")]
+    MissingCapability,
+    #[error("Operand {0:?} has invalid type.")]
+    InvalidOperand(Handle<crate::Expression>),
+    #[error("Invocation index {0:?} is not an unsigned integer scalar")]
+    InvalidIndex(Handle<crate::Expression>),
+    #[error("Broadcast invocation index {0:?} is not a constant")]
+    NonConstantIndex(Handle<crate::Expression>),
+    #[error("Operation {0:?} can't be used as {1:?}")]
+    UnsupportedOperation(crate::SubgroupOperation, crate::CollectiveOperation),
+    #[error("Result expression {0:?} has already been introduced earlier")]
+    ResultAlreadyInScope(Handle<crate::Expression>),
+    #[error("Result type for {0:?} doesn't match the statement")]
+    ResultTypeMismatch(Handle<crate::Expression>),
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum MeshFunctionError {
+    #[error("Mesh outputs can only be written by the entry point of a mesh stage")]
+    OutsideMeshEntryPoint,
+    #[error("Output count {0:?} is not an unsigned integer scalar")]
+    InvalidCount(Handle<crate::Expression>),
+    #[error("Output index {0:?} is not an unsigned integer scalar")]
+    InvalidIndex(Handle<crate::Expression>),
+    #[error("Output value {0:?} doesn't match the declared outputs")]
+    InvalidValue(Handle<crate::Expression>),
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+#[cfg_attr(test, derive(PartialEq))]
+pub enum RayQueryError {
+    #[error("Pointer {0:?} to a ray query is invalid")]
+    InvalidQuery(Handle<crate::Expression>),
+    #[error("Acceleration structure {0:?} is invalid")]
+    InvalidAccelerationStructure(Handle<crate::Expression>),
+    #[error("Ray descriptor {0:?} is not a RayDesc structure")]
+    InvalidDescriptor(Handle<crate::Expression>),
+    #[error("Result expression {0:?} has already been introduced earlier")]
+    ResultAlreadyInScope(Handle<crate::Expression>),
+    #[error("Result expression {0:?} is not a ray query proceed result")]
+    InvalidResult(Handle<crate::Expression>),
+}
+
 #[derive(Clone, Debug, thiserror::Error)]
 #[cfg_attr(test, derive(PartialEq))]
 pub enum LocalVariableError {
@@ -98,6 +161,14 @@ pub enum FunctionError {
         #[source]
         error: CallError,
     },
+    #[error("Atomic operation is invalid")]
+    InvalidAtomic(#[from] AtomicError),
+    #[error("Subgroup operation is invalid")]
+    InvalidSubgroup(#[from] SubgroupError),
+    #[error("Mesh function is invalid")]
+    InvalidMeshFunction(#[from] MeshFunctionError),
+    #[error("Ray query is invalid")]
+    InvalidRayQuery(#[from] RayQueryError),
     #[error(
         "Required uniformity of control flow for {0:?} in {1:?} is not fulfilled because of {2:?}"
     )]
@@ -108,6 +179,110 @@ pub enum FunctionError {
     ),
 }
 
+impl FunctionError {
+    /// Collect the source spans of the function items this error refers to.
+    pub(super) fn spans(&self, fun: &crate::Function) -> Vec<crate::SpanContext> {
+        let expression = |handle: Handle<crate::Expression>, description: &str| {
+            (fun.expressions.get_span(handle), description.to_string())
+        };
+        let mut spans = Vec::new();
+        match *self {
+            Self::Expression { handle, .. } => {
+                spans.push(expression(handle, "invalid expression"));
+            }
+            Self::ExpressionAlreadyInScope(handle) => {
+                spans.push(expression(handle, "evaluated more than once"));
+            }
+            Self::LocalVariable { handle, .. } => {
+                spans.push((
+                    fun.local_variables.get_span(handle),
+                    "invalid local variable".to_string(),
+                ));
+            }
+            Self::InvalidReturnType(Some(handle)) => {
+                spans.push(expression(handle, "returned value"));
+            }
+            Self::InvalidIfType(handle) => spans.push(expression(handle, "condition")),
+            Self::InvalidSwitchType(handle) => spans.push(expression(handle, "selector")),
+            Self::InvalidStorePointer(handle) => {
+                spans.push(expression(handle, "store destination"));
+            }
+            Self::InvalidStoreValue(handle) => spans.push(expression(handle, "stored value")),
+            Self::InvalidStoreTypes { pointer, value } => {
+                spans.push(expression(value, "stored value"));
+                spans.push(expression(pointer, "store destination"));
+            }
+            Self::InvalidExpression(handle) => spans.push(expression(handle, "corrupted")),
+            Self::InvalidCall { ref error, .. } => match *error {
+                CallError::ResultAlreadyInScope(handle)
+                | CallError::ExpressionMismatch(Some(handle)) => {
+                    spans.push(expression(handle, "call result"));
+                }
+                CallError::ArgumentType {
+                    seen_expression, ..
+                } => spans.push(expression(seen_expression, "argument")),
+                _ => {}
+            },
+            Self::InvalidAtomic(ref error) => match *error {
+                AtomicError::InvalidPointer(handle) | AtomicError::InvalidStorageClass(handle) => {
+                    spans.push(expression(handle, "atomic pointer"));
+                }
+                AtomicError::InvalidOperand(handle) => {
+                    spans.push(expression(handle, "atomic operand"));
+                }
+                AtomicError::ResultAlreadyInScope(handle)
+                | AtomicError::ResultTypeMismatch(handle) => {
+                    spans.push(expression(handle, "atomic result"));
+                }
+            },
+            Self::InvalidSubgroup(ref error) => match *error {
+                SubgroupError::InvalidOperand(handle) => {
+                    spans.push(expression(handle, "subgroup operand"));
+                }
+                SubgroupError::InvalidIndex(handle) | SubgroupError::NonConstantIndex(handle) => {
+                    spans.push(expression(handle, "invocation index"));
+                }
+                SubgroupError::ResultAlreadyInScope(handle)
+                | SubgroupError::ResultTypeMismatch(handle) => {
+                    spans.push(expression(handle, "subgroup result"));
+                }
+                SubgroupError::MissingCapability | SubgroupError::UnsupportedOperation(..) => {}
+            },
+            Self::InvalidMeshFunction(ref error) => match *error {
+                MeshFunctionError::InvalidCount(handle) => {
+                    spans.push(expression(handle, "output count"));
+                }
+                MeshFunctionError::InvalidIndex(handle) => {
+                    spans.push(expression(handle, "output index"));
+                }
+                MeshFunctionError::InvalidValue(handle) => {
+                    spans.push(expression(handle, "output value"));
+                }
+                MeshFunctionError::OutsideMeshEntryPoint => {}
+            },
+            Self::InvalidRayQuery(ref error) => match *error {
+                RayQueryError::InvalidQuery(handle) => spans.push(expression(handle, "ray query")),
+                RayQueryError::InvalidAccelerationStructure(handle) => {
+                    spans.push(expression(handle, "acceleration structure"));
+                }
+                RayQueryError::InvalidDescriptor(handle) => {
+                    spans.push(expression(handle, "ray descriptor"));
+                }
+                RayQueryError::ResultAlreadyInScope(handle)
+                | RayQueryError::InvalidResult(handle) => {
+                    spans.push(expression(handle, "ray query result"));
+                }
+            },
+            Self::NonUniformControlFlow(_, handle, _) => {
+                spans.push(expression(handle, "requires uniform control flow"));
+            }
+            _ => {}
+        }
+        spans.retain(|&(span, _)| span.is_defined());
+        spans
+    }
+}
+
 bitflags::bitflags! {
     #[repr(transparent)]
     struct ControlFlowAbility: u8 {
@@ -129,6 +304,7 @@ struct BlockContext<'a> {
     functions: &'a Arena<crate::Function>,
     prev_infos: &'a [FunctionInfo],
     return_type: Option<Handle<crate::Type>>,
+    mesh_info: Option<&'a crate::MeshStageInfo>,
 }
 
 impl<'a> BlockContext<'a> {
@@ -137,6 +313,7 @@ impl<'a> BlockContext<'a> {
         module: &'a crate::Module,
         info: &'a FunctionInfo,
         prev_infos: &'a [FunctionInfo],
+        mesh_info: Option<&'a crate::MeshStageInfo>,
     ) -> Self {
         Self {
             abilities: ControlFlowAbility::RETURN,
@@ -147,6 +324,7 @@ impl<'a> BlockContext<'a> {
             functions: &module.functions,
             prev_infos,
             return_type: fun.result.as_ref().map(|fr| fr.ty),
+            mesh_info,
         }
     }
 
@@ -250,6 +428,334 @@ impl super::Validator {
         Ok(callee_info.available_stages)
     }
 
+    fn validate_atomic(
+        &mut self,
+        pointer: Handle<crate::Expression>,
+        fun: &crate::AtomicFunction,
+        value: Handle<crate::Expression>,
+        result: Handle<crate::Expression>,
+        context: &BlockContext,
+    ) -> Result<(), FunctionError> {
+        let pointer_inner = context.resolve_type(pointer, &self.valid_expression_set)?;
+        let (ptr_kind, ptr_width) = match *pointer_inner {
+            crate::TypeInner::Pointer { base, class } => {
+                match class {
+                    crate::StorageClass::Storage | crate::StorageClass::WorkGroup => {}
+                    _ => return Err(AtomicError::InvalidStorageClass(pointer).into()),
+                }
+                match context.types[base].inner {
+                    crate::TypeInner::Atomic { kind, width } => (kind, width),
+                    ref other => {
+                        log::error!("Atomic pointer to type {:?}", other);
+                        return Err(AtomicError::InvalidPointer(pointer).into());
+                    }
+                }
+            }
+            ref other => {
+                log::error!("Atomic on type {:?}", other);
+                return Err(AtomicError::InvalidPointer(pointer).into());
+            }
+        };
+
+        let value_inner = context.resolve_type(value, &self.valid_expression_set)?;
+        match *value_inner {
+            crate::TypeInner::Scalar { width, kind } if kind == ptr_kind && width == ptr_width => {}
+            ref other => {
+                log::error!("Atomic operand type {:?}", other);
+                return Err(AtomicError::InvalidOperand(value).into());
+            }
+        }
+
+        if let crate::AtomicFunction::Exchange { compare: Some(cmp) } = *fun {
+            if context.resolve_type(cmp, &self.valid_expression_set)? != value_inner {
+                log::error!("Atomic exchange comparison has a different type from the value");
+                return Err(AtomicError::InvalidOperand(cmp).into());
+            }
+        }
+
+        if self.valid_expression_set.insert(result.index()) {
+            self.valid_expression_list.push(result);
+        } else {
+            return Err(AtomicError::ResultAlreadyInScope(result).into());
+        }
+        match context.expressions[result] {
+            crate::Expression::AtomicResult {
+                kind,
+                width,
+                comparison,
+            } if kind == ptr_kind
+                && width == ptr_width
+                && comparison
+                    == matches!(*fun, crate::AtomicFunction::Exchange { compare: Some(_) }) => {}
+            _ => return Err(AtomicError::ResultTypeMismatch(result).into()),
+        }
+        Ok(())
+    }
+
+    fn validate_subgroup_result(
+        &mut self,
+        result: Handle<crate::Expression>,
+        expected: &crate::TypeInner,
+        context: &BlockContext,
+    ) -> Result<(), FunctionError> {
+        if self.valid_expression_set.insert(result.index()) {
+            self.valid_expression_list.push(result);
+        } else {
+            return Err(SubgroupError::ResultAlreadyInScope(result).into());
+        }
+        let good = match context.expressions[result] {
+            crate::Expression::SubgroupBallotResult => {
+                *expected
+                    == crate::TypeInner::Vector {
+                        size: crate::VectorSize::Quad,
+                        kind: crate::ScalarKind::Uint,
+                        width: 4,
+                    }
+            }
+            crate::Expression::SubgroupOperationResult { ty } => {
+                context.types.try_get(ty).map(|ty| &ty.inner) == Some(expected)
+            }
+            _ => false,
+        };
+        if !good {
+            return Err(SubgroupError::ResultTypeMismatch(result).into());
+        }
+        Ok(())
+    }
+
+    fn validate_subgroup_ballot(
+        &mut self,
+        result: Handle<crate::Expression>,
+        predicate: Option<Handle<crate::Expression>>,
+        context: &BlockContext,
+    ) -> Result<(), FunctionError> {
+        if let Some(predicate) = predicate {
+            match *context.resolve_type(predicate, &self.valid_expression_set)? {
+                crate::TypeInner::Scalar {
+                    kind: crate::ScalarKind::Bool,
+                    width: _,
+                } => {}
+                ref other => {
+                    log::error!("Subgroup ballot predicate type {:?}", other);
+                    return Err(SubgroupError::InvalidOperand(predicate).into());
+                }
+            }
+        }
+        let result_inner = crate::TypeInner::Vector {
+            size: crate::VectorSize::Quad,
+            kind: crate::ScalarKind::Uint,
+            width: 4,
+        };
+        self.validate_subgroup_result(result, &result_inner, context)
+    }
+
+    fn validate_subgroup_operation(
+        &mut self,
+        op: crate::SubgroupOperation,
+        collective_op: crate::CollectiveOperation,
+        argument: Handle<crate::Expression>,
+        result: Handle<crate::Expression>,
+        context: &BlockContext,
+    ) -> Result<(), FunctionError> {
+        use crate::{ScalarKind as Sk, SubgroupOperation as Sg};
+
+        let argument_inner = context.resolve_type(argument, &self.valid_expression_set)?;
+        let (kind, is_scalar) = match *argument_inner {
+            crate::TypeInner::Scalar { kind, .. } => (kind, true),
+            crate::TypeInner::Vector { kind, .. } => (kind, false),
+            ref other => {
+                log::error!("Subgroup operand type {:?}", other);
+                return Err(SubgroupError::InvalidOperand(argument).into());
+            }
+        };
+        let good = match op {
+            Sg::All | Sg::Any => {
+                if collective_op != crate::CollectiveOperation::Reduce {
+                    return Err(SubgroupError::UnsupportedOperation(op, collective_op).into());
+                }
+                kind == Sk::Bool && is_scalar
+            }
+            Sg::Add | Sg::Mul | Sg::Min | Sg::Max => kind != Sk::Bool,
+            Sg::And | Sg::Or | Sg::Xor => kind != Sk::Float,
+        };
+        if !good {
+            log::error!("Subgroup {:?} on type {:?}", op, argument_inner);
+            return Err(SubgroupError::InvalidOperand(argument).into());
+        }
+        self.validate_subgroup_result(result, argument_inner, context)
+    }
+
+    fn validate_subgroup_gather(
+        &mut self,
+        mode: &crate::GatherMode,
+        argument: Handle<crate::Expression>,
+        result: Handle<crate::Expression>,
+        context: &BlockContext,
+    ) -> Result<(), FunctionError> {
+        if let Some(index) = mode.index() {
+            match *context.resolve_type(index, &self.valid_expression_set)? {
+                crate::TypeInner::Scalar {
+                    kind: crate::ScalarKind::Uint,
+                    width: 4,
+                } => {}
+                ref other => {
+                    log::error!("Subgroup gather index type {:?}", other);
+                    return Err(SubgroupError::InvalidIndex(index).into());
+                }
+            }
+        }
+        if let crate::GatherMode::Broadcast(index) = *mode {
+            match context.expressions[index] {
+                crate::Expression::Constant(_) => {}
+                _ => return Err(SubgroupError::NonConstantIndex(index).into()),
+            }
+        }
+        let argument_inner = context.resolve_type(argument, &self.valid_expression_set)?;
+        match *argument_inner {
+            crate::TypeInner::Scalar { .. } | crate::TypeInner::Vector { .. } => {}
+            ref other => {
+                log::error!("Subgroup gather operand type {:?}", other);
+                return Err(SubgroupError::InvalidOperand(argument).into());
+            }
+        }
+        self.validate_subgroup_result(result, argument_inner, context)
+    }
+
+    fn validate_ray_query(
+        &mut self,
+        query: Handle<crate::Expression>,
+        fun: &crate::RayQueryFunction,
+        context: &BlockContext,
+    ) -> Result<(), RayQueryError> {
+        let is_query = match context.resolve_pointer_type(query) {
+            Ok(&crate::TypeInner::Pointer { base, .. }) => match context.types[base].inner {
+                crate::TypeInner::RayQuery => true,
+                _ => false,
+            },
+            _ => false,
+        };
+        if !is_query {
+            return Err(RayQueryError::InvalidQuery(query));
+        }
+
+        match *fun {
+            crate::RayQueryFunction::Initialize {
+                acceleration_structure,
+                descriptor,
+            } => {
+                match context.resolve_type(acceleration_structure, &self.valid_expression_set) {
+                    Ok(&crate::TypeInner::AccelerationStructure) => {}
+                    _ => {
+                        return Err(RayQueryError::InvalidAccelerationStructure(
+                            acceleration_structure,
+                        ))
+                    }
+                }
+                let is_descriptor = context
+                    .resolve_type(descriptor, &self.valid_expression_set)
+                    .is_ok()
+                    && match context.info[descriptor].ty {
+                        crate::proc::TypeResolution::Handle(ty) => {
+                            crate::proc::RayQueryStruct::Desc.matches(context.types, ty)
+                        }
+                        crate::proc::TypeResolution::Value(_) => false,
+                    };
+                if !is_descriptor {
+                    return Err(RayQueryError::InvalidDescriptor(descriptor));
+                }
+            }
+            crate::RayQueryFunction::Proceed { result } => {
+                if self.valid_expression_set.insert(result.index()) {
+                    self.valid_expression_list.push(result);
+                } else {
+                    return Err(RayQueryError::ResultAlreadyInScope(result));
+                }
+                match context.expressions[result] {
+                    crate::Expression::RayQueryProceedResult => {}
+                    _ => return Err(RayQueryError::InvalidResult(result)),
+                }
+            }
+            crate::RayQueryFunction::Terminate => {}
+        }
+        Ok(())
+    }
+
+    fn validate_mesh_function(
+        &self,
+        fun: &crate::MeshFunction,
+        context: &BlockContext,
+    ) -> Result<(), MeshFunctionError> {
+        let mesh_info = context
+            .mesh_info
+            .ok_or(MeshFunctionError::OutsideMeshEntryPoint)?;
+        let is_uint = |handle| match context.resolve_type(handle, &self.valid_expression_set) {
+            Ok(&crate::TypeInner::Scalar {
+                kind: crate::ScalarKind::Uint,
+                width: _,
+            }) => true,
+            _ => false,
+        };
+        let (index, value, expected) = match *fun {
+            crate::MeshFunction::SetMeshOutputs {
+                vertex_count,
+                primitive_count,
+            } => {
+                for &count in [vertex_count, primitive_count].iter() {
+                    if !is_uint(count) {
+                        return Err(MeshFunctionError::InvalidCount(count));
+                    }
+                }
+                return Ok(());
+            }
+            crate::MeshFunction::SetVertex { index, value } => (
+                index,
+                value,
+                Some(&context.types[mesh_info.vertex_output_type].inner),
+            ),
+            crate::MeshFunction::SetPrimitive { index, value } => (
+                index,
+                value,
+                mesh_info
+                    .primitive_output_type
+                    .map(|ty| &context.types[ty].inner),
+            ),
+            crate::MeshFunction::SetIndices { index, value } => {
+                let kind = crate::ScalarKind::Uint;
+                let width = 4;
+                let ty = match mesh_info.topology {
+                    crate::MeshOutputTopology::Points => crate::TypeInner::Scalar { kind, width },
+                    crate::MeshOutputTopology::Lines => crate::TypeInner::Vector {
+                        size: crate::VectorSize::Bi,
+                        kind,
+                        width,
+                    },
+                    crate::MeshOutputTopology::Triangles => crate::TypeInner::Vector {
+                        size: crate::VectorSize::Tri,
+                        kind,
+                        width,
+                    },
+                };
+                if !is_uint(index) {
+                    return Err(MeshFunctionError::InvalidIndex(index));
+                }
+                return match context.resolve_type(value, &self.valid_expression_set) {
+                    Ok(inner) if *inner == ty => Ok(()),
+                    _ => Err(MeshFunctionError::InvalidValue(value)),
+                };
+            }
+        };
+        if !is_uint(index) {
+            return Err(MeshFunctionError::InvalidIndex(index));
+        }
+        match (
+            context.resolve_type(value, &self.valid_expression_set),
+            expected,
+        ) {
+            (Ok(inner), Some(expected)) if inner == expected => Ok(()),
+            _ => Err(MeshFunctionError::InvalidValue(value)),
+        }
+    }
+
     fn validate_block_impl(
         &mut self,
         statements: &[crate::Statement],
@@ -375,7 +881,7 @@ impl super::Validator {
                     finished = true;
                 }
                 S::Barrier(_) => {
-                    stages &= ShaderStages::COMPUTE;
+                    stages &= ShaderStages::COMPUTE | ShaderStages::TASK | ShaderStages::MESH;
                 }
                 S::Store { pointer, value } => {
                     let mut current = pointer;
@@ -399,7 +905,10 @@ impl super::Validator {
                         _ => {}
                     }
                     let good = match *context.resolve_pointer_type(pointer)? {
-                        Ti::Pointer { base, class: _ } => *value_ty == context.types[base].inner,
+                        Ti::Pointer { base, class: _ } => match context.types[base].inner {
+                            Ti::Atomic { kind, width } => *value_ty == Ti::Scalar { kind, width },
+                            ref other => value_ty == other,
+                        },
                         Ti::ValuePointer {
                             size: Some(size),
                             kind,
@@ -426,18 +935,18 @@ impl super::Validator {
                 } => {
                     //Note: this code uses a lot of `FunctionError::InvalidImageStore`,
                     // and could probably be refactored.
-                    let var = match *context.get_expression(image)? {
-                        crate::Expression::GlobalVariable(var_handle) => {
-                            &context.global_vars[var_handle]
-                        }
-                        _ => {
-                            return Err(FunctionError::InvalidImageStore(
-                                ExpressionError::ExpectedGlobalVariable,
-                            ))
-                        }
-                    };
+                    context.get_expression(image)?;
+                    let (_, image_ty) = super::resolve_resource(
+                        image,
+                        context.expressions,
+                        context.global_vars,
+                        context.types,
+                    )
+                    .ok_or(FunctionError::InvalidImageStore(
+                        ExpressionError::ExpectedGlobalVariable,
+                    ))?;
 
-                    let value_ty = match context.types[var.ty].inner {
+                    let value_ty = match context.types[image_ty].inner {
                         Ti::Image {
                             class,
                             arrayed,
@@ -489,7 +998,7 @@ impl super::Validator {
                         }
                         _ => {
                             return Err(FunctionError::InvalidImageStore(
-                                ExpressionError::ExpectedImageType(var.ty),
+                                ExpressionError::ExpectedImageType(image_ty),
                             ))
                         }
                     };
@@ -506,6 +1015,49 @@ impl super::Validator {
                     Ok(callee_stages) => stages &= callee_stages,
                     Err(error) => return Err(FunctionError::InvalidCall { function, error }),
                 },
+                S::Atomic {
+                    pointer,
+                    ref fun,
+                    value,
+                    result,
+                } => {
+                    self.validate_atomic(pointer, fun, value, result, context)?;
+                }
+                S::SubgroupBallot { result, predicate } => {
+                    if !self.capabilities.contains(Capabilities::SUBGROUP) {
+                        return Err(SubgroupError::MissingCapability.into());
+                    }
+                    self.validate_subgroup_ballot(result, predicate, context)?;
+                }
+                S::SubgroupCollectiveOperation {
+                    op,
+                    collective_op,
+                    argument,
+                    result,
+                } => {
+                    if !self.capabilities.contains(Capabilities::SUBGROUP) {
+                        return Err(SubgroupError::MissingCapability.into());
+                    }
+                    self.validate_subgroup_operation(op, collective_op, argument, result, context)?;
+                }
+                S::SubgroupGather {
+                    ref mode,
+                    argument,
+                    result,
+                } => {
+                    if !self.capabilities.contains(Capabilities::SUBGROUP) {
+                        return Err(SubgroupError::MissingCapability.into());
+                    }
+                    self.validate_subgroup_gather(mode, argument, result, context)?;
+                }
+                S::MeshFunction(ref fun) => {
+                    self.validate_mesh_function(fun, context)?;
+                    stages &= ShaderStages::MESH;
+                }
+                S::RayQuery { query, ref fun } => {
+                    self.validate_ray_query(query, fun, context)?;
+                    stages &= ShaderStages::FRAGMENT | ShaderStages::COMPUTE;
+                }
             }
         }
         Ok(stages)
@@ -531,9 +1083,15 @@ impl super::Validator {
         constants: &Arena<crate::Constant>,
     ) -> Result<(), LocalVariableError> {
         log::debug!("var {:?}", var);
-        if !self.types[var.ty.index()]
-            .flags
-            .contains(TypeFlags::DATA | TypeFlags::SIZED)
+        // ray queries are opaque, but they can only live in local variables
+        let is_ray_query = match types[var.ty].inner {
+            crate::TypeInner::RayQuery => true,
+            _ => false,
+        };
+        if !is_ray_query
+            && !self.types[var.ty.index()]
+                .flags
+                .contains(TypeFlags::DATA | TypeFlags::SIZED)
         {
             return Err(LocalVariableError::InvalidType(var.ty));
         }
@@ -563,6 +1121,7 @@ impl super::Validator {
         fun: &crate::Function,
         module: &crate::Module,
         mod_info: &ModuleInfo,
+        mesh_info: Option<&crate::MeshStageInfo>,
     ) -> Result<FunctionInfo, FunctionError> {
         let mut info = mod_info.process_function(fun, module, self.flags)?;
 
@@ -611,7 +1170,7 @@ impl super::Validator {
         if self.flags.contains(ValidationFlags::BLOCKS) {
             let stages = self.validate_block(
                 &fun.body,
-                &BlockContext::new(fun, module, &info, &mod_info.functions),
+                &BlockContext::new(fun, module, &info, &mod_info.functions, mesh_info),
             )?;
             info.available_stages &= stages;
         }
@@ -8,6 +8,7 @@ use crate::arena::{Arena, Handle};
 use bit_set::BitSet;
 
 const MAX_WORKGROUP_SIZE: u32 = 0x4000;
+const MAX_MESH_OUTPUTS: u32 = 256;
 
 #[derive(Clone, Debug, thiserror::Error)]
 pub enum GlobalVariableError {
@@ -53,6 +54,44 @@ pub enum VaryingError {
     BindingCollision { location: u32 },
     #[error("Built-in {0:?} is present more than once")]
     DuplicateBuiltIn(crate::BuiltIn),
+    #[error("Capability {0:?} is not supported")]
+    UnsupportedCapability(Capabilities),
+    #[error("Second blend source is only allowed on fragment outputs at location 0")]
+    InvalidSecondBlendSource,
+    #[error("Dual-source blending requires exactly one other output, at location 0")]
+    InvalidDualSourceBlending,
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum PipelineError {
+    #[error("Entry point '{name}' is a {stage:?} shader, but a {expected:?} shader is required")]
+    InvalidStage {
+        name: String,
+        stage: crate::ShaderStage,
+        expected: crate::ShaderStage,
+    },
+    #[error("Vertex shader does not output the {0:?} built-in")]
+    MissingBuiltIn(crate::BuiltIn),
+    #[error("Fragment shader input at location {location} is not written by the vertex shader")]
+    MissingVarying { location: u32 },
+    #[error("Location {location} has type {output:?} in the vertex shader, but {input:?} in the fragment shader")]
+    TypeMismatch {
+        location: u32,
+        output: crate::TypeInner,
+        input: crate::TypeInner,
+    },
+    #[error("Location {location} has interpolation {output:?} in the vertex shader, but {input:?} in the fragment shader")]
+    InterpolationMismatch {
+        location: u32,
+        output: crate::Interpolation,
+        input: crate::Interpolation,
+    },
+    #[error("Location {location} has sampling {output:?} in the vertex shader, but {input:?} in the fragment shader")]
+    SamplingMismatch {
+        location: u32,
+        output: Option<crate::Sampling>,
+        input: Option<crate::Sampling>,
+    },
 }
 
 #[derive(Clone, Debug, thiserror::Error)]
@@ -65,6 +104,16 @@ pub enum EntryPointError {
     UnexpectedWorkgroupSize,
     #[error("Workgroup size is out of range")]
     OutOfRangeWorkgroupSize,
+    #[error("Capability {0:?} is not supported")]
+    UnsupportedCapability(Capabilities),
+    #[error("Mesh output information is missing or not applicable")]
+    InvalidMeshInfo,
+    #[error("Mesh output counts are out of range")]
+    OutOfRangeMeshOutputs,
+    #[error("Task stages have to return the mesh task size, and mesh stages nothing")]
+    InvalidMeshStageResult,
+    #[error("Task payload {0:?} is missing or not applicable")]
+    InvalidTaskPayload(Option<Handle<crate::GlobalVariable>>),
     #[error("Uses operations forbidden at this stage")]
     ForbiddenStageOperations,
     #[error("Global variable {0:?} is used incorrectly as {1:?}")]
@@ -75,6 +124,10 @@ pub enum EntryPointError {
     Argument(u32, #[source] VaryingError),
     #[error("Result varying error")]
     Result(#[source] VaryingError),
+    #[error("Mesh vertex output varying error")]
+    VertexOutput(#[source] VaryingError),
+    #[error("Mesh primitive output varying error")]
+    PrimitiveOutput(#[source] VaryingError),
     #[error("Location {location} onterpolation of an integer has to be flat")]
     InvalidIntegerInterpolation { location: u32 },
     #[error(transparent)]
@@ -96,9 +149,14 @@ struct VaryingContext<'a> {
     ty: Handle<crate::Type>,
     stage: crate::ShaderStage,
     output: bool,
+    /// Per-primitive outputs of a mesh shader.
+    per_primitive: bool,
     types: &'a Arena<crate::Type>,
     location_mask: &'a mut BitSet,
     built_in_mask: u32,
+    /// Set once the second blend source is seen, it doesn't take a location.
+    second_blend_source: bool,
+    capabilities: Capabilities,
 }
 
 impl VaryingContext<'_> {
@@ -116,6 +174,17 @@ impl VaryingContext<'_> {
                 }
                 self.built_in_mask |= bit;
 
+                let required = match built_in {
+                    Bi::SubgroupSize | Bi::SubgroupInvocationId => Capabilities::SUBGROUP,
+                    Bi::MeshTaskSize | Bi::CullPrimitive => Capabilities::MESH_SHADER,
+                    Bi::PrimitiveIndex => Capabilities::PRIMITIVE_INDEX,
+                    Bi::ViewIndex => Capabilities::MULTIVIEW,
+                    _ => Capabilities::empty(),
+                };
+                if !self.capabilities.contains(required) {
+                    return Err(VaryingError::UnsupportedCapability(required));
+                }
+
                 let width = 4;
                 let (visible, type_good) = match built_in {
                     Bi::BaseInstance | Bi::BaseVertex | Bi::InstanceIndex | Bi::VertexIndex => (
@@ -127,7 +196,7 @@ impl VaryingContext<'_> {
                             },
                     ),
                     Bi::ClipDistance | Bi::CullDistance => (
-                        self.stage == St::Vertex && self.output,
+                        matches!(self.stage, St::Vertex | St::Mesh) && self.output,
                         match *ty_inner {
                             Ti::Array { base, .. } => {
                                 self.types[base].inner
@@ -140,7 +209,7 @@ impl VaryingContext<'_> {
                         },
                     ),
                     Bi::PointSize => (
-                        self.stage == St::Vertex && self.output,
+                        matches!(self.stage, St::Vertex | St::Mesh) && self.output,
                         *ty_inner
                             == Ti::Scalar {
                                 kind: Sk::Float,
@@ -149,9 +218,9 @@ impl VaryingContext<'_> {
                     ),
                     Bi::Position => (
                         match self.stage {
-                            St::Vertex => self.output,
+                            St::Vertex | St::Mesh => self.output,
                             St::Fragment => !self.output,
-                            St::Compute => false,
+                            St::Compute | St::Task => false,
                         },
                         *ty_inner
                             == Ti::Vector {
@@ -160,6 +229,14 @@ impl VaryingContext<'_> {
                                 width,
                             },
                     ),
+                    Bi::ViewIndex => (
+                        matches!(self.stage, St::Vertex | St::Fragment) && !self.output,
+                        *ty_inner
+                            == Ti::Scalar {
+                                kind: Sk::Uint,
+                                width,
+                            },
+                    ),
                     Bi::FragDepth => (
                         self.stage == St::Fragment && self.output,
                         *ty_inner
@@ -176,6 +253,14 @@ impl VaryingContext<'_> {
                                 width: crate::BOOL_WIDTH,
                             },
                     ),
+                    Bi::PrimitiveIndex => (
+                        self.stage == St::Fragment && !self.output,
+                        *ty_inner
+                            == Ti::Scalar {
+                                kind: Sk::Uint,
+                                width,
+                            },
+                    ),
                     Bi::SampleIndex => (
                         self.stage == St::Fragment && !self.output,
                         *ty_inner
@@ -193,7 +278,7 @@ impl VaryingContext<'_> {
                             },
                     ),
                     Bi::LocalInvocationIndex => (
-                        self.stage == St::Compute && !self.output,
+                        matches!(self.stage, St::Compute | St::Task | St::Mesh) && !self.output,
                         *ty_inner
                             == Ti::Scalar {
                                 kind: Sk::Uint,
@@ -204,7 +289,27 @@ impl VaryingContext<'_> {
                     | Bi::LocalInvocationId
                     | Bi::WorkGroupId
                     | Bi::WorkGroupSize => (
-                        self.stage == St::Compute && !self.output,
+                        matches!(self.stage, St::Compute | St::Task | St::Mesh) && !self.output,
+                        *ty_inner
+                            == Ti::Vector {
+                                size: Vs::Tri,
+                                kind: Sk::Uint,
+                                width,
+                            },
+                    ),
+                    Bi::SubgroupSize | Bi::SubgroupInvocationId => (
+                        match self.stage {
+                            St::Compute | St::Fragment | St::Task | St::Mesh => !self.output,
+                            St::Vertex => false,
+                        },
+                        *ty_inner
+                            == Ti::Scalar {
+                                kind: Sk::Uint,
+                                width,
+                            },
+                    ),
+                    Bi::MeshTaskSize => (
+                        self.stage == St::Task && self.output,
                         *ty_inner
                             == Ti::Vector {
                                 size: Vs::Tri,
@@ -212,7 +317,17 @@ impl VaryingContext<'_> {
                                 width,
                             },
                     ),
+                    Bi::CullPrimitive => (
+                        self.stage == St::Mesh && self.output,
+                        *ty_inner
+                            == Ti::Scalar {
+                                kind: Sk::Bool,
+                                width: crate::BOOL_WIDTH,
+                            },
+                    ),
                 };
+                // only the per-primitive built-ins can be written per primitive
+                let visible = visible && (built_in == Bi::CullPrimitive) == self.per_primitive;
 
                 if !visible {
                     return Err(VaryingError::InvalidBuiltInStage(built_in));
@@ -224,10 +339,27 @@ impl VaryingContext<'_> {
             }
             crate::Binding::Location {
                 location,
+                second_blend_source,
                 interpolation,
                 sampling,
             } => {
-                if !self.location_mask.insert(location as usize) {
+                if second_blend_source {
+                    if !self
+                        .capabilities
+                        .contains(Capabilities::DUAL_SOURCE_BLENDING)
+                    {
+                        return Err(VaryingError::UnsupportedCapability(
+                            Capabilities::DUAL_SOURCE_BLENDING,
+                        ));
+                    }
+                    if self.stage != St::Fragment || !self.output || location != 0 {
+                        return Err(VaryingError::InvalidSecondBlendSource);
+                    }
+                    if self.second_blend_source {
+                        return Err(VaryingError::BindingCollision { location });
+                    }
+                    self.second_blend_source = true;
+                } else if !self.location_mask.insert(location as usize) {
                     return Err(VaryingError::BindingCollision { location });
                 }
 
@@ -237,6 +369,7 @@ impl VaryingContext<'_> {
                 // interpolation must be `Flat`.
                 let needs_interpolation = match self.stage {
                     crate::ShaderStage::Vertex => self.output,
+                    crate::ShaderStage::Mesh => self.output && !self.per_primitive,
                     crate::ShaderStage::Fragment => !self.output,
                     _ => false,
                 };
@@ -295,6 +428,53 @@ impl VaryingContext<'_> {
     }
 }
 
+/// Interface of one side of a pipeline stage boundary.
+#[derive(Default)]
+struct StageInterface<'a> {
+    built_ins: Vec<crate::BuiltIn>,
+    /// Varyings by location, with their type, interpolation and sampling,
+    /// where the defaults are applied.
+    varyings: Vec<(
+        u32,
+        &'a crate::TypeInner,
+        crate::Interpolation,
+        Option<crate::Sampling>,
+    )>,
+}
+
+impl<'a> StageInterface<'a> {
+    /// Add a binding of type `ty`, or the members of `ty` if it's a struct.
+    fn add(
+        &mut self,
+        types: &'a Arena<crate::Type>,
+        binding: Option<&crate::Binding>,
+        ty: Handle<crate::Type>,
+    ) {
+        match binding {
+            Some(&crate::Binding::BuiltIn(built_in)) => self.built_ins.push(built_in),
+            Some(&crate::Binding::Location {
+                location,
+                interpolation,
+                sampling,
+                ..
+            }) => {
+                let inner = &types[ty].inner;
+                let (interpolation, sampling) =
+                    crate::proc::resolve_interpolation(inner, interpolation, sampling);
+                self.varyings
+                    .push((location, inner, interpolation, sampling))
+            }
+            None => {
+                if let crate::TypeInner::Struct { ref members, .. } = types[ty].inner {
+                    for member in members {
+                        self.add(types, member.binding.as_ref(), member.ty);
+                    }
+                }
+            }
+        }
+    }
+}
+
 impl super::Validator {
     pub(super) fn validate_global_var(
         &self,
@@ -302,7 +482,12 @@ impl super::Validator {
         types: &Arena<crate::Type>,
     ) -> Result<(), GlobalVariableError> {
         log::debug!("var {:?}", var);
-        let type_info = &self.types[var.ty.index()];
+        // binding arrays are checked against the type of their elements
+        let (ty, is_binding_array) = match types[var.ty].inner {
+            crate::TypeInner::BindingArray { base, .. } => (base, true),
+            _ => (var.ty, false),
+        };
+        let type_info = &self.types[ty.index()];
 
         let (allowed_storage_access, required_type_flags, is_resource) = match var.class {
             crate::StorageClass::Function => return Err(GlobalVariableError::InvalidUsage),
@@ -334,14 +519,14 @@ impl super::Validator {
                 )
             }
             crate::StorageClass::Handle => {
-                let access = match types[var.ty].inner {
+                let access = match types[ty].inner {
                     crate::TypeInner::Image {
                         class: crate::ImageClass::Storage(_),
                         ..
                     } => crate::StorageAccess::all(),
-                    crate::TypeInner::Image { .. } | crate::TypeInner::Sampler { .. } => {
-                        crate::StorageAccess::empty()
-                    }
+                    crate::TypeInner::Image { .. }
+                    | crate::TypeInner::Sampler { .. }
+                    | crate::TypeInner::AccelerationStructure => crate::StorageAccess::empty(),
                     _ => return Err(GlobalVariableError::InvalidType),
                 };
                 (access, TypeFlags::empty(), true)
@@ -363,6 +548,18 @@ impl super::Validator {
                     false,
                 )
             }
+            crate::StorageClass::TaskPayload => {
+                if !self.capabilities.contains(Capabilities::MESH_SHADER) {
+                    return Err(GlobalVariableError::UnsupportedCapability(
+                        Capabilities::MESH_SHADER,
+                    ));
+                }
+                (
+                    crate::StorageAccess::empty(),
+                    TypeFlags::DATA | TypeFlags::SIZED,
+                    false,
+                )
+            }
         };
 
         if !allowed_storage_access.contains(var.storage_access) {
@@ -382,6 +579,9 @@ impl super::Validator {
         if is_resource != var.binding.is_some() {
             return Err(GlobalVariableError::InvalidBinding);
         }
+        if is_binding_array && !is_resource {
+            return Err(GlobalVariableError::InvalidType);
+        }
 
         Ok(())
     }
@@ -395,7 +595,16 @@ impl super::Validator {
         if ep.early_depth_test.is_some() && ep.stage != crate::ShaderStage::Fragment {
             return Err(EntryPointError::UnexpectedEarlyDepthTest);
         }
-        if ep.stage == crate::ShaderStage::Compute {
+        let is_mesh_stage = match ep.stage {
+            crate::ShaderStage::Task | crate::ShaderStage::Mesh => true,
+            _ => false,
+        };
+        if is_mesh_stage && !self.capabilities.contains(Capabilities::MESH_SHADER) {
+            return Err(EntryPointError::UnsupportedCapability(
+                Capabilities::MESH_SHADER,
+            ));
+        }
+        if ep.stage == crate::ShaderStage::Compute || is_mesh_stage {
             if ep
                 .workgroup_size
                 .iter()
@@ -407,13 +616,48 @@ impl super::Validator {
             return Err(EntryPointError::UnexpectedWorkgroupSize);
         }
 
-        let stage_bit = match ep.stage {
-            crate::ShaderStage::Vertex => ShaderStages::VERTEX,
-            crate::ShaderStage::Fragment => ShaderStages::FRAGMENT,
-            crate::ShaderStage::Compute => ShaderStages::COMPUTE,
+        let stage_bit = ShaderStages::from(ep.stage);
+
+        match (ep.stage, ep.mesh_info.as_ref()) {
+            (crate::ShaderStage::Mesh, Some(mesh_info)) => {
+                if [mesh_info.max_vertices, mesh_info.max_primitives]
+                    .iter()
+                    .any(|&count| count == 0 || count > MAX_MESH_OUTPUTS)
+                {
+                    return Err(EntryPointError::OutOfRangeMeshOutputs);
+                }
+            }
+            (crate::ShaderStage::Mesh, None) | (_, Some(_)) => {
+                return Err(EntryPointError::InvalidMeshInfo)
+            }
+            (_, None) => {}
+        }
+
+        let payload_good = match ep.task_payload {
+            Some(handle) => {
+                is_mesh_stage
+                    && module.global_variables.try_get(handle).map(|var| var.class)
+                        == Some(crate::StorageClass::TaskPayload)
+            }
+            None => ep.stage != crate::ShaderStage::Task,
         };
+        if !payload_good {
+            return Err(EntryPointError::InvalidTaskPayload(ep.task_payload));
+        }
 
-        let info = self.validate_function(&ep.function, module, mod_info)?;
+        let result_good = match (ep.stage, ep.function.result.as_ref()) {
+            (crate::ShaderStage::Task, Some(fr)) => {
+                fr.binding == Some(crate::Binding::BuiltIn(crate::BuiltIn::MeshTaskSize))
+            }
+            (crate::ShaderStage::Task, None) => false,
+            (crate::ShaderStage::Mesh, result) => result.is_none(),
+            _ => true,
+        };
+        if !result_good {
+            return Err(EntryPointError::InvalidMeshStageResult);
+        }
+
+        let info = self.validate_function(&ep.function, module, mod_info, ep.mesh_info.as_ref())?;
 
         if !info.available_stages.contains(stage_bit) {
             return Err(EntryPointError::ForbiddenStageOperations);
@@ -426,9 +670,12 @@ impl super::Validator {
                 ty: fa.ty,
                 stage: ep.stage,
                 output: false,
+                per_primitive: false,
                 types: &module.types,
                 location_mask: &mut self.location_mask,
                 built_in_mask: argument_built_ins,
+                second_blend_source: false,
+                capabilities: self.capabilities,
             };
             ctx.validate(fa.binding.as_ref())
                 .map_err(|e| EntryPointError::Argument(index as u32, e))?;
@@ -441,12 +688,45 @@ impl super::Validator {
                 ty: fr.ty,
                 stage: ep.stage,
                 output: true,
+                per_primitive: false,
                 types: &module.types,
                 location_mask: &mut self.location_mask,
                 built_in_mask: 0,
+                second_blend_source: false,
+                capabilities: self.capabilities,
             };
             ctx.validate(fr.binding.as_ref())
                 .map_err(EntryPointError::Result)?;
+            if ctx.second_blend_source
+                && (ctx.location_mask.len() != 1 || !ctx.location_mask.contains(0))
+            {
+                return Err(EntryPointError::Result(
+                    VaryingError::InvalidDualSourceBlending,
+                ));
+            }
+        }
+
+        if let Some(ref mesh_info) = ep.mesh_info {
+            // vertex and primitive outputs share the locations
+            let mut ctx = VaryingContext {
+                ty: mesh_info.vertex_output_type,
+                stage: ep.stage,
+                output: true,
+                per_primitive: false,
+                types: &module.types,
+                location_mask: &mut self.location_mask,
+                built_in_mask: 0,
+                second_blend_source: false,
+                capabilities: self.capabilities,
+            };
+            ctx.validate(None).map_err(EntryPointError::VertexOutput)?;
+            if let Some(ty) = mesh_info.primitive_output_type {
+                ctx.ty = ty;
+                ctx.per_primitive = true;
+                ctx.built_in_mask = 0;
+                ctx.validate(None)
+                    .map_err(EntryPointError::PrimitiveOutput)?;
+            }
         }
 
         for bg in self.bind_group_masks.iter_mut() {
@@ -471,7 +751,16 @@ impl super::Validator {
                 },
                 crate::StorageClass::Private | crate::StorageClass::WorkGroup => GlobalUse::all(),
                 crate::StorageClass::PushConstant => GlobalUse::READ,
+                crate::StorageClass::TaskPayload => match ep.stage {
+                    crate::ShaderStage::Task => GlobalUse::all(),
+                    crate::ShaderStage::Mesh => GlobalUse::READ | GlobalUse::QUERY,
+                    _ => GlobalUse::empty(),
+                },
             };
+            if var.class == crate::StorageClass::TaskPayload && ep.task_payload != Some(var_handle)
+            {
+                return Err(EntryPointError::InvalidTaskPayload(Some(var_handle)));
+            }
             if !allowed_usage.contains(usage) {
                 log::warn!("\tUsage error for: {:?}", var);
                 log::warn!(
@@ -494,4 +783,78 @@ impl super::Validator {
 
         Ok(info)
     }
+
+    /// Validate the interface between a vertex and a fragment entry point,
+    /// which may come from different modules.
+    ///
+    /// Both entry points are expected to be valid by themselves.
+    pub fn validate_pipeline(
+        &self,
+        vertex: (&crate::Module, &crate::EntryPoint),
+        fragment: (&crate::Module, &crate::EntryPoint),
+    ) -> Result<(), PipelineError> {
+        let (vertex_module, vertex_ep) = vertex;
+        let (fragment_module, fragment_ep) = fragment;
+        for &(ep, expected) in [
+            (vertex_ep, crate::ShaderStage::Vertex),
+            (fragment_ep, crate::ShaderStage::Fragment),
+        ]
+        .iter()
+        {
+            if ep.stage != expected {
+                return Err(PipelineError::InvalidStage {
+                    name: ep.name.clone(),
+                    stage: ep.stage,
+                    expected,
+                });
+            }
+        }
+
+        let mut outputs = StageInterface::default();
+        if let Some(ref result) = vertex_ep.function.result {
+            outputs.add(&vertex_module.types, result.binding.as_ref(), result.ty);
+        }
+        if !outputs.built_ins.contains(&crate::BuiltIn::Position) {
+            return Err(PipelineError::MissingBuiltIn(crate::BuiltIn::Position));
+        }
+
+        let mut inputs = StageInterface::default();
+        for argument in fragment_ep.function.arguments.iter() {
+            inputs.add(
+                &fragment_module.types,
+                argument.binding.as_ref(),
+                argument.ty,
+            );
+        }
+        for &(location, input_ty, input_interpolation, input_sampling) in inputs.varyings.iter() {
+            let &(_, output_ty, output_interpolation, output_sampling) = outputs
+                .varyings
+                .iter()
+                .find(|output| output.0 == location)
+                .ok_or(PipelineError::MissingVarying { location })?;
+            if output_ty != input_ty {
+                return Err(PipelineError::TypeMismatch {
+                    location,
+                    output: output_ty.clone(),
+                    input: input_ty.clone(),
+                });
+            }
+            if output_interpolation != input_interpolation {
+                return Err(PipelineError::InterpolationMismatch {
+                    location,
+                    output: output_interpolation,
+                    input: input_interpolation,
+                });
+            }
+            if output_sampling != input_sampling {
+                return Err(PipelineError::SamplingMismatch {
+                    location,
+                    output: output_sampling,
+                    input: input_sampling,
+                });
+            }
+        }
+
+        Ok(())
+    }
 }
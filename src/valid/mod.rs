@@ -8,7 +8,7 @@ mod r#type;
 use crate::{
     arena::{Arena, Handle},
     proc::{InvalidBaseType, Layouter},
-    FastHashSet,
+    FastHashSet, WithSpan,
 };
 use bit_set::BitSet;
 use std::ops;
@@ -19,8 +19,10 @@ use std::ops;
 pub use analyzer::{ExpressionInfo, FunctionInfo, GlobalUse, Uniformity, UniformityRequirements};
 pub use compose::ComposeError;
 pub use expression::ExpressionError;
-pub use function::{CallError, FunctionError, LocalVariableError};
-pub use interface::{EntryPointError, GlobalVariableError, VaryingError};
+pub use function::{
+    AtomicError, CallError, FunctionError, LocalVariableError, MeshFunctionError, SubgroupError,
+};
+pub use interface::{EntryPointError, GlobalVariableError, PipelineError, VaryingError};
 pub use r#type::{Disalignment, TypeError, TypeFlags};
 
 bitflags::bitflags! {
@@ -53,11 +55,29 @@ bitflags::bitflags! {
     #[derive(Default)]
     #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
     #[cfg_attr(feature = "deserialize", derive(serde::Deserialize))]
-    pub struct Capabilities: u8 {
+    pub struct Capabilities: u16 {
         /// Support for `StorageClass:PushConstant`.
         const PUSH_CONSTANT = 0x1;
         /// Float values with width = 8.
         const FLOAT64 = 0x2;
+        /// Subgroup operations and built-ins.
+        const SUBGROUP = 0x4;
+        /// Float values with width = 2.
+        const FLOAT16 = 0x8;
+        /// Integer values with width = 8.
+        const INT64 = 0x10;
+        /// Indexing binding arrays with non-uniform values.
+        const BINDING_ARRAY_NON_UNIFORM_INDEXING = 0x20;
+        /// Task and mesh shader stages.
+        const MESH_SHADER = 0x40;
+        /// Acceleration structures and ray queries.
+        const RAY_QUERY = 0x80;
+        /// Primitive index built-in in fragment shaders.
+        const PRIMITIVE_INDEX = 0x100;
+        /// View index built-in for multiview rendering.
+        const MULTIVIEW = 0x200;
+        /// Second blend source of fragment outputs.
+        const DUAL_SOURCE_BLENDING = 0x400;
     }
 }
 
@@ -69,6 +89,20 @@ bitflags::bitflags! {
         const VERTEX = 0x1;
         const FRAGMENT = 0x2;
         const COMPUTE = 0x4;
+        const TASK = 0x8;
+        const MESH = 0x10;
+    }
+}
+
+impl From<crate::ShaderStage> for ShaderStages {
+    fn from(stage: crate::ShaderStage) -> Self {
+        match stage {
+            crate::ShaderStage::Vertex => Self::VERTEX,
+            crate::ShaderStage::Fragment => Self::FRAGMENT,
+            crate::ShaderStage::Compute => Self::COMPUTE,
+            crate::ShaderStage::Task => Self::TASK,
+            crate::ShaderStage::Mesh => Self::MESH,
+        }
     }
 }
 
@@ -108,6 +142,8 @@ pub enum ConstantError {
     UnresolvedComponent(Handle<crate::Constant>),
     #[error("The array size handle {0:?} can not be resolved")]
     UnresolvedSize(Handle<crate::Constant>),
+    #[error("Only scalar constants can be specialized")]
+    NonScalarSpecialization,
     #[error(transparent)]
     Compose(#[from] ComposeError),
 }
@@ -155,12 +191,39 @@ pub enum ValidationError {
     Corrupted,
 }
 
+/// Returns the global variable behind an image or sampler expression, and the
+/// type of the resource.
+///
+/// The expression is either a global variable itself, or an element
+/// of a global binding array.
+fn resolve_resource(
+    expr: Handle<crate::Expression>,
+    expressions: &Arena<crate::Expression>,
+    global_vars: &Arena<crate::GlobalVariable>,
+    types: &Arena<crate::Type>,
+) -> Option<(Handle<crate::GlobalVariable>, Handle<crate::Type>)> {
+    match expressions[expr] {
+        crate::Expression::GlobalVariable(var) => Some((var, global_vars[var].ty)),
+        crate::Expression::Access { base, .. } | crate::Expression::AccessIndex { base, .. } => {
+            match expressions[base] {
+                crate::Expression::GlobalVariable(var) => match types[global_vars[var].ty].inner {
+                    crate::TypeInner::BindingArray { base, .. } => Some((var, base)),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
 impl crate::TypeInner {
     fn is_sized(&self) -> bool {
         match *self {
             Self::Scalar { .. }
             | Self::Vector { .. }
             | Self::Matrix { .. }
+            | Self::Atomic { .. }
             | Self::Array {
                 size: crate::ArraySize::Constant(_),
                 ..
@@ -168,7 +231,12 @@ impl crate::TypeInner {
             | Self::Pointer { .. }
             | Self::ValuePointer { .. }
             | Self::Struct { .. } => true,
-            Self::Array { .. } | Self::Image { .. } | Self::Sampler { .. } => false,
+            Self::Array { .. }
+            | Self::Image { .. }
+            | Self::Sampler { .. }
+            | Self::AccelerationStructure
+            | Self::RayQuery
+            | Self::BindingArray { .. } => false,
         }
     }
 
@@ -223,6 +291,9 @@ impl Validator {
                 }
             }
             crate::ConstantInner::Composite { ty, ref components } => {
+                if con.specialization.is_some() {
+                    return Err(ConstantError::NonScalarSpecialization);
+                }
                 match types[ty].inner {
                     crate::TypeInner::Array {
                         size: crate::ArraySize::Constant(size_handle),
@@ -249,17 +320,39 @@ impl Validator {
     }
 
     /// Check the given module to be valid.
-    pub fn validate(&mut self, module: &crate::Module) -> Result<ModuleInfo, ValidationError> {
+    ///
+    /// The returned error carries the source spans of the offending items,
+    /// if the front-end has provided them.
+    pub fn validate(
+        &mut self,
+        module: &crate::Module,
+    ) -> Result<ModuleInfo, WithSpan<ValidationError>> {
         self.reset_types(module.types.len());
-        self.layouter.update(&module.types, &module.constants)?;
+        self.layouter
+            .update(&module.types, &module.constants)
+            .map_err(|error| {
+                let InvalidBaseType(handle) = error;
+                WithSpan::new(ValidationError::from(error)).with_handle(
+                    handle,
+                    &module.types,
+                    "type",
+                )
+            })?;
 
         if self.flags.contains(ValidationFlags::CONSTANTS) {
             for (handle, constant) in module.constants.iter() {
                 self.validate_constant(handle, &module.constants, &module.types)
-                    .map_err(|error| ValidationError::Constant {
-                        handle,
-                        name: constant.name.clone().unwrap_or_default(),
-                        error,
+                    .map_err(|error| {
+                        WithSpan::new(ValidationError::Constant {
+                            handle,
+                            name: constant.name.clone().unwrap_or_default(),
+                            error,
+                        })
+                        .with_handle(
+                            handle,
+                            &module.constants,
+                            "constant",
+                        )
                     })?;
             }
         }
@@ -267,20 +360,30 @@ impl Validator {
         for (handle, ty) in module.types.iter() {
             let ty_info = self
                 .validate_type(handle, &module.types, &module.constants)
-                .map_err(|error| ValidationError::Type {
-                    handle,
-                    name: ty.name.clone().unwrap_or_default(),
-                    error,
+                .map_err(|error| {
+                    WithSpan::new(ValidationError::Type {
+                        handle,
+                        name: ty.name.clone().unwrap_or_default(),
+                        error,
+                    })
+                    .with_handle(handle, &module.types, "type")
                 })?;
             self.types[handle.index()] = ty_info;
         }
 
         for (var_handle, var) in module.global_variables.iter() {
             self.validate_global_var(var, &module.types)
-                .map_err(|error| ValidationError::GlobalVariable {
-                    handle: var_handle,
-                    name: var.name.clone().unwrap_or_default(),
-                    error,
+                .map_err(|error| {
+                    WithSpan::new(ValidationError::GlobalVariable {
+                        handle: var_handle,
+                        name: var.name.clone().unwrap_or_default(),
+                        error,
+                    })
+                    .with_handle(
+                        var_handle,
+                        &module.global_variables,
+                        "global variable",
+                    )
                 })?;
         }
 
@@ -290,14 +393,17 @@ impl Validator {
         };
 
         for (handle, fun) in module.functions.iter() {
-            match self.validate_function(fun, module, &mod_info) {
+            match self.validate_function(fun, module, &mod_info, None) {
                 Ok(info) => mod_info.functions.push(info),
                 Err(error) => {
-                    return Err(ValidationError::Function {
+                    let spans = error.spans(fun);
+                    return Err(WithSpan::new(ValidationError::Function {
                         handle,
                         name: fun.name.clone().unwrap_or_default(),
                         error,
                     })
+                    .with_context(spans)
+                    .with_handle(handle, &module.functions, "function"));
                 }
             }
         }
@@ -305,21 +411,31 @@ impl Validator {
         let mut ep_map = FastHashSet::default();
         for ep in module.entry_points.iter() {
             if !ep_map.insert((ep.stage, &ep.name)) {
-                return Err(ValidationError::EntryPoint {
+                return Err(WithSpan::new(ValidationError::EntryPoint {
                     stage: ep.stage,
                     name: ep.name.clone(),
                     error: EntryPointError::Conflict,
-                });
+                }));
             }
 
             match self.validate_entry_point(ep, module, &mod_info) {
                 Ok(info) => mod_info.entry_points.push(info),
                 Err(error) => {
-                    return Err(ValidationError::EntryPoint {
+                    let spans = match error {
+                        EntryPointError::Function(ref error) => error.spans(&ep.function),
+                        EntryPointError::InvalidGlobalUsage(handle, _)
+                        | EntryPointError::BindingCollision(handle) => vec![(
+                            module.global_variables.get_span(handle),
+                            "global variable".to_string(),
+                        )],
+                        _ => Vec::new(),
+                    };
+                    return Err(WithSpan::new(ValidationError::EntryPoint {
                         stage: ep.stage,
                         name: ep.name.clone(),
                         error,
                     })
+                    .with_context(spans.into_iter().filter(|&(span, _)| span.is_defined())));
                 }
             }
         }
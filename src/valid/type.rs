@@ -69,6 +69,8 @@ pub enum Disalignment {
 pub enum TypeError {
     #[error("The {0:?} scalar width {1} is not supported")]
     InvalidWidth(crate::ScalarKind, crate::Bytes),
+    #[error("The {0:?} scalar width {1} is not supported for an atomic")]
+    InvalidAtomicWidth(crate::ScalarKind, crate::Bytes),
     #[error("The base handle {0:?} can not be resolved")]
     UnresolvedBase(Handle<crate::Type>),
     #[error("Invalid type for pointer target {0:?}")]
@@ -100,6 +102,10 @@ pub enum TypeError {
     },
     #[error("The composite type contains a top-level structure")]
     NestedTopLevel,
+    #[error("Base type {0:?} for the binding array is not a resource")]
+    InvalidBindingArrayBaseType(Handle<crate::Type>),
+    #[error("Capability {0:?} is required")]
+    MissingCapability(Capabilities),
 }
 
 // Only makes sense if `flags.contains(HOST_SHARED)`
@@ -164,9 +170,13 @@ impl super::Validator {
         match kind {
             crate::ScalarKind::Bool => width == crate::BOOL_WIDTH,
             crate::ScalarKind::Float => {
-                width == 4 || (width == 8 && self.capabilities.contains(Capabilities::FLOAT64))
+                width == 4
+                    || (width == 8 && self.capabilities.contains(Capabilities::FLOAT64))
+                    || (width == 2 && self.capabilities.contains(Capabilities::FLOAT16))
+            }
+            crate::ScalarKind::Sint | crate::ScalarKind::Uint => {
+                width == 4 || (width == 8 && self.capabilities.contains(Capabilities::INT64))
             }
-            crate::ScalarKind::Sint | crate::ScalarKind::Uint => width == 4,
         }
     }
 
@@ -226,6 +236,19 @@ impl super::Validator {
                     count * (width as u32),
                 )
             }
+            Ti::Atomic { kind, width } => {
+                let good = match kind {
+                    crate::ScalarKind::Bool | crate::ScalarKind::Float => false,
+                    crate::ScalarKind::Sint | crate::ScalarKind::Uint => width == 4,
+                };
+                if !good {
+                    return Err(TypeError::InvalidAtomicWidth(kind, width));
+                }
+                TypeInfo::new(
+                    TypeFlags::DATA | TypeFlags::SIZED | TypeFlags::HOST_SHARED,
+                    width as u32,
+                )
+            }
             Ti::Pointer { base, class: _ } => {
                 if base >= handle {
                     return Err(TypeError::UnresolvedBase(base));
@@ -389,7 +412,12 @@ impl super::Validator {
                     if !base_info.flags.contains(TypeFlags::DATA) {
                         return Err(TypeError::InvalidData(member.ty));
                     }
-                    if top_level && !base_info.flags.contains(TypeFlags::INTERFACE) {
+                    // atomics are not interface types, but they can live in blocks
+                    if top_level
+                        && !base_info
+                            .flags
+                            .intersects(TypeFlags::INTERFACE | TypeFlags::HOST_SHARED)
+                    {
                         return Err(TypeError::InvalidBlockType(member.ty));
                     }
                     if base_info.flags.contains(TypeFlags::TOP_LEVEL) {
@@ -461,6 +489,35 @@ impl super::Validator {
                 ti
             }
             Ti::Image { .. } | Ti::Sampler { .. } => TypeInfo::new(TypeFlags::empty(), 0),
+            Ti::AccelerationStructure | Ti::RayQuery => {
+                if !self.capabilities.contains(Capabilities::RAY_QUERY) {
+                    return Err(TypeError::MissingCapability(Capabilities::RAY_QUERY));
+                }
+                TypeInfo::new(TypeFlags::empty(), 0)
+            }
+            Ti::BindingArray { base, size } => {
+                if base >= handle {
+                    return Err(TypeError::UnresolvedBase(base));
+                }
+                match types[base].inner {
+                    Ti::Image { .. } | Ti::Sampler { .. } => {}
+                    Ti::Struct {
+                        top_level: true, ..
+                    } => {}
+                    _ => return Err(TypeError::InvalidBindingArrayBaseType(base)),
+                }
+                if let crate::ArraySize::Constant(const_handle) = size {
+                    match constants
+                        .try_get(const_handle)
+                        .and_then(crate::Constant::to_array_length)
+                    {
+                        Some(0) => return Err(TypeError::NonPositiveArrayLength(const_handle)),
+                        Some(_) => {}
+                        None => return Err(TypeError::InvalidArraySizeConstant(const_handle)),
+                    }
+                }
+                TypeInfo::new(TypeFlags::empty(), 0)
+            }
         })
     }
 }
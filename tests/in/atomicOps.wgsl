@@ -0,0 +1,28 @@
+[[block]]
+struct Counters {
+    total: atomic<u32>;
+    extents: array<atomic<i32>, 2u>;
+};
+
+[[group(0), binding(0)]]
+var<storage> counters: [[access(read_write)]] Counters;
+
+var<workgroup> workgroup_counter: atomic<u32>;
+
+[[stage(compute), workgroup_size(2)]]
+fn cs_main([[builtin(local_invocation_id)]] id: vec3<u32>) {
+    atomicStore(&workgroup_counter, 0u);
+    workgroupBarrier();
+
+    let local_index = atomicAdd(&workgroup_counter, 1u);
+    atomicSub(&counters.total, 1u);
+    atomicMin(&counters.extents[0], i32(id.x));
+    atomicMax(&counters.extents[1], i32(id.x));
+    atomicAnd(&counters.total, 255u);
+    atomicOr(&counters.total, local_index);
+    atomicXor(&counters.total, 2u);
+    let old = atomicExchange(&counters.total, atomicLoad(&workgroup_counter));
+    let swapped = atomicCompareExchangeWeak(&counters.total, old, 7u);
+    let retried = atomicCompareExchangeWeak(&counters.total, old + 1u, swapped.x);
+    atomicStore(&counters.total, retried.x + swapped.y);
+}
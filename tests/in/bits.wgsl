@@ -0,0 +1,24 @@
+[[stage(compute), workgroup_size(1)]]
+fn main() {
+    var i: i32 = 0;
+    var i2: vec2<i32> = vec2<i32>(0);
+    var i4: vec4<i32> = vec4<i32>(0);
+    var u: u32 = 0u;
+    var u3: vec3<u32> = vec3<u32>(0u);
+    i = countTrailingZeros(i);
+    u3 = countTrailingZeros(u3);
+    i2 = countLeadingZeros(i2);
+    u = countLeadingZeros(u);
+    i4 = countOneBits(i4);
+    u = countOneBits(u);
+    i2 = reverseBits(i2);
+    u3 = reverseBits(u3);
+    i = extractBits(i, 5u, 10u);
+    u3 = extractBits(u3, 5u, 10u);
+    i4 = insertBits(i4, i4, 5u, 10u);
+    u = insertBits(u, u, 5u, 10u);
+    i2 = firstTrailingBit(i2);
+    u = firstTrailingBit(u);
+    i = firstLeadingBit(i);
+    u3 = firstLeadingBit(u3);
+}
@@ -0,0 +1,14 @@
+let WIDTH: u32 = 4u;
+let HEIGHT: u32 = WIDTH * 2u;
+let SCALE: f32 = 0.5 * 3.0;
+let MASK: vec4<f32> = vec4<f32>(1.0, 2.0, 3.0, 4.0) * SCALE;
+let LENGTH: f32 = length(vec2<f32>(3.0, 4.0));
+let BITS: u32 = countOneBits(255u) + (1u << 3u);
+let SWIZZLED: vec2<f32> = MASK.wz;
+
+var<private> data: array<f32, WIDTH * HEIGHT + 1u>;
+
+[[stage(compute), workgroup_size(1)]]
+fn main() {
+    data[BITS] = MASK.x + LENGTH + SWIZZLED.y;
+}
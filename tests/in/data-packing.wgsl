@@ -0,0 +1,21 @@
+[[block]]
+struct Packing {
+    packed: u32;
+    pair: vec2<f32>;
+    quad: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<storage> data: [[access(read_write)]] Packing;
+
+[[stage(compute), workgroup_size(1)]]
+fn main() {
+    let pair = data.pair;
+    let quad = data.quad;
+    let packed = pack4x8snorm(quad) + pack4x8unorm(quad) + pack2x16snorm(pair) + pack2x16unorm(pair) + pack2x16float(pair);
+    data.packed = packed;
+
+    let value = data.packed;
+    data.quad = unpack4x8snorm(value) + unpack4x8unorm(value);
+    data.pair = unpack2x16snorm(value) + unpack2x16unorm(value) + unpack2x16float(value);
+}
@@ -0,0 +1,19 @@
+enable f16;
+
+[[block]]
+struct Halves {
+    value: f16;
+    pair: vec2<f16>;
+    quad: vec4<f16>;
+    scale: f32;
+};
+
+[[group(0), binding(0)]]
+var<storage> halves: [[access(read_write)]] Halves;
+
+[[stage(compute), workgroup_size(1)]]
+fn main() {
+    let squared = halves.pair * halves.pair;
+    halves.value = halves.value + squared.x;
+    halves.quad = vec4<f16>(squared, halves.value, squared.y);
+}
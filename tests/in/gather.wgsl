@@ -0,0 +1,26 @@
+[[group(0), binding(0)]]
+var image_2d: texture_2d<f32>;
+[[group(0), binding(1)]]
+var image_2d_u32: texture_2d<u32>;
+[[group(0), binding(2)]]
+var image_cube_array: texture_cube_array<f32>;
+[[group(0), binding(3)]]
+var image_2d_depth: texture_depth_2d;
+[[group(0), binding(4)]]
+var image_2d_shadow: texture_depth_2d_array;
+[[group(1), binding(0)]]
+var sampler_reg: sampler;
+[[group(1), binding(1)]]
+var sampler_cmp: sampler_comparison;
+
+[[stage(fragment)]]
+fn main() -> [[location(0)]] vec4<f32> {
+    let tc = vec2<f32>(0.5);
+    let s2d = textureGather(1, image_2d, sampler_reg, tc);
+    let s2d_offset = textureGather(3, image_2d, sampler_reg, tc, vec2<i32>(3, 1));
+    let s_cube = textureGather(2, image_cube_array, sampler_reg, vec3<f32>(0.5), 1);
+    let s_depth = textureGather(image_2d_depth, sampler_reg, tc);
+    let s_depth_cmp = textureGatherCompare(image_2d_shadow, sampler_cmp, tc, 0, 0.5, vec2<i32>(3, 1));
+    let u = textureGather(0, image_2d_u32, sampler_reg, tc);
+    return s2d + s2d_offset + s_cube + s_depth + s_depth_cmp + vec4<f32>(u);
+}
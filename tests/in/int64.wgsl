@@ -0,0 +1,16 @@
+[[block]]
+struct Culling {
+    key: u64;
+    offset: i64;
+    counts: vec2<u64>;
+};
+
+[[group(0), binding(0)]]
+var<storage> culling: [[access(read_write)]] Culling;
+
+[[stage(compute), workgroup_size(1)]]
+fn main() {
+    culling.key = culling.key * 4294967296u64 + 1u64;
+    culling.offset = -culling.offset - 1i64;
+    culling.counts = culling.counts + vec2<u64>(culling.key, 1u64);
+}
@@ -0,0 +1,35 @@
+enable mesh_shading;
+
+struct TaskPayload {
+    colorMask: vec4<f32>;
+    visible: bool;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+};
+
+struct PrimitiveOutput {
+    [[location(1)]] colorMask: vec4<f32>;
+    [[builtin(cull_primitive)]] cull: bool;
+};
+
+var<task_payload> taskPayload: TaskPayload;
+
+[[stage(task), workgroup_size(1), payload(taskPayload)]]
+fn ts_main() -> [[builtin(mesh_task_size)]] vec3<u32> {
+    taskPayload.colorMask = vec4<f32>(1.0, 1.0, 0.0, 1.0);
+    taskPayload.visible = true;
+    return vec3<u32>(3u, 1u, 1u);
+}
+
+[[stage(mesh), workgroup_size(1), output_topology(triangles), max_vertices(3), max_primitives(1), vertex_output(VertexOutput), primitive_output(PrimitiveOutput), payload(taskPayload)]]
+fn ms_main([[builtin(local_invocation_index)]] index: u32, [[builtin(global_invocation_id)]] id: vec3<u32>) {
+    setMeshOutputs(3u, 1u);
+    setVertex(0u, VertexOutput(vec4<f32>(0.0, 1.0, 0.0, 1.0), vec4<f32>(0.0, 1.0, 0.0, 1.0) * taskPayload.colorMask));
+    setVertex(1u, VertexOutput(vec4<f32>(-1.0, -1.0, 0.0, 1.0), vec4<f32>(0.0, 0.0, 1.0, 1.0) * taskPayload.colorMask));
+    setVertex(2u, VertexOutput(vec4<f32>(1.0, -1.0, 0.0, 1.0), vec4<f32>(1.0, 0.0, 0.0, 1.0) * taskPayload.colorMask));
+    setIndices(0u, vec3<u32>(0u, 1u, 2u));
+    setPrimitive(0u, PrimitiveOutput(vec4<f32>(1.0, 0.0, 1.0, 1.0), !taskPayload.visible));
+}
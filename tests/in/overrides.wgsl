@@ -0,0 +1,14 @@
+[[id(0)]] override has_point_light: bool = true;
+[[id(1200)]] override specular_param: f32 = 2.3;
+[[id(1300)]] override gain: f32 = 1.1;
+override width: f32 = 0.0;
+override depth: f32 = 0.5;
+override height = 2.0;
+
+[[stage(compute), workgroup_size(1)]]
+fn main() {
+    var t: f32 = height * 5.0;
+    let a: bool = !has_point_light;
+    var x: bool = a;
+    t = t + gain * width - depth * specular_param;
+}
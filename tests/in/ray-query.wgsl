@@ -0,0 +1,25 @@
+[[group(0), binding(0)]]
+var acc_struct: acceleration_structure;
+
+[[stage(fragment)]]
+fn main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    var rq: ray_query;
+
+    let dir = vec3<f32>(0.0, 0.0, 1.0);
+    let origin = vec3<f32>(position.x, position.y, 0.0);
+    let desc = RayDesc(4u, 255u, 0.1, 100.0, origin, dir);
+    rayQueryInitialize(&rq, acc_struct, desc);
+
+    loop {
+        if (!rayQueryProceed(&rq)) {
+            break;
+        }
+    }
+
+    let intersection = rayQueryGetCommittedIntersection(&rq);
+    if (intersection.kind == 0u) {
+        rayQueryTerminate(&rq);
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    return vec4<f32>(intersection.barycentrics, intersection.t, 1.0);
+}
@@ -0,0 +1,30 @@
+[[stage(compute), workgroup_size(64)]]
+fn main(
+    [[builtin(subgroup_size)]] size: u32,
+    [[builtin(subgroup_invocation_id)]] invocation_id: u32,
+) {
+    let even = (invocation_id & 1u) == 0u;
+    let ballot = subgroupBallot(even);
+    let active = subgroupBallot();
+    let all = subgroupAll(even);
+    let any = subgroupAny(even);
+
+    let sum = subgroupAdd(invocation_id);
+    let product = subgroupMul(invocation_id);
+    let min = subgroupMin(invocation_id);
+    let max = subgroupMax(vec2<u32>(invocation_id, size));
+    let and = subgroupAnd(invocation_id);
+    let or = subgroupOr(invocation_id);
+    let xor = subgroupXor(invocation_id);
+    let exclusive_sum = subgroupExclusiveAdd(invocation_id);
+    let exclusive_product = subgroupExclusiveMul(invocation_id);
+    let inclusive_sum = subgroupInclusiveAdd(invocation_id);
+    let inclusive_product = subgroupInclusiveMul(invocation_id);
+
+    let first = subgroupBroadcastFirst(invocation_id);
+    let broadcast = subgroupBroadcast(invocation_id, 4u);
+    let shuffle = subgroupShuffle(invocation_id, size - 1u - invocation_id);
+    let down = subgroupShuffleDown(invocation_id, 1u);
+    let up = subgroupShuffleUp(invocation_id, 1u);
+    let xored = subgroupShuffleXor(invocation_id, size - 1u);
+}
@@ -0,0 +1,36 @@
+#version 310 es
+
+precision highp float;
+
+layout(local_size_x = 2, local_size_y = 1, local_size_z = 1) in;
+
+buffer Counters_block_0 {
+    uint total;
+    int extents[2];
+} _group_0_binding_0;
+
+shared uint workgroup_counter;
+
+
+void main() {
+    uvec3 id = gl_LocalInvocationID;
+    workgroup_counter = 0u;
+    groupMemoryBarrier();
+    uint _expr5 = atomicAdd(workgroup_counter, 1u);
+    uint _expr8 = atomicAdd(_group_0_binding_0.total, -(1u));
+    int _expr14 = atomicMin(_group_0_binding_0.extents[0], int(id.x));
+    int _expr20 = atomicMax(_group_0_binding_0.extents[1], int(id.x));
+    uint _expr23 = atomicAnd(_group_0_binding_0.total, 255u);
+    uint _expr25 = atomicOr(_group_0_binding_0.total, _expr5);
+    uint _expr28 = atomicXor(_group_0_binding_0.total, 2u);
+    uint _expr30 = workgroup_counter;
+    uint _expr31 = atomicExchange(_group_0_binding_0.total, _expr30);
+    uint _expr34_old = atomicCompSwap(_group_0_binding_0.total, _expr31, 7u);
+    uvec2 _expr34 = uvec2(_expr34_old, uint(_expr34_old == _expr31));
+    uint _expr39_compare = (_expr31 + 1u);
+    uint _expr39_old = atomicCompSwap(_group_0_binding_0.total, _expr39_compare, _expr34.x);
+    uvec2 _expr39 = uvec2(_expr39_old, uint(_expr39_old == _expr39_compare));
+    _group_0_binding_0.total = (_expr39.x + _expr34.y);
+    return;
+}
+
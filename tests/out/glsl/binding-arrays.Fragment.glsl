@@ -0,0 +1,28 @@
+#version 450 core
+#extension GL_EXT_nonuniform_qualifier : require
+struct FragmentIn {
+    uint index;
+    vec2 uv;
+};
+
+uniform highp sampler2D _group_0_binding_0[5];
+
+uniform UniformIndex_block_0 {
+    uint index;
+} _group_0_binding_2;
+
+flat in uint _vs2fs_location0;
+smooth in vec2 _vs2fs_location1;
+layout(location = 0) out vec4 _fs2p_location0;
+
+void main() {
+    FragmentIn fragment_in = FragmentIn(_vs2fs_location0, _vs2fs_location1);
+    uint uniform_index = _group_0_binding_2.index;
+    uint non_uniform_index = fragment_in.index;
+    vec4 first = texture(_group_0_binding_0[0], vec2(fragment_in.uv));
+    vec4 uniform1 = texture(_group_0_binding_0[uniform_index], vec2(fragment_in.uv));
+    vec4 non_uniform = texture(_group_0_binding_0[nonuniformEXT(non_uniform_index)], vec2(fragment_in.uv));
+    _fs2p_location0 = ((first + uniform1) + non_uniform);
+    return;
+}
+
@@ -0,0 +1,51 @@
+#version 450 core
+#extension GL_ARB_compute_shader : require
+layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+
+void main() {
+    int i = 0;
+    ivec2 i2_;
+    ivec4 i4_;
+    uint u = 0u;
+    uvec3 u3_;
+    i2_ = ivec2(0);
+    i4_ = ivec4(0);
+    u3_ = uvec3(0u);
+    int _expr13 = i;
+    i = int(min(uint(findLSB(_expr13)), uint(32u)));
+    uvec3 _expr15 = u3_;
+    u3_ = uvec3(min(uvec3(findLSB(_expr15)), uvec3(32u)));
+    ivec2 _expr17 = i2_;
+    i2_ = ivec2(ivec2(31) - findMSB(uvec2(_expr17)));
+    uint _expr19 = u;
+    u = uint(int(31) - findMSB(uint(_expr19)));
+    ivec4 _expr21 = i4_;
+    i4_ = bitCount(_expr21);
+    uint _expr23 = u;
+    u = uint(bitCount(_expr23));
+    ivec2 _expr25 = i2_;
+    i2_ = bitfieldReverse(_expr25);
+    uvec3 _expr27 = u3_;
+    u3_ = bitfieldReverse(_expr27);
+    int _expr29 = i;
+    i = bitfieldExtract(_expr29, int(5u), int(10u));
+    uvec3 _expr33 = u3_;
+    u3_ = bitfieldExtract(_expr33, int(5u), int(10u));
+    ivec4 _expr37 = i4_;
+    ivec4 _expr38 = i4_;
+    i4_ = bitfieldInsert(_expr37, _expr38, int(5u), int(10u));
+    uint _expr42 = u;
+    uint _expr43 = u;
+    u = bitfieldInsert(_expr42, _expr43, int(5u), int(10u));
+    ivec2 _expr47 = i2_;
+    i2_ = findLSB(_expr47);
+    uint _expr49 = u;
+    u = uint(findLSB(_expr49));
+    int _expr51 = i;
+    i = findMSB(_expr51);
+    uvec3 _expr53 = u3_;
+    u3_ = uvec3(findMSB(_expr53));
+    return;
+}
+
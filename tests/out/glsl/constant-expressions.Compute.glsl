@@ -0,0 +1,14 @@
+#version 310 es
+
+precision highp float;
+
+layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+float data[33];
+
+
+void main() {
+    data[16] = ((vec4(1.5, 3.0, 4.5, 6.0).x + 5.0) + vec2(6.0, 4.5).y);
+    return;
+}
+
@@ -0,0 +1,23 @@
+#version 420 core
+#extension GL_ARB_compute_shader : require
+#extension GL_ARB_shader_storage_buffer_object : require
+layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+buffer Packing_block_0 {
+    uint packed;
+    vec2 pair;
+    vec4 quad;
+} _group_0_binding_0;
+
+
+void main() {
+    vec2 pair = _group_0_binding_0.pair;
+    vec4 quad = _group_0_binding_0.quad;
+    uint packed = ((((packSnorm4x8(quad) + packUnorm4x8(quad)) + packSnorm2x16(pair)) + packUnorm2x16(pair)) + packHalf2x16(pair));
+    _group_0_binding_0.packed = packed;
+    uint value = _group_0_binding_0.packed;
+    _group_0_binding_0.quad = (unpackSnorm4x8(value) + unpackUnorm4x8(value));
+    _group_0_binding_0.pair = ((unpackSnorm2x16(value) + unpackUnorm2x16(value)) + unpackHalf2x16(value));
+    return;
+}
+
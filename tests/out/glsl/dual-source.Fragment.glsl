@@ -0,0 +1,19 @@
+#version 450 core
+#extension GL_EXT_multiview : require
+struct FragmentOutput {
+    vec4 color;
+    vec4 mask;
+};
+
+layout(location = 0) out vec4 _fs2p_location0;
+layout(location = 0, index = 1) out vec4 _fs2p_location0_index1;
+
+void main() {
+    uint primitive_index = uint(gl_PrimitiveID);
+    uint view_index = uint(gl_ViewIndex);
+    FragmentOutput _tmp_return = FragmentOutput(vec4(0.4, 0.3, 0.2, 0.1), vec4(0.9, 0.8, 0.7, 0.6));
+    _fs2p_location0 = _tmp_return.color;
+    _fs2p_location0_index1 = _tmp_return.mask;
+    return;
+}
+
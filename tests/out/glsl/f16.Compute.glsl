@@ -0,0 +1,26 @@
+#version 450 core
+#extension GL_ARB_compute_shader : require
+#extension GL_ARB_shader_storage_buffer_object : require
+#extension GL_EXT_shader_16bit_storage : require
+#extension GL_EXT_shader_explicit_arithmetic_types_float16 : require
+layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+buffer Halves_block_0 {
+    float16_t value;
+    f16vec2 pair;
+    f16vec4 quad;
+    float scale;
+} _group_0_binding_0;
+
+
+void main() {
+    f16vec2 _expr2 = _group_0_binding_0.pair;
+    f16vec2 _expr4 = _group_0_binding_0.pair;
+    f16vec2 squared = (_expr2 * _expr4);
+    float16_t _expr8 = _group_0_binding_0.value;
+    _group_0_binding_0.value = (_expr8 + squared.x);
+    float16_t _expr13 = _group_0_binding_0.value;
+    _group_0_binding_0.quad = f16vec4(squared, _expr13, squared.y);
+    return;
+}
+
@@ -0,0 +1,25 @@
+#version 450 core
+uniform highp sampler2D _group_0_binding_0;
+
+uniform highp usampler2D _group_0_binding_1;
+
+uniform highp samplerCubeArray _group_0_binding_2;
+
+uniform highp sampler2DShadow _group_0_binding_3;
+
+uniform highp sampler2DArrayShadow _group_0_binding_4;
+
+layout(location = 0) out vec4 _fs2p_location0;
+
+void main() {
+    vec2 tc = vec2(0.5);
+    vec4 s2d = textureGather(_group_0_binding_0, tc, 1);
+    vec4 s2d_offset = textureGatherOffset(_group_0_binding_0, tc, ivec2(3, 1), 3);
+    vec4 s_cube = textureGather(_group_0_binding_2, vec4(vec3(0.5), 1), 2);
+    vec4 s_depth = textureGather(_group_0_binding_3, tc);
+    vec4 s_depth_cmp = textureGatherOffset(_group_0_binding_4, vec3(tc, 0), 0.5, ivec2(3, 1));
+    uvec4 u = textureGather(_group_0_binding_1, tc);
+    _fs2p_location0 = (((((s2d + s2d_offset) + s_cube) + s_depth) + s_depth_cmp) + vec4(u));
+    return;
+}
+
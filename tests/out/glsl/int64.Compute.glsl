@@ -0,0 +1,24 @@
+#version 450 core
+#extension GL_ARB_compute_shader : require
+#extension GL_ARB_shader_storage_buffer_object : require
+#extension GL_ARB_gpu_shader_int64 : require
+layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+buffer Culling_block_0 {
+    uint64_t key;
+    int64_t offset;
+    u64vec2 counts;
+} _group_0_binding_0;
+
+
+void main() {
+    uint64_t _expr3 = _group_0_binding_0.key;
+    _group_0_binding_0.key = ((_expr3 * 4294967296ul) + 1ul);
+    int64_t _expr10 = _group_0_binding_0.offset;
+    _group_0_binding_0.offset = ((- _expr10) - 1l);
+    u64vec2 _expr16 = _group_0_binding_0.counts;
+    uint64_t _expr18 = _group_0_binding_0.key;
+    _group_0_binding_0.counts = (_expr16 + u64vec2(_expr18, 1ul));
+    return;
+}
+
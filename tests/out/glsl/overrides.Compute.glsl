@@ -0,0 +1,43 @@
+#version 310 es
+
+precision highp float;
+
+layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+#ifndef SPIRV_CROSS_CONSTANT_ID_0
+#define SPIRV_CROSS_CONSTANT_ID_0 true
+#endif
+const bool has_point_light = SPIRV_CROSS_CONSTANT_ID_0;
+#ifndef SPIRV_CROSS_CONSTANT_ID_1200
+#define SPIRV_CROSS_CONSTANT_ID_1200 2.3
+#endif
+const float specular_param = SPIRV_CROSS_CONSTANT_ID_1200;
+#ifndef SPIRV_CROSS_CONSTANT_ID_1300
+#define SPIRV_CROSS_CONSTANT_ID_1300 1.1
+#endif
+const float gain = SPIRV_CROSS_CONSTANT_ID_1300;
+#ifndef SPIRV_CROSS_CONSTANT_ID_1
+#define SPIRV_CROSS_CONSTANT_ID_1 0.0
+#endif
+const float width = SPIRV_CROSS_CONSTANT_ID_1;
+#ifndef SPIRV_CROSS_CONSTANT_ID_2
+#define SPIRV_CROSS_CONSTANT_ID_2 0.5
+#endif
+const float depth = SPIRV_CROSS_CONSTANT_ID_2;
+#ifndef SPIRV_CROSS_CONSTANT_ID_3
+#define SPIRV_CROSS_CONSTANT_ID_3 2.0
+#endif
+const float height = SPIRV_CROSS_CONSTANT_ID_3;
+
+
+void main() {
+    float t;
+    bool x;
+    t = (height * 5.0);
+    bool a = (! has_point_light);
+    x = a;
+    float _expr11 = t;
+    t = ((_expr11 + (gain * width)) - (depth * specular_param));
+    return;
+}
+
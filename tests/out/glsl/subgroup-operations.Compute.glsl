@@ -0,0 +1,41 @@
+#version 310 es
+#extension GL_KHR_shader_subgroup_basic : require
+#extension GL_KHR_shader_subgroup_vote : require
+#extension GL_KHR_shader_subgroup_ballot : require
+#extension GL_KHR_shader_subgroup_arithmetic : require
+#extension GL_KHR_shader_subgroup_shuffle : require
+#extension GL_KHR_shader_subgroup_shuffle_relative : require
+
+precision highp float;
+
+layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+
+void main() {
+    uint size = gl_SubgroupSize;
+    uint invocation_id = gl_SubgroupInvocationID;
+    bool even = ((invocation_id & 1u) == 0u);
+    uvec4 _expr6 = subgroupBallot(even);
+    uvec4 _expr7 = subgroupBallot(true);
+    bool _expr8 = subgroupAll(even);
+    bool _expr9 = subgroupAny(even);
+    uint _expr10 = subgroupAdd(invocation_id);
+    uint _expr11 = subgroupMul(invocation_id);
+    uint _expr12 = subgroupMin(invocation_id);
+    uvec2 _expr14 = subgroupMax(uvec2(invocation_id, size));
+    uint _expr15 = subgroupAnd(invocation_id);
+    uint _expr16 = subgroupOr(invocation_id);
+    uint _expr17 = subgroupXor(invocation_id);
+    uint _expr18 = subgroupExclusiveAdd(invocation_id);
+    uint _expr19 = subgroupExclusiveMul(invocation_id);
+    uint _expr20 = subgroupInclusiveAdd(invocation_id);
+    uint _expr21 = subgroupInclusiveMul(invocation_id);
+    uint _expr22 = subgroupBroadcastFirst(invocation_id);
+    uint _expr24 = subgroupBroadcast(invocation_id, 4u);
+    uint _expr28 = subgroupShuffle(invocation_id, ((size - 1u) - invocation_id));
+    uint _expr30 = subgroupShuffleDown(invocation_id, 1u);
+    uint _expr32 = subgroupShuffleUp(invocation_id, 1u);
+    uint _expr35 = subgroupShuffleXor(invocation_id, (size - 1u));
+    return;
+}
+
@@ -0,0 +1,44 @@
+struct Counters {
+    uint total;
+    int extents[2];
+};
+
+RWStructuredBuffer<Counters> counters : register(u0);
+groupshared uint workgroup_counter;
+
+struct ComputeInput {
+    uint3 id1 : SV_GroupThreadID;
+};
+
+[numthreads(2, 1, 1)]
+void comp_main(ComputeInput computeinput)
+{
+    workgroup_counter = 0u;
+    GroupMemoryBarrierWithGroupSync();
+    uint _e5;
+    InterlockedAdd(workgroup_counter, 1u, _e5);
+    uint _e8;
+    InterlockedAdd(counters[0].total, -(1u), _e8);
+    int _e14;
+    InterlockedMin(counters[0].extents[0], int(computeinput.id1.x), _e14);
+    int _e20;
+    InterlockedMax(counters[0].extents[1], int(computeinput.id1.x), _e20);
+    uint _e23;
+    InterlockedAnd(counters[0].total, 255u, _e23);
+    uint _e25;
+    InterlockedOr(counters[0].total, _e5, _e25);
+    uint _e28;
+    InterlockedXor(counters[0].total, 2u, _e28);
+    uint _expr30 = workgroup_counter;
+    uint _e31;
+    InterlockedExchange(counters[0].total, _expr30, _e31);
+    uint _e34_old;
+    InterlockedCompareExchange(counters[0].total, _e31, 7u, _e34_old);
+    uint2 _e34 = uint2(_e34_old, uint(_e34_old == _e31));
+    uint _e39_compare = (_e31 + 1u);
+    uint _e39_old;
+    InterlockedCompareExchange(counters[0].total, _e39_compare, _e34.x, _e39_old);
+    uint2 _e39 = uint2(_e39_old, uint(_e39_old == _e39_compare));
+    counters[0].total = (_e39.x + _e34.y);
+    return;
+}
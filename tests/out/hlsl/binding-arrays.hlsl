@@ -0,0 +1,26 @@
+struct UniformIndex {
+    uint index;
+};
+
+struct FragmentIn {
+    uint index : LOC0;
+    float2 uv : LOC1;
+};
+
+Texture2D<float4> textures[5] : register(t0);
+SamplerState samplers[5] : register(s1);
+static UniformIndex uni : register(2);
+
+struct FragmentInput {
+    FragmentIn fragment_in1;
+};
+
+float4 frag_main(FragmentInput fragmentinput) : SV_Target0
+{
+    uint uniform_index = uni.index;
+    uint non_uniform_index = fragmentinput.fragment_in1.index;
+    float4 first = textures[0].Sample(samplers[0], fragmentinput.fragment_in1.uv);
+    float4 uniform1 = textures[uniform_index].Sample(samplers[uniform_index], fragmentinput.fragment_in1.uv);
+    float4 non_uniform = textures[NonUniformResourceIndex(non_uniform_index)].Sample(samplers[NonUniformResourceIndex(non_uniform_index)], fragmentinput.fragment_in1.uv);
+    return ((first + uniform1) + non_uniform);
+}
@@ -0,0 +1,48 @@
+[numthreads(1, 1, 1)]
+void comp_main()
+{
+    int i = 0;
+    int2 i2_;
+    int4 i4_;
+    uint u = 0u;
+    uint3 u3_;
+
+    i2_ = ((int2)0);
+    i4_ = ((int4)0);
+    u3_ = ((uint3)0u);
+    int _expr13 = i;
+    i = asint(min(32u, firstbitlow(_expr13)));
+    uint3 _expr15 = u3_;
+    u3_ = (min(32u, firstbitlow(_expr15)));
+    int2 _expr17 = i2_;
+    i2_ = asint(31u - firstbithigh(asuint(_expr17)));
+    uint _expr19 = u;
+    u = (31u - firstbithigh((_expr19)));
+    int4 _expr21 = i4_;
+    i4_ = asint(countbits(asuint(_expr21)));
+    uint _expr23 = u;
+    u = (countbits((_expr23)));
+    int2 _expr25 = i2_;
+    i2_ = asint(reversebits(asuint(_expr25)));
+    uint3 _expr27 = u3_;
+    u3_ = (reversebits((_expr27)));
+    int _expr29 = i;
+    i = (10u == 0u ? (int)0 : (_expr29 << (32u - 10u - 5u)) >> (32u - 10u));
+    uint3 _expr33 = u3_;
+    u3_ = (10u == 0u ? (uint3)0 : (_expr33 << (32u - 10u - 5u)) >> (32u - 10u));
+    int4 _expr37 = i4_;
+    int4 _expr38 = i4_;
+    i4_ = asint((asuint(_expr37) & ~(10u == 0u ? 0u : (0xFFFFFFFFu >> (32u - 10u)) << 5u)) | ((asuint(_expr38) << 5u) & (10u == 0u ? 0u : (0xFFFFFFFFu >> (32u - 10u)) << 5u)));
+    uint _expr42 = u;
+    uint _expr43 = u;
+    u = (((_expr42) & ~(10u == 0u ? 0u : (0xFFFFFFFFu >> (32u - 10u)) << 5u)) | (((_expr43) << 5u) & (10u == 0u ? 0u : (0xFFFFFFFFu >> (32u - 10u)) << 5u)));
+    int2 _expr47 = i2_;
+    i2_ = asint(firstbitlow(_expr47));
+    uint _expr49 = u;
+    u = (firstbitlow(_expr49));
+    int _expr51 = i;
+    i = asint(firstbithigh(_expr51));
+    uint3 _expr53 = u3_;
+    u3_ = (firstbithigh(_expr53));
+    return;
+}
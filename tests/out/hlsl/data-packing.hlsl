@@ -0,0 +1,20 @@
+struct Packing {
+    uint packed;
+    float2 pair;
+    float4 quad;
+};
+
+RWStructuredBuffer<Packing> data : register(u0);
+
+[numthreads(1, 1, 1)]
+void comp_main()
+{
+    float2 pair = data[0].pair;
+    float4 quad = data[0].quad;
+    uint packed = ((((((uint(int(round(clamp(quad.x, -1.0, 1.0) * 127.0)) & 0xFF)) | (uint(int(round(clamp(quad.y, -1.0, 1.0) * 127.0)) & 0xFF) << 8) | (uint(int(round(clamp(quad.z, -1.0, 1.0) * 127.0)) & 0xFF) << 16) | (uint(int(round(clamp(quad.w, -1.0, 1.0) * 127.0)) & 0xFF) << 24)) + ((uint(round(saturate(quad.x) * 255.0))) | (uint(round(saturate(quad.y) * 255.0)) << 8) | (uint(round(saturate(quad.z) * 255.0)) << 16) | (uint(round(saturate(quad.w) * 255.0)) << 24))) + ((uint(int(round(clamp(pair.x, -1.0, 1.0) * 32767.0)) & 0xFFFF)) | (uint(int(round(clamp(pair.y, -1.0, 1.0) * 32767.0)) & 0xFFFF) << 16))) + ((uint(round(saturate(pair.x) * 65535.0))) | (uint(round(saturate(pair.y) * 65535.0)) << 16))) + (f32tof16(pair.x) | f32tof16(pair.y) << 16));
+    data[0].packed = packed;
+    uint value = data[0].packed;
+    data[0].quad = (max(float4(int4(value << 24, value << 16, value << 8, value) >> 24) / 127.0, -1.0) + (float4(value & 0xFF, value >> 8 & 0xFF, value >> 16 & 0xFF, value >> 24) / 255.0));
+    data[0].pair = ((max(float2(int2(value << 16, value) >> 16) / 32767.0, -1.0) + (float2(value & 0xFFFF, value >> 16) / 65535.0)) + float2(f16tof32(value), f16tof32(value >> 16)));
+    return;
+}
@@ -0,0 +1,15 @@
+struct FragmentOutput {
+    float4 color : SV_Target0;
+    float4 mask : SV_Target1;
+};
+
+struct FragmentInput {
+    uint primitive_index1 : SV_PrimitiveID;
+    uint view_index1 : SV_ViewID;
+};
+
+FragmentOutput frag_main(FragmentInput fragmentinput)
+{
+    const FragmentOutput fragmentoutput1 = { float4(0.4, 0.3, 0.2, 0.1), float4(0.9, 0.8, 0.7, 0.6) };
+    return fragmentoutput1;
+}
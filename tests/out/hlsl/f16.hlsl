@@ -0,0 +1,21 @@
+struct Halves {
+    float16_t value;
+    float16_t2 pair;
+    float16_t4 quad;
+    float scale;
+};
+
+RWStructuredBuffer<Halves> halves : register(u0);
+
+[numthreads(1, 1, 1)]
+void comp_main()
+{
+    float16_t2 _expr2 = halves[0].pair;
+    float16_t2 _expr4 = halves[0].pair;
+    float16_t2 squared = (_expr2 * _expr4);
+    float16_t _expr8 = halves[0].value;
+    halves[0].value = (_expr8 + squared.x);
+    float16_t _expr13 = halves[0].value;
+    halves[0].quad = float16_t4(squared, _expr13, squared.y);
+    return;
+}
@@ -0,0 +1,19 @@
+Texture2D<float4> image_2d : register(t0);
+Texture2D<uint4> image_2d_u32_ : register(t1);
+TextureCubeArray<float4> image_cube_array : register(t2);
+Texture2D<float> image_2d_depth : register(t3);
+Texture2DArray<float> image_2d_shadow : register(t4);
+SamplerState sampler_reg : register(s0);
+SamplerComparisonState sampler_cmp : register(s1);
+
+float4 frag_main() : SV_Target0
+{
+    float2 tc = ((float2)0.5);
+    float4 s2d = image_2d.GatherGreen(sampler_reg, tc);
+    float4 s2d_offset = image_2d.GatherAlpha(sampler_reg, tc, int2(3, 1));
+    float4 s_cube = image_cube_array.GatherBlue(sampler_reg, float4(((float3)0.5), 1));
+    float4 s_depth = image_2d_depth.GatherRed(sampler_reg, tc);
+    float4 s_depth_cmp = image_2d_shadow.GatherCmp(sampler_cmp, float3(tc, 0), 0.5, int2(3, 1));
+    uint4 u = image_2d_u32_.GatherRed(sampler_reg, tc);
+    return (((((s2d + s2d_offset) + s_cube) + s_depth) + s_depth_cmp) + float4(u));
+}
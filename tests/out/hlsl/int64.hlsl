@@ -0,0 +1,20 @@
+struct Culling {
+    uint64_t key;
+    int64_t offset;
+    uint64_t2 counts;
+};
+
+RWStructuredBuffer<Culling> culling : register(u0);
+
+[numthreads(1, 1, 1)]
+void comp_main()
+{
+    uint64_t _expr3 = culling[0].key;
+    culling[0].key = ((_expr3 * 4294967296UL) + 1UL);
+    int64_t _expr10 = culling[0].offset;
+    culling[0].offset = ((- _expr10) - 1L);
+    uint64_t2 _expr16 = culling[0].counts;
+    uint64_t _expr18 = culling[0].key;
+    culling[0].counts = (_expr16 + uint64_t2(_expr18, 1UL));
+    return;
+}
@@ -0,0 +1,52 @@
+struct TaskPayload {
+    float4 colorMask;
+    bool visible;
+};
+
+struct VertexOutput {
+    float4 position : SV_Position;
+    float4 color : LOC0;
+};
+
+struct PrimitiveOutput {
+    float4 colorMask : LOC1;
+    bool cull : SV_CullPrimitive;
+};
+
+groupshared TaskPayload taskPayload;
+
+struct MeshInput {
+    uint index1 : SV_GroupIndex;
+    uint3 id1 : SV_DispatchThreadID;
+};
+
+[numthreads(1, 1, 1)]
+void task_main()
+{
+    taskPayload.colorMask = float4(1.0, 1.0, 0.0, 1.0);
+    taskPayload.visible = true;
+    const uint3 mesh_task_size = uint3(3u, 1u, 1u);
+    DispatchMesh(mesh_task_size.x, mesh_task_size.y, mesh_task_size.z, taskPayload);
+    return;
+}
+
+[outputtopology("triangle")]
+[numthreads(1, 1, 1)]
+void mesh_main(MeshInput meshinput, in payload TaskPayload taskPayload, out vertices VertexOutput vertices[3], out indices uint3 indices[1], out primitives PrimitiveOutput primitives[1])
+{
+    SetMeshOutputCounts(3u, 1u);
+    float4 _expr17 = taskPayload.colorMask;
+    const VertexOutput vertexoutput1 = { float4(0.0, 1.0, 0.0, 1.0), (float4(0.0, 1.0, 0.0, 1.0) * _expr17) };
+    vertices[0u] = vertexoutput1;
+    float4 _expr32 = taskPayload.colorMask;
+    const VertexOutput vertexoutput2 = { float4(-1.0, -1.0, 0.0, 1.0), (float4(0.0, 0.0, 1.0, 1.0) * _expr32) };
+    vertices[1u] = vertexoutput2;
+    float4 _expr47 = taskPayload.colorMask;
+    const VertexOutput vertexoutput3 = { float4(1.0, -1.0, 0.0, 1.0), (float4(1.0, 0.0, 0.0, 1.0) * _expr47) };
+    vertices[2u] = vertexoutput3;
+    indices[0u] = uint3(0u, 1u, 2u);
+    bool _expr62 = taskPayload.visible;
+    const PrimitiveOutput primitiveoutput1 = { float4(1.0, 0.0, 1.0, 1.0), (! _expr62) };
+    primitives[0u] = primitiveoutput1;
+    return;
+}
@@ -0,0 +1,58 @@
+struct RayDesc1 {
+    uint flags;
+    uint cull_mask;
+    float t_min;
+    float t_max;
+    float3 origin;
+    float3 dir;
+};
+
+struct RayIntersection {
+    uint kind;
+    float t;
+    uint instance_custom_index;
+    uint instance_id;
+    uint sbt_record_offset;
+    uint geometry_index;
+    uint primitive_index;
+    float2 barycentrics;
+    bool front_face;
+    float4x3 object_to_world;
+    float4x3 world_to_object;
+};
+
+RaytracingAccelerationStructure acc_struct : register(t0);
+
+struct FragmentInput {
+    float4 position1 : SV_Position;
+};
+
+float4 frag_main(FragmentInput fragmentinput) : SV_Target0
+{
+    RayQuery<RAY_FLAG_NONE> rq;
+
+    float3 dir = float3(0.0, 0.0, 1.0);
+    float3 origin = float3(fragmentinput.position1.x, fragmentinput.position1.y, 0.0);
+    RayDesc1 desc = { 4u, 255u, 0.1, 100.0, origin, dir };
+    {
+        const RayDesc1 _desc = desc;
+        RayDesc _ray;
+        _ray.Origin = _desc.origin;
+        _ray.TMin = _desc.t_min;
+        _ray.Direction = _desc.dir;
+        _ray.TMax = _desc.t_max;
+        rq.TraceRayInline(acc_struct, _desc.flags, _desc.cull_mask, _ray);
+    }
+    while(true) {
+        bool _e16 = rq.Proceed();
+        if ((! _e16)) {
+            break;
+        }
+    }
+    RayIntersection intersection = { rq.CommittedStatus(), rq.CommittedRayT(), rq.CommittedInstanceID(), rq.CommittedInstanceIndex(), rq.CommittedInstanceContributionToHitGroupIndex(), rq.CommittedGeometryIndex(), rq.CommittedPrimitiveIndex(), rq.CommittedTriangleBarycentrics(), rq.CommittedTriangleFrontFace(), rq.CommittedObjectToWorld4x3(), rq.CommittedWorldToObject4x3() };
+    if ((intersection.kind == 0u)) {
+        rq.Abort();
+        return float4(0.0, 0.0, 0.0, 1.0);
+    }
+    return float4(intersection.barycentrics, intersection.t, 1.0);
+}
@@ -0,0 +1,30 @@
+struct ComputeInput {
+};
+
+[numthreads(64, 1, 1)]
+void comp_main(ComputeInput computeinput)
+{
+    bool even = ((WaveGetLaneIndex() & 1u) == 0u);
+    uint4 _e6 = WaveActiveBallot(even);
+    uint4 _e7 = WaveActiveBallot(true);
+    bool _e8 = WaveActiveAllTrue(even);
+    bool _e9 = WaveActiveAnyTrue(even);
+    uint _e10 = WaveActiveSum(WaveGetLaneIndex());
+    uint _e11 = WaveActiveProduct(WaveGetLaneIndex());
+    uint _e12 = WaveActiveMin(WaveGetLaneIndex());
+    uint2 _e14 = WaveActiveMax(uint2(WaveGetLaneIndex(), WaveGetLaneCount()));
+    uint _e15 = WaveActiveBitAnd(WaveGetLaneIndex());
+    uint _e16 = WaveActiveBitOr(WaveGetLaneIndex());
+    uint _e17 = WaveActiveBitXor(WaveGetLaneIndex());
+    uint _e18 = WavePrefixSum(WaveGetLaneIndex());
+    uint _e19 = WavePrefixProduct(WaveGetLaneIndex());
+    uint _e20 = WavePrefixSum(WaveGetLaneIndex()) + WaveGetLaneIndex();
+    uint _e21 = WavePrefixProduct(WaveGetLaneIndex()) * WaveGetLaneIndex();
+    uint _e22 = WaveReadLaneFirst(WaveGetLaneIndex());
+    uint _e24 = WaveReadLaneAt(WaveGetLaneIndex(), 4u);
+    uint _e28 = WaveReadLaneAt(WaveGetLaneIndex(), ((WaveGetLaneCount() - 1u) - WaveGetLaneIndex()));
+    uint _e30 = WaveReadLaneAt(WaveGetLaneIndex(), WaveGetLaneIndex() + 1u);
+    uint _e32 = WaveReadLaneAt(WaveGetLaneIndex(), WaveGetLaneIndex() - 1u);
+    uint _e35 = WaveReadLaneAt(WaveGetLaneIndex(), WaveGetLaneIndex() ^ (WaveGetLaneCount() - 1u));
+    return;
+}
@@ -9,11 +9,10 @@ var<private> gl_GlobalInvocationID: vec3<u32>;
 
 fn collatz_iterations(n: u32) -> u32 {
     var n1: u32;
-    var i: u32;
+    var i: u32 = 0u;
     var local: u32;
 
     n1 = n;
-    i = u32(0);
     loop {
         let _e7: u32 = n1;
         if (!((_e7 != u32(1)))) {
@@ -1,9 +1,7 @@
 fn main1() {
-    var a: mat4x4<f32>;
+    var a: mat4x4<f32> = mat4x4<f32>(vec4<f32>(1.0, 1.0, 1.0, 1.0), vec4<f32>(1.0, 1.0, 1.0, 1.0), vec4<f32>(1.0, 1.0, 1.0, 1.0), vec4<f32>(1.0, 1.0, 1.0, 1.0));
 
     let _e2: vec4<f32> = vec4<f32>(f32(1));
-    a = mat4x4<f32>(_e2, _e2, _e2, _e2);
-    return;
 }
 
 [[stage(vertex)]]
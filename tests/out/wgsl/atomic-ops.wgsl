@@ -0,0 +1,46 @@
+[[block]]
+struct Counters {
+    total: u32;
+    extents: [[stride(4)]] array<i32,2u>;
+};
+
+[[block]]
+struct Counters1 {
+    total: atomic<u32>;
+    extents: [[stride(4)]] array<i32,2u>;
+};
+
+[[block]]
+struct Counters2 {
+    total: atomic<u32>;
+    extents: [[stride(4)]] array<atomic<i32>,2u>;
+};
+
+[[group(0), binding(0)]]
+var<storage> counters: [[access(read_write)]] Counters2;
+var<workgroup> workgroup_counter: atomic<u32>;
+var<private> id1: vec3<u32>;
+
+fn cs_main1() {
+    let _e17: vec3<u32> = id1;
+    atomicStore(&workgroup_counter, 0u);
+    workgroupBarrier();
+    let _e18: u32 = atomicAdd(&workgroup_counter, 1u);
+    let _e20: u32 = atomicSub(&counters.total, 1u);
+    let _e25: i32 = atomicMin(&counters.extents[0u], i32(_e17.x));
+    let _e30: i32 = atomicMax(&counters.extents[1u], i32(_e17.x));
+    let _e32: u32 = atomicAnd(&counters.total, 255u);
+    let _e34: u32 = atomicOr(&counters.total, _e18);
+    let _e36: u32 = atomicXor(&counters.total, 2u);
+    let _e37: u32 = atomicLoad(&workgroup_counter);
+    let _e39: u32 = atomicExchange(&counters.total, _e37);
+    let _e41: vec2<u32> = atomicCompareExchangeWeak(&counters.total, _e39, 7u);
+    atomicStore(&counters.total, _e41.x);
+    return;
+}
+
+[[stage(compute), workgroup_size(2, 1, 1)]]
+fn cs_main([[builtin(local_invocation_id)]] id: vec3<u32>) {
+    id1 = id;
+    cs_main1();
+}
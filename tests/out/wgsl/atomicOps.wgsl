@@ -0,0 +1,28 @@
+[[block]]
+struct Counters {
+    total: atomic<u32>;
+    extents: [[stride(4)]] array<atomic<i32>,2u>;
+};
+
+[[group(0), binding(0)]]
+var<storage> counters: [[access(read_write)]] Counters;
+var<workgroup> workgroup_counter: atomic<u32>;
+
+[[stage(compute), workgroup_size(2, 1, 1)]]
+fn cs_main([[builtin(local_invocation_id)]] id: vec3<u32>) {
+    atomicStore(&workgroup_counter, 0u);
+    workgroupBarrier();
+    let _e5: u32 = atomicAdd(&workgroup_counter, 1u);
+    let _e8: u32 = atomicSub(&counters.total, 1u);
+    let _e14: i32 = atomicMin(&counters.extents[0], i32(id.x));
+    let _e20: i32 = atomicMax(&counters.extents[1], i32(id.x));
+    let _e23: u32 = atomicAnd(&counters.total, 255u);
+    let _e25: u32 = atomicOr(&counters.total, _e5);
+    let _e28: u32 = atomicXor(&counters.total, 2u);
+    let _e30: u32 = atomicLoad(&workgroup_counter);
+    let _e31: u32 = atomicExchange(&counters.total, _e30);
+    let _e34: vec2<u32> = atomicCompareExchangeWeak(&counters.total, _e31, 7u);
+    let _e39: vec2<u32> = atomicCompareExchangeWeak(&counters.total, (_e31 + 1u), _e34.x);
+    atomicStore(&counters.total, (_e39.x + _e34.y));
+    return;
+}
@@ -0,0 +1,26 @@
+[[block]]
+struct UniformIndex {
+    index: u32;
+};
+
+struct FragmentIn {
+    [[location(0), interpolate(flat)]] index: u32;
+    [[location(1), interpolate(perspective)]] uv: vec2<f32>;
+};
+
+[[group(0), binding(0)]]
+var textures: binding_array<texture_2d<f32>,5u>;
+[[group(0), binding(1)]]
+var samplers: binding_array<sampler,5u>;
+[[group(0), binding(2)]]
+var<uniform> uni: UniformIndex;
+
+[[stage(fragment)]]
+fn main(fragment_in: FragmentIn) -> [[location(0)]] vec4<f32> {
+    let uniform_index: u32 = uni.index;
+    let non_uniform_index: u32 = fragment_in.index;
+    let first: vec4<f32> = textureSample(textures[0], samplers[0], fragment_in.uv);
+    let uniform: vec4<f32> = textureSample(textures[uniform_index], samplers[uniform_index], fragment_in.uv);
+    let non_uniform: vec4<f32> = textureSample(textures[non_uniform_index], samplers[non_uniform_index], fragment_in.uv);
+    return ((first + uniform) + non_uniform);
+}
@@ -0,0 +1,47 @@
+[[stage(compute), workgroup_size(1, 1, 1)]]
+fn main() {
+    var i: i32 = 0;
+    var i2_: vec2<i32>;
+    var i4_: vec4<i32>;
+    var u: u32 = 0u;
+    var u3_: vec3<u32>;
+
+    i2_ = vec2<i32>(0);
+    i4_ = vec4<i32>(0);
+    u3_ = vec3<u32>(0u);
+    let _e13: i32 = i;
+    i = countTrailingZeros(_e13);
+    let _e15: vec3<u32> = u3_;
+    u3_ = countTrailingZeros(_e15);
+    let _e17: vec2<i32> = i2_;
+    i2_ = countLeadingZeros(_e17);
+    let _e19: u32 = u;
+    u = countLeadingZeros(_e19);
+    let _e21: vec4<i32> = i4_;
+    i4_ = countOneBits(_e21);
+    let _e23: u32 = u;
+    u = countOneBits(_e23);
+    let _e25: vec2<i32> = i2_;
+    i2_ = reverseBits(_e25);
+    let _e27: vec3<u32> = u3_;
+    u3_ = reverseBits(_e27);
+    let _e29: i32 = i;
+    i = extractBits(_e29, 5u, 10u);
+    let _e33: vec3<u32> = u3_;
+    u3_ = extractBits(_e33, 5u, 10u);
+    let _e37: vec4<i32> = i4_;
+    let _e38: vec4<i32> = i4_;
+    i4_ = insertBits(_e37, _e38, 5u, 10u);
+    let _e42: u32 = u;
+    let _e43: u32 = u;
+    u = insertBits(_e42, _e43, 5u, 10u);
+    let _e47: vec2<i32> = i2_;
+    i2_ = firstTrailingBit(_e47);
+    let _e49: u32 = u;
+    u = firstTrailingBit(_e49);
+    let _e51: i32 = i;
+    i = firstLeadingBit(_e51);
+    let _e53: vec3<u32> = u3_;
+    u3_ = firstLeadingBit(_e53);
+    return;
+}
@@ -7,11 +7,10 @@ struct Data {
 var<uniform> global: Data;
 
 fn function() -> vec4<f32> {
-    var sum: vec4<f32>;
+    var sum: vec4<f32> = vec4<f32>(0.0, 0.0, 0.0, 0.0);
     var i: i32 = 0;
     var local: i32;
 
-    sum = vec4<f32>(f32(0));
     loop {
         let _e9: i32 = i;
         if (!((_e9 < 42))) {
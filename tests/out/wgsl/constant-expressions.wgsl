@@ -0,0 +1,19 @@
+let WIDTH: u32 = 4u;
+
+let HEIGHT: u32 = 8u;
+
+let SCALE: f32 = 1.5;
+
+let MASK: vec4<f32> = vec4<f32>(1.5, 3.0, 4.5, 6.0);
+let LENGTH: f32 = 5.0;
+
+let BITS: u32 = 16u;
+
+let SWIZZLED: vec2<f32> = vec2<f32>(6.0, 4.5);
+var<private> data: array<f32,33u>;
+
+[[stage(compute), workgroup_size(1, 1, 1)]]
+fn main() {
+    data[16] = ((vec4<f32>(1.5, 3.0, 4.5, 6.0).x + LENGTH) + vec2<f32>(6.0, 4.5).y);
+    return;
+}
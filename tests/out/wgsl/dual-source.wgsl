@@ -0,0 +1,9 @@
+struct FragmentOutput {
+    [[location(0)]] color: vec4<f32>;
+    [[location(0), index(1)]] mask: vec4<f32>;
+};
+
+[[stage(fragment)]]
+fn main([[builtin(primitive_index)]] primitive_index: u32, [[builtin(view_index)]] view_index: u32) -> FragmentOutput {
+    return FragmentOutput(vec4<f32>(0.4, 0.3, 0.2, 0.1), vec4<f32>(0.9, 0.8, 0.7, 0.6));
+}
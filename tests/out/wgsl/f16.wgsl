@@ -0,0 +1,24 @@
+enable f16;
+
+[[block]]
+struct Halves {
+    value: f16;
+    pair: vec2<f16>;
+    quad: vec4<f16>;
+    scale: f32;
+};
+
+[[group(0), binding(0)]]
+var<storage> halves: [[access(read_write)]] Halves;
+
+[[stage(compute), workgroup_size(1, 1, 1)]]
+fn main() {
+    let _e2: vec2<f16> = halves.pair;
+    let _e4: vec2<f16> = halves.pair;
+    let squared: vec2<f16> = (_e2 * _e4);
+    let _e8: f16 = halves.value;
+    halves.value = (_e8 + squared.x);
+    let _e13: f16 = halves.value;
+    halves.quad = vec4<f16>(squared, _e13, squared.y);
+    return;
+}
@@ -0,0 +1,21 @@
+[[block]]
+struct Culling {
+    key: u64;
+    offset: i64;
+    counts: vec2<u64>;
+};
+
+[[group(0), binding(0)]]
+var<storage> culling: [[access(read_write)]] Culling;
+
+[[stage(compute), workgroup_size(1, 1, 1)]]
+fn main() {
+    let _e3: u64 = culling.key;
+    culling.key = ((_e3 * 4294967296u64) + 1u64);
+    let _e10: i64 = culling.offset;
+    culling.offset = (-(_e10) - 1i64);
+    let _e16: vec2<u64> = culling.counts;
+    let _e18: u64 = culling.key;
+    culling.counts = (_e16 + vec2<u64>(_e18, 1u64));
+    return;
+}
@@ -0,0 +1,24 @@
+[[id(0)]] override has_point_light: bool = true;
+
+[[id(1200)]] override specular_param: f32 = 2.3;
+
+[[id(1300)]] override gain: f32 = 1.1;
+
+[[id(1)]] override width: f32 = 0.0;
+
+[[id(2)]] override depth: f32 = 0.5;
+
+[[id(3)]] override height: f32 = 2.0;
+
+[[stage(compute), workgroup_size(1, 1, 1)]]
+fn main() {
+    var t: f32;
+    var x: bool;
+
+    t = (height * 5.0);
+    let a: bool = !(has_point_light);
+    x = a;
+    let _e11: f32 = t;
+    t = ((_e11 + (gain * width)) - (depth * specular_param));
+    return;
+}
@@ -0,0 +1,47 @@
+struct RayDesc {
+    flags: u32;
+    cull_mask: u32;
+    t_min: f32;
+    t_max: f32;
+    origin: vec3<f32>;
+    dir: vec3<f32>;
+};
+
+struct RayIntersection {
+    kind: u32;
+    t: f32;
+    instance_custom_index: u32;
+    instance_id: u32;
+    sbt_record_offset: u32;
+    geometry_index: u32;
+    primitive_index: u32;
+    barycentrics: vec2<f32>;
+    front_face: bool;
+    object_to_world: mat4x3<f32>;
+    world_to_object: mat4x3<f32>;
+};
+
+[[group(0), binding(0)]]
+var acc_struct: acceleration_structure;
+
+[[stage(fragment)]]
+fn main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    var rq: ray_query;
+
+    let dir: vec3<f32> = vec3<f32>(0.0, 0.0, 1.0);
+    let origin: vec3<f32> = vec3<f32>(position.x, position.y, 0.0);
+    let desc: RayDesc = RayDesc(4u, 255u, 0.1, 100.0, origin, dir);
+    rayQueryInitialize(&rq, acc_struct, desc);
+    loop {
+        let _e16: bool = rayQueryProceed(&rq);
+        if (!(_e16)) {
+            break;
+        }
+    }
+    let intersection: RayIntersection = rayQueryGetCommittedIntersection(&rq);
+    if ((intersection.kind == 0u)) {
+        rayQueryTerminate(&rq);
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    return vec4<f32>(intersection.barycentrics, intersection.t, 1.0);
+}
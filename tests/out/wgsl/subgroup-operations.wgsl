@@ -0,0 +1,26 @@
+[[stage(compute), workgroup_size(64, 1, 1)]]
+fn main([[builtin(subgroup_size)]] size: u32, [[builtin(subgroup_invocation_id)]] invocation_id: u32) {
+    let even: bool = ((invocation_id & 1u) == 0u);
+    let _e6: vec4<u32> = subgroupBallot(even);
+    let _e7: vec4<u32> = subgroupBallot();
+    let _e8: bool = subgroupAll(even);
+    let _e9: bool = subgroupAny(even);
+    let _e10: u32 = subgroupAdd(invocation_id);
+    let _e11: u32 = subgroupMul(invocation_id);
+    let _e12: u32 = subgroupMin(invocation_id);
+    let _e14: vec2<u32> = subgroupMax(vec2<u32>(invocation_id, size));
+    let _e15: u32 = subgroupAnd(invocation_id);
+    let _e16: u32 = subgroupOr(invocation_id);
+    let _e17: u32 = subgroupXor(invocation_id);
+    let _e18: u32 = subgroupExclusiveAdd(invocation_id);
+    let _e19: u32 = subgroupExclusiveMul(invocation_id);
+    let _e20: u32 = subgroupInclusiveAdd(invocation_id);
+    let _e21: u32 = subgroupInclusiveMul(invocation_id);
+    let _e22: u32 = subgroupBroadcastFirst(invocation_id);
+    let _e24: u32 = subgroupBroadcast(invocation_id, 4u);
+    let _e28: u32 = subgroupShuffle(invocation_id, ((size - 1u) - invocation_id));
+    let _e30: u32 = subgroupShuffleDown(invocation_id, 1u);
+    let _e32: u32 = subgroupShuffleUp(invocation_id, 1u);
+    let _e35: u32 = subgroupShuffleXor(invocation_id, (size - 1u));
+    return;
+}
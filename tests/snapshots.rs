@@ -61,6 +61,54 @@ struct Parameters {
     #[cfg_attr(not(feature = "glsl-out"), allow(dead_code))]
     #[serde(default)]
     glsl_comp_ep_name: Option<String>,
+    #[cfg_attr(not(feature = "hlsl-out"), allow(dead_code))]
+    #[serde(default)]
+    hlsl_shader_model: Option<u16>,
+}
+
+/// Check that the module is still valid after removing its unused parts.
+#[allow(dead_code)]
+fn check_compact(module: &mut naga::Module) {
+    naga::proc::compact(module);
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(module)
+    .unwrap();
+}
+
+/// Check that the module is still valid after inlining all the calls
+/// and removing its unused parts.
+#[allow(dead_code)]
+fn check_processed(module: &mut naga::Module) {
+    naga::proc::inline_functions(module, naga::proc::InlinePolicy::Always);
+    check_compact(module);
+}
+
+/// Check that `module` is the same as `expected`, by their debug output,
+/// since modules aren't comparable.
+#[allow(dead_code)]
+fn check_same_module(module: &naga::Module, expected: &naga::Module) {
+    // the order of the named expressions isn't meaningful
+    fn take_names(module: &mut naga::Module) -> Vec<Vec<(usize, String)>> {
+        let functions = module.functions.iter_mut().map(|(_, function)| function);
+        let entry_points = module.entry_points.iter_mut().map(|ep| &mut ep.function);
+        functions
+            .chain(entry_points)
+            .map(|function| {
+                let mut names = std::mem::take(&mut function.named_expressions)
+                    .into_iter()
+                    .map(|(handle, name)| (handle.index(), name))
+                    .collect::<Vec<_>>();
+                names.sort();
+                names
+            })
+            .collect()
+    }
+    let (mut module, mut expected) = (module.clone(), expected.clone());
+    assert_eq!(take_names(&mut module), take_names(&mut expected));
+    assert_eq!(format!("{:#?}", module), format!("{:#?}", expected));
 }
 
 #[allow(dead_code, unused_variables)]
@@ -98,6 +146,16 @@ fn check_targets(module: &naga::Module, name: &str, targets: Targets) {
         }
     }
 
+    #[cfg(all(feature = "ir-in", feature = "ir-out"))]
+    {
+        let string = naga::back::ir::write_string(module).unwrap();
+        let reparsed = naga::front::ir::parse_str(&string).unwrap();
+        check_same_module(&reparsed, module);
+        if targets.contains(Targets::IR) {
+            fs::write(dest.join(format!("ir/{}.ir", name)), string).unwrap();
+        }
+    }
+
     #[cfg(feature = "spv-out")]
     {
         if targets.contains(Targets::SPIRV) {
@@ -128,7 +186,7 @@ fn check_targets(module: &naga::Module, name: &str, targets: Targets) {
     #[cfg(feature = "hlsl-out")]
     {
         if targets.contains(Targets::HLSL) {
-            write_output_hlsl(module, &info, &dest, name);
+            write_output_hlsl(module, &info, &dest, name, &params);
         }
     }
     #[cfg(feature = "wgsl-out")]
@@ -259,9 +317,16 @@ fn write_output_hlsl(
     info: &naga::valid::ModuleInfo,
     destination: &PathBuf,
     file_name: &str,
+    params: &Parameters,
 ) {
     use naga::back::hlsl;
-    let options = hlsl::Options::default();
+    let shader_model = params
+        .hlsl_shader_model
+        .unwrap_or(hlsl::DEFAULT_SHADER_MODEL);
+    let options = hlsl::Options {
+        shader_model: hlsl::ShaderModel::new(shader_model),
+        ..Default::default()
+    };
     let string = hlsl::write_string(module, info, &options).unwrap();
 
     fs::write(destination.join(format!("hlsl/{}.hlsl", file_name)), string).unwrap();
@@ -272,15 +337,21 @@ fn write_output_hlsl(
     let mut config_str = String::from("");
     for ep in module.entry_points.iter() {
         let (stage_str, profile, ep_name) = match ep.stage {
-            naga::ShaderStage::Vertex => ("vertex", "vs_5_0", &options.vertex_entry_point_name),
-            naga::ShaderStage::Fragment => {
-                ("fragment", "ps_5_0", &options.fragment_entry_point_name)
-            }
-            naga::ShaderStage::Compute => ("compute", "cs_5_0", &options.compute_entry_point_name),
+            naga::ShaderStage::Vertex => ("vertex", "vs", &options.vertex_entry_point_name),
+            naga::ShaderStage::Fragment => ("fragment", "ps", &options.fragment_entry_point_name),
+            naga::ShaderStage::Compute => ("compute", "cs", &options.compute_entry_point_name),
+            naga::ShaderStage::Task => ("task", "as", &options.task_entry_point_name),
+            naga::ShaderStage::Mesh => ("mesh", "ms", &options.mesh_entry_point_name),
         };
         config_str = format!(
-            "{}{}={}\n{}_name={}\n",
-            config_str, stage_str, profile, stage_str, ep_name
+            "{}{}={}_{}_{}\n{}_name={}\n",
+            config_str,
+            stage_str,
+            profile,
+            shader_model / 10,
+            shader_model % 10,
+            stage_str,
+            ep_name
         );
     }
     fs::write(
@@ -368,6 +439,57 @@ fn convert_wgsl() {
             Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::WGSL,
         ),
         ("bounds-check-zero", Targets::SPIRV),
+        (
+            "atomicOps",
+            Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
+        ),
+        (
+            "overrides",
+            Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::WGSL,
+        ),
+        (
+            "subgroup-operations",
+            Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
+        ),
+        (
+            "f16",
+            Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
+        ),
+        (
+            "int64",
+            Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
+        ),
+        (
+            "gather",
+            Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
+        ),
+        (
+            "bits",
+            Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
+        ),
+        (
+            "data-packing",
+            Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
+        ),
+        (
+            "binding-arrays",
+            Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
+        ),
+        (
+            "dual-source",
+            Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::HLSL | Targets::WGSL,
+        ),
+        (
+            "constant-expressions",
+            Targets::SPIRV | Targets::METAL | Targets::GLSL | Targets::WGSL,
+        ),
+        // the SPIR-V disassembler doesn't know about `SPV_EXT_mesh_shader` yet
+        (
+            "mesh-shader",
+            Targets::METAL | Targets::HLSL | Targets::WGSL,
+        ),
+        // nor about `SPV_KHR_ray_query`
+        ("ray-query", Targets::METAL | Targets::HLSL | Targets::WGSL),
     ];
 
     for &(name, targets) in inputs.iter() {
@@ -376,7 +498,11 @@ fn convert_wgsl() {
         let file = fs::read_to_string(format!("{}/{}/{}.wgsl", root, BASE_DIR_IN, name))
             .expect("Couldn't find wgsl file");
         match naga::front::wgsl::parse_str(&file) {
-            Ok(module) => check_targets(&module, name, targets),
+            Ok(mut module) => {
+                check_targets(&module, name, targets);
+                check_compact(&mut module.clone());
+                check_processed(&mut module);
+            }
             Err(e) => panic!("{}", e),
         }
     }
@@ -385,7 +511,7 @@ fn convert_wgsl() {
 #[cfg(feature = "spv-in")]
 fn convert_spv(name: &str, adjust_coordinate_space: bool, targets: Targets) {
     let root = env!("CARGO_MANIFEST_DIR");
-    let module = naga::front::spv::parse_u8_slice(
+    let mut module = naga::front::spv::parse_u8_slice(
         &fs::read(format!("{}/{}/spv/{}.spv", root, BASE_DIR_IN, name))
             .expect("Couldn't find spv file"),
         &naga::front::spv::Options {
@@ -402,6 +528,8 @@ fn convert_spv(name: &str, adjust_coordinate_space: bool, targets: Targets) {
     )
     .validate(&module)
     .unwrap();
+    check_compact(&mut module.clone());
+    check_processed(&mut module);
 }
 
 #[cfg(feature = "spv-in")]
@@ -420,6 +548,12 @@ fn convert_spv_shadow() {
     convert_spv("shadow", true, Targets::IR | Targets::ANALYSIS);
 }
 
+#[cfg(feature = "spv-in")]
+#[test]
+fn convert_spv_atomic_ops() {
+    convert_spv("atomic-ops", true, Targets::WGSL);
+}
+
 #[cfg(all(feature = "spv-in", feature = "spv-out"))]
 #[test]
 fn convert_spv_pointer_access() {
@@ -109,6 +109,143 @@ fn unknown_identifier() {
     );
 }
 
+#[test]
+fn invalid_override() {
+    check(
+        "override scale: vec2<f32>;",
+        r###"error: override 'scale' must be a scalar
+  ┌─ wgsl:1:10
+  │
+1 │ override scale: vec2<f32>;
+  │          ^^^^^ not a scalar
+
+"###,
+    );
+}
+
+#[test]
+fn missing_override_initializer() {
+    check(
+        "override scale: f32;",
+        r###"error: override 'scale' has no initializer
+  ┌─ wgsl:1:10
+  │
+1 │ override scale: f32;
+  │          ^^^^^ needs an initializer
+  │
+  = note: the pipeline isn't required to provide the value
+
+"###,
+    );
+}
+
+#[test]
+fn invalid_subgroup_operand() {
+    check(
+        r#"
+            fn main() {
+                let m = mat2x2<f32>(vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 1.0));
+                let x = subgroupAdd(m);
+            }
+        "#,
+        r###"error: subgroup operand 'm' must be a scalar or a vector
+  ┌─ wgsl:4:37
+  │
+4 │                 let x = subgroupAdd(m);
+  │                                     ^ not a scalar or a vector
+
+"###,
+    );
+}
+
+#[test]
+fn unknown_extension() {
+    check(
+        "enable f128;",
+        r###"error: unknown extension: 'f128'
+  ┌─ wgsl:1:8
+  │
+1 │ enable f128;
+  │        ^^^^ unknown extension
+  │
+  = note: The supported extensions are f16 and mesh_shading
+
+"###,
+    );
+}
+
+#[test]
+fn mesh_shading_not_enabled() {
+    check(
+        "var<task_payload> payload: u32;",
+        r###"error: 'task_payload' requires the mesh_shading extension
+  ┌─ wgsl:1:5
+  │
+1 │ var<task_payload> payload: u32;
+  │     ^^^^^^^^^^^^ uses mesh shading
+  │
+  = note: Add `enable mesh_shading;` at the start of the module
+
+"###,
+    );
+}
+
+#[test]
+fn incomplete_mesh_outputs() {
+    check(
+        r#"enable mesh_shading;
+struct Vertex { [[builtin(position)]] position: vec4<f32>; };
+[[stage(mesh), workgroup_size(1), max_vertices(3), vertex_output(Vertex)]]
+fn main() {}
+"#,
+        r###"error: mesh outputs are incomplete
+  ┌─ wgsl:4:1
+  │
+4 │ fn main() {}
+  │ ^^^^^^^ mesh entry point
+  │
+  = note: `output_topology`, `max_vertices`, `max_primitives` and `vertex_output` are all required
+
+"###,
+    );
+}
+
+#[test]
+fn invalid_gather_component() {
+    check(
+        r#"
+            [[group(0), binding(0)]] var t: texture_2d<f32>;
+            [[group(0), binding(1)]] var s: sampler;
+            fn main() -> vec4<f32> {
+                return textureGather(4, t, s, vec2<f32>(0.5));
+            }
+        "#,
+        r###"error: gather component '4' is invalid
+  ┌─ wgsl:5:38
+  │
+5 │                 return textureGather(4, t, s, vec2<f32>(0.5));
+  │                                      ^ must be a constant between 0 and 3
+
+"###,
+    );
+}
+
+#[test]
+fn f16_not_enabled() {
+    check(
+        "var<private> x: vec2<f16>;",
+        r###"error: type 'vec2' requires the f16 extension
+  ┌─ wgsl:1:17
+  │
+1 │ var<private> x: vec2<f16>;
+  │                 ^^^^ uses f16
+  │
+  = note: Add `enable f16;` at the start of the module
+
+"###,
+    );
+}
+
 #[test]
 fn negative_index() {
     check(
@@ -177,6 +314,7 @@ fn validation_error(source: &str) -> Result<naga::valid::ModuleInfo, naga::valid
         naga::valid::Capabilities::empty(),
     )
     .validate(&module)
+    .map_err(|e| e.into_inner())
 }
 
 #[test]
@@ -437,3 +575,405 @@ fn invalid_local_vars() {
         if local_var_name == "not_okay"
     }
 }
+
+#[cfg(feature = "span")]
+fn check_validation_spans(input: &str, snapshot: &str) {
+    let module = naga::front::wgsl::parse_str(input).expect("expected a valid parse");
+    let output = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .expect_err("expected validation error")
+    .emit_to_string(input);
+    if output != snapshot {
+        for diff in diff::lines(&output, snapshot) {
+            match diff {
+                diff::Result::Left(l) => println!("-{}", l),
+                diff::Result::Both(l, _) => println!(" {}", l),
+                diff::Result::Right(r) => println!("+{}", r),
+            }
+        }
+        panic!("Error snapshot failed");
+    }
+}
+
+#[cfg(feature = "span")]
+#[test]
+fn validation_spans() {
+    check_validation_spans(
+        "fn main() {
+    let x: f32 = 1.0;
+    if (x + 2.0) {}
+}",
+        r###"error: Function [1] 'main' is invalid
+  ┌─ shader:3:9
+  │
+1 │ fn main() {
+  │ ------- function
+2 │     let x: f32 = 1.0;
+3 │     if (x + 2.0) {}
+  │         ^^^^^^^ condition
+  │
+  = The `if` condition [3] is not a boolean scalar
+
+"###,
+    );
+}
+
+#[test]
+fn missing_subgroup_capability() {
+    check_validation_error! {
+        "fn main() { let x = subgroupAdd(1u); }",
+        "fn main() { let x = subgroupBallot(); }":
+        Err(naga::valid::ValidationError::Function {
+            error: naga::valid::FunctionError::InvalidSubgroup(
+                naga::valid::SubgroupError::MissingCapability
+            ),
+            ..
+        })
+    }
+
+    check_validation_error! {
+        "[[stage(compute), workgroup_size(1)]]
+        fn main([[builtin(subgroup_size)]] size: u32) {}":
+        Err(naga::valid::ValidationError::EntryPoint {
+            error: naga::valid::EntryPointError::Argument(
+                0,
+                naga::valid::VaryingError::UnsupportedCapability(_),
+            ),
+            ..
+        })
+    }
+}
+
+#[test]
+fn invalid_subgroup_broadcast() {
+    let module = naga::front::wgsl::parse_str(
+        "
+        [[stage(compute), workgroup_size(1)]]
+        fn main([[builtin(subgroup_invocation_id)]] id: u32) {
+            let x = subgroupBroadcast(1.0, id);
+        }",
+    )
+    .unwrap();
+    let error = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::SUBGROUP,
+    )
+    .validate(&module)
+    .map_err(|e| e.into_inner())
+    .unwrap_err();
+    match error {
+        naga::valid::ValidationError::EntryPoint {
+            error:
+                naga::valid::EntryPointError::Function(naga::valid::FunctionError::InvalidSubgroup(
+                    naga::valid::SubgroupError::NonConstantIndex(_),
+                )),
+            ..
+        } => {}
+        other => panic!("unexpected error {:?}", other),
+    }
+}
+
+#[test]
+fn missing_float16_capability() {
+    check_validation_error! {
+        "enable f16; var<private> x: f16;",
+        "enable f16; var<private> x: vec4<f16>;":
+        Err(naga::valid::ValidationError::Type {
+            error: naga::valid::TypeError::InvalidWidth(naga::ScalarKind::Float, 2),
+            ..
+        })
+    }
+}
+
+#[test]
+fn missing_int64_capability() {
+    check_validation_error! {
+        "var<private> x: i64;":
+        Err(naga::valid::ValidationError::Type {
+            error: naga::valid::TypeError::InvalidWidth(naga::ScalarKind::Sint, 8),
+            ..
+        })
+    }
+
+    check_validation_error! {
+        "var<private> x: vec2<u64>;":
+        Err(naga::valid::ValidationError::Type {
+            error: naga::valid::TypeError::InvalidWidth(naga::ScalarKind::Uint, 8),
+            ..
+        })
+    }
+}
+
+#[test]
+fn invalid_gather() {
+    check_validation_error! {
+        r#"
+            [[group(0), binding(0)]] var t: texture_3d<f32>;
+            [[group(0), binding(1)]] var s: sampler;
+            fn main() -> vec4<f32> {
+                return textureGather(0, t, s, vec3<f32>(0.5));
+            }
+        "#:
+        Err(naga::valid::ValidationError::Function {
+            error: naga::valid::FunctionError::Expression {
+                error: naga::valid::ExpressionError::InvalidGatherDimension(
+                    naga::ImageDimension::D3
+                ),
+                ..
+            },
+            ..
+        })
+    }
+}
+
+#[test]
+fn missing_binding_array_capability() {
+    check_validation_error! {
+        r#"
+            [[group(0), binding(0)]] var textures: binding_array<texture_2d<f32>, 4u>;
+            fn load(index: u32) -> vec4<f32> {
+                return textureLoad(textures[index], vec2<i32>(0), 0);
+            }
+        "#:
+        Err(naga::valid::ValidationError::Function {
+            error: naga::valid::FunctionError::Expression {
+                error: naga::valid::ExpressionError::MissingCapabilities(
+                    naga::valid::Capabilities::BINDING_ARRAY_NON_UNIFORM_INDEXING
+                ),
+                ..
+            },
+            ..
+        })
+    }
+}
+
+#[test]
+fn missing_mesh_shader_capability() {
+    check_validation_error! {
+        r#"
+            enable mesh_shading;
+            var<task_payload> payload: u32;
+            [[stage(task), workgroup_size(1), payload(payload)]]
+            fn main() -> [[builtin(mesh_task_size)]] vec3<u32> {
+                return vec3<u32>(1u);
+            }
+        "#:
+        Err(naga::valid::ValidationError::GlobalVariable {
+            error: naga::valid::GlobalVariableError::UnsupportedCapability(
+                naga::valid::Capabilities::MESH_SHADER
+            ),
+            ..
+        })
+    }
+}
+
+#[test]
+fn missing_ray_query_capability() {
+    check_validation_error! {
+        r#"
+            [[group(0), binding(0)]] var acc_struct: acceleration_structure;
+        "#:
+        Err(naga::valid::ValidationError::Type {
+            error: naga::valid::TypeError::MissingCapability(
+                naga::valid::Capabilities::RAY_QUERY
+            ),
+            ..
+        })
+    }
+}
+
+#[test]
+fn bad_blend_source_index() {
+    check(
+        "struct Output { [[location(0), index(2)]] mask: vec4<f32>; };",
+        r###"error: blend source index must be 0 or 1, found `2`
+  ┌─ wgsl:1:38
+  │
+1 │ struct Output { [[location(0), index(2)]] mask: vec4<f32>; };
+  │                                      ^ invalid blend source index
+
+"###,
+    );
+}
+
+#[test]
+fn missing_dual_source_blending_capability() {
+    check_validation_error! {
+        "
+        struct Output {
+            [[location(0)]] color: vec4<f32>;
+            [[location(0), index(1)]] mask: vec4<f32>;
+        };
+        [[stage(fragment)]]
+        fn main() -> Output {
+            return Output(vec4<f32>(1.0, 1.0, 1.0, 1.0), vec4<f32>(1.0, 1.0, 1.0, 1.0));
+        }
+        ":
+        Err(naga::valid::ValidationError::EntryPoint {
+            stage: naga::ShaderStage::Fragment,
+            error: naga::valid::EntryPointError::Result(
+                naga::valid::VaryingError::UnsupportedCapability(
+                    naga::valid::Capabilities::DUAL_SOURCE_BLENDING
+                ),
+            ),
+            ..
+        })
+    }
+}
+
+fn pipeline_error(vertex: &str, fragment: &str) -> Result<(), naga::valid::PipelineError> {
+    let vertex = naga::front::wgsl::parse_str(vertex).unwrap();
+    let fragment = naga::front::wgsl::parse_str(fragment).unwrap();
+    let mut validator = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    );
+    validator.validate(&vertex).unwrap();
+    validator.validate(&fragment).unwrap();
+    validator.validate_pipeline(
+        (&vertex, &vertex.entry_points[0]),
+        (&fragment, &fragment.entry_points[0]),
+    )
+}
+
+#[test]
+fn invalid_pipelines() {
+    let vertex = "
+        struct VertexOutput {
+            [[builtin(position)]] position: vec4<f32>;
+            [[location(0)]] uv: vec2<f32>;
+            [[location(1), interpolate(flat)]] index: u32;
+        };
+        [[stage(vertex)]]
+        fn main() -> VertexOutput {
+            return VertexOutput(vec4<f32>(1.0), vec2<f32>(0.0), 0u);
+        }
+    ";
+    let fragment = |inputs: &str| {
+        format!(
+            "
+            [[stage(fragment)]]
+            fn main({}) -> [[location(0)]] vec4<f32> {{
+                return vec4<f32>(1.0);
+            }}
+            ",
+            inputs
+        )
+    };
+
+    assert!(pipeline_error(
+        vertex,
+        &fragment("[[location(1), interpolate(flat)]] index: u32, [[location(0)]] uv: vec2<f32>")
+    )
+    .is_ok());
+    // flat varyings aren't sampled
+    assert!(pipeline_error(
+        "
+        struct VertexOutput {
+            [[builtin(position)]] position: vec4<f32>;
+            [[location(0), interpolate(flat)]] weight: f32;
+        };
+        [[stage(vertex)]]
+        fn main() -> VertexOutput {
+            return VertexOutput(vec4<f32>(1.0), 1.0);
+        }
+        ",
+        &fragment("[[location(0), interpolate(flat, centroid)]] weight: f32")
+    )
+    .is_ok());
+    assert!(matches!(
+        pipeline_error(vertex, &fragment("[[location(2)]] normal: vec3<f32>")),
+        Err(naga::valid::PipelineError::MissingVarying { location: 2 })
+    ));
+    assert!(matches!(
+        pipeline_error(vertex, &fragment("[[location(0)]] uv: vec3<f32>")),
+        Err(naga::valid::PipelineError::TypeMismatch { location: 0, .. })
+    ));
+    assert!(matches!(
+        pipeline_error(
+            vertex,
+            &fragment("[[location(0), interpolate(linear)]] uv: vec2<f32>")
+        ),
+        Err(naga::valid::PipelineError::InterpolationMismatch {
+            location: 0,
+            output: naga::Interpolation::Perspective,
+            input: naga::Interpolation::Linear,
+        })
+    ));
+    assert!(matches!(
+        pipeline_error(
+            vertex,
+            &fragment("[[location(0), interpolate(perspective, centroid)]] uv: vec2<f32>")
+        ),
+        Err(naga::valid::PipelineError::SamplingMismatch { location: 0, .. })
+    ));
+    assert!(matches!(
+        pipeline_error(
+            "
+            [[stage(vertex)]]
+            fn main() -> [[location(0)]] vec4<f32> {
+                return vec4<f32>(1.0);
+            }
+            ",
+            &fragment("")
+        ),
+        Err(naga::valid::PipelineError::MissingBuiltIn(
+            naga::BuiltIn::Position
+        ))
+    ));
+    assert!(matches!(
+        pipeline_error(&fragment(""), &fragment("")),
+        Err(naga::valid::PipelineError::InvalidStage {
+            stage: naga::ShaderStage::Fragment,
+            expected: naga::ShaderStage::Vertex,
+            ..
+        })
+    ));
+}
+
+#[cfg(feature = "spv-in")]
+#[test]
+fn mixed_front_end_pipeline() {
+    let vertex = naga::front::spv::parse_u8_slice(
+        &std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/in/spv/quad-vert.spv"
+        ))
+        .unwrap(),
+        &Default::default(),
+    )
+    .unwrap();
+    let pipeline_error = |inputs: &str| {
+        let fragment = naga::front::wgsl::parse_str(&format!(
+            "
+            [[stage(fragment)]]
+            fn main({}) -> [[location(0)]] vec4<f32> {{
+                return vec4<f32>(1.0);
+            }}
+            ",
+            inputs
+        ))
+        .unwrap();
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate_pipeline(
+            (&vertex, &vertex.entry_points[0]),
+            (&fragment, &fragment.entry_points[0]),
+        )
+    };
+
+    assert!(pipeline_error("[[location(0)]] uv: vec2<f32>").is_ok());
+    assert!(matches!(
+        pipeline_error("[[location(0), interpolate(linear)]] uv: vec2<f32>"),
+        Err(naga::valid::PipelineError::InterpolationMismatch {
+            location: 0,
+            output: naga::Interpolation::Perspective,
+            input: naga::Interpolation::Linear,
+        })
+    ));
+}